@@ -0,0 +1,37 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Compiles the translations in po/ into the catalogs gettext reads, under
+// $OUT_DIR/locale, for the languages po/LINGUAS lists. Without msgfmt the
+// app builds all the same, in English.
+fn main() {
+    println!("cargo:rerun-if-changed=po");
+    println!("cargo:rerun-if-env-changed=LOCALEDIR");
+
+    let locale = PathBuf::from(env::var("OUT_DIR").unwrap()).join("locale");
+    let linguas = fs::read_to_string("po/LINGUAS").unwrap_or_default();
+    let languages = linguas
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for language in languages {
+        let dir = locale.join(language).join("LC_MESSAGES");
+        fs::create_dir_all(&dir).unwrap();
+        let status = Command::new("msgfmt")
+            .arg("--check")
+            .arg("-o")
+            .arg(dir.join("watch-rust-errors.mo"))
+            .arg(format!("po/{}.po", language))
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(_) => panic!("po/{}.po doesn't compile", language),
+            Err(err) => {
+                println!("cargo:warning=Not translating, msgfmt didn't run: {}", err);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+/* A C API for embedding the watch-rust-errors watcher. Build the library with
+ * `cargo build --release --manifest-path ffi/Cargo.toml`, then link against
+ * libwatch_rust_errors_ffi. */
+
+#ifndef WATCH_RUST_ERRORS_H
+#define WATCH_RUST_ERRORS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct WreSession WreSession;
+
+/* Called with every event of a session as a JSON object, e.g.
+ *
+ *   {"event": "started"}
+ *   {"event": "diagnostic", "diagnostic": {"type_": "Error", "message": ...}}
+ *   {"event": "finished", "result": {"success": false, "errors": [...], ...}}
+ *
+ * Each build's diagnostics come one by one before its "finished" event. Other
+ * events are "installing-toolchain", "fetching", "compiling" and "scheduled".
+ * Callbacks are made from a thread of the session's own, and `event` is only
+ * valid during the call. */
+typedef void (*WreCallback)(const char *event, void *user_data);
+
+/* Starts watching `project_root`, running `command` once right away and then
+ * whenever something changes. `user_data` is passed to `callback` as is.
+ * Returns NULL on failure; see wre_last_error. */
+WreSession *wre_session_start(const char *project_root, const char *command,
+                              WreCallback callback, void *user_data);
+
+/* Stops watching and frees `session`. No callbacks are made once it returns,
+ * so it must not be called from within one. */
+void wre_session_stop(WreSession *session);
+
+/* Why the latest call on this thread failed, or NULL. Valid until the next
+ * failing call on the same thread. */
+const char *wre_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,131 @@
+// A C API over `WatchSession`, for editors and IDEs that want to embed the
+// watcher rather than run it as a subprocess. include/watch_rust_errors.h
+// documents it for C.
+
+// the safety requirements are the C header's to document
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use futures::executor::block_on;
+use futures::future;
+use futures::stream::StreamExt;
+use watch_rust_errors::session::{self, SessionConfig, WatchSession};
+
+// Called with every event of a session as a JSON object, from a thread of
+// the session's own. The string is only valid during the call.
+pub type WreCallback = extern "C" fn(event: *const c_char, user_data: *mut c_void);
+
+pub struct WreSession {
+    session: WatchSession,
+    // false once stopped; held while calling back so that stopping waits for
+    // a callback that is under way
+    delivering: Arc<Mutex<bool>>,
+}
+
+// whatever the caller handed in for its callback, passed back untouched
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Starts watching `project_root`, compiling with `command` once right away and
+// then whenever something changes. Returns null on failure, leaving the reason
+// to `wre_last_error`.
+#[no_mangle]
+pub unsafe extern "C" fn wre_session_start(
+    project_root: *const c_char,
+    command: *const c_char,
+    callback: Option<WreCallback>,
+    user_data: *mut c_void,
+) -> *mut WreSession {
+    match start(project_root, command, callback, user_data) {
+        Ok(session) => Box::into_raw(Box::new(session)),
+        Err(err) => {
+            set_last_error(&err);
+            ptr::null_mut()
+        }
+    }
+}
+
+// Stops watching and frees `session`. No callbacks are made once it returns,
+// though the compile that was running may go on in the background until done.
+#[no_mangle]
+pub unsafe extern "C" fn wre_session_stop(session: *mut WreSession) {
+    if session.is_null() {
+        return;
+    }
+    let mut session = Box::from_raw(session);
+    *session.delivering.lock().unwrap() = false;
+    session.session.stop();
+}
+
+// Why the latest call on this thread failed, or null. Valid until the next
+// failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn wre_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map(|error| error.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+unsafe fn start(
+    project_root: *const c_char,
+    command: *const c_char,
+    callback: Option<WreCallback>,
+    user_data: *mut c_void,
+) -> Result<WreSession, String> {
+    let project_root = to_str(project_root, "project root")?;
+    let command = to_str(command, "command")?;
+    let callback = callback.ok_or_else(|| "No callback given.".to_string())?;
+
+    let (session, events) = WatchSession::start(SessionConfig::new(project_root, command))?;
+    let delivering = Arc::new(Mutex::new(true));
+    let active = delivering.clone();
+    let user_data = UserData(user_data);
+    thread::Builder::new()
+        .name("wre-callback".to_string())
+        .spawn(move || {
+            block_on(events.for_each(|event| {
+                let delivering = active.lock().unwrap();
+                let event = CString::new(session::to_json(&event).to_string());
+                // a string with a NUL in it can't be handed over
+                if let (true, Ok(event)) = (*delivering, event) {
+                    callback(event.as_ptr(), user_data.0);
+                }
+                future::ready(())
+            }));
+        })
+        .map_err(|e| format!("{:?}", e))?;
+
+    Ok(WreSession {
+        session,
+        delivering,
+    })
+}
+
+unsafe fn to_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("No {} given.", what));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| format!("The {} isn't valid UTF-8.", what))
+}
+
+fn set_last_error(err: &str) {
+    let err = CString::new(err.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(err));
+}
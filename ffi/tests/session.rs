@@ -0,0 +1,67 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use serde_json::Value;
+use watch_rust_errors_ffi::{wre_last_error, wre_session_start, wre_session_stop};
+
+extern "C" fn forward(event: *const c_char, user_data: *mut c_void) {
+    let events = unsafe { &*(user_data as *const Sender<String>) };
+    let event = unsafe { CStr::from_ptr(event) }.to_str().unwrap();
+    let _ = events.send(event.to_string());
+}
+
+fn next(events: &Receiver<String>) -> Value {
+    let event = events.recv_timeout(Duration::from_secs(30)).unwrap();
+    serde_json::from_str(&event).unwrap()
+}
+
+#[test]
+fn events_reach_the_callback_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = CString::new(dir.path().to_str().unwrap()).unwrap();
+    let command = CString::new("true").unwrap();
+    let (tx, rx) = mpsc::channel::<String>();
+
+    let session = unsafe {
+        wre_session_start(
+            root.as_ptr(),
+            command.as_ptr(),
+            Some(forward),
+            &tx as *const Sender<String> as *mut c_void,
+        )
+    };
+    assert!(!session.is_null());
+
+    let started = next(&rx);
+    assert_eq!(started["event"], "started");
+    let finished = next(&rx);
+    assert_eq!(finished["event"], "finished");
+    assert_eq!(finished["result"]["success"], true);
+
+    unsafe { wre_session_stop(session) };
+}
+
+#[test]
+fn failures_are_explained() {
+    let root = CString::new("/no/such/project").unwrap();
+    let command = CString::new("cargo check").unwrap();
+
+    let session = unsafe {
+        wre_session_start(
+            root.as_ptr(),
+            command.as_ptr(),
+            Some(forward),
+            ptr::null_mut(),
+        )
+    };
+    assert!(session.is_null());
+
+    let error = unsafe { CStr::from_ptr(wre_last_error()) };
+    assert_eq!(
+        error.to_str().unwrap(),
+        "/no/such/project is not a directory."
+    );
+}
@@ -0,0 +1,132 @@
+// Python bindings, for build scripts and notebooks that want the diagnostic
+// parser or the watcher without the app. Results and events come out as
+// dicts and lists shaped like their JSON over the C API.
+
+// set off by the code pyo3's macros generate for functions returning `PyResult`
+#![allow(clippy::useless_conversion)]
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::future;
+use futures::stream::StreamExt;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use watch_rust_errors::cargo;
+use watch_rust_errors::session::{self, SessionConfig, WatchSession};
+
+// how long waiting for an event goes before checking for Ctrl+C
+const SIGNAL_CHECK: Duration = Duration::from_millis(100);
+
+// Reads the diagnostics, and test results if any, out of a build's output,
+// e.g. `cargo check` with stderr and stdout captured together.
+#[pyfunction]
+fn parse_output(py: Python<'_>, output: &str) -> PyResult<PyObject> {
+    let mut result = cargo::parse(true, output, output).map_err(PyRuntimeError::new_err)?;
+    result.success = result.errors.is_empty();
+    to_python(py, &result)
+}
+
+// Builds once with `command` in `project_root` and returns the results.
+#[pyfunction]
+fn run_once(py: Python<'_>, project_root: &str, command: &str) -> PyResult<PyObject> {
+    let result = py
+        .allow_threads(|| cargo::run(project_root, command))
+        .map_err(PyRuntimeError::new_err)?;
+    to_python(py, &result)
+}
+
+// Starts watching `project_root`, building with `command` right away and
+// then whenever something changes.
+#[pyfunction]
+fn watch(project_root: &str, command: &str) -> PyResult<Watch> {
+    Watch::start(project_root, command).map_err(PyRuntimeError::new_err)
+}
+
+// A running watch. Iterating it waits for each event in turn, e.g.
+// {"event": "finished", "result": {...}}, until it's stopped. Used in a
+// `with` block it stops at the end of it.
+#[pyclass]
+struct Watch {
+    session: Mutex<Option<WatchSession>>,
+    events: Mutex<Receiver<Value>>,
+}
+
+impl Watch {
+    fn start(project_root: &str, command: &str) -> Result<Self, String> {
+        let (session, events) = WatchSession::start(SessionConfig::new(project_root, command))?;
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("wre-python".to_string())
+            .spawn(move || {
+                block_on(events.for_each(|event| {
+                    let _ = tx.send(session::to_json(&event));
+                    future::ready(())
+                }));
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(Watch {
+            session: Mutex::new(Some(session)),
+            events: Mutex::new(rx),
+        })
+    }
+}
+
+#[pymethods]
+impl Watch {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let events = &self.events;
+        loop {
+            match py.allow_threads(|| events.lock().unwrap().recv_timeout(SIGNAL_CHECK)) {
+                Ok(event) => return to_python(py, &event).map(Some),
+                Err(RecvTimeoutError::Timeout) => py.check_signals()?,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+
+    // Stops watching. The events so far can still be iterated over, along
+    // with those of the build that was running, if any.
+    fn stop(&self) {
+        if let Some(mut session) = self.session.lock().unwrap().take() {
+            session.stop();
+        }
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(&self, _exc_type: PyObject, _exc_value: PyObject, _traceback: PyObject) -> bool {
+        self.stop();
+        false
+    }
+}
+
+// by way of JSON, which is what the results already know how to turn into
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json =
+        serde_json::to_string(value).map_err(|e| PyRuntimeError::new_err(format!("{:?}", e)))?;
+    let loads = py.import_bound("json")?.getattr("loads")?;
+    Ok(loads.call1((json,))?.unbind())
+}
+
+#[pymodule]
+#[pyo3(name = "watch_rust_errors")]
+pub fn init(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse_output, module)?)?;
+    module.add_function(wrap_pyfunction!(run_once, module)?)?;
+    module.add_function(wrap_pyfunction!(watch, module)?)?;
+    module.add_class::<Watch>()?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+
+// runs `code` with the bindings imported as `wre`, returning what it left
+// in `out`
+fn run(code: &str) -> String {
+    Python::with_gil(|py| {
+        let module = PyModule::new_bound(py, "watch_rust_errors").unwrap();
+        watch_rust_errors_py::init(&module).unwrap();
+        let locals = PyDict::new_bound(py);
+        locals.set_item("wre", module).unwrap();
+        py.run_bound(code, None, Some(&locals)).unwrap();
+        locals.get_item("out").unwrap().unwrap().to_string()
+    })
+}
+
+#[test]
+fn output_is_parsed_into_dicts() {
+    let out = run(r#"
+result = wre.parse_output("error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n")
+error = result["errors"][0]
+out = (result["success"], error["num"], error["file"], error["line"])
+"#);
+    assert_eq!(out, "(False, 'E0308', 'src/main.rs', 4)");
+}
+
+#[test]
+fn builds_can_be_run_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(&format!(
+        "out = wre.run_once({:?}, 'true')['success']",
+        dir.path().to_str().unwrap()
+    ));
+    assert_eq!(out, "True");
+}
+
+#[test]
+fn watches_are_iterated_for_their_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(&format!(
+        r#"
+with wre.watch({:?}, 'true') as watch:
+    events = iter(watch)
+    out = (next(events)["event"], next(events)["event"])
+"#,
+        dir.path().to_str().unwrap()
+    ));
+    assert_eq!(out, "('started', 'finished')");
+}
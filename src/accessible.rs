@@ -0,0 +1,32 @@
+use atk::AtkObjectExt;
+use vgtk::lib::gtk::{prelude::*, Widget};
+
+// Names a widget for screen readers such as Orca where what it shows
+// doesn't say what it is, e.g. `<Button accessible_name="Next error">` for
+// a button with only an icon, or a row made of several labels. Tooltips are
+// read as descriptions by themselves.
+pub trait AccessibleNameExt {
+    fn set_accessible_name(&self, name: &str);
+}
+
+impl<W: IsA<Widget>> AccessibleNameExt for W {
+    fn set_accessible_name(&self, name: &str) {
+        if let Some(accessible) = self.get_accessible() {
+            accessible.set_name(name);
+        }
+    }
+}
+
+// Says more about a widget than its name, read after it, e.g. a row's
+// details.
+pub trait AccessibleDescriptionExt {
+    fn set_accessible_description(&self, description: &str);
+}
+
+impl<W: IsA<Widget>> AccessibleDescriptionExt for W {
+    fn set_accessible_description(&self, description: &str) {
+        if let Some(accessible) = self.get_accessible() {
+            accessible.set_description(description);
+        }
+    }
+}
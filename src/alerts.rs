@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::thread;
+
+use glib::DateTime;
+use vgtk::lib::gdk::Display;
+use vgtk::lib::gio::{self, ApplicationExt, Notification};
+use vgtk::lib::gtk::{prelude::*, Inhibit, Window};
+
+use watch_rust_errors::config::{Alert, QuietHours, TimeOfDay};
+use watch_rust_errors::notifications::{Notice, NotificationSink, Notifier, SinkConfig, SinkKind};
+
+thread_local! {
+    // the window whose urgency hint goes away once it's focused, so that
+    // the handler doing that is only connected once
+    static FLASHING: RefCell<Option<Window>> = RefCell::new(None);
+}
+
+// Draws attention to a failed build the way the user asked for, unless the
+// window has the focus and its results are in sight already.
+pub fn build_failed(alert: Alert, quiet_hours: Option<QuietHours>) {
+    let window = match vgtk::current_window() {
+        Some(window) => window,
+        None => return,
+    };
+    if alert == Alert::Off || window.is_active() {
+        return;
+    }
+
+    if alert.sound() && !is_quiet(quiet_hours) {
+        if let Some(display) = Display::get_default() {
+            display.beep();
+        }
+    }
+    if alert.flash() {
+        clear_when_focused(&window);
+        window.set_urgency_hint(true);
+    }
+}
+
+fn clear_when_focused(window: &Window) {
+    FLASHING.with(|flashing| {
+        let mut flashing = flashing.borrow_mut();
+        if flashing.as_ref() != Some(window) {
+            window.connect_focus_in_event(|window, _| {
+                window.set_urgency_hint(false);
+                Inhibit(false)
+            });
+            *flashing = Some(window.clone());
+        }
+    });
+}
+
+// Where notices go: the app's own sinks on the main thread, since GTK wants
+// them there, and the rest from a thread of their own so that a slow server
+// doesn't hold up the window.
+pub struct Notifiers {
+    local: Notifier,
+    remote: Arc<Notifier>,
+    // when the app's own sinks keep quiet
+    quiet_hours: Option<QuietHours>,
+}
+
+impl Notifiers {
+    pub fn new(configs: &[SinkConfig], quiet_hours: Option<QuietHours>) -> Self {
+        let mut local = Notifier::default();
+        for config in configs {
+            match config.kind {
+                SinkKind::Desktop => local.add(config.events.clone(), Box::new(Desktop)),
+                SinkKind::Sound => local.add(config.events.clone(), Box::new(Sound)),
+                _ => {}
+            }
+        }
+        Notifiers {
+            local,
+            remote: Arc::new(Notifier::remote(configs)),
+            quiet_hours,
+        }
+    }
+
+    pub fn send(&self, notice: Notice) {
+        if !is_quiet(self.quiet_hours) {
+            if let Err(err) = self.local.send(&notice) {
+                eprintln!("{}", err);
+            }
+        }
+        if self.remote.wants(notice.event) {
+            let remote = self.remote.clone();
+            thread::spawn(move || {
+                if let Err(err) = remote.send(&notice) {
+                    eprintln!("{}", err);
+                }
+            });
+        }
+    }
+}
+
+// Shows a desktop notification, replacing the previous one of its kind.
+struct Desktop;
+
+impl NotificationSink for Desktop {
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        let app = gio::Application::get_default().ok_or("No application to notify from.")?;
+        let notification = Notification::new(&notice.title);
+        notification.set_body(Some(&notice.body));
+        app.send_notification(Some(notice.event.as_str()), &notification);
+        Ok(())
+    }
+}
+
+struct Sound;
+
+impl NotificationSink for Sound {
+    fn send(&self, _notice: &Notice) -> Result<(), String> {
+        let display = Display::get_default().ok_or("No display to beep on.")?;
+        display.beep();
+        Ok(())
+    }
+}
+
+// Checked whenever there's something to say rather than once, since a watch
+// with scheduled jobs may well run overnight.
+fn is_quiet(quiet_hours: Option<QuietHours>) -> bool {
+    quiet_hours
+        .map(|quiet_hours| {
+            let now = DateTime::new_now_local();
+            quiet_hours.contains(TimeOfDay::new(
+                now.get_hour() as u32,
+                now.get_minute() as u32,
+            ))
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // any CSI sequence; only the SGR ones (ending in "m") carry styling
+    static ref REGEX_CSI: Regex = Regex::new("\x1b\\[([0-9;?]*)([A-Za-z])").unwrap();
+}
+
+// the Tango palette, which reads well on both light and dark backgrounds
+const COLORS: [&str; 8] = [
+    "#2e3436", "#cc0000", "#4e9a06", "#c4a000", "#3465a4", "#75507b", "#06989a", "#d3d7cf",
+];
+const BRIGHT_COLORS: [&str; 8] = [
+    "#555753", "#ef2929", "#8ae234", "#fce94f", "#729fcf", "#ad7fa8", "#34e2e2", "#eeeeec",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Style {
+    foreground: Option<&'static str>,
+    bold: bool,
+    underline: bool,
+}
+
+impl Style {
+    fn apply(&mut self, params: &str) {
+        let mut codes = params.split(';').map(|c| c.parse::<u32>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                4 => self.underline = true,
+                22 => self.bold = false,
+                24 => self.underline = false,
+                30..=37 => self.foreground = Some(COLORS[(code - 30) as usize]),
+                39 => self.foreground = None,
+                90..=97 => self.foreground = Some(BRIGHT_COLORS[(code - 90) as usize]),
+                // 256 color and true color foregrounds/backgrounds. Only the
+                // first 16 of the 256 colors are mapped, the rest just have
+                // their arguments skipped rather than read as codes.
+                38 | 48 => match codes.next() {
+                    Some(5) => {
+                        let color = codes.next().unwrap_or(0) as usize;
+                        if code == 38 {
+                            self.foreground = match color {
+                                0..=7 => Some(COLORS[color]),
+                                8..=15 => Some(BRIGHT_COLORS[color - 8]),
+                                _ => None,
+                            };
+                        }
+                    }
+                    Some(2) => {
+                        codes.nth(2);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn open_tag(&self) -> Option<String> {
+        if *self == Style::default() {
+            return None;
+        }
+
+        let mut tag = "<span".to_string();
+        if let Some(color) = self.foreground {
+            tag.push_str(&format!(" foreground=\"{}\"", color));
+        }
+        if self.bold {
+            tag.push_str(" weight=\"bold\"");
+        }
+        if self.underline {
+            tag.push_str(" underline=\"single\"");
+        }
+        tag.push('>');
+        Some(tag)
+    }
+}
+
+// Removes terminal escape sequences, e.g. from `cargo --color=always` output,
+// so that it can be parsed.
+pub fn strip(inp: &str) -> Cow<'_, str> {
+    REGEX_CSI.replace_all(inp, "")
+}
+
+// Builds markup, only opening a span once there is text to put in it.
+#[derive(Default)]
+struct Markup {
+    out: String,
+    // the style of the span that is currently open, if any
+    open: Option<Style>,
+}
+
+impl Markup {
+    fn push(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        if self.open.unwrap_or_default() != style {
+            self.close();
+            if let Some(tag) = style.open_tag() {
+                self.out.push_str(&tag);
+                self.open = Some(style);
+            }
+        }
+        self.out.push_str(&escape(text));
+    }
+
+    fn close(&mut self) {
+        if self.open.take().is_some() {
+            self.out.push_str("</span>");
+        }
+    }
+}
+
+// Converts text with SGR color codes to Pango markup. Everything else is
+// escaped, so the result can always be handed to a markup setter.
+pub fn to_pango(inp: &str) -> String {
+    let mut markup = Markup::default();
+    let mut style = Style::default();
+    let mut start = 0;
+    for caps in REGEX_CSI.captures_iter(inp) {
+        let m = caps.get(0).unwrap();
+        markup.push(&inp[start..m.start()], style);
+        start = m.end();
+        if &caps[2] == "m" {
+            style.apply(&caps[1]);
+        }
+    }
+    markup.push(&inp[start..], style);
+    markup.close();
+
+    markup.out
+}
+
+// Escapes text for use in Pango markup.
+pub fn escape(inp: &str) -> String {
+    inp.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\'', "&#39;")
+        .replace('"', "&quot;")
+}
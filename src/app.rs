@@ -0,0 +1,311 @@
+use gettextrs::{gettext, ngettext};
+use vgtk::lib::gio::{ApplicationFlags, File, FileExt, SimpleAction};
+use vgtk::lib::gtk::{
+    prelude::*, Application, ButtonsType, DialogFlags, MessageDialog, MessageType, ResponseType,
+};
+use vgtk::{ext::*, gtk, on_signal, Component, UpdateAction, VNode};
+
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::resume::{self, SavedSession};
+use watch_rust_errors::watcher::BuildQueue;
+
+use crate::close_guard;
+use crate::{Model, WindowProps};
+
+const APP_ID: &str = "in.nerdworks.watch-rust-errors";
+
+// A window the app has opened, and what for.
+#[derive(Clone, Debug, Default)]
+struct Slot {
+    id: usize,
+    project_root: Option<String>,
+    replay: Option<(String, bool)>,
+    // reads the compiler output piped into the app
+    piped: bool,
+    // what to build with and whether to start watching, from the command line
+    command: Option<String>,
+    start: bool,
+    // a session cut short that the window goes on with
+    resumed: Option<SavedSession>,
+    closed: bool,
+}
+
+// What the app was started with, or an instance that's running already was
+// asked for, e.g. `watch-rust-errors ~/src/app --command "cargo clippy"
+// --start`: projects to open, the command to build them with and whether to
+// start watching straight away.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Launch {
+    pub project_roots: Vec<String>,
+    pub command: Option<String>,
+    pub start: bool,
+}
+
+impl Launch {
+    // Reads a command line, leaving out the program and the options that are
+    // dealt with before the app starts, like `--new-window`.
+    pub fn parse(args: &[String]) -> Self {
+        let mut launch = Launch::default();
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--command" {
+                launch.command = args.next().cloned();
+            } else if let Some(command) = arg.strip_prefix("--command=") {
+                launch.command = Some(command.to_string());
+            } else if arg == "--start" {
+                launch.start = true;
+            } else if !arg.starts_with("--") {
+                launch.project_roots.push(arg.clone());
+            }
+        }
+        launch
+    }
+
+    // Whether it asks for nothing more than the app, e.g. a window.
+    pub fn is_empty(&self) -> bool {
+        self.project_roots.is_empty() && self.command.is_none() && !self.start
+    }
+}
+
+// The application, with a window of its own for every project, each one a
+// `Model` with its own watch.
+pub struct App {
+    // Closed windows keep their place, since windows are matched up with
+    // their models by position when the app re-renders.
+    windows: Vec<Slot>,
+    // the first window is still the blank one the app started with, and
+    // takes whatever the app was started to open
+    fresh: bool,
+    // every window's builds take their turn in it
+    build_queue: BuildQueue,
+    // whether the app has looked for sessions to resume, which it does once
+    // as it starts
+    resume_offered: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App {
+            windows: vec![Slot::default()],
+            fresh: true,
+            build_queue: BuildQueue::default(),
+            resume_offered: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AppMessage {
+    // started without anything to open
+    Activated,
+    // go on with the watches an instance that crashed or was killed had
+    Resume(Vec<SavedSession>),
+    Open(Vec<String>),
+    Launch(Launch),
+    NewWindow(Option<String>),
+    Replay(String, bool),
+    // `--stdin`, with the project the output is from
+    ReadStdin(String),
+    Closed(usize),
+    Quit,
+}
+
+impl App {
+    fn open(&mut self, project_root: Option<String>, replay: Option<(String, bool)>) -> &mut Slot {
+        if self.fresh && !self.windows.is_empty() {
+            self.fresh = false;
+            let first = &mut self.windows[0];
+            first.project_root = project_root;
+            first.replay = replay;
+            return first;
+        }
+        self.fresh = false;
+        let id = self.windows.len();
+        self.windows.push(Slot {
+            id,
+            project_root,
+            replay,
+            ..Slot::default()
+        });
+        &mut self.windows[id]
+    }
+}
+
+impl Component for App {
+    type Message = AppMessage;
+    type Properties = ();
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            // the first time, offers to resume the watches that were cut
+            // short, in the window the app starts with
+            AppMessage::Activated => {
+                if self.fresh && !self.resume_offered && !crate::safe_mode_requested() {
+                    self.resume_offered = true;
+                    let sessions = resume::interrupted();
+                    if !sessions.is_empty() {
+                        return UpdateAction::defer(offer_resume(sessions));
+                    }
+                }
+                self.fresh = false;
+                UpdateAction::None
+            }
+
+            AppMessage::Resume(sessions) => {
+                for session in sessions {
+                    let slot = self.open(Some(session.project_root.clone()), None);
+                    slot.command = Some(session.command.clone());
+                    slot.start = true;
+                    slot.resumed = Some(session);
+                }
+                UpdateAction::Render
+            }
+
+            AppMessage::Open(project_roots) => {
+                for project_root in project_roots {
+                    self.open(Some(project_root), None);
+                }
+                UpdateAction::Render
+            }
+
+            // a window for each project, or one without when there's only a
+            // command
+            AppMessage::Launch(launch) => {
+                let project_roots = match launch.project_roots.len() {
+                    0 => vec![None],
+                    _ => launch.project_roots.into_iter().map(Some).collect(),
+                };
+                for project_root in project_roots {
+                    let slot = self.open(project_root, None);
+                    slot.command = launch.command.clone();
+                    slot.start = launch.start;
+                }
+                UpdateAction::Render
+            }
+
+            AppMessage::NewWindow(project_root) => {
+                self.fresh = false;
+                self.open(project_root, None);
+                UpdateAction::Render
+            }
+
+            // sent as a replay starts, and so after GTK's activate
+            AppMessage::Replay(path, original_timing) => {
+                self.fresh = true;
+                self.open(None, Some((path, original_timing)));
+                UpdateAction::Render
+            }
+
+            // like a replay, in the window the app starts with
+            AppMessage::ReadStdin(project_root) => {
+                self.fresh = true;
+                self.open(Some(project_root), None);
+                if let Some(first) = self.windows.first_mut() {
+                    first.piped = true;
+                }
+                UpdateAction::Render
+            }
+
+            AppMessage::Closed(id) => {
+                if let Some(slot) = self.windows.get_mut(id) {
+                    slot.closed = true;
+                }
+                if self.windows.iter().all(|slot| slot.closed) {
+                    vgtk::quit();
+                }
+                UpdateAction::None
+            }
+
+            // through the windows, which stop their watches and quit once
+            // they're all closed
+            AppMessage::Quit => {
+                if !close_guard::quit() {
+                    vgtk::quit();
+                }
+                UpdateAction::None
+            }
+        }
+    }
+
+    fn view(&self) -> VNode<App> {
+        let windows = self.windows.iter().map(|slot| {
+            let id = slot.id;
+            gtk! {
+                <@Model project_root=slot.project_root.clone() replay=slot.replay.clone()
+                        piped=slot.piped command=slot.command.clone() start=slot.start
+                        resumed=slot.resumed.clone()
+                        build_queue=self.build_queue.clone()
+                        on open_project=|project_root| AppMessage::NewWindow(Some(project_root))
+                        on closed=|_| AppMessage::Closed(id) />
+            }
+        });
+
+        gtk! {
+            <Application::new_unwrap(Some(APP_ID), ApplicationFlags::HANDLES_OPEN)
+                    on activate=|_| AppMessage::Activated
+                    on open=|_, files, _| AppMessage::Open(paths(files))>
+
+                <SimpleAction::new("quit", None) Application::accels=["<Ctrl>q"].as_ref() enabled=true
+                        on activate=|a, _| AppMessage::Quit />
+
+                <SimpleAction::new("new-window", None) Application::accels=["<Ctrl>n"].as_ref()
+                        enabled=true on activate=|a, _| AppMessage::NewWindow(None) />
+
+                { windows }
+            </Application>
+        }
+    }
+}
+
+// Asks whether to go on with the watches of an instance that ended without
+// stopping them, forgetting them if not.
+async fn offer_resume(sessions: Vec<SavedSession>) -> AppMessage {
+    let projects = sessions
+        .iter()
+        .map(|session| format!("{}  ({})", session.project_root, session.command))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let dialog = MessageDialog::new(
+        vgtk::current_window().as_ref(),
+        DialogFlags::MODAL,
+        MessageType::Question,
+        ButtonsType::None,
+        &gettext("Resume watching where you left off?"),
+    );
+    dialog.set_property_secondary_text(Some(&fill(
+        ngettext(
+            "The app ended while watching this project:\n\n{}",
+            "The app ended while watching these projects:\n\n{}",
+            sessions.len() as u32,
+        ),
+        &[&projects],
+    )));
+    dialog.add_button(&gettext("Don't Resume"), ResponseType::Reject);
+    dialog.add_button(&gettext("Resume"), ResponseType::Accept);
+    dialog.set_default_response(ResponseType::Accept);
+    dialog.show();
+
+    let response = on_signal!(dialog, connect_response).await;
+    dialog.destroy();
+    if let Ok(ResponseType::Accept) = response {
+        return AppMessage::Resume(sessions);
+    }
+    for session in &sessions {
+        if let Err(err) = SavedSession::remove(&session.project_root) {
+            eprintln!(
+                "Failed to forget the session on {}: {}",
+                session.project_root, err
+            );
+        }
+    }
+    // as if there had been nothing to resume
+    AppMessage::Activated
+}
+
+fn paths(files: &[File]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|file| file.get_path())
+        .filter_map(|path| path.into_os_string().into_string().ok())
+        .collect()
+}
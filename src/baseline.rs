@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cargo::{CompileResult, DiagnosticId};
+use crate::lock;
+use crate::rust::{RustDiagnostic, Type};
+
+const APP_DIR: &str = "watch-rust-errors";
+const BASELINES_DIR: &str = "baselines";
+
+// FNV-1a's, since fingerprints are saved and std's hashers may change
+// between releases
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// The diagnostics a project had at some point, to tell the ones that came
+// after apart from those that were already there. Kept per project root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub taken_at: SystemTime,
+    // how many diagnostics there were of each fingerprint
+    counts: BTreeMap<String, usize>,
+}
+
+impl Baseline {
+    pub fn capture(result: &CompileResult) -> Self {
+        let mut counts = BTreeMap::new();
+        for diag in result.diagnostics() {
+            *counts.entry(fingerprint(diag)).or_insert(0) += 1;
+        }
+        Baseline {
+            taken_at: SystemTime::now(),
+            counts,
+        }
+    }
+
+    // how many diagnostics it holds
+    pub fn len(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    // The diagnostics of `result` that the baseline doesn't account for. Of
+    // several with the same fingerprint, the ones past the baseline's count
+    // are new.
+    pub fn new_diagnostics(&self, result: &CompileResult) -> HashSet<DiagnosticId> {
+        let mut left = self.counts.clone();
+        result
+            .diagnostics()
+            .enumerate()
+            .filter(|(_, diag)| match left.get_mut(&fingerprint(diag)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .map(|(index, _)| DiagnosticId(index))
+            .collect()
+    }
+
+    // e.g. ~/.local/share/watch-rust-errors/baselines
+    pub fn dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join(APP_DIR).join(BASELINES_DIR))
+    }
+
+    pub fn load<P: AsRef<Path>>(project_root: P) -> Result<Option<Self>, String> {
+        Self::load_from(baselines_dir()?, project_root)
+    }
+
+    pub fn load_from<D, P>(dir: D, project_root: P) -> Result<Option<Self>, String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        match fs::read_to_string(path(dir.as_ref(), project_root.as_ref())) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map(Some)
+                .map_err(|e| format!("{:?}", e)),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("{:?}", e)),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, project_root: P) -> Result<(), String> {
+        self.save_to(baselines_dir()?, project_root)
+    }
+
+    pub fn save_to<D, P>(&self, dir: D, project_root: P) -> Result<(), String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(dir.as_ref()).map_err(|e| format!("{:?}", e))?;
+        let contents = serde_json::to_string(self).map_err(|e| format!("{:?}", e))?;
+        fs::write(path(dir.as_ref(), project_root.as_ref()), contents)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    // Forgets the project's baseline, if it has one.
+    pub fn remove<P: AsRef<Path>>(project_root: P) -> Result<(), String> {
+        Self::remove_from(baselines_dir()?, project_root)
+    }
+
+    pub fn remove_from<D, P>(dir: D, project_root: P) -> Result<(), String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        match fs::remove_file(path(dir.as_ref(), project_root.as_ref())) {
+            Err(ref e) if e.kind() != ErrorKind::NotFound => Err(format!("{:?}", e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Identifies `diag` across builds by its kind, code, message and file. Like
+// `RustDiagnostic::is_same` it leaves out the line, which unrelated edits
+// move around, and the file is spelled the same however the compiler put it.
+pub fn fingerprint(diag: &RustDiagnostic) -> String {
+    let kind = match diag.type_ {
+        Type::Error => "error",
+        Type::Warning => "warning",
+    };
+    let file = diag
+        .file
+        .as_deref()
+        .map(|file| file.replace('\\', "/"))
+        .map(|file| file.trim_start_matches("./").to_string())
+        .unwrap_or_default();
+    let fields = [
+        kind,
+        diag.num.as_deref().unwrap_or_default(),
+        diag.lint.as_deref().unwrap_or_default(),
+        &diag.message,
+        &file,
+    ];
+
+    let mut hash = FNV_OFFSET;
+    // a separator between fields keeps "ab" + "c" apart from "a" + "bc"
+    for byte in fields.join("\0").bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    format!("{:016x}", hash)
+}
+
+fn baselines_dir() -> Result<PathBuf, String> {
+    Baseline::dir().ok_or_else(|| "No data directory available.".to_string())
+}
+
+fn path(dir: &Path, project_root: &Path) -> PathBuf {
+    dir.join(format!("{}.json", lock::key(project_root)))
+}
@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::cargo::{self, CompileResult};
+use crate::git::git;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::rust::RustDiagnostic;
+
+lazy_static! {
+    static ref REGEX_FIRST_BAD: Regex =
+        Regex::new(r"(?m)^([0-9a-f]{7,40}) is the first bad commit").unwrap();
+    // e.g. "Bisecting: 6 revisions left to test after this (roughly 3 steps)"
+    static ref REGEX_STEPS: Regex = Regex::new(r"\(roughly ([0-9]+) steps?\)").unwrap();
+}
+
+// tells apart the worktrees of this process's bisections
+static WORKTREES: AtomicUsize = AtomicUsize::new(0);
+
+// Where a diagnostic first showed up in the repository's history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bisection {
+    pub commit: String,
+    // e.g. "1a2b3c4 Switch to the new parser"
+    pub summary: String,
+    // how many commits were built to find it
+    pub builds: usize,
+}
+
+// How a bisection is getting on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BisectEvent {
+    // building a commit, and roughly how many more builds are to come
+    Testing(String, Option<usize>),
+    Done(Result<Bisection, String>),
+}
+
+pub fn bisect<P, F>(
+    project_root: P,
+    good: &str,
+    command: &str,
+    env: &[(String, String)],
+    diag: &RustDiagnostic,
+    on_event: F,
+) -> Result<Bisection, String>
+where
+    P: AsRef<Path>,
+    F: FnMut(BisectEvent),
+{
+    bisect_with(
+        &SystemRunner,
+        project_root,
+        good,
+        command,
+        env,
+        diag,
+        on_event,
+    )
+}
+
+// Bisects on a thread of its own, every build being a while, for its events
+// to come in as they happen. They end with the bisection.
+pub fn spawn<P: AsRef<Path>>(
+    project_root: P,
+    good: &str,
+    command: &str,
+    env: &[(String, String)],
+    diag: &RustDiagnostic,
+) -> UnboundedReceiver<BisectEvent> {
+    let project_root = project_root.as_ref().to_path_buf();
+    let good = good.to_string();
+    let command = command.to_string();
+    let env = env.to_vec();
+    let diag = diag.clone();
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let _ = bisect(&project_root, &good, &command, &env, &diag, |event| {
+            let _ = tx.unbounded_send(event);
+        });
+    });
+    rx
+}
+
+// Finds the first commit after `good` up to HEAD whose build with `command`
+// reports `diag`, with `git bisect` in a temporary worktree so that nothing in
+// the working tree has to be stashed. `on_event` hears about each build, and
+// then about how it ended, whichever way it did.
+pub fn bisect_with<R, P, F>(
+    runner: &R,
+    project_root: P,
+    good: &str,
+    command: &str,
+    env: &[(String, String)],
+    diag: &RustDiagnostic,
+    mut on_event: F,
+) -> Result<Bisection, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+    F: FnMut(BisectEvent),
+{
+    let result = bisect_in(
+        runner,
+        project_root.as_ref(),
+        good,
+        command,
+        env,
+        diag,
+        &mut on_event,
+    );
+    on_event(BisectEvent::Done(result.clone()));
+    result
+}
+
+fn bisect_in<R, F>(
+    runner: &R,
+    project_root: &Path,
+    good: &str,
+    command: &str,
+    env: &[(String, String)],
+    diag: &RustDiagnostic,
+    on_event: &mut F,
+) -> Result<Bisection, String>
+where
+    R: ProcessRunner + ?Sized,
+    F: FnMut(BisectEvent),
+{
+    let prefix = git(runner, project_root, &["rev-parse", "--show-prefix"])?;
+    let head = git(runner, project_root, &["rev-parse", "HEAD"])?;
+    // a worktree left behind by a process that had the same ID, e.g. before
+    // a reboot, is in the way too
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis())
+        .unwrap_or_default();
+    let worktree = std::env::temp_dir().join(format!(
+        "watch-rust-errors-bisect-{}-{}-{}",
+        process::id(),
+        WORKTREES.fetch_add(1, Ordering::SeqCst),
+        started
+    ));
+    let worktree_arg = worktree.to_string_lossy();
+    git(
+        runner,
+        project_root,
+        &["worktree", "add", "--detach", &worktree_arg, head.trim()],
+    )?;
+
+    let mut bisector = Bisector {
+        runner,
+        worktree: &worktree,
+        build_dir: worktree.join(prefix.trim()),
+        command,
+        env: build_env(project_root, env),
+        diag,
+        builds: 0,
+    };
+    let result = bisector.run(good, head.trim(), on_event);
+    let _ = git(runner, &worktree, &["bisect", "reset"]);
+    git(
+        runner,
+        project_root,
+        &["worktree", "remove", "--force", &worktree_arg],
+    )?;
+    result
+}
+
+struct Bisector<'a, R: ?Sized> {
+    runner: &'a R,
+    worktree: &'a Path,
+    // where in the worktree the project is
+    build_dir: PathBuf,
+    command: &'a str,
+    env: Vec<(String, String)>,
+    diag: &'a RustDiagnostic,
+    builds: usize,
+}
+
+impl<'a, R> Bisector<'a, R>
+where
+    R: ProcessRunner + ?Sized,
+{
+    fn run<F>(&mut self, good: &str, bad: &str, on_event: &mut F) -> Result<Bisection, String>
+    where
+        F: FnMut(BisectEvent),
+    {
+        // both ends have to be what they're said to be, or git will blame
+        // whichever commit is next to the one that's wrong
+        if self.verdict(on_event, None)? != "bad" {
+            return Err(
+                "The diagnostic isn't there at HEAD, so it comes from changes that aren't \
+                 committed yet."
+                    .to_string(),
+            );
+        }
+        git(
+            self.runner,
+            self.worktree,
+            &["checkout", "--quiet", "--detach", good],
+        )?;
+        if self.verdict(on_event, None)? == "bad" {
+            return Err(format!("The diagnostic is already there at {}.", good));
+        }
+
+        let mut output = git(self.runner, self.worktree, &["bisect", "start", bad, good])?;
+        loop {
+            if let Some(commit) = first_bad_commit(&output) {
+                let summary = git(
+                    self.runner,
+                    self.worktree,
+                    &["log", "-1", "--format=%h %s", commit],
+                )?;
+                return Ok(Bisection {
+                    commit: commit.to_string(),
+                    summary: summary.trim().to_string(),
+                    builds: self.builds,
+                });
+            }
+            if output.contains("only 'skip'ped commits left") {
+                return Err(format!(
+                    "None of the commits left could be built:\n{}",
+                    output.trim()
+                ));
+            }
+            let verdict = self.verdict(on_event, steps_left(&output))?;
+            output = git(self.runner, self.worktree, &["bisect", verdict])?;
+        }
+    }
+
+    // Builds what's checked out: "bad" if it reports the diagnostic, "good"
+    // if it doesn't, and "skip" if it couldn't be built at all.
+    fn verdict<F>(&mut self, on_event: &mut F, steps: Option<usize>) -> Result<&'static str, String>
+    where
+        F: FnMut(BisectEvent),
+    {
+        let commit = git(
+            self.runner,
+            self.worktree,
+            &["rev-parse", "--short", "HEAD"],
+        )?;
+        on_event(BisectEvent::Testing(commit.trim().to_string(), steps));
+        self.builds += 1;
+        let result = cargo::run_with(self.runner, &self.build_dir, self.command, &self.env)?;
+        Ok(verdict(&result, self.diag))
+    }
+}
+
+fn verdict(result: &CompileResult, diag: &RustDiagnostic) -> &'static str {
+    if result.diagnostics().any(|d| d.is_same(diag)) {
+        "bad"
+    } else if !result.success && result.errors.is_empty() {
+        // e.g. a manifest cargo can't read, or dependencies it can't get
+        "skip"
+    } else {
+        "good"
+    }
+}
+
+// The commit `git bisect` settled on, once it has.
+pub fn first_bad_commit(output: &str) -> Option<&str> {
+    REGEX_FIRST_BAD
+        .captures(output)
+        .and_then(|caps| caps.get(1))
+        .map(|commit| commit.as_str())
+}
+
+// How many more builds `git bisect` expects to need.
+pub fn steps_left(output: &str) -> Option<usize> {
+    REGEX_STEPS
+        .captures(output)
+        .and_then(|caps| caps[1].parse().ok())
+}
+
+// One target directory for every commit, like comparisons have, so that each
+// build only compiles what changed since the one before.
+fn build_env(project_root: &Path, env: &[(String, String)]) -> Vec<(String, String)> {
+    let mut env = env.to_vec();
+    if !env.iter().any(|(k, _)| k == "CARGO_TARGET_DIR") {
+        let target_dir = project_root.join("target").join("watch-rust-errors-bisect");
+        env.push((
+            "CARGO_TARGET_DIR".to_string(),
+            target_dir.to_string_lossy().into_owned(),
+        ));
+    }
+    env
+}
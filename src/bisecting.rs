@@ -0,0 +1,141 @@
+use futures::future;
+use futures::stream::StreamExt;
+use gettextrs::gettext;
+use glib::MainContext;
+use vgtk::lib::gtk::{ButtonsType, DialogFlags, MessageType};
+use vgtk::scope::Scope;
+use vgtk::UpdateAction;
+
+use watch_rust_errors::bisect::{self, BisectEvent};
+use watch_rust_errors::cargo::{CompileResult, DiagnosticId};
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::rust::RustDiagnostic;
+
+use crate::{ask_git_ref, show_error, Message, Model};
+
+// Finding the commit a diagnostic first appeared in, from asking where to
+// start to saying where it was found.
+#[derive(Clone, Debug)]
+pub enum BisectMessage {
+    SelectStart(DiagnosticId),
+    Start(RustDiagnostic, String),
+    Progress(BisectEvent),
+}
+
+// What a bisection running for a diagnostic is building, for the status bar.
+// One runs at a time.
+#[derive(Default)]
+pub struct Bisecting {
+    status: Option<String>,
+}
+
+// What bisecting goes by: the window's latest results to find the
+// diagnostic in, and how its project is built.
+pub struct BisectTarget<'a> {
+    pub results: Option<&'a CompileResult>,
+    pub project_root: &'a str,
+    pub command: &'a str,
+    pub env: Vec<(String, String)>,
+}
+
+impl Bisecting {
+    pub fn status(&self) -> Option<String> {
+        self.status.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.is_some()
+    }
+
+    pub fn update(
+        &mut self,
+        message: BisectMessage,
+        target: BisectTarget<'_>,
+        scope: &Scope<Model>,
+    ) -> UpdateAction<Model> {
+        match message {
+            BisectMessage::SelectStart(id) => {
+                // the results may move on while the dialog is up
+                let diag = match target.results.and_then(|r| r.diagnostic(id)) {
+                    Some(diag) => diag.clone(),
+                    None => return UpdateAction::None,
+                };
+                UpdateAction::defer(async move {
+                    let good = ask_git_ref(
+                        &gettext("When Did This Appear?"),
+                        &gettext("Bisect"),
+                        &gettext(
+                            "A commit from before the diagnostic appeared. The commits from \
+                             there up to HEAD are built in a temporary worktree with git bisect \
+                             until the first one reporting it is found.",
+                        ),
+                        "HEAD~20",
+                    )
+                    .await;
+                    match good {
+                        Some(good) => Message::Bisect(BisectMessage::Start(diag, good)),
+                        None => Message::NoOp,
+                    }
+                })
+            }
+
+            BisectMessage::Start(diag, good) => {
+                self.status = Some(fill(gettext("Bisecting from {}…"), &[&good]));
+
+                // every step is a build, so report each one as it starts
+                let events = bisect::spawn(
+                    target.project_root,
+                    &good,
+                    target.command,
+                    &target.env,
+                    &diag,
+                );
+                let scope = scope.clone();
+                MainContext::ref_thread_default().spawn_local(events.for_each(move |event| {
+                    scope.send_message(Message::Bisect(BisectMessage::Progress(event)));
+                    future::ready(())
+                }));
+                UpdateAction::Render
+            }
+
+            BisectMessage::Progress(BisectEvent::Testing(commit, steps)) => {
+                self.status = Some(match steps {
+                    Some(steps) => fill(
+                        gettext("Bisecting: building {0}, about {1} to go…"),
+                        &[&commit, &steps],
+                    ),
+                    None => fill(gettext("Bisecting: building {}…"), &[&commit]),
+                });
+                UpdateAction::Render
+            }
+
+            BisectMessage::Progress(BisectEvent::Done(result)) => {
+                self.status = None;
+                let text = match result {
+                    Ok(bisection) => fill(
+                        gettext("The diagnostic first appeared in\n\n{0}\n\nfound in {1} builds."),
+                        &[&bisection.summary, &bisection.builds],
+                    ),
+                    Err(err) => {
+                        return UpdateAction::defer(async move {
+                            show_error(&gettext("COULD NOT BISECT!"), &err).await;
+                            Message::NoOp
+                        })
+                    }
+                };
+                UpdateAction::defer(async move {
+                    vgtk::message_dialog(
+                        vgtk::current_window().as_ref(),
+                        DialogFlags::empty(),
+                        MessageType::Info,
+                        ButtonsType::Ok,
+                        false,
+                        text,
+                    )
+                    .await;
+                    Message::NoOp
+                })
+            }
+        }
+    }
+}
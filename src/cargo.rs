@@ -1,16 +1,824 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fmt::{self, Display};
-use std::ops::Deref;
 use std::path::Path;
-use std::process::Command;
-use std::str;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::rust::{RustDiagnostic, Type};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default)]
+use crate::ansi;
+use crate::cascade::{self, Cascades};
+use crate::config::{self, SortOrder};
+use crate::git::GitTrigger;
+use crate::libtest::{self, TestResult};
+use crate::parser::{Parser, RustcParser};
+use crate::process::{self, ProcessRunner, SystemRunner};
+use crate::rust::{self, RustDiagnostic, Type};
+use crate::wrapper::CacheStats;
+
+// Commands starting with this run through the shell, for pipelines,
+// redirections and the like, e.g. "shell: cargo check 2>&1 | tee build.log".
+// Everything else is split into a program and its arguments and started
+// directly.
+pub const SHELL_PREFIX: &str = "shell:";
+
+// Sends the shell's stderr to stdout before running the command passed as $1,
+// so that diagnostics keep their place among whatever else is printed.
+const MERGE_STREAMS: &str = "exec 2>&1\neval \"$1\"";
+
+// How many of a build's diagnostics are kept unless the project says
+// otherwise, and how much of what the command printed.
+pub const MAX_DIAGNOSTICS: usize = 5_000;
+pub const MAX_OUTPUT: usize = 4 * 1024 * 1024;
+
+lazy_static! {
+    // a variable set for the command, e.g. "RUSTFLAGS=-Dwarnings"
+    static ref REGEX_ASSIGNMENT: Regex = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)=(.*)$").unwrap();
+    static ref REGEX_JSON_MESSAGES: Regex =
+        Regex::new(r"(?:^|\s)--message-format[= ]json").unwrap();
+    static ref REGEX_UNIT: Regex =
+        Regex::new(r"^ +(?:Compiling|Checking|Documenting) ([^ ]+) v").unwrap();
+    static ref REGEX_COULD_NOT_COMPILE: Regex = Regex::new(
+        r"^error: could not compile `([^`]+)`(?: \([^)]*\))?(?:.*due to ([0-9]+ )?previous errors?)?(?:; ([0-9]+) warnings? emitted)?"
+    )
+    .unwrap();
+    // cargo's hints when offline mode kept it from resolving dependencies
+    static ref REGEX_OFFLINE: Regex =
+        Regex::new(r"--offline was specified|you're using offline mode").unwrap();
+    // cargo's status lines while it gets dependencies
+    static ref REGEX_UPDATING: Regex = Regex::new(r"^ *Updating (.+)$").unwrap();
+    static ref REGEX_DOWNLOADING: Regex = Regex::new(r"^ *Downloading crates").unwrap();
+    static ref REGEX_DOWNLOADED: Regex =
+        Regex::new(r"^ *Download(?:ed|ing) ([^ ]+) (v[^ ]+)").unwrap();
+    static ref REGEX_DOWNLOADS_DONE: Regex =
+        Regex::new(r"^ *Downloaded ([0-9]+ crates? .*)$").unwrap();
+    // cargo's progress bar, e.g. "Building [=====>    ] 45/120: serde, tokio"
+    static ref REGEX_BUILDING: Regex =
+        Regex::new(r"^ *Building \[[^\]]*\] ([0-9]+)/([0-9]+): (.+)$").unwrap();
+    static ref REGEX_GENERATED: Regex =
+        Regex::new(r"^warning: `([^`]+)`(?: \([^)]*\))? generated ([0-9]+) warnings?").unwrap();
+    // rustc's own closing counts when it's run without cargo, e.g. "error:
+    // aborting due to 2 previous errors; 1 warning emitted", where a lone
+    // error is "previous error"
+    static ref REGEX_ABORTING: Regex = Regex::new(
+        r"^error: aborting due to ([0-9]+ )?previous errors?(?:; ([0-9]+) warnings? emitted)?"
+    )
+    .unwrap();
+    static ref REGEX_EMITTED: Regex = Regex::new(r"^warning: ([0-9]+) warnings? emitted").unwrap();
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct CompileResult {
     pub success: bool,
     pub errors: Vec<RustDiagnostic>,
     pub warnings: Vec<RustDiagnostic>,
+    pub tests: Option<TestResult>,
+    pub crates: Vec<CrateSummary>,
+    // the compiler that produced the results, when known
+    pub rustc_version: Option<String>,
+    // whether `rustc_version` differs from the previous build's
+    pub toolchain_changed: bool,
+    // everything the command printed
+    #[serde(default)]
+    pub output: String,
+    // offline mode kept cargo from getting dependencies it doesn't have yet
+    #[serde(default)]
+    pub needs_fetch: bool,
+    // when the command started, when it was run by us
+    #[serde(default)]
+    pub started_at: Option<SystemTime>,
+    // how long the command took, when it was run by us
+    #[serde(default)]
+    pub duration: Option<Duration>,
+    // when the command finished, when it was run by us
+    #[serde(default)]
+    pub finished_at: Option<SystemTime>,
+    // the changed files that started the build, none for a manual one
+    #[serde(default)]
+    pub triggered_by: Vec<String>,
+    // what happened to them, each change once, in the order the watcher saw
+    // them
+    #[serde(default)]
+    pub trigger_ops: Vec<PathOp>,
+    // what git did to start the build, when it was git
+    #[serde(default)]
+    pub git_trigger: Option<GitTrigger>,
+    // the wrapper cargo ran rustc through, e.g. "sccache", when known
+    #[serde(default)]
+    pub compiler_wrapper: Option<String>,
+    // what the build did with the wrapper's cache, for wrappers we can ask
+    #[serde(default)]
+    pub cache_stats: Option<CacheStats>,
+    // what cargo's and rustc's closing lines say the build found
+    #[serde(default)]
+    pub reported: Option<ReportedCounts>,
+    // why the post-processor left the diagnostics as they were, if it failed
+    #[serde(default)]
+    pub post_process_error: Option<String>,
+    // how many diagnostics were dropped to keep within the limit
+    #[serde(default)]
+    pub omitted: usize,
+}
+
+// The counts a build's summary lines add up to, checked against the
+// diagnostics read from its output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportedCounts {
+    pub errors: usize,
+    pub warnings: usize,
+    // fewer diagnostics were read than this, e.g. ones in a shape the parser
+    // doesn't know
+    pub unread: bool,
+}
+
+impl Display for ReportedCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} error{}, {} warning{}",
+            self.errors,
+            if self.errors == 1 { "" } else { "s" },
+            self.warnings,
+            if self.warnings == 1 { "" } else { "s" }
+        )
+    }
+}
+
+// A change the watcher saw that started a build, with the path relative to
+// the project root when it's in it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathOp {
+    pub path: String,
+    pub op: Op,
+    // for renames where both ends are known
+    #[serde(default)]
+    pub renamed_to: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+    // e.g. a change to a file's permissions
+    Changed,
+}
+
+impl Display for PathOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Created => "created",
+            Op::Modified => "modified",
+            Op::Removed => "removed",
+            Op::Renamed => "renamed",
+            Op::Changed => "changed",
+        };
+        match self.renamed_to.as_ref() {
+            Some(to) => write!(f, "{} {} → {}", op, self.path, to),
+            None => write!(f, "{} {}", op, self.path),
+        }
+    }
+}
+
+// A line that closes a build or a crate's part in it rather than saying
+// anything about the code, though it looks like a diagnostic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SummaryLine {
+    // cargo's "error: could not compile `foo` (lib) due to 2 previous
+    // errors; 1 warning emitted"
+    CouldNotCompile {
+        krate: String,
+        errors: Option<usize>,
+        warnings: Option<usize>,
+    },
+    // cargo's "warning: `foo` (lib) generated 3 warnings"
+    Generated {
+        krate: String,
+        warnings: usize,
+    },
+    // rustc's "error: aborting due to 2 previous errors; 1 warning emitted"
+    Aborting {
+        errors: usize,
+        warnings: usize,
+    },
+    // rustc's "warning: 2 warnings emitted"
+    Emitted {
+        warnings: usize,
+    },
+}
+
+impl SummaryLine {
+    // `line` without its colors
+    pub fn classify(line: &str) -> Option<Self> {
+        let count = |m: Option<regex::Match>| m.and_then(|m| m.as_str().trim().parse().ok());
+        if let Some(caps) = REGEX_COULD_NOT_COMPILE.captures(line) {
+            // "due to previous error" is the one
+            let errors = caps.get(2).map_or_else(
+                || Some(1).filter(|_| line.contains("previous error")),
+                |m| count(Some(m)),
+            );
+            Some(SummaryLine::CouldNotCompile {
+                krate: caps[1].to_string(),
+                errors,
+                warnings: count(caps.get(3)),
+            })
+        } else if let Some(caps) = REGEX_GENERATED.captures(line) {
+            Some(SummaryLine::Generated {
+                krate: caps[1].to_string(),
+                warnings: count(caps.get(2))?,
+            })
+        } else if let Some(caps) = REGEX_ABORTING.captures(line) {
+            Some(SummaryLine::Aborting {
+                errors: count(caps.get(1)).unwrap_or(1),
+                warnings: count(caps.get(2)).unwrap_or(0),
+            })
+        } else {
+            let caps = REGEX_EMITTED.captures(line)?;
+            Some(SummaryLine::Emitted {
+                warnings: count(caps.get(1))?,
+            })
+        }
+    }
+}
+
+// Something to do around every build the watcher runs, e.g. asking a
+// compiler wrapper what it did. Hooks can't fail the build; whatever they
+// find out goes into its results.
+pub trait BuildHook: Send + Sync {
+    fn before_build(&self, _project_root: &Path, _env: &[(String, String)]) {}
+
+    fn after_build(
+        &self,
+        project_root: &Path,
+        env: &[(String, String)],
+        result: &mut CompileResult,
+    );
+}
+
+// What cargo's trailing summary lines say about a single crate, e.g.
+// "error: could not compile `foo` due to 2 previous errors".
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateSummary {
+    pub name: String,
+    pub failed: bool,
+    pub errors: Option<usize>,
+    pub warnings: Option<usize>,
+}
+
+// What cargo has said so far about getting dependencies, which can take
+// minutes on a first build or after the lockfile changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchProgress {
+    // e.g. "crates.io index", while cargo updates it
+    pub updating: Option<String>,
+    // e.g. "serde v1.0.104", in the order they finished downloading
+    pub downloaded: Vec<String>,
+    // cargo's closing line, e.g. "23 crates (2.1 MB) in 3.20s"
+    pub finished: Option<String>,
+}
+
+impl FetchProgress {
+    // Takes a line of cargo's output into account. Returns whether the line
+    // was about fetching at all.
+    pub fn update(&mut self, line: &str) -> bool {
+        let line = ansi::strip(line);
+        if let Some(caps) = REGEX_DOWNLOADS_DONE.captures(&line) {
+            self.updating = None;
+            self.finished = Some(caps[1].to_string());
+        } else if let Some(caps) = REGEX_DOWNLOADED.captures(&line) {
+            self.updating = None;
+            self.downloaded.push(format!("{} {}", &caps[1], &caps[2]));
+        } else if REGEX_DOWNLOADING.is_match(&line) {
+            self.updating = None;
+        } else if let Some(caps) = REGEX_UPDATING.captures(&line) {
+            self.updating = Some(caps[1].to_string());
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+}
+
+impl Display for FetchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(finished) = self.finished.as_ref() {
+            write!(f, "Downloaded {}", finished)
+        } else if let Some(updating) = self.updating.as_ref() {
+            write!(f, "Updating {}…", updating)
+        } else {
+            match self.downloaded.len() {
+                0 => write!(f, "Downloading crates…"),
+                1 => write!(f, "Downloading crates… 1 done"),
+                n => write!(f, "Downloading crates… {} done", n),
+            }
+        }
+    }
+}
+
+// How far a build has got through the crates it compiles. Only cargo's
+// progress bar says how many there are in all, and cargo only draws it for a
+// terminal, so most of the time this is a count of the crates started.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildProgress {
+    pub done: usize,
+    pub total: Option<usize>,
+    // e.g. "serde", or "serde, tokio" going by the progress bar
+    pub current: Option<String>,
+    // the crate the latest JSON message says is done
+    pub finished: Option<FinishedUnit>,
+}
+
+// A crate compiled, or found up to date, as one of cargo's JSON messages
+// says.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinishedUnit {
+    // cargo's id for the package, as `cargo metadata` has it too
+    pub package_id: String,
+    // up to date, so not compiled at all
+    pub fresh: bool,
+    // how long compiling it took, which only `--timings=json` tells
+    pub duration: Option<Duration>,
+}
+
+impl BuildProgress {
+    // Takes a line of cargo's output into account, as text or as one of its
+    // JSON messages. Returns whether the line was about compiling at all.
+    pub fn update(&mut self, line: &str) -> bool {
+        self.finished = None;
+        if line.starts_with('{') {
+            return self.update_json(line);
+        }
+        // the progress bar redraws itself in place
+        let line = ansi::strip(line);
+        let line = line
+            .rsplit('\r')
+            .find(|s| !s.trim().is_empty())
+            .unwrap_or("");
+        if let Some(caps) = REGEX_BUILDING.captures(line) {
+            self.done = caps[1].parse().unwrap_or(self.done);
+            self.total = caps[2].parse().ok();
+            self.current = Some(caps[3].trim().to_string());
+        } else if let Some(caps) = REGEX_UNIT.captures(line) {
+            self.done += 1;
+            self.current = Some(caps[1].to_string());
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    // `--message-format=json`'s messages for a crate compiled and a build
+    // script run
+    fn update_json(&mut self, line: &str) -> bool {
+        let message = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(message) => message,
+            Err(_) => return false,
+        };
+        let package_id = message["package_id"].as_str().map(ToString::to_string);
+        match message["reason"].as_str() {
+            Some("compiler-artifact") => {
+                self.done += 1;
+                self.current = message["target"]["name"].as_str().map(ToString::to_string);
+                // a build script is compiled before the rest of its package
+                let build_script = message["target"]["kind"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|kind| kind == "custom-build");
+                self.finished =
+                    package_id
+                        .filter(|_| !build_script)
+                        .map(|package_id| FinishedUnit {
+                            package_id,
+                            fresh: message["fresh"].as_bool().unwrap_or(false),
+                            duration: None,
+                        });
+            }
+            Some("timing-info") => {
+                self.finished = package_id.map(|package_id| FinishedUnit {
+                    package_id,
+                    fresh: false,
+                    duration: message["duration"].as_f64().map(Duration::from_secs_f64),
+                });
+            }
+            Some("build-script-executed") => {
+                // e.g. "serde 1.0.136 (registry+https://...)"
+                self.current = message["package_id"]
+                    .as_str()
+                    .and_then(|id| id.split_whitespace().next())
+                    .map(ToString::to_string);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    // how much of the build is done, when that's known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.done as f64 / total as f64).min(1.0))
+    }
+}
+
+impl Display for BuildProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.current.as_ref() {
+            Some(current) => write!(f, "Compiling {}", current)?,
+            None => write!(f, "Compiling")?,
+        }
+        match self.total {
+            Some(total) => write!(f, " ({}/{})", self.done, total),
+            None => write!(f, " ({} so far)", self.done),
+        }
+    }
+}
+
+// What a running build has to say about itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Progress {
+    Fetch(FetchProgress),
+    Build(BuildProgress),
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Fetch(progress) => progress.fmt(f),
+            Progress::Build(progress) => progress.fmt(f),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DiagnosticId(pub usize);
+
+pub struct CrateGroup<'a> {
+    pub name: Option<&'a str>,
+    pub failed: bool,
+    pub diagnostics: Vec<(DiagnosticId, &'a RustDiagnostic)>,
+}
+
+pub struct FileGroup<'a> {
+    pub file: Option<&'a str>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<(DiagnosticId, &'a RustDiagnostic)>,
+}
+
+impl FileGroup<'_> {
+    // How many of the file's lines its diagnostics point at.
+    pub fn lines(&self) -> usize {
+        rust::covered_lines(self.diagnostics.iter().map(|(_, diag)| *diag))
+    }
+}
+
+pub struct CodeGroup<'a> {
+    pub code: Option<&'a str>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<(DiagnosticId, &'a RustDiagnostic)>,
+}
+
+impl CompileResult {
+    // A build that couldn't be run or read, with why as its one error.
+    pub fn failed(error: &str) -> Self {
+        let mut result = CompileResult {
+            finished_at: Some(SystemTime::now()),
+            ..Default::default()
+        };
+        result.push(RustDiagnostic::new(
+            Type::Error,
+            None,
+            &format!("build failed: {}", error),
+            None,
+            None,
+            None,
+            None,
+        ));
+        result
+    }
+
+    pub fn push(&mut self, mut diag: RustDiagnostic) {
+        diag.order = self.errors.len() + self.warnings.len();
+        match diag.type_ {
+            Type::Error => self.errors.push(diag),
+            Type::Warning => self.warnings.push(diag),
+        }
+    }
+
+    // errors first, followed by warnings; `DiagnosticId`s index into this
+    pub fn diagnostics(&self) -> impl Iterator<Item = &RustDiagnostic> {
+        self.errors.iter().chain(self.warnings.iter())
+    }
+
+    pub fn diagnostic(&self, id: DiagnosticId) -> Option<&RustDiagnostic> {
+        self.diagnostics().nth(id.0)
+    }
+
+    // The counterpart of `diag`, from another build, among these results. The
+    // one nearest to where `diag` was if the problem is reported more than
+    // once.
+    pub fn find_same(&self, diag: &RustDiagnostic) -> Option<DiagnosticId> {
+        let line = |d: &RustDiagnostic| i64::from(d.line.unwrap_or_default());
+        self.diagnostics()
+            .enumerate()
+            .filter(|(_, d)| d.is_same(diag))
+            .min_by_key(|(_, d)| (line(d) - line(diag)).abs())
+            .map(|(index, _)| DiagnosticId(index))
+    }
+
+    // Folds diagnostics that were reported more than once, e.g. for every
+    // crate in a workspace that includes the same file, into the first one.
+    pub fn dedup(&mut self) {
+        for diags in [&mut self.errors, &mut self.warnings] {
+            let mut seen: HashMap<_, usize> = HashMap::new();
+            let mut kept: Vec<RustDiagnostic> = vec![];
+            for diag in diags.drain(..) {
+                let key = (
+                    diag.file.clone(),
+                    diag.line,
+                    diag.column,
+                    diag.num.clone().or_else(|| diag.lint.clone()),
+                    diag.message.clone(),
+                );
+                match seen.get(&key).copied() {
+                    Some(index) => kept[index].repeats += 1 + diag.repeats,
+                    None => {
+                        seen.insert(key, kept.len());
+                        kept.push(diag);
+                    }
+                }
+            }
+            *diags = kept;
+        }
+    }
+
+    // Keeps the first `max` diagnostics, errors before warnings, and the first
+    // `MAX_OUTPUT` bytes of the output, so that a build gone badly wrong,
+    // e.g. after a find-and-replace, doesn't have to be sent and listed whole.
+    pub fn truncate(&mut self, max: usize) {
+        let max_warnings = max.saturating_sub(self.errors.len());
+        let dropped = self.errors.len().saturating_sub(max)
+            + self.warnings.len().saturating_sub(max_warnings);
+        self.errors.truncate(max);
+        self.warnings.truncate(max_warnings);
+        self.omitted += dropped;
+
+        if self.output.len() > MAX_OUTPUT {
+            let mut end = MAX_OUTPUT;
+            while !self.output.is_char_boundary(end) {
+                end -= 1;
+            }
+            let left_out = self.output.len() - end;
+            self.output.truncate(end);
+            self.output
+                .push_str(&format!("\n[{} more bytes left out]\n", left_out));
+        }
+    }
+
+    // Drops the diagnostics whose error code or lint is in `ignored`, e.g.
+    // "E0601" or "dead_code".
+    pub fn remove_ignored(&mut self, ignored: &[String]) {
+        let keep = |diag: &RustDiagnostic| {
+            !diag
+                .num
+                .iter()
+                .chain(diag.lint.iter())
+                .any(|code| ignored.contains(code))
+        };
+        self.errors.retain(keep);
+        self.warnings.retain(keep);
+    }
+
+    pub fn crate_summary(&self, name: &str) -> Option<&CrateSummary> {
+        self.crates.iter().find(|c| c.name == name)
+    }
+
+    fn crate_summary_mut(&mut self, name: &str) -> &mut CrateSummary {
+        let index = match self.crates.iter().position(|c| c.name == name) {
+            Some(index) => index,
+            None => {
+                self.crates.push(CrateSummary {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.crates.len() - 1
+            }
+        };
+
+        &mut self.crates[index]
+    }
+
+    // Keeps what a summary line says: cargo's with the crate it's about, and
+    // rustc's, which has no crate to go with, in `rustc`.
+    fn add_summary(&mut self, summary: SummaryLine, rustc: &mut Option<(usize, usize)>) {
+        match summary {
+            SummaryLine::CouldNotCompile {
+                krate,
+                errors,
+                warnings,
+            } => {
+                let summary = self.crate_summary_mut(&krate);
+                summary.failed = true;
+                summary.errors = errors;
+                if warnings.is_some() {
+                    summary.warnings = warnings;
+                }
+            }
+            SummaryLine::Generated { krate, warnings } => {
+                self.crate_summary_mut(&krate).warnings = Some(warnings)
+            }
+            SummaryLine::Aborting { errors, warnings } => {
+                let counts = rustc.get_or_insert((0, 0));
+                counts.0 += errors;
+                counts.1 += warnings;
+            }
+            SummaryLine::Emitted { warnings } => rustc.get_or_insert((0, 0)).1 += warnings,
+        }
+    }
+
+    // What the summary lines add up to, cargo's if there are any since they
+    // cover every crate, checked against what was read.
+    fn reported_counts(&self, rustc: Option<(usize, usize)>) -> Option<ReportedCounts> {
+        let counted = self
+            .crates
+            .iter()
+            .filter(|c| c.errors.is_some() || c.warnings.is_some())
+            .collect::<Vec<_>>();
+        let (errors, warnings) = if !counted.is_empty() {
+            counted.iter().fold((0, 0), |(errors, warnings), c| {
+                (
+                    errors + c.errors.unwrap_or(0),
+                    warnings + c.warnings.unwrap_or(0),
+                )
+            })
+        } else {
+            rustc?
+        };
+        Some(ReportedCounts {
+            errors,
+            warnings,
+            unread: self.errors.len() < errors || self.warnings.len() < warnings,
+        })
+    }
+
+    // Groups diagnostics by the crate they were reported for, with the crates
+    // that failed to build first. Diagnostics that could not be attributed to
+    // a crate end up in a final group without a name.
+    pub fn by_crate(&self) -> Vec<CrateGroup<'_>> {
+        let mut groups: Vec<CrateGroup<'_>> = vec![];
+        for (index, diag) in self.diagnostics().enumerate() {
+            let name = diag.krate.as_deref();
+            let group = match groups.iter().position(|g| g.name == name) {
+                Some(position) => &mut groups[position],
+                None => {
+                    groups.push(CrateGroup {
+                        name,
+                        failed: name
+                            .and_then(|n| self.crate_summary(n))
+                            .map(|c| c.failed)
+                            .unwrap_or(false),
+                        diagnostics: vec![],
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+
+            if let Type::Error = diag.type_ {
+                group.failed = true;
+            }
+            group.diagnostics.push((DiagnosticId(index), diag));
+        }
+
+        // crates that failed without reporting any diagnostics of their own
+        for summary in self.crates.iter().filter(|c| c.failed) {
+            if !groups.iter().any(|g| g.name == Some(summary.name.as_str())) {
+                groups.push(CrateGroup {
+                    name: Some(summary.name.as_str()),
+                    failed: true,
+                    diagnostics: vec![],
+                });
+            }
+        }
+
+        // stable, so compiler order is kept within failed and passing crates
+        groups.sort_by_key(|g| (!g.failed, g.name.is_none()));
+        groups
+    }
+
+    // Groups diagnostics by the source file they point at, sorted by path.
+    // Diagnostics without a location end up in a final group without a file.
+    pub fn by_file(&self) -> Vec<FileGroup<'_>> {
+        let mut groups: Vec<FileGroup<'_>> = vec![];
+        for (index, diag) in self.diagnostics().enumerate() {
+            let file = diag.file.as_deref();
+            let group = match groups.iter().position(|g| g.file == file) {
+                Some(position) => &mut groups[position],
+                None => {
+                    groups.push(FileGroup {
+                        file,
+                        errors: 0,
+                        warnings: 0,
+                        diagnostics: vec![],
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+
+            match diag.type_ {
+                Type::Error => group.errors += 1,
+                Type::Warning => group.warnings += 1,
+            }
+            group.diagnostics.push((DiagnosticId(index), diag));
+        }
+
+        groups.sort_by_key(|g| (g.file.is_none(), g.file));
+        groups
+    }
+
+    // Groups diagnostics by their error code or lint, the codes with the most
+    // diagnostics first. Diagnostics without a code end up in a final group.
+    pub fn by_code(&self) -> Vec<CodeGroup<'_>> {
+        let mut groups: Vec<CodeGroup<'_>> = vec![];
+        for (index, diag) in self.diagnostics().enumerate() {
+            let code = diag.code();
+            let group = match groups.iter().position(|g| g.code == code) {
+                Some(position) => &mut groups[position],
+                None => {
+                    groups.push(CodeGroup {
+                        code,
+                        errors: 0,
+                        warnings: 0,
+                        diagnostics: vec![],
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+
+            match diag.type_ {
+                Type::Error => group.errors += 1,
+                Type::Warning => group.warnings += 1,
+            }
+            group.diagnostics.push((DiagnosticId(index), diag));
+        }
+
+        groups.sort_by_key(|g| (g.code.is_none(), Reverse(g.diagnostics.len()), g.code));
+        groups
+    }
+}
+
+// What a build has to come to for it to count as passing, on top of the
+// command exiting successfully: no warnings either, as with `-D warnings`,
+// and some error codes and lints that count neither way.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SuccessPolicy {
+    pub warnings_fail: bool,
+    pub tolerated: Vec<String>,
+}
+
+impl SuccessPolicy {
+    // Whether `result` passes. A failed build passes only when every error it
+    // had is tolerated and no test failed, since otherwise it failed for some
+    // other reason than its diagnostics.
+    pub fn passes(&self, result: &CompileResult) -> bool {
+        let counted = |diag: &&RustDiagnostic| {
+            !diag
+                .num
+                .iter()
+                .chain(diag.lint.iter())
+                .any(|code| self.tolerated.contains(code))
+        };
+        let errors = result.errors.iter().filter(counted).count();
+        let warnings = result.warnings.iter().filter(counted).count();
+        if self.warnings_fail && warnings > 0 {
+            return false;
+        }
+        if result.success {
+            return true;
+        }
+        let tests_failed = result
+            .tests
+            .as_ref()
+            .map(|tests| !tests.failures.is_empty())
+            .unwrap_or(false);
+        !result.errors.is_empty() && errors == 0 && !tests_failed
+    }
+
+    pub fn apply(&self, result: &mut CompileResult) {
+        result.success = self.passes(result);
+    }
 }
 
 impl Display for CompileResult {
@@ -23,6 +831,13 @@ impl Display for CompileResult {
             write!(f, "{}", wrn)?;
         }
 
+        if let Some(tests) = self.tests.as_ref() {
+            for failure in tests.failures.iter() {
+                write!(f, "{}", failure)?;
+            }
+            writeln!(f, "Tests: {}", tests)?;
+        }
+
         if self.success {
             write!(f, "Compile succeeded.")
         } else {
@@ -31,58 +846,348 @@ impl Display for CompileResult {
     }
 }
 
+// Orders diagnostics, e.g. a group's, for display. Ties keep the compiler's
+// order.
+pub fn sort_diagnostics(diags: &mut [(DiagnosticId, &RustDiagnostic)], order: SortOrder) {
+    match order {
+        SortOrder::Compiler => diags.sort_by_key(|&(_, d)| d.order),
+        SortOrder::Severity => diags.sort_by_key(|&(_, d)| (d.type_ != Type::Error, d.order)),
+        SortOrder::File => diags.sort_by_key(|&(_, d)| {
+            let file = d.file.as_deref();
+            (file.is_none(), file, d.line, d.column, d.order)
+        }),
+        SortOrder::Code => diags.sort_by_key(|&(_, d)| (d.code().is_none(), d.code(), d.order)),
+        SortOrder::RootCause => {
+            let mut reported = diags.to_vec();
+            reported.sort_by_key(|&(_, d)| d.order);
+            let cascades = Cascades::among(reported);
+            // within a stage, errors that follow from others go last and
+            // those with the most following from them first
+            diags.sort_by_key(|&(id, d)| {
+                (
+                    cascade::stage(d),
+                    cascades.cause_of(id).is_some(),
+                    Reverse(cascades.consequences_of(id).len()),
+                    d.order,
+                )
+            })
+        }
+    }
+}
+
 enum ParseState {
     Nothing,
     Diagnostic(String),
 }
 
 pub fn run<P: AsRef<Path>>(project_root: P, command: &str) -> Result<CompileResult, String> {
-    let inp;
-    let (cmd, args) = if cfg!(target_os = "windows") {
-        inp = ["/C", command];
-        ("cmd", inp.into_iter().map(Deref::deref).collect::<Vec<_>>())
+    run_with(&SystemRunner, project_root, command, &[])
+}
+
+pub fn run_with<R, P>(
+    runner: &R,
+    project_root: P,
+    command: &str,
+    env: &[(String, String)],
+) -> Result<CompileResult, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    run_with_progress(
+        runner,
+        project_root,
+        command,
+        env,
+        &RustcParser,
+        &[],
+        |_| {},
+    )
+}
+
+// Like `run_with`, reading the output with `parser`, running `hooks` around
+// the command and calling `on_progress` whenever cargo reports progress on
+// getting dependencies or compiling.
+pub fn run_with_progress<R, P, F>(
+    runner: &R,
+    project_root: P,
+    command: &str,
+    env: &[(String, String)],
+    parser: &dyn Parser,
+    hooks: &[Box<dyn BuildHook>],
+    mut on_progress: F,
+) -> Result<CompileResult, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+    F: FnMut(&Progress),
+{
+    let Invocation { program, args, env } = Invocation::new(command, env)?;
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let project_root = project_root.as_ref();
+    for hook in hooks {
+        hook.before_build(project_root, &env);
+    }
+
+    // wrappers like `just check` may print diagnostics on either stream
+    let started = Instant::now();
+    let started_at = SystemTime::now();
+    let mut fetch = FetchProgress::default();
+    let mut build = BuildProgress::default();
+    // what was printed, both streams in the order they came in
+    let mut printed = String::new();
+    let run = runner.run_streaming(&program, &args, &env, project_root, &mut |line| {
+        printed.push_str(line);
+        printed.push('\n');
+        if fetch.update(line) {
+            on_progress(&Progress::Fetch(fetch.clone()));
+        } else if build.update(line) {
+            on_progress(&Progress::Build(build.clone()));
+        }
+    });
+    let mut stopped = None;
+    let (success, output) = match run {
+        Ok(run) if !printed.is_empty() => (run.success, printed),
+        // runners that don't pass lines on leave only the streams, one after
+        // the other; a stray byte, e.g. in a path or a message in another
+        // locale, shouldn't cost the whole build's output
+        Ok(run) => {
+            let stderr = String::from_utf8_lossy(&run.stderr);
+            let stdout = String::from_utf8_lossy(&run.stdout);
+            (run.success, format!("{}{}", stderr, stdout))
+        }
+        Err(e) if process::is_timed_out(&e) => {
+            stopped = Some(timeout_error(&e));
+            (false, printed)
+        }
+        Err(e) if process::is_cancelled(&e) => {
+            stopped = Some(cancel_error());
+            (false, printed)
+        }
+        Err(e) => match spawn_error(&program, &e) {
+            Some(output) => (false, output),
+            None => return Err(e),
+        },
+    };
+
+    let mut result = parser.parse(success, &output, &output)?;
+    if let Some(stopped) = stopped {
+        result.success = false;
+        result.errors.insert(0, stopped);
+    }
+    result.output = output;
+    result.started_at = Some(started_at);
+    result.duration = Some(started.elapsed());
+    result.finished_at = Some(SystemTime::now());
+    for hook in hooks {
+        hook.after_build(project_root, &env, &mut result);
+    }
+    Ok(result)
+}
+
+// Whether `command` asks cargo for its JSON messages, which say which crate
+// is done as it happens.
+pub fn streams_json(command: &str) -> bool {
+    REGEX_JSON_MESSAGES.is_match(command)
+}
+
+// The program `command` starts, or `None` when the shell runs it.
+pub fn program(command: &str) -> Result<Option<String>, String> {
+    if command.trim_start().starts_with(SHELL_PREFIX) {
+        return Ok(None);
+    }
+    Invocation::new(command, &[]).map(|invocation| Some(invocation.program))
+}
+
+// What starting a command comes down to.
+struct Invocation {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+impl Invocation {
+    // `command` as a program and its arguments, with the variables it sets
+    // up front, as in "RUSTFLAGS=-Dwarnings cargo check", added to `env`.
+    // Shell commands go to the shell as they are.
+    fn new(command: &str, env: &[(String, String)]) -> Result<Self, String> {
+        let mut env = env.to_vec();
+        let shell_command = command.trim_start().strip_prefix(SHELL_PREFIX);
+        if let Some(command) = shell_command.map(str::trim_start) {
+            let (program, args) = if cfg!(target_os = "windows") {
+                ("cmd", vec!["/C", command])
+            } else {
+                ("sh", vec!["-c", MERGE_STREAMS, "sh", command])
+            };
+            return Ok(Invocation {
+                program: program.to_string(),
+                args: args.into_iter().map(ToString::to_string).collect(),
+                env,
+            });
+        }
+
+        let mut words = process::split_command(command)?.into_iter().peekable();
+        while let Some(caps) = words
+            .peek()
+            .and_then(|word| REGEX_ASSIGNMENT.captures(word))
+        {
+            let (name, value) = (caps[1].to_string(), caps[2].to_string());
+            env.retain(|(k, _)| *k != name);
+            env.push((name, value));
+            words.next();
+        }
+        let program = words
+            .next()
+            .ok_or_else(|| format!("No program to run in `{}`", command))?;
+        Ok(Invocation {
+            program,
+            args: words.collect(),
+            env,
+        })
+    }
+}
+
+// A hung command as an error of the build, for the watch to carry on after.
+fn timeout_error(timed_out: &str) -> RustDiagnostic {
+    RustDiagnostic::new(
+        Type::Error,
+        None,
+        &format!("build {}", timed_out),
+        None,
+        None,
+        None,
+        Some(
+            "hint: the command and everything it started were stopped; check for a build \
+             script that doesn't finish or a prompt waiting for input, or raise the \
+             project's `build_timeout`",
+        ),
+    )
+}
+
+// A build stopped from the build queue, which says why it failed.
+fn cancel_error() -> RustDiagnostic {
+    RustDiagnostic::new(
+        Type::Error,
+        None,
+        "build cancelled",
+        None,
+        None,
+        None,
+        Some("hint: the command and everything it started were stopped from the build queue"),
+    )
+}
+
+// A program that couldn't be started is reported the way the shell would,
+// so that it's listed as an error with a hint rather than failing the run.
+fn spawn_error(program: &str, error: &str) -> Option<String> {
+    let reason = if error.contains("kind: NotFound") {
+        if program.contains('/') {
+            "No such file or directory"
+        } else {
+            "not found"
+        }
+    } else if error.contains("kind: PermissionDenied") {
+        "Permission denied"
     } else {
-        inp = ["-c", command];
-        ("sh", inp.into_iter().map(Deref::deref).collect::<Vec<_>>())
+        return None;
     };
+    Some(format!("watch-rust-errors: {}: {}\n", program, reason))
+}
 
-    let command = Command::new(cmd)
-        .args(&args)
-        .current_dir(project_root)
-        .output()
-        .map_err(|e| format!("{:?}", e))?;
-    let output = str::from_utf8(&command.stderr).map_err(|e| format!("{:?}", e))?;
+// Downloads the project's dependencies, even when the environment asks cargo
+// to stay offline.
+pub fn fetch_with<R, P>(runner: &R, project_root: P, env: &[(String, String)]) -> Result<(), String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let mut env = env
+        .iter()
+        .filter(|(k, _)| k != config::OFFLINE_VAR)
+        .cloned()
+        .collect::<Vec<_>>();
+    env.push((config::OFFLINE_VAR.to_string(), "false".to_string()));
+    let output = runner.run("cargo", &["fetch"], &env, project_root.as_ref())?;
+    if output.success {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
 
+// Parses the output of a cargo invocation. Human readable diagnostics are
+// read from `output` while libtest results and JSON diagnostics come from
+// `stdout`; both may be the same text when the streams were captured together.
+// Either may be colored.
+pub fn parse(success: bool, output: &str, stdout: &str) -> Result<CompileResult, String> {
+    let stdout = ansi::strip(stdout);
     let mut state = ParseState::Nothing;
     let mut result = CompileResult {
-        success: command.status.success(),
-        errors: vec![],
-        warnings: vec![],
+        success,
+        tests: libtest::parse(&stdout),
+        ..Default::default()
     };
-    for line in output.lines() {
+    let mut current_crate = None;
+    let mut rustc_counts = None;
+    for raw in output.lines() {
+        // match on the plain text but keep the colors for the diagnostic
+        let line = ansi::strip(raw);
+        result.needs_fetch |= REGEX_OFFLINE.is_match(&line);
         match state {
             ParseState::Nothing => {
-                // skip the line if it does not begin with "warning" or "error"
-                if line.starts_with("warning") || line.starts_with("error") {
-                    state = ParseState::Diagnostic(String::from(&format!("{}\n", line)));
+                // track which crate is being built and pick up cargo's per-crate
+                // summaries and rustc's, which look like diagnostics but aren't;
+                // otherwise skip the line if it does not begin with "warning" or
+                // "error"
+                if let Some(caps) = REGEX_UNIT.captures(&line) {
+                    current_crate = Some(caps[1].to_string());
+                } else if let Some(summary) = SummaryLine::classify(&line) {
+                    result.add_summary(summary, &mut rustc_counts);
+                } else if let Some(diag) = RustDiagnostic::from_shell_error(&line) {
+                    result.push(diag);
+                } else if line.starts_with("warning") || line.starts_with("error") {
+                    state = ParseState::Diagnostic(String::from(&format!("{}\n", raw)));
                 }
             }
             ParseState::Diagnostic(mut diag) => {
-                // if the line is empty, then we are done
-                state = if line.is_empty() {
-                    let diag: RustDiagnostic = diag.parse()?;
-                    match diag.type_ {
-                        Type::Error => result.errors.push(diag),
-                        Type::Warning => result.warnings.push(diag),
-                    };
+                // if the line is empty, or cargo has moved on to the next
+                // crate after e.g. a warning about the manifest, then we
+                // are done; a block that doesn't parse is left for the
+                // summary lines to count as unread rather than failing the
+                // build
+                let unit = REGEX_UNIT.captures(&line);
+                state = if line.is_empty() || unit.is_some() {
+                    if let Ok(mut diag) = diag.parse::<RustDiagnostic>() {
+                        if diag.krate.is_none() {
+                            diag.krate = current_crate.clone();
+                        }
+                        result.push(diag);
+                    }
+                    if let Some(caps) = unit {
+                        current_crate = Some(caps[1].to_string());
+                    }
                     ParseState::Nothing
                 } else {
-                    diag.push_str(&format!("{}\n", line));
+                    diag.push_str(&format!("{}\n", raw));
                     ParseState::Diagnostic(diag)
                 }
             }
         }
     }
 
+    // diagnostics emitted with `--message-format=json` arrive on stdout; a
+    // line that doesn't parse, e.g. the last of a build stopped part way
+    // through printing it, is left out
+    for line in stdout.lines().filter(|l| l.starts_with("{\"reason\":")) {
+        if let Ok(Some(diag)) = RustDiagnostic::from_json(line) {
+            match SummaryLine::classify(&diag.title()) {
+                Some(summary) => result.add_summary(summary, &mut rustc_counts),
+                None => result.push(diag),
+            }
+        }
+    }
+
+    result.reported = result.reported_counts(rustc_counts);
     Ok(result)
 }
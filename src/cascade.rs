@@ -0,0 +1,163 @@
+use std::collections::{HashMap, HashSet};
+use std::iter;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::cargo::{CompileResult, DiagnosticId};
+use crate::rust::{RustDiagnostic, Type};
+
+// Errors about a name that couldn't be found, which tend to be followed by an
+// error for every place that name is used, e.g. "unresolved import `foo::Bar`"
+// or "file not found for module `foo`".
+const CAUSE_CODES: &[&str] = &[
+    "E0405", "E0412", "E0422", "E0425", "E0432", "E0433", "E0463", "E0583",
+];
+
+// path segments too common to tie two errors together
+const SKIPPED_NAMES: &[&str] = &["self", "Self", "crate", "super"];
+
+// missing module files and crates
+const MODULE_CODES: &[&str] = &["E0460", "E0461", "E0462", "E0463", "E0583"];
+// names that don't resolve or can't be used from where they are
+const RESOLVE_CODES: &[&str] = &[
+    "E0405", "E0412", "E0422", "E0423", "E0425", "E0432", "E0433", "E0531", "E0574", "E0603",
+];
+const BORROW_CODES: &[&str] = &[
+    "E0373", "E0381", "E0382", "E0384", "E0499", "E0502", "E0503", "E0505", "E0506", "E0507",
+    "E0596", "E0597", "E0716",
+];
+
+lazy_static! {
+    // rustc's parser errors, which have no codes
+    static ref REGEX_SYNTAX: Regex = Regex::new(
+        r"^(expected |unexpected |unknown start of token|unterminated |unclosed |mismatched closing delimiter|this file contains an unclosed delimiter)"
+    )
+    .unwrap();
+    static ref REGEX_QUOTED: Regex = Regex::new(r"`([^`]+)`").unwrap();
+    static ref REGEX_IDENT: Regex = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap();
+}
+
+// Which errors likely only follow from an earlier one. A guess: an error is
+// taken to follow from an earlier unresolved name if it mentions that name and
+// was reported for the same crate.
+#[derive(Clone, Debug, Default)]
+pub struct Cascades {
+    causes: HashMap<DiagnosticId, DiagnosticId>,
+    consequences: HashMap<DiagnosticId, Vec<DiagnosticId>>,
+}
+
+impl Cascades {
+    pub fn detect(result: &CompileResult) -> Self {
+        // errors come first among the diagnostics, in the order reported
+        Cascades::among(
+            result
+                .errors
+                .iter()
+                .enumerate()
+                .map(|(index, diag)| (DiagnosticId(index), diag)),
+        )
+    }
+
+    // Like `detect`, for some of a build's diagnostics given in the order they
+    // were reported.
+    pub fn among<'a, I>(diags: I) -> Self
+    where
+        I: IntoIterator<Item = (DiagnosticId, &'a RustDiagnostic)>,
+    {
+        let mut cascades = Cascades::default();
+        // names each cause failed to resolve, including those of the errors
+        // that follow from it
+        let mut names: Vec<(DiagnosticId, &RustDiagnostic, HashSet<String>)> = vec![];
+
+        for (id, diag) in diags {
+            if diag.type_ != Type::Error {
+                continue;
+            }
+            let mentioned = idents(&diag.message);
+            let cause = names.iter_mut().find(|(_, cause, names)| {
+                cause.krate == diag.krate && mentioned.iter().any(|n| names.contains(n))
+            });
+
+            match cause {
+                Some((cause, _, names)) => {
+                    names.extend(unresolved_name(diag));
+                    cascades.causes.insert(id, *cause);
+                    cascades.consequences.entry(*cause).or_default().push(id);
+                }
+                None => {
+                    if let Some(name) = unresolved_name(diag) {
+                        names.push((id, diag, iter::once(name).collect()));
+                    }
+                }
+            }
+        }
+
+        cascades
+    }
+
+    // The error `id` probably follows from, if any.
+    pub fn cause_of(&self, id: DiagnosticId) -> Option<DiagnosticId> {
+        self.causes.get(&id).copied()
+    }
+
+    // The errors that probably follow from `id`, in the order reported.
+    pub fn consequences_of(&self, id: DiagnosticId) -> &[DiagnosticId] {
+        self.consequences
+            .get(&id)
+            .map(|ids| ids.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.causes.is_empty()
+    }
+}
+
+// How early in compilation the problem `diag` reports is found, from 0 for
+// parsing, through finding modules and crates, resolving names and type
+// checking, to borrow checking. Warnings come after all of them.
+pub fn stage(diag: &RustDiagnostic) -> u8 {
+    if diag.type_ == Type::Warning {
+        return 5;
+    }
+    match diag.num.as_deref() {
+        None if REGEX_SYNTAX.is_match(&diag.message) => 0,
+        Some(code) if MODULE_CODES.contains(&code) => 1,
+        Some(code) if RESOLVE_CODES.contains(&code) => 2,
+        Some(code) if BORROW_CODES.contains(&code) => 4,
+        _ => 3,
+    }
+}
+
+// The last path segment of the first name quoted in an error that's about a
+// name not being found, e.g. "Bar" for "unresolved import `foo::Bar`".
+fn unresolved_name(diag: &RustDiagnostic) -> Option<String> {
+    let code = diag.num.as_deref()?;
+    if !CAUSE_CODES.contains(&code) {
+        return None;
+    }
+
+    let quoted = REGEX_QUOTED.captures(&diag.message)?;
+    quoted[1]
+        .rsplit("::")
+        .next()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && !SKIPPED_NAMES.contains(name))
+        .map(ToString::to_string)
+}
+
+// Every identifier quoted in `message`, e.g. "Vec" and "Bar" for
+// "expected `Vec<Bar>`".
+fn idents(message: &str) -> HashSet<String> {
+    REGEX_QUOTED
+        .captures_iter(message)
+        .flat_map(|quoted| {
+            REGEX_IDENT
+                .find_iter(quoted.get(1).unwrap().as_str())
+                .map(|m| m.as_str().to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|name| !SKIPPED_NAMES.contains(&name.as_str()))
+        .collect()
+}
@@ -0,0 +1,128 @@
+use std::cell::RefCell;
+
+use gettextrs::gettext;
+use vgtk::lib::gtk::{
+    prelude::*, ApplicationWindow, ButtonsType, DialogFlags, Inhibit, MessageDialog, MessageType,
+    ResponseType, Window,
+};
+
+// What closing a window does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseGuard {
+    Close,
+    // asks first, as it would stop a watch
+    Confirm,
+    // minimizes it instead, for the watch to go on in the background
+    Minimize,
+}
+
+thread_local! {
+    // the windows with a guard, and what it is
+    static GUARDS: RefCell<Vec<(Window, CloseGuard)>> = RefCell::new(vec![]);
+}
+
+// Decides what closing the window does, e.g.
+// `<ApplicationWindow close_guard=CloseGuard::Confirm />`.
+pub trait CloseGuardExt {
+    fn set_close_guard(&self, guard: CloseGuard);
+}
+
+impl CloseGuardExt for ApplicationWindow {
+    fn set_close_guard(&self, guard: CloseGuard) {
+        let window = self.clone().upcast::<Window>();
+        let known = GUARDS.with(|guards| {
+            let mut guards = guards.borrow_mut();
+            match guards.iter_mut().find(|(known, _)| *known == window) {
+                Some(entry) => {
+                    entry.1 = guard;
+                    true
+                }
+                None => {
+                    guards.push((window.clone(), guard));
+                    false
+                }
+            }
+        });
+        if known {
+            return;
+        }
+        window.connect_delete_event(|window, _| match guard_of(window) {
+            CloseGuard::Close => Inhibit(false),
+            CloseGuard::Minimize => {
+                window.iconify();
+                Inhibit(true)
+            }
+            CloseGuard::Confirm => {
+                confirm(window, vec![window.clone()]);
+                Inhibit(true)
+            }
+        });
+        window.connect_destroy(|window| {
+            GUARDS.with(|guards| guards.borrow_mut().retain(|(known, _)| known != window));
+        });
+    }
+}
+
+fn guard_of(window: &Window) -> CloseGuard {
+    GUARDS.with(|guards| {
+        guards
+            .borrow()
+            .iter()
+            .find(|(known, _)| known == window)
+            .map(|(_, guard)| *guard)
+            .unwrap_or(CloseGuard::Close)
+    })
+}
+
+// Closes every window, which quits the app, asking first if that stops a
+// watch; minimizing instead is only for closing a window. Returns `false`
+// when there's no window to close.
+pub fn quit() -> bool {
+    let guards = GUARDS.with(|guards| guards.borrow().clone());
+    let windows = guards
+        .iter()
+        .map(|(window, _)| window.clone())
+        .collect::<Vec<_>>();
+    match guards.iter().find(|(_, guard)| *guard != CloseGuard::Close) {
+        Some((watching, _)) => confirm(watching, windows),
+        None => close(&windows),
+    }
+    !guards.is_empty()
+}
+
+// Asks whether to stop watching, and closes `windows` if so.
+fn confirm(parent: &Window, windows: Vec<Window>) {
+    let dialog = MessageDialog::new(
+        Some(parent),
+        DialogFlags::MODAL,
+        MessageType::Question,
+        ButtonsType::None,
+        &gettext("Stop watching and quit?"),
+    );
+    dialog.set_property_secondary_text(Some(&gettext(
+        "A build that is running is stopped as well.",
+    )));
+    dialog.add_button(&gettext("Cancel"), ResponseType::Cancel);
+    dialog.add_button(&gettext("Stop and Quit"), ResponseType::Accept);
+    dialog.connect_response(move |dialog, response| {
+        dialog.destroy();
+        if response == ResponseType::Accept {
+            close(&windows);
+        }
+    });
+    dialog.show();
+}
+
+// Closes `windows` without asking.
+fn close(windows: &[Window]) {
+    for window in windows {
+        GUARDS.with(|guards| {
+            for (known, guard) in guards.borrow_mut().iter_mut() {
+                if known == window {
+                    *guard = CloseGuard::Close;
+                }
+            }
+        });
+        window.close();
+    }
+}
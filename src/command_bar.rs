@@ -0,0 +1,246 @@
+use gettextrs::gettext;
+use vgtk::lib::gdk::{self, enums::key};
+use vgtk::lib::gio::Menu;
+use vgtk::lib::glib::Type;
+use vgtk::lib::gtk::{
+    prelude::*, Box, Button, Entry, EntryCompletion, EntryExt, Inhibit, Label, ListStore,
+    MenuButton, TreeModel, Widget, Window, WindowType,
+};
+use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
+
+use watch_rust_errors::i18n::fill;
+
+use crate::accessible::AccessibleNameExt;
+use crate::config;
+use crate::style::InvalidExt;
+use crate::LABEL_CHARS;
+
+// The command to build with, the profile it's from and the button that
+// starts and stops watching.
+#[derive(Default)]
+pub struct CommandBar {
+    props: CommandProps,
+}
+
+#[derive(Clone, Default)]
+pub struct CommandProps {
+    pub command: String,
+    // not while following someone else's watch
+    pub editable: bool,
+    // why watching couldn't start with what's entered
+    pub error: Option<String>,
+    pub profile_label: String,
+    // the names of the project's profiles, to switch between
+    pub profiles: Vec<String>,
+    // the features the project declares, to turn on and off
+    pub features: Vec<String>,
+    pub toolchain_label: String,
+    // the rustup toolchains to pick from
+    pub toolchains: Vec<String>,
+    pub target_label: String,
+    // the targets the toolchain can build for
+    pub targets: Vec<String>,
+    pub watching: bool,
+    // the commands watched with before, the latest first
+    pub history: Vec<String>,
+    pub on_changed: Callback<String>,
+    pub on_toggle_watch: Callback<()>,
+}
+
+#[derive(Clone, Debug)]
+pub enum CommandMessage {
+    NoOp,
+    Changed(String),
+    ToggleWatch,
+}
+
+impl Component for CommandBar {
+    type Message = CommandMessage;
+    type Properties = CommandProps;
+
+    fn create(props: Self::Properties) -> Self {
+        CommandBar { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        self.props = props;
+        UpdateAction::Render
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            CommandMessage::NoOp => {}
+            CommandMessage::Changed(command) => self.props.on_changed.send(command),
+            CommandMessage::ToggleWatch => self.props.on_toggle_watch.send(()),
+        }
+        UpdateAction::None
+    }
+
+    fn view(&self) -> VNode<CommandBar> {
+        let profile_tooltip = gettext("Switch profile");
+        let toolchain_tooltip = gettext("Switch toolchain");
+        let target_tooltip = gettext("Switch target");
+        let features_tooltip = gettext("Pick the features to build with");
+        let entry_name = gettext("Build command");
+        let profile_name = fill(gettext("Profile: {}"), &[&self.props.profile_label]);
+        let toolchain_name = fill(gettext("Toolchain: {}"), &[&self.props.toolchain_label]);
+        let target_name = fill(gettext("Target: {}"), &[&self.props.target_label]);
+        gtk! {
+            <Box spacing=10>
+                <Label label=gettext("Command:") width_chars=LABEL_CHARS xalign=1.0 />
+                <Box spacing=6 hexpand=true>
+                    <Entry hexpand=true accessible_name=entry_name.as_str()
+                           editable=self.props.editable
+                           invalid=self.props.error.is_some()
+                           tooltip_text=self.props.error.as_deref().unwrap_or("")
+                           text=self.props.command.clone()
+                           placeholder_text="cargo check"
+                           completion=Some(&history_completion(&self.props.history))
+                           on realize=|entry| {
+                               entry.connect_key_press_event(recall);
+                               CommandMessage::NoOp
+                           }
+                           on property_text_notify=|inp| {
+                               match inp.get_text().map(|s| s.as_str().to_owned()) {
+                                   Some(command) => CommandMessage::Changed(command),
+                                   None => CommandMessage::NoOp,
+                               }
+                           } />
+                    <MenuButton label=self.props.profile_label.clone()
+                            tooltip_text=profile_tooltip.as_str()
+                            accessible_name=profile_name.as_str()
+                            menu_model=Some(&profile_menu(&self.props.profiles)) />
+                    <MenuButton label=self.props.toolchain_label.clone()
+                            tooltip_text=toolchain_tooltip.as_str()
+                            accessible_name=toolchain_name.as_str()
+                            menu_model=Some(&toolchain_menu(&self.props.toolchains))
+                            no_show_all=true visible=!self.props.toolchains.is_empty() />
+                    <MenuButton label=self.props.target_label.clone()
+                            tooltip_text=target_tooltip.as_str()
+                            accessible_name=target_name.as_str()
+                            menu_model=Some(&target_menu(&self.props.targets))
+                            no_show_all=true visible={ self.props.targets.len() > 1 } />
+                    <MenuButton label=gettext("Features") tooltip_text=features_tooltip.as_str()
+                            menu_model=Some(&feature_menu(&self.props.features))
+                            no_show_all=true visible=!self.props.features.is_empty() />
+                </Box>
+                <Button label={ if self.props.watching { gettext("Stop Watching") } else { gettext("Start Watching") } }
+                        on clicked=|_| CommandMessage::ToggleWatch />
+            </Box>
+        }
+    }
+}
+
+// Offers the commands from the history that start with what's typed.
+fn history_completion(history: &[String]) -> EntryCompletion {
+    let store = ListStore::new(&[Type::String]);
+    for command in history {
+        store.insert_with_values(None, &[0], &[command]);
+    }
+    let completion = EntryCompletion::new();
+    completion.set_model(Some(&store));
+    completion.set_text_column(0);
+    completion
+}
+
+// Up and Down go back and forth through the history, which the entry's
+// completion holds as of the last render.
+fn recall(entry: &Entry, event: &gdk::EventKey) -> Inhibit {
+    let older = match event.get_keyval() {
+        key::Up => true,
+        key::Down => false,
+        _ => return Inhibit(false),
+    };
+    // with the completion's popup open, the keys pick from it instead
+    if popup_open(entry) {
+        return Inhibit(false);
+    }
+    let history = entry
+        .get_completion()
+        .and_then(|completion| completion.get_model())
+        .map(|model| commands(&model))
+        .unwrap_or_default();
+    let current = entry.get_text().map(|s| s.to_string()).unwrap_or_default();
+    if let Some(command) = config::recall_command(&history, &current, older) {
+        entry.set_text(command);
+        entry.set_position(-1);
+    }
+    Inhibit(true)
+}
+
+fn popup_open(entry: &Entry) -> bool {
+    let entry: &Widget = entry.upcast_ref();
+    Window::list_toplevels()
+        .into_iter()
+        .filter_map(|widget| widget.downcast::<Window>().ok())
+        .any(|window| {
+            window.get_window_type() == WindowType::Popup
+                && window.is_visible()
+                && window.get_attached_to().as_ref() == Some(entry)
+        })
+}
+
+fn commands(model: &TreeModel) -> Vec<String> {
+    let mut commands = vec![];
+    if let Some(iter) = model.get_iter_first() {
+        loop {
+            if let Ok(Some(command)) = model.get_value(&iter, 0).get::<String>() {
+                commands.push(command);
+            }
+            if !model.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+    commands
+}
+
+// The default features, then each of the others, as checkboxes that change
+// the command's `--no-default-features` and `--features`.
+pub fn feature_menu(features: &[String]) -> Menu {
+    let menu = Menu::new();
+    menu.append(
+        Some(&gettext("Default features")),
+        Some("win.default-features"),
+    );
+    let section = Menu::new();
+    for (index, name) in features.iter().enumerate() {
+        // menus take underscores for mnemonics
+        let label = name.replace('_', "__");
+        section.append(Some(&label), Some(&format!("win.feature-{}", index)));
+    }
+    menu.append_section(None, &section);
+    menu
+}
+
+// Whatever rustup picks for the project, then each toolchain to pick instead.
+pub fn toolchain_menu(toolchains: &[String]) -> Menu {
+    let menu = Menu::new();
+    menu.append(Some(&gettext("Project default")), Some("win.toolchain::"));
+    let section = Menu::new();
+    for name in toolchains {
+        section.append(Some(name), Some(&format!("win.toolchain::{}", name)));
+    }
+    menu.append_section(None, &section);
+    menu
+}
+
+// The host, then each target rustup has the standard library for.
+pub fn target_menu(targets: &[String]) -> Menu {
+    let menu = Menu::new();
+    menu.append(Some(&gettext("Host")), Some("win.target::"));
+    let section = Menu::new();
+    for target in targets {
+        section.append(Some(target), Some(&format!("win.target::{}", target)));
+    }
+    menu.append_section(None, &section);
+    menu
+}
+
+pub fn profile_menu(profiles: &[String]) -> Menu {
+    let menu = Menu::new();
+    for name in profiles {
+        menu.append(Some(name), Some(&format!("win.profile::{}", name)));
+    }
+    menu
+}
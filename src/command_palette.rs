@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+use std::ptr;
+use std::rc::Rc;
+
+use gettextrs::gettext;
+use vgtk::lib::gdk::{self, enums::key};
+use vgtk::lib::gio::{ActionGroupExt, MenuModel, MenuModelExt};
+use vgtk::lib::glib::{ToVariant, VariantTy};
+use vgtk::lib::gtk::{
+    self, prelude::*, Align, ApplicationWindow, Inhibit, Label, ListBox, ListBoxRow, Orientation,
+    Popover, PositionType, ScrolledWindow, SearchEntry, SelectionMode,
+};
+
+use watch_rust_errors::palette::{self, Command};
+
+// how big the list of commands is, in pixels
+const LIST_WIDTH: i32 = 420;
+const LIST_HEIGHT: i32 = 320;
+
+// the action that shows the palette, which it doesn't offer itself
+pub const ACTION: &str = "win.command-palette";
+
+// The items of `menu` and the menus in it as commands, with the label of the
+// section they're in, e.g. "Theme: Dark", and `prefix`'s before that.
+pub fn menu_commands(menu: &MenuModel, prefix: Option<&str>) -> Vec<Command> {
+    let mut commands = vec![];
+    for index in 0..menu.get_n_items() {
+        let label = menu
+            .get_item_attribute_value(index, "label", Some(VariantTy::new("s").unwrap()))
+            .and_then(|label| label.get_str().map(without_mnemonics));
+        let section = menu
+            .get_item_link(index, "section")
+            .or_else(|| menu.get_item_link(index, "submenu"));
+        if let Some(section) = section {
+            let prefix = match (prefix, label.as_deref()) {
+                (Some(prefix), Some(label)) => Some(format!("{}: {}", prefix, label)),
+                (prefix, label) => prefix.or(label).map(ToString::to_string),
+            };
+            commands.extend(menu_commands(&section, prefix.as_deref()));
+            continue;
+        }
+        let action = menu
+            .get_item_attribute_value(index, "action", Some(VariantTy::new("s").unwrap()))
+            .and_then(|action| action.get_str().map(ToString::to_string));
+        let target = menu
+            .get_item_attribute_value(index, "target", Some(VariantTy::new("s").unwrap()))
+            .and_then(|target| target.get_str().map(ToString::to_string));
+        let (label, action) = match (label, action) {
+            (Some(label), Some(action)) => (label, action),
+            _ => continue,
+        };
+        let label = match prefix {
+            Some(prefix) => format!("{}: {}", prefix, label),
+            None => label,
+        };
+        let action = match target {
+            Some(target) => format!("{}::{}", action, target),
+            None => action,
+        };
+        commands.push(Command::new(&label, &action));
+    }
+    commands
+}
+
+// Menus take underscores for mnemonics, and two for an underscore.
+fn without_mnemonics(label: &str) -> String {
+    label
+        .split("__")
+        .map(|part| part.replace('_', ""))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+// Shows the palette under the window's header bar, with the commands it can
+// run now, and runs the one picked.
+pub fn show(window: &ApplicationWindow, mut commands: Vec<Command>) {
+    commands.retain(|command| command.action != ACTION && enabled(window, &command.action));
+    let relative_to = match window.get_titlebar() {
+        Some(titlebar) => titlebar,
+        None => window.clone().upcast(),
+    };
+    let popover = Popover::new(Some(&relative_to));
+    popover.set_position(PositionType::Bottom);
+
+    let search = SearchEntry::new();
+    search.set_placeholder_text(Some(&gettext("Type a command")));
+    let list = ListBox::new();
+    list.set_selection_mode(SelectionMode::Browse);
+    list.set_activate_on_single_click(true);
+    let mut rows = vec![];
+    for command in &commands {
+        let row = ListBoxRow::new();
+        let line = gtk::Box::new(Orientation::Horizontal, 10);
+        let label = Label::new(Some(&command.label));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        line.add(&label);
+        if let Some(accel) = accel_label(window, &command.action) {
+            let accel = Label::new(Some(&accel));
+            accel.get_style_context().add_class("dim-label");
+            line.add(&accel);
+        }
+        line.set_border_width(6);
+        row.add(&line);
+        list.add(&row);
+        rows.push(row);
+    }
+    let commands = Rc::new(commands);
+    let rows = Rc::new(rows);
+    // where each command is in the list for what's typed, if it's there
+    let ranks = Rc::new(RefCell::new(
+        (0..commands.len()).map(Some).collect::<Vec<_>>(),
+    ));
+    let index_of = move |row: &ListBoxRow| rows.iter().position(|known| known == row);
+    let rank_of = {
+        let ranks = ranks.clone();
+        let index_of = index_of.clone();
+        move |row: &ListBoxRow| index_of(row).and_then(|index| ranks.borrow()[index])
+    };
+    list.set_filter_func(Some(Box::new({
+        let rank_of = rank_of.clone();
+        move |row| rank_of(row).is_some()
+    })));
+    list.set_sort_func(Some(Box::new(move |a, b| {
+        rank_of(a).cmp(&rank_of(b)) as i32
+    })));
+
+    search.connect_search_changed({
+        let list = list.clone();
+        let commands = commands.clone();
+        move |search| {
+            let query = search.get_text().map(|s| s.to_string()).unwrap_or_default();
+            let matches = palette::filter(&commands, &query);
+            ranks.replace(
+                commands
+                    .iter()
+                    .map(|command| matches.iter().position(|m| ptr::eq(*m, command)))
+                    .collect(),
+            );
+            list.invalidate_filter();
+            list.invalidate_sort();
+            list.select_row(first_row(&list).as_ref());
+        }
+    });
+    // Enter runs the best match, Down goes through the others
+    search.connect_activate({
+        let list = list.clone();
+        move |_| {
+            if let Some(row) = list.get_selected_row().or_else(|| first_row(&list)) {
+                row.activate();
+            }
+        }
+    });
+    search.connect_key_press_event({
+        let list = list.clone();
+        move |_, event: &gdk::EventKey| match event.get_keyval() {
+            key::Down => {
+                if let Some(row) = list.get_selected_row().or_else(|| first_row(&list)) {
+                    row.grab_focus();
+                }
+                Inhibit(true)
+            }
+            _ => Inhibit(false),
+        }
+    });
+    list.connect_row_activated({
+        let popover = popover.clone();
+        let window = window.clone();
+        move |_, row| {
+            popover.popdown();
+            if let Some(index) = index_of(row) {
+                run(&window, &commands[index].action);
+            }
+        }
+    });
+
+    let scrolled = ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+    scrolled.set_size_request(LIST_WIDTH, LIST_HEIGHT);
+    scrolled.add(&list);
+    let content = gtk::Box::new(Orientation::Vertical, 6);
+    content.set_border_width(6);
+    content.add(&search);
+    content.add(&scrolled);
+    popover.add(&content);
+
+    popover.connect_closed(|popover| popover.destroy());
+    popover.show_all();
+    list.select_row(first_row(&list).as_ref());
+    search.grab_focus();
+}
+
+fn first_row(list: &ListBox) -> Option<ListBoxRow> {
+    list.get_children()
+        .into_iter()
+        .filter_map(|child| child.downcast::<ListBoxRow>().ok())
+        .find(|row| row.get_child_visible())
+}
+
+fn enabled(window: &ApplicationWindow, action: &str) -> bool {
+    match palette::parse_action(action) {
+        Some(("win", name, _)) => window.has_action(name) && window.get_action_enabled(name),
+        Some(("app", name, _)) => window.get_application().map_or(false, |app| {
+            app.has_action(name) && app.get_action_enabled(name)
+        }),
+        _ => false,
+    }
+}
+
+// Runs `action` as its menu item would.
+fn run(window: &ApplicationWindow, action: &str) {
+    let (group, name, target) = match palette::parse_action(action) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+    let target = target.map(|target| target.to_variant());
+    match group {
+        "win" => window.activate_action(name, target.as_ref()),
+        "app" => {
+            if let Some(app) = window.get_application() {
+                app.activate_action(name, target.as_ref());
+            }
+        }
+        _ => {}
+    }
+}
+
+// The first keyboard shortcut for `action`, as it's shown in menus.
+fn accel_label(window: &ApplicationWindow, action: &str) -> Option<String> {
+    let app = window.get_application()?;
+    let accel = app.get_accels_for_action(action).into_iter().next()?;
+    let (key, mods) = gtk::accelerator_parse(&accel);
+    gtk::accelerator_get_label(key, mods).map(|label| label.to_string())
+}
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::cargo::{self, CompileResult, DiagnosticId};
+use crate::git::git;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::rust::RustDiagnostic;
+
+// What the working tree's build reports that a build of another ref doesn't,
+// and the other way around.
+#[derive(Clone, Debug, Default)]
+pub struct Comparison {
+    pub git_ref: String,
+    pub introduced: Vec<RustDiagnostic>,
+    pub fixed: Vec<RustDiagnostic>,
+}
+
+// What changed between one build and the next.
+#[derive(Clone, Debug, Default)]
+pub struct RunDiff {
+    // the later build's diagnostics that the earlier one didn't have
+    pub new: HashSet<DiagnosticId>,
+    pub fixed: Vec<RustDiagnostic>,
+    pub unchanged: usize,
+}
+
+pub fn compare<P: AsRef<Path>>(
+    project_root: P,
+    git_ref: &str,
+    command: &str,
+    env: &[(String, String)],
+    current: &CompileResult,
+) -> Result<Comparison, String> {
+    compare_with(&SystemRunner, project_root, git_ref, command, env, current)
+}
+
+pub fn compare_with<R, P>(
+    runner: &R,
+    project_root: P,
+    git_ref: &str,
+    command: &str,
+    env: &[(String, String)],
+    current: &CompileResult,
+) -> Result<Comparison, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let base = build_ref(runner, project_root, git_ref, command, env)?;
+    Ok(diff(git_ref, &base, current))
+}
+
+// Runs `command` in a temporary worktree checked out at `git_ref`, so that
+// nothing in the working tree has to be stashed.
+pub fn build_ref<R, P>(
+    runner: &R,
+    project_root: P,
+    git_ref: &str,
+    command: &str,
+    env: &[(String, String)],
+) -> Result<CompileResult, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let project_root = project_root.as_ref();
+    // the project may live in a sub directory of the repository
+    let prefix = git(runner, project_root, &["rev-parse", "--show-prefix"])?;
+    let worktree =
+        std::env::temp_dir().join(format!("watch-rust-errors-compare-{}", process::id()));
+    let worktree_arg = worktree.to_string_lossy();
+    git(
+        runner,
+        project_root,
+        &["worktree", "add", "--detach", &worktree_arg, git_ref],
+    )?;
+
+    // reuse one target directory across comparisons instead of building the
+    // other ref from scratch every time
+    let mut env = env.to_vec();
+    if !env.iter().any(|(k, _)| k == "CARGO_TARGET_DIR") {
+        env.push((
+            "CARGO_TARGET_DIR".to_string(),
+            target_dir(project_root).to_string_lossy().into_owned(),
+        ));
+    }
+    let result = cargo::run_with(runner, worktree.join(prefix.trim()), command, &env);
+
+    git(
+        runner,
+        project_root,
+        &["worktree", "remove", "--force", &worktree_arg],
+    )?;
+
+    result
+}
+
+// Matches diagnostics by kind, code, message and file. Line numbers are left
+// out since unrelated edits move them around.
+pub fn diff(git_ref: &str, base: &CompileResult, head: &CompileResult) -> Comparison {
+    let runs = diff_runs(base, head);
+    Comparison {
+        git_ref: git_ref.to_string(),
+        introduced: head
+            .diagnostics()
+            .enumerate()
+            .filter(|(index, _)| runs.new.contains(&DiagnosticId(*index)))
+            .map(|(_, diag)| diag.clone())
+            .collect(),
+        fixed: runs.fixed,
+    }
+}
+
+// Like `diff`, for builds of the same tree, keeping track of which of the
+// later build's diagnostics are new.
+pub fn diff_runs(previous: &CompileResult, current: &CompileResult) -> RunDiff {
+    let mut unmatched = previous.diagnostics().collect::<Vec<_>>();
+    let mut new = HashSet::new();
+    for (index, diag) in current.diagnostics().enumerate() {
+        match unmatched.iter().position(|d| d.is_same(diag)) {
+            Some(position) => {
+                unmatched.remove(position);
+            }
+            None => {
+                new.insert(DiagnosticId(index));
+            }
+        }
+    }
+
+    RunDiff {
+        unchanged: current.diagnostics().count() - new.len(),
+        new,
+        fixed: unmatched.into_iter().cloned().collect(),
+    }
+}
+
+fn target_dir(project_root: &Path) -> PathBuf {
+    project_root
+        .join("target")
+        .join("watch-rust-errors-compare")
+}
@@ -0,0 +1,1053 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cargo::{self, SuccessPolicy};
+use crate::container::{self, ContainerRunner};
+use crate::git;
+use crate::history::SlowBuilds;
+use crate::hooks::{self, Hook};
+use crate::notifications::{NotifyEvent, SinkConfig, SinkKind};
+use crate::parser::{GccParser, Parser, PassthroughParser, ProblemMatcher, RustcParser};
+use crate::postprocess::PostProcessor;
+use crate::priority::Priority;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::quickfix::{self, EditorPreset};
+use crate::toolchain;
+
+const APP_DIR: &str = "watch-rust-errors";
+const CONFIG_FILE: &str = "config.toml";
+
+// how many of the commands watched with are remembered
+pub const MAX_HISTORY: usize = 50;
+
+// the file at a project's root with settings of its own, see `LocalConfig`
+pub const LOCAL_CONFIG: &str = ".watch-rust-errors.toml";
+
+// cargo's equivalent of passing `--offline` to every command
+pub const OFFLINE_VAR: &str = "CARGO_NET_OFFLINE";
+
+// offered for projects that haven't set up any profiles of their own
+const DEFAULT_PROFILES: &[(&str, &str)] = &[
+    ("Check", "cargo check"),
+    ("Clippy", "cargo clippy"),
+    ("Test", "cargo test"),
+    ("Release build", "cargo build --release"),
+    // rustdoc's warnings, e.g. broken intra-doc links, read like rustc's
+    ("Docs", "cargo doc --no-deps"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "system" => Ok(Theme::System),
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            _ => Err(format!("Invalid theme {}", inp)),
+        }
+    }
+}
+
+// How to draw attention to a failed build while the window is in the
+// background.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alert {
+    #[default]
+    Off,
+    Sound,
+    // the taskbar's urgency hint
+    Flash,
+    SoundAndFlash,
+}
+
+impl Alert {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Alert::Off => "off",
+            Alert::Sound => "sound",
+            Alert::Flash => "flash",
+            Alert::SoundAndFlash => "sound-and-flash",
+        }
+    }
+
+    pub fn sound(self) -> bool {
+        matches!(self, Alert::Sound | Alert::SoundAndFlash)
+    }
+
+    pub fn flash(self) -> bool {
+        matches!(self, Alert::Flash | Alert::SoundAndFlash)
+    }
+}
+
+impl FromStr for Alert {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "off" => Ok(Alert::Off),
+            "sound" => Ok(Alert::Sound),
+            "flash" => Ok(Alert::Flash),
+            "sound-and-flash" => Ok(Alert::SoundAndFlash),
+            _ => Err(format!("Invalid alert {}", inp)),
+        }
+    }
+}
+
+// A time of day as minutes since midnight, stored as "HH:MM".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub u32);
+
+impl TimeOfDay {
+    pub fn new(hour: u32, minute: u32) -> Self {
+        TimeOfDay(hour * 60 + minute)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time of day {}", inp);
+        let (hour, minute) = inp.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.trim().parse::<u32>().map_err(|_| invalid())?;
+        let minute = minute.trim().parse::<u32>().map_err(|_| invalid())?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(TimeOfDay::new(hour, minute))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(inp: String) -> Result<Self, Self::Error> {
+        inp.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.0 / 60, time.0 % 60)
+    }
+}
+
+// When sounds and desktop notifications keep quiet, in local time. A start
+// later than the end runs overnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl QuietHours {
+    pub fn contains(&self, now: TimeOfDay) -> bool {
+        if self.start <= self.end {
+            self.start <= now && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        }
+    }
+}
+
+// The main window as it was when last closed, for opening the next one the
+// same way.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    // the size it had when it wasn't maximized
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub maximized: bool,
+    // the label of the tab that was open, e.g. "Raw output"
+    #[serde(default)]
+    pub tab: Option<String>,
+    // how far down the divider between errors and warnings was, in pixels
+    #[serde(default)]
+    pub split_position: Option<i32>,
+}
+
+// How diagnostics are ordered within the results list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    // the order the compiler reported them in
+    Compiler,
+    // errors before warnings, otherwise in compiler order
+    #[default]
+    Severity,
+    File,
+    // by error code, or lint name for warnings
+    Code,
+    // what likely needs fixing first: errors from earlier compilation stages,
+    // and those others seem to follow from, before the rest
+    RootCause,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Compiler => "compiler",
+            SortOrder::Severity => "severity",
+            SortOrder::File => "file",
+            SortOrder::Code => "code",
+            SortOrder::RootCause => "root-cause",
+        }
+    }
+}
+
+// Where the results list scrolls to when new results come in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScrollTo {
+    // selecting it, as "Next error" would
+    FirstError,
+    // wherever it was scrolled to before
+    #[default]
+    Keep,
+    Bottom,
+}
+
+impl ScrollTo {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScrollTo::FirstError => "first-error",
+            ScrollTo::Keep => "keep",
+            ScrollTo::Bottom => "bottom",
+        }
+    }
+}
+
+// Whether the results list is one list, or split into errors on top and
+// warnings below, each scrolling on its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResultsLayout {
+    #[default]
+    Single,
+    Split,
+}
+
+impl ResultsLayout {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResultsLayout::Single => "single",
+            ResultsLayout::Split => "split",
+        }
+    }
+}
+
+impl FromStr for ResultsLayout {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "single" => Ok(ResultsLayout::Single),
+            "split" => Ok(ResultsLayout::Split),
+            _ => Err(format!("Invalid results layout {}", inp)),
+        }
+    }
+}
+
+impl FromStr for ScrollTo {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "first-error" => Ok(ScrollTo::FirstError),
+            "keep" => Ok(ScrollTo::Keep),
+            "bottom" => Ok(ScrollTo::Bottom),
+            _ => Err(format!("Invalid scroll position {}", inp)),
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "compiler" => Ok(SortOrder::Compiler),
+            "severity" => Ok(SortOrder::Severity),
+            "file" => Ok(SortOrder::File),
+            "code" => Ok(SortOrder::Code),
+            "root-cause" => Ok(SortOrder::RootCause),
+            _ => Err(format!("Invalid sort order {}", inp)),
+        }
+    }
+}
+
+// Which kind of output a project's build command prints, and so how it is
+// read. Stored by name, with any name that isn't built in referring to one of
+// the config's problem matchers.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ParserKind {
+    // cargo and rustc
+    #[default]
+    Rustc,
+    // "file:line:col: error: message" lines from GCC, Clang and the like
+    Gcc,
+    // no diagnostics, just whether the command succeeded and what it printed
+    Passthrough,
+    Matcher(String),
+}
+
+impl ParserKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ParserKind::Rustc => "rustc",
+            ParserKind::Gcc => "gcc",
+            ParserKind::Passthrough => "passthrough",
+            ParserKind::Matcher(name) => name,
+        }
+    }
+}
+
+impl FromStr for ParserKind {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "rustc" => Ok(ParserKind::Rustc),
+            "gcc" => Ok(ParserKind::Gcc),
+            "passthrough" => Ok(ParserKind::Passthrough),
+            "" => Err("Invalid parser: no name given".to_string()),
+            _ => Ok(ParserKind::Matcher(inp.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for ParserKind {
+    type Error = String;
+
+    fn try_from(inp: String) -> Result<Self, Self::Error> {
+        inp.parse()
+    }
+}
+
+impl From<ParserKind> for String {
+    fn from(kind: ParserKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+// A user defined parser in the style of VS Code's problem matchers: every
+// line `regexp` matches is a diagnostic, its parts taken from the numbered
+// capture groups, e.g.
+//
+//   [matchers.eslint]
+//   regexp = '^(.+):(\d+):(\d+): (error|warning) (.+)$'
+//   file = 1
+//   line = 2
+//   column = 3
+//   severity = 4
+//   message = 5
+//   extensions = ["js", "ts"]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatcherConfig {
+    pub regexp: String,
+    pub file: usize,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    // lines without one are errors, as are ones that don't start with "warn"
+    pub severity: Option<usize>,
+    pub code: Option<usize>,
+    pub message: usize,
+    // the files whose changes start a build, by extension; any file if empty
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+// Settings that only apply to a single project.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    // keeps cargo off the network so a flaky connection can't stall builds;
+    // plain values have to come before the tables below in TOML
+    pub offline: bool,
+    pub parser: ParserKind,
+    // name of the selected entry in `profiles`
+    pub profile: Option<String>,
+    // only list diagnostics in files changed since `base_ref`, HEAD if unset
+    pub only_changes: bool,
+    pub base_ref: Option<String>,
+    // the rustup toolchain to build with, e.g. "nightly", instead of the one
+    // rustup would pick for the project
+    pub toolchain: Option<String>,
+    // the target triple to build for, e.g. "wasm32-unknown-unknown", instead
+    // of the host's
+    pub target: Option<String>,
+    // a script the diagnostics go through before they're listed, see
+    // `PostProcessor`, and how many seconds it gets
+    pub post_process: Option<String>,
+    pub post_process_timeout: Option<u64>,
+    // commands run after each build, see `Hook`, and how many seconds each
+    // gets
+    pub post_build: Vec<String>,
+    pub post_build_timeout: Option<u64>,
+    // how many of a build's diagnostics are kept, `cargo::MAX_DIAGNOSTICS`
+    // if unset
+    pub max_diagnostics: Option<usize>,
+    // how many seconds a build may run before it's stopped, for commands
+    // that hang; unset for no limit
+    pub build_timeout: Option<u64>,
+    // an image to build in instead of on the host, see `ContainerRunner`,
+    // and what runs it, `container::DEFAULT_ENGINE` if unset
+    pub container_image: Option<String>,
+    pub container_engine: Option<String>,
+    // whether each build is appended to the project's `RunLog`
+    pub log_runs: bool,
+    // whether changes to files the project's `.gitignore` and `.ignore`
+    // files leave out start builds anyway
+    pub watch_ignored: bool,
+    // what it takes for a build to pass, see `SuccessPolicy`: no warnings,
+    // and codes that don't count against it, along with the ignored ones
+    pub warnings_fail: bool,
+    pub tolerated: Vec<String>,
+    // whether what the project's own config file runs is let through, unset
+    // until the user is asked
+    pub trust_local_config: Option<bool>,
+    // the page to file issues about diagnostics on, with {repository},
+    // {title} and {body} filled in, when it isn't GitHub's or GitLab's
+    pub issue_url: Option<String>,
+    // the page showing a line of the repository, with {repository},
+    // {commit}, {path} and {line} filled in, when it isn't GitHub's or
+    // GitLab's
+    pub permalink_url: Option<String>,
+    // error codes and lints whose diagnostics are left out, e.g. "E0601" or
+    // "dead_code" while scaffolding
+    pub ignored: Vec<String>,
+    // extra variables for the compile command, e.g. RUSTFLAGS
+    pub env: BTreeMap<String, String>,
+    pub jobs: Vec<ScheduledJob>,
+    // empty until one of the default profiles is changed
+    pub profiles: Vec<Profile>,
+}
+
+// Settings a project keeps in `LOCAL_CONFIG` at its root, for everyone
+// working on it, e.g.
+//
+//   command = "cargo clippy --all-targets"
+//   post_process = "scripts/triage.py"
+//   post_build = ["scripts/led.sh"]
+//   ignored = ["dead_code"]
+//
+//   [env]
+//   RUSTFLAGS = "-D warnings"
+//
+// Anyone who can commit to a repository can write one, so the command, the
+// post-processor, the post-build hooks, the variables (which can name a
+// program, like RUSTC_WRAPPER) and scheduled jobs only take effect once the user trusts
+// the project.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalConfig {
+    pub command: Option<String>,
+    pub post_process: Option<String>,
+    pub post_build: Vec<String>,
+    pub ignored: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub jobs: Vec<ScheduledJob>,
+}
+
+impl LocalConfig {
+    // `None` if the project has no config of its own.
+    pub fn load<P: AsRef<Path>>(project_root: P) -> Result<Option<Self>, String> {
+        let path = project_root.as_ref().join(LOCAL_CONFIG);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path).map_err(|e| format!("{:?}", e))?;
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // whether any of it needs the user's say-so
+    pub fn runs_commands(&self) -> bool {
+        self.command.is_some()
+            || self.post_process.is_some()
+            || !self.post_build.is_empty()
+            || !self.env.is_empty()
+            || !self.jobs.is_empty()
+    }
+
+    // The command to watch `project` with, once it's trusted.
+    pub fn command_for(&self, project: &ProjectConfig) -> Option<&str> {
+        self.command
+            .as_deref()
+            .filter(|_| project.trust_local_config == Some(true))
+    }
+}
+
+// A named command to watch a project with, quicker to switch to than
+// retyping it, e.g.
+//
+//   [[projects."/src/foo".profiles]]
+//   name = "Clippy"
+//   command = "cargo clippy --all-targets"
+//   ignored = ["clippy::too_many_arguments"]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub command: String,
+    // left out on top of the project's own ignored codes
+    #[serde(default)]
+    pub ignored: Vec<String>,
+}
+
+impl Profile {
+    pub fn new(name: &str, command: &str) -> Self {
+        Profile {
+            name: name.to_string(),
+            command: command.to_string(),
+            ignored: vec![],
+        }
+    }
+}
+
+// A command run on a timer rather than on file changes, e.g.
+//
+//   [[projects."/src/foo".jobs]]
+//   name = "clippy"
+//   command = "cargo clippy --all-targets --all-features"
+//   every_minutes = 60
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub command: String,
+    pub every_minutes: u64,
+}
+
+impl ScheduledJob {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.every_minutes.max(1) * 60)
+    }
+}
+
+impl ProjectConfig {
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        let mut env: Vec<_> = self
+            .env
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if self.offline {
+            env.push((OFFLINE_VAR.to_string(), "true".to_string()));
+        }
+        if let Some(toolchain) = self.toolchain.as_ref() {
+            env.push((toolchain::TOOLCHAIN_VAR.to_string(), toolchain.clone()));
+        }
+        if let Some(target) = self.target.as_ref() {
+            env.push((toolchain::TARGET_VAR.to_string(), target.clone()));
+        }
+        env
+    }
+
+    pub fn base_ref(&self) -> &str {
+        self.base_ref.as_deref().unwrap_or(git::DEFAULT_BASE)
+    }
+
+    // The profiles set up for the project, or a few common ones if there are
+    // none yet.
+    pub fn profiles(&self) -> Vec<Profile> {
+        if !self.profiles.is_empty() {
+            return self.profiles.clone();
+        }
+        DEFAULT_PROFILES
+            .iter()
+            .map(|(name, command)| Profile::new(name, command))
+            .collect()
+    }
+
+    pub fn current_profile(&self) -> Option<Profile> {
+        let name = self.profile.as_ref()?;
+        self.profiles().into_iter().find(|p| &p.name == name)
+    }
+
+    // Selects the profile called `name`, first keeping `command` as the
+    // command of the one selected until now so edits to it aren't lost.
+    // Returns the command to run for the new profile, or `None` if there's no
+    // such profile.
+    pub fn switch_profile(&mut self, name: &str, command: &str) -> Option<String> {
+        let mut profiles = self.profiles();
+        let next = profiles.iter().find(|p| p.name == name)?.command.clone();
+
+        if let Some(current) = self.profile.as_ref() {
+            let command = command.trim();
+            let edited = profiles
+                .iter_mut()
+                .find(|p| &p.name == current && p.command != command && !command.is_empty());
+            if let Some(profile) = edited {
+                profile.command = command.to_string();
+                self.profiles = profiles;
+            }
+        }
+        self.profile = Some(name.to_string());
+
+        Some(next)
+    }
+
+    // Everything left out of the results, the project's ignored codes along
+    // with those of the selected profile.
+    // These settings with what the project's own config file adds to them,
+    // leaving out what runs commands unless the project is trusted. Ours
+    // win where both set a variable.
+    pub fn with_local(&self, local: Option<&LocalConfig>) -> ProjectConfig {
+        let mut project = self.clone();
+        let local = match local {
+            Some(local) => local,
+            None => return project,
+        };
+        for code in &local.ignored {
+            if !project.ignored.contains(code) {
+                project.ignored.push(code.clone());
+            }
+        }
+        if self.trust_local_config == Some(true) {
+            for (name, value) in &local.env {
+                project
+                    .env
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            project.jobs.extend(local.jobs.iter().cloned());
+            if project.post_process.is_none() {
+                project.post_process = local.post_process.clone();
+            }
+            project.post_build.extend(local.post_build.iter().cloned());
+        }
+        project
+    }
+
+    pub fn post_processor(&self) -> Option<PostProcessor> {
+        let command = self
+            .post_process
+            .as_deref()
+            .filter(|c| !c.trim().is_empty())?;
+        let mut post_processor = PostProcessor::new(command);
+        if let Some(secs) = self.post_process_timeout {
+            post_processor.timeout = Duration::from_secs(secs.max(1));
+        }
+        Some(post_processor)
+    }
+
+    pub fn post_build_hooks(&self) -> Vec<Hook> {
+        let timeout = self
+            .post_build_timeout
+            .map(|secs| Duration::from_secs(secs.max(1)))
+            .unwrap_or(hooks::TIMEOUT);
+        self.post_build
+            .iter()
+            .filter(|command| !command.trim().is_empty())
+            .map(|command| Hook {
+                timeout,
+                ..Hook::new(command)
+            })
+            .collect()
+    }
+
+    // What builds and jobs run with: the host, or a container from the
+    // project's image.
+    pub fn runner<P: AsRef<Path>>(&self, project_root: P) -> Arc<dyn ProcessRunner> {
+        match self.container(project_root) {
+            Some(container) => Arc::new(container),
+            None => Arc::new(SystemRunner),
+        }
+    }
+
+    pub fn container<P: AsRef<Path>>(&self, project_root: P) -> Option<ContainerRunner> {
+        let image = self
+            .container_image
+            .as_deref()
+            .filter(|i| !i.trim().is_empty())?;
+        let engine = self
+            .container_engine
+            .as_deref()
+            .unwrap_or(container::DEFAULT_ENGINE);
+        Some(ContainerRunner::new(engine, image, project_root))
+    }
+
+    // None unless the project asks for more than a successful exit.
+    pub fn success_policy(&self) -> Option<SuccessPolicy> {
+        if !self.warnings_fail && self.tolerated.is_empty() {
+            return None;
+        }
+        let mut tolerated = self.tolerated.clone();
+        for code in self.ignored_codes() {
+            if !tolerated.contains(&code) {
+                tolerated.push(code);
+            }
+        }
+        Some(SuccessPolicy {
+            warnings_fail: self.warnings_fail,
+            tolerated,
+        })
+    }
+
+    pub fn max_diagnostics(&self) -> usize {
+        self.max_diagnostics
+            .unwrap_or(cargo::MAX_DIAGNOSTICS)
+            .max(1)
+    }
+
+    pub fn build_timeout(&self) -> Option<Duration> {
+        self.build_timeout
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    pub fn ignored_codes(&self) -> Vec<String> {
+        let mut ignored = self.ignored.clone();
+        if let Some(profile) = self.current_profile() {
+            for code in profile.ignored {
+                if !ignored.contains(&code) {
+                    ignored.push(code);
+                }
+            }
+        }
+        ignored
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub theme: Theme,
+    // editor command for quickfix lists, `{}` standing in for the file
+    pub quickfix_command: Option<String>,
+    // editor command for opening a single diagnostic where it is, see
+    // `quickfix::fill`; without one it opens as a quickfix list of its file
+    pub open_command: Option<String>,
+    // for opening a terminal at the project, `{dir}` standing in for it
+    pub terminal_command: Option<String>,
+    // keep diagnostics that cargo reports once per crate instead of folding them
+    pub show_duplicates: bool,
+    // nest diagnostics under a collapsible header per source file
+    pub group_by_file: bool,
+    // one expandable group per error code or lint instead, showing one of
+    // its diagnostics and where the others are
+    pub group_by_code: bool,
+    // leave out the diagnostics a project's baseline already had
+    pub hide_baseline: bool,
+    // highlight what changed since the build before the latest
+    pub show_changes: bool,
+    // only list the file edited last, with everything else folded away
+    pub focus_file: bool,
+    // whether the health check that runs on first launch has run
+    pub health_checked: bool,
+    // whether a watch has started once, after which windows no longer open
+    // on the welcome screen
+    pub onboarded: bool,
+    pub sort_order: SortOrder,
+    pub scroll_to: ScrollTo,
+    pub results_layout: ResultsLayout,
+    pub alert: Alert,
+    // where to serve the latest results for dashboards and other tools, e.g.
+    // "127.0.0.1:7878"; off unless set
+    pub serve: Option<String>,
+    // the web pages that may read the served results, e.g.
+    // "http://localhost:3000"; none unless set
+    pub serve_origins: Vec<String>,
+    // where editors can connect for diagnostics as a language server would
+    // publish them, e.g. "127.0.0.1:7879"; off unless set
+    pub lsp: Option<String>,
+    // take `in.nerdworks.WatchRustErrors` on the session bus, for scripts
+    // and status bars to drive the watch and hear about builds
+    pub dbus: bool,
+    // how many builds may run at once across every window; the rest wait
+    // their turn in the build queue; unset for no limit
+    pub max_parallel_builds: Option<usize>,
+    // how builds run next to everything else on the machine
+    pub build_priority: Priority,
+    // changes wait to build while the window is in the background, or
+    // while the machine runs on battery; "Run now" still builds
+    pub build_only_when_focused: bool,
+    pub build_only_on_ac_power: bool,
+    // closing a window that's watching minimizes it instead, for the watch
+    // to go on in the background
+    pub minimize_on_close: bool,
+    // a file to append every build event to as a line of JSON, to look
+    // into or replay a session later; off unless set
+    pub event_log: Option<String>,
+    // the commands watched with, in any project, the latest first
+    pub command_history: Vec<String>,
+    // e.g. start = "22:00" and end = "07:00"; results still come in as usual
+    pub quiet_hours: Option<QuietHours>,
+    // an editor to open diagnostics in when there's no `open_command`
+    pub editor: Option<EditorPreset>,
+    // where to send word of failed builds and the like
+    pub notifications: Vec<SinkConfig>,
+    // when a build took so much longer than usual that it's called out
+    pub slow_builds: SlowBuilds,
+    // restored when a window opens; remembered whenever one closes
+    pub window: Option<WindowState>,
+    // keyed by project root
+    pub projects: BTreeMap<String, ProjectConfig>,
+    // problem matchers by the name projects use for them
+    pub matchers: BTreeMap<String, MatcherConfig>,
+}
+
+impl Config {
+    // e.g. ~/.config/watch-rust-errors/config.toml
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_DIR).join(CONFIG_FILE))
+    }
+
+    // Loads the user's config, falling back to defaults if there is none or
+    // it cannot be read.
+    pub fn load() -> Self {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path).unwrap_or_else(|err| {
+                eprintln!("Ignoring invalid config {}: {}", path.display(), err);
+                Config::default()
+            }),
+            _ => Config::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or_else(|| "No config directory available.".to_string())?;
+        self.save_to(path)
+    }
+
+    pub fn quickfix_command(&self) -> &str {
+        self.quickfix_command
+            .as_deref()
+            .unwrap_or(quickfix::DEFAULT_COMMAND)
+    }
+
+    pub fn terminal_command(&self) -> &str {
+        self.terminal_command
+            .as_deref()
+            .unwrap_or(quickfix::DEFAULT_TERMINAL)
+    }
+
+    // The command that opens a diagnostic where it is, if any, spelled out
+    // or from the editor preset.
+    pub fn open_command(&self, runner: &dyn ProcessRunner) -> Option<Result<String, String>> {
+        match (self.open_command.as_ref(), self.editor.as_ref()) {
+            (Some(command), _) => Some(Ok(command.clone())),
+            (None, Some(editor)) => Some(editor.open_command(runner)),
+            (None, None) => None,
+        }
+    }
+
+    // The configured sinks, or without any, the desktop notifications for
+    // scheduled jobs there always were.
+    pub fn notification_sinks(&self) -> Vec<SinkConfig> {
+        if self.notifications.is_empty() {
+            vec![SinkConfig {
+                kind: SinkKind::Desktop,
+                events: vec![NotifyEvent::ScheduledFindings],
+            }]
+        } else {
+            self.notifications.clone()
+        }
+    }
+
+    // Puts `command` at the top of the history, moving it there if it was
+    // already in it further down.
+    pub fn remember_command(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        self.command_history.retain(|c| c != command);
+        self.command_history.insert(0, command.to_string());
+        self.command_history.truncate(MAX_HISTORY);
+    }
+
+    pub fn project(&self, project_root: &str) -> ProjectConfig {
+        self.projects.get(project_root).cloned().unwrap_or_default()
+    }
+
+    // Stores `project`, dropping the entry altogether once it holds nothing
+    // but defaults.
+    pub fn set_project(&mut self, project_root: &str, project: ProjectConfig) {
+        if project == ProjectConfig::default() {
+            self.projects.remove(project_root);
+        } else {
+            self.projects.insert(project_root.to_string(), project);
+        }
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{:?}", e))?;
+        let config: Config = toml::from_str(&contents).map_err(|e| format!("{}", e))?;
+        config.check_matchers()?;
+        Ok(config)
+    }
+
+    // Compiles every problem matcher so that mistakes show up when the config
+    // is loaded rather than once a build is read, failing on the first that
+    // is invalid or on projects that use one that doesn't exist.
+    pub fn check_matchers(&self) -> Result<(), String> {
+        for (name, matcher) in &self.matchers {
+            if !matches!(name.parse(), Ok(ParserKind::Matcher(_))) {
+                return Err(format!(
+                    "Matcher {} has the name of a built-in parser",
+                    name
+                ));
+            }
+            ProblemMatcher::new(matcher).map_err(|e| format!("Invalid matcher {}: {}", name, e))?;
+        }
+        for (project_root, project) in &self.projects {
+            if let ParserKind::Matcher(name) = &project.parser {
+                if !self.matchers.contains_key(name) {
+                    return Err(format!(
+                        "{} uses matcher {}, which is not defined",
+                        project_root, name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parser(&self, kind: &ParserKind) -> Result<Arc<dyn Parser>, String> {
+        match kind {
+            ParserKind::Rustc => Ok(Arc::new(RustcParser)),
+            ParserKind::Gcc => Ok(Arc::new(GccParser)),
+            ParserKind::Passthrough => Ok(Arc::new(PassthroughParser)),
+            ParserKind::Matcher(name) => match self.matchers.get(name) {
+                Some(matcher) => Ok(Arc::new(ProblemMatcher::new(matcher)?)),
+                None => Err(format!("No matcher named {}", name)),
+            },
+        }
+    }
+
+    // The settings at the top of the config file at `path`, e.g. "theme" or
+    // "projects", sorted by name.
+    pub fn sections<P: AsRef<Path>>(path: P) -> Result<Vec<String>, String> {
+        Ok(read_table(path.as_ref())?.keys().cloned().collect())
+    }
+
+    // Takes `section` out of the config file at `path`, so that it's back to
+    // its defaults, keeping the file as it was next to it with ".bak" added.
+    // It needn't be a valid config, since a broken one is what this is for.
+    pub fn reset_section<P: AsRef<Path>>(path: P, section: &str) -> Result<PathBuf, String> {
+        let path = path.as_ref();
+        let mut table = read_table(path)?;
+        if table.remove(section).is_none() {
+            return Err(format!("{} has no {} settings", path.display(), section));
+        }
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        fs::copy(path, &backup).map_err(|e| format!("{:?}", e))?;
+        let contents = toml::to_string_pretty(&table).map_err(|e| format!("{}", e))?;
+        fs::write(path, contents).map_err(|e| format!("{:?}", e))?;
+        Ok(backup)
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{:?}", e))?;
+        }
+
+        let contents = toml::to_string_pretty(self).map_err(|e| format!("{}", e))?;
+        fs::write(path, contents).map_err(|e| format!("{:?}", e))
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::value::Table, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{:?}", e))?;
+    contents
+        .parse::<toml::Value>()
+        .map_err(|e| format!("{}", e))?
+        .as_table()
+        .cloned()
+        .ok_or_else(|| format!("{} is not a table", path.display()))
+}
+
+// The command Up (`older`) or Down leads to from `current` in `history`:
+// Up from anything not in it goes to the latest, and Down from the latest
+// goes nowhere.
+pub fn recall_command<'a>(history: &'a [String], current: &str, older: bool) -> Option<&'a str> {
+    let at = history.iter().position(|c| c == current.trim());
+    let next = match (at, older) {
+        (None, true) => 0,
+        (None, false) | (Some(0), false) => return None,
+        (Some(at), true) => at + 1,
+        (Some(at), false) => at - 1,
+    };
+    history.get(next).map(String::as_str)
+}
+
+// Parses "KEY=value" lines as typed into the environment editor. Blank lines
+// and lines starting with '#' are skipped.
+pub fn parse_env(inp: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut env = BTreeMap::new();
+    for line in inp.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.find('=') {
+            Some(index) => (line[..index].trim(), line[index + 1..].trim_start()),
+            None => return Err(format!("Expected KEY=value but found \"{}\"", line)),
+        };
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!("Invalid variable name \"{}\"", key));
+        }
+        env.insert(key.to_string(), value.to_string());
+    }
+
+    Ok(env)
+}
+
+pub fn format_env(env: &BTreeMap<String, String>) -> String {
+    env.iter().map(|(k, v)| format!("{}={}\n", k, v)).collect()
+}
+
+// Parses the ignored codes and lints as typed into their editor, one or more
+// per line separated by spaces or commas. Lines starting with '#' are skipped.
+pub fn parse_ignored(inp: &str) -> Vec<String> {
+    let mut ignored: Vec<String> = vec![];
+    for line in inp.lines().map(str::trim).filter(|l| !l.starts_with('#')) {
+        for code in line.split(|c: char| c == ',' || c.is_whitespace()) {
+            if !code.is_empty() && !ignored.iter().any(|i| i == code) {
+                ignored.push(code.to_string());
+            }
+        }
+    }
+    ignored
+}
+
+pub fn format_ignored(ignored: &[String]) -> String {
+    ignored.iter().map(|code| format!("{}\n", code)).collect()
+}
@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::process::{self, ProcessOutput, ProcessRunner, Stop, SystemRunner};
+
+// where the project is mounted inside the container
+pub const MOUNT: &str = "/src";
+
+// what runs the container unless the project picks e.g. "podman"
+pub const DEFAULT_ENGINE: &str = "docker";
+
+// tells apart the containers this process names
+static CONTAINERS: AtomicUsize = AtomicUsize::new(0);
+
+// Runs commands inside a container from `image` instead of on the host, for
+// builds that only work in e.g. CI's image. The project is bind-mounted at
+// `MOUNT` and the command runs where it would have on the host:
+//
+//   docker run --rm -v {root}:/src -w /src {image} cargo check
+//
+// Each command gets a container of its own, removed once it exits. Ones that
+// can be stopped, on a timeout, are named so that they can be killed.
+#[derive(Clone)]
+pub struct ContainerRunner {
+    pub engine: String,
+    pub image: String,
+    pub project_root: PathBuf,
+    host: Arc<dyn ProcessRunner>,
+}
+
+impl ContainerRunner {
+    pub fn new<P: AsRef<Path>>(engine: &str, image: &str, project_root: P) -> Self {
+        Self::with_host(engine, image, project_root, Arc::new(SystemRunner))
+    }
+
+    // `host` runs the engine itself.
+    pub fn with_host<P: AsRef<Path>>(
+        engine: &str,
+        image: &str,
+        project_root: P,
+        host: Arc<dyn ProcessRunner>,
+    ) -> Self {
+        ContainerRunner {
+            engine: engine.to_string(),
+            image: image.to_string(),
+            project_root: project_root.as_ref().to_path_buf(),
+            host,
+        }
+    }
+
+    // What the engine is run with for `program` to run in `cwd`. Directories
+    // outside the project, which aren't mounted, run at its root.
+    pub fn args(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Vec<String> {
+        self.words(None, program, args, env, cwd)
+    }
+
+    // Like `args`, for a container called `name`, which `kill` can stop.
+    pub fn named_args(
+        &self,
+        name: &str,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Vec<String> {
+        self.words(Some(name), program, args, env, cwd)
+    }
+
+    fn words(
+        &self,
+        name: Option<&str>,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Vec<String> {
+        let workdir = match cwd.strip_prefix(&self.project_root) {
+            Ok(rel) if rel.as_os_str().is_empty() => MOUNT.to_string(),
+            Ok(rel) => format!("{}/{}", MOUNT, rel.to_string_lossy()),
+            Err(_) => MOUNT.to_string(),
+        };
+        let mut words = vec!["run".to_string(), "--rm".to_string()];
+        if let Some(name) = name {
+            words.push("--name".to_string());
+            words.push(name.to_string());
+        }
+        words.extend(vec![
+            "-v".to_string(),
+            format!("{}:{}", self.project_root.display(), MOUNT),
+            "-w".to_string(),
+            workdir,
+        ]);
+        // only the names: the engine passes the values on from its own
+        // environment, which, unlike its arguments, `ps` doesn't show
+        for (name, _) in env {
+            words.push("-e".to_string());
+            words.push(name.clone());
+        }
+        words.push(self.image.clone());
+        words.push(program.to_string());
+        words.extend(args.iter().map(ToString::to_string));
+        words
+    }
+
+    // Stops the container called `name`, and the build in it. Stopping the
+    // engine's client isn't enough: the container carries on until what
+    // it runs exits, which a hung build never does.
+    pub fn kill(&self, name: &str) {
+        let _ = self
+            .host
+            .run(&self.engine, &["kill", name], &[], &self.project_root);
+    }
+}
+
+// e.g. "wre-1234-0"
+fn container_name() -> String {
+    format!(
+        "wre-{}-{}",
+        std::process::id(),
+        CONTAINERS.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+impl ProcessRunner for ContainerRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        let words = self.args(program, args, env, cwd);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host.run(&self.engine, &words, env, &self.project_root)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let words = self.args(program, args, env, cwd);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host
+            .run_streaming(&self.engine, &words, env, &self.project_root, on_line)
+    }
+
+    // A container that's still going when its client is stopped is killed,
+    // and then removed by `--rm`.
+    fn run_streaming_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let name = container_name();
+        let words = self.named_args(&name, program, args, env, cwd);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        let output = self.host.run_streaming_with_timeout(
+            &self.engine,
+            &words,
+            env,
+            &self.project_root,
+            timeout,
+            on_line,
+        );
+        if let Err(err) = &output {
+            if process::is_timed_out(err) {
+                self.kill(&name);
+            }
+        }
+        output
+    }
+
+    // Like `run_streaming_with_timeout`, for builds that can be cancelled
+    // too.
+    fn run_streaming_until(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        stop: &Stop,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        if stop.timeout.is_none() && stop.cancel.is_none() {
+            return self.run_streaming(program, args, env, cwd, on_line);
+        }
+        let name = container_name();
+        let words = self.named_args(&name, program, args, env, cwd);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        let output = self.host.run_streaming_until(
+            &self.engine,
+            &words,
+            env,
+            &self.project_root,
+            stop,
+            on_line,
+        );
+        if let Err(err) = &output {
+            if process::is_timed_out(err) || process::is_cancelled(err) {
+                self.kill(&name);
+            }
+        }
+        output
+    }
+}
@@ -0,0 +1,799 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+
+use crate::cargo::CompileResult;
+
+// the name scripts and status bars call the app by on the session bus
+pub const BUS_NAME: &str = "in.nerdworks.WatchRustErrors";
+pub const OBJECT_PATH: &str = "/in/nerdworks/WatchRustErrors";
+pub const INTERFACE: &str = "in.nerdworks.WatchRustErrors";
+
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+const BUS_INTERFACE: &str = "org.freedesktop.DBus";
+
+const UNKNOWN_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+const UNKNOWN_OBJECT: &str = "org.freedesktop.DBus.Error.UnknownObject";
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+
+// RequestName's flag to fail rather than wait for the name, and its replies
+// when the name is ours
+const DO_NOT_QUEUE: u32 = 4;
+const PRIMARY_OWNER: u32 = 1;
+const ALREADY_OWNER: u32 = 4;
+
+// a caller that doesn't want to hear back
+const NO_REPLY_EXPECTED: u8 = 1;
+
+// the most the spec lets a message carry
+const MAX_MESSAGE_LEN: usize = 1 << 27;
+
+const INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="in.nerdworks.WatchRustErrors">
+    <method name="StartWatching">
+      <arg name="path" type="s" direction="in"/>
+      <arg name="command" type="s" direction="in"/>
+    </method>
+    <method name="StopWatching"/>
+    <method name="TriggerRun"/>
+    <method name="GetStatus">
+      <arg name="watching" type="b" direction="out"/>
+      <arg name="path" type="s" direction="out"/>
+      <arg name="success" type="b" direction="out"/>
+      <arg name="errors" type="u" direction="out"/>
+      <arg name="warnings" type="u" direction="out"/>
+    </method>
+    <signal name="BuildFinished">
+      <arg name="success" type="b"/>
+      <arg name="errors" type="u"/>
+      <arg name="warnings" type="u"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml" type="s" direction="out"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Peer">
+    <method name="Ping"/>
+  </interface>
+</node>
+"#;
+
+// What a script asked the app to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Call {
+    // an empty path or command keeps the window's own
+    StartWatching {
+        project_root: String,
+        command: String,
+    },
+    StopWatching,
+    TriggerRun,
+}
+
+// What `GetStatus` answers with. `success` stays false until a build has
+// finished.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    pub project_root: Option<String>,
+    pub success: bool,
+    pub errors: u32,
+    pub warnings: u32,
+}
+
+// A window's service on the session bus, for window managers, status bars
+// and scripts: methods to start, stop and run its watch, and a signal with
+// every build's outcome. Only one window or instance can own the name at a
+// time. Clones share the same connection, which closes, giving up the name,
+// once the last one is dropped.
+#[derive(Clone)]
+pub struct DbusService {
+    shared: Arc<Shared>,
+}
+
+type CallListener = Box<dyn Fn(Call) + Send>;
+
+struct Shared {
+    writer: Mutex<UnixStream>,
+    serial: AtomicU32,
+    status: Mutex<Status>,
+    // told about every call, on the thread reading from the bus
+    listeners: Mutex<Vec<CallListener>>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // ends the reading thread's copy of the connection too
+        if let Ok(writer) = self.writer.lock() {
+            let _ = writer.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl DbusService {
+    // Takes `BUS_NAME` on the session bus.
+    pub fn start() -> Result<Self, String> {
+        let address = std::env::var("DBUS_SESSION_BUS_ADDRESS")
+            .map_err(|_| "There's no session bus (DBUS_SESSION_BUS_ADDRESS isn't set).")?;
+        DbusService::connect(&address)
+    }
+
+    // Takes `BUS_NAME` on the bus at `address`, e.g.
+    // "unix:path=/run/user/1000/bus".
+    pub fn connect(address: &str) -> Result<Self, String> {
+        let mut stream = open(address)?;
+        authenticate(&mut stream)?;
+
+        let serial = AtomicU32::new(1);
+        call_bus(&mut stream, &serial, "Hello", vec![])?;
+        let reply = call_bus(
+            &mut stream,
+            &serial,
+            "RequestName",
+            vec![Value::Str(BUS_NAME.to_string()), Value::U32(DO_NOT_QUEUE)],
+        )?;
+        match reply.body.first() {
+            Some(Value::U32(PRIMARY_OWNER)) | Some(Value::U32(ALREADY_OWNER)) => {}
+            _ => {
+                return Err(format!(
+                    "{} is taken, by another window or instance.",
+                    BUS_NAME
+                ))
+            }
+        }
+
+        let mut reader = stream.try_clone().map_err(|e| format!("{:?}", e))?;
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(stream),
+            serial,
+            status: Mutex::new(Status::default()),
+            listeners: Mutex::new(vec![]),
+        });
+
+        let serving = Arc::downgrade(&shared);
+        thread::Builder::new()
+            .name("dbus".to_string())
+            .spawn(move || serve(&mut reader, &serving))
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(DbusService { shared })
+    }
+
+    // Calls `listener` with every call scripts make, on the thread reading
+    // from the bus.
+    pub fn on_call<F: Fn(Call) + Send + 'static>(&self, listener: F) {
+        self.shared
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(listener));
+    }
+
+    pub fn status(&self) -> Status {
+        self.shared.status.lock().unwrap().clone()
+    }
+
+    // What's being watched, if anything. A new watch starts out without
+    // results.
+    pub fn set_watching(&self, project_root: Option<&str>) {
+        let mut status = self.shared.status.lock().unwrap();
+        if status.project_root.as_deref() != project_root {
+            *status = Status {
+                project_root: project_root.map(ToString::to_string),
+                ..Status::default()
+            };
+        }
+    }
+
+    // Keeps `result` for `GetStatus` and sends `BuildFinished` with it.
+    pub fn publish(&self, result: &CompileResult) {
+        let (success, errors, warnings) = {
+            let mut status = self.shared.status.lock().unwrap();
+            status.success = result.success;
+            status.errors = result.errors.len() as u32;
+            status.warnings = result.warnings.len() as u32;
+            (status.success, status.errors, status.warnings)
+        };
+        let signal = Message::signal(
+            OBJECT_PATH,
+            INTERFACE,
+            "BuildFinished",
+            vec![
+                Value::Bool(success),
+                Value::U32(errors),
+                Value::U32(warnings),
+            ],
+        );
+        if let Err(err) = self.shared.send(signal) {
+            eprintln!("Could not send BuildFinished: {}", err);
+        }
+    }
+}
+
+impl Shared {
+    fn send(&self, mut message: Message) -> Result<(), String> {
+        message.serial = self.serial.fetch_add(1, Ordering::SeqCst);
+        self.writer
+            .lock()
+            .unwrap()
+            .write_all(&message.encode())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    fn answer(&self, call: &Message) -> Result<Vec<Value>, (&'static str, String)> {
+        let member = call.member.as_deref().unwrap_or_default();
+        if call.path.as_deref() != Some(OBJECT_PATH) {
+            return Err((
+                UNKNOWN_OBJECT,
+                format!("There's nothing at {}.", call.path.as_deref().unwrap_or("")),
+            ));
+        }
+        // callers may leave the interface out
+        let interface = call.interface.as_deref();
+        let ours = interface.is_none() || interface == Some(INTERFACE);
+
+        let request = match member {
+            "Introspect" if ours || interface == Some("org.freedesktop.DBus.Introspectable") => {
+                return Ok(vec![Value::Str(INTROSPECTION.to_string())]);
+            }
+            "Ping" if ours || interface == Some("org.freedesktop.DBus.Peer") => return Ok(vec![]),
+            "GetStatus" if ours => {
+                let status = self.status.lock().unwrap().clone();
+                return Ok(vec![
+                    Value::Bool(status.project_root.is_some()),
+                    Value::Str(status.project_root.unwrap_or_default()),
+                    Value::Bool(status.success),
+                    Value::U32(status.errors),
+                    Value::U32(status.warnings),
+                ]);
+            }
+            "StartWatching" if ours => match call.body.as_slice() {
+                [Value::Str(project_root), Value::Str(command)] => Call::StartWatching {
+                    project_root: project_root.clone(),
+                    command: command.clone(),
+                },
+                _ => {
+                    return Err((
+                        INVALID_ARGS,
+                        "StartWatching takes a path and a command.".to_string(),
+                    ))
+                }
+            },
+            "StopWatching" if ours => Call::StopWatching,
+            "TriggerRun" if ours => Call::TriggerRun,
+            _ => {
+                return Err((
+                    UNKNOWN_METHOD,
+                    format!("There's no method {} on {}.", member, OBJECT_PATH),
+                ))
+            }
+        };
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(request.clone());
+        }
+        Ok(vec![])
+    }
+}
+
+// Answers method calls until the bus or the service goes away.
+fn serve(reader: &mut UnixStream, shared: &Weak<Shared>) {
+    loop {
+        let message = match read_raw(reader) {
+            Ok(buf) => Message::decode(&buf),
+            Err(_) => return,
+        };
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+        // e.g. calls with arguments of other types, which are left unanswered
+        let message = match message {
+            Ok(message) if message.type_ == MessageType::MethodCall => message,
+            _ => continue,
+        };
+
+        let answer = shared.answer(&message);
+        if message.flags & NO_REPLY_EXPECTED != 0 {
+            continue;
+        }
+        let reply = match answer {
+            Ok(body) => Message::reply_to(&message, body),
+            Err((name, text)) => Message::error_to(&message, name, &text),
+        };
+        if let Err(err) = shared.send(reply) {
+            eprintln!("Could not answer a D-Bus call: {}", err);
+        }
+    }
+}
+
+// Connects to the first of the `;` separated addresses that takes it.
+fn open(address: &str) -> Result<UnixStream, String> {
+    let mut last_err = format!("No usable bus address in {}", address);
+    for entry in address.split(';') {
+        let (transport, params) = entry.split_once(':').unwrap_or((entry, ""));
+        if transport != "unix" {
+            continue;
+        }
+        for param in params.split(',') {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let connected = match key {
+                "path" => UnixStream::connect(unescape(value)),
+                "abstract" => connect_abstract(&unescape(value)),
+                _ => continue,
+            };
+            match connected {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = format!("{:?}", err),
+            }
+        }
+    }
+    Err(last_err)
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &str) -> std::io::Result<UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    UnixStream::connect_addr(&SocketAddr::from_abstract_name(name)?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn connect_abstract(_name: &str) -> std::io::Result<UnixStream> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+// Address values escape bytes as %xx.
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// Proves who we are by the uid the bus sees on the socket.
+fn authenticate(stream: &mut UnixStream) -> Result<(), String> {
+    let uid = fs::metadata("/proc/self")
+        .map(|meta| meta.uid())
+        .map_err(|e| format!("{:?}", e))?;
+    let uid = uid
+        .to_string()
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    write!(stream, "\0AUTH EXTERNAL {}\r\n", uid).map_err(|e| format!("{:?}", e))?;
+
+    // a byte at a time, so that nothing after the line is read along
+    let mut line = vec![];
+    let mut byte = [0; 1];
+    while !line.ends_with(b"\r\n") {
+        stream
+            .read_exact(&mut byte)
+            .map_err(|e| format!("{:?}", e))?;
+        line.push(byte[0]);
+    }
+    let line = String::from_utf8_lossy(&line);
+    if !line.starts_with("OK ") {
+        return Err(format!("The bus refused us: {}", line.trim()));
+    }
+    stream
+        .write_all(b"BEGIN\r\n")
+        .map_err(|e| format!("{:?}", e))
+}
+
+// Calls one of the bus's own methods, waiting for its answer.
+fn call_bus(
+    stream: &mut UnixStream,
+    serial: &AtomicU32,
+    member: &str,
+    body: Vec<Value>,
+) -> Result<Message, String> {
+    let mut call = Message::call(BUS_INTERFACE, BUS_PATH, BUS_INTERFACE, member, body);
+    call.serial = serial.fetch_add(1, Ordering::SeqCst);
+    stream
+        .write_all(&call.encode())
+        .map_err(|e| format!("{:?}", e))?;
+
+    loop {
+        let message = read_message(stream)?;
+        if message.reply_serial != Some(call.serial) {
+            continue;
+        }
+        return match message.type_ {
+            MessageType::Error => Err(format!(
+                "{} failed: {}",
+                member,
+                message
+                    .body
+                    .first()
+                    .and_then(Value::as_str)
+                    .or(message.error_name.as_deref())
+                    .unwrap_or_default()
+            )),
+            _ => Ok(message),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+// The few kinds of values the interface passes around.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    U32(u32),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+}
+
+impl Value {
+    fn signature(&self) -> char {
+        match self {
+            Value::Bool(_) => 'b',
+            Value::U32(_) => 'u',
+            Value::Str(_) => 's',
+            Value::ObjectPath(_) => 'o',
+            Value::Signature(_) => 'g',
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) | Value::ObjectPath(s) | Value::Signature(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+// A D-Bus message, with the header fields it can have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub type_: MessageType,
+    pub flags: u8,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(type_: MessageType) -> Self {
+        Message {
+            type_,
+            flags: 0,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: vec![],
+        }
+    }
+
+    pub fn call(
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Self {
+        Message {
+            destination: Some(destination.to_string()),
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            body,
+            ..Message::new(MessageType::MethodCall)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Self {
+        Message {
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            body,
+            ..Message::new(MessageType::Signal)
+        }
+    }
+
+    pub fn reply_to(call: &Message, body: Vec<Value>) -> Self {
+        Message {
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            body,
+            ..Message::new(MessageType::MethodReturn)
+        }
+    }
+
+    pub fn error_to(call: &Message, name: &str, text: &str) -> Self {
+        Message {
+            error_name: Some(name.to_string()),
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            body: vec![Value::Str(text.to_string())],
+            ..Message::new(MessageType::Error)
+        }
+    }
+
+    // The message as it goes on the wire, little endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Writer::default();
+        for value in self.body.iter() {
+            body.value(value);
+        }
+
+        let signature = self.body.iter().map(Value::signature).collect::<String>();
+        let fields = vec![
+            (1, self.path.clone().map(Value::ObjectPath)),
+            (2, self.interface.clone().map(Value::Str)),
+            (3, self.member.clone().map(Value::Str)),
+            (4, self.error_name.clone().map(Value::Str)),
+            (5, self.reply_serial.map(Value::U32)),
+            (6, self.destination.clone().map(Value::Str)),
+            (7, self.sender.clone().map(Value::Str)),
+            (
+                8,
+                Some(Value::Signature(signature)).filter(|_| !self.body.is_empty()),
+            ),
+        ];
+
+        let mut out = Writer::default();
+        out.bytes(&[b'l', self.type_ as u8, self.flags, 1]);
+        out.u32(body.buf.len() as u32);
+        out.u32(self.serial);
+        // an array of (code, variant), with its length filled in after
+        let length_at = out.buf.len();
+        out.u32(0);
+        out.align(8);
+        let start = out.buf.len();
+        for (code, value) in fields
+            .into_iter()
+            .filter_map(|(code, value)| value.map(|value| (code, value)))
+        {
+            out.align(8);
+            out.bytes(&[code]);
+            out.signature(&value.signature().to_string());
+            out.value(&value);
+        }
+        let length = (out.buf.len() - start) as u32;
+        out.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+        out.align(8);
+
+        out.buf.extend(body.buf);
+        out.buf
+    }
+
+    // Reads a message encoded either way round.
+    pub fn decode(buf: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader {
+            buf,
+            pos: 0,
+            big: buf.first() == Some(&b'B'),
+        };
+        let endianness = reader.byte()?;
+        if endianness != b'l' && endianness != b'B' {
+            return Err("Not a D-Bus message".to_string());
+        }
+        let type_ = match reader.byte()? {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            other => return Err(format!("Unknown message type {}", other)),
+        };
+        let mut message = Message::new(type_);
+        message.flags = reader.byte()?;
+        reader.byte()?;
+        reader.u32()?;
+        message.serial = reader.u32()?;
+
+        let fields_len = reader.u32()? as usize;
+        reader.align(8)?;
+        let end = reader.pos + fields_len;
+        let mut signature = String::new();
+        while reader.pos < end {
+            reader.align(8)?;
+            let code = reader.byte()?;
+            let type_ = reader.signature()?;
+            let value = reader.value(&type_)?;
+            let text = value.as_str().map(ToString::to_string);
+            match (code, value) {
+                (1, _) => message.path = text,
+                (2, _) => message.interface = text,
+                (3, _) => message.member = text,
+                (4, _) => message.error_name = text,
+                (5, Value::U32(serial)) => message.reply_serial = Some(serial),
+                (6, _) => message.destination = text,
+                (7, _) => message.sender = text,
+                (8, _) => signature = text.unwrap_or_default(),
+                _ => {}
+            }
+        }
+        reader.align(8)?;
+
+        for type_ in signature.chars() {
+            let value = reader.value(&type_.to_string())?;
+            message.body.push(value);
+        }
+        Ok(message)
+    }
+}
+
+// Reads the next whole message off the bus.
+pub fn read_message<R: Read>(stream: &mut R) -> Result<Message, String> {
+    Message::decode(&read_raw(stream)?)
+}
+
+fn read_raw<R: Read>(stream: &mut R) -> Result<Vec<u8>, String> {
+    let mut fixed = [0; 16];
+    stream
+        .read_exact(&mut fixed)
+        .map_err(|e| format!("{:?}", e))?;
+    let number = |at: usize| {
+        let bytes = [fixed[at], fixed[at + 1], fixed[at + 2], fixed[at + 3]];
+        if fixed[0] == b'B' {
+            u32::from_be_bytes(bytes) as usize
+        } else {
+            u32::from_le_bytes(bytes) as usize
+        }
+    };
+    let header_len = (16 + number(12)).div_ceil(8) * 8;
+    let len = header_len + number(4);
+    if len > MAX_MESSAGE_LEN {
+        return Err(format!("A {} byte message is too long", len));
+    }
+
+    let mut buf = fixed.to_vec();
+    buf.resize(len, 0);
+    stream
+        .read_exact(&mut buf[16..])
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(buf)
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, n: usize) {
+        while !self.buf.len().is_multiple_of(n) {
+            self.buf.push(0);
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.align(4);
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn signature(&mut self, s: &str) {
+        self.bytes(&[s.len() as u8]);
+        self.bytes(s.as_bytes());
+        self.bytes(&[0]);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Bool(b) => self.u32(u32::from(*b)),
+            Value::U32(n) => self.u32(*n),
+            Value::Str(s) | Value::ObjectPath(s) => {
+                self.u32(s.len() as u32);
+                self.bytes(s.as_bytes());
+                self.bytes(&[0]);
+            }
+            Value::Signature(s) => self.signature(s),
+        }
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| "The message ends early".to_string())?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn align(&mut self, n: usize) -> Result<(), String> {
+        let padding = (n - self.pos % n) % n;
+        self.take(padding).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.align(4)?;
+        let bytes = self.take(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if self.big {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn text(&mut self, len: usize) -> Result<String, String> {
+        let text = String::from_utf8_lossy(self.take(len)?).into_owned();
+        self.take(1)?;
+        Ok(text)
+    }
+
+    fn signature(&mut self) -> Result<String, String> {
+        let len = self.byte()? as usize;
+        self.text(len)
+    }
+
+    fn value(&mut self, type_: &str) -> Result<Value, String> {
+        Ok(match type_ {
+            "b" => Value::Bool(self.u32()? != 0),
+            "u" => Value::U32(self.u32()?),
+            "s" | "o" => {
+                let len = self.u32()? as usize;
+                let text = self.text(len)?;
+                if type_ == "s" {
+                    Value::Str(text)
+                } else {
+                    Value::ObjectPath(text)
+                }
+            }
+            "g" => Value::Signature(self.signature()?),
+            other => return Err(format!("Values of type {} aren't supported", other)),
+        })
+    }
+}
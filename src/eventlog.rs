@@ -0,0 +1,134 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::session;
+use crate::watcher::BuildEvent;
+
+// the longest a replay at the original pace waits between two events, so
+// that a session left alone over lunch doesn't stall it
+const MAX_PAUSE: Duration = Duration::from_secs(10);
+
+// Appends the events of a watch to a file as newline delimited JSON, each
+// line what `session::to_json` makes of an event along with when it came in,
+// e.g. {"event": "started", "at": 1700000000000}. What a build took and what
+// started it are in its "finished" line's result.
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    // Opens `path` for appending, creating it and its directory if need be.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("{:?}", e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(EventLog { file })
+    }
+
+    pub fn record(&mut self, event: &BuildEvent) -> Result<(), String> {
+        let mut line = entry(event, SystemTime::now()).to_string();
+        line.push('\n');
+        // a single write per line, so that a reader never sees half of one
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
+// `event` as a line of the log, `at` in milliseconds since the Unix epoch.
+pub fn entry(event: &BuildEvent, at: SystemTime) -> Value {
+    let mut entry = session::to_json(event);
+    let at = at
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default();
+    entry["at"] = json!(at);
+    entry
+}
+
+// An event read back from a log.
+#[derive(Clone, Debug)]
+pub struct LoggedEvent {
+    pub event: BuildEvent,
+    // milliseconds since the Unix epoch
+    pub at: u64,
+}
+
+// Reads back the events of a log, skipping the kinds this version doesn't
+// know.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<LoggedEvent>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{:?}", e))?;
+    let mut events = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(line)
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|entry| parse_entry(&entry))
+            .map_err(|err| format!("Line {}: {}", index + 1, err))?;
+        events.extend(entry);
+    }
+    Ok(events)
+}
+
+// The event a line of the log stands for, or `None` for one of a kind that
+// isn't known.
+pub fn parse_entry(entry: &Value) -> Result<Option<LoggedEvent>, String> {
+    let event = match entry["event"].as_str() {
+        Some("started") => BuildEvent::Started,
+        Some("installing-toolchain") => BuildEvent::InstallingToolchain(field(entry, "toolchain")?),
+        // only the summary was logged at first
+        Some("fetching") => BuildEvent::Fetching(field(entry, "fetch").unwrap_or_default()),
+        Some("compiling") => BuildEvent::Compiling(field(entry, "build")?),
+        Some("extra-roots") => BuildEvent::ExtraRoots(field(entry, "roots")?),
+        Some("diagnostic") => BuildEvent::Diagnostic(field(entry, "diagnostic")?),
+        Some("finished") => BuildEvent::Finished(field(entry, "result")?),
+        Some("scheduled") => BuildEvent::Scheduled(field(entry, "job")?, field(entry, "result")?),
+        Some("hook-failed") => BuildEvent::HookFailed(field(entry, "reason")?),
+        Some("warning") => BuildEvent::Warning(field(entry, "warning")?),
+        Some("stopped") => BuildEvent::Stopped(field(entry, "reason")?),
+        Some(_) => return Ok(None),
+        None => return Err("Not an event.".to_string()),
+    };
+    Ok(Some(LoggedEvent {
+        event,
+        at: entry["at"].as_u64().unwrap_or_default(),
+    }))
+}
+
+fn field<T: DeserializeOwned>(entry: &Value, name: &str) -> Result<T, String> {
+    serde_json::from_value(entry[name].clone()).map_err(|e| format!("{}: {:?}", name, e))
+}
+
+// Hands the events to `send` one after the other, waiting between them as
+// long as they were apart when logged if `original_timing` is set, up to a
+// point. Stops early once `send` returns false.
+pub fn replay<F>(events: Vec<LoggedEvent>, original_timing: bool, mut send: F)
+where
+    F: FnMut(BuildEvent) -> bool,
+{
+    let mut previous = None;
+    for LoggedEvent { event, at } in events {
+        if let (true, Some(previous)) = (original_timing, previous) {
+            let pause = Duration::from_millis(at.saturating_sub(previous));
+            thread::sleep(pause.min(MAX_PAUSE));
+        }
+        previous = Some(at);
+        if !send(event) {
+            return;
+        }
+    }
+}
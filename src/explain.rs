@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use crate::ansi;
+use crate::process::ProcessRunner;
+
+// Whether rustc can explain `code`: its error codes, e.g. "E0308", but not
+// lints.
+pub fn is_explainable(code: &str) -> bool {
+    code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+// What `rustc --explain` says about `code`, as markdown, from the compiler
+// the project builds with.
+pub fn explain(
+    runner: &dyn ProcessRunner,
+    project_root: &Path,
+    env: &[(String, String)],
+    code: &str,
+) -> Result<String, String> {
+    if !is_explainable(code) {
+        return Err(format!("{} isn't one of rustc's error codes.", code));
+    }
+    let output = runner.run("rustc", &["--explain", code], env, project_root)?;
+    if !output.success {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Pango markup for an explanation: code blocks in monospace, without the
+// lines rustdoc hides, headings in bold and `code` in monospace.
+pub fn to_pango(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            if in_code {
+                close_code(&mut out);
+            } else {
+                out.push_str("<tt>");
+            }
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            let trimmed = line.trim_start();
+            if trimmed == "#" || trimmed.starts_with("# ") {
+                continue;
+            }
+            out.push_str(&ansi::escape(line));
+        } else if line.starts_with('#') {
+            out.push_str("<b>");
+            out.push_str(&inline(line.trim_start_matches('#').trim()));
+            out.push_str("</b>");
+        } else {
+            out.push_str(&inline(line));
+        }
+        out.push('\n');
+    }
+    if in_code {
+        close_code(&mut out);
+    }
+    out.trim_end().to_string()
+}
+
+fn close_code(out: &mut String) {
+    if out.ends_with('\n') {
+        out.pop();
+    }
+    out.push_str("</tt>\n");
+}
+
+// `code` spans in monospace, the rest as it is.
+fn inline(line: &str) -> String {
+    let parts = line.split('`').collect::<Vec<_>>();
+    // an odd number of backticks leaves the last one as it is
+    let spans = (parts.len() - 1) / 2 * 2;
+    let mut out = String::new();
+    for (index, part) in parts.iter().enumerate() {
+        if index > spans {
+            out.push('`');
+            out.push_str(&ansi::escape(part));
+        } else if index % 2 == 1 {
+            out.push_str("<tt>");
+            out.push_str(&ansi::escape(part));
+            out.push_str("</tt>");
+        } else {
+            out.push_str(&ansi::escape(part));
+        }
+    }
+    out
+}
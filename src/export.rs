@@ -0,0 +1,168 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::cargo::CompileResult;
+use crate::rust::{RustDiagnostic, Type};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const TOOL_URI: &str = "https://github.com/avranju/watch-rust-errors";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Markdown,
+    Sarif,
+}
+
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "md" | "markdown" => Some(Format::Markdown),
+            "sarif" => Some(Format::Sarif),
+            _ => None,
+        }
+    }
+}
+
+// Writes `result` to `path` in the format its extension asks for.
+pub fn write_to<P: AsRef<Path>>(result: &CompileResult, path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).ok_or_else(|| {
+        format!(
+            "Don't know how to export to {}. Use a .json, .md or .sarif file.",
+            path.display()
+        )
+    })?;
+
+    fs::write(path, export(result, format)?).map_err(|e| format!("{:?}", e))
+}
+
+pub fn export(result: &CompileResult, format: Format) -> Result<String, String> {
+    match format {
+        Format::Json => serde_json::to_string_pretty(result).map_err(|e| format!("{:?}", e)),
+        Format::Markdown => Ok(to_markdown(result)),
+        Format::Sarif => {
+            serde_json::to_string_pretty(&to_sarif(result)).map_err(|e| format!("{:?}", e))
+        }
+    }
+}
+
+fn to_markdown(result: &CompileResult) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# {}",
+        if result.success {
+            "Compile succeeded"
+        } else {
+            "Compile failed"
+        }
+    );
+    if let Some(version) = result.rustc_version.as_ref() {
+        let _ = writeln!(out, "\nBuilt with `{}`.", version);
+    }
+
+    for (heading, diags) in [("Errors", &result.errors), ("Warnings", &result.warnings)] {
+        if diags.is_empty() {
+            continue;
+        }
+
+        let _ = writeln!(out, "\n## {}", heading);
+        for diag in diags.iter() {
+            let _ = writeln!(out, "\n### {}\n", diag.title());
+            if let Some(location) = diag.location() {
+                let _ = writeln!(out, "`{}`\n", location);
+            }
+            if let Some(details) = diag.details.as_ref() {
+                let _ = writeln!(out, "```text\n{}\n```", details.trim_end());
+            }
+        }
+    }
+
+    if let Some(tests) = result.tests.as_ref() {
+        let _ = writeln!(out, "\n## Tests\n\n{}", tests);
+        for failure in tests.failures.iter() {
+            let _ = writeln!(out, "\n### {}\n", failure.name);
+            if let Some(file) = failure.file.as_ref() {
+                let _ = writeln!(
+                    out,
+                    "`{}:{}:{}`\n",
+                    file,
+                    failure.line.unwrap_or_default(),
+                    failure.column.unwrap_or_default()
+                );
+            }
+            if let Some(message) = failure.message.as_ref() {
+                let _ = writeln!(out, "```text\n{}\n```", message.trim_end());
+            }
+        }
+    }
+
+    out
+}
+
+fn to_sarif(result: &CompileResult) -> Value {
+    let mut results: Vec<Value> = result.diagnostics().map(sarif_result).collect();
+
+    if let Some(tests) = result.tests.as_ref() {
+        results.extend(tests.failures.iter().map(|failure| {
+            json!({
+                "ruleId": "test-failure",
+                "level": "error",
+                "message": { "text": format!("test failed: {}", failure.name) },
+                "locations": sarif_locations(failure.file.as_deref(), failure.line, failure.column),
+            })
+        }));
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": TOOL_URI,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn sarif_result(diag: &RustDiagnostic) -> Value {
+    let mut value = json!({
+        "level": match diag.type_ {
+            Type::Error => "error",
+            Type::Warning => "warning",
+        },
+        "message": { "text": diag.message },
+        "locations": sarif_locations(diag.file.as_deref(), diag.line, diag.column),
+    });
+    if let Some(rule) = diag.num.as_ref().or(diag.lint.as_ref()) {
+        value["ruleId"] = json!(rule);
+    }
+    value
+}
+
+fn sarif_locations(file: Option<&str>, line: Option<u32>, column: Option<u32>) -> Value {
+    let file = match file {
+        Some(file) => file,
+        None => return json!([]),
+    };
+
+    let mut location = json!({ "artifactLocation": { "uri": file } });
+    if let Some(line) = line {
+        location["region"] = json!({ "startLine": line });
+        if let Some(column) = column {
+            location["region"]["startColumn"] = json!(column);
+        }
+    }
+
+    json!([{ "physicalLocation": location }])
+}
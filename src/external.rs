@@ -0,0 +1,281 @@
+use std::fs;
+use std::io::{BufRead, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::ansi;
+use crate::cargo::{self, BuildProgress, CompileResult, FetchProgress};
+use crate::libtest::{TestFailure, TestResult};
+use crate::parser::Parser;
+use crate::rust::{RustDiagnostic, Type};
+use crate::watcher::BuildEvent;
+
+// where `bacon --headless` exports locations when `[export]` is enabled
+const BACON_LOCATIONS: &str = ".bacon-locations";
+
+lazy_static! {
+    // bacon's default export line format: "{kind} {path}:{line}:{column} {message}"
+    static ref REGEX_BACON_LOCATION: Regex =
+        Regex::new(r"^([a-z]+) (.+):([0-9]+):([0-9]+) (.*)$").unwrap();
+    static ref REGEX_CARGO_WATCH_RUNNING: Regex = Regex::new(r"^\[Running '.*'\]$").unwrap();
+    static ref REGEX_CARGO_WATCH_FINISHED: Regex =
+        Regex::new(r"^\[Finished running\. Exit status: ([0-9]+)\]$").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Bacon,
+    CargoWatch,
+}
+
+// Follows the output of a watcher that is already running in a terminal
+// instead of compiling the project ourselves.
+pub struct ExternalSource {
+    path: PathBuf,
+    format: Format,
+    modified: Option<SystemTime>,
+}
+
+impl ExternalSource {
+    pub fn bacon<P: AsRef<Path>>(project_root: P) -> Result<Self, String> {
+        let project_root = project_root.as_ref();
+        if !project_root.is_dir() {
+            return Err(format!("{} is not a directory.", project_root.display()));
+        }
+
+        Ok(ExternalSource {
+            path: project_root.join(BACON_LOCATIONS),
+            format: Format::Bacon,
+            modified: None,
+        })
+    }
+
+    // e.g. the file written by `cargo watch -x check 2>&1 | tee cargo-watch.log`
+    pub fn cargo_watch<P: AsRef<Path>>(log_path: P) -> Self {
+        ExternalSource {
+            path: log_path.as_ref().to_path_buf(),
+            format: Format::CargoWatch,
+            modified: None,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    // Returns the latest results if the export changed since the last poll.
+    pub fn poll(&mut self) -> Result<Option<CompileResult>, String> {
+        let modified = match fs::metadata(&self.path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&self.path).map_err(|e| format!("{:?}", e))?;
+        let result = match self.format {
+            Format::Bacon => parse_bacon_locations(&contents),
+            Format::CargoWatch => parse_cargo_watch_log(&contents)?,
+        };
+        self.modified = Some(modified);
+        Ok(Some(result))
+    }
+}
+
+pub fn parse_bacon_locations(contents: &str) -> CompileResult {
+    let mut result = CompileResult::default();
+    let mut failures = vec![];
+
+    for caps in contents
+        .lines()
+        .filter_map(|l| REGEX_BACON_LOCATION.captures(l))
+    {
+        let file = caps[2].to_string();
+        let line = caps[3].parse().ok();
+        let column = caps[4].parse().ok();
+        let message = caps[5].to_string();
+
+        let type_ = match &caps[1] {
+            "error" => Type::Error,
+            "warning" => Type::Warning,
+            "test" => {
+                failures.push(TestFailure {
+                    name: message,
+                    message: None,
+                    file: Some(file),
+                    line,
+                    column,
+                });
+                continue;
+            }
+            _ => continue,
+        };
+        result.push(RustDiagnostic {
+            type_,
+            num: None,
+            message,
+            file: Some(file),
+            line,
+            column,
+            end_line: None,
+            end_column: None,
+            details: None,
+            lint: None,
+            suggestions: vec![],
+            krate: None,
+            styled_details: None,
+            repeats: 0,
+            order: 0,
+            children: vec![],
+            secondary_spans: vec![],
+            macro_backtrace: vec![],
+            tags: vec![],
+        });
+    }
+
+    // bacon only exports failure locations, so there are no pass counts
+    if !failures.is_empty() {
+        result.tests = Some(TestResult {
+            failures,
+            ..Default::default()
+        });
+    }
+    result.success = result.errors.is_empty() && result.tests.is_none();
+    result
+}
+
+// cargo watch brackets every run with "[Running '<cmd>']" and
+// "[Finished running. Exit status: <n>]"; only the latest run is of interest.
+pub fn parse_cargo_watch_log(contents: &str) -> Result<CompileResult, String> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let start = lines
+        .iter()
+        .rposition(|l| REGEX_CARGO_WATCH_RUNNING.is_match(l))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let run = &lines[start..];
+
+    let status = run.iter().find_map(|l| {
+        REGEX_CARGO_WATCH_FINISHED
+            .captures(l)
+            .map(|caps| &caps[1] == "0")
+    });
+    let output = format!("{}\n", run.join("\n"));
+
+    let mut result = cargo::parse(status.unwrap_or(false), &output, &output)?;
+    if status.is_none() {
+        // still running, or run without cargo watch's markers
+        result.success = result.errors.is_empty();
+    }
+    result.output = output;
+    Ok(result)
+}
+
+// A run of piped in output, from its first line on.
+struct PipedRun {
+    output: String,
+    started: Instant,
+    started_at: SystemTime,
+    fetch: FetchProgress,
+    build: BuildProgress,
+}
+
+impl PipedRun {
+    fn new() -> Self {
+        PipedRun {
+            output: String::new(),
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            fetch: FetchProgress::default(),
+            build: BuildProgress::default(),
+        }
+    }
+
+    // The run's results, passing if `status` says so, or without one if
+    // nothing in them is an error.
+    fn finish(self, parser: &dyn Parser, status: Option<bool>) -> Result<CompileResult, String> {
+        let mut result = parser.parse(status.unwrap_or(true), &self.output, &self.output)?;
+        if status.is_none() {
+            result.success = result.errors.is_empty();
+        }
+        result.output = self.output;
+        result.started_at = Some(self.started_at);
+        result.duration = Some(self.started.elapsed());
+        result.finished_at = Some(SystemTime::now());
+        Ok(result)
+    }
+}
+
+// Reads compiler output piped in instead of running anything, e.g. from
+//
+//   cargo check 2>&1 | watch-rust-errors --stdin
+//
+// handing `send` what a watch would: `Started` with a run's first line,
+// progress as cargo reports it, and the results once the input ends. Output
+// cargo watch brackets makes a run of each of its runs. Stops early once
+// `send` returns false.
+pub fn read_piped<R, F>(input: R, parser: &dyn Parser, mut send: F) -> Result<(), String>
+where
+    R: BufRead,
+    F: FnMut(BuildEvent) -> bool,
+{
+    let mut run: Option<PipedRun> = None;
+    for line in input.lines() {
+        let line = line.map_err(|e| format!("{:?}", e))?;
+        let plain = ansi::strip(&line);
+        if REGEX_CARGO_WATCH_RUNNING.is_match(&plain) {
+            if let Some(previous) = run.take() {
+                if !send(BuildEvent::Finished(previous.finish(parser, None)?)) {
+                    return Ok(());
+                }
+            }
+            run = Some(PipedRun::new());
+            if !send(BuildEvent::Started) {
+                return Ok(());
+            }
+            continue;
+        }
+        if let Some(caps) = REGEX_CARGO_WATCH_FINISHED.captures(&plain) {
+            let status = Some(&caps[1] == "0");
+            let finished = run.take().unwrap_or_else(PipedRun::new);
+            if !send(BuildEvent::Finished(finished.finish(parser, status)?)) {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let current = match run.as_mut() {
+            Some(current) => current,
+            None => {
+                if !send(BuildEvent::Started) {
+                    return Ok(());
+                }
+                run.get_or_insert_with(PipedRun::new)
+            }
+        };
+        current.output.push_str(&line);
+        current.output.push('\n');
+        let progress = if current.fetch.update(&line) {
+            Some(BuildEvent::Fetching(current.fetch.clone()))
+        } else if current.build.update(&line) {
+            Some(BuildEvent::Compiling(current.build.clone()))
+        } else {
+            None
+        };
+        if let Some(progress) = progress {
+            if !send(progress) {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(last) = run.take() {
+        send(BuildEvent::Finished(last.finish(parser, None)?));
+    }
+    Ok(())
+}
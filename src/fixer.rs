@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+use futures::channel::oneshot;
+use futures::future::{Future, FutureExt};
+
+use crate::ansi;
+use crate::rust::{RustDiagnostic, Suggestion};
+
+// how many unchanged lines a preview shows either side of a change
+const CONTEXT_LINES: usize = 1;
+
+// A line of a fix's preview.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    // heads each file's lines when a fix touches more than one
+    File(String),
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+// Applies the machine-applicable suggestions carried by `diag` directly to
+// the files on disk. If that is not possible (e.g. the file has changed since
+// the diagnostic was produced) and the diagnostic names a lint, falls back to
+// running `cargo fix` restricted to that lint.
+pub fn apply<P: AsRef<Path>>(project_root: P, diag: &RustDiagnostic) -> Result<(), String> {
+    let project_root = project_root.as_ref();
+
+    match apply_suggestions(project_root, &diag.suggestions) {
+        Ok(()) => Ok(()),
+        Err(err) => match diag.lint.as_ref() {
+            Some(lint) => cargo_fix(project_root, lint),
+            None => Err(err),
+        },
+    }
+}
+
+// Applies `diag`'s fix on a thread of its own, since editing files or running
+// `cargo fix` can take a while.
+pub fn apply_in_background<P: AsRef<Path>>(
+    project_root: P,
+    diag: &RustDiagnostic,
+) -> impl Future<Output = Result<(), String>> {
+    let project_root = project_root.as_ref().to_path_buf();
+    let diag = diag.clone();
+    let (tx, rx) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = tx.send(apply(&project_root, &diag));
+    });
+    rx.map(|applied| applied.unwrap_or_else(|err| Err(format!("{:?}", err))))
+}
+
+// What applying `diag`'s suggestions would change, without changing
+// anything: the lines they touch in each file before and after.
+pub fn preview<P: AsRef<Path>>(
+    project_root: P,
+    diag: &RustDiagnostic,
+) -> Result<Vec<DiffLine>, String> {
+    let edits = fixed(project_root.as_ref(), &diag.suggestions)?;
+    let several = edits.len() > 1;
+    let mut lines = vec![];
+    for (file, _, before, after) in edits.into_iter() {
+        if several {
+            lines.push(DiffLine::File(file));
+        }
+        lines.extend(diff(&before, &after));
+    }
+    Ok(lines)
+}
+
+// A preview as Pango markup, removed lines in red and added ones in green.
+pub fn diff_markup(lines: &[DiffLine], dark: bool) -> String {
+    let (red, green) = if dark {
+        ("#ff7b63", "#8ff0a4")
+    } else {
+        ("#c01c28", "#26a269")
+    };
+    lines
+        .iter()
+        .map(|line| match line {
+            DiffLine::File(file) => format!("<b>{}</b>", ansi::escape(file)),
+            DiffLine::Context(text) => format!("  {}", ansi::escape(text)),
+            DiffLine::Removed(text) => {
+                format!(
+                    "<span foreground=\"{}\">- {}</span>",
+                    red,
+                    ansi::escape(text)
+                )
+            }
+            DiffLine::Added(text) => {
+                format!(
+                    "<span foreground=\"{}\">+ {}</span>",
+                    green,
+                    ansi::escape(text)
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The lines that differ between `before` and `after`, as one change with
+// the unchanged lines around it.
+fn diff(before: &str, after: &str) -> Vec<DiffLine> {
+    let old = before.lines().collect::<Vec<_>>();
+    let new = after.lines().collect::<Vec<_>>();
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let context = |lines: &[&str]| {
+        lines
+            .iter()
+            .map(|line| DiffLine::Context(line.to_string()))
+            .collect::<Vec<_>>()
+    };
+    let mut lines = context(&old[prefix.saturating_sub(CONTEXT_LINES)..prefix]);
+    lines.extend(
+        old[prefix..old.len() - suffix]
+            .iter()
+            .map(|line| DiffLine::Removed(line.to_string())),
+    );
+    lines.extend(
+        new[prefix..new.len() - suffix]
+            .iter()
+            .map(|line| DiffLine::Added(line.to_string())),
+    );
+    let end = old.len() - suffix;
+    lines.extend(context(&old[end..(end + CONTEXT_LINES).min(old.len())]));
+    lines
+}
+
+fn apply_suggestions(project_root: &Path, suggestions: &[Suggestion]) -> Result<(), String> {
+    for (_, path, _, contents) in fixed(project_root, suggestions)?.into_iter() {
+        fs::write(&path, contents).map_err(|e| format!("{:?}", e))?;
+    }
+
+    Ok(())
+}
+
+// Each file the suggestions touch, by name and path, with its contents
+// before and after they're applied, in the order of their names.
+fn fixed(
+    project_root: &Path,
+    suggestions: &[Suggestion],
+) -> Result<Vec<(String, PathBuf, String, String)>, String> {
+    if suggestions.is_empty() {
+        return Err("Diagnostic has no machine applicable suggestions.".to_string());
+    }
+
+    let mut by_file: HashMap<&str, Vec<&Suggestion>> = HashMap::new();
+    for suggestion in suggestions.iter() {
+        by_file
+            .entry(suggestion.file.as_str())
+            .or_default()
+            .push(suggestion);
+    }
+
+    // compute all the edits before writing anything so that a bad suggestion
+    // does not leave the tree half fixed
+    let mut edits = vec![];
+    for (file, mut suggestions) in by_file.into_iter() {
+        let path = project_root.join(file);
+        let before = fs::read_to_string(&path).map_err(|e| format!("{:?}", e))?;
+        let mut contents = before.clone();
+
+        // apply from the end of the file so earlier byte offsets stay valid
+        suggestions.sort_by_key(|s| s.byte_start);
+        let mut end = contents.len();
+        for suggestion in suggestions.into_iter().rev() {
+            if suggestion.byte_start > suggestion.byte_end
+                || suggestion.byte_end > end
+                || !contents.is_char_boundary(suggestion.byte_start)
+                || !contents.is_char_boundary(suggestion.byte_end)
+            {
+                return Err(format!(
+                    "Suggestion for {} does not match the file on disk.",
+                    file
+                ));
+            }
+
+            contents.replace_range(
+                suggestion.byte_start..suggestion.byte_end,
+                &suggestion.replacement,
+            );
+            end = suggestion.byte_start;
+        }
+
+        edits.push((file.to_string(), path, before, contents));
+    }
+
+    edits.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(edits)
+}
+
+fn cargo_fix(project_root: &Path, lint: &str) -> Result<(), String> {
+    let output = Command::new("cargo")
+        .args(["fix", "--allow-dirty", "--allow-staged"])
+        .env("RUSTFLAGS", format!("-A warnings -W {}", lint))
+        .current_dir(project_root)
+        .output()
+        .map_err(|e| format!("{:?}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
@@ -0,0 +1,32 @@
+use gettextrs::gettext;
+use vgtk::lib::gtk::{ButtonsType, DialogFlags, MessageType, ResponseType};
+
+use watch_rust_errors::fixer;
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::rust::RustDiagnostic;
+
+use crate::show_error;
+
+// Applies the fix `diag` suggests to the files in `project_root` once the
+// user agrees to, saying so if it couldn't be.
+pub async fn apply(project_root: String, diag: RustDiagnostic) {
+    let response = vgtk::message_dialog(
+        vgtk::current_window().as_ref(),
+        DialogFlags::empty(),
+        MessageType::Question,
+        ButtonsType::YesNo,
+        true,
+        fill(
+            gettext("<b>Apply the suggested fix?</b>\n\nThis will modify files in <tt>{}</tt>."),
+            &[&glib::markup_escape_text(&project_root)],
+        ),
+    )
+    .await;
+    if response != ResponseType::Yes {
+        return;
+    }
+
+    if let Err(err) = fixer::apply_in_background(&project_root, &diag).await {
+        show_error(&gettext("COULD NOT APPLY FIX!"), &err).await;
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cargo::CompileResult;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::rust::RustDiagnostic;
+
+// what changes are looked for against unless a project sets its own base
+pub const DEFAULT_BASE: &str = "HEAD";
+
+// The files that differ from a base ref, whether committed since, staged,
+// still being edited or not even tracked yet. Deleted files are listed too,
+// which does no harm since they can't have diagnostics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChangedFiles {
+    pub base: String,
+    project_root: PathBuf,
+    // relative to the project root, with forward slashes as git gives them
+    files: HashSet<String>,
+}
+
+impl ChangedFiles {
+    pub fn list<P: AsRef<Path>>(project_root: P, base: &str) -> Result<Self, String> {
+        Self::list_with(&SystemRunner, project_root, base)
+    }
+
+    pub fn list_with<R, P>(runner: &R, project_root: P, base: &str) -> Result<Self, String>
+    where
+        R: ProcessRunner + ?Sized,
+        P: AsRef<Path>,
+    {
+        let project_root = project_root.as_ref();
+        let changed = git(
+            runner,
+            project_root,
+            &["diff", "--name-only", "--relative", base, "--"],
+        )?;
+        let untracked = git(
+            runner,
+            project_root,
+            &["ls-files", "--others", "--exclude-standard"],
+        )?;
+
+        Ok(ChangedFiles {
+            base: base.to_string(),
+            project_root: project_root.to_path_buf(),
+            files: changed
+                .lines()
+                .chain(untracked.lines())
+                .map(str::trim)
+                .filter(|file| !file.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    // Whether `file`, as a diagnostic names it, is one of the changed ones.
+    // Compilers give either paths relative to the project root, sometimes
+    // starting with "./", or absolute ones.
+    pub fn contains(&self, file: &str) -> bool {
+        let path = Path::new(file);
+        let relative = path.strip_prefix(&self.project_root).unwrap_or(path);
+        let relative = relative.strip_prefix(".").unwrap_or(relative);
+        self.files
+            .contains(&relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    // Drops the diagnostics in files that haven't changed. Ones that aren't
+    // in any file, like a failed link, are kept since there's no telling
+    // whether a change caused them.
+    pub fn retain(&self, result: &mut CompileResult) {
+        let keep = |diag: &RustDiagnostic| {
+            diag.file
+                .as_deref()
+                .map(|f| self.contains(f))
+                .unwrap_or(true)
+        };
+        result.errors.retain(keep);
+        result.warnings.retain(keep);
+    }
+}
+
+// What git did to the repository that made for a build.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum GitTrigger {
+    // a checkout of another branch, or of a commit when detached
+    Switched { to: String },
+    // the branch that's checked out moved, e.g. with a pull or a commit
+    Moved { branch: String },
+}
+
+impl Display for GitTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitTrigger::Switched { to } => write!(f, "branch switched to {}", to),
+            GitTrigger::Moved { branch } => write!(f, "{} moved", branch),
+        }
+    }
+}
+
+// The directory with the repository `project_root` is in, looked for up from
+// it the way git does. Worktrees and submodules have a ".git" file saying
+// where it is instead.
+pub fn git_dir(project_root: &Path) -> Option<PathBuf> {
+    project_root.ancestors().find_map(|dir| {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        }
+        let contents = fs::read_to_string(&dot_git).ok()?;
+        let path = contents.trim().strip_prefix("gitdir:")?.trim();
+        Some(dir.join(path))
+    })
+}
+
+// What HEAD in `git_dir` is on: a branch, or the start of a commit's hash
+// when it's detached.
+pub fn head(git_dir: &Path) -> Option<String> {
+    let contents = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let contents = contents.trim();
+    match contents.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            )
+        }
+        None => Some(contents.chars().take(7).collect()).filter(|hash: &String| !hash.is_empty()),
+    }
+}
+
+// Runs git in `cwd`, returning what it printed or, when it fails, what it
+// complained about.
+pub fn git<R>(runner: &R, cwd: &Path, args: &[&str]) -> Result<String, String>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let output = runner.run("git", args, &[], cwd)?;
+    if !output.success {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    str::from_utf8(&output.stdout)
+        .map(ToString::to_string)
+        .map_err(|e| format!("{:?}", e))
+}
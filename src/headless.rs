@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+
+use futures::executor::block_on;
+use futures::future;
+use futures::stream::{StreamExt, TryStreamExt};
+
+use watch_rust_errors::cargo::CompileResult;
+use watch_rust_errors::config::{Config, ParserKind};
+use watch_rust_errors::history::{BuildRecord, History};
+use watch_rust_errors::lsp::LspServer;
+use watch_rust_errors::notifications::{Notice, Notifier};
+use watch_rust_errors::priority;
+use watch_rust_errors::quickfix;
+use watch_rust_errors::server::Server;
+use watch_rust_errors::session::{SessionConfig, WatchSession};
+use watch_rust_errors::watcher::BuildEvent;
+
+const USAGE: &str = "Usage: watch-rust-errors --headless <project root> \
+                     [--command <command>] [--format human|gnu] \
+                     [--parser rustc|gcc|passthrough|<matcher>] \
+                     [--serve <address>] [--serve-origin <origin>]... \
+                     [--lsp <address>] \
+                     [--event-log <file>] [--log-runs]";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    // the same text "Copy all output" puts on the clipboard
+    Human,
+    // "file:line:col: error: message" lines for Emacs' compilation-mode and
+    // anything else that reads GNU style diagnostics
+    Gnu,
+}
+
+struct Options {
+    project_root: String,
+    command: String,
+    format: OutputFormat,
+    parser: ParserKind,
+    // where to serve results as well, e.g. "127.0.0.1:7878"
+    serve: Option<String>,
+    // the web pages that may read them
+    serve_origins: Vec<String>,
+    // where editors can connect for diagnostics
+    lsp: Option<String>,
+    // where to append every event as a line of JSON
+    event_log: Option<String>,
+    // whether every build goes into the project's run log
+    log_runs: bool,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut project_root = None;
+        let mut command = "cargo check".to_string();
+        let mut format = OutputFormat::Human;
+        let mut parser = ParserKind::default();
+        let mut serve = None;
+        let mut serve_origins = vec![];
+        let mut lsp = None;
+        let mut event_log = None;
+        let mut log_runs = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {}
+                "--command" => command = args.next().ok_or(USAGE)?,
+                "--format" => {
+                    format = match args.next().as_deref() {
+                        Some("human") => OutputFormat::Human,
+                        Some("gnu") => OutputFormat::Gnu,
+                        _ => return Err(USAGE.to_string()),
+                    }
+                }
+                "--parser" => parser = args.next().and_then(|p| p.parse().ok()).ok_or(USAGE)?,
+                "--serve" => serve = Some(args.next().ok_or(USAGE)?),
+                "--serve-origin" => serve_origins.push(args.next().ok_or(USAGE)?),
+                "--lsp" => lsp = Some(args.next().ok_or(USAGE)?),
+                "--event-log" => event_log = Some(args.next().ok_or(USAGE)?),
+                "--log-runs" => log_runs = true,
+                _ if project_root.is_none() && !arg.starts_with("--") => project_root = Some(arg),
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+
+        Ok(Options {
+            project_root: project_root.ok_or(USAGE)?,
+            command,
+            format,
+            parser,
+            serve,
+            serve_origins,
+            lsp,
+            event_log,
+            log_runs,
+        })
+    }
+}
+
+pub fn is_requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--headless")
+}
+
+// Watches the project without a window, printing every build's results to
+// stdout until the process is killed.
+pub fn run() -> i32 {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+
+    // problem matchers come from the same config the window uses, and so do
+    // notifications, less the ones that need a window
+    let app_config = Config::load();
+    let parser = match app_config.parser(&options.parser) {
+        Ok(parser) => parser,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+
+    let server = match options.serve.as_deref().map(Server::start).transpose() {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Could not serve results: {}", err);
+            return 1;
+        }
+    };
+    if let Some(server) = server.as_ref() {
+        server.allow_origins(options.serve_origins.clone());
+    }
+
+    let lsp_server = match options.lsp.as_deref().map(LspServer::start).transpose() {
+        Ok(lsp_server) => lsp_server,
+        Err(err) => {
+            eprintln!("Could not serve editors: {}", err);
+            return 1;
+        }
+    };
+
+    let mut config = SessionConfig::new(&options.project_root, &options.command);
+    config.parser = parser;
+    config.server = server;
+    config.lsp_server = lsp_server;
+    config.event_log = options.event_log.map(PathBuf::from);
+    config.log_runs = options.log_runs;
+    config.build_timeout = app_config.project(&options.project_root).build_timeout();
+    config.post_build = app_config.project(&options.project_root).post_build_hooks();
+    config.process = priority::runner(app_config.build_priority, config.process);
+    // there's no window to be in the background, only the power to go by
+    config.ac_power_only = app_config.build_only_on_ac_power;
+    let (_session, events) = match WatchSession::start(config) {
+        Ok(started) => started,
+        Err(err) => {
+            eprintln!("Could not start watching: {}", err);
+            return 1;
+        }
+    };
+
+    let project_root = options.project_root.clone();
+    let format = options.format;
+    let notifier = Notifier::remote(&app_config.notification_sinks());
+    let mut previous_success = None;
+    let mut history = History::default();
+    let outcome = block_on(events.map(Ok).try_for_each(|event| {
+        match event {
+            BuildEvent::Started
+            | BuildEvent::Compiling(_)
+            | BuildEvent::Pipeline(_)
+            | BuildEvent::ExtraRoots(_)
+            | BuildEvent::Diagnostic(_) => {}
+            BuildEvent::InstallingToolchain(channel) => {
+                eprintln!("Installing toolchain {}…", channel)
+            }
+            BuildEvent::Fetching(progress) => eprintln!("{}", progress),
+            BuildEvent::Finished(result) => {
+                print!("{}", render(&project_root, &result, format));
+                if let Some(notice) = Notice::for_build(&project_root, previous_success, &result) {
+                    if let Err(err) = notifier.send(&notice) {
+                        eprintln!("{}", err);
+                    }
+                }
+                previous_success = Some(result.success);
+                history.push(BuildRecord::new(&result));
+                if let Some(slowdown) = history.slowdown(&app_config.slow_builds) {
+                    let notice = Notice::for_slow_build(&project_root, &slowdown);
+                    eprintln!("{}", notice.title);
+                    if let Err(err) = notifier.send(&notice) {
+                        eprintln!("{}", err);
+                    }
+                }
+            }
+            // headless mode doesn't run scheduled jobs
+            BuildEvent::Scheduled(..) => {}
+            BuildEvent::HookFailed(reason) => eprintln!("Post-build hook failed: {}", reason),
+            BuildEvent::Warning(warning) => eprintln!("{}", warning),
+            BuildEvent::Stopped(reason) => return future::ready(Err(reason)),
+        }
+        future::ready(Ok(()))
+    }));
+
+    match outcome {
+        Ok(()) => 0,
+        Err(reason) => {
+            eprintln!("Watcher stopped unexpectedly: {}", reason);
+            1
+        }
+    }
+}
+
+fn render(project_root: &str, result: &CompileResult, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Human => format!("{}\n", result),
+        OutputFormat::Gnu => format!(
+            "{}{}\n",
+            quickfix::to_quickfix(project_root, result.diagnostics()),
+            if result.success {
+                "Compile succeeded."
+            } else {
+                "Compile failed."
+            }
+        ),
+    }
+}
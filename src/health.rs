@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::notifications::SinkKind;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::sandbox;
+use crate::validate;
+
+// where Linux keeps the limit on inotify watches, and the least that's
+// comfortable for a large workspace with its target directory
+const MAX_WATCHES: &str = "/proc/sys/fs/inotify/max_user_watches";
+pub const MIN_WATCHES: u64 = 65_536;
+
+// the name a notification daemon takes on the session bus
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+
+// One thing the app needs from its environment, and whether it's there: what
+// was found if so, and otherwise what's wrong and how to fix it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: String) -> Self {
+        Check {
+            name,
+            passed: true,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: String, hint: &str) -> Self {
+        Check {
+            name,
+            passed: false,
+            detail,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+// Where the checks look, so that tests can point them elsewhere.
+#[derive(Clone, Debug)]
+pub struct Paths {
+    pub max_watches: PathBuf,
+    pub config_file: Option<PathBuf>,
+    pub flatpak_info: PathBuf,
+}
+
+impl Paths {
+    pub fn system() -> Self {
+        Paths {
+            max_watches: PathBuf::from(MAX_WATCHES),
+            config_file: Config::path(),
+            flatpak_info: PathBuf::from(sandbox::FLATPAK_INFO),
+        }
+    }
+}
+
+pub fn check(config: &Config) -> Vec<Check> {
+    check_with(&SystemRunner, config, &Paths::system())
+}
+
+// Goes through what most often keeps things from working: the tools builds
+// need, the file system watcher's limits, somewhere to show notifications,
+// the editor command, somewhere to save settings and, in a Flatpak sandbox,
+// what it lets the app see. Checks that don't apply, like inotify's off
+// Linux, are left out.
+pub fn check_with(runner: &dyn ProcessRunner, config: &Config, paths: &Paths) -> Vec<Check> {
+    let flatpak = fs::read_to_string(&paths.flatpak_info).ok();
+    let mut checks = vec![
+        version(
+            runner,
+            "cargo",
+            if flatpak.is_some() {
+                "In a Flatpak sandbox builds need the Rust SDK extension: \
+                 `flatpak install flathub org.freedesktop.Sdk.Extension.rust-stable`."
+            } else {
+                "Install Rust from https://rustup.rs, or add ~/.cargo/bin to the PATH \
+                 the app is started with."
+            },
+        ),
+        version(
+            runner,
+            "rustup",
+            "Picking toolchains and targets needs rustup; install it from \
+             https://rustup.rs.",
+        ),
+    ];
+    checks.extend(watches(&paths.max_watches));
+    if config
+        .notification_sinks()
+        .iter()
+        .any(|sink| sink.kind == SinkKind::Desktop)
+    {
+        checks.push(notifications(runner));
+    }
+    checks.push(editor(runner, config));
+    checks.push(config_dir(paths.config_file.as_deref()));
+    checks.extend(flatpak.as_deref().map(sandbox_files));
+    checks
+}
+
+fn version(runner: &dyn ProcessRunner, program: &'static str, hint: &str) -> Check {
+    match runner.run(program, &["--version"], &[], Path::new(".")) {
+        Ok(output) if output.success => {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            Check::pass(program, version)
+        }
+        Ok(output) => Check::fail(
+            program,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            hint,
+        ),
+        Err(_) => Check::fail(program, format!("{} isn't on the PATH.", program), hint),
+    }
+}
+
+fn watches(path: &Path) -> Option<Check> {
+    let limit = fs::read_to_string(path).ok()?.trim().parse::<u64>().ok()?;
+    Some(if limit >= MIN_WATCHES {
+        Check::pass(
+            "inotify watches",
+            format!("Up to {} files can be watched.", limit),
+        )
+    } else {
+        Check::fail(
+            "inotify watches",
+            format!(
+                "Only {} files can be watched, which large projects run out of.",
+                limit
+            ),
+            "Raise it with `sudo sysctl fs.inotify.max_user_watches=524288`, and in a \
+             file in /etc/sysctl.d to keep it after a reboot.",
+        )
+    })
+}
+
+fn notifications(runner: &dyn ProcessRunner) -> Check {
+    const NAME: &str = "notifications";
+    const HINT: &str = "Desktop notifications need a notification daemon, e.g. the \
+                        desktop's own, dunst or mako.";
+    let args = [
+        "call",
+        "--session",
+        "--dest",
+        "org.freedesktop.DBus",
+        "--object-path",
+        "/org/freedesktop/DBus",
+        "--method",
+        "org.freedesktop.DBus.NameHasOwner",
+        NOTIFICATIONS,
+    ];
+    match runner.run("gdbus", &args, &[], Path::new(".")) {
+        // e.g. "(true,)"
+        Ok(output)
+            if output.success && String::from_utf8_lossy(&output.stdout).contains("true") =>
+        {
+            Check::pass(NAME, "A notification daemon is running.".to_string())
+        }
+        Ok(output) if output.success => {
+            Check::fail(NAME, "No notification daemon is running.".to_string(), HINT)
+        }
+        Ok(output) => Check::fail(
+            NAME,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            HINT,
+        ),
+        Err(err) => Check::fail(
+            NAME,
+            format!("Could not ask the session bus: {}", err),
+            HINT,
+        ),
+    }
+}
+
+fn editor(runner: &dyn ProcessRunner, config: &Config) -> Check {
+    const NAME: &str = "editor";
+    const HINT: &str =
+        "Fix `open_command`, `quickfix_command` or the `editor` preset in config.toml.";
+    // without a way to open a single diagnostic, its file opens as a
+    // quickfix list
+    let command = match config.open_command(runner) {
+        Some(Ok(command)) => command,
+        Some(Err(err)) => return Check::fail(NAME, err, HINT),
+        None => config.quickfix_command().to_string(),
+    };
+    match validate::program_problem(&command) {
+        Some(problem) => Check::fail(NAME, problem, HINT),
+        None => Check::pass(NAME, format!("Diagnostics open with `{}`.", command)),
+    }
+}
+
+// Whether settings can be saved, by writing a file next to where they go.
+fn config_dir(config_file: Option<&Path>) -> Check {
+    const NAME: &str = "config directory";
+    let dir = match config_file.and_then(Path::parent) {
+        Some(dir) => dir,
+        None => {
+            return Check::fail(
+                NAME,
+                "There's no config directory.".to_string(),
+                "Set XDG_CONFIG_HOME or HOME, so that settings have somewhere to go.",
+            )
+        }
+    };
+    let probe = dir.join(".write-test");
+    let written = fs::create_dir_all(dir).and_then(|_| fs::write(&probe, ""));
+    let _ = fs::remove_file(&probe);
+    match written {
+        Ok(()) => Check::pass(NAME, format!("Settings are saved in {}.", dir.display())),
+        Err(err) => Check::fail(
+            NAME,
+            format!("{} can't be written to: {}", dir.display(), err),
+            "Check the directory's owner and permissions; until then settings aren't saved.",
+        ),
+    }
+}
+
+// Whether the Flatpak sandbox described in `info` lets the app see projects
+// where they are, rather than only the folders picked in the file chooser.
+fn sandbox_files(info: &str) -> Check {
+    const NAME: &str = "sandbox";
+    if sandbox::sees_home(info) {
+        return Check::pass(
+            NAME,
+            "The Flatpak sandbox lets the app see your home folder.".to_string(),
+        );
+    }
+    Check::fail(
+        NAME,
+        "The Flatpak sandbox only lets the app see the folders picked in the file chooser, \
+         where changes made by other apps are found by looking every few seconds."
+            .to_string(),
+        &format!(
+            "Give it your home folder with `flatpak override --user --filesystem=home {}`.",
+            sandbox::app_id()
+        ),
+    )
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::cargo::CompileResult;
+use crate::parser::Parser;
+use crate::rust::Type;
+
+// directories that never hold the project's own code
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+// diagnostics per thousand lines at which a module moves up a heat level
+const HEAT_LEVELS: &[f64] = &[0.0, 2.0, 10.0, 30.0];
+
+// A directory of the project's sources and how many diagnostics point into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleStats {
+    // relative to the project root, "." for the root itself
+    pub path: String,
+    pub lines: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl ModuleStats {
+    // diagnostics per thousand lines of code
+    pub fn density(&self) -> f64 {
+        (self.errors + self.warnings) as f64 * 1000.0 / self.lines.max(1) as f64
+    }
+
+    // 0 for a module without diagnostics, up to `HEAT_LEVELS.len()`
+    pub fn heat(&self) -> usize {
+        if self.errors + self.warnings == 0 {
+            return 0;
+        }
+        HEAT_LEVELS
+            .iter()
+            .filter(|level| self.density() > **level)
+            .count()
+    }
+}
+
+// The module, i.e. directory relative to the project root, a diagnostic's file
+// is in. `None` for files outside of the project.
+pub fn module_of<P: AsRef<Path>>(project_root: P, file: &str) -> Option<String> {
+    let file = Path::new(file);
+    let relative = if file.is_absolute() {
+        file.strip_prefix(project_root).ok()?
+    } else {
+        file
+    };
+    if relative.starts_with("..") {
+        return None;
+    }
+
+    Some(match relative.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_string_lossy().replace('\\', "/"),
+        _ => ".".to_string(),
+    })
+}
+
+// Counts the lines of every source file `parser` watches under the project
+// root by directory, and the latest diagnostics against them. Directories
+// without sources are left out, as are hidden and build output directories.
+pub fn module_stats<P: AsRef<Path>>(
+    project_root: P,
+    result: &CompileResult,
+    parser: &dyn Parser,
+) -> Vec<ModuleStats> {
+    let project_root = project_root.as_ref();
+    let mut modules = BTreeMap::new();
+    count_lines(project_root, Path::new(""), parser, &mut modules);
+
+    for diag in result.diagnostics() {
+        let module = diag
+            .file
+            .as_deref()
+            .and_then(|file| module_of(project_root, file))
+            .and_then(|module| modules.get_mut(&module));
+        if let Some(module) = module {
+            match diag.type_ {
+                Type::Error => module.errors += 1,
+                Type::Warning => module.warnings += 1,
+            }
+        }
+    }
+
+    modules.into_values().collect()
+}
+
+fn count_lines(
+    project_root: &Path,
+    relative: &Path,
+    parser: &dyn Parser,
+    modules: &mut BTreeMap<String, ModuleStats>,
+) {
+    let entries = match fs::read_dir(project_root.join(relative)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let path = relative.join(name.as_ref());
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+
+        if file_type.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                count_lines(project_root, &path, parser, modules);
+            }
+        } else if file_type.is_file() && parser.watches(&path) {
+            // binary files and the like aren't code
+            let lines = match fs::read_to_string(entry.path()) {
+                Ok(contents) => contents.lines().count(),
+                Err(_) => continue,
+            };
+            let module =
+                module_of(project_root, &path.to_string_lossy()).unwrap_or_else(|| ".".to_string());
+            modules
+                .entry(module.clone())
+                .or_insert_with(|| ModuleStats {
+                    path: module,
+                    lines: 0,
+                    errors: 0,
+                    warnings: 0,
+                })
+                .lines += lines;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// Lays `sizes` out as a treemap filling `width` by `height`, each rectangle's
+// area proportional to its size. Rectangles are placed in rows from the top,
+// left to right and in the order given, starting a new row whenever adding to
+// the current one would make its rectangles any less square. Sizes should be
+// sorted from largest to smallest for the best result.
+pub fn layout(sizes: &[f64], width: f64, height: f64) -> Vec<Vec<Rect>> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 || width <= 0.0 || height <= 0.0 {
+        return vec![];
+    }
+    // area per unit of size
+    let scale = width * height / total;
+
+    let mut rows: Vec<Vec<f64>> = vec![];
+    let mut row: Vec<f64> = vec![];
+    for size in sizes.iter().map(|s| s * scale) {
+        let mut longer = row.clone();
+        longer.push(size);
+        if !row.is_empty() && worst_ratio(&longer, width) > worst_ratio(&row, width) {
+            rows.push(std::mem::take(&mut row));
+        }
+        row.push(size);
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+
+    let mut y = 0.0;
+    rows.into_iter()
+        .map(|areas| {
+            let row_height = areas.iter().sum::<f64>() / width;
+            let mut x = 0.0;
+            let rects = areas
+                .into_iter()
+                .map(|area| {
+                    let rect = Rect {
+                        x,
+                        y,
+                        width: area / row_height,
+                        height: row_height,
+                    };
+                    x += rect.width;
+                    rect
+                })
+                .collect();
+            y += row_height;
+            rects
+        })
+        .collect()
+}
+
+// How far from square the least square rectangle in a row of `areas`
+// spanning `width` is, as the ratio of its longer side to its shorter one.
+fn worst_ratio(areas: &[f64], width: f64) -> f64 {
+    let height = areas.iter().sum::<f64>() / width;
+    areas
+        .iter()
+        .map(|area| {
+            let cell = area / height;
+            (cell / height).max(height / cell)
+        })
+        .fold(1.0, f64::max)
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cargo::CompileResult;
+
+// how many builds the statistics chart looks back on, and slow builds are
+// held up against
+pub const HISTORY_LEN: usize = 30;
+
+// When a build counts as slow: taking `factor` times as long as the median of
+// the kept builds before it, e.g. 1.5 for half as long again, and at least
+// `min_slowdown_secs` longer, so that quick builds jittering don't count.
+// There have to be `min_builds` to go by first.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlowBuilds {
+    pub enabled: bool,
+    pub factor: f64,
+    pub min_builds: usize,
+    pub min_slowdown_secs: u64,
+}
+
+impl Default for SlowBuilds {
+    fn default() -> Self {
+        SlowBuilds {
+            enabled: true,
+            factor: 1.5,
+            min_builds: 5,
+            min_slowdown_secs: 2,
+        }
+    }
+}
+
+// A build that took longer than usual, and how long they usually take.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Slowdown {
+    pub duration: Duration,
+    pub median: Duration,
+}
+
+impl Slowdown {
+    // how much longer the build took, e.g. 80 for 1.8 times the median
+    pub fn percent_longer(&self) -> u32 {
+        let median = self.median.as_secs_f64().max(0.001);
+        ((self.duration.as_secs_f64() / median - 1.0) * 100.0).round() as u32
+    }
+}
+
+// What a single build came to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildRecord {
+    pub finished_at: SystemTime,
+    // unknown for results read from another watcher
+    pub duration: Option<Duration>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub success: bool,
+}
+
+impl BuildRecord {
+    pub fn new(result: &CompileResult) -> Self {
+        BuildRecord {
+            finished_at: result.finished_at.unwrap_or_else(SystemTime::now),
+            duration: result.duration,
+            errors: result.errors.len(),
+            warnings: result.warnings.len(),
+            success: result.success,
+        }
+    }
+}
+
+// The builds of a watch session. Only the latest `capacity` are kept, but
+// the average build time covers all of them.
+#[derive(Clone, Debug)]
+pub struct History {
+    records: VecDeque<BuildRecord>,
+    capacity: usize,
+    timed_builds: u32,
+    total_time: Duration,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(HISTORY_LEN)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            timed_builds: 0,
+            total_time: Duration::default(),
+        }
+    }
+
+    pub fn push(&mut self, record: BuildRecord) {
+        if let Some(duration) = record.duration {
+            self.timed_builds += 1;
+            self.total_time += duration;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    pub fn clear(&mut self) {
+        *self = History::new(self.capacity);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&BuildRecord> {
+        self.records.back()
+    }
+
+    // oldest first
+    pub fn records(&self) -> impl Iterator<Item = &BuildRecord> {
+        self.records.iter()
+    }
+
+    // How many builds the session had that took a known time.
+    pub fn timed_builds(&self) -> u32 {
+        self.timed_builds
+    }
+
+    pub fn average_duration(&self) -> Option<Duration> {
+        if self.timed_builds == 0 {
+            None
+        } else {
+            Some(self.total_time / self.timed_builds)
+        }
+    }
+
+    // Whether the latest build took much longer than the kept ones before it
+    // usually did, as told by `threshold`, e.g. after a dependency or build
+    // script was added.
+    pub fn slowdown(&self, threshold: &SlowBuilds) -> Option<Slowdown> {
+        if !threshold.enabled {
+            return None;
+        }
+        let duration = self.records.back()?.duration?;
+        let mut before = self
+            .records
+            .iter()
+            .rev()
+            .skip(1)
+            .filter_map(|record| record.duration)
+            .collect::<Vec<_>>();
+        if before.is_empty() || before.len() < threshold.min_builds {
+            return None;
+        }
+        let median = median(&mut before);
+        let slow = duration.as_secs_f64() >= median.as_secs_f64() * threshold.factor
+            && duration >= median + Duration::from_secs(threshold.min_slowdown_secs);
+        if slow {
+            Some(Slowdown { duration, median })
+        } else {
+            None
+        }
+    }
+
+    // The error count of each kept build, oldest first.
+    pub fn error_counts(&self) -> Vec<usize> {
+        self.records.iter().map(|r| r.errors).collect()
+    }
+}
+
+// the middle one, or halfway between the middle two
+fn median(durations: &mut [Duration]) -> Duration {
+    durations.sort();
+    let middle = durations.len() / 2;
+    if durations.len().is_multiple_of(2) {
+        (durations[middle - 1] + durations[middle]) / 2
+    } else {
+        durations[middle]
+    }
+}
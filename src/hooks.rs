@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::cargo::CompileResult;
+use crate::postprocess;
+
+// how long a hook gets unless the config says otherwise
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+// A command run after each build, e.g. to light an LED, post to a chat or
+// touch a file another tool waits on. It runs in the project root with the
+// project's variables and
+//
+//   WRE_SUCCESS=false WRE_ERRORS=2 WRE_WARNINGS=5
+//
+// and reads the results on stdin as the post-processor does. What it prints
+// is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hook {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+// What hooks are told about a build, taken before its results are sent on.
+#[derive(Clone, Debug)]
+pub struct Report {
+    vars: Vec<(String, String)>,
+    input: Vec<u8>,
+}
+
+impl Report {
+    pub fn new(project_root: &Path, result: &CompileResult) -> Self {
+        let vars = vec![
+            ("WRE_SUCCESS".to_string(), result.success.to_string()),
+            ("WRE_ERRORS".to_string(), result.errors.len().to_string()),
+            (
+                "WRE_WARNINGS".to_string(),
+                result.warnings.len().to_string(),
+            ),
+        ];
+        let input = json!({
+            "project_root": project_root,
+            "success": result.success,
+            "errors": result.errors,
+            "warnings": result.warnings,
+        });
+        Report {
+            vars,
+            input: input.to_string().into_bytes(),
+        }
+    }
+
+    pub fn vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+}
+
+impl Hook {
+    pub fn new(command: &str) -> Self {
+        Hook {
+            command: command.to_string(),
+            timeout: TIMEOUT,
+        }
+    }
+
+    // Fails when the command does, or takes too long and is stopped.
+    pub fn run(
+        &self,
+        project_root: &Path,
+        env: &[(String, String)],
+        report: &Report,
+    ) -> Result<(), String> {
+        let mut env = env.to_vec();
+        env.extend(report.vars.iter().cloned());
+        postprocess::run(
+            &self.command,
+            self.timeout,
+            project_root,
+            &env,
+            report.input.clone(),
+        )
+        .map(|_| ())
+    }
+}
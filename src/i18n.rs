@@ -0,0 +1,74 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use gettextrs::{bind_textdomain_codeset, bindtextdomain, setlocale, textdomain, LocaleCategory};
+
+// what the translations are installed as, e.g.
+// /usr/share/locale/de/LC_MESSAGES/watch-rust-errors.mo
+pub const DOMAIN: &str = "watch-rust-errors";
+
+// Where the translations are: in WRE_LOCALE_DIR if it's set, else where a
+// build with LOCALEDIR set installs them, else where the build put them.
+fn locale_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("WRE_LOCALE_DIR") {
+        return PathBuf::from(dir);
+    }
+    match option_env!("LOCALEDIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(concat!(env!("OUT_DIR"), "/locale")),
+    }
+}
+
+// Makes `gettext` translate into the language of the user's locale, e.g.
+// from LANG or LC_MESSAGES. What there's no translation for stays English.
+pub fn init() {
+    setlocale(LocaleCategory::LcAll, "");
+    let dir = locale_dir();
+    if let Err(err) = bindtextdomain(DOMAIN, dir.clone()) {
+        eprintln!(
+            "Failed to load translations from {}: {}",
+            dir.display(),
+            err
+        );
+        return;
+    }
+    // GTK wants UTF-8 whatever the locale's encoding
+    if let Err(err) = bind_textdomain_codeset(DOMAIN, "UTF-8") {
+        eprintln!("Failed to set the translations' encoding: {}", err);
+    }
+    if let Err(err) = textdomain(DOMAIN) {
+        eprintln!("Failed to load translations: {}", err);
+    }
+}
+
+// Puts `args` in place of the placeholders of a translated message, e.g.
+// `fill(gettext("Built {} ago"), &[&ago])`. `{}`s take them in order, and
+// `{0}`, `{1}` and so on by number, for translations to put them in another
+// order than the English does. Placeholders without an argument are left.
+pub fn fill(message: String, args: &[&dyn Display]) -> String {
+    let mut filled = String::with_capacity(message.len());
+    let mut next = 0;
+    let mut rest = message.as_str();
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let inner = &rest[1..end];
+        let index = if inner.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            inner.parse::<usize>().ok()
+        };
+        match index.and_then(|index| args.get(index)) {
+            Some(arg) => filled.push_str(&arg.to_string()),
+            None => filled.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,101 @@
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+
+// the files a project's ignore rules are read from, in each directory
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+// What a project's `.gitignore` and `.ignore` files leave out, so that
+// changes to e.g. generated code or `node_modules` don't start builds.
+// Nested files apply to their own directory and override the ones above
+// them, as they do for git.
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    // by directory, deepest first
+    matchers: Vec<(PathBuf, Gitignore)>,
+    // what went wrong reading the files, which leaves their rules out
+    pub errors: Vec<String>,
+}
+
+impl IgnoreRules {
+    // Reads the ignore files under `project_root`, skipping directories
+    // they already leave out.
+    pub fn load<P: AsRef<Path>>(project_root: P) -> Self {
+        let mut rules = IgnoreRules::default();
+        rules.add(project_root);
+        rules
+    }
+
+    // Reads the ignore files under another directory too.
+    pub fn add<P: AsRef<Path>>(&mut self, root: P) {
+        let walk = WalkBuilder::new(root.as_ref())
+            .hidden(false)
+            // the rules apply whether or not the project is in a repository
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+
+        for entry in walk.filter_map(Result::ok) {
+            if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let dir = entry.path();
+            let mut builder = GitignoreBuilder::new(dir);
+            let mut found = false;
+            for name in IGNORE_FILES {
+                let file = dir.join(name);
+                if file.is_file() {
+                    found = true;
+                    if let Some(err) = builder.add(&file) {
+                        self.errors
+                            .push(format!("Failed to read {}: {}", file.display(), err));
+                    }
+                }
+            }
+            if !found {
+                continue;
+            }
+            match builder.build() {
+                Ok(gitignore) => self.matchers.push((dir.to_path_buf(), gitignore)),
+                Err(err) => self.errors.push(format!(
+                    "Failed to read the ignore files in {}: {}",
+                    dir.display(),
+                    err
+                )),
+            }
+        }
+
+        self.matchers
+            .sort_by_key(|(dir, _)| Reverse(dir.components().count()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    // Whether `path`, or a directory it's in, is left out by the rules
+    // closest to it that say anything about it.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let is_dir = path.is_dir();
+        for (dir, gitignore) in self.matchers.iter() {
+            if !path.starts_with(dir) {
+                continue;
+            }
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    // Whether the rules would be read differently after a change to `path`.
+    pub fn is_ignore_file(path: &Path) -> bool {
+        path.file_name()
+            .map(|name| IGNORE_FILES.iter().any(|file| name == *file))
+            .unwrap_or(false)
+    }
+}
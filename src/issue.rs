@@ -0,0 +1,184 @@
+use std::fs;
+use std::path::Path;
+
+use crate::git;
+use crate::notifications::encode;
+use crate::process::ProcessRunner;
+use crate::rust::RustDiagnostic;
+
+// New issue pages with {repository}, {title} and {body} for the draft's
+// parts, picked by where the repository is hosted unless a project sets its
+// own.
+const GITHUB_TEMPLATE: &str = "{repository}/issues/new?title={title}&body={body}";
+const GITLAB_TEMPLATE: &str =
+    "{repository}/-/issues/new?issue[title]={title}&issue[description]={body}";
+
+// Lines of a repository's files at a commit, with {repository}, {commit},
+// {path} and {line}, likewise picked by where it's hosted.
+const GITHUB_PERMALINK: &str = "{repository}/blob/{commit}/{path}#L{line}";
+const GITLAB_PERMALINK: &str = "{repository}/-/blob/{commit}/{path}#L{line}";
+
+// where compiler crashes are reported
+const RUST_REPOSITORY: &str = "https://github.com/rust-lang/rust";
+
+// An issue about a diagnostic, ready to be filed wherever it belongs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssueDraft {
+    // e.g. "https://github.com/serde-rs/serde"
+    pub repository: String,
+    pub title: String,
+    pub body: String,
+}
+
+impl IssueDraft {
+    // A draft with the diagnostic as the compiler printed it, where it is,
+    // linked to when it's in the project's own repository, and what it was
+    // built with.
+    pub fn new<R>(
+        runner: &R,
+        project_root: &Path,
+        diag: &RustDiagnostic,
+        rustc_version: Option<&str>,
+        command: &str,
+    ) -> Result<Self, String>
+    where
+        R: ProcessRunner + ?Sized,
+    {
+        let origin = origin(runner, project_root);
+        let repository = repository(project_root, diag)
+            .or_else(|| origin.clone())
+            .ok_or_else(|| "There's no repository to file an issue with.".to_string())?;
+
+        let mut body = String::new();
+        if let Some(location) = diag.location() {
+            let link = Some(&repository)
+                .filter(|_| origin.as_ref() == Some(&repository))
+                .and_then(|repository| blob_link(runner, project_root, repository, diag, None));
+            match link {
+                Some(link) => body.push_str(&format!("At [{}]({})\n\n", location, link)),
+                None => body.push_str(&format!("At {}\n\n", location)),
+            }
+        }
+        body.push_str(&format!("```\n{}```\n\n", diag));
+        if let Some(version) = rustc_version {
+            body.push_str(&format!("- Toolchain: {}\n", version));
+        }
+        body.push_str(&format!("- Command: `{}`\n", command));
+
+        Ok(IssueDraft {
+            repository,
+            title: diag.title(),
+            body,
+        })
+    }
+
+    // The page that opens the draft, from `template` or else the one for
+    // where the repository is hosted.
+    pub fn url(&self, template: Option<&str>) -> String {
+        let template = template.unwrap_or_else(|| {
+            if self.repository.contains("gitlab") {
+                GITLAB_TEMPLATE
+            } else {
+                GITHUB_TEMPLATE
+            }
+        });
+        template
+            .replace("{repository}", self.repository.trim_end_matches('/'))
+            .replace("{title}", &encode(&self.title))
+            .replace("{body}", &encode(&self.body))
+    }
+}
+
+// A link to `diag`'s line in the project's repository, on the code host its
+// origin remote is on, for pasting into reviews. It's to the commit that's
+// checked out, so that it stays put as the branch moves on; `template` is
+// for hosts other than GitHub and GitLab.
+pub fn permalink<R>(
+    runner: &R,
+    project_root: &Path,
+    diag: &RustDiagnostic,
+    template: Option<&str>,
+) -> Result<String, String>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let repository = origin(runner, project_root)
+        .ok_or_else(|| "The project has no origin remote to link to.".to_string())?;
+    blob_link(runner, project_root, &repository, diag, template)
+        .ok_or_else(|| "The diagnostic isn't in a file of the repository.".to_string())
+}
+
+// Where `diag` is better reported than the project: the compiler's own
+// repository for its crashes, or the `repository` in the manifest of the
+// crate the diagnostic is in, e.g. a dependency's.
+fn repository(project_root: &Path, diag: &RustDiagnostic) -> Option<String> {
+    if diag.message.starts_with("internal compiler error") {
+        return Some(RUST_REPOSITORY.to_string());
+    }
+    let file = project_root.join(diag.file.as_ref()?);
+    file.ancestors().skip(1).find_map(|dir| {
+        let contents = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        let manifest = contents.parse::<toml::Value>().ok()?;
+        manifest
+            .get("package")?
+            .get("repository")?
+            .as_str()
+            .map(|url| url.trim_end_matches(".git").to_string())
+    })
+}
+
+// The project's "origin" remote as a web address, e.g. for
+// "git@github.com:avranju/watch-rust-errors.git".
+fn origin<R>(runner: &R, project_root: &Path) -> Option<String>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let url = git::git(runner, project_root, &["remote", "get-url", "origin"]).ok()?;
+    let url = url.trim().trim_end_matches(".git");
+    let url = match url.strip_prefix("git@") {
+        Some(scp) => format!("https://{}", scp.replacen(':', "/", 1)),
+        None => url.to_string(),
+    };
+    Some(url).filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+}
+
+// A link to the diagnostic's line at the commit that's checked out, which
+// stays put as the branch moves on.
+fn blob_link<R>(
+    runner: &R,
+    project_root: &Path,
+    repository: &str,
+    diag: &RustDiagnostic,
+    template: Option<&str>,
+) -> Option<String>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let file = Path::new(diag.file.as_ref()?);
+    let file = file.strip_prefix(project_root).unwrap_or(file);
+    // e.g. a dependency's
+    if file.is_absolute() {
+        return None;
+    }
+    let commit = git::git(runner, project_root, &["rev-parse", "HEAD"]).ok()?;
+    let prefix =
+        git::git(runner, project_root, &["rev-parse", "--show-prefix"]).unwrap_or_default();
+    let template = template.unwrap_or_else(|| {
+        if repository.contains("gitlab") {
+            GITLAB_PERMALINK
+        } else {
+            GITHUB_PERMALINK
+        }
+    });
+    let link = template
+        .replace("{repository}", repository.trim_end_matches('/'))
+        .replace("{commit}", commit.trim())
+        .replace(
+            "{path}",
+            &format!("{}{}", prefix.trim(), file.to_string_lossy()),
+        );
+    Some(match diag.line {
+        Some(line) => link.replace("{line}", &line.to_string()),
+        None => link.replace("#L{line}", "").replace("{line}", ""),
+    })
+}
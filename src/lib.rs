@@ -0,0 +1,53 @@
+pub mod ansi;
+pub mod baseline;
+pub mod bisect;
+pub mod cargo;
+pub mod cascade;
+pub mod compare;
+pub mod config;
+pub mod container;
+// the session bus is reached over a Unix socket
+#[cfg(unix)]
+pub mod dbus;
+pub mod eventlog;
+pub mod explain;
+pub mod export;
+pub mod external;
+pub mod fixer;
+pub mod git;
+pub mod health;
+pub mod heatmap;
+pub mod history;
+pub mod hooks;
+pub mod i18n;
+pub mod ignores;
+pub mod issue;
+pub mod libtest;
+pub mod list_state;
+pub mod listing;
+pub mod lock;
+pub mod logging;
+pub mod lsp;
+pub mod notifications;
+pub mod palette;
+pub mod parser;
+pub mod pathdeps;
+pub mod pipeline;
+pub mod postprocess;
+pub mod power;
+pub mod priority;
+pub mod process;
+pub mod project;
+pub mod quickfix;
+pub mod resume;
+pub mod rust;
+pub mod sandbox;
+pub mod schedule;
+pub mod server;
+pub mod session;
+pub mod shortcuts;
+pub mod snippet;
+pub mod toolchain;
+pub mod validate;
+pub mod watcher;
+pub mod wrapper;
@@ -0,0 +1,197 @@
+use std::fmt::{self, Display};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref REGEX_TEST: Regex = Regex::new(r"^test (.+) \.\.\. (ok|FAILED|ignored)").unwrap();
+    static ref REGEX_SECTION: Regex = Regex::new(r"^---- (.+) stdout ----$").unwrap();
+    static ref REGEX_PANIC: Regex = Regex::new(
+        r"^thread '[^']*'(?: \([0-9]+\))? panicked at (?:'(.*)', )?([^:]+):([0-9]+):([0-9]+):?$"
+    )
+    .unwrap();
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TestFailure {
+    pub name: String,
+    pub message: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TestResult {
+    pub passed: usize,
+    pub ignored: usize,
+    pub failures: Vec<TestFailure>,
+}
+
+impl Display for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "test failed: {}", self.name)?;
+
+        if let Some(file) = self.file.as_ref() {
+            writeln!(
+                f,
+                "  --> {}:{}:{}",
+                file,
+                self.line
+                    .map(|l| l.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                self.column
+                    .map(|l| l.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            )?;
+        }
+
+        if let Some(message) = self.message.as_ref() {
+            writeln!(f, "{}", message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} passed; {} failed; {} ignored",
+            self.passed,
+            self.failures.len(),
+            self.ignored
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonEvent {
+    #[serde(rename = "type")]
+    type_: String,
+    event: String,
+    name: Option<String>,
+    stdout: Option<String>,
+}
+
+enum ParseState {
+    Nothing,
+    // the bool tracks whether we are inside the panic message
+    Failure(TestFailure, bool),
+}
+
+// Extracts test outcomes from the stdout of `cargo test`, in either the human
+// readable format or libtest's `--format json`. Returns `None` if no tests
+// were run at all.
+pub fn parse(output: &str) -> Option<TestResult> {
+    let mut result = TestResult::default();
+    let mut seen = false;
+    let mut state = ParseState::Nothing;
+
+    for line in output.lines() {
+        if line.starts_with('{') {
+            if let Ok(event) = serde_json::from_str::<JsonEvent>(line) {
+                seen |= parse_json_event(event, &mut result);
+            }
+            continue;
+        }
+
+        if let Some(caps) = REGEX_TEST.captures(line) {
+            seen = true;
+            match &caps[2] {
+                "ok" => result.passed += 1,
+                "ignored" => result.ignored += 1,
+                _ => (),
+            }
+            continue;
+        }
+
+        // the captured stdout of a failed test runs until the next test's
+        // section or the summary list of failed tests
+        if let ParseState::Failure(ref mut failure, ref mut in_message) = state {
+            if !REGEX_SECTION.is_match(line) && line != "failures:" {
+                *in_message = parse_panic_line(failure, *in_message, line);
+                continue;
+            }
+        }
+
+        if let ParseState::Failure(failure, _) = state {
+            result.failures.push(failure);
+        }
+        state = match REGEX_SECTION.captures(line) {
+            Some(caps) => ParseState::Failure(
+                TestFailure {
+                    name: caps[1].to_string(),
+                    ..Default::default()
+                },
+                false,
+            ),
+            None => ParseState::Nothing,
+        };
+    }
+
+    if let ParseState::Failure(failure, _) = state {
+        result.failures.push(failure);
+    }
+
+    if seen {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn parse_json_event(event: JsonEvent, result: &mut TestResult) -> bool {
+    if event.type_ != "test" {
+        return false;
+    }
+
+    match event.event.as_str() {
+        "ok" => result.passed += 1,
+        "ignored" => result.ignored += 1,
+        "failed" => {
+            let mut failure = TestFailure {
+                name: event.name.unwrap_or_default(),
+                ..Default::default()
+            };
+            let mut in_message = false;
+            for line in event.stdout.unwrap_or_default().lines() {
+                in_message = parse_panic_line(&mut failure, in_message, line);
+            }
+            result.failures.push(failure);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+// Feeds one line of a failed test's captured output into `failure` and
+// returns whether the following line is still part of the panic message. The
+// location comes from the "thread '..' panicked at" line and the message is
+// everything after it up to the backtrace.
+fn parse_panic_line(failure: &mut TestFailure, in_message: bool, line: &str) -> bool {
+    if let Some(caps) = REGEX_PANIC.captures(line) {
+        failure.message = caps.get(1).map(|m| m.as_str().to_string());
+        failure.file = Some(caps[2].to_string());
+        failure.line = caps[3].parse().ok();
+        failure.column = caps[4].parse().ok();
+
+        // older toolchains put the message on the same line
+        return failure.message.is_none();
+    }
+
+    if !in_message || line.is_empty() || line.starts_with("note: ") || line == "stack backtrace:" {
+        return false;
+    }
+
+    let message = failure.message.get_or_insert_with(String::new);
+    if !message.is_empty() {
+        message.push('\n');
+    }
+    message.push_str(line);
+
+    true
+}
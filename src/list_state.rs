@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::cargo::DiagnosticId;
+use crate::config::Config;
+
+// What the window's results list is narrowed down to and what's opened or
+// folded away in it, as the filters, the codes tab and the list's rows
+// leave it.
+#[derive(Clone, Debug, Default)]
+pub struct ListState {
+    // the module and code the list is narrowed down to, from the overview
+    // and codes tabs
+    pub module_filter: Option<String>,
+    pub code_filter: Option<String>,
+    // whether the codes tab goes by code rather than the most first
+    pub codes_by_name: bool,
+    // whether what's not in the focused file is listed after it
+    pub show_elsewhere: bool,
+    // files whose diagnostics are folded away when grouping by file, and
+    // the codes listing all their locations when grouping by code, kept
+    // across builds
+    pub collapsed_files: HashSet<Option<String>>,
+    pub expanded_codes: HashSet<Option<String>>,
+    // the causes whose consequences are listed under them, and the
+    // diagnostics whose macro backtrace is shown, in the latest results
+    pub expanded_causes: HashSet<DiagnosticId>,
+    pub expanded_macro_traces: HashSet<DiagnosticId>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ListMessage {
+    // `None` shows the diagnostics of every module again
+    FilterModule(Option<String>),
+    // `None` shows the diagnostics of every code again
+    FilterCode(Option<String>),
+    // sorts the codes tab by code, or the most first
+    SortCodes(bool),
+    // opens or closes what's not in the focused file
+    ToggleElsewhere,
+    ToggleFile(Option<String>),
+    ToggleCode(Option<String>),
+    ToggleCascade(DiagnosticId),
+    ToggleMacroTrace(DiagnosticId),
+}
+
+impl ListState {
+    // Returns whether `message` changed which diagnostics are listed, for
+    // the list to start again from the top.
+    pub fn update(&mut self, message: ListMessage) -> bool {
+        match message {
+            ListMessage::FilterModule(module) => {
+                self.module_filter = module;
+                true
+            }
+            ListMessage::FilterCode(code) => {
+                self.code_filter = code;
+                true
+            }
+            ListMessage::SortCodes(by_name) => {
+                self.codes_by_name = by_name;
+                false
+            }
+            ListMessage::ToggleElsewhere => {
+                self.show_elsewhere = !self.show_elsewhere;
+                false
+            }
+            ListMessage::ToggleFile(file) => toggle(&mut self.collapsed_files, file),
+            ListMessage::ToggleCode(code) => toggle(&mut self.expanded_codes, code),
+            ListMessage::ToggleCascade(id) => toggle(&mut self.expanded_causes, id),
+            ListMessage::ToggleMacroTrace(id) => toggle(&mut self.expanded_macro_traces, id),
+        }
+    }
+
+    // For new results, whose diagnostics the ones opened before don't
+    // stand for.
+    pub fn forget_expanded(&mut self) {
+        self.expanded_causes.clear();
+        self.expanded_macro_traces.clear();
+    }
+
+    // For when the results are cleared, leaving nothing to narrow down.
+    pub fn clear(&mut self) {
+        self.forget_expanded();
+        self.module_filter = None;
+        self.code_filter = None;
+    }
+}
+
+// Opens or closes `item`, which doesn't change what's listed.
+fn toggle<T: Eq + Hash>(items: &mut HashSet<T>, item: T) -> bool {
+    if !items.remove(&item) {
+        items.insert(item);
+    }
+    false
+}
+
+// The ways of listing the results that are kept in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListOption {
+    ShowDuplicates,
+    GroupByFile,
+    GroupByCode,
+    ShowChanges,
+    FocusFile,
+    HideBaseline,
+}
+
+impl ListOption {
+    // Turns the option on or off in `config`, returning whether that changed
+    // which diagnostics are listed. Grouping goes by file or by code, not
+    // both.
+    pub fn toggle(self, config: &mut Config) -> bool {
+        match self {
+            // takes effect with the next build
+            ListOption::ShowDuplicates => {
+                config.show_duplicates = !config.show_duplicates;
+                false
+            }
+            ListOption::GroupByFile => {
+                config.group_by_file = !config.group_by_file;
+                config.group_by_code &= !config.group_by_file;
+                false
+            }
+            ListOption::GroupByCode => {
+                config.group_by_code = !config.group_by_code;
+                config.group_by_file &= !config.group_by_code;
+                false
+            }
+            ListOption::ShowChanges => {
+                config.show_changes = !config.show_changes;
+                false
+            }
+            ListOption::FocusFile => {
+                config.focus_file = !config.focus_file;
+                true
+            }
+            ListOption::HideBaseline => {
+                config.hide_baseline = !config.hide_baseline;
+                true
+            }
+        }
+    }
+}
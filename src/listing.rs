@@ -0,0 +1,365 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use crate::cargo::{self, CodeGroup, CompileResult, CrateGroup, DiagnosticId, FileGroup};
+use crate::cascade::Cascades;
+use crate::config::SortOrder;
+use crate::git::ChangedFiles;
+use crate::heatmap;
+use crate::rust::{RustDiagnostic, Type};
+
+// What the results list leaves out of a build's diagnostics and how it
+// arranges the rest: by crate, by file or by code, narrowed down to a
+// module, to the files changed since a ref, to a code or to what the
+// baseline doesn't have, and with the errors that likely follow from another
+// folded under it. Focused on a file, everything else goes into a section
+// of its own after it. Split into panes, each lists only its errors or its
+// warnings.
+#[derive(Clone, Copy)]
+pub struct Listing<'a> {
+    pub project_root: &'a str,
+    pub group_by_file: bool,
+    // one of each code stands in for the rest, and wins over grouping by file
+    pub group_by_code: bool,
+    pub sort_order: SortOrder,
+    pub module_filter: Option<&'a str>,
+    // e.g. "E0308" or "clippy::unwrap_used"
+    pub code_filter: Option<&'a str>,
+    pub changed_files: Option<&'a ChangedFiles>,
+    // while the baseline is hidden, the diagnostics it doesn't have
+    pub new_diagnostics: Option<&'a HashSet<DiagnosticId>>,
+    pub focused_file: Option<&'a str>,
+    // whether the section with what's not in the focused file is open
+    pub show_elsewhere: bool,
+    pub cascades: &'a Cascades,
+    pub expanded_causes: &'a HashSet<DiagnosticId>,
+    pub collapsed_files: &'a HashSet<Option<String>>,
+    // the codes whose every location is listed
+    pub expanded_codes: &'a HashSet<Option<String>>,
+    pub pane: Option<Pane>,
+}
+
+// The two halves of the results list when it's split, errors on top and
+// warnings below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pane {
+    Errors,
+    Warnings,
+}
+
+impl Pane {
+    pub fn contains(self, diag: &RustDiagnostic) -> bool {
+        match self {
+            Pane::Errors => diag.type_ == Type::Error,
+            Pane::Warnings => diag.type_ != Type::Error,
+        }
+    }
+}
+
+impl<'a> Listing<'a> {
+    // The results' crates, or just their diagnostics that the list isn't
+    // narrowed down to leave out.
+    pub fn crate_groups<'r>(&self, result: &'r CompileResult) -> Vec<CrateGroup<'r>> {
+        let mut groups = result.by_crate();
+        for group in groups.iter_mut() {
+            group
+                .diagnostics
+                .retain(|(id, diag)| self.is_listed(*id, diag));
+        }
+        groups.retain(|g| !g.diagnostics.is_empty());
+        groups
+    }
+
+    pub fn file_groups<'r>(&self, result: &'r CompileResult) -> Vec<FileGroup<'r>> {
+        let mut groups = result.by_file();
+        for group in groups.iter_mut() {
+            group
+                .diagnostics
+                .retain(|(id, diag)| self.is_listed(*id, diag));
+            group.errors = group
+                .diagnostics
+                .iter()
+                .filter(|(_, diag)| diag.type_ == Type::Error)
+                .count();
+            group.warnings = group.diagnostics.len() - group.errors;
+        }
+        groups.retain(|g| !g.diagnostics.is_empty());
+        // the files with the most errors first, unless asked to go by path
+        if self.sort_order != SortOrder::File {
+            groups.sort_by_key(|g| (g.file.is_none(), Reverse(g.errors), Reverse(g.warnings)));
+        }
+        groups
+    }
+
+    // The results' codes with the diagnostics of each that are listed, the
+    // one that stands in for the rest first.
+    pub fn code_groups<'r>(&self, result: &'r CompileResult) -> Vec<CodeGroup<'r>> {
+        let mut groups = result.by_code();
+        for group in groups.iter_mut() {
+            group
+                .diagnostics
+                .retain(|(id, diag)| self.is_listed(*id, diag));
+            cargo::sort_diagnostics(&mut group.diagnostics, self.sort_order);
+            group.errors = group
+                .diagnostics
+                .iter()
+                .filter(|(_, diag)| diag.type_ == Type::Error)
+                .count();
+            group.warnings = group.diagnostics.len() - group.errors;
+        }
+        groups.retain(|g| !g.diagnostics.is_empty());
+        groups
+    }
+
+    pub fn is_listed(&self, id: DiagnosticId, diag: &RustDiagnostic) -> bool {
+        let file = diag.file.as_deref();
+        self.in_module_filter(file)
+            && self.in_code_filter(diag.code())
+            && self.in_changed_files(file)
+            && !self.in_hidden_baseline(id)
+            && self.in_focus(file)
+            && self.in_pane(diag)
+    }
+
+    // How many of the results' diagnostics are listed, wherever they are.
+    pub fn count_listed(&self, result: &CompileResult) -> usize {
+        result
+            .diagnostics()
+            .enumerate()
+            .filter(|(i, diag)| self.is_listed(DiagnosticId(*i), diag))
+            .count()
+    }
+
+    pub fn in_code_filter(&self, code: Option<&str>) -> bool {
+        match self.code_filter {
+            Some(filter) => code == Some(filter),
+            None => true,
+        }
+    }
+
+    pub fn in_module_filter(&self, file: Option<&str>) -> bool {
+        match self.module_filter {
+            Some(module) => {
+                file.and_then(|file| heatmap::module_of(self.project_root, file))
+                    .as_deref()
+                    == Some(module)
+            }
+            None => true,
+        }
+    }
+
+    // Diagnostics that aren't in any file are kept, as `ChangedFiles::retain`
+    // does.
+    pub fn in_changed_files(&self, file: Option<&str>) -> bool {
+        match (self.changed_files, file) {
+            (Some(changes), Some(file)) => changes.contains(file),
+            _ => true,
+        }
+    }
+
+    pub fn in_pane(&self, diag: &RustDiagnostic) -> bool {
+        match self.pane {
+            Some(pane) => pane.contains(diag),
+            None => true,
+        }
+    }
+
+    pub fn in_focus(&self, file: Option<&str>) -> bool {
+        match self.focused_file {
+            Some(focused_file) => file == Some(focused_file),
+            None => true,
+        }
+    }
+
+    // While focused on a file, what the list would show from all the others,
+    // sorted for display.
+    pub fn elsewhere<'r>(
+        &self,
+        result: &'r CompileResult,
+    ) -> Vec<(DiagnosticId, &'r RustDiagnostic)> {
+        if self.focused_file.is_none() {
+            return vec![];
+        }
+        let unfocused = Listing {
+            focused_file: None,
+            ..*self
+        };
+        let diags = result
+            .diagnostics()
+            .enumerate()
+            .map(|(i, diag)| (DiagnosticId(i), diag))
+            .filter(|(id, diag)| {
+                unfocused.is_listed(*id, diag) && !self.in_focus(diag.file.as_deref())
+            })
+            .collect();
+        unfocused.arrange(result, diags)
+    }
+
+    fn in_hidden_baseline(&self, id: DiagnosticId) -> bool {
+        match self.new_diagnostics {
+            Some(new_diagnostics) => !new_diagnostics.contains(&id),
+            None => false,
+        }
+    }
+
+    pub fn is_collapsed(&self, file: Option<&str>) -> bool {
+        self.collapsed_files
+            .contains(&file.map(ToString::to_string))
+    }
+
+    pub fn is_expanded(&self, code: Option<&str>) -> bool {
+        self.expanded_codes.contains(&code.map(ToString::to_string))
+    }
+
+    // Sorts a group's diagnostics for display. Errors that likely follow from
+    // another are left out, and only listed right after their cause while it's
+    // expanded, unless the cause itself isn't shown.
+    pub fn arrange<'r>(
+        &self,
+        result: &'r CompileResult,
+        mut diags: Vec<(DiagnosticId, &'r RustDiagnostic)>,
+    ) -> Vec<(DiagnosticId, &'r RustDiagnostic)> {
+        cargo::sort_diagnostics(&mut diags, self.sort_order);
+        if self.cascades.is_empty() {
+            return diags;
+        }
+
+        let mut arranged = vec![];
+        for (id, diag) in diags {
+            let folded = self
+                .cascades
+                .cause_of(id)
+                .and_then(|cause| {
+                    result
+                        .diagnostic(cause)
+                        .map(|diag| self.is_listed(cause, diag))
+                })
+                .unwrap_or(false);
+            if folded {
+                continue;
+            }
+
+            arranged.push((id, diag));
+            if self.expanded_causes.contains(&id) {
+                let consequences = self.cascades.consequences_of(id).iter();
+                arranged.extend(consequences.filter_map(|c| Some((*c, result.diagnostic(*c)?))));
+            }
+        }
+        arranged
+    }
+
+    // The diagnostics in the order the results list shows them, per group
+    // and leaving out collapsed files.
+    pub fn display_order(&self, result: &CompileResult) -> Vec<Vec<DiagnosticId>> {
+        let mut order = if self.group_by_code {
+            // the others are only listed by their locations, and nothing's
+            // folded under a cause
+            self.code_groups(result)
+                .into_iter()
+                .map(|g| g.diagnostics.iter().take(1).map(|(id, _)| *id).collect())
+                .collect::<Vec<_>>()
+        } else {
+            let groups = if self.group_by_file {
+                self.file_groups(result)
+                    .into_iter()
+                    .filter(|g| !self.is_collapsed(g.file))
+                    .map(|g| g.diagnostics)
+                    .collect::<Vec<_>>()
+            } else {
+                self.crate_groups(result)
+                    .into_iter()
+                    .map(|g| g.diagnostics)
+                    .collect()
+            };
+            groups
+                .into_iter()
+                .map(|diags| {
+                    self.arrange(result, diags)
+                        .into_iter()
+                        .map(|(id, _)| id)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        if self.show_elsewhere {
+            let elsewhere = self.elsewhere(result);
+            order.push(elsewhere.into_iter().map(|(id, _)| id).collect());
+        }
+        order
+    }
+
+    // How many rows the diagnostics take up once all of them are shown.
+    pub fn total_rows(&self, result: &CompileResult) -> usize {
+        let elsewhere = match self.focused_file {
+            Some(_) if self.show_elsewhere => 1 + self.elsewhere(result).len(),
+            Some(_) => 1,
+            None => 0,
+        };
+        self.focused_rows(result) + elsewhere
+    }
+
+    fn focused_rows(&self, result: &CompileResult) -> usize {
+        if self.group_by_code {
+            // a header, the diagnostic standing in for the others and, when
+            // expanded, their locations
+            self.code_groups(result)
+                .into_iter()
+                .map(|g| {
+                    if g.diagnostics.len() > 1 && self.is_expanded(g.code) {
+                        3
+                    } else {
+                        2
+                    }
+                })
+                .sum()
+        } else if self.group_by_file {
+            self.file_groups(result)
+                .into_iter()
+                .map(|g| {
+                    1 + if self.is_collapsed(g.file) {
+                        0
+                    } else {
+                        self.arrange(result, g.diagnostics).len()
+                    }
+                })
+                .sum()
+        } else {
+            let groups = self.crate_groups(result);
+            let headers = if groups.iter().any(|g| g.name.is_some()) {
+                groups.len()
+            } else {
+                0
+            };
+            let diagnostics = groups
+                .into_iter()
+                .map(|g| self.arrange(result, g.diagnostics).len())
+                .sum::<usize>();
+            diagnostics + headers
+        }
+    }
+}
+
+// The build's codes with how many diagnostics each has, for triaging them a
+// code at a time: the most first, or in the order of the codes. Diagnostics
+// without one are left out, as there's no code to narrow the list down to.
+pub fn code_counts(result: &CompileResult, by_code: bool) -> Vec<CodeGroup<'_>> {
+    let mut groups = result.by_code();
+    groups.retain(|g| g.code.is_some());
+    if by_code {
+        groups.sort_by_key(|g| g.code);
+    }
+    groups
+}
+
+// A count the way it's shown, with its thousands separated, e.g. "4,213".
+pub fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut shown = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && i % 3 == digits.len() % 3 {
+            shown.push(',');
+        }
+        shown.push(digit);
+    }
+    shown
+}
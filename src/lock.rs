@@ -0,0 +1,238 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+use crate::cargo::CompileResult;
+
+const APP_DIR: &str = "watch-rust-errors";
+const WATCHES_DIR: &str = "watches";
+
+pub enum Acquire {
+    Owned(ProjectLock),
+    // another live process, identified by its pid, is watching the root
+    HeldBy(u32),
+}
+
+// Marks a project root as being watched by this process. The owner publishes
+// its latest results next to the lock so other instances can follow along
+// instead of compiling the same project again.
+pub struct ProjectLock {
+    lock_path: PathBuf,
+    status_path: PathBuf,
+}
+
+impl ProjectLock {
+    // e.g. ~/.local/share/watch-rust-errors/watches
+    pub fn state_dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join(APP_DIR).join(WATCHES_DIR))
+    }
+
+    pub fn acquire<P: AsRef<Path>>(project_root: P) -> Result<Acquire, String> {
+        Self::acquire_in(state_dir()?, project_root)
+    }
+
+    pub fn acquire_in<D, P>(state_dir: D, project_root: P) -> Result<Acquire, String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        let lock = ProjectLock::new(state_dir.as_ref(), project_root.as_ref())?;
+
+        // a second attempt is only needed when a stale lock was cleared
+        for _ in 0..2 {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock.lock_path)
+            {
+                Ok(mut file) => {
+                    write!(file, "{}", process::id()).map_err(|e| format!("{:?}", e))?;
+                    return Ok(Acquire::Owned(lock));
+                }
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                    match read_pid(&lock.lock_path) {
+                        Some(pid) if is_running(pid) => return Ok(Acquire::HeldBy(pid)),
+                        // left behind by an instance that didn't shut down cleanly
+                        _ => remove_if_exists(&lock.lock_path)?,
+                    }
+                }
+                Err(e) => return Err(format!("{:?}", e)),
+            }
+        }
+
+        Err(format!(
+            "Could not lock {}.",
+            project_root.as_ref().display()
+        ))
+    }
+
+    // Claims the root regardless of who holds it. The previous owner notices
+    // the next time it checks `is_held`.
+    pub fn take_over<P: AsRef<Path>>(project_root: P) -> Result<ProjectLock, String> {
+        Self::take_over_in(state_dir()?, project_root)
+    }
+
+    pub fn take_over_in<D, P>(state_dir: D, project_root: P) -> Result<ProjectLock, String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        let lock = ProjectLock::new(state_dir.as_ref(), project_root.as_ref())?;
+        fs::write(&lock.lock_path, process::id().to_string()).map_err(|e| format!("{:?}", e))?;
+        Ok(lock)
+    }
+
+    pub fn is_held(&self) -> bool {
+        read_pid(&self.lock_path) == Some(process::id())
+    }
+
+    pub fn write_status(&self, result: &CompileResult) -> Result<(), String> {
+        let contents = serde_json::to_string(result).map_err(|e| format!("{:?}", e))?;
+
+        // write then rename so followers never see a partially written file
+        let tmp_path = self.status_path.with_extension("json.tmp");
+        fs::write(&tmp_path, contents).map_err(|e| format!("{:?}", e))?;
+        fs::rename(&tmp_path, &self.status_path).map_err(|e| format!("{:?}", e))
+    }
+
+    fn new(state_dir: &Path, project_root: &Path) -> Result<Self, String> {
+        fs::create_dir_all(state_dir).map_err(|e| format!("{:?}", e))?;
+
+        let key = key(project_root);
+        Ok(ProjectLock {
+            lock_path: state_dir.join(format!("{}.lock", key)),
+            status_path: state_dir.join(format!("{}.json", key)),
+        })
+    }
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        // don't pull the lock out from under whoever took over
+        if self.is_held() {
+            let _ = fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+// Reads the results published by the instance that owns a project root.
+pub struct StatusReader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl StatusReader {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Result<Self, String> {
+        Self::new_in(state_dir()?, project_root)
+    }
+
+    pub fn new_in<D, P>(state_dir: D, project_root: P) -> Result<Self, String>
+    where
+        D: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        Ok(StatusReader {
+            path: state_dir
+                .as_ref()
+                .join(format!("{}.json", key(project_root.as_ref()))),
+            modified: None,
+        })
+    }
+
+    // Returns the owner's latest results if they changed since the last poll.
+    pub fn poll(&mut self) -> Result<Option<CompileResult>, String> {
+        let modified = match fs::metadata(&self.path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&self.path).map_err(|e| format!("{:?}", e))?;
+        let result = serde_json::from_str(&contents).map_err(|e| format!("{:?}", e))?;
+        self.modified = Some(modified);
+        Ok(Some(result))
+    }
+}
+
+// What can be told about a project from outside: whether some live process
+// is watching it and the latest results published for it, which may be from
+// a watch that has stopped since.
+pub struct ProjectStatus {
+    pub project_root: String,
+    pub watched_by: Option<u32>,
+    pub latest: Option<CompileResult>,
+    lock_path: PathBuf,
+    reader: StatusReader,
+}
+
+impl ProjectStatus {
+    pub fn new(project_root: &str) -> Result<Self, String> {
+        Self::new_in(state_dir()?, project_root)
+    }
+
+    pub fn new_in<D: AsRef<Path>>(state_dir: D, project_root: &str) -> Result<Self, String> {
+        let state_dir = state_dir.as_ref();
+        Ok(ProjectStatus {
+            project_root: project_root.to_string(),
+            watched_by: None,
+            latest: None,
+            lock_path: state_dir.join(format!("{}.lock", key(Path::new(project_root)))),
+            reader: StatusReader::new_in(state_dir, project_root)?,
+        })
+    }
+
+    // Looks again, returning whether anything changed.
+    pub fn poll(&mut self) -> Result<bool, String> {
+        let watched_by = read_pid(&self.lock_path).filter(|pid| is_running(*pid));
+        let mut changed = watched_by != self.watched_by;
+        self.watched_by = watched_by;
+        if let Some(result) = self.reader.poll()? {
+            self.latest = Some(result);
+            changed = true;
+        }
+        Ok(changed)
+    }
+}
+
+fn state_dir() -> Result<PathBuf, String> {
+    ProjectLock::state_dir().ok_or_else(|| "No state directory available.".to_string())
+}
+
+// Different spellings of the same root should share a lock, and a baseline.
+pub(crate) fn key(project_root: &Path) -> String {
+    let root = fs::canonicalize(project_root).unwrap_or_else(|_| project_root.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    root.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| contents.trim().parse().ok())
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() != ErrorKind::NotFound => Err(format!("{:?}", e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// without a cheap way to ask, assume the owner is still around
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn is_running(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,107 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::cargo::CompileResult;
+
+// where in a project the run log is kept, away from its sources
+pub const LOG_DIR: &str = ".watch-rust-errors";
+const LOG_FILE: &str = "runs.jsonl";
+
+// how big the log gets before it's moved aside for a new one, and how many
+// of those are kept, as runs.1.jsonl, runs.2.jsonl and so on
+pub const MAX_SIZE: u64 = 10 * 1024 * 1024;
+const KEEP: usize = 3;
+
+// A record of every build of a project, for looking back on what broke while
+// nobody was watching. Each run is a line of JSON, e.g.
+//
+//   {"at": 1700000000000, "duration_ms": 2310, "command": "cargo check",
+//    "success": false, "errors": [...], "warnings": [...]}
+//
+// with the diagnostics as `/results` serves them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunLog {
+    pub path: PathBuf,
+    pub max_size: u64,
+}
+
+impl RunLog {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        RunLog {
+            path: project_root.as_ref().join(LOG_DIR).join(LOG_FILE),
+            max_size: MAX_SIZE,
+        }
+    }
+
+    // Appends a run, first moving the log aside if it would grow past
+    // `max_size`.
+    pub fn record(&self, command: &str, result: &CompileResult) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{:?}", e))?;
+        }
+        let mut line = entry(command, result, SystemTime::now()).to_string();
+        line.push('\n');
+
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("{:?}", e))?;
+        // a single write per line, so that a reader never sees half of one
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    // The log moved aside `n` rotations ago; 0 is the current one.
+    pub fn rotated(&self, n: usize) -> PathBuf {
+        if n == 0 {
+            return self.path.clone();
+        }
+        let stem = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!("{}.{}.jsonl", stem, n))
+    }
+
+    fn rotate(&self) -> Result<(), String> {
+        for n in (0..KEEP).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated(n + 1)).map_err(|e| format!("{:?}", e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A run as a line of the log, `at` in milliseconds since the Unix epoch
+// unless the result says when it finished.
+pub fn entry(command: &str, result: &CompileResult, now: SystemTime) -> Value {
+    let at = result
+        .finished_at
+        .unwrap_or(now)
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default();
+    json!({
+        "at": at,
+        "duration_ms": result.duration.map(|d| d.as_millis() as u64),
+        "command": command,
+        "success": result.success,
+        "errors": result.errors,
+        "warnings": result.warnings,
+        "omitted": result.omitted,
+        "triggered_by": result.triggered_by,
+        "trigger_ops": result.trigger_ops,
+    })
+}
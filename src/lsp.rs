@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::cargo::CompileResult;
+use crate::rust::{RustDiagnostic, Type};
+
+// LSP's DiagnosticSeverity
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+// JSON-RPC's code for requests we don't answer
+const METHOD_NOT_FOUND: i32 = -32601;
+
+// what file URIs keep as is besides letters and digits
+const URI_SAFE: &[u8] = b"-._~/:";
+
+// as in `Server`, so a stuck editor can't hold up the watcher
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// the longest header line and the largest message body that's read; an
+// editor sending more is dropped rather than have the app allocate for it
+const MAX_HEADER: usize = 8192;
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+// how many editors can be connected at once; any more are hung up on
+const MAX_EDITORS: usize = 16;
+
+// Turns results into `textDocument/publishDiagnostics` notifications, one per
+// file. Each replaces what the editor has for the file, so files whose
+// diagnostics have all gone since the previous results get an empty one.
+#[derive(Clone, Debug, Default)]
+pub struct Publisher {
+    // the diagnostics last published for each file, by URI
+    published: BTreeMap<String, Vec<Value>>,
+}
+
+impl Publisher {
+    pub fn publish(&mut self, project_root: &Path, result: &CompileResult) -> Vec<Value> {
+        let mut files: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+        for diag in result.diagnostics() {
+            if let Some(file) = diag.file.as_ref() {
+                files
+                    .entry(file_uri(&project_root.join(file)))
+                    .or_default()
+                    .push(diagnostic(diag));
+            }
+        }
+
+        let mut notifications = self
+            .published
+            .keys()
+            .filter(|uri| !files.contains_key(*uri))
+            .map(|uri| notification(uri, &[]))
+            .collect::<Vec<_>>();
+        notifications.extend(files.iter().map(|(uri, diags)| notification(uri, diags)));
+        self.published = files;
+        notifications
+    }
+
+    // What an editor that connects now needs to catch up.
+    pub fn current(&self) -> Vec<Value> {
+        self.published
+            .iter()
+            .map(|(uri, diags)| notification(uri, diags))
+            .collect()
+    }
+}
+
+// `diag` as an LSP `Diagnostic`. Positions are 0-based in LSP. The range
+// ends where the compiler's JSON output says the problem does; its text only
+// says where a problem starts, which leaves the range empty.
+pub fn diagnostic(diag: &RustDiagnostic) -> Value {
+    let position = |line: Option<u32>, column: Option<u32>| {
+        json!({
+            "line": line.unwrap_or(1).saturating_sub(1),
+            "character": column.unwrap_or(1).saturating_sub(1),
+        })
+    };
+    let start = position(diag.line, diag.column);
+    let end = match (diag.end_line, diag.end_column) {
+        (Some(line), Some(column)) => position(Some(line), Some(column)),
+        _ => start.clone(),
+    };
+    let mut value = json!({
+        "range": { "start": start, "end": end },
+        "severity": match diag.type_ {
+            Type::Error => SEVERITY_ERROR,
+            Type::Warning => SEVERITY_WARNING,
+        },
+        "source": env!("CARGO_PKG_NAME"),
+        "message": diag.message,
+    });
+    if let Some(code) = diag.num.as_ref().or(diag.lint.as_ref()) {
+        value["code"] = json!(code);
+    }
+    value
+}
+
+fn notification(uri: &str, diags: &[Value]) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diags },
+    })
+}
+
+// e.g. "file:///src/my%20project/main.rs"
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    if !path.starts_with("/") {
+        uri.push('/');
+    }
+    for byte in path.to_string_lossy().replace('\\', "/").bytes() {
+        if byte.is_ascii_alphanumeric() || URI_SAFE.contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+// Frames `message` with the header the base protocol puts before each one.
+pub fn encode(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut framed = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    framed.extend(body.as_bytes());
+    framed
+}
+
+// Reads the next framed message, or `None` once the other end is done.
+pub fn decode<R: BufRead>(reader: &mut R) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        let read = reader
+            .by_ref()
+            .take(MAX_HEADER as u64 + 1)
+            .read_line(&mut line)
+            .map_err(|e| format!("{:?}", e))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read > MAX_HEADER {
+            return Err("Message header too long.".to_string());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| "Message without a Content-Length.".to_string())?;
+    if length > MAX_MESSAGE {
+        return Err(format!("Message of {} bytes is too large.", length));
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("{:?}", e))?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| format!("{:?}", e))
+}
+
+// Serves diagnostics to editors over TCP, speaking just enough of the
+// language server protocol for them to show what the latest build found:
+// `initialize` and `shutdown` are answered, and diagnostics are published
+// for every result from then on. Clones share the same listener and editors.
+#[derive(Clone)]
+pub struct LspServer {
+    address: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    publisher: Publisher,
+    // the editors that are initialized
+    clients: Vec<TcpStream>,
+}
+
+impl LspServer {
+    // Listens on `address`, e.g. "127.0.0.1:7879"; port 0 picks a free one.
+    pub fn start(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{:?}", e))?;
+        let address = listener.local_addr().map_err(|e| format!("{:?}", e))?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let accepting = shared.clone();
+        let connected = Arc::new(AtomicUsize::new(0));
+        thread::Builder::new()
+            .name("lsp".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if connected.fetch_add(1, Ordering::SeqCst) >= MAX_EDITORS {
+                        connected.fetch_sub(1, Ordering::SeqCst);
+                        let _ = stream.shutdown(Shutdown::Both);
+                        continue;
+                    }
+                    let shared = accepting.clone();
+                    let connected = connected.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = serve(stream, &shared) {
+                            eprintln!("Editor connection failed: {}", err);
+                        }
+                        connected.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(LspServer { address, shared })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Sends the diagnostics of `result` to every editor, dropping those that
+    // have gone away.
+    pub fn publish(&self, project_root: &Path, result: &CompileResult) {
+        let mut shared = self.shared.lock().unwrap();
+        let frames = shared
+            .publisher
+            .publish(project_root, result)
+            .iter()
+            .map(encode)
+            .collect::<Vec<_>>();
+        shared
+            .clients
+            .retain(|mut client| frames.iter().all(|frame| client.write_all(frame).is_ok()));
+    }
+}
+
+fn serve(stream: TcpStream, shared: &Mutex<Shared>) -> Result<(), String> {
+    stream
+        .set_write_timeout(Some(WRITE_TIMEOUT))
+        .map_err(|e| format!("{:?}", e))?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| format!("{:?}", e))?);
+
+    while let Some(message) = decode(&mut reader)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        // notifications other than exit need nothing from us
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None if method == "exit" => break,
+            None => continue,
+        };
+
+        let response = match method {
+            "initialize" => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "capabilities": {},
+                    "serverInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+            }),
+            "shutdown" => json!({ "jsonrpc": "2.0", "id": id, "result": null }),
+            _ => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": "Method not found" },
+            }),
+        };
+
+        // written while holding the lock so it doesn't end up in the middle
+        // of a notification
+        let mut shared = shared.lock().unwrap();
+        (&stream)
+            .write_all(&encode(&response))
+            .map_err(|e| format!("{:?}", e))?;
+        if method == "initialize" {
+            for notification in shared.publisher.current() {
+                (&stream)
+                    .write_all(&encode(&notification))
+                    .map_err(|e| format!("{:?}", e))?;
+            }
+            shared
+                .clients
+                .push(stream.try_clone().map_err(|e| format!("{:?}", e))?);
+        }
+    }
+
+    // publishing drops the editor on its next write
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
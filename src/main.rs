@@ -1,28 +1,150 @@
 #![recursion_limit = "512"]
 
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, AbortHandle};
+use futures::stream::StreamExt;
+use gettextrs::{gettext, ngettext};
 
 use glib::{
     source::{Continue, SourceId},
     MainContext,
 };
-use vgtk::lib::gio::{ActionExt, ApplicationFlags, File, FileExt, SimpleAction};
-use vgtk::lib::glib::Error;
+use vgtk::lib::gdk::{self, DragAction};
+use vgtk::lib::gio::{
+    self, ActionExt, ActionGroupExt, AppInfo, AppLaunchContext, ApplicationExt, ApplicationFlags,
+    File, FileExt, Menu, SimpleAction,
+};
+use vgtk::lib::glib::{DateTime, Error, ToVariant, Variant, VariantTy};
 use vgtk::lib::gtk::{
-    prelude::*, Align, Application, ApplicationWindow, Button, ButtonsType, DialogFlags, Entry,
-    EntryExt, FileChooserAction, FileChooserNative, Grid, HeaderBar, Label, ListBox, ListBoxRow,
-    MessageType, ResponseType, ScrolledWindow, SelectionMode, Window,
+    prelude::*, Adjustment, Align, Application, ApplicationWindow, Box, Button, ButtonsType,
+    Clipboard, DestDefaults, Dialog, DialogFlags, DrawingArea, Entry, EntryExt, Expander,
+    FileChooserAction, FileChooserNative, FlowBox, HeaderBar, Image, Justification, Label, ListBox,
+    ListBoxRow, MenuButton, MessageDialog, MessageType, Notebook, Orientation, PackType, Paned,
+    PositionType, ReliefStyle, ResponseType, ScrolledWindow, SelectionMode, TextBuffer,
+    TextTagTable, TextView, Window,
 };
-use vgtk::{ext::*, gtk, on_signal, run, Component, UpdateAction, VNode};
 use vgtk::scope::Scope;
+use vgtk::{ext::*, gtk, on_signal, Callback, Component, UpdateAction, VNode};
+
+use watch_rust_errors::ansi;
+use watch_rust_errors::baseline::Baseline;
+use watch_rust_errors::cargo::{
+    self, BuildProgress, CodeGroup, CompileResult, DiagnosticId, FetchProgress,
+};
+use watch_rust_errors::cascade::Cascades;
+use watch_rust_errors::compare::{self, Comparison, RunDiff};
+use watch_rust_errors::config::{
+    self, Alert, Config, LocalConfig, ParserKind, ProjectConfig, ResultsLayout, ScrollTo,
+    SortOrder, Theme, WindowState,
+};
+#[cfg(unix)]
+use watch_rust_errors::dbus::{self, DbusService};
+use watch_rust_errors::eventlog;
+use watch_rust_errors::explain;
+use watch_rust_errors::export;
+use watch_rust_errors::external::{self, ExternalSource, Format};
+use watch_rust_errors::git::ChangedFiles;
+use watch_rust_errors::health::{self, Check};
+use watch_rust_errors::heatmap::{self, ModuleStats, Rect};
+use watch_rust_errors::history::{BuildRecord, History, Slowdown};
+use watch_rust_errors::i18n::{self, fill};
+use watch_rust_errors::issue::{self, IssueDraft};
+use watch_rust_errors::list_state::{ListMessage, ListOption, ListState};
+use watch_rust_errors::listing::{self, Listing, Pane};
+use watch_rust_errors::lock::{Acquire, ProjectLock, ProjectStatus, StatusReader};
+use watch_rust_errors::lsp::LspServer;
+use watch_rust_errors::notifications::Notice;
+use watch_rust_errors::palette::Command;
+use watch_rust_errors::parser::{Parser, PassthroughParser};
+use watch_rust_errors::pipeline::{Pipeline, UnitState};
+use watch_rust_errors::priority::{self, Priority};
+use watch_rust_errors::process::SystemRunner;
+use watch_rust_errors::project::{self, FeatureSelection};
+use watch_rust_errors::quickfix;
+use watch_rust_errors::resume::SavedSession;
+use watch_rust_errors::rust::{RustDiagnostic, Type};
+use watch_rust_errors::sandbox;
+use watch_rust_errors::server::Server;
+use watch_rust_errors::session::{SessionConfig, WatchSession};
+use watch_rust_errors::shortcuts::WINDOW_ACCELS;
+use watch_rust_errors::snippet::SnippetCache;
+use watch_rust_errors::toolchain;
+use watch_rust_errors::validate::{self, Problems};
+use watch_rust_errors::watcher::{self, BuildEvent, BuildQueue, Job, Watcher};
+
+mod accessible;
+mod alerts;
+mod app;
+mod bisecting;
+mod close_guard;
+mod command_bar;
+mod command_palette;
+mod fixes;
+mod headless;
+mod project_selector;
+mod queue_list;
+mod results_list;
+mod sparkline;
+mod status_bar;
+mod style;
+
+use crate::accessible::AccessibleNameExt;
+use crate::alerts::Notifiers;
+use crate::app::{App, AppMessage, Launch};
+use crate::bisecting::{BisectMessage, BisectTarget, Bisecting};
+use crate::close_guard::{CloseGuard, CloseGuardExt};
+use crate::command_bar::{feature_menu, profile_menu, target_menu, toolchain_menu, CommandBar};
+use crate::project_selector::ProjectSelector;
+use crate::queue_list::QueueList;
+use crate::results_list::{ListRow, ResultRow, ResultsList};
+use crate::sparkline::SparklineExt;
+use crate::status_bar::StatusBar;
+use crate::style::{
+    Change, CriticalExt, CurrentExt, FlashExt, HeatExt, Radiator, RadiatorExt, Severity,
+    SeverityExt, StyleClassExt, UnitStateExt,
+};
 
-mod cargo;
-mod rust;
-mod watcher;
+// custom responses for the "already watched" dialog
+const TAKE_OVER_RESPONSE: u16 = 1;
+const FOLLOW_RESPONSE: u16 = 2;
 
-use crate::cargo::CompileResult;
-use crate::watcher::Watcher;
+// how many result rows are added at a time
+const RESULTS_PAGE: usize = 200;
+
+// how far down the divider between errors and warnings starts, in pixels
+const SPLIT_POSITION: i32 = 240;
+
+// the size of the overview's treemap, in pixels
+const HEATMAP_WIDTH: f64 = 720.0;
+const HEATMAP_HEIGHT: f64 = 480.0;
+
+// how often the radiator looks at the other projects' published results
+const PROJECTS_POLL_SECS: u32 = 2;
+
+// how wide the labels in front of the project root and command are, so that
+// their entries line up
+const LABEL_CHARS: i32 = 13;
+
+// how long the radiator flashes when the build goes from passing to failing
+// or back
+const RADIATOR_FLASH_SECS: u32 = 5;
+
+// how long the project root and command are left alone after an edit before
+// a running watch restarts with them
+const RESTART_DELAY_SECS: u32 = 1;
+
+// how big a window opens the first time
+const WINDOW_WIDTH: i32 = 800;
+const WINDOW_HEIGHT: i32 = 480;
 
 #[derive(Clone, Debug)]
 enum AppState {
@@ -52,258 +174,5236 @@ impl AppState {
 #[derive(Clone, Debug)]
 enum Message {
     NoOp,
+    ResetConfigSection(String),
     FolderSelected(String),
     SelectFolder,
     FileError(Error),
     PathChanged(String),
+    // the project root or command was edited while watching, and has been
+    // left alone since
+    RestartWatch,
+    ProjectRootEntered,
+    MoveFailed(String),
     CommandChanged(String),
+    ToggleFeature(String),
+    // empty for the one rustup picks
+    SelectToolchain(String),
+    // empty for the host
+    SelectTarget(String),
+    ToggleDefaultFeatures,
     ToggleWatch,
-    Refresh,
-    Exit,
+    // like the Start button, unless the window is watching already
+    StartWatching,
+    AlreadyWatched(u32),
+    TakeOver,
+    FollowWatch,
+    FollowBacon,
+    SelectCargoWatchLog,
+    FollowCargoWatch(String),
+    // whether to keep to the pace the log was written at
+    SelectEventLog(bool),
+    Replay(String, bool),
+    ReadStdin,
+    BuildStarted,
+    BuildTick,
+    InstallingToolchain(String),
+    Fetching(FetchProgress),
+    Compiling(BuildProgress),
+    PipelineLoaded(Pipeline),
+    ExtraRoots(Vec<PathBuf>),
+    ResultsArrived(CompileResult),
+    ScheduledResult(String, CompileResult),
+    ApplyFix(DiagnosticId),
+    // what rustc says about an error code
+    Explain(String),
+    Explained(String, Result<String, String>),
+    SelectExportPath,
+    Export(String),
+    ExportFailed(String),
+    CopyMessage(DiagnosticId),
+    CopyLocation(DiagnosticId),
+    CopyAll,
+    CopySummary,
+    RunNow,
+    ShowMoreResults,
+    NextDiagnostic,
+    PreviousDiagnostic,
+    NextError,
+    PreviousError,
+    OpenCurrent,
+    ClearOutput,
+    ShowCommandPalette,
+    OpenConfig,
+    // opens the diagnostics of the given one's file, or all of them, in an editor
+    OpenQuickfix(Option<DiagnosticId>),
+    // opens the editor right where the diagnostic is
+    OpenDiagnostic(DiagnosticId),
+    QuickfixFailed(String),
+    // finds the editor preset's pane and says where diagnostics would go
+    TestEditor,
+    // shows a directory, or the one a file is in, in the file manager
+    Reveal(String),
+    RevealDiagnostic(DiagnosticId),
+    FileIssue(DiagnosticId),
+    CopyLink(DiagnosticId),
+    Bisect(BisectMessage),
+    // at the given directory, or else the project root
+    OpenTerminal(Option<String>),
+    SelectCompareRef,
+    CompareWith(String),
+    ComparisonReady(Comparison),
+    CompareFailed(String),
+    WatchFailed(String),
+    WatcherStopped(String),
+    HookFailed(String),
+    Warning(String),
+    RestartWatcher,
+    CheckHealth,
+    HealthChecked(Vec<Check>),
+    CloseHealth,
+    WindowResized((i32, i32), bool),
+    TabSwitched(Option<String>),
+    SetTheme(Theme),
+    SetAlert(Alert),
+    SetPriority(Priority),
+    ToggleBuildWhenFocused,
+    ToggleBuildOnAcPower,
+    ToggleMinimizeOnClose,
+    WindowFocused(bool),
+    SetSortOrder(SortOrder),
+    SetScrollTo(ScrollTo),
+    SetResultsLayout(ResultsLayout),
+    TogglePane(Pane),
+    SplitMoved(i32),
+    ToggleOption(ListOption),
+    // a path an editor says it's showing, maybe absolute
+    FocusFile(String),
+    #[cfg(unix)]
+    Dbus(dbus::Call),
+    List(ListMessage),
+    ToggleOnlyChanges,
+    TrustLocalConfig(bool),
+    ChangesListed(ChangedFiles),
+    ListChangesFailed(String),
+    SnapshotBaseline,
+    ClearBaseline,
+    EditEnvironment,
+    EnvironmentEdited(String),
+    SetEnvironment(BTreeMap<String, String>),
+    EditIgnored,
+    SetIgnored(Vec<String>),
+    ToggleOffline,
+    ToggleLogRuns,
+    ToggleWatchIgnored,
+    ToggleWarningsFail,
+    SetParser(ParserKind),
+    SelectProfile(String),
+    FetchDependencies,
+    DependenciesFetched,
+    FetchFailed(String),
+    InvalidEnvironment(String),
+    // look at how every configured project is doing again
+    PollProjects,
+    OpenProject(String),
+    // a build was queued, started, finished or cancelled in any window
+    QueueChanged,
+    ToggleRadiator,
+    RadiatorTick,
+    StopFlashing,
+    Closed,
+}
+
+// What the app hands every window: what to open in it, if anything, and
+// where to say that another project should get a window or that this one
+// was closed.
+#[derive(Clone, Default)]
+struct WindowProps {
+    project_root: Option<String>,
+    // an event log, and whether to keep to its timing
+    replay: Option<(String, bool)>,
+    // the window shows the compiler output piped into the app
+    piped: bool,
+    // `--command` and `--start`, for the window's project
+    command: Option<String>,
+    start: bool,
+    // the session cut short that the window goes on with, for the filters
+    // it had; its project and command come as the ones above
+    resumed: Option<SavedSession>,
+    // shared by every window, so that builds take turns across them
+    build_queue: BuildQueue,
+    on_open_project: Callback<String>,
+    on_closed: Callback<()>,
 }
 
 struct Model {
+    config: Config,
     project_root: String,
     command: String,
-    results: Rc<RefCell<Option<CompileResult>>>,
+    // what kept the last try at watching from starting, until it's edited
+    problems: Problems,
+    results: Option<CompileResult>,
+    // how many of the results' rows to show
+    visible_rows: usize,
     state: AppState,
+    // the watch, and its watcher for settings that change while watching
+    session: Option<WatchSession>,
     watcher: Option<Watcher>,
+    lock: Option<ProjectLock>,
+    // the root the watcher is on, so that a running watch can be moved to
+    // another project
+    watched_root: Option<String>,
+    // the command the watcher runs, and the timeout that restarts it with the
+    // project root and command being edited
+    watched_command: Option<String>,
+    restart_timer: Option<SourceId>,
+    // a restart is under way, until its first build starts
+    restarting: bool,
+    // describes what we're following when someone else does the compiling
+    following: Option<String>,
+    // the toolchain rustup is downloading before the next compile can start
+    installing_toolchain: Option<String>,
+    // the channel the project root's toolchain file pins
+    pinned_toolchain: Option<String>,
+    // the features the project's package declares, to pick from
+    features: Vec<String>,
+    // the toolchains rustup has, read once a project is picked
+    toolchains: Vec<String>,
+    // the targets the project's toolchain has, read with the project
+    targets: Vec<String>,
+    // cargo's progress on getting dependencies during the current build
+    fetch_progress: Option<FetchProgress>,
+    // how far the current build has got compiling
+    build_progress: Option<BuildProgress>,
+    // the crates builds go through and which of them the current one is
+    // done with, when the command streams cargo's JSON messages
+    pipeline: Option<Pipeline>,
+    // the path dependencies' directories outside the project the watch
+    // follows too
+    extra_roots: Vec<PathBuf>,
+    // when the build that is running started, and the timeout that redraws
+    // its elapsed time
+    build_started: Option<Instant>,
+    build_timer: Option<SourceId>,
+    // the diagnostic keyboard navigation is on
+    current: Option<DiagnosticId>,
+    // source around the latest results' diagnostics, read as rows need it
+    snippets: RefCell<SnippetCache>,
+    // what the results list is narrowed down to and what's opened in it
+    list: ListState,
+    // why the watcher thread gave up on its own, until the watch restarts
+    watcher_stopped: Option<String>,
+    // why one of the hooks run after the latest build failed
+    hook_error: Option<String>,
+    // the latest thing that went wrong that the watch and the window go on
+    // despite, until the watch restarts
+    warning: Option<String>,
+    // how much longer than usual the latest build took, if it stood out
+    slowdown: Option<Slowdown>,
+    // the module and code filters of a resumed session, until its watch
+    // starts, and what's written down about the watch for it to be resumed
+    resumed_filters: Option<(Option<String>, Option<String>)>,
+    saved_session: Option<SavedSession>,
+    // what rustc explained error codes as, kept for as long as the window
+    // is open
+    explanations: HashMap<String, String>,
+    // what the latest health check found, while its panel is open
+    health: Option<Vec<Check>>,
+    // the window's size when it isn't maximized, whether it is, and the tab
+    // that's open, to be remembered when it closes
+    window_size: Option<(i32, i32)>,
+    maximized: bool,
+    current_tab: Option<String>,
+    // with the results list split, the panes folded down to their header,
+    // and where the divider between them was dragged to
+    collapsed_panes: HashSet<Pane>,
+    split_position: Option<i32>,
+    // errors that likely follow from another in the latest results
+    cascades: Cascades,
+    // the latest results by module for the overview
+    modules: Vec<ModuleStats>,
+    // the file changed last, or that an editor says it's showing, for when
+    // the list focuses on it
+    focused_file: Option<String>,
+    // the files changed since the project's base ref while only their
+    // diagnostics are listed, looked up again after every build
+    changed_files: Option<ChangedFiles>,
+    // the project's snapshot of diagnostics to hold builds up against, and
+    // the latest results' diagnostics that it doesn't have
+    baseline: Option<Baseline>,
+    new_diagnostics: HashSet<DiagnosticId>,
+    // the settings the project keeps in its own config file, if any
+    local_config: Option<LocalConfig>,
+    // created on first use since GTK isn't up yet when the model is
+    raw_output: Option<TextBuffer>,
+    // the results list's scroll position, handed to the list so that it
+    // outlives re-renders; also created on first use
+    results_scroll: Option<Adjustment>,
+    // the ref being built to compare against, and the latest comparison
+    comparing: Option<String>,
+    comparison: Option<Comparison>,
+    // the bisection running for a diagnostic, if any
+    bisecting: Bisecting,
+    // how the latest results differ from the previous build's
+    since_previous: Option<RunDiff>,
+    // the latest result of each scheduled job, by name
+    scheduled: BTreeMap<String, CompileResult>,
+    // the builds in the build queue, of every window
+    jobs: Vec<Job>,
+    // where word of failed builds and scheduled findings goes
+    notifiers: Notifiers,
+    // every configured project as the radiator tab shows it, when there's
+    // more than the one in this window, and the timeout that looks again
+    projects: Vec<ProjectStatus>,
+    projects_poll: Option<SourceId>,
+    // whether the window is showing the fullscreen radiator, the timeout
+    // that keeps its times current, and while it flashes, the timeout that
+    // stops it
+    radiator: bool,
+    radiator_timer: Option<SourceId>,
+    radiator_flash: Option<SourceId>,
+    // when the build last went from passing to failing or back
+    status_changed: Option<SystemTime>,
+    // whether the window is the one in the foreground
+    focused: bool,
+    props: WindowProps,
+    // the builds since watching started
+    history: History,
+    // serve results to other tools and editors when the config asks for
+    // it; started with the first watch and kept for the ones after
+    server: Option<Server>,
+    lsp_server: Option<LspServer>,
+    // the window's service on the session bus, when the config asks for it
+    // and no other window has it
+    #[cfg(unix)]
+    dbus: Option<DbusService>,
     receiver_id: Option<SourceId>,
+    // stops handing the session's events to the update loop
+    session_events: Option<AbortHandle>,
+    // started with `--safe-mode`: on default settings that are never saved,
+    // and with the sections of the config file that can be reset
+    safe_mode: bool,
+    config_sections: Vec<String>,
     scope: Option<Scope<Self>>,
 }
 
 impl Default for Model {
     fn default() -> Self {
+        let safe_mode = safe_mode_requested();
+        let config = if safe_mode {
+            Config::default()
+        } else {
+            Config::load()
+        };
         Model {
+            notifiers: Notifiers::new(&config.notification_sinks(), config.quiet_hours),
+            projects: vec![],
+            projects_poll: None,
+            radiator: false,
+            radiator_timer: None,
+            radiator_flash: None,
+            status_changed: None,
+            focused: true,
+            props: WindowProps::default(),
+            config,
             project_root: "".to_string(),
             command: "cargo check".to_string(),
-            results: Rc::new(RefCell::new(None)),
+            problems: Problems::default(),
+            results: None,
+            visible_rows: RESULTS_PAGE,
             state: AppState::default(),
+            session: None,
             watcher: None,
+            lock: None,
+            watched_root: None,
+            watched_command: None,
+            restart_timer: None,
+            restarting: false,
+            following: None,
+            installing_toolchain: None,
+            pinned_toolchain: None,
+            features: vec![],
+            toolchains: vec![],
+            targets: vec![],
+            fetch_progress: None,
+            build_progress: None,
+            pipeline: None,
+            extra_roots: vec![],
+            build_started: None,
+            build_timer: None,
+            current: None,
+            snippets: RefCell::new(SnippetCache::default()),
+            list: ListState::default(),
+            collapsed_panes: HashSet::new(),
+            split_position: None,
+            watcher_stopped: None,
+            hook_error: None,
+            warning: None,
+            slowdown: None,
+            resumed_filters: None,
+            saved_session: None,
+            explanations: HashMap::new(),
+            health: None,
+            window_size: None,
+            maximized: false,
+            current_tab: None,
+            cascades: Cascades::default(),
+            modules: vec![],
+            focused_file: None,
+            changed_files: None,
+            baseline: None,
+            new_diagnostics: HashSet::new(),
+            local_config: None,
+            raw_output: None,
+            results_scroll: None,
+            comparing: None,
+            comparison: None,
+            bisecting: Bisecting::default(),
+            since_previous: None,
+            scheduled: BTreeMap::new(),
+            jobs: vec![],
+            history: History::default(),
+            server: None,
+            lsp_server: None,
+            #[cfg(unix)]
+            dbus: None,
             receiver_id: None,
+            session_events: None,
+            safe_mode,
+            config_sections: if safe_mode { config_sections() } else { vec![] },
             scope: None,
         }
     }
 }
 
 impl Model {
-    fn render_results<'a>(&'a self) -> impl Iterator<Item = VNode<Model>> + 'a {
-        self.results
-            .borrow()
-            .clone()
-            .into_iter()
-            .flat_map(|result| {
-                let output = if result.success {
-                    "Compile succeeded.".to_string()
-                } else {
-                    "Compile failed.".to_string()
-                };
+    fn start_watching(&mut self) -> Result<(), String> {
+        if self.server.is_none() {
+            if let Some(address) = self.config.serve.as_ref() {
+                let server = Server::start(address)?;
+                server.allow_origins(self.config.serve_origins.clone());
+                // editors post what they show from the server's threads
+                let (tx, rx) = mpsc::unbounded();
+                server.on_focus(move |file| {
+                    let _ = tx.unbounded_send(file);
+                });
+                let scope = self.scope.as_ref().unwrap().clone();
+                MainContext::ref_thread_default().spawn_local(rx.for_each(move |file| {
+                    scope.send_message(Message::FocusFile(file));
+                    future::ready(())
+                }));
+                self.server = Some(server);
+            }
+        }
+        if self.lsp_server.is_none() {
+            if let Some(address) = self.config.lsp.as_ref() {
+                self.lsp_server = Some(LspServer::start(address)?);
+            }
+        }
 
-                result
-                    .errors
-                    .into_iter()
-                    .map(|d| d.to_string())
-                    .chain(result.warnings.into_iter().map(|d| d.to_string()))
-                    .chain(vec![output])
+        let project = self.project();
+        let mut config = SessionConfig::new(&self.project_root, &self.command);
+        config.parser = self.parser(&project);
+        config.env = project.env_vars();
+        config.post_processor = project.post_processor();
+        config.post_build = project.post_build_hooks();
+        config.max_diagnostics = project.max_diagnostics();
+        config.build_timeout = project.build_timeout();
+        config.success_policy = project.success_policy();
+        config.respect_ignores = !project.watch_ignored;
+        config.process = priority::runner(
+            self.config.build_priority,
+            project.runner(&self.project_root),
+        );
+        config.held = self.held();
+        config.ac_power_only = self.config.build_only_on_ac_power;
+        config.log_runs = project.log_runs;
+        config.jobs = project.jobs;
+        config.server = self.server.clone();
+        config.lsp_server = self.lsp_server.clone();
+        config.event_log = self.config.event_log.as_ref().map(PathBuf::from);
+        config.build_hooks = !self.safe_mode;
+        self.props
+            .build_queue
+            .set_parallelism(self.config.max_parallel_builds);
+        config.build_queue = Some(self.props.build_queue.clone());
+        let (session, events) = WatchSession::start(config)?;
+        self.watcher = Some(session.watcher().clone());
+        self.session = Some(session);
+        self.watched_root = Some(self.project_root.clone());
+        self.watched_command = Some(self.command.clone());
+        self.remember_command();
+
+        // hand results to the update loop so every arrival re-renders the view
+        let scope = self.scope.as_ref().unwrap().clone();
+        let (receiving, handle) = future::abortable(events.for_each(move |event| {
+            scope.send_message(event_message(event));
+            future::ready(())
+        }));
+        MainContext::ref_thread_default().spawn_local(async move {
+            let _ = receiving.await;
+        });
+        self.session_events = Some(handle);
+
+        self.state = AppState::Watching;
+        Ok(())
+    }
+
+    // Takes the app's name on the session bus, handing what scripts call
+    // for to the update loop. Another window or instance having it already
+    // isn't worth more than a warning.
+    #[cfg(unix)]
+    fn start_dbus(&mut self, scope: &Scope<Self>) {
+        let service = match DbusService::start() {
+            Ok(service) => service,
+            Err(err) => {
+                scope.send_message(Message::Warning(format!("Not on D-Bus: {}", err)));
+                return;
+            }
+        };
+        let (tx, rx) = mpsc::unbounded();
+        service.on_call(move |call| {
+            let _ = tx.unbounded_send(call);
+        });
+        let scope = scope.clone();
+        MainContext::ref_thread_default().spawn_local(rx.for_each(move |call| {
+            scope.send_message(Message::Dbus(call));
+            future::ready(())
+        }));
+        self.dbus = Some(service);
+    }
+
+    // Plays a logged session back in place of watching, as fast as the window
+    // keeps up or at the pace it was logged at.
+    fn start_replay(&mut self, path: &str, original_timing: bool) -> Result<(), String> {
+        let events = eventlog::read(path)?;
+        let (tx, rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            eventlog::replay(events, original_timing, |event| {
+                tx.unbounded_send(event).is_ok()
             })
-            .map(|result| {
-                let label = format!("<span font_family=\"monospace\">{}</span>", result);
-                gtk! {
-                    <ListBoxRow>
-                        <Label label=label use_markup=true halign=Align::Start />
-                    </ListBoxRow>
-                }
+        });
+
+        let scope = self.scope.as_ref().unwrap().clone();
+        let (receiving, handle) = future::abortable(rx.for_each(move |event| {
+            scope.send_message(event_message(event));
+            future::ready(())
+        }));
+        MainContext::ref_thread_default().spawn_local(async move {
+            let _ = receiving.await;
+        });
+        self.session_events = Some(handle);
+
+        self.state = AppState::Watching;
+        self.following = Some(gettext("Replaying an event log"));
+        Ok(())
+    }
+
+    // Shows the compiler output piped into the app instead of watching, for
+    // builds that something else runs. The window's project says where the
+    // files it mentions are.
+    fn start_reading_stdin(&mut self) {
+        let parser = self.parser(&self.project());
+        let (tx, rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let read = external::read_piped(stdin.lock(), parser.as_ref(), |event| {
+                tx.unbounded_send(event).is_ok()
+            });
+            if let Err(err) = read {
+                let _ = tx.unbounded_send(BuildEvent::Stopped(err));
+            }
+        });
+
+        let scope = self.scope.as_ref().unwrap().clone();
+        let (receiving, handle) = future::abortable(rx.for_each(move |event| {
+            scope.send_message(event_message(event));
+            future::ready(())
+        }));
+        MainContext::ref_thread_default().spawn_local(async move {
+            let _ = receiving.await;
+        });
+        self.session_events = Some(handle);
+
+        self.state = AppState::Watching;
+        self.following = Some(gettext("Reading piped output"));
+    }
+
+    // Looks at what the project root holds, switching to the command of the
+    // project's profile or the one its config file asks for once trusted, or
+    // else the one that suits it unless the user typed their own.
+    fn detect_project(&mut self) {
+        let info = project::detect(&self.project_root);
+        // safe mode is for when something's wrong with a config, which the
+        // project's own might be
+        self.local_config = if self.safe_mode {
+            None
+        } else {
+            LocalConfig::load(&self.project_root).unwrap_or_else(|err| {
+                self.warn(format!("Failed to load the project's config: {}", err));
+                None
             })
+        };
+        let project = self.config.project(&self.project_root);
+        let command = project
+            .current_profile()
+            .map(|profile| profile.command)
+            .or_else(|| {
+                self.local_config
+                    .as_ref()
+                    .and_then(|local| local.command_for(&project))
+                    .map(ToString::to_string)
+            });
+        if let Some(command) = command {
+            if let Some(watcher) = self.watcher.as_ref() {
+                watcher.set_command(&command);
+            }
+            self.command = command;
+        } else if let Some(command) = info.default_command() {
+            if project::is_default_command(&self.command) && self.command != command {
+                self.command = command.to_string();
+                if let Some(watcher) = self.watcher.as_ref() {
+                    watcher.set_command(command);
+                }
+            }
+        }
+        self.pinned_toolchain = info.toolchain;
+        self.features = info.features;
+        if self.toolchains.is_empty() {
+            self.toolchains = toolchain::installed_with(&SystemRunner, &self.project_root, &[]);
+        }
+        self.list_targets();
+        // another project has changes of its own, if it wants them listed
+        self.changed_files = None;
+        self.list_changes();
+        self.baseline = Baseline::load(&self.project_root).unwrap_or_else(|err| {
+            self.warn(format!("Failed to load baseline: {}", err));
+            None
+        });
+        self.mark_new();
     }
-}
 
-impl Component for Model {
-    type Message = Message;
-    type Properties = ();
+    fn mark_new(&mut self) {
+        self.new_diagnostics = match (self.baseline.as_ref(), self.results.as_ref()) {
+            (Some(baseline), Some(result)) => baseline.new_diagnostics(result),
+            _ => HashSet::new(),
+        };
+    }
 
-    fn init(&mut self, scope: Scope<Self>) {
-        self.scope = Some(scope);
+    // Finds out in the background which files changed since the project's
+    // base ref, when only their diagnostics are to be listed.
+    fn list_changes(&self) {
+        let project = self.config.project(&self.project_root);
+        if !project.only_changes || self.project_root.is_empty() {
+            return;
+        }
+        let project_root = self.project_root.clone();
+        let base = project.base_ref().to_string();
+
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = tx.send(ChangedFiles::list(&project_root, &base));
+        });
+        let scope = self.scope.as_ref().unwrap().clone();
+        MainContext::ref_thread_default().spawn_local(async move {
+            scope.send_message(match rx.await {
+                Ok(Ok(changes)) => Message::ChangesListed(changes),
+                Ok(Err(err)) => Message::ListChangesFailed(err),
+                Err(err) => Message::ListChangesFailed(format!("{:?}", err)),
+            });
+        });
     }
 
-    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
-        match msg {
-            Message::NoOp => UpdateAction::None,
+    // Points a running watch at the project root that was just entered. The
+    // watcher keeps going; only what belongs to a project is swapped out.
+    fn move_watch(&mut self) {
+        let watcher = match self.watcher.as_ref() {
+            Some(watcher) => watcher.clone(),
+            None => return,
+        };
+        if self.watched_root.as_ref() == Some(&self.project_root) {
+            return;
+        }
+        let moved = self
+            .relock()
+            .and_then(|_| watcher.set_project_root(&self.project_root));
+        if let Err(err) = moved {
+            self.scope
+                .as_ref()
+                .unwrap()
+                .send_message(Message::MoveFailed(err));
+            return;
+        }
+        self.watched_root = Some(self.project_root.clone());
+        #[cfg(unix)]
+        if let Some(dbus) = self.dbus.as_ref() {
+            dbus.set_watching(Some(&self.project_root));
+        }
 
-            Message::FileError(error) => UpdateAction::defer(async move {
-                vgtk::message_dialog(
-                    vgtk::current_window().as_ref(),
-                    DialogFlags::empty(),
-                    MessageType::Error,
-                    ButtonsType::Ok,
-                    true,
-                    format!("<b>AN ERROR HAS OCCURRED!</b>\n\n{}", error),
-                )
-                .await;
-                Message::NoOp
-            }),
+        let project = self.project();
+        watcher.set_env(project.env_vars());
+        watcher.set_parser(self.parser(&project));
+        watcher.set_post_processor(project.post_processor());
+        watcher.set_post_build(project.post_build_hooks());
+        watcher.set_max_diagnostics(project.max_diagnostics());
+        watcher.set_build_timeout(project.build_timeout());
+        watcher.set_success_policy(project.success_policy());
+        watcher.set_respect_ignores(!project.watch_ignored);
+        watcher.set_log_runs(project.log_runs);
+        self.schedule_jobs();
 
-            Message::SelectFolder => UpdateAction::defer(async {
-                match select_folder().await {
-                    Ok(Some(file)) => Message::FolderSelected(
-                        file.get_path()
-                            .and_then(|p| p.into_os_string().into_string().ok())
-                            .unwrap_or_else(|| "".to_string()),
-                    ),
-                    Ok(None) => Message::NoOp,
-                    Err(err) => Message::FileError(err),
+        self.clear_output();
+        self.comparison = None;
+        self.scheduled.clear();
+        self.history.clear();
+        thread::spawn(move || watcher.inject(&[]));
+    }
+
+    // Swaps the lock on the old project root for one on the new root, failing
+    // if someone else is watching it.
+    fn relock(&mut self) -> Result<(), String> {
+        if !Path::new(&self.project_root).is_dir() {
+            return Err(fill(
+                gettext("{} is not a directory."),
+                &[&self.project_root],
+            ));
+        }
+
+        // the old lock is only given up once the new one is held
+        match ProjectLock::acquire(&self.project_root) {
+            Ok(Acquire::Owned(lock)) => self.lock = Some(lock),
+            Ok(Acquire::HeldBy(pid)) => {
+                return Err(fill(
+                    gettext("{0} is already being watched by process {1}."),
+                    &[&self.project_root, &pid],
+                ))
+            }
+            Err(err) => {
+                self.warn(format!("Failed to lock project root: {}", err));
+                self.lock = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_following(&mut self) -> Result<(), String> {
+        let mut reader = StatusReader::new(&self.project_root)?;
+        self.start_polling(
+            gettext("Following another instance (read-only)"),
+            move || reader.poll(),
+        );
+        Ok(())
+    }
+
+    fn start_following_external(&mut self, mut source: ExternalSource) {
+        let subtitle = match source.format() {
+            Format::Bacon => gettext("Following bacon (read-only)"),
+            Format::CargoWatch => gettext("Following cargo watch (read-only)"),
+        };
+        self.start_polling(subtitle, move || source.poll());
+    }
+
+    // Checks for new results every second; used when someone else is doing
+    // the compiling.
+    fn start_polling<F>(&mut self, subtitle: String, mut poll: F)
+    where
+        F: FnMut() -> Result<Option<CompileResult>, String> + 'static,
+    {
+        let scope = self.scope.as_ref().unwrap().clone();
+        self.receiver_id = Some(glib::timeout_add_seconds_local(1, move || {
+            match poll() {
+                Ok(Some(result)) => scope.send_message(Message::ResultsArrived(result)),
+                Ok(None) => {}
+                Err(err) => {
+                    scope.send_message(Message::Warning(format!("Failed to read results: {}", err)))
                 }
-            }),
+            }
 
-            Message::FolderSelected(path) => {
-                self.project_root = path;
-                UpdateAction::Render
+            Continue(true)
+        }));
+
+        self.state = AppState::Watching;
+        self.following = Some(subtitle);
+    }
+
+    // Reports a failed start, making sure no half started watch is left behind.
+    fn watch_started(&mut self, outcome: Result<(), String>) -> UpdateAction<Self> {
+        if let Err(err) = outcome {
+            let _ = self.stop_watching();
+            self.scope
+                .as_ref()
+                .unwrap()
+                .send_message(Message::WatchFailed(err));
+        } else {
+            #[cfg(unix)]
+            if let (Some(dbus), AppState::Watching) = (self.dbus.as_ref(), &self.state) {
+                dbus.set_watching(Some(&self.project_root));
+            }
+            if let (Some((module, code)), AppState::Watching) =
+                (self.resumed_filters.take(), &self.state)
+            {
+                self.list.module_filter = module;
+                self.list.code_filter = code;
             }
+            self.save_session();
+            if !self.config.onboarded {
+                self.config.onboarded = true;
+                self.save_config();
+            }
+        }
+        UpdateAction::Render
+    }
 
-            Message::ToggleWatch => {
-                self.state = match self.state {
-                    AppState::Watching => {
-                        // stop the watcher (this may not actually stop the watcher)
-                        self.watcher.take().unwrap().try_stop();
-
-                        // get rid of the receiver
-                        let context = MainContext::ref_thread_default();
-                        let source = context
-                            .find_source_by_id(&self.receiver_id.take().unwrap())
-                            .unwrap();
-                        source.destroy();
+    // Tears down whatever parts of a watch exist. Always leaves the model idle,
+    // even if the receiver's source has already gone away.
+    fn stop_watching(&mut self) -> Result<(), String> {
+        self.state = AppState::Idle;
+        self.following = None;
+        self.watcher_stopped = None;
+        self.warning = None;
+        self.installing_toolchain = None;
+        self.fetch_progress = None;
+        self.build_progress = None;
+        self.pipeline = None;
+        self.extra_roots.clear();
+        self.stop_build_timer();
+        #[cfg(unix)]
+        if let Some(dbus) = self.dbus.as_ref() {
+            dbus.set_watching(None);
+        }
 
-                        // clear output
-                        self.results.borrow_mut().take();
+        // stop the watcher, and drop whatever it still reports
+        self.watcher = None;
+        if let Some(mut session) = self.session.take() {
+            session.stop();
+        }
+        if let Some(handle) = self.session_events.take() {
+            handle.abort();
+        }
+        self.lock = None;
+        self.watched_root = None;
+        self.watched_command = None;
+        self.restarting = false;
+        self.cancel_restart();
 
-                        AppState::Idle
-                    }
+        // clear output
+        self.clear_output();
+        self.comparison = None;
+        self.scheduled.clear();
+        self.history.clear();
+        self.save_session();
 
-                    AppState::Idle => {
-                        let (sender, receiver) = MainContext::channel(Default::default());
-                        self.watcher = {
-                            let mut watcher =
-                                Watcher::new(&self.project_root, &self.command, sender)
-                                    .expect("Failed to create watcher.");
+        // get rid of the receiver
+        let receiver_id = match self.receiver_id.take() {
+            Some(receiver_id) => receiver_id,
+            None => return Ok(()),
+        };
+        let context = MainContext::ref_thread_default();
+        match context.find_source_by_id(&receiver_id) {
+            Some(source) => {
+                source.destroy();
+                Ok(())
+            }
+            None => Err(gettext("The results channel was already closed.")),
+        }
+    }
 
-                            watcher.start();
+    // Writes down what the watch is doing as that changes, for the app to
+    // offer to go on with it should it end without stopping the watch.
+    fn save_session(&mut self) {
+        let session = match (&self.state, &self.following, &self.watched_root) {
+            (AppState::Watching, None, Some(root)) => {
+                let command = self.watched_command.as_deref().unwrap_or(&self.command);
+                let mut session = SavedSession::new(root, command);
+                session.module_filter = self.list.module_filter.clone();
+                session.code_filter = self.list.code_filter.clone();
+                Some(session)
+            }
+            _ => None,
+        };
+        if session == self.saved_session {
+            return;
+        }
+        if let Some(saved) = self.saved_session.take() {
+            let moved =
+                session.as_ref().map(|session| &session.project_root) != Some(&saved.project_root);
+            if moved {
+                if let Err(err) = SavedSession::remove(&saved.project_root) {
+                    self.warn(format!(
+                        "Failed to forget the session on {}: {}",
+                        saved.project_root, err
+                    ));
+                }
+            }
+        }
+        if let Some(session) = session.as_ref() {
+            if let Err(err) = session.save() {
+                self.warn(format!("Failed to save the session: {}", err));
+            }
+        }
+        self.saved_session = session;
+    }
 
-                            Some(watcher)
-                        };
+    fn clear_output(&mut self) {
+        self.results = None;
+        self.current = None;
+        self.cascades = Cascades::default();
+        self.list.clear();
+        self.visible_rows = RESULTS_PAGE;
+        self.modules.clear();
+        self.new_diagnostics.clear();
+        self.since_previous = None;
+        self.slowdown = None;
+        self.set_raw_output("");
+    }
 
-                        let results = self.results.clone();
-                        let scope = self.scope.as_ref().unwrap().clone();
-                        self.receiver_id = Some(receiver.attach(None, move |result| {
-                            // add the results to UI
-                            *results.borrow_mut() = Some(result);
-                            scope.send_message(Message::Refresh);
+    // How the results list is narrowed down and arranged.
+    fn listing(&self) -> Listing<'_> {
+        Listing {
+            project_root: &self.project_root,
+            group_by_file: self.config.group_by_file,
+            group_by_code: self.config.group_by_code,
+            sort_order: self.config.sort_order,
+            module_filter: self.list.module_filter.as_deref(),
+            code_filter: self.list.code_filter.as_deref(),
+            changed_files: self.changed_files.as_ref(),
+            new_diagnostics: Some(&self.new_diagnostics)
+                .filter(|_| self.config.hide_baseline && self.baseline.is_some()),
+            focused_file: self
+                .focused_file
+                .as_deref()
+                .filter(|_| self.config.focus_file),
+            show_elsewhere: self.list.show_elsewhere,
+            cascades: &self.cascades,
+            expanded_causes: &self.list.expanded_causes,
+            collapsed_files: &self.list.collapsed_files,
+            expanded_codes: &self.list.expanded_codes,
+            pane: None,
+        }
+    }
 
-                            Continue(true)
-                        }));
+    // The panes the results list shows, top to bottom: the one list, or
+    // those of errors and warnings that aren't collapsed.
+    fn shown_panes(&self) -> Vec<Option<Pane>> {
+        match self.config.results_layout {
+            ResultsLayout::Single => vec![None],
+            ResultsLayout::Split => [Pane::Errors, Pane::Warnings]
+                .iter()
+                .filter(|pane| !self.collapsed_panes.contains(pane))
+                .map(|pane| Some(*pane))
+                .collect(),
+        }
+    }
 
-                        AppState::Watching
-                    }
-                };
-                UpdateAction::Render
+    // Moves keyboard navigation to the next or previous diagnostic, staying
+    // put at either end of the list.
+    fn select_next(&mut self, forward: bool, errors_only: bool) {
+        let result = match self.results.as_ref() {
+            Some(result) => result,
+            None => return,
+        };
+        let groups = self
+            .shown_panes()
+            .into_iter()
+            .flat_map(|pane| {
+                Listing {
+                    pane,
+                    ..self.listing()
+                }
+                .display_order(result)
+            })
+            .collect::<Vec<_>>();
+        let order = groups.iter().flatten().copied().collect::<Vec<_>>();
+        let current = self
+            .current
+            .and_then(|current| order.iter().position(|id| *id == current));
+
+        let mut candidates = order.iter().enumerate().filter(|(_, id)| {
+            !errors_only
+                || result
+                    .diagnostic(**id)
+                    .map(|d| d.type_ == Type::Error)
+                    .unwrap_or(false)
+        });
+        let next = if forward {
+            candidates.find(|(index, _)| current.map(|c| *index > c).unwrap_or(true))
+        } else {
+            candidates
+                .filter(|(index, _)| current.map(|c| *index < c).unwrap_or(true))
+                .last()
+        };
+
+        if let Some((index, id)) = next {
+            self.current = Some(*id);
+            // the row has to exist to be focused; every group may have a header
+            self.visible_rows = self.visible_rows.max(index + 1 + groups.len());
+        }
+    }
+
+    // What keeps the project root or command from being watched, if
+    // anything.
+    fn check_problems(&self) -> Problems {
+        let project = self.project();
+        if project.container(&self.project_root).is_some() {
+            validate::check_in_container(&self.project_root, &self.command)
+        } else {
+            validate::check(&self.project_root, &self.command, &project.env_vars())
+        }
+    }
+
+    // Restarts a running watch with the project root and command as they're
+    // being edited, once they've been left alone for a moment.
+    fn schedule_restart(&mut self) {
+        if self.watcher.is_none() {
+            return;
+        }
+        self.cancel_restart();
+        let scope = self.scope.as_ref().unwrap().clone();
+        self.restart_timer = Some(glib::timeout_add_seconds_local(
+            RESTART_DELAY_SECS,
+            move || {
+                scope.send_message(Message::RestartWatch);
+                Continue(false)
+            },
+        ));
+    }
+
+    fn cancel_restart(&mut self) {
+        if let Some(timer) = self.restart_timer.take() {
+            if let Some(source) = MainContext::ref_thread_default().find_source_by_id(&timer) {
+                source.destroy();
             }
+        }
+    }
 
-            Message::PathChanged(path) => {
-                self.project_root = path;
-                UpdateAction::None
+    // Points the watch at the project root and command that were edited, and
+    // builds with them. Problems with either are pointed out instead, leaving
+    // the watch as it was.
+    fn restart_watch(&mut self) -> UpdateAction<Self> {
+        let watcher = match self.watcher.clone() {
+            Some(watcher) => watcher,
+            None => return UpdateAction::None,
+        };
+        self.problems = self.check_problems();
+        if !self.problems.is_empty() {
+            return UpdateAction::Render;
+        }
+
+        let moved = self.watched_root.as_ref() != Some(&self.project_root);
+        if moved {
+            self.detect_project();
+        }
+        let command_changed = self.watched_command.as_ref() != Some(&self.command);
+        watcher.set_command(&self.command);
+        self.watched_command = Some(self.command.clone());
+        if command_changed {
+            self.remember_command();
+        }
+        self.restarting = moved || command_changed;
+        if moved {
+            self.move_watch();
+        } else if command_changed {
+            // results of the old command say nothing about the new one
+            self.clear_output();
+            thread::spawn(move || watcher.inject(&[]));
+        }
+        UpdateAction::Render
+    }
+
+    fn stop_build_timer(&mut self) {
+        self.build_started = None;
+        if let Some(timer) = self.build_timer.take() {
+            if let Some(source) = MainContext::ref_thread_default().find_source_by_id(&timer) {
+                source.destroy();
             }
+        }
+    }
 
-            Message::CommandChanged(command) => {
-                self.command = command;
-                UpdateAction::None
+    fn stop_radiator(&mut self) {
+        self.radiator = false;
+        for timer in self
+            .radiator_timer
+            .take()
+            .into_iter()
+            .chain(self.radiator_flash.take())
+        {
+            if let Some(source) = MainContext::ref_thread_default().find_source_by_id(&timer) {
+                source.destroy();
             }
+        }
+    }
+
+    // Flashes the radiator for a while, starting over if it already is.
+    fn flash_radiator(&mut self) {
+        if let Some(flash) = self.radiator_flash.take() {
+            if let Some(source) = MainContext::ref_thread_default().find_source_by_id(&flash) {
+                source.destroy();
+            }
+        }
+        let scope = self.scope.as_ref().unwrap().clone();
+        self.radiator_flash = Some(glib::timeout_add_seconds_local(
+            RADIATOR_FLASH_SECS,
+            move || {
+                scope.send_message(Message::StopFlashing);
+                Continue(false)
+            },
+        ));
+    }
 
-            Message::Refresh => UpdateAction::Render,
+    // The parser the project's builds are read with, falling back to just
+    // showing the output if its matcher has gone missing from the config.
+    fn parser(&self, project: &ProjectConfig) -> Arc<dyn Parser> {
+        self.config.parser(&project.parser).unwrap_or_else(|err| {
+            self.warn(err);
+            Arc::new(PassthroughParser)
+        })
+    }
 
-            Message::Exit => {
-                vgtk::quit();
-                UpdateAction::None
+    fn toolchain_label(&self) -> String {
+        match self.config.project(&self.project_root).toolchain {
+            Some(toolchain) => format!("+{}", toolchain),
+            None => gettext("Toolchain"),
+        }
+    }
+
+    // The channels, then any other toolchain rustup has, by their short
+    // names. Without rustup there's nothing to pick.
+    fn toolchain_names(&self) -> Vec<String> {
+        if self.toolchains.is_empty() {
+            return vec![];
+        }
+        let mut names = toolchain::CHANNELS
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        for installed in &self.toolchains {
+            let name = toolchain::short_name(installed);
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
             }
         }
+        names
     }
 
-    fn view(&self) -> VNode<Model> {
-        gtk! {
-            <Application::new_unwrap(Some("in.nerdworks.watch-rust-errors"), ApplicationFlags::empty())>
-
-                <SimpleAction::new("quit", None) Application::accels=["<Ctrl>q"].as_ref() enabled=true
-                        on activate=|a, _| Message::Exit/>
-
-                <ApplicationWindow default_width=800 default_height=480 border_width=20 on destroy=|_| Message::Exit>
-                    <HeaderBar title="Watch Rust Errors" show_close_button=true />
-                    <Grid row_spacing=10 column_spacing=10>
-                        // Row 0
-                        <Label label="Project Root:" halign=Align::End />
-                        <Entry Grid::left=1 hexpand=true
-                               editable={ self.state.map(|| true, || false) }
-                               text=self.project_root.clone()
-                               on property_text_notify=|inp| {
-                                   match inp.get_text().map(|s| s.as_str().to_owned()) {
-                                       Some(path) => Message::PathChanged(path),
-                                       None => Message::NoOp,
-                                   }
-                                } />
-                        <Button label="..."
-                                Grid::left=2
-                                sensitive={ self.state.map(|| true, || false) }
-                                on clicked=|_| Message::SelectFolder />
-
-                        // Row 1
-                        <Label label="Command:" halign=Align::End Grid::top=1 />
-                        <Entry Grid::left=1 Grid::top=1
-                               hexpand=true
-                               editable={ self.state.map(|| true, || false) }
-                               text=self.command.clone()
-                               placeholder_text="cargo check"
-                               on property_text_notify=|inp| {
-                                   match inp.get_text().map(|s| s.as_str().to_owned()) {
-                                       Some(command) => Message::CommandChanged(command),
-                                       None => Message::NoOp,
-                                   }
-                               } />
-                        <Button label={ self.state.map(|| "Start Watching", || "Stop Watching") }
-                            Grid::left=2
-                            Grid::top=1
-                            on clicked=|_| Message::ToggleWatch />
-
-                        // Row 2
-                        <ScrolledWindow Grid::top=2 Grid::width=3 hexpand=true vexpand=true>
-                            <ListBox selection_mode=SelectionMode::None>
-                               {
-                                   self.render_results()
-                               }
-                            </ListBox>
-                        </ScrolledWindow>
-                    </Grid>
-                </ApplicationWindow>
-            </Application>
-        }
-    }
-}
-
-async fn select_folder() -> Result<Option<File>, Error> {
-    let dialog = FileChooserNative::new(
-        Some("Select root folder of your crate"),
-        vgtk::current_object()
-            .and_then(|w| w.downcast::<Window>().ok())
-            .as_ref(),
-        FileChooserAction::SelectFolder,
-        Some("Select"),
-        None,
-    );
-    dialog.set_modal(true);
-    dialog.show();
+    // The targets the picked toolchain has, which another toolchain may not.
+    fn list_targets(&mut self) {
+        let env = self.project().env_vars();
+        self.targets = toolchain::installed_targets_with(&SystemRunner, &self.project_root, &env);
+    }
 
-    if on_signal!(dialog, connect_response).await == Ok(ResponseType::Accept) {
-        Ok(dialog.get_file())
-    } else {
-        Ok(None)
+    fn target_label(&self) -> String {
+        match self.config.project(&self.project_root).target {
+            Some(target) => target,
+            None => gettext("Host"),
+        }
     }
-}
 
-fn main() {
-    std::process::exit(run::<Model>());
+    fn profile_label(&self) -> String {
+        match self.config.project(&self.project_root).profile {
+            Some(name) => name,
+            None => gettext("Profile"),
+        }
+    }
+
+    // One checkbox action per feature of the project, by position since
+    // action names can't have every character feature names can.
+    fn render_feature_actions(
+        &self,
+        selection: &FeatureSelection,
+        enabled: bool,
+    ) -> impl Iterator<Item = VNode<Model>> {
+        self.features
+            .iter()
+            .enumerate()
+            .map(|(index, feature)| {
+                let on = selection.features.contains(feature);
+                let feature = feature.clone();
+                gtk! {
+                    <SimpleAction::new_stateful(&format!("feature-{}", index), None, &on.to_variant())
+                            state=&on.to_variant()
+                            enabled=enabled
+                            on activate=|a, _| Message::ToggleFeature(feature.clone()) />
+                }
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    // Puts the features picked in the command in place of the ones it had,
+    // which restarts the watch like typing it would.
+    fn select_features(&mut self, selection: FeatureSelection) -> UpdateAction<Model> {
+        self.command = selection.apply(&self.command);
+        self.schedule_restart();
+        UpdateAction::Render
+    }
+
+    // What the command palette offers: what the buttons do, then everything
+    // in the menus, by the menu and section it's in.
+    fn palette_commands(&self) -> Vec<Command> {
+        let watch = self
+            .state
+            .map(|| gettext("Start Watching"), || gettext("Stop Watching"));
+        let mut commands = vec![
+            Command::new(&watch, "win.toggle-watch"),
+            Command::new(&gettext("Run now"), "win.run-now"),
+            Command::new(&gettext("Previous error"), "win.previous-error"),
+            Command::new(&gettext("Next error"), "win.next-error"),
+            Command::new(&gettext("Copy all output"), "win.copy-all"),
+            Command::new(&gettext("Clear output"), "win.clear"),
+            Command::new(&gettext("Open config file"), "win.open-config"),
+        ];
+        let menus = vec![
+            (gettext("Profile"), profile_menu(&self.profile_names())),
+            (
+                gettext("Toolchain"),
+                toolchain_menu(&self.toolchain_names()),
+            ),
+            (gettext("Target"), target_menu(&self.targets)),
+            (gettext("Features"), feature_menu(&self.features)),
+            (gettext("Sort by"), sort_menu()),
+        ];
+        for (name, menu) in menus {
+            commands.extend(command_palette::menu_commands(
+                menu.upcast_ref(),
+                Some(&name),
+            ));
+        }
+        if !self.project_root.is_empty() {
+            let menu = project_menu(&self.project_root);
+            commands.extend(command_palette::menu_commands(menu.upcast_ref(), None));
+        }
+        let menu = app_menu(&self.config);
+        commands.extend(command_palette::menu_commands(menu.upcast_ref(), None));
+        commands
+    }
+
+    fn profile_names(&self) -> Vec<String> {
+        self.config
+            .project(&self.project_root)
+            .profiles()
+            .into_iter()
+            .map(|profile| profile.name)
+            .collect()
+    }
+
+    // Saves the current project's settings and hands its environment to
+    // whatever is building it.
+    fn set_project(&mut self, project: ProjectConfig) {
+        self.config.set_project(&self.project_root, project);
+        self.save_config();
+        let project = self.project();
+        if let Some(watcher) = self.watcher.as_ref() {
+            watcher.set_env(project.env_vars());
+            watcher.set_parser(self.parser(&project));
+            watcher.set_post_processor(project.post_processor());
+            watcher.set_post_build(project.post_build_hooks());
+            watcher.set_max_diagnostics(project.max_diagnostics());
+            watcher.set_build_timeout(project.build_timeout());
+            watcher.set_success_policy(project.success_policy());
+            watcher.set_respect_ignores(!project.watch_ignored);
+            watcher.set_log_runs(project.log_runs);
+        }
+        if let Some(scheduler) = self.session.as_ref().and_then(WatchSession::scheduler) {
+            scheduler.set_env(project.env_vars());
+        }
+    }
+
+    // Whether changes wait to build, for the window to come back to the
+    // foreground.
+    fn held(&self) -> bool {
+        self.config.build_only_when_focused && !self.focused
+    }
+
+    // What closing the window does: a watch doesn't stop without asking, or
+    // goes on with the window minimized if the user would rather.
+    fn close_guard(&self) -> CloseGuard {
+        match self.state {
+            AppState::Watching if self.config.minimize_on_close => CloseGuard::Minimize,
+            AppState::Watching => CloseGuard::Confirm,
+            AppState::Idle => CloseGuard::Close,
+        }
+    }
+
+    // The current project's settings as builds use them, with what its own
+    // config file adds.
+    fn project(&self) -> ProjectConfig {
+        self.config
+            .project(&self.project_root)
+            .with_local(self.local_config.as_ref())
+    }
+
+    fn schedule_jobs(&mut self) {
+        let project = self.project();
+        if let Some(session) = self.session.as_mut() {
+            if let Err(err) =
+                session.schedule(&self.project_root, &project.jobs, project.env_vars())
+            {
+                self.warn(format!("Failed to start scheduled jobs: {}", err));
+            }
+        }
+    }
+
+    // Shows `warning` in the status bar, for something that went wrong that
+    // the window goes on despite.
+    fn warn(&self, warning: String) {
+        if let Some(scope) = self.scope.as_ref() {
+            scope.send_message(Message::Warning(warning));
+        }
+    }
+
+    // Safe mode leaves the config file as it was.
+    fn save_config(&self) {
+        if self.safe_mode {
+            return;
+        }
+        if let Err(err) = self.config.save() {
+            self.warn(format!("Failed to save config: {}", err));
+        }
+    }
+
+    // Puts the command being watched with at the top of the history the
+    // command entry recalls with Up and Down.
+    fn remember_command(&mut self) {
+        if self.config.command_history.first() != Some(&self.command) {
+            self.config.remember_command(&self.command);
+            self.save_config();
+        }
+    }
+
+    // Keeps the window's size, whether it's maximized and the open tab for
+    // the next window to open with. One that was only ever maximized keeps
+    // the size it had before.
+    fn remember_window(&mut self) {
+        let previous = self.config.window.as_ref();
+        let (width, height) = match (self.window_size, previous) {
+            (Some(size), _) => size,
+            (None, Some(previous)) => (previous.width, previous.height),
+            (None, None) => (WINDOW_WIDTH, WINDOW_HEIGHT),
+        };
+        let window = WindowState {
+            width,
+            height,
+            maximized: self.maximized,
+            tab: self.current_tab.clone(),
+            split_position: self
+                .split_position
+                .or_else(|| previous.and_then(|previous| previous.split_position)),
+        };
+        if self.config.window.as_ref() != Some(&window) {
+            self.config.window = Some(window);
+            self.save_config();
+        }
+    }
+
+    // Shows the command's output with its colors, if it printed any.
+    fn set_raw_output(&mut self, output: &str) {
+        let buffer = self
+            .raw_output
+            .get_or_insert_with(|| TextBuffer::new(None::<&TextTagTable>));
+        buffer.set_text("");
+        buffer.insert_markup(&mut buffer.get_end_iter(), &ansi::to_pango(output));
+    }
+
+    // Scrolls the results list once new results are in place: to the first
+    // error, to the bottom or back to where it was, since the list can
+    // briefly shrink while they're put in.
+    fn scroll_results(&mut self) {
+        let adjustment = self
+            .results_scroll
+            .get_or_insert_with(|| Adjustment::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0))
+            .clone();
+        let scroll_to = self.config.scroll_to;
+        if scroll_to == ScrollTo::FirstError {
+            self.current = None;
+            self.select_next(true, true);
+        }
+        let has_error = self.current.is_some();
+        let value = adjustment.get_value();
+        glib::idle_add_local(move || {
+            match scroll_to {
+                ScrollTo::Keep => adjustment.set_value(value),
+                ScrollTo::Bottom => {
+                    adjustment.set_value(adjustment.get_upper() - adjustment.get_page_size())
+                }
+                // the error's row scrolls into view as it takes the focus
+                ScrollTo::FirstError if has_error => {}
+                ScrollTo::FirstError => adjustment.set_value(adjustment.get_lower()),
+            }
+            Continue(false)
+        });
+    }
+
+    // The rows of the results list, or of one of its panes, of which only
+    // the first `visible_rows` diagnostics are built, since building rows for
+    // thousands of them freezes the UI until the user scrolls. The filters
+    // and how the build went are listed with the errors.
+    fn list_rows(&self, pane: Option<Pane>) -> Vec<ListRow> {
+        let result = match self.results.as_ref() {
+            Some(result) => result,
+            None => return vec![],
+        };
+        let listing = Listing {
+            pane,
+            ..self.listing()
+        };
+
+        let output = match (result.success, result.duration) {
+            (true, Some(took)) => ResultRow::text(
+                Severity::Info,
+                &fill(
+                    gettext("Compile succeeded in {}."),
+                    &[&format_duration(took)],
+                ),
+            ),
+            (true, None) => ResultRow::text(Severity::Info, &gettext("Compile succeeded.")),
+            (false, Some(took)) => ResultRow::text(
+                Severity::Error,
+                &fill(gettext("Compile failed in {}."), &[&format_duration(took)]),
+            ),
+            (false, None) => ResultRow::text(Severity::Error, &gettext("Compile failed.")),
+        };
+
+        // git touches plenty of files on a checkout, so what it did says more
+        let triggered = if let Some(trigger) = result.git_trigger.as_ref() {
+            Some(ResultRow::text(
+                Severity::Info,
+                &fill(gettext("Triggered by: {}"), &[trigger]),
+            ))
+        } else if result.triggered_by.is_empty() {
+            None
+        } else {
+            Some(ResultRow::text(
+                Severity::Info,
+                &fill(
+                    gettext("Triggered by: {}"),
+                    &[&format_files(&result.triggered_by)],
+                ),
+            ))
+        };
+
+        let test_failures = result
+            .tests
+            .iter()
+            .flat_map(|tests| tests.failures.iter())
+            .map(ResultRow::test_failure);
+        let test_summary = result
+            .tests
+            .as_ref()
+            .map(|tests| ResultRow::text(Severity::Info, &fill(gettext("Tests: {}"), &[tests])));
+
+        let mut rows = if self.config.group_by_code {
+            self.code_rows(&listing, result)
+        } else if self.config.group_by_file {
+            self.file_rows(&listing, result)
+        } else {
+            self.crate_rows(&listing, result)
+        };
+        if listing.focused_file.is_some() && rows.len() < self.visible_rows {
+            let elsewhere = listing.elsewhere(result);
+            let errors = elsewhere
+                .iter()
+                .filter(|(_, diag)| diag.type_ == Type::Error)
+                .count();
+            rows.push(ListRow::Elsewhere {
+                errors,
+                warnings: elsewhere.len() - errors,
+                expanded: self.list.show_elsewhere,
+            });
+            if self.list.show_elsewhere {
+                let room = self.visible_rows - rows.len();
+                for (id, diag) in elsewhere.into_iter().take(room) {
+                    rows.push(ListRow::Result(self.diagnostic_row(id, diag)));
+                }
+            }
+        }
+        let hidden = listing.total_rows(result) - rows.len();
+
+        let fetch = if result.needs_fetch {
+            Some(ListRow::Fetch)
+        } else {
+            None
+        };
+
+        let filter = self.list.module_filter.clone().map(ListRow::ModuleFilter);
+        let code_filter = self.list.code_filter.clone().map(ListRow::CodeFilter);
+        let focus = listing
+            .focused_file
+            .map(|file| ListRow::FocusFilter(file.to_string()));
+        let changes = self
+            .changed_files
+            .as_ref()
+            .map(|changes| ListRow::ChangesFilter(changes.base.clone()));
+        let baseline = if self.config.hide_baseline && self.baseline.is_some() {
+            Some(ListRow::BaselineFilter(
+                result.diagnostics().count() - self.new_diagnostics.len(),
+            ))
+        } else {
+            None
+        };
+
+        // what the last edit did, when showing that: new diagnostics are
+        // highlighted among the rest, fixed ones follow them
+        let since_previous = self.shown_run_diff();
+        let run_summary = since_previous.map(|diff| {
+            ResultRow::text(
+                Severity::Info,
+                &fill(
+                    gettext("Since the previous build: {0} new, {1} fixed, {2} unchanged."),
+                    &[&diff.new.len(), &diff.fixed.len(), &diff.unchanged],
+                ),
+            )
+        });
+        let fixed = since_previous
+            .into_iter()
+            .flat_map(|diff| diff.fixed.iter())
+            .filter(|diag| {
+                let file = diag.file.as_deref();
+                listing.in_module_filter(file)
+                    && listing.in_code_filter(diag.code())
+                    && listing.in_changed_files(file)
+                    && listing.in_pane(diag)
+            })
+            .map(|diag| ResultRow {
+                change: Change::Fixed,
+                ..ResultRow::listed(diag, Some(gettext("FIXED")))
+            });
+        let show_more = if hidden > 0 {
+            Some(ListRow::ShowMore(hidden))
+        } else {
+            None
+        };
+
+        if pane == Some(Pane::Warnings) {
+            let none = if rows.is_empty() {
+                Some(ResultRow::text(Severity::Info, &gettext("No warnings.")))
+            } else {
+                None
+            };
+            return rows
+                .into_iter()
+                .chain(show_more)
+                .chain(fixed.chain(none).map(ListRow::Result))
+                .collect();
+        }
+
+        fetch
+            .into_iter()
+            .chain(filter)
+            .chain(code_filter)
+            .chain(focus)
+            .chain(changes)
+            .chain(baseline)
+            .chain(run_summary.map(ListRow::Result))
+            .chain(rows)
+            .chain(show_more)
+            .chain(
+                fixed
+                    .chain(test_failures)
+                    .chain(test_summary)
+                    .chain(triggered)
+                    .chain(iter::once(output))
+                    .map(ListRow::Result),
+            )
+            .collect()
+    }
+
+    fn crate_rows(&self, listing: &Listing, result: &CompileResult) -> Vec<ListRow> {
+        // only workspaces and cargo output give us crate names to group by
+        let groups = listing.crate_groups(result);
+        let show_crates = groups.iter().any(|g| g.name.is_some());
+
+        let mut rows = vec![];
+        'groups: for group in groups {
+            if rows.len() >= self.visible_rows {
+                break;
+            }
+            if show_crates {
+                rows.push(ListRow::Result(ResultRow::crate_header(&group)));
+            }
+            for (id, diag) in listing.arrange(result, group.diagnostics) {
+                if rows.len() >= self.visible_rows {
+                    break 'groups;
+                }
+                rows.push(ListRow::Result(self.diagnostic_row(id, diag)));
+            }
+        }
+        rows
+    }
+
+    fn file_rows(&self, listing: &Listing, result: &CompileResult) -> Vec<ListRow> {
+        let mut rows = vec![];
+        'groups: for group in listing.file_groups(result) {
+            if rows.len() >= self.visible_rows {
+                break;
+            }
+            let collapsed = listing.is_collapsed(group.file);
+            rows.push(ListRow::file(&group, collapsed));
+            if collapsed {
+                continue;
+            }
+            for (id, diag) in listing.arrange(result, group.diagnostics) {
+                if rows.len() >= self.visible_rows {
+                    break 'groups;
+                }
+                rows.push(ListRow::Result(self.diagnostic_row(id, diag)));
+            }
+        }
+        rows
+    }
+
+    fn code_rows(&self, listing: &Listing, result: &CompileResult) -> Vec<ListRow> {
+        let mut rows = vec![];
+        for group in listing.code_groups(result) {
+            if rows.len() >= self.visible_rows {
+                break;
+            }
+            let expanded = group.diagnostics.len() > 1 && listing.is_expanded(group.code);
+            rows.push(ListRow::code(&group, expanded));
+            if let Some((id, diag)) = group.diagnostics.first() {
+                rows.push(ListRow::Result(self.diagnostic_row(*id, diag)));
+            }
+            if expanded {
+                rows.push(ListRow::locations(&group));
+            }
+        }
+        rows
+    }
+
+    fn diagnostic_row(&self, id: DiagnosticId, diag: &RustDiagnostic) -> ResultRow {
+        let snippet = self.snippets.borrow_mut().markup(diag, style::is_dark());
+        let mut row = ResultRow::diagnostic(id, diag, snippet, self.current == Some(id));
+        row.fix_preview = self
+            .snippets
+            .borrow_mut()
+            .fix_markup(diag, style::is_dark());
+        let consequences = self.cascades.consequences_of(id).len();
+        if consequences > 0 {
+            row.cascade = Some((id, consequences, self.list.expanded_causes.contains(&id)));
+        }
+        row.consequence = self.cascades.cause_of(id).is_some();
+        if let Some((_, _, expanded)) = row.macro_trace.as_mut() {
+            *expanded = self.list.expanded_macro_traces.contains(&id);
+        }
+        if let Some(since_previous) = self.shown_run_diff() {
+            if since_previous.new.contains(&id) {
+                row.change = Change::New;
+            }
+        }
+        if self.new_diagnostics.contains(&id) {
+            row.badge = Some(match row.badge.take() {
+                Some(repeats) => fill(gettext("NEW {}"), &[&repeats]),
+                None => gettext("NEW"),
+            });
+        }
+        row
+    }
+
+    fn shown_run_diff(&self) -> Option<&RunDiff> {
+        self.since_previous
+            .as_ref()
+            .filter(|_| self.config.show_changes)
+    }
+
+    // What the working tree introduces and fixes relative to the compared
+    // ref, as an extra tab.
+    // Says what safe mode leaves out, with a menu of the config's sections to
+    // reset.
+    fn render_safe_mode(&self) -> impl Iterator<Item = VNode<Model>> {
+        if !self.safe_mode {
+            return None.into_iter();
+        }
+
+        let reset = reset_menu(&self.config_sections);
+        let can_reset = !self.config_sections.is_empty();
+        let tooltip = gettext("Put a section of the config file back to its defaults");
+        Some(gtk! {
+            <Box spacing=10 style_class="banner">
+                <Label hexpand=true xalign=0.0 line_wrap=true
+                        label=gettext("Safe mode: default settings, no build hooks and nothing opened by itself. Your config file is left as it is.") />
+                <MenuButton label=gettext("Reset…") sensitive=can_reset menu_model=Some(&reset)
+                        tooltip_text=tooltip.as_str() />
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // Asks whether to let the project's own config file run what it asks
+    // for, until the user has answered once.
+    fn render_trust_prompt(&self) -> impl Iterator<Item = VNode<Model>> {
+        let asked = self.config.project(&self.project_root).trust_local_config;
+        let local = match self.local_config.as_ref() {
+            Some(local) if local.runs_commands() && asked.is_none() => local,
+            _ => return None.into_iter(),
+        };
+
+        let text = fill(
+            gettext("This project's {0} wants to {1}. Only trust projects you know."),
+            &[&config::LOCAL_CONFIG, &trust_request(local)],
+        );
+        Some(gtk! {
+            <Box spacing=10 style_class="banner">
+                <Label hexpand=true xalign=0.0 line_wrap=true label=text />
+                <Button label=gettext("Trust") on clicked=|_| Message::TrustLocalConfig(true) />
+                <Button label=gettext("Ignore") on clicked=|_| Message::TrustLocalConfig(false) />
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // Says the watch isn't building anything anymore, though it hasn't been
+    // stopped, with a way to start it again.
+    fn render_watcher_stopped(&self) -> impl Iterator<Item = VNode<Model>> {
+        let reason = match self.watcher_stopped.as_ref() {
+            Some(reason) => reason,
+            None => return None.into_iter(),
+        };
+
+        let text = fill(gettext("Watcher stopped unexpectedly: {}"), &[reason]);
+        Some(gtk! {
+            <Box spacing=10 style_class="banner">
+                <Label hexpand=true xalign=0.0 line_wrap=true selectable=true label=text />
+                <Button label=gettext("Restart") on clicked=|_| Message::RestartWatcher />
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // Says changes to a folder the document portal shows a Flatpak sandbox
+    // are only noticed by looking for them, and how to let the app see it
+    // as it is.
+    fn render_portal_notice(&self) -> impl Iterator<Item = VNode<Model>> {
+        if !sandbox::is_document(Path::new(&self.project_root)) {
+            return None.into_iter();
+        }
+
+        let text = fill(
+            gettext("The sandbox only shows this folder through the file chooser, so changes to it are looked for every few seconds. `flatpak override --user --filesystem=home {}` lets the app see it as it is."),
+            &[&sandbox::app_id()],
+        );
+        Some(gtk! {
+            <Box spacing=10 style_class="banner">
+                <Label hexpand=true xalign=0.0 line_wrap=true selectable=true label=text />
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // What the health check found, each check with whether it passed and, if
+    // it didn't, how to fix it.
+    fn render_health(&self) -> impl Iterator<Item = VNode<Model>> {
+        let checks = match self.health.as_ref() {
+            Some(checks) => checks,
+            None => return None.into_iter(),
+        };
+
+        let failed = checks.iter().filter(|check| !check.passed).count();
+        let summary = if failed == 0 {
+            gettext("Everything the app needs is in place.")
+        } else {
+            fill(
+                gettext("{0} of {1} checks failed."),
+                &[&failed, &checks.len()],
+            )
+        };
+        let rows = checks
+            .iter()
+            .map(|check| {
+                let icon = if check.passed {
+                    "emblem-ok-symbolic"
+                } else {
+                    "dialog-error-symbolic"
+                };
+                let text = match check.hint.as_ref() {
+                    Some(hint) => format!("{}\n{}", check.detail, hint),
+                    None => check.detail.clone(),
+                };
+                gtk! {
+                    <Box spacing=10>
+                        <Image property_icon_name=Some(icon) />
+                        <Label label=check.name style_class="title" xalign=0.0 />
+                        <Label hexpand=true xalign=0.0 line_wrap=true selectable=true label=text />
+                    </Box>
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(gtk! {
+            <Box orientation=Orientation::Vertical spacing=6 style_class="banner">
+                <Box spacing=10>
+                    <Label hexpand=true xalign=0.0 label=summary style_class="title" />
+                    <Button label=gettext("Check again") on clicked=|_| Message::CheckHealth />
+                    <Button label=gettext("Close") on clicked=|_| Message::CloseHealth />
+                </Box>
+                { rows.into_iter() }
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // The crates the build goes through, in layers from those that depend on
+    // nothing up, colored by whether they're waiting, being compiled or done,
+    // with the chain that took the longest outlined, as an extra tab.
+    fn render_pipeline(&self) -> impl Iterator<Item = VNode<Model>> {
+        let pipeline = match self.pipeline.as_ref() {
+            Some(pipeline) if !pipeline.units.is_empty() => pipeline,
+            _ => return None.into_iter(),
+        };
+
+        let summary = fill(
+            gettext("{0} of {1} crates done, {2} being compiled, {3} waiting"),
+            &[
+                &pipeline.count(UnitState::Done),
+                &pipeline.units.len(),
+                &pipeline.count(UnitState::Active),
+                &pipeline.count(UnitState::Pending),
+            ],
+        );
+        let critical = pipeline.critical_path();
+        let critical_summary = if critical.is_empty() {
+            gettext("Nothing compiled yet")
+        } else {
+            let took = critical.iter().map(|id| pipeline.duration(id)).sum();
+            let names = critical
+                .iter()
+                .map(|id| pipeline.units[id].name.as_str())
+                .collect::<Vec<_>>();
+            fill(
+                gettext("Critical path, {0}: {1}"),
+                &[&format_duration(took), &names.join(" › ")],
+            )
+        };
+        let layers = pipeline
+            .layers()
+            .into_iter()
+            .map(|layer| {
+                let units = layer
+                    .iter()
+                    .map(|id| render_unit(pipeline, id, critical.contains(id)))
+                    .collect::<Vec<_>>();
+                gtk! {
+                    <FlowBox selection_mode=SelectionMode::None column_spacing=4 row_spacing=4>
+                        { units.into_iter() }
+                    </FlowBox>
+                }
+            })
+            .collect::<Vec<_>>();
+        let tab = gettext("Pipeline");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <Box orientation=Orientation::Vertical spacing=10 border_width=10>
+                    <Label label=summary halign=Align::Start />
+                    <Label label=critical_summary halign=Align::Start wrap=true />
+                    { layers.into_iter() }
+                </Box>
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // The results list as a tab: one list, or errors above warnings with a
+    // divider between them that can be dragged.
+    fn render_diagnostics(&self, tab: &str) -> impl Iterator<Item = VNode<Model>> {
+        if self.config.results_layout == ResultsLayout::Single {
+            return iter::once(gtk! {
+                <ScrolledWindow Notebook::tab_label=Some(tab)
+                        vadjustment=self.results_scroll.as_ref()
+                        on edge_reached=|_, position| show_more_at(position)>
+                    { self.render_results_list(None) }
+                </ScrolledWindow>
+            });
+        }
+
+        // there's nothing to divide while a pane is folded down to its header
+        if !self.collapsed_panes.is_empty() {
+            return iter::once(gtk! {
+                <Box Notebook::tab_label=Some(tab) orientation=Orientation::Vertical>
+                    { self.render_panes() }
+                </Box>
+            });
+        }
+        let position = self
+            .split_position
+            .or_else(|| self.config.window.as_ref()?.split_position)
+            .unwrap_or(SPLIT_POSITION);
+        iter::once(gtk! {
+            <Paned Notebook::tab_label=Some(tab) orientation=Orientation::Vertical
+                    wide_handle=true
+                    on realize=|paned| {
+                        paned.set_position(position);
+                        Message::NoOp
+                    }
+                    on property_position_notify=|paned| Message::SplitMoved(paned.get_position())>
+                { self.render_panes() }
+            </Paned>
+        })
+    }
+
+    // The errors and the warnings, each under a header that folds it away.
+    fn render_panes(&self) -> impl Iterator<Item = VNode<Model>> + '_ {
+        [Pane::Errors, Pane::Warnings].iter().map(move |pane| {
+            let pane = *pane;
+            let count = self
+                .results
+                .as_ref()
+                .map(|result| {
+                    Listing {
+                        pane: Some(pane),
+                        ..self.listing()
+                    }
+                    .count_listed(result)
+                })
+                .unwrap_or(0);
+            let label = match pane {
+                Pane::Errors => ngettext("{} error", "{} errors", count as u32),
+                Pane::Warnings => ngettext("{} warning", "{} warnings", count as u32),
+            };
+            let label = fill(label, &[&listing::thousands(count)]);
+            let expanded = !self.collapsed_panes.contains(&pane);
+
+            gtk! {
+                <Expander label=Some(label.as_str()) expanded=expanded vexpand=expanded
+                        on activate=|_| Message::TogglePane(pane)>
+                    <ScrolledWindow vexpand=true
+                            on edge_reached=|_, position| show_more_at(position)>
+                        { self.render_results_list(Some(pane)) }
+                    </ScrolledWindow>
+                </Expander>
+            }
+        })
+    }
+
+    fn render_results_list(&self, pane: Option<Pane>) -> impl Iterator<Item = VNode<Model>> {
+        iter::once(gtk! {
+            <@ResultsList rows=self.list_rows(pane)
+                    on toggle_cascade=|id| Message::List(ListMessage::ToggleCascade(id))
+                    on toggle_macro_trace=|id| Message::List(ListMessage::ToggleMacroTrace(id))
+                    on apply_fix=|id| Message::ApplyFix(id)
+                    on explain=|code| Message::Explain(code)
+                    on toggle_file=|file| Message::List(ListMessage::ToggleFile(file))
+                    on toggle_code=|code| Message::List(ListMessage::ToggleCode(code))
+                    on fetch=|_| Message::FetchDependencies
+                    on show_all_modules=|_| Message::List(ListMessage::FilterModule(None))
+                    on show_all_codes=|_| Message::List(ListMessage::FilterCode(None))
+                    on show_all_changes=|_| Message::ToggleOnlyChanges
+                    on show_baseline=|_| Message::ToggleOption(ListOption::HideBaseline)
+                    on show_unfocused=|_| Message::ToggleOption(ListOption::FocusFile)
+                    on toggle_elsewhere=|_| Message::List(ListMessage::ToggleElsewhere)
+                    on show_more=|_| Message::ShowMoreResults />
+        })
+    }
+
+    fn render_comparison(&self) -> impl Iterator<Item = VNode<Model>> + '_ {
+        self.comparison.iter().map(|comparison| {
+            let label = fill(gettext("Compared with {}"), &[&comparison.git_ref]);
+            let rows = comparison
+                .introduced
+                .iter()
+                .map(|diag| ResultRow::listed(diag, Some(gettext("NEW"))))
+                .chain(
+                    comparison
+                        .fixed
+                        .iter()
+                        .map(|diag| ResultRow::listed(diag, Some(gettext("FIXED")))),
+                )
+                .chain(
+                    if comparison.introduced.is_empty() && comparison.fixed.is_empty() {
+                        Some(ResultRow::text(Severity::Info, &gettext("No differences.")))
+                    } else {
+                        None
+                    },
+                )
+                .map(ListRow::Result)
+                .collect::<Vec<_>>();
+
+            gtk! {
+                <ScrolledWindow Notebook::tab_label=Some(label.as_str())>
+                    <@ResultsList rows=rows />
+                </ScrolledWindow>
+            }
+        })
+    }
+
+    // The latest result of every scheduled job, as an extra tab.
+    // How long builds take and how many errors they had, as an extra tab.
+    fn render_statistics(&self) -> impl Iterator<Item = VNode<Model>> {
+        if self.history.is_empty() {
+            return None.into_iter();
+        }
+
+        let last = self
+            .history
+            .last()
+            .and_then(|record| record.duration)
+            .map(format_duration)
+            .unwrap_or_else(|| gettext("unknown"));
+        let average = match self.history.average_duration() {
+            Some(average) => fill(
+                ngettext(
+                    "{0} over {1} build",
+                    "{0} over {1} builds",
+                    self.history.timed_builds() as u32,
+                ),
+                &[&format_duration(average), &self.history.timed_builds()],
+            ),
+            None => gettext("unknown"),
+        };
+        let wrapper = self.results.as_ref().and_then(|result| {
+            let wrapper = result.compiler_wrapper.as_ref()?;
+            Some(match result.cache_stats {
+                Some(stats) => match stats.hit_rate() {
+                    Some(rate) => fill(
+                        gettext("Compiler cache ({0}): {1} hits, {2} misses ({3}% hit rate) in the last build"),
+                        &[wrapper, &stats.hits, &stats.misses, &format!("{:.0}", rate)],
+                    ),
+                    None => fill(
+                        gettext("Compiler cache ({0}): {1} hits, {2} misses in the last build"),
+                        &[wrapper, &stats.hits, &stats.misses],
+                    ),
+                },
+                None => fill(gettext("Compiler wrapper: {}"), &[wrapper]),
+            })
+        });
+        let counts = self.history.error_counts();
+        let errors = fill(
+            gettext("Errors over the last {0} builds, at most {1}:"),
+            &[
+                &counts.len(),
+                &counts.iter().max().copied().unwrap_or_default(),
+            ],
+        );
+        let tab = gettext("Statistics");
+
+        Some(gtk! {
+            <Box Notebook::tab_label=Some(tab.as_str()) orientation=Orientation::Vertical
+                    spacing=10 border_width=10>
+                <Label label=fill(gettext("Last build: {}"), &[&last]) halign=Align::Start />
+                <Label label=fill(gettext("Average build: {}"), &[&average]) halign=Align::Start />
+                { wrapper.into_iter().map(|wrapper| gtk! {
+                    <Label label=wrapper halign=Align::Start />
+                }) }
+                <Label label=errors halign=Align::Start />
+                <DrawingArea sparkline=counts height_request=60 hexpand=true
+                        style_class="sparkline" />
+            </Box>
+        })
+        .into_iter()
+    }
+
+    fn render_scheduled(&self) -> impl Iterator<Item = VNode<Model>> {
+        if self.scheduled.is_empty() {
+            return None.into_iter();
+        }
+
+        let rows = self
+            .scheduled
+            .iter()
+            .flat_map(|(job, result)| {
+                let summary = fill(
+                    gettext("{0} ({1} errors, {2} warnings)"),
+                    &[job, &result.errors.len(), &result.warnings.len()],
+                );
+                let header = ResultRow {
+                    badge: Some(if result.success {
+                        gettext("OK")
+                    } else {
+                        gettext("FAILED")
+                    }),
+                    ..ResultRow::text(
+                        if result.success {
+                            Severity::Info
+                        } else {
+                            Severity::Error
+                        },
+                        &summary,
+                    )
+                };
+                iter::once(header).chain(
+                    result
+                        .diagnostics()
+                        .map(|diag| ResultRow::listed(diag, None)),
+                )
+            })
+            .map(ListRow::Result)
+            .collect::<Vec<_>>();
+        let tab = gettext("Scheduled");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <@ResultsList rows=rows />
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // Every module of the project as a treemap cell, sized by its lines of
+    // code and colored by how many diagnostics it has for its size, as an
+    // extra tab. Clicking a cell narrows the results list down to it.
+    fn render_heatmap(&self) -> impl Iterator<Item = VNode<Model>> {
+        if self.modules.is_empty() {
+            return None.into_iter();
+        }
+
+        let mut modules = self.modules.iter().collect::<Vec<_>>();
+        modules.sort_by(|a, b| b.lines.cmp(&a.lines));
+        let sizes = modules
+            .iter()
+            .map(|m| m.lines.max(1) as f64)
+            .collect::<Vec<_>>();
+        let mut modules = modules.into_iter();
+        let rows = heatmap::layout(&sizes, HEATMAP_WIDTH, HEATMAP_HEIGHT)
+            .into_iter()
+            .map(|rects| {
+                let cells = rects
+                    .into_iter()
+                    .zip(&mut modules)
+                    .map(|(rect, module)| {
+                        let selected = self.list.module_filter.as_ref() == Some(&module.path);
+                        render_module(module, rect, selected)
+                    })
+                    .collect::<Vec<_>>();
+                gtk! {
+                    <Box>
+                        { cells.into_iter() }
+                    </Box>
+                }
+            })
+            .collect::<Vec<_>>();
+        let tab = gettext("Overview");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <Box orientation=Orientation::Vertical halign=Align::Center valign=Align::Center>
+                    { rows.into_iter() }
+                </Box>
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // How many diagnostics each code has in the latest results, as an extra
+    // tab for working through a large build a code at a time. Clicking a code
+    // narrows the results list down to it.
+    fn render_codes(&self) -> impl Iterator<Item = VNode<Model>> {
+        let groups = self
+            .results
+            .as_ref()
+            .map(|result| listing::code_counts(result, self.list.codes_by_name))
+            .unwrap_or_default();
+        if groups.is_empty() {
+            return None.into_iter();
+        }
+
+        let summary = fill(
+            gettext("{0} codes, {1} diagnostics"),
+            &[
+                &listing::thousands(groups.len()),
+                &listing::thousands(groups.iter().map(|g| g.diagnostics.len()).sum()),
+            ],
+        );
+        let by_name = self.list.codes_by_name;
+        let rows = groups
+            .iter()
+            .map(|group| render_code_count(group, self.list.code_filter.as_deref() == group.code))
+            .collect::<Vec<_>>();
+        let tab = gettext("By code");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <Box orientation=Orientation::Vertical spacing=6>
+                    <Box spacing=6>
+                        <Label label=summary hexpand=true halign=Align::Start />
+                        <Button label={ if by_name { gettext("Most first") } else { gettext("Sort by code") } }
+                                relief=ReliefStyle::None
+                                on clicked=|_| Message::List(ListMessage::SortCodes(!by_name)) />
+                    </Box>
+                    <ListBox selection_mode=SelectionMode::None>
+                        { rows.into_iter() }
+                    </ListBox>
+                </Box>
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // A card for every configured project with how its latest build went, as
+    // an extra tab, so that one window keeps an eye on them all.
+    fn render_projects(&self) -> impl Iterator<Item = VNode<Model>> {
+        if self.projects.is_empty() {
+            return None.into_iter();
+        }
+
+        let cards = self
+            .projects
+            .iter()
+            .map(|status| {
+                if status.project_root == self.project_root {
+                    let building = self.build_started.is_some();
+                    render_project(status, self.results.as_ref(), building, true)
+                } else {
+                    render_project(status, status.latest.as_ref(), false, false)
+                }
+            })
+            .collect::<Vec<_>>();
+        let tab = gettext("Projects");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <ListBox selection_mode=SelectionMode::None>
+                    { cards.into_iter() }
+                </ListBox>
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // The builds of every window, waiting, running and done, as an extra tab
+    // once there's more than this window's own build to see.
+    fn render_queue(&self) -> impl Iterator<Item = VNode<Model>> {
+        if !watcher::worth_listing(&self.jobs) {
+            return None.into_iter();
+        }
+        let tab = gettext("Queue");
+
+        Some(gtk! {
+            <ScrolledWindow Notebook::tab_label=Some(tab.as_str())>
+                <@QueueList jobs=self.jobs.clone()
+                        project_root=self.project_root.clone()
+                        build_queue=self.props.build_queue.clone() />
+            </ScrolledWindow>
+        })
+        .into_iter()
+    }
+
+    // Whether the window shows the welcome screen: until a watch has started
+    // once, as long as there's nothing else to show.
+    fn onboarding(&self) -> bool {
+        !self.config.onboarded
+            && !self.radiator
+            && self.project_root.is_empty()
+            && self.results.is_none()
+            && self.following.is_none()
+    }
+
+    // What the app does and how to start, instead of an empty list.
+    fn render_onboarding(&self) -> impl Iterator<Item = VNode<Model>> {
+        if !self.onboarding() {
+            return None.into_iter();
+        }
+
+        let examples = example_commands()
+            .into_iter()
+            .map(|(command, description)| {
+                let command = command.to_string();
+                let label = format!("{} — {}", command, description);
+                gtk! {
+                    <Button relief=ReliefStyle::None
+                            on clicked=|_| Message::CommandChanged(command.clone())>
+                        <Label label=label xalign=0.0 />
+                    </Button>
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(gtk! {
+            <Box orientation=Orientation::Vertical spacing=20 valign=Align::Center
+                    halign=Align::Center vexpand=true style_class="onboarding">
+                <Label label=gettext("Watch a Rust project") style_class="onboarding-title" />
+                <Label line_wrap=true justify=Justification::Center max_width_chars=60
+                       label=gettext("Every time a file in the project changes, a command like cargo check runs again and its errors and warnings are listed here, with the one to fix first on top. Choose the project's folder, or drop it onto the window, to start.") />
+                <Button label=gettext("Choose project…") halign=Align::Center
+                        style_class="suggested-action"
+                        on clicked=|_| Message::SelectFolder />
+                <Label label=fill(gettext("Builds with {}, or:"), &[&self.command]) />
+                <Box orientation=Orientation::Vertical spacing=2 halign=Align::Center>
+                    { examples.into_iter() }
+                </Box>
+            </Box>
+        })
+        .into_iter()
+    }
+
+    // The build's status, big enough to read across the room.
+    fn render_radiator(&self) -> impl Iterator<Item = VNode<Model>> {
+        if !self.radiator {
+            return None.into_iter();
+        }
+
+        let since = self
+            .status_changed
+            .and_then(|at| at.elapsed().ok())
+            .map(format_duration);
+        let (radiator, status) = match (self.results.as_ref(), since) {
+            (Some(result), Some(since)) if result.success => (
+                Radiator::Passing,
+                fill(gettext("Passing for {}"), &[&since]),
+            ),
+            (Some(result), None) if result.success => (Radiator::Passing, gettext("Passing")),
+            (Some(_), Some(since)) => (
+                Radiator::Failing,
+                fill(gettext("Failing for {}"), &[&since]),
+            ),
+            (Some(_), None) => (Radiator::Failing, gettext("Failing")),
+            (None, _) => (Radiator::NotBuilt, gettext("Not built yet")),
+        };
+        let counts = self
+            .results
+            .as_ref()
+            .map(|result| {
+                fill(
+                    gettext("{0} errors    {1} warnings"),
+                    &[&result.errors.len(), &result.warnings.len()],
+                )
+            })
+            .unwrap_or_default();
+        let when = match (self.build_started, self.results.as_ref()) {
+            (Some(started), _) => fill(
+                gettext("Building for {}"),
+                &[&format_duration(started.elapsed())],
+            ),
+            (None, Some(result)) => result
+                .finished_at
+                .and_then(|at| at.elapsed().ok())
+                .map(|ago| fill(gettext("Built {} ago"), &[&format_duration(ago)]))
+                .unwrap_or_default(),
+            (None, None) => String::new(),
+        };
+
+        Some(gtk! {
+            <Box orientation=Orientation::Vertical spacing=20 valign=Align::Fill vexpand=true
+                    style_class="radiator" radiator=radiator flash=self.radiator_flash.is_some()>
+                <Label label=project_name(&self.project_root) style_class="radiator-name"
+                       vexpand=true valign=Align::End />
+                <Label label=status style_class="radiator-status" />
+                <Label label=counts style_class="radiator-counts" />
+                <Label label=when style_class="radiator-when" vexpand=true valign=Align::Start />
+            </Box>
+        })
+        .into_iter()
+    }
+}
+
+impl Component for Model {
+    type Message = Message;
+    type Properties = WindowProps;
+
+    fn create(props: Self::Properties) -> Self {
+        Model {
+            props,
+            ..Default::default()
+        }
+    }
+
+    // Only the app's first window is ever handed something to open after
+    // it's up, when the app was started to open it.
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        let scope = self.scope.as_ref().unwrap();
+        if props.project_root != self.props.project_root {
+            if let Some(project_root) = props.project_root.clone() {
+                scope.send_message(Message::FolderSelected(project_root));
+            }
+        }
+        if props.replay != self.props.replay {
+            if let Some((path, original_timing)) = props.replay.clone() {
+                scope.send_message(Message::Replay(path, original_timing));
+            }
+        }
+        if props.piped && !self.props.piped {
+            scope.send_message(Message::ReadStdin);
+        }
+        if props.command != self.props.command {
+            if let Some(command) = props.command.clone() {
+                scope.send_message(Message::CommandChanged(command));
+            }
+        }
+        if props.resumed != self.props.resumed {
+            if let Some(session) = props.resumed.clone() {
+                self.resumed_filters = Some((session.module_filter, session.code_filter));
+            }
+        }
+        if props.start && !self.props.start {
+            scope.send_message(Message::StartWatching);
+        }
+        self.props = props;
+        UpdateAction::None
+    }
+
+    fn init(&mut self, scope: Scope<Self>) {
+        style::install(self.config.theme);
+
+        // other windows and instances publish their projects' results as they
+        // come in, so the radiator keeps looking
+        let poller = scope.clone();
+        self.projects_poll = Some(glib::timeout_add_seconds_local(
+            PROJECTS_POLL_SECS,
+            move || {
+                poller.send_message(Message::PollProjects);
+                Continue(true)
+            },
+        ));
+        scope.send_message(Message::PollProjects);
+
+        if let Some(project_root) = self.props.project_root.clone() {
+            scope.send_message(Message::FolderSelected(project_root));
+        }
+        if let Some((path, original_timing)) = self.props.replay.clone() {
+            scope.send_message(Message::Replay(path, original_timing));
+        }
+        if self.props.piped {
+            scope.send_message(Message::ReadStdin);
+        }
+        if let Some(command) = self.props.command.clone() {
+            scope.send_message(Message::CommandChanged(command));
+        }
+        if let Some(session) = self.props.resumed.clone() {
+            self.resumed_filters = Some((session.module_filter, session.code_filter));
+        }
+        if self.props.start {
+            scope.send_message(Message::StartWatching);
+        }
+        // most "it doesn't work" turns out to be the environment, so look
+        // at it once up front
+        if !self.config.health_checked {
+            scope.send_message(Message::CheckHealth);
+        }
+        #[cfg(unix)]
+        if self.config.dbus {
+            self.start_dbus(&scope);
+        }
+        // the queue changes on the watchers' threads
+        let (tx, rx) = mpsc::unbounded();
+        self.props.build_queue.on_change(move || {
+            let _ = tx.unbounded_send(());
+        });
+        let queue_scope = scope.clone();
+        MainContext::ref_thread_default().spawn_local(rx.for_each(move |_| {
+            queue_scope.send_message(Message::QueueChanged);
+            future::ready(())
+        }));
+        self.scope = Some(scope);
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            Message::NoOp => UpdateAction::None,
+
+            Message::ResetConfigSection(section) => {
+                let reset = Config::path()
+                    .ok_or_else(|| gettext("There's no config file to reset."))
+                    .and_then(|path| Config::reset_section(path, &section));
+                match reset {
+                    Ok(backup) => {
+                        self.config_sections.retain(|s| *s != section);
+                        let text = fill(
+                            gettext("The {0} settings are back to their defaults. The config as it was before is in {1}."),
+                            &[&section, &backup.display()],
+                        );
+                        UpdateAction::defer(async move {
+                            vgtk::message_dialog(
+                                vgtk::current_window().as_ref(),
+                                DialogFlags::empty(),
+                                MessageType::Info,
+                                ButtonsType::Ok,
+                                false,
+                                text,
+                            )
+                            .await;
+                            Message::NoOp
+                        })
+                    }
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT RESET THE SETTINGS!"), &err).await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            Message::FileError(error) => UpdateAction::defer(async move {
+                show_error(&gettext("AN ERROR HAS OCCURRED!"), &error.to_string()).await;
+                Message::NoOp
+            }),
+
+            Message::SelectFolder => UpdateAction::defer(async {
+                match select_file(
+                    &gettext("Select root folder of your crate"),
+                    FileChooserAction::SelectFolder,
+                )
+                .await
+                {
+                    Ok(Some(file)) => Message::FolderSelected(
+                        file.get_path()
+                            .and_then(|p| p.into_os_string().into_string().ok())
+                            .unwrap_or_else(|| "".to_string()),
+                    ),
+                    Ok(None) => Message::NoOp,
+                    Err(err) => Message::FileError(err),
+                }
+            }),
+
+            Message::FolderSelected(path) => {
+                // what's picked or dropped in from outside a Flatpak sandbox
+                // comes through the document portal
+                self.project_root = sandbox::project_root(&SystemRunner, Path::new(&path))
+                    .display()
+                    .to_string();
+                self.detect_project();
+                self.move_watch();
+                UpdateAction::Render
+            }
+
+            Message::ProjectRootEntered => {
+                self.cancel_restart();
+                self.detect_project();
+                self.move_watch();
+                UpdateAction::Render
+            }
+
+            Message::MoveFailed(error) => {
+                self.restarting = false;
+                UpdateAction::defer(async move {
+                    show_error(&gettext("COULD NOT WATCH THE NEW FOLDER!"), &error).await;
+                    Message::NoOp
+                })
+            }
+
+            Message::ToggleWatch => {
+                if let AppState::Idle = self.state {
+                    self.problems = self.check_problems();
+                    if !self.problems.is_empty() {
+                        return UpdateAction::Render;
+                    }
+                }
+
+                let outcome = match self.state {
+                    AppState::Watching => self.stop_watching(),
+                    AppState::Idle => match ProjectLock::acquire(&self.project_root) {
+                        Ok(Acquire::Owned(lock)) => {
+                            self.lock = Some(lock);
+                            self.start_watching()
+                        }
+                        Ok(Acquire::HeldBy(pid)) => {
+                            self.scope
+                                .as_ref()
+                                .unwrap()
+                                .send_message(Message::AlreadyWatched(pid));
+                            return UpdateAction::None;
+                        }
+                        Err(err) => {
+                            // not being able to coordinate shouldn't stop us watching
+                            self.warn(format!("Failed to lock project root: {}", err));
+                            self.start_watching()
+                        }
+                    },
+                };
+
+                self.watch_started(outcome)
+            }
+
+            Message::StartWatching => {
+                if let AppState::Idle = self.state {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::ToggleWatch);
+                }
+                UpdateAction::None
+            }
+
+            Message::AlreadyWatched(pid) => UpdateAction::defer(async move {
+                let dialog = MessageDialog::new(
+                    vgtk::current_object()
+                        .and_then(|w| w.downcast::<Window>().ok())
+                        .as_ref(),
+                    DialogFlags::MODAL,
+                    MessageType::Warning,
+                    ButtonsType::None,
+                    &fill(
+                        gettext("This project is already being watched by another instance (process {})."),
+                        &[&pid],
+                    ),
+                );
+                dialog.set_property_secondary_text(Some(&gettext(
+                    "Watching it here as well would compile everything twice. You can take \
+                     over the watch or follow the other instance's results read-only.",
+                )));
+                dialog.add_button(&gettext("Cancel"), ResponseType::Cancel);
+                dialog.add_button(
+                    &gettext("Watch Read-only"),
+                    ResponseType::Other(FOLLOW_RESPONSE),
+                );
+                dialog.add_button(
+                    &gettext("Take Over"),
+                    ResponseType::Other(TAKE_OVER_RESPONSE),
+                );
+                dialog.show();
+
+                let response = on_signal!(dialog, connect_response).await;
+                dialog.destroy();
+                match response {
+                    Ok(ResponseType::Other(TAKE_OVER_RESPONSE)) => Message::TakeOver,
+                    Ok(ResponseType::Other(FOLLOW_RESPONSE)) => Message::FollowWatch,
+                    _ => Message::NoOp,
+                }
+            }),
+
+            Message::TakeOver => {
+                let outcome = ProjectLock::take_over(&self.project_root).and_then(|lock| {
+                    self.lock = Some(lock);
+                    self.start_watching()
+                });
+                self.watch_started(outcome)
+            }
+
+            Message::FollowWatch => {
+                let outcome = self.start_following();
+                self.watch_started(outcome)
+            }
+
+            Message::FollowBacon => {
+                let outcome = ExternalSource::bacon(&self.project_root)
+                    .map(|source| self.start_following_external(source));
+                self.watch_started(outcome)
+            }
+
+            Message::SelectCargoWatchLog => UpdateAction::defer(async {
+                match select_file(
+                    &gettext("Select the cargo watch log"),
+                    FileChooserAction::Open,
+                )
+                .await
+                {
+                    Ok(Some(file)) => match file.get_path() {
+                        Some(path) => {
+                            Message::FollowCargoWatch(path.to_string_lossy().into_owned())
+                        }
+                        None => Message::NoOp,
+                    },
+                    Ok(None) => Message::NoOp,
+                    Err(err) => Message::FileError(err),
+                }
+            }),
+
+            Message::FollowCargoWatch(path) => {
+                self.start_following_external(ExternalSource::cargo_watch(path));
+                UpdateAction::Render
+            }
+
+            Message::SelectEventLog(original_timing) => UpdateAction::defer(async move {
+                match select_file(&gettext("Select the event log"), FileChooserAction::Open).await {
+                    Ok(Some(file)) => match file.get_path() {
+                        Some(path) => {
+                            Message::Replay(path.to_string_lossy().into_owned(), original_timing)
+                        }
+                        None => Message::NoOp,
+                    },
+                    Ok(None) => Message::NoOp,
+                    Err(err) => Message::FileError(err),
+                }
+            }),
+
+            Message::Replay(path, original_timing) => {
+                let outcome = self.start_replay(&path, original_timing);
+                self.watch_started(outcome)
+            }
+
+            Message::ReadStdin => {
+                self.start_reading_stdin();
+                UpdateAction::Render
+            }
+
+            Message::WatchFailed(error) => UpdateAction::defer(async move {
+                show_error(&gettext("COULD NOT WATCH PROJECT!"), &error).await;
+                Message::NoOp
+            }),
+
+            Message::WatcherStopped(reason) => {
+                self.watcher_stopped = Some(reason);
+                self.installing_toolchain = None;
+                self.fetch_progress = None;
+                self.build_progress = None;
+                self.stop_build_timer();
+                UpdateAction::Render
+            }
+
+            Message::HookFailed(reason) => {
+                self.hook_error = Some(reason);
+                UpdateAction::Render
+            }
+
+            Message::Warning(warning) => {
+                self.warning = Some(warning);
+                UpdateAction::Render
+            }
+
+            Message::RestartWatcher => {
+                let _ = self.stop_watching();
+                self.scope
+                    .as_ref()
+                    .unwrap()
+                    .send_message(Message::ToggleWatch);
+                UpdateAction::Render
+            }
+
+            Message::CheckHealth => {
+                // asking the tools for their versions takes a moment
+                let config = self.config.clone();
+                let (tx, rx) = oneshot::channel();
+                thread::spawn(move || {
+                    let _ = tx.send(health::check(&config));
+                });
+                let scope = self.scope.as_ref().unwrap().clone();
+                MainContext::ref_thread_default().spawn_local(async move {
+                    if let Ok(checks) = rx.await {
+                        scope.send_message(Message::HealthChecked(checks));
+                    }
+                });
+                UpdateAction::None
+            }
+
+            Message::HealthChecked(checks) => {
+                self.health = Some(checks);
+                if !self.config.health_checked {
+                    self.config.health_checked = true;
+                    self.save_config();
+                }
+                UpdateAction::Render
+            }
+
+            Message::CloseHealth => {
+                self.health = None;
+                UpdateAction::Render
+            }
+
+            Message::WindowResized(size, maximized) => {
+                // a maximized window's size isn't the one to come back to
+                if !maximized {
+                    self.window_size = Some(size);
+                }
+                self.maximized = maximized;
+                UpdateAction::None
+            }
+
+            Message::TabSwitched(tab) => {
+                self.current_tab = tab;
+                UpdateAction::None
+            }
+
+            Message::PathChanged(path) => {
+                self.project_root = path;
+                self.schedule_restart();
+                if self.problems.project_root.take().is_some() {
+                    UpdateAction::Render
+                } else {
+                    UpdateAction::None
+                }
+            }
+
+            Message::CommandChanged(command) => {
+                self.command = command;
+                self.schedule_restart();
+                // the welcome screen shows the command picked
+                if self.problems.command.take().is_some() || self.onboarding() {
+                    UpdateAction::Render
+                } else {
+                    UpdateAction::None
+                }
+            }
+
+            Message::SelectToolchain(toolchain) => {
+                let mut project = self.config.project(&self.project_root);
+                project.toolchain = Some(toolchain).filter(|t| !t.is_empty());
+                self.set_project(project);
+                self.list_targets();
+
+                // results from another compiler say nothing about this one's
+                if let Some(watcher) = self.watcher.clone() {
+                    self.clear_output();
+                    thread::spawn(move || watcher.inject(&[]));
+                }
+                UpdateAction::Render
+            }
+
+            Message::SelectTarget(target) => {
+                let mut project = self.config.project(&self.project_root);
+                project.target = Some(target).filter(|t| !t.is_empty());
+                self.set_project(project);
+
+                // the host's diagnostics aren't the target's
+                if let Some(watcher) = self.watcher.clone() {
+                    self.clear_output();
+                    thread::spawn(move || watcher.inject(&[]));
+                }
+                UpdateAction::Render
+            }
+
+            Message::ToggleFeature(feature) => {
+                let mut selection = FeatureSelection::of(&self.command);
+                selection.toggle(&feature);
+                self.select_features(selection)
+            }
+
+            Message::ToggleDefaultFeatures => {
+                let mut selection = FeatureSelection::of(&self.command);
+                selection.no_default_features = !selection.no_default_features;
+                self.select_features(selection)
+            }
+
+            Message::InstallingToolchain(channel) => {
+                self.installing_toolchain = Some(channel);
+                UpdateAction::Render
+            }
+
+            Message::RestartWatch => {
+                self.restart_timer = None;
+                self.restart_watch()
+            }
+
+            Message::BuildStarted => {
+                self.save_session();
+                self.restarting = false;
+                self.hook_error = None;
+                self.build_started = Some(Instant::now());
+                self.build_progress = None;
+                if let Some(pipeline) = self.pipeline.as_mut() {
+                    pipeline.reset();
+                }
+                if self.build_timer.is_none() {
+                    let scope = self.scope.as_ref().unwrap().clone();
+                    self.build_timer = Some(glib::timeout_add_seconds_local(1, move || {
+                        scope.send_message(Message::BuildTick);
+                        Continue(true)
+                    }));
+                }
+                UpdateAction::Render
+            }
+
+            Message::BuildTick => UpdateAction::Render,
+
+            Message::Fetching(progress) => {
+                self.fetch_progress = Some(progress);
+                UpdateAction::Render
+            }
+
+            Message::Compiling(progress) => {
+                let started = self.build_started.map(|started| started.elapsed());
+                if let (Some(pipeline), Some(finished), Some(at)) =
+                    (self.pipeline.as_mut(), progress.finished.as_ref(), started)
+                {
+                    pipeline.finish(finished, at);
+                }
+                self.build_progress = Some(progress);
+                UpdateAction::Render
+            }
+
+            Message::PipelineLoaded(pipeline) => {
+                self.pipeline = Some(pipeline);
+                UpdateAction::Render
+            }
+
+            Message::ExtraRoots(roots) => {
+                self.extra_roots = roots;
+                UpdateAction::Render
+            }
+
+            Message::ResultsArrived(mut result) => {
+                self.installing_toolchain = None;
+                self.fetch_progress = None;
+                self.build_progress = None;
+                self.stop_build_timer();
+                if let Some(lock) = self.lock.as_ref() {
+                    if !lock.is_held() {
+                        let _ = self.stop_watching();
+                        self.scope
+                            .as_ref()
+                            .unwrap()
+                            .send_message(Message::WatchFailed(gettext(
+                                "Another instance took over watching this project.",
+                            )));
+                        return UpdateAction::Render;
+                    }
+                    if let Err(err) = lock.write_status(&result) {
+                        self.warn(format!("Failed to publish watch status: {}", err));
+                    }
+                }
+
+                if !result.success {
+                    alerts::build_failed(self.config.alert, self.config.quiet_hours);
+                }
+                let previous_success = self.results.as_ref().map(|previous| previous.success);
+                if previous_success != Some(result.success) {
+                    self.status_changed = Some(SystemTime::now());
+                    if self.radiator && previous_success.is_some() {
+                        self.flash_radiator();
+                    }
+                }
+                if let Some(notice) =
+                    Notice::for_build(&self.project_root, previous_success, &result)
+                {
+                    self.notifiers.send(notice);
+                }
+                if !self.config.show_duplicates {
+                    result.dedup();
+                }
+                let project = self.project();
+                result.remove_ignored(&project.ignored_codes());
+                // with the counts the window shows
+                #[cfg(unix)]
+                if let Some(dbus) = self.dbus.as_ref() {
+                    dbus.publish(&result);
+                }
+                self.set_raw_output(&result.output);
+                // ids don't carry over between builds, but the diagnostic
+                // keyboard navigation is on often does
+                let current = self
+                    .current
+                    .and_then(|id| self.results.as_ref()?.diagnostic(id))
+                    .and_then(|diag| result.find_same(diag));
+                self.current = current;
+                self.snippets = RefCell::new(SnippetCache::new(&self.project_root));
+                self.modules = heatmap::module_stats(
+                    &self.project_root,
+                    &result,
+                    self.parser(&project).as_ref(),
+                );
+                self.cascades = Cascades::detect(&result);
+                self.list.forget_expanded();
+                self.history.push(BuildRecord::new(&result));
+                self.slowdown = self.history.slowdown(&self.config.slow_builds);
+                if let Some(slowdown) = self.slowdown.as_ref() {
+                    self.notifiers
+                        .send(Notice::for_slow_build(&self.project_root, slowdown));
+                }
+                self.since_previous = self
+                    .results
+                    .as_ref()
+                    .map(|previous| compare::diff_runs(previous, &result));
+                if let Some(file) = result.triggered_by.last() {
+                    self.focused_file = Some(file.clone());
+                }
+                self.results = Some(result);
+                self.mark_new();
+                self.scroll_results();
+                // whatever was edited to start the build may be a new change
+                self.list_changes();
+                UpdateAction::Render
+            }
+
+            Message::ScheduledResult(job, mut result) => {
+                result.remove_ignored(&self.project().ignored);
+                // only findings that are new since the job's previous run are
+                // worth interrupting for
+                if let Some(previous) = self.scheduled.get(&job) {
+                    let new = compare::diff(&job, previous, &result).introduced.len();
+                    if new > 0 {
+                        self.notifiers
+                            .send(Notice::for_scheduled(&self.project_root, &job, new));
+                    }
+                }
+                self.scheduled.insert(job, result);
+                UpdateAction::Render
+            }
+
+            Message::ApplyFix(id) => {
+                let diag = match self.results.as_ref().and_then(|r| r.diagnostic(id)) {
+                    Some(diag) => diag.clone(),
+                    None => return UpdateAction::None,
+                };
+                let project_root = self.project_root.clone();
+                UpdateAction::defer(async move {
+                    fixes::apply(project_root, diag).await;
+                    Message::NoOp
+                })
+            }
+
+            Message::Explain(code) => {
+                if let Some(explanation) = self.explanations.get(&code).cloned() {
+                    return UpdateAction::defer(async move {
+                        show_explanation(&code, &explanation).await;
+                        Message::NoOp
+                    });
+                }
+                // the project's own compiler, in its container if it builds
+                // in one
+                let project = self.project();
+                let runner = project.runner(&self.project_root);
+                let env = project.env_vars();
+                let project_root = PathBuf::from(&self.project_root);
+
+                UpdateAction::defer(async move {
+                    let (tx, rx) = oneshot::channel();
+                    let asked = code.clone();
+                    thread::spawn(move || {
+                        let _ = tx.send(explain::explain(
+                            runner.as_ref(),
+                            &project_root,
+                            &env,
+                            &asked,
+                        ));
+                    });
+                    let explained = rx.await.unwrap_or_else(|err| Err(format!("{:?}", err)));
+                    Message::Explained(code, explained)
+                })
+            }
+
+            Message::Explained(code, Ok(explanation)) => {
+                self.explanations.insert(code.clone(), explanation.clone());
+                UpdateAction::defer(async move {
+                    show_explanation(&code, &explanation).await;
+                    Message::NoOp
+                })
+            }
+
+            Message::Explained(code, Err(error)) => UpdateAction::defer(async move {
+                let heading = fill(gettext("COULD NOT EXPLAIN {}!"), &[&code]);
+                show_error(&heading, &error).await;
+                Message::NoOp
+            }),
+
+            Message::SelectExportPath => UpdateAction::defer(async {
+                match select_file(&gettext("Export results"), FileChooserAction::Save).await {
+                    Ok(Some(file)) => match file.get_path() {
+                        Some(path) => Message::Export(path.to_string_lossy().into_owned()),
+                        None => Message::NoOp,
+                    },
+                    Ok(None) => Message::NoOp,
+                    Err(err) => Message::FileError(err),
+                }
+            }),
+
+            Message::Export(path) => {
+                let result = match self.results.as_ref() {
+                    Some(result) => result,
+                    None => return UpdateAction::None,
+                };
+                if let Err(err) = export::write_to(result, &path) {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::ExportFailed(err));
+                }
+                UpdateAction::None
+            }
+
+            Message::ExportFailed(error) => UpdateAction::defer(async move {
+                show_error(&gettext("COULD NOT EXPORT RESULTS!"), &error).await;
+                Message::NoOp
+            }),
+
+            Message::CopyMessage(id) => {
+                if let Some(diag) = self.results.as_ref().and_then(|r| r.diagnostic(id)) {
+                    copy_to_clipboard(diag.to_string().trim_end());
+                }
+                UpdateAction::None
+            }
+
+            Message::CopyLocation(id) => {
+                let location = self
+                    .results
+                    .as_ref()
+                    .and_then(|r| r.diagnostic(id))
+                    .and_then(|diag| {
+                        diag.file.as_ref().map(|file| match diag.line {
+                            Some(line) => format!("{}:{}", file, line),
+                            None => file.clone(),
+                        })
+                    });
+                if let Some(location) = location {
+                    copy_to_clipboard(&location);
+                }
+                UpdateAction::None
+            }
+
+            Message::ShowMoreResults => {
+                let total = self
+                    .results
+                    .as_ref()
+                    .map(|r| self.listing().total_rows(r))
+                    .unwrap_or_default();
+                if self.visible_rows >= total {
+                    return UpdateAction::None;
+                }
+                self.visible_rows += RESULTS_PAGE;
+                UpdateAction::Render
+            }
+
+            Message::NextDiagnostic => {
+                self.select_next(true, false);
+                UpdateAction::Render
+            }
+
+            Message::PreviousDiagnostic => {
+                self.select_next(false, false);
+                UpdateAction::Render
+            }
+
+            Message::NextError => {
+                self.select_next(true, true);
+                UpdateAction::Render
+            }
+
+            Message::PreviousError => {
+                self.select_next(false, true);
+                UpdateAction::Render
+            }
+
+            Message::OpenCurrent => {
+                if let Some(id) = self.current {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::OpenDiagnostic(id));
+                }
+                UpdateAction::None
+            }
+
+            Message::ClearOutput => {
+                self.clear_output();
+                UpdateAction::Render
+            }
+
+            Message::ShowCommandPalette => {
+                let window = vgtk::current_window()
+                    .and_then(|window| window.downcast::<ApplicationWindow>().ok());
+                if let Some(window) = window {
+                    command_palette::show(&window, self.palette_commands());
+                }
+                UpdateAction::None
+            }
+
+            Message::OpenConfig => {
+                // the defaults are written out for there to be something to edit
+                let opened = Config::path()
+                    .ok_or_else(|| gettext("There's no config file to open."))
+                    .and_then(|path| {
+                        if !path.exists() {
+                            self.config.save()?;
+                        }
+                        let uri = File::new_for_path(&path).get_uri();
+                        AppInfo::launch_default_for_uri(&uri, None::<&AppLaunchContext>)
+                            .map_err(|err| err.to_string())
+                    });
+                match opened {
+                    Ok(()) => UpdateAction::None,
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT OPEN THE CONFIG FILE!"), &err).await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            Message::CopyAll => {
+                if let Some(result) = self.results.as_ref() {
+                    copy_to_clipboard(result.to_string().trim_end());
+                }
+                UpdateAction::None
+            }
+
+            Message::CopySummary => {
+                if let Some(result) = self.results.as_ref() {
+                    copy_to_clipboard(&run_summary(result));
+                }
+                UpdateAction::None
+            }
+
+            // the window's project and command, unless the call names others
+            #[cfg(unix)]
+            Message::Dbus(dbus::Call::StartWatching {
+                project_root,
+                command,
+            }) => {
+                if !project_root.is_empty() && project_root != self.project_root {
+                    self.project_root = project_root;
+                    self.detect_project();
+                    self.move_watch();
+                }
+                if !command.is_empty() && command != self.command {
+                    self.command = command;
+                    if let Some(watcher) = self.watcher.as_ref() {
+                        watcher.set_command(&self.command);
+                    }
+                }
+                if let AppState::Idle = self.state {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::ToggleWatch);
+                }
+                UpdateAction::Render
+            }
+
+            #[cfg(unix)]
+            Message::Dbus(dbus::Call::StopWatching) => {
+                if let AppState::Watching = self.state {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::ToggleWatch);
+                }
+                UpdateAction::None
+            }
+
+            #[cfg(unix)]
+            Message::Dbus(dbus::Call::TriggerRun) => {
+                self.scope.as_ref().unwrap().send_message(Message::RunNow);
+                UpdateAction::None
+            }
+
+            Message::RunNow => {
+                if let Some(watcher) = self.watcher.clone() {
+                    thread::spawn(move || watcher.inject(&[]));
+                }
+                UpdateAction::None
+            }
+
+            Message::OpenQuickfix(id) => {
+                let result = match self.results.as_ref() {
+                    Some(result) => result,
+                    None => return UpdateAction::None,
+                };
+                let file = match id {
+                    Some(id) => match result.diagnostic(id).and_then(|d| d.file.as_deref()) {
+                        Some(file) => Some(file),
+                        None => return UpdateAction::None,
+                    },
+                    None => None,
+                };
+
+                let opened = quickfix::write(&self.project_root, result, file).and_then(|path| {
+                    quickfix::open(self.config.quickfix_command(), path, &self.project_root)
+                });
+                if let Err(err) = opened {
+                    self.scope
+                        .as_ref()
+                        .unwrap()
+                        .send_message(Message::QuickfixFailed(err));
+                }
+                UpdateAction::None
+            }
+
+            Message::OpenDiagnostic(id) => {
+                let scope = self.scope.as_ref().unwrap();
+                let command = match self.config.open_command(&SystemRunner) {
+                    Some(Ok(command)) => command,
+                    Some(Err(err)) => {
+                        scope.send_message(Message::QuickfixFailed(err));
+                        return UpdateAction::None;
+                    }
+                    None => {
+                        scope.send_message(Message::OpenQuickfix(Some(id)));
+                        return UpdateAction::None;
+                    }
+                };
+                let diag = match self.results.as_ref().and_then(|r| r.diagnostic(id)) {
+                    Some(diag) => diag,
+                    None => return UpdateAction::None,
+                };
+                if let Err(err) = quickfix::open_at(&command, &self.project_root, diag) {
+                    scope.send_message(Message::QuickfixFailed(err));
+                }
+                UpdateAction::None
+            }
+
+            Message::Reveal(path) => {
+                let path = Path::new(&path);
+                let dir = if path.is_dir() {
+                    path
+                } else {
+                    path.parent().unwrap_or(path)
+                };
+                let uri = File::new_for_path(dir).get_uri();
+                match AppInfo::launch_default_for_uri(&uri, None::<&AppLaunchContext>) {
+                    Ok(()) => UpdateAction::None,
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(
+                            &gettext("COULD NOT OPEN THE FILE MANAGER!"),
+                            &err.to_string(),
+                        )
+                        .await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            // Opens a new issue page with the diagnostic filled in, on the
+            // tracker of whichever crate it's about.
+            Message::FileIssue(id) => {
+                let result = match self.results.as_ref() {
+                    Some(result) => result,
+                    None => return UpdateAction::None,
+                };
+                let diag = match result.diagnostic(id) {
+                    Some(diag) => diag,
+                    None => return UpdateAction::None,
+                };
+                let template = self.config.project(&self.project_root).issue_url;
+                let opened = IssueDraft::new(
+                    &SystemRunner,
+                    Path::new(&self.project_root),
+                    diag,
+                    result.rustc_version.as_deref(),
+                    &self.command,
+                )
+                .and_then(|draft| {
+                    let url = draft.url(template.as_deref());
+                    AppInfo::launch_default_for_uri(&url, None::<&AppLaunchContext>)
+                        .map_err(|e| e.to_string())
+                });
+                match opened {
+                    Ok(()) => UpdateAction::None,
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT FILE AN ISSUE!"), &err).await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            Message::CopyLink(id) => {
+                let diag = match self.results.as_ref().and_then(|r| r.diagnostic(id)) {
+                    Some(diag) => diag,
+                    None => return UpdateAction::None,
+                };
+                let template = self.config.project(&self.project_root).permalink_url;
+                let link = issue::permalink(
+                    &SystemRunner,
+                    Path::new(&self.project_root),
+                    diag,
+                    template.as_deref(),
+                );
+                match link {
+                    Ok(link) => {
+                        copy_to_clipboard(&link);
+                        UpdateAction::None
+                    }
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT LINK TO THE CODE!"), &err).await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            Message::RevealDiagnostic(id) => {
+                let file = self
+                    .results
+                    .as_ref()
+                    .and_then(|result| result.diagnostic(id))
+                    .and_then(|diag| diag.file.as_ref());
+                let path = match file {
+                    Some(file) => Path::new(&self.project_root).join(file),
+                    None => return UpdateAction::None,
+                };
+                self.scope
+                    .as_ref()
+                    .unwrap()
+                    .send_message(Message::Reveal(path.to_string_lossy().into_owned()));
+                UpdateAction::None
+            }
+
+            Message::OpenTerminal(dir) => {
+                let dir = dir.unwrap_or_else(|| self.project_root.clone());
+                match quickfix::open_terminal(self.config.terminal_command(), dir) {
+                    Ok(()) => UpdateAction::None,
+                    Err(err) => UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT OPEN A TERMINAL!"), &err).await;
+                        Message::NoOp
+                    }),
+                }
+            }
+
+            Message::TestEditor => {
+                let outcome = match self.config.editor.as_ref() {
+                    Some(editor) => editor.check(&SystemRunner),
+                    None => Err(gettext("There's no `editor` preset in config.toml.")),
+                };
+                UpdateAction::defer(async move {
+                    match outcome {
+                        Ok(found) => {
+                            vgtk::message_dialog(
+                                vgtk::current_window().as_ref(),
+                                DialogFlags::empty(),
+                                MessageType::Info,
+                                ButtonsType::Ok,
+                                false,
+                                found,
+                            )
+                            .await;
+                        }
+                        Err(err) => show_error(&gettext("COULD NOT FIND THE EDITOR!"), &err).await,
+                    }
+                    Message::NoOp
+                })
+            }
+
+            Message::QuickfixFailed(error) => UpdateAction::defer(async move {
+                show_error(&gettext("COULD NOT OPEN EDITOR!"), &error).await;
+                Message::NoOp
+            }),
+
+            Message::SelectCompareRef => UpdateAction::defer(async {
+                let git_ref = ask_git_ref(
+                    &gettext("Compare with Branch"),
+                    &gettext("Compare"),
+                    &gettext(
+                        "Branch, tag or commit to build in a temporary worktree. Diagnostics \
+                         are compared with the latest results.",
+                    ),
+                    "main",
+                )
+                .await;
+                match git_ref {
+                    Some(git_ref) => Message::CompareWith(git_ref),
+                    None => Message::NoOp,
+                }
+            }),
+
+            Message::CompareWith(git_ref) => {
+                let current = match self.results.as_ref() {
+                    Some(result) => result.clone(),
+                    None => return UpdateAction::None,
+                };
+                let project_root = self.project_root.clone();
+                let command = self.command.clone();
+                let env = self.project().env_vars();
+                self.comparing = Some(git_ref.clone());
+
+                // building another ref can take as long as a clean build, so
+                // report back whenever it's done and show progress meanwhile
+                let (tx, rx) = oneshot::channel();
+                thread::spawn(move || {
+                    let _ = tx.send(compare::compare(
+                        &project_root,
+                        &git_ref,
+                        &command,
+                        &env,
+                        &current,
+                    ));
+                });
+                let scope = self.scope.as_ref().unwrap().clone();
+                MainContext::ref_thread_default().spawn_local(async move {
+                    scope.send_message(match rx.await {
+                        Ok(Ok(comparison)) => Message::ComparisonReady(comparison),
+                        Ok(Err(err)) => Message::CompareFailed(err),
+                        Err(err) => Message::CompareFailed(format!("{:?}", err)),
+                    });
+                });
+                UpdateAction::Render
+            }
+
+            Message::Bisect(message) => {
+                let target = BisectTarget {
+                    results: self.results.as_ref(),
+                    project_root: &self.project_root,
+                    command: &self.command,
+                    env: self.project().env_vars(),
+                };
+                let scope = self.scope.as_ref().unwrap();
+                self.bisecting.update(message, target, scope)
+            }
+
+            Message::ComparisonReady(comparison) => {
+                self.comparing = None;
+                self.comparison = Some(comparison);
+                UpdateAction::Render
+            }
+
+            Message::CompareFailed(error) => {
+                self.comparing = None;
+                UpdateAction::defer(async move {
+                    show_error(&gettext("COULD NOT COMPARE!"), &error).await;
+                    Message::NoOp
+                })
+            }
+
+            Message::ToggleOption(option) => {
+                let relisted = option.toggle(&mut self.config);
+                self.save_config();
+                if relisted {
+                    self.current = None;
+                    self.visible_rows = RESULTS_PAGE;
+                }
+                UpdateAction::Render
+            }
+
+            Message::FocusFile(file) => {
+                let file = Path::new(&file)
+                    .strip_prefix(&self.project_root)
+                    .map(|file| file.to_string_lossy().into_owned())
+                    .unwrap_or(file);
+                self.focused_file = Some(file);
+                if self.config.focus_file {
+                    UpdateAction::Render
+                } else {
+                    UpdateAction::None
+                }
+            }
+
+            Message::List(message) => {
+                if self.list.update(message) {
+                    self.save_session();
+                    self.current = None;
+                    self.visible_rows = RESULTS_PAGE;
+                }
+                UpdateAction::Render
+            }
+
+            Message::ToggleOnlyChanges => {
+                let mut project = self.config.project(&self.project_root);
+                project.only_changes = !project.only_changes;
+                self.set_project(project);
+                self.changed_files = None;
+                self.current = None;
+                self.visible_rows = RESULTS_PAGE;
+                self.list_changes();
+                UpdateAction::Render
+            }
+
+            Message::TrustLocalConfig(trusted) => {
+                let mut project = self.config.project(&self.project_root);
+                project.trust_local_config = Some(trusted);
+                self.set_project(project);
+                if trusted {
+                    self.detect_project();
+                    self.schedule_jobs();
+                }
+                UpdateAction::Render
+            }
+
+            Message::ChangesListed(changes) => {
+                // unless turned off while git was running
+                if self.config.project(&self.project_root).only_changes {
+                    self.changed_files = Some(changes);
+                }
+                UpdateAction::Render
+            }
+
+            Message::SnapshotBaseline => {
+                let baseline = match self.results.as_ref() {
+                    Some(result) => Baseline::capture(result),
+                    None => return UpdateAction::None,
+                };
+                if let Err(err) = baseline.save(&self.project_root) {
+                    return UpdateAction::defer(async move {
+                        show_error(&gettext("COULD NOT SAVE THE BASELINE!"), &err).await;
+                        Message::NoOp
+                    });
+                }
+                self.baseline = Some(baseline);
+                self.mark_new();
+                UpdateAction::Render
+            }
+
+            Message::ClearBaseline => {
+                if let Err(err) = Baseline::remove(&self.project_root) {
+                    self.warn(format!("Failed to remove baseline: {}", err));
+                }
+                self.baseline = None;
+                self.mark_new();
+                UpdateAction::Render
+            }
+
+            Message::ListChangesFailed(error) => {
+                // e.g. not a repository; listing everything beats listing
+                // nothing
+                let mut project = self.config.project(&self.project_root);
+                project.only_changes = false;
+                self.set_project(project);
+                self.changed_files = None;
+                UpdateAction::defer(async move {
+                    show_error(&gettext("COULD NOT LIST CHANGED FILES!"), &error).await;
+                    Message::NoOp
+                })
+            }
+
+            Message::SetSortOrder(order) => {
+                self.config.sort_order = order;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::SetScrollTo(scroll_to) => {
+                self.config.scroll_to = scroll_to;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::SetResultsLayout(layout) => {
+                self.config.results_layout = layout;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::TogglePane(pane) => {
+                if !self.collapsed_panes.remove(&pane) {
+                    self.collapsed_panes.insert(pane);
+                }
+                UpdateAction::Render
+            }
+
+            Message::SplitMoved(position) => {
+                self.split_position = Some(position);
+                UpdateAction::None
+            }
+
+            Message::SetTheme(theme) => {
+                self.config.theme = theme;
+                style::apply(theme);
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::SetAlert(alert) => {
+                self.config.alert = alert;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            // the runner is picked as watching starts
+            Message::SetPriority(priority) => {
+                self.config.build_priority = priority;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::ToggleBuildWhenFocused => {
+                self.config.build_only_when_focused = !self.config.build_only_when_focused;
+                self.save_config();
+                if let Some(watcher) = self.watcher.as_ref() {
+                    watcher.set_held(self.held());
+                }
+                UpdateAction::Render
+            }
+
+            Message::ToggleMinimizeOnClose => {
+                self.config.minimize_on_close = !self.config.minimize_on_close;
+                self.save_config();
+                UpdateAction::Render
+            }
+
+            Message::ToggleBuildOnAcPower => {
+                self.config.build_only_on_ac_power = !self.config.build_only_on_ac_power;
+                self.save_config();
+                if let Some(watcher) = self.watcher.as_ref() {
+                    watcher.set_ac_power_only(self.config.build_only_on_ac_power);
+                }
+                UpdateAction::Render
+            }
+
+            Message::WindowFocused(focused) => {
+                self.focused = focused;
+                if let Some(watcher) = self.watcher.as_ref() {
+                    watcher.set_held(self.held());
+                }
+                UpdateAction::None
+            }
+
+            Message::EditEnvironment => {
+                let project_root = self.project_root.clone();
+                let env = config::format_env(&self.config.project(&project_root).env);
+
+                UpdateAction::defer(async move {
+                    let hint = fill(
+                        gettext("Variables to set when building {}, one KEY=value per line."),
+                        &[&project_root],
+                    );
+                    match edit_text(&gettext("Environment"), &hint, &env).await {
+                        Some(env) => Message::EnvironmentEdited(env),
+                        None => Message::NoOp,
+                    }
+                })
+            }
+
+            Message::EnvironmentEdited(env) => {
+                let message = match config::parse_env(&env) {
+                    Ok(env) => Message::SetEnvironment(env),
+                    Err(err) => Message::InvalidEnvironment(err),
+                };
+                self.scope.as_ref().unwrap().send_message(message);
+                UpdateAction::None
+            }
+
+            Message::SetEnvironment(env) => {
+                let mut project = self.config.project(&self.project_root);
+                project.env = env;
+                self.set_project(project);
+                UpdateAction::None
+            }
+
+            Message::EditIgnored => {
+                let project_root = self.project_root.clone();
+                let ignored = config::format_ignored(&self.config.project(&project_root).ignored);
+
+                UpdateAction::defer(async move {
+                    let hint = fill(
+                        gettext(
+                            "Error codes and lints to leave out when building {}, e.g. E0601 or \
+                             dead_code, one per line. Codes taken off the list show up again \
+                             from the next build.",
+                        ),
+                        &[&project_root],
+                    );
+                    match edit_text(&gettext("Ignored Codes"), &hint, &ignored).await {
+                        Some(ignored) => Message::SetIgnored(config::parse_ignored(&ignored)),
+                        None => Message::NoOp,
+                    }
+                })
+            }
+
+            Message::SetIgnored(ignored) => {
+                let mut project = self.config.project(&self.project_root);
+                let parser = self.parser(&project);
+                if let Some(result) = self.results.as_mut() {
+                    result.remove_ignored(&ignored);
+                    self.current = None;
+                    self.modules =
+                        heatmap::module_stats(&self.project_root, result, parser.as_ref());
+                    self.cascades = Cascades::detect(result);
+                    self.list.forget_expanded();
+                }
+                project.ignored = ignored;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::ToggleOffline => {
+                let mut project = self.config.project(&self.project_root);
+                project.offline = !project.offline;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::ToggleLogRuns => {
+                let mut project = self.config.project(&self.project_root);
+                project.log_runs = !project.log_runs;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::ToggleWatchIgnored => {
+                let mut project = self.config.project(&self.project_root);
+                project.watch_ignored = !project.watch_ignored;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::ToggleWarningsFail => {
+                let mut project = self.config.project(&self.project_root);
+                project.warnings_fail = !project.warnings_fail;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::SetParser(parser) => {
+                let mut project = self.config.project(&self.project_root);
+                project.parser = parser;
+                self.set_project(project);
+                UpdateAction::Render
+            }
+
+            Message::SelectProfile(name) => {
+                let mut project = self.config.project(&self.project_root);
+                let command = match project.switch_profile(&name, &self.command) {
+                    Some(command) => command,
+                    None => return UpdateAction::None,
+                };
+                self.set_project(project);
+                self.command = command;
+
+                // results of the old command say nothing about the new one
+                if let Some(watcher) = self.watcher.clone() {
+                    watcher.set_command(&self.command);
+                    self.watched_command = Some(self.command.clone());
+                    self.clear_output();
+                    thread::spawn(move || watcher.inject(&[]));
+                }
+                UpdateAction::Render
+            }
+
+            Message::FetchDependencies => {
+                let project_root = self.project_root.clone();
+                let env = self.project().env_vars();
+
+                UpdateAction::defer(async move {
+                    let (tx, rx) = oneshot::channel();
+                    thread::spawn(move || {
+                        let _ = tx.send(cargo::fetch_with(&SystemRunner, &project_root, &env));
+                    });
+
+                    match rx.await {
+                        Ok(Ok(())) => Message::DependenciesFetched,
+                        Ok(Err(err)) => Message::FetchFailed(err),
+                        Err(err) => Message::FetchFailed(format!("{:?}", err)),
+                    }
+                })
+            }
+
+            Message::DependenciesFetched => {
+                // nothing on disk changed that the watcher cares about, so
+                // ask it for a build; off the UI thread since it compiles
+                if let Some(watcher) = self.watcher.clone() {
+                    thread::spawn(move || watcher.inject(&[]));
+                }
+                UpdateAction::None
+            }
+
+            Message::FetchFailed(error) => UpdateAction::defer(async move {
+                show_error(&gettext("COULD NOT FETCH DEPENDENCIES!"), &error).await;
+                Message::NoOp
+            }),
+
+            Message::InvalidEnvironment(error) => UpdateAction::defer(async move {
+                show_error(&gettext("INVALID ENVIRONMENT!"), &error).await;
+                Message::NoOp
+            }),
+
+            Message::PollProjects => {
+                let mut roots = self
+                    .config
+                    .projects
+                    .keys()
+                    .cloned()
+                    .collect::<BTreeSet<_>>();
+                if !self.project_root.is_empty() {
+                    roots.insert(self.project_root.clone());
+                }
+                if roots.len() < 2 {
+                    if self.projects.is_empty() {
+                        return UpdateAction::None;
+                    }
+                    self.projects.clear();
+                    return UpdateAction::Render;
+                }
+
+                let mut changed = false;
+                let mut previous = std::mem::take(&mut self.projects);
+                for root in roots {
+                    let mut status = match previous.iter().position(|s| s.project_root == root) {
+                        Some(index) => previous.swap_remove(index),
+                        None => match ProjectStatus::new(&root) {
+                            Ok(status) => {
+                                changed = true;
+                                status
+                            }
+                            Err(err) => {
+                                self.warn(format!("Failed to look up {}: {}", root, err));
+                                continue;
+                            }
+                        },
+                    };
+                    match status.poll() {
+                        Ok(polled) => changed |= polled,
+                        Err(err) => {
+                            self.warn(format!("Failed to read results of {}: {}", root, err))
+                        }
+                    }
+                    self.projects.push(status);
+                }
+                changed |= !previous.is_empty();
+
+                if changed {
+                    UpdateAction::Render
+                } else {
+                    UpdateAction::None
+                }
+            }
+
+            Message::OpenProject(project_root) => {
+                self.props.on_open_project.send(project_root);
+                UpdateAction::None
+            }
+
+            Message::QueueChanged => {
+                self.jobs = self.props.build_queue.jobs();
+                UpdateAction::Render
+            }
+
+            Message::ToggleRadiator => {
+                let window = vgtk::current_window();
+                if self.radiator {
+                    self.stop_radiator();
+                    if let Some(window) = window {
+                        window.unfullscreen();
+                    }
+                } else {
+                    self.radiator = true;
+                    let scope = self.scope.as_ref().unwrap().clone();
+                    self.radiator_timer = Some(glib::timeout_add_seconds_local(1, move || {
+                        scope.send_message(Message::RadiatorTick);
+                        Continue(true)
+                    }));
+                    if let Some(window) = window {
+                        window.fullscreen();
+                    }
+                }
+                UpdateAction::Render
+            }
+
+            Message::RadiatorTick => UpdateAction::Render,
+
+            Message::StopFlashing => {
+                self.radiator_flash = None;
+                UpdateAction::Render
+            }
+
+            Message::Closed => {
+                // the app holds on to the models of closed windows, so this
+                // one has to stop everything it's doing itself, builds too
+                if let Some(session) = self.session.as_mut() {
+                    session.abort();
+                }
+                let _ = self.stop_watching();
+                self.stop_radiator();
+                self.remember_window();
+                if let Some(poll) = self.projects_poll.take() {
+                    if let Some(source) = MainContext::ref_thread_default().find_source_by_id(&poll)
+                    {
+                        source.destroy();
+                    }
+                }
+                self.props.on_closed.send(());
+                UpdateAction::None
+            }
+        }
+    }
+
+    fn view(&self) -> VNode<Model> {
+        // feature flags only mean something to cargo
+        let features = FeatureSelection::of(&self.command);
+        let features_enabled = self.following.is_none()
+            && cargo::program(&self.command).ok().flatten().as_deref() == Some("cargo");
+
+        // the window as it was last closed
+        let (width, height, maximized, tab) = match self.config.window.as_ref() {
+            Some(window) => (
+                window.width,
+                window.height,
+                window.maximized,
+                window.tab.clone(),
+            ),
+            None => (WINDOW_WIDTH, WINDOW_HEIGHT, false, None),
+        };
+        let has_errors = self
+            .results
+            .as_ref()
+            .map(|result| !result.errors.is_empty())
+            .unwrap_or(false);
+        let previous_tooltip = gettext("Previous error");
+        let next_tooltip = gettext("Next error");
+        let sort_tooltip = gettext("Sort by");
+        let copy_tooltip = gettext("Copy all output");
+        let menu_name = gettext("Main menu");
+        let diagnostics_tab = gettext("Diagnostics");
+        let raw_output_tab = gettext("Raw output");
+
+        gtk! {
+            <ApplicationWindow default_width=width default_height=height border_width=20
+                    close_guard=self.close_guard() on destroy=|_| Message::Closed
+                    on realize=|window| {
+                        if maximized {
+                            window.maximize();
+                        }
+                        // folders and manifests dragged in from a file manager
+                        window.drag_dest_set(DestDefaults::ALL, &[], DragAction::COPY);
+                        window.drag_dest_add_uri_targets();
+                        Message::NoOp
+                    }
+                    on size_allocate=|window, _| Message::WindowResized(window.get_size(), window.is_maximized())
+                    on property_is_active_notify=|window| Message::WindowFocused(window.is_active())
+                    on drag_data_received=|_, _, _, _, data, _, _| match project::dropped_root(&data.get_uris()) {
+                        Some(project_root) => Message::FolderSelected(project_root),
+                        None => Message::NoOp,
+                    }>
+                <SimpleAction::new("copy-message", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::CopyMessage(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("copy-location", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::CopyLocation(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("copy-link", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::CopyLink(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("copy-all", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::CopyAll />
+
+                <SimpleAction::new("next-diagnostic", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::NextDiagnostic />
+
+                <SimpleAction::new("previous-diagnostic", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::PreviousDiagnostic />
+
+                <SimpleAction::new("next-error", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::NextError />
+
+                <SimpleAction::new("previous-error", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::PreviousError />
+
+                <SimpleAction::new("open-current", None)
+                        enabled={ self.current.is_some() }
+                        on activate=|a, _| Message::OpenCurrent />
+
+                <SimpleAction::new("clear", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::ClearOutput />
+
+                <SimpleAction::new("command-palette", None) enabled=true
+                        on activate=|a, _| Message::ShowCommandPalette />
+
+                <SimpleAction::new("toggle-watch", None) enabled=true
+                        on activate=|a, _| Message::ToggleWatch />
+
+                <SimpleAction::new("run-now", None) enabled={ self.watcher.is_some() }
+                        on activate=|a, _| Message::RunNow />
+
+                <SimpleAction::new("open-config", None) enabled=true
+                        on activate=|a, _| Message::OpenConfig />
+
+                <SimpleAction::new("open-diagnostic", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::OpenDiagnostic(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("quickfix-file", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::OpenQuickfix(Some(id)),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("quickfix", None) enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::OpenQuickfix(None) />
+
+                <SimpleAction::new("test-editor", None) enabled=true
+                        on activate=|a, _| Message::TestEditor />
+
+                <SimpleAction::new("reset-config-section", Some(VariantTy::new("s").unwrap()))
+                        enabled=self.safe_mode
+                        on activate=|a, value| match value.and_then(|v| v.get_str()) {
+                            Some(section) => Message::ResetConfigSection(section.to_string()),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("reveal", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match value.and_then(|v| v.get_str()) {
+                            Some(path) => Message::Reveal(path.to_string()),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("reveal-diagnostic", Some(VariantTy::new("s").unwrap()))
+                        enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::RevealDiagnostic(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("file-issue", Some(VariantTy::new("s").unwrap()))
+                        enabled=true
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::FileIssue(id),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("bisect", Some(VariantTy::new("s").unwrap()))
+                        enabled={ !self.bisecting.is_running() }
+                        on activate=|a, value| match parse_id(value) {
+                            Some(id) => Message::Bisect(BisectMessage::SelectStart(id)),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("terminal", Some(VariantTy::new("s").unwrap())) enabled=true
+                        on activate=|a, value| match value.and_then(|v| v.get_str()) {
+                            Some(dir) => Message::OpenTerminal(Some(dir.to_string())),
+                            None => Message::NoOp,
+                        } />
+
+                <SimpleAction::new("terminal-here", None) enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::OpenTerminal(None) />
+
+                <SimpleAction::new("baseline", None)
+                        enabled={ self.results.is_some() && !self.project_root.is_empty() }
+                        on activate=|a, _| Message::SnapshotBaseline />
+
+                <SimpleAction::new("clear-baseline", None) enabled={ self.baseline.is_some() }
+                        on activate=|a, _| Message::ClearBaseline />
+
+                <SimpleAction::new_stateful("hide-baseline", None, &self.config.hide_baseline.to_variant())
+                        state=&self.config.hide_baseline.to_variant()
+                        enabled={ self.baseline.is_some() }
+                        on activate=|a, _| Message::ToggleOption(ListOption::HideBaseline) />
+
+                <SimpleAction::new("check-health", None)
+                        on activate=|a, _| Message::CheckHealth />
+
+                <SimpleAction::new("compare", None)
+                        enabled={ self.results.is_some() && self.comparing.is_none() }
+                        on activate=|a, _| Message::SelectCompareRef />
+
+                <SimpleAction::new("environment", None) enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::EditEnvironment />
+                <SimpleAction::new("ignored", None) enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::EditIgnored />
+
+                <SimpleAction::new("export", None)
+                        enabled={ self.results.is_some() }
+                        on activate=|a, _| Message::SelectExportPath />
+
+                <SimpleAction::new("follow-bacon", None) enabled={ self.state.map(|| true, || false) }
+                        on activate=|a, _| Message::FollowBacon />
+
+                <SimpleAction::new("follow-cargo-watch", None) enabled={ self.state.map(|| true, || false) }
+                        on activate=|a, _| Message::SelectCargoWatchLog />
+
+                <SimpleAction::new("replay", None) enabled={ self.state.map(|| true, || false) }
+                        on activate=|a, _| Message::SelectEventLog(false) />
+
+                <SimpleAction::new("replay-timed", None) enabled={ self.state.map(|| true, || false) }
+                        on activate=|a, _| Message::SelectEventLog(true) />
+
+                <SimpleAction::new_stateful("theme", Some(VariantTy::new("s").unwrap()), &self.config.theme.as_str().to_variant())
+                        state=&self.config.theme.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(theme) => Message::SetTheme(theme),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("alert", Some(VariantTy::new("s").unwrap()), &self.config.alert.as_str().to_variant())
+                        state=&self.config.alert.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(alert) => Message::SetAlert(alert),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("priority", Some(VariantTy::new("s").unwrap()), &self.config.build_priority.as_str().to_variant())
+                        state=&self.config.build_priority.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(priority) => Message::SetPriority(priority),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("build-when-focused", None, &self.config.build_only_when_focused.to_variant())
+                        state=&self.config.build_only_when_focused.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleBuildWhenFocused />
+
+                <SimpleAction::new_stateful("build-on-ac-power", None, &self.config.build_only_on_ac_power.to_variant())
+                        state=&self.config.build_only_on_ac_power.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleBuildOnAcPower />
+
+                <SimpleAction::new_stateful("minimize-on-close", None, &self.config.minimize_on_close.to_variant())
+                        state=&self.config.minimize_on_close.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleMinimizeOnClose />
+
+                <SimpleAction::new_stateful("sort-order", Some(VariantTy::new("s").unwrap()), &self.config.sort_order.as_str().to_variant())
+                        state=&self.config.sort_order.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(order) => Message::SetSortOrder(order),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("scroll-to", Some(VariantTy::new("s").unwrap()), &self.config.scroll_to.as_str().to_variant())
+                        state=&self.config.scroll_to.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(scroll_to) => Message::SetScrollTo(scroll_to),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("results-layout", Some(VariantTy::new("s").unwrap()), &self.config.results_layout.as_str().to_variant())
+                        state=&self.config.results_layout.as_str().to_variant()
+                        enabled=true
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(layout) => Message::SetResultsLayout(layout),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("offline", None, &self.config.project(&self.project_root).offline.to_variant())
+                        state=&self.config.project(&self.project_root).offline.to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::ToggleOffline />
+
+                <SimpleAction::new_stateful("log-runs", None, &self.config.project(&self.project_root).log_runs.to_variant())
+                        state=&self.config.project(&self.project_root).log_runs.to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::ToggleLogRuns />
+
+                <SimpleAction::new_stateful("skip-ignored", None, &(!self.config.project(&self.project_root).watch_ignored).to_variant())
+                        state=&(!self.config.project(&self.project_root).watch_ignored).to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::ToggleWatchIgnored />
+
+                <SimpleAction::new_stateful("warnings-fail", None, &self.config.project(&self.project_root).warnings_fail.to_variant())
+                        state=&self.config.project(&self.project_root).warnings_fail.to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::ToggleWarningsFail />
+
+                <SimpleAction::new_stateful("parser", Some(VariantTy::new("s").unwrap()), &self.config.project(&self.project_root).parser.as_str().to_variant())
+                        state=&self.config.project(&self.project_root).parser.as_str().to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()).and_then(|v| v.parse().ok()) {
+                                Some(parser) => Message::SetParser(parser),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("profile", Some(VariantTy::new("s").unwrap()), &self.config.project(&self.project_root).profile.unwrap_or_default().to_variant())
+                        state=&self.config.project(&self.project_root).profile.unwrap_or_default().to_variant()
+                        enabled={ !self.project_root.is_empty() && self.following.is_none() }
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()) {
+                                Some(name) => Message::SelectProfile(name.to_string()),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("toolchain", Some(VariantTy::new("s").unwrap()), &self.config.project(&self.project_root).toolchain.unwrap_or_default().to_variant())
+                        state=&self.config.project(&self.project_root).toolchain.unwrap_or_default().to_variant()
+                        enabled={ !self.project_root.is_empty() && self.following.is_none() }
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()) {
+                                Some(toolchain) => Message::SelectToolchain(toolchain.to_string()),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("target", Some(VariantTy::new("s").unwrap()), &self.config.project(&self.project_root).target.unwrap_or_default().to_variant())
+                        state=&self.config.project(&self.project_root).target.unwrap_or_default().to_variant()
+                        enabled={ !self.project_root.is_empty() && self.following.is_none() }
+                        on activate=|a, value| {
+                            match value.and_then(|v| v.get_str()) {
+                                Some(target) => Message::SelectTarget(target.to_string()),
+                                None => Message::NoOp,
+                            }
+                        } />
+
+                <SimpleAction::new_stateful("default-features", None, &(!features.no_default_features).to_variant())
+                        state=&(!features.no_default_features).to_variant()
+                        enabled=features_enabled
+                        on activate=|a, _| Message::ToggleDefaultFeatures />
+                { self.render_feature_actions(&features, features_enabled) }
+
+                <SimpleAction::new_stateful("show-duplicates", None, &self.config.show_duplicates.to_variant())
+                        state=&self.config.show_duplicates.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleOption(ListOption::ShowDuplicates) />
+
+                <SimpleAction::new_stateful("group-by-file", None, &self.config.group_by_file.to_variant())
+                        state=&self.config.group_by_file.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleOption(ListOption::GroupByFile) />
+
+                <SimpleAction::new_stateful("group-by-code", None, &self.config.group_by_code.to_variant())
+                        state=&self.config.group_by_code.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleOption(ListOption::GroupByCode) />
+
+                <SimpleAction::new_stateful("show-changes", None, &self.config.show_changes.to_variant())
+                        state=&self.config.show_changes.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleOption(ListOption::ShowChanges) />
+
+                <SimpleAction::new_stateful("focus-file", None, &self.config.focus_file.to_variant())
+                        state=&self.config.focus_file.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleOption(ListOption::FocusFile) />
+
+                <SimpleAction::new_stateful("radiator", None, &self.radiator.to_variant())
+                        state=&self.radiator.to_variant()
+                        enabled=true
+                        on activate=|a, _| Message::ToggleRadiator />
+
+                <SimpleAction::new_stateful("only-changes", None, &self.config.project(&self.project_root).only_changes.to_variant())
+                        state=&self.config.project(&self.project_root).only_changes.to_variant()
+                        enabled={ !self.project_root.is_empty() }
+                        on activate=|a, _| Message::ToggleOnlyChanges />
+
+                <HeaderBar title="Watch Rust Errors" show_close_button=true
+                        subtitle=self.following.as_deref()>
+                    <Button tooltip_text=previous_tooltip.as_str()
+                            accessible_name=previous_tooltip.as_str()
+                            sensitive=has_errors
+                            on clicked=|_| Message::PreviousError>
+                        <Image property_icon_name=Some("go-up-symbolic") />
+                    </Button>
+                    <Button tooltip_text=next_tooltip.as_str()
+                            accessible_name=next_tooltip.as_str()
+                            sensitive=has_errors
+                            on clicked=|_| Message::NextError>
+                        <Image property_icon_name=Some("go-down-symbolic") />
+                    </Button>
+                    <MenuButton HeaderBar::pack_type=PackType::End
+                            accessible_name=menu_name.as_str()
+                            menu_model=Some(&app_menu(&self.config))>
+                        <Image property_icon_name=Some("open-menu-symbolic") />
+                    </MenuButton>
+                    <MenuButton HeaderBar::pack_type=PackType::End
+                            tooltip_text=sort_tooltip.as_str() accessible_name=sort_tooltip.as_str()
+                            menu_model=Some(&sort_menu())>
+                        <Image property_icon_name=Some("view-sort-ascending-symbolic") />
+                    </MenuButton>
+                    <Button HeaderBar::pack_type=PackType::End
+                            tooltip_text=copy_tooltip.as_str()
+                            accessible_name=copy_tooltip.as_str()
+                            sensitive={ self.results.is_some() }
+                            on clicked=|_| Message::CopyAll>
+                        <Image property_icon_name=Some("edit-copy-symbolic") />
+                    </Button>
+                </HeaderBar>
+                <Box orientation=Orientation::Vertical>
+                    { self.render_safe_mode() }
+                    { self.render_trust_prompt() }
+                    { self.render_watcher_stopped() }
+                    { self.render_portal_notice() }
+                    { self.render_health() }
+                    <Box orientation=Orientation::Vertical spacing=10
+                            visible={ !self.radiator && !self.onboarding() }>
+                        <@ProjectSelector project_root=self.project_root.clone()
+                                editable=self.following.is_none()
+                                error=self.problems.project_root.clone()
+                                on changed=|path| Message::PathChanged(path)
+                                on entered=|_| Message::ProjectRootEntered
+                                on browse=|_| Message::SelectFolder />
+                        <@CommandBar command=self.command.clone()
+                                editable=self.following.is_none()
+                                profile_label=self.profile_label()
+                                profiles=self.profile_names()
+                                features=self.features.clone()
+                                toolchain_label=self.toolchain_label()
+                                toolchains=self.toolchain_names()
+                                target_label=self.target_label()
+                                targets=self.targets.clone()
+                                watching=self.state.map(|| false, || true)
+                                error=self.problems.command.clone()
+                                history=self.config.command_history.clone()
+                                on changed=|command| Message::CommandChanged(command)
+                                on toggle_watch=|_| Message::ToggleWatch />
+                        <Notebook hexpand=true vexpand=true
+                                on realize=|notebook| {
+                                    if let Some(tab) = tab.as_deref() {
+                                        open_tab(notebook, tab);
+                                    }
+                                    Message::NoOp
+                                }
+                                on switch_page=|notebook, page, _| Message::TabSwitched(
+                                    notebook.get_tab_label_text(page).map(|label| label.to_string())
+                                )>
+                            { self.render_diagnostics(&diagnostics_tab) }
+                            // what the command printed, for when parsing falls short
+                            <ScrolledWindow Notebook::tab_label=Some(raw_output_tab.as_str())>
+                                <TextView buffer=self.raw_output.as_ref() editable=false
+                                          cursor_visible=false monospace=true
+                                          style_class="raw-output" />
+                            </ScrolledWindow>
+                            { self.render_heatmap() }
+                            { self.render_codes() }
+                            { self.render_pipeline() }
+                            { self.render_statistics() }
+                            { self.render_comparison() }
+                            { self.render_scheduled() }
+                            { self.render_projects() }
+                            { self.render_queue() }
+                        </Notebook>
+
+                        <@StatusBar build_started=self.build_started
+                                comparing=self.comparing.clone()
+                                bisecting=self.bisecting.status()
+                                fetch_progress=self.fetch_progress.clone()
+                                build_progress=self.build_progress.clone()
+                                restarting=self.restarting
+                                installing_toolchain=self.installing_toolchain.clone()
+                                pinned_toolchain=self.pinned_toolchain.clone()
+                                rustc_version=self.results.as_ref().and_then(|r| r.rustc_version.clone())
+                                toolchain_changed=self.results.as_ref().map(|r| r.toolchain_changed).unwrap_or(false)
+                                reported=self.results.as_ref().and_then(|r| r.reported)
+                                post_process_error=self.results.as_ref().and_then(|r| r.post_process_error.clone())
+                                hook_error=self.hook_error.clone()
+                                warning=self.warning.clone()
+                                slowdown=self.slowdown
+                                omitted=self.results.as_ref().map(|r| r.omitted).unwrap_or(0)
+                                extra_roots=self.extra_roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>()
+                                last_run=self.results.as_ref().map(run_summary)
+                                trigger_menu=self.results.as_ref().and_then(|r| trigger_menu(&self.project_root, r))
+                                watching=self.watcher.is_some()
+                                on run_now=|_| Message::RunNow
+                                on copy_summary=|_| Message::CopySummary />
+                    </Box>
+                    // after the rest, so that they keep their place
+                    { self.render_onboarding() }
+                    { self.render_radiator() }
+                </Box>
+            </ApplicationWindow>
+        }
+    }
+}
+
+// A project's card on the radiator. This window's own project is shown with
+// what the window knows, the rest with what their watches last published.
+fn render_project(
+    status: &ProjectStatus,
+    latest: Option<&CompileResult>,
+    building: bool,
+    this_window: bool,
+) -> VNode<Model> {
+    let project_root = status.project_root.clone();
+    let tooltip = project_root.clone();
+    let name = project_name(&project_root);
+    let severity = match latest {
+        Some(result) if !result.success => Severity::Error,
+        Some(result) if !result.warnings.is_empty() => Severity::Warning,
+        _ => Severity::Info,
+    };
+    let badge = if building {
+        gettext("BUILDING")
+    } else {
+        match latest {
+            Some(result) if result.success => gettext("OK"),
+            Some(_) => gettext("FAILED"),
+            None => gettext("NO BUILDS"),
+        }
+    };
+    let counts = match latest {
+        Some(result) => {
+            let ago = result.finished_at.and_then(|at| at.elapsed().ok());
+            match ago {
+                Some(ago) => fill(
+                    gettext("{0} errors, {1} warnings, built {2} ago"),
+                    &[
+                        &result.errors.len(),
+                        &result.warnings.len(),
+                        &format_duration(ago),
+                    ],
+                ),
+                None => fill(
+                    gettext("{0} errors, {1} warnings"),
+                    &[&result.errors.len(), &result.warnings.len()],
+                ),
+            }
+        }
+        None => gettext("Not built yet"),
+    };
+    let watch = match status.watched_by {
+        _ if this_window => gettext("Watched in this window"),
+        Some(pid) => fill(gettext("Watched by process {}"), &[&pid]),
+        None => gettext("Not being watched"),
+    };
+    let menu = project_menu(&project_root);
+    let more = gettext("More");
+    let accessible_name = [
+        name.as_str(),
+        badge.as_str(),
+        counts.as_str(),
+        watch.as_str(),
+    ]
+    .join(", ");
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity accessible_name=accessible_name.as_str()>
+            <Box spacing=10>
+                <Image property_icon_name=Some(severity.icon_name()) valign=Align::Start />
+                <Box orientation=Orientation::Vertical spacing=4 hexpand=true>
+                    <Box spacing=6>
+                        <Label label=name style_class="title" halign=Align::Start
+                               tooltip_text=tooltip.as_str() />
+                        <Label label=badge style_class="badge" valign=Align::Start />
+                    </Box>
+                    <Label label=counts halign=Align::Start />
+                    <Label label=watch style_class="location" halign=Align::Start />
+                </Box>
+                <Button label=gettext("Open") valign=Align::Start
+                        no_show_all=true visible=!this_window
+                        on clicked=|_| Message::OpenProject(project_root.clone()) />
+                <MenuButton valign=Align::Start relief=ReliefStyle::None
+                        tooltip_text=more.as_str() accessible_name=more.as_str()
+                        menu_model=Some(&menu)>
+                    // show_all doesn't reach past the button
+                    <Image property_icon_name=Some("view-more-symbolic") visible=true />
+                </MenuButton>
+            </Box>
+        </ListBoxRow>
+    }
+}
+
+// A module's cell in the overview's treemap. Its name and counts are left
+// out when they wouldn't fit, leaving them to the tooltip.
+fn render_module(module: &ModuleStats, rect: Rect, selected: bool) -> VNode<Model> {
+    let path = module.path.clone();
+    let summary = format!(
+        "{}\n{}",
+        module.path,
+        fill(
+            gettext("{0} errors, {1} warnings"),
+            &[&module.errors, &module.warnings],
+        )
+    );
+    let fits = rect.width > 8.0 * module.path.len().max(20) as f64 && rect.height > 48.0;
+    let tooltip = format!(
+        "{}\n{}",
+        summary,
+        fill(
+            gettext("{0} lines, {1} diagnostics per 1000 lines"),
+            &[&module.lines, &format!("{:.1}", module.density())],
+        )
+    );
+    let accessible_name = summary.replace('\n', ", ");
+
+    gtk! {
+        <Button width_request={ rect.width.round() as i32 }
+                height_request={ rect.height.round() as i32 }
+                relief=ReliefStyle::None
+                tooltip_text=tooltip.as_str()
+                accessible_name=accessible_name.as_str()
+                heat=module.heat()
+                current=selected
+                on clicked=|_| Message::List(ListMessage::FilterModule(Some(path.clone())))>
+            <Label label={ if fits { summary.clone() } else { String::new() } } />
+        </Button>
+    }
+}
+
+// A code in the codes tab, e.g. "E0308 ×12".
+fn render_code_count(group: &CodeGroup, selected: bool) -> VNode<Model> {
+    let code = group.code.unwrap_or_default().to_string();
+    let severity = if group.errors > 0 {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let count = format!("×{}", listing::thousands(group.diagnostics.len()));
+    let split = fill(
+        gettext("{0} errors, {1} warnings"),
+        &[&group.errors, &group.warnings],
+    );
+    let tooltip = gettext("Only list these");
+    let accessible_name = [code.as_str(), count.as_str(), split.as_str()].join(", ");
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity>
+            <Button relief=ReliefStyle::None current=selected
+                    tooltip_text=tooltip.as_str() accessible_name=accessible_name.as_str()
+                    on clicked=|_| Message::List(ListMessage::FilterCode(Some(code.clone())))>
+                <Box spacing=10>
+                    <Image property_icon_name=Some(severity.icon_name()) />
+                    <Label label=code.clone() style_class="title" halign=Align::Start
+                           hexpand=true />
+                    <Label label=split style_class="location" />
+                    <Label label=count style_class="badge" />
+                </Box>
+            </Button>
+        </ListBoxRow>
+    }
+}
+
+// A crate in the pipeline tab, with how long it took in its tooltip.
+fn render_unit(pipeline: &Pipeline, id: &str, critical: bool) -> VNode<Model> {
+    let unit = &pipeline.units[id];
+    let state = pipeline.state(id);
+    let tooltip = match state {
+        UnitState::Pending => fill(gettext("{0} {1}: waiting"), &[&unit.name, &unit.version]),
+        UnitState::Active => fill(
+            gettext("{0} {1}: being compiled"),
+            &[&unit.name, &unit.version],
+        ),
+        UnitState::Done if unit.fresh => {
+            fill(gettext("{0} {1}: up to date"), &[&unit.name, &unit.version])
+        }
+        UnitState::Done => fill(
+            gettext("{0} {1}: took {2}"),
+            &[
+                &unit.name,
+                &unit.version,
+                &format_duration(pipeline.duration(id)),
+            ],
+        ),
+    };
+
+    gtk! {
+        <Label label=unit.name.clone() tooltip_text=tooltip.as_str()
+               style_class="unit" unit_state=state critical=critical />
+    }
+}
+
+async fn show_error(heading: &str, details: &str) {
+    vgtk::message_dialog(
+        vgtk::current_window().as_ref(),
+        DialogFlags::empty(),
+        MessageType::Error,
+        ButtonsType::Ok,
+        true,
+        format!(
+            "<b>{}</b>\n\n{}",
+            heading,
+            glib::markup_escape_text(details)
+        ),
+    )
+    .await;
+}
+
+fn project_menu(project_root: &str) -> Menu {
+    let menu = Menu::new();
+    menu.append(
+        Some(&gettext("Show in file manager")),
+        Some(&format!("win.reveal::{}", project_root)),
+    );
+    menu.append(
+        Some(&gettext("Open terminal here")),
+        Some(&format!("win.terminal::{}", project_root)),
+    );
+    menu
+}
+
+fn app_menu(config: &Config) -> Menu {
+    let theme = Menu::new();
+    theme.append(Some(&gettext("Follow system")), Some("win.theme::system"));
+    theme.append(Some(&gettext("Light")), Some("win.theme::light"));
+    theme.append(Some(&gettext("Dark")), Some("win.theme::dark"));
+
+    // for people who already run a watcher in a terminal
+    let follow = Menu::new();
+    follow.append(
+        Some(&gettext("Follow bacon export")),
+        Some("win.follow-bacon"),
+    );
+    follow.append(
+        Some(&gettext("Follow cargo watch log…")),
+        Some("win.follow-cargo-watch"),
+    );
+
+    // while the window is in the background
+    let alert = Menu::new();
+    alert.append(Some(&gettext("Off")), Some("win.alert::off"));
+    alert.append(Some(&gettext("Play a sound")), Some("win.alert::sound"));
+    alert.append(
+        Some(&gettext("Flash the taskbar")),
+        Some("win.alert::flash"),
+    );
+    alert.append(Some(&gettext("Both")), Some("win.alert::sound-and-flash"));
+
+    // how builds share the machine, from the next watch on
+    let priority = Menu::new();
+    priority.append(
+        Some(&gettext("Normal priority")),
+        Some("win.priority::normal"),
+    );
+    priority.append(Some(&gettext("Low priority")), Some("win.priority::low"));
+    priority.append(Some(&gettext("Idle priority")), Some("win.priority::idle"));
+    priority.append(
+        Some(&gettext("Only build when focused")),
+        Some("win.build-when-focused"),
+    );
+    priority.append(
+        Some(&gettext("Only build on AC power")),
+        Some("win.build-on-ac-power"),
+    );
+
+    // sessions written to an event log before
+    let replay = Menu::new();
+    replay.append(Some(&gettext("Replay event log…")), Some("win.replay"));
+    replay.append(
+        Some(&gettext("Replay in real time…")),
+        Some("win.replay-timed"),
+    );
+
+    // per project, for builds that aren't cargo's
+    let parser = Menu::new();
+    parser.append(Some(&gettext("Rust")), Some("win.parser::rustc"));
+    parser.append(Some(&gettext("GCC / Clang")), Some("win.parser::gcc"));
+    parser.append(
+        Some(&gettext("Plain output")),
+        Some("win.parser::passthrough"),
+    );
+    for name in config.matchers.keys() {
+        parser.append(Some(name), Some(&format!("win.parser::{}", name)));
+    }
+
+    // diagnostics that were there before are the baseline's
+    let baseline = Menu::new();
+    baseline.append(Some(&gettext("Snapshot as baseline")), Some("win.baseline"));
+    baseline.append(Some(&gettext("Hide baseline")), Some("win.hide-baseline"));
+    baseline.append(Some(&gettext("Clear baseline")), Some("win.clear-baseline"));
+
+    // once new results are in
+    let scroll = Menu::new();
+    scroll.append(Some(&gettext("Keep position")), Some("win.scroll-to::keep"));
+    scroll.append(
+        Some(&gettext("Jump to first error")),
+        Some("win.scroll-to::first-error"),
+    );
+    scroll.append(
+        Some(&gettext("Scroll to bottom")),
+        Some("win.scroll-to::bottom"),
+    );
+
+    // the results as one list, or errors and warnings apart
+    let layout = Menu::new();
+    layout.append(
+        Some(&gettext("One list")),
+        Some("win.results-layout::single"),
+    );
+    layout.append(
+        Some(&gettext("Errors above warnings")),
+        Some("win.results-layout::split"),
+    );
+
+    let menu = Menu::new();
+    menu.append(Some(&gettext("New window")), Some("app.new-window"));
+    menu.append(
+        Some(&gettext("Command palette")),
+        Some(command_palette::ACTION),
+    );
+    menu.append_section(Some(&gettext("Follow")), &follow);
+    menu.append_section(Some(&gettext("Replay")), &replay);
+    menu.append_section(Some(&gettext("Theme")), &theme);
+    menu.append_section(Some(&gettext("Alert on failure")), &alert);
+    menu.append_section(Some(&gettext("Builds")), &priority);
+    menu.append_section(Some(&gettext("On new results")), &scroll);
+    menu.append_section(Some(&gettext("Results layout")), &layout);
+    menu.append_section(Some(&gettext("Output format")), &parser);
+    menu.append(
+        Some(&gettext("Show duplicates")),
+        Some("win.show-duplicates"),
+    );
+    menu.append(Some(&gettext("Group by file")), Some("win.group-by-file"));
+    menu.append(Some(&gettext("Group by code")), Some("win.group-by-code"));
+    menu.append(
+        Some(&gettext("Changes since last build")),
+        Some("win.show-changes"),
+    );
+    menu.append(
+        Some(&gettext("Focus on the file edited last")),
+        Some("win.focus-file"),
+    );
+    menu.append(Some(&gettext("Only my changes")), Some("win.only-changes"));
+    menu.append(Some(&gettext("Environment…")), Some("win.environment"));
+    menu.append(Some(&gettext("Ignored codes…")), Some("win.ignored"));
+    menu.append(Some(&gettext("Work offline")), Some("win.offline"));
+    menu.append(Some(&gettext("Log every run")), Some("win.log-runs"));
+    menu.append(
+        Some(&gettext("Skip ignored files")),
+        Some("win.skip-ignored"),
+    );
+    menu.append(
+        Some(&gettext("Warnings fail the build")),
+        Some("win.warnings-fail"),
+    );
+    menu.append(Some(&gettext("Open all in editor")), Some("win.quickfix"));
+    menu.append(Some(&gettext("Test editor")), Some("win.test-editor"));
+    menu.append(Some(&gettext("Compare with branch…")), Some("win.compare"));
+    menu.append_section(Some(&gettext("Baseline")), &baseline);
+    menu.append(Some(&gettext("Export…")), Some("win.export"));
+    menu.append(Some(&gettext("Radiator")), Some("win.radiator"));
+    menu.append(Some(&gettext("Check setup")), Some("win.check-health"));
+    menu.append(
+        Some(&gettext("Minimize instead of closing")),
+        Some("win.minimize-on-close"),
+    );
+    menu.append(Some(&gettext("Quit")), Some("app.quit"));
+    menu
+}
+
+// The commands the welcome screen suggests, with what they're good for.
+fn example_commands() -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "cargo check",
+            gettext("The quickest way to see errors and warnings"),
+        ),
+        ("cargo clippy", gettext("Lints on top of them")),
+        (
+            "cargo test --no-run",
+            gettext("Keeps the tests compiling too"),
+        ),
+    ]
+}
+
+// the last part of `project_root`, e.g. "watch-rust-errors"
+fn project_name(project_root: &str) -> String {
+    Path::new(project_root)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| project_root.to_string())
+}
+
+// e.g. "1.2 s", "37 s" or "2 min 05 s"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 10 {
+        fill(
+            gettext("{} s"),
+            &[&format!("{:.1}", duration.as_secs_f64())],
+        )
+    } else if secs < 60 {
+        fill(gettext("{} s"), &[&secs])
+    } else {
+        fill(
+            gettext("{0} min {1} s"),
+            &[&(secs / 60), &format!("{:02}", secs % 60)],
+        )
+    }
+}
+
+// e.g. "Last run: 14:32:05 · 2.3 s · 0 errors / 4 warnings · triggered by
+// src/lib.rs", leaving out what isn't known, like the time of results read
+// from another watcher.
+fn run_summary(result: &CompileResult) -> String {
+    let mut parts = vec![];
+    let at = result
+        .started_at
+        .or(result.finished_at)
+        .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+        .and_then(|since| DateTime::new_from_unix_local(since.as_secs() as i64).format("%H:%M:%S"));
+    if let Some(at) = at {
+        parts.push(at.to_string());
+    }
+    if let Some(duration) = result.duration {
+        parts.push(format_duration(duration));
+    }
+    parts.push(fill(
+        gettext("{0} errors / {1} warnings"),
+        &[&result.errors.len(), &result.warnings.len()],
+    ));
+    if let Some(trigger) = result.git_trigger.as_ref() {
+        parts.push(fill(gettext("triggered by {}"), &[trigger]));
+    } else if !result.triggered_by.is_empty() {
+        parts.push(fill(
+            gettext("triggered by {}"),
+            &[&format_files(&result.triggered_by)],
+        ));
+    }
+    fill(gettext("Last run: {}"), &[&parts.join(" · ")])
+}
+
+// e.g. "src/main.rs, src/lib.rs, build.rs and 2 more"
+fn format_files(files: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let mut list = files[..files.len().min(SHOWN)].join(", ");
+    if files.len() > SHOWN {
+        list = fill(
+            gettext("{0} and {1} more"),
+            &[&list, &(files.len() - SHOWN)],
+        );
+    }
+    list
+}
+
+// What the project's config file asks to run, e.g. "build with `cargo
+// clippy` and set RUSTFLAGS".
+fn trust_request(local: &LocalConfig) -> String {
+    let mut asks = vec![];
+    if let Some(command) = local.command.as_ref() {
+        asks.push(fill(gettext("build with `{}`"), &[command]));
+    }
+    if let Some(script) = local.post_process.as_ref() {
+        asks.push(fill(
+            gettext("pass the diagnostics through `{}`"),
+            &[script],
+        ));
+    }
+    if !local.env.is_empty() {
+        let names = local.env.keys().cloned().collect::<Vec<_>>();
+        asks.push(fill(gettext("set {}"), &[&names.join(", ")]));
+    }
+    if !local.jobs.is_empty() {
+        let commands = local
+            .jobs
+            .iter()
+            .map(|job| format!("`{}`", job.command))
+            .collect::<Vec<_>>();
+        asks.push(fill(gettext("run {} on a timer"), &[&commands.join(", ")]));
+    }
+    asks.join(gettext(" and ").as_str())
+}
+
+fn reset_menu(sections: &[String]) -> Menu {
+    let menu = Menu::new();
+    for section in sections {
+        let action = format!("win.reset-config-section::{}", section);
+        menu.append(Some(section), Some(&action));
+    }
+    menu
+}
+
+// What happened to the files that started a build, each item revealing its
+// file. Long lists are cut short; the run log has them all.
+fn trigger_menu(project_root: &str, result: &CompileResult) -> Option<Menu> {
+    const SHOWN: usize = 20;
+    if result.trigger_ops.is_empty() {
+        return None;
+    }
+    let ops = Menu::new();
+    for op in result.trigger_ops.iter().take(SHOWN) {
+        let path = Path::new(project_root).join(op.renamed_to.as_ref().unwrap_or(&op.path));
+        let action = format!("win.reveal::{}", path.display());
+        ops.append(Some(&op.to_string()), Some(&action));
+    }
+    let more = result.trigger_ops.len().saturating_sub(SHOWN);
+    if more > 0 {
+        ops.append(Some(&fill(gettext("and {} more"), &[&more])), None);
+    }
+    let menu = Menu::new();
+    menu.append_section(Some(&gettext("What triggered this run")), &ops);
+    Some(menu)
+}
+
+fn sort_menu() -> Menu {
+    let menu = Menu::new();
+    menu.append(Some(&gettext("Severity")), Some("win.sort-order::severity"));
+    menu.append(Some(&gettext("File")), Some("win.sort-order::file"));
+    menu.append(Some(&gettext("Error code")), Some("win.sort-order::code"));
+    menu.append(
+        Some(&gettext("Compiler order")),
+        Some("win.sort-order::compiler"),
+    );
+    menu.append(
+        Some(&gettext("Root cause first")),
+        Some("win.sort-order::root-cause"),
+    );
+    menu
+}
+
+fn parse_id(value: Option<&Variant>) -> Option<DiagnosticId> {
+    value
+        .and_then(|v| v.get_str())
+        .and_then(|v| v.parse().ok())
+        .map(DiagnosticId)
+}
+
+// More results are built as a list is scrolled to its bottom.
+fn show_more_at(position: PositionType) -> Message {
+    if position == PositionType::Bottom {
+        Message::ShowMoreResults
+    } else {
+        Message::NoOp
+    }
+}
+
+// Opens the notebook's tab with `label`, if it has one.
+fn open_tab(notebook: &Notebook, label: &str) {
+    let page = notebook
+        .get_children()
+        .into_iter()
+        .find(|page| notebook.get_tab_label_text(page).as_deref() == Some(label));
+    if let Some(page) = page {
+        notebook.set_current_page(notebook.page_num(&page));
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(text);
+}
+
+// Shows what rustc says about an error code, with its examples in
+// monospace. The window doesn't keep the results from being used.
+async fn show_explanation(code: &str, explanation: &str) {
+    let dialog = Dialog::new();
+    dialog.set_title(&fill(gettext("Error {}"), &[&code]));
+    dialog.set_transient_for(vgtk::current_window().as_ref());
+    dialog.set_default_size(640, 480);
+    dialog.add_button(&gettext("Close"), ResponseType::Close);
+
+    let text = Label::new(None);
+    text.set_markup(&explain::to_pango(explanation));
+    text.set_line_wrap(true);
+    text.set_selectable(true);
+    text.set_xalign(0.0);
+    text.set_yalign(0.0);
+    text.set_margin_start(6);
+    text.set_margin_end(6);
+
+    let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+    scrolled.set_vexpand(true);
+    scrolled.add(&text);
+    dialog.get_content_area().add(&scrolled);
+    dialog.show_all();
+
+    let _ = on_signal!(dialog, connect_response).await;
+    dialog.destroy();
+}
+
+// Lets the user edit a project setting as lines of text, e.g. the extra
+// variables for its builds. Returns the text if it was saved.
+async fn edit_text(title: &str, hint: &str, text: &str) -> Option<String> {
+    let dialog = Dialog::new();
+    dialog.set_title(title);
+    dialog.set_transient_for(vgtk::current_window().as_ref());
+    dialog.set_modal(true);
+    dialog.set_default_size(480, 320);
+    dialog.add_button(&gettext("Cancel"), ResponseType::Cancel);
+    dialog.add_button(&gettext("Save"), ResponseType::Accept);
+
+    let hint = Label::new(Some(hint));
+    hint.set_line_wrap(true);
+    hint.set_xalign(0.0);
+
+    let buffer = TextBuffer::new(None::<&TextTagTable>);
+    buffer.set_text(text);
+    let view = TextView::new();
+    view.set_buffer(Some(&buffer));
+    view.set_monospace(true);
+
+    let scrolled = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+    scrolled.set_vexpand(true);
+    scrolled.add(&view);
+
+    let content = dialog.get_content_area();
+    content.set_spacing(6);
+    content.add(&hint);
+    content.add(&scrolled);
+    dialog.show_all();
+
+    let response = on_signal!(dialog, connect_response).await;
+    let (start, end) = buffer.get_bounds();
+    let text = buffer.get_text(&start, &end, false);
+    dialog.destroy();
+
+    match response {
+        Ok(ResponseType::Accept) => text.map(|t| t.to_string()),
+        _ => None,
+    }
+}
+
+// Asks for a branch, tag or commit, with `initial` filled in.
+async fn ask_git_ref(title: &str, button: &str, hint: &str, initial: &str) -> Option<String> {
+    let dialog = Dialog::new();
+    dialog.set_title(title);
+    dialog.set_transient_for(vgtk::current_window().as_ref());
+    dialog.set_modal(true);
+    dialog.add_button(&gettext("Cancel"), ResponseType::Cancel);
+    dialog.add_button(button, ResponseType::Accept);
+    dialog.set_default_response(ResponseType::Accept);
+
+    let hint = Label::new(Some(hint));
+    hint.set_line_wrap(true);
+    hint.set_xalign(0.0);
+
+    let entry = Entry::new();
+    entry.set_text(initial);
+    entry.set_activates_default(true);
+
+    let content = dialog.get_content_area();
+    content.set_spacing(6);
+    content.add(&hint);
+    content.add(&entry);
+    dialog.show_all();
+
+    let response = on_signal!(dialog, connect_response).await;
+    let git_ref = entry.get_text().map(|s| s.trim().to_string());
+    dialog.destroy();
+
+    match (response, git_ref) {
+        (Ok(ResponseType::Accept), Some(git_ref)) if !git_ref.is_empty() => Some(git_ref),
+        _ => None,
+    }
+}
+
+async fn select_file(title: &str, action: FileChooserAction) -> Result<Option<File>, Error> {
+    let accept = if action == FileChooserAction::Save {
+        gettext("Save")
+    } else {
+        gettext("Select")
+    };
+    let dialog = FileChooserNative::new(
+        Some(title),
+        vgtk::current_object()
+            .and_then(|w| w.downcast::<Window>().ok())
+            .as_ref(),
+        action,
+        Some(&accept),
+        None,
+    );
+    dialog.set_modal(true);
+    if action == FileChooserAction::Save {
+        // the extension picks the export format, so suggest one
+        dialog.set_current_name("results.md");
+        dialog.set_do_overwrite_confirmation(true);
+    }
+    dialog.show();
+
+    if on_signal!(dialog, connect_response).await == Ok(ResponseType::Accept) {
+        Ok(dialog.get_file())
+    } else {
+        Ok(None)
+    }
+}
+
+fn main() {
+    if headless::is_requested() {
+        std::process::exit(headless::run());
+    }
+
+    i18n::init();
+
+    if let Some((path, original_timing)) = replay_requested() {
+        std::process::exit(run_replay(path, original_timing));
+    }
+
+    if let Some(project_root) = stdin_requested() {
+        std::process::exit(run_stdin(project_root));
+    }
+
+    std::process::exit(run_app());
+}
+
+// What the window makes of an event of the watch, or of a replayed one.
+fn event_message(event: BuildEvent) -> Message {
+    match event {
+        BuildEvent::Started => Message::BuildStarted,
+        BuildEvent::InstallingToolchain(channel) => Message::InstallingToolchain(channel),
+        BuildEvent::Fetching(progress) => Message::Fetching(progress),
+        BuildEvent::Compiling(progress) => Message::Compiling(progress),
+        BuildEvent::Pipeline(pipeline) => Message::PipelineLoaded(pipeline),
+        BuildEvent::ExtraRoots(roots) => Message::ExtraRoots(roots),
+        // the results list shows them all at once
+        BuildEvent::Diagnostic(_) => Message::NoOp,
+        BuildEvent::Finished(result) => Message::ResultsArrived(result),
+        BuildEvent::Scheduled(job, result) => Message::ScheduledResult(job, result),
+        BuildEvent::HookFailed(reason) => Message::HookFailed(reason),
+        BuildEvent::Warning(warning) => Message::Warning(warning),
+        BuildEvent::Stopped(reason) => Message::WatcherStopped(reason),
+    }
+}
+
+// Project roots on the command line open in windows of their own, in the
+// instance that's running already if there is one, with `--command` to build
+// them with and `--start` to start watching straight away. `--new-window`
+// without any asks that instance for a blank window rather than raising its
+// own.
+fn run_app() -> i32 {
+    let args = std::env::args().collect::<Vec<_>>();
+    let (app, scope) = vgtk::start::<App>();
+    set_window_accels(&app);
+
+    if safe_mode_requested() {
+        // in an instance of its own that opens nothing by itself, in case
+        // it's what's opened that's the trouble
+        app.set_flags(app.get_flags() | ApplicationFlags::NON_UNIQUE);
+        return app.run(&args[..1]);
+    }
+    // the whole command line goes to the instance that handles it, which
+    // GTK would otherwise take for files to open
+    app.set_flags(app.get_flags() | ApplicationFlags::HANDLES_COMMAND_LINE);
+    if args.iter().any(|arg| arg == "--new-window") && Launch::parse(&args).is_empty() {
+        if let Err(err) = app.register(None::<&gio::Cancellable>) {
+            eprintln!("Failed to register: {}", err);
+            return 1;
+        }
+        if app.get_is_remote() {
+            app.activate_action("new-window", None);
+            return 0;
+        }
+    }
+
+    app.connect_command_line(move |app, command_line| {
+        let args = command_line
+            .get_arguments()
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let mut launch = Launch::parse(&args);
+        if launch.is_empty() {
+            app.activate();
+            return 0;
+        }
+        // relative to where the command was run, which for an instance
+        // that's running already isn't where it was started
+        launch.project_roots = launch
+            .project_roots
+            .iter()
+            .filter_map(|root| command_line.create_file_for_arg(root).get_path())
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        scope.send_message(AppMessage::Launch(launch));
+        0
+    });
+    app.run(&args)
+}
+
+// the accelerators of the actions every window has
+fn set_window_accels(app: &Application) {
+    for (action, accels) in WINDOW_ACCELS {
+        app.set_accels_for_action(&format!("win.{}", action), accels);
+    }
+}
+
+// `--safe-mode` starts on default settings without build hooks, to find out
+// whether it's the config that's broken.
+fn safe_mode_requested() -> bool {
+    std::env::args().any(|arg| arg == "--safe-mode")
+}
+
+// The sections of the config file, for safe mode to offer resetting.
+fn config_sections() -> Vec<String> {
+    Config::path()
+        .and_then(|path| Config::sections(path).ok())
+        .unwrap_or_default()
+}
+
+// `--replay <event log> [--original-timing]` plays a logged session back,
+// e.g. one attached to a bug report.
+fn replay_requested() -> Option<(String, bool)> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+    let path = args.next()?;
+    let original_timing = std::env::args().any(|arg| arg == "--original-timing");
+    Some((path, original_timing))
+}
+
+// `--stdin [project root]` shows the compiler output piped in, e.g.
+// `cargo check 2>&1 | watch-rust-errors --stdin`, for a project in the
+// current directory unless another is given.
+fn stdin_requested() -> Option<String> {
+    if !std::env::args().any(|arg| arg == "--stdin") {
+        return None;
+    }
+    let mut args = std::env::args().skip_while(|arg| arg != "--stdin").skip(1);
+    let project_root = match args.next().filter(|arg| !arg.starts_with("--")) {
+        Some(project_root) => PathBuf::from(project_root),
+        None => std::env::current_dir().unwrap_or_default(),
+    };
+    let project_root = project_root.canonicalize().unwrap_or(project_root);
+    Some(project_root.to_string_lossy().into_owned())
+}
+
+fn run_stdin(project_root: String) -> i32 {
+    let args = std::env::args().collect::<Vec<_>>();
+    let (app, scope) = vgtk::start::<App>();
+    set_window_accels(&app);
+    // stdin is this process's, so its window can't go to another instance
+    app.set_flags(app.get_flags() | ApplicationFlags::NON_UNIQUE);
+    scope.send_message(AppMessage::ReadStdin(project_root));
+    app.run(&args[..1])
+}
+
+fn run_replay(path: String, original_timing: bool) -> i32 {
+    let args = std::env::args().collect::<Vec<_>>();
+    let (app, scope) = vgtk::start::<App>();
+    set_window_accels(&app);
+    // in an instance of its own, so that a replay never mixes with a watch
+    app.set_flags(app.get_flags() | ApplicationFlags::NON_UNIQUE);
+    scope.send_message(AppMessage::Replay(path, original_timing));
+    app.run(&args[..1])
 }
@@ -0,0 +1,381 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::cargo::CompileResult;
+use crate::history::Slowdown;
+use crate::process::{ProcessOutput, ProcessRunner, SystemRunner};
+
+// What a notification can be about. Each sink is only sent the kinds it
+// was configured for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyEvent {
+    BuildFailed,
+    // a build succeeded after one that failed
+    BuildFixed,
+    // a scheduled job found something its previous run didn't
+    ScheduledFindings,
+    // a build took much longer than the ones before it
+    SlowBuild,
+}
+
+impl NotifyEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyEvent::BuildFailed => "build-failed",
+            NotifyEvent::BuildFixed => "build-fixed",
+            NotifyEvent::ScheduledFindings => "scheduled-findings",
+            NotifyEvent::SlowBuild => "slow-build",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notice {
+    pub event: NotifyEvent,
+    pub title: String,
+    pub body: String,
+}
+
+impl Notice {
+    // What's worth telling about `result`, given whether the build before it
+    // succeeded. Builds that keep succeeding, or keep failing, aren't news.
+    pub fn for_build(
+        project_root: &str,
+        previous_success: Option<bool>,
+        result: &CompileResult,
+    ) -> Option<Self> {
+        let (event, title) = if !result.success && previous_success != Some(false) {
+            (
+                NotifyEvent::BuildFailed,
+                format!("Build failed with {} error(s)", result.errors.len()),
+            )
+        } else if result.success && previous_success == Some(false) {
+            (NotifyEvent::BuildFixed, "Build fixed".to_string())
+        } else {
+            return None;
+        };
+        Some(Notice {
+            event,
+            title,
+            body: project_root.to_string(),
+        })
+    }
+
+    pub fn for_scheduled(project_root: &str, job: &str, new_findings: usize) -> Self {
+        Notice {
+            event: NotifyEvent::ScheduledFindings,
+            title: format!("{}: {} new finding(s)", job, new_findings),
+            body: project_root.to_string(),
+        }
+    }
+
+    pub fn for_slow_build(project_root: &str, slowdown: &Slowdown) -> Self {
+        Notice {
+            event: NotifyEvent::SlowBuild,
+            title: format!(
+                "Build took {}% longer than usual ({:.1} s, median {:.1} s)",
+                slowdown.percent_longer(),
+                slowdown.duration.as_secs_f64(),
+                slowdown.median.as_secs_f64()
+            ),
+            body: project_root.to_string(),
+        }
+    }
+}
+
+// Somewhere notices can be sent. Sends block until they're delivered, so
+// callers with a window to keep responsive send from a thread of their own.
+pub trait NotificationSink: Send + Sync {
+    fn send(&self, notice: &Notice) -> Result<(), String>;
+}
+
+// A sink as the config describes it, e.g.
+//
+//   [[notifications]]
+//   kind = "slack"
+//   url = "https://hooks.slack.com/services/..."
+//   events = ["build-failed", "build-fixed"]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    #[serde(default = "default_events")]
+    pub events: Vec<NotifyEvent>,
+}
+
+fn default_events() -> Vec<NotifyEvent> {
+    vec![NotifyEvent::BuildFailed, NotifyEvent::BuildFixed]
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SinkKind {
+    // these two need the app, so they do nothing in headless mode
+    Desktop,
+    Sound,
+    // posts {"event", "title", "body"} as JSON
+    Webhook {
+        url: String,
+    },
+    // anything that takes Slack's incoming webhook payload, which
+    // Mattermost and Discord's /slack endpoints do too
+    Slack {
+        url: String,
+    },
+    Matrix {
+        homeserver: String,
+        // the room's ID, e.g. "!abcdef:example.org"
+        room: String,
+        access_token: String,
+    },
+    // handed to the local `sendmail`, which whatever MTA is installed
+    // provides
+    Email {
+        to: String,
+    },
+}
+
+// Hands each notice to the sinks that want its kind.
+#[derive(Default)]
+pub struct Notifier {
+    sinks: Vec<(Vec<NotifyEvent>, Box<dyn NotificationSink>)>,
+}
+
+impl Notifier {
+    // The sinks in `configs` that talk to other machines, leaving the ones
+    // that need the app to it.
+    pub fn remote(configs: &[SinkConfig]) -> Self {
+        Self::remote_with(Arc::new(SystemRunner), configs)
+    }
+
+    pub fn remote_with(runner: Arc<dyn ProcessRunner>, configs: &[SinkConfig]) -> Self {
+        let mut notifier = Notifier::default();
+        for config in configs {
+            if let Some(sink) = remote_sink(&runner, &config.kind) {
+                notifier.add(config.events.clone(), sink);
+            }
+        }
+        notifier
+    }
+
+    pub fn add(&mut self, events: Vec<NotifyEvent>, sink: Box<dyn NotificationSink>) {
+        self.sinks.push((events, sink));
+    }
+
+    pub fn wants(&self, event: NotifyEvent) -> bool {
+        self.sinks.iter().any(|(events, _)| events.contains(&event))
+    }
+
+    // Sends `notice` everywhere it's wanted, going on past sinks that fail.
+    pub fn send(&self, notice: &Notice) -> Result<(), String> {
+        let errors = self
+            .sinks
+            .iter()
+            .filter(|(events, _)| events.contains(&notice.event))
+            .filter_map(|(_, sink)| sink.send(notice).err())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+fn remote_sink(
+    runner: &Arc<dyn ProcessRunner>,
+    kind: &SinkKind,
+) -> Option<Box<dyn NotificationSink>> {
+    let runner = runner.clone();
+    match kind {
+        SinkKind::Desktop | SinkKind::Sound => None,
+        SinkKind::Webhook { url } => Some(Box::new(Webhook {
+            url: url.clone(),
+            runner,
+        })),
+        SinkKind::Slack { url } => Some(Box::new(SlackWebhook {
+            url: url.clone(),
+            runner,
+        })),
+        SinkKind::Matrix {
+            homeserver,
+            room,
+            access_token,
+        } => Some(Box::new(Matrix {
+            homeserver: homeserver.clone(),
+            room: room.clone(),
+            access_token: access_token.clone(),
+            runner,
+        })),
+        SinkKind::Email { to } => Some(Box::new(Email {
+            to: to.clone(),
+            runner,
+        })),
+    }
+}
+
+pub struct Webhook {
+    url: String,
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl NotificationSink for Webhook {
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        let payload = json!({
+            "event": notice.event.as_str(),
+            "title": notice.title,
+            "body": notice.body,
+        });
+        request(&*self.runner, "POST", &self.url, &[], &payload)
+    }
+}
+
+pub struct SlackWebhook {
+    url: String,
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl NotificationSink for SlackWebhook {
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        let payload = json!({ "text": format!("*{}*\n{}", notice.title, notice.body) });
+        request(&*self.runner, "POST", &self.url, &[], &payload)
+    }
+}
+
+pub struct Matrix {
+    homeserver: String,
+    room: String,
+    access_token: String,
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl NotificationSink for Matrix {
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        // the homeserver drops a message it has already seen the
+        // transaction ID of, so every send needs its own
+        let txn = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_nanos())
+            .unwrap_or_default();
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/wre{}",
+            self.homeserver.trim_end_matches('/'),
+            encode(&self.room),
+            txn
+        );
+        let payload = json!({
+            "msgtype": "m.text",
+            "body": format!("{}\n{}", notice.title, notice.body),
+        });
+        let auth = format!("Authorization: Bearer {}", self.access_token);
+        request(&*self.runner, "PUT", &url, &[&auth], &payload)
+    }
+}
+
+pub struct Email {
+    to: String,
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl NotificationSink for Email {
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        // a line break in a header would start another one
+        let subject = notice.title.replace(['\r', '\n'], " ");
+        let message = format!(
+            "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}\n",
+            self.to.trim(),
+            subject,
+            notice.body
+        );
+        // -t takes the recipient from the message, -i keeps a line of a
+        // single "." from ending it early
+        delivered(self.runner.run_with_input(
+            "sendmail",
+            &["-t", "-i"],
+            &[],
+            Path::new("."),
+            message.as_bytes(),
+        )?)
+    }
+}
+
+// by way of curl, which every machine worth building on has and which
+// knows its way around proxies and certificates. The URL and headers, which
+// can hold secrets, go on its stdin rather than where `ps` shows them.
+fn request(
+    runner: &dyn ProcessRunner,
+    method: &str,
+    url: &str,
+    headers: &[&str],
+    payload: &Value,
+) -> Result<(), String> {
+    let payload = payload.to_string();
+    let args = [
+        "--silent",
+        "--show-error",
+        "--fail",
+        "--max-time",
+        "30",
+        "--request",
+        method,
+        "--header",
+        "Content-Type: application/json",
+        "--data",
+        &payload,
+        "--config",
+        "-",
+    ];
+    let mut config = format!("url = {}\n", quoted(url));
+    for header in headers {
+        config.push_str(&format!("header = {}\n", quoted(header)));
+    }
+
+    delivered(runner.run_with_input("curl", &args, &[], Path::new("."), config.as_bytes())?)
+}
+
+fn delivered(output: ProcessOutput) -> Result<(), String> {
+    if output.success {
+        Ok(())
+    } else {
+        Err(format!(
+            "Could not send a notification: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// as a value in curl's config, where backslashes escape
+fn quoted(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// percent-encodes what can't go in a URL's path or query as is, like the "!"
+// and ":" in a room ID
+pub fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
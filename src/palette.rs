@@ -0,0 +1,87 @@
+use std::cmp::Reverse;
+
+// A command the palette offers: what it's called, e.g. "Theme: Dark", and
+// the action it runs, detailed as menus give it, e.g. "win.theme::dark".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub label: String,
+    pub action: String,
+}
+
+impl Command {
+    pub fn new(label: &str, action: &str) -> Self {
+        Command {
+            label: label.to_string(),
+            action: action.to_string(),
+        }
+    }
+}
+
+// The group, name and string target of a detailed action, e.g. ("win",
+// "theme", Some("dark")) for "win.theme::dark".
+pub fn parse_action(detailed: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (name, target) = match detailed.find("::") {
+        Some(at) => (&detailed[..at], Some(&detailed[at + 2..])),
+        None => (detailed, None),
+    };
+    let dot = name.find('.')?;
+    let (group, name) = (&name[..dot], &name[dot + 1..]);
+    if group.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some((group, name, target))
+}
+
+// How well `query` matches `label`, higher being better, or `None` if the
+// label doesn't have all of its letters in order. Letters that start a word
+// or follow the one matched before count more, so that "gbf" finds "Group
+// by file" first.
+pub fn score(query: &str, label: &str) -> Option<u32> {
+    let query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let mut wanted = query.iter().peekable();
+    let mut score = 0;
+    let mut word_start = true;
+    let mut matched_previous = false;
+    for c in label.chars() {
+        let want = match wanted.peek() {
+            Some(want) => **want,
+            None => break,
+        };
+        let matched = c.to_lowercase().eq(Some(want));
+        if matched {
+            wanted.next();
+            score += 1;
+            if word_start {
+                score += 3;
+            }
+            if matched_previous {
+                score += 2;
+            }
+        }
+        matched_previous = matched;
+        word_start = !c.is_alphanumeric();
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    // shorter labels are the closer match
+    Some(score * 100 + 99 - label.chars().count().min(99) as u32)
+}
+
+// The commands matching `query`, best first, and in the order given where
+// they match as well. An empty query matches them all.
+pub fn filter<'a>(commands: &'a [Command], query: &str) -> Vec<&'a Command> {
+    if query.trim().is_empty() {
+        return commands.iter().collect();
+    }
+    let mut matches = commands
+        .iter()
+        .filter_map(|command| score(query, &command.label).map(|score| (score, command)))
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|(score, _)| Reverse(*score));
+    matches.into_iter().map(|(_, command)| command).collect()
+}
@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::ansi;
+use crate::cargo::{self, CompileResult};
+use crate::config::MatcherConfig;
+use crate::rust::{RustDiagnostic, Type};
+
+const RUST_EXTENSIONS: &[&str] = &["rs", "toml"];
+const C_EXTENSIONS: &[&str] = &[
+    "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "mk", "cmake",
+];
+const C_BUILD_FILES: &[&str] = &["Makefile", "makefile", "GNUmakefile", "CMakeLists.txt"];
+
+lazy_static! {
+    // e.g. "src/main.c:12:5: error: 'x' undeclared" or, from older compilers
+    // without columns, "main.c:3: warning: unused variable 'y' [-Wunused-variable]"
+    static ref REGEX_GCC: Regex = Regex::new(
+        r"^(.+?):([0-9]+):(?:([0-9]+):)? (fatal error|error|warning|note): (.*?)(?: \[(-W[^\]]+)\])?$"
+    )
+    .unwrap();
+}
+
+// Reads the output of a build command into diagnostics.
+pub trait Parser: Send + Sync {
+    // `output` holds everything the command printed and `stdout` only what it
+    // wrote to stdout; both may be the same text.
+    fn parse(&self, success: bool, output: &str, stdout: &str) -> Result<CompileResult, String>;
+
+    // Whether a change to `path`, relative to the project root, can affect
+    // the build.
+    fn watches(&self, path: &Path) -> bool;
+
+    // Whether the build runs rust's compiler, making its toolchain worth
+    // keeping track of.
+    fn is_rust(&self) -> bool {
+        false
+    }
+}
+
+// cargo and rustc's human readable or JSON diagnostics.
+pub struct RustcParser;
+
+impl Parser for RustcParser {
+    fn parse(&self, success: bool, output: &str, stdout: &str) -> Result<CompileResult, String> {
+        cargo::parse(success, output, stdout)
+    }
+
+    fn watches(&self, path: &Path) -> bool {
+        has_extension(path, RUST_EXTENSIONS)
+    }
+
+    fn is_rust(&self) -> bool {
+        true
+    }
+}
+
+// GCC and Clang style diagnostics, as printed by most C and C++ tool chains
+// and anything run through make or cmake.
+pub struct GccParser;
+
+impl Parser for GccParser {
+    fn parse(&self, success: bool, output: &str, _stdout: &str) -> Result<CompileResult, String> {
+        let mut result = CompileResult {
+            success,
+            ..Default::default()
+        };
+        let mut current: Option<RustDiagnostic> = None;
+        for raw in output.lines() {
+            let line = ansi::strip(raw);
+            if let Some(caps) = REGEX_GCC.captures(&line) {
+                // notes belong to whatever was reported before them
+                if &caps[4] == "note" {
+                    if let Some(diag) = current.as_mut() {
+                        push_detail(diag, &line);
+                    }
+                    continue;
+                }
+
+                finish(&mut result, current.take());
+                let type_ = if &caps[4] == "warning" {
+                    Type::Warning
+                } else {
+                    Type::Error
+                };
+                let mut diag = RustDiagnostic::new(
+                    type_,
+                    None,
+                    &caps[5],
+                    Some(&caps[1]),
+                    caps[2].parse().ok(),
+                    caps.get(3).and_then(|m| m.as_str().parse().ok()),
+                    Some(&line),
+                );
+                diag.lint = caps.get(6).map(|m| m.as_str().to_string());
+                current = Some(diag);
+            } else if let Some(diag) = RustDiagnostic::from_shell_error(&line) {
+                finish(&mut result, current.take());
+                result.push(diag);
+            } else {
+                match current.as_mut() {
+                    // the source line and caret under it
+                    Some(diag) if line.starts_with(' ') => push_detail(diag, &line),
+                    // e.g. "In function 'main':" or make's own messages
+                    _ => finish(&mut result, current.take()),
+                }
+            }
+        }
+        finish(&mut result, current);
+
+        Ok(result)
+    }
+
+    fn watches(&self, path: &Path) -> bool {
+        // cmake puts generated sources and makefiles in its build directories
+        let generated = path.components().any(|c| {
+            c.as_os_str()
+                .to_str()
+                .map(|c| c == "build" || c.starts_with("cmake-build"))
+                .unwrap_or(false)
+        });
+        let build_file = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| C_BUILD_FILES.contains(&n))
+            .unwrap_or(false);
+
+        !generated && (build_file || has_extension(path, C_EXTENSIONS))
+    }
+}
+
+// Reports no diagnostics, only whether the command succeeded. For tools whose
+// output can't be read but is still worth seeing.
+pub struct PassthroughParser;
+
+impl Parser for PassthroughParser {
+    fn parse(&self, success: bool, _output: &str, _stdout: &str) -> Result<CompileResult, String> {
+        Ok(CompileResult {
+            success,
+            ..Default::default()
+        })
+    }
+
+    fn watches(&self, _path: &Path) -> bool {
+        true
+    }
+}
+
+// A parser defined in the config, see `MatcherConfig`.
+#[derive(Debug)]
+pub struct ProblemMatcher {
+    regex: Regex,
+    config: MatcherConfig,
+}
+
+impl ProblemMatcher {
+    pub fn new(config: &MatcherConfig) -> Result<Self, String> {
+        let regex = Regex::new(&config.regexp).map_err(|e| format!("{}", e))?;
+        let groups = [
+            Some(config.file),
+            config.line,
+            config.column,
+            config.severity,
+            config.code,
+            Some(config.message),
+        ];
+        for group in groups.iter().flatten() {
+            if *group == 0 || *group >= regex.captures_len() {
+                return Err(format!("the regexp has no capture group {}", group));
+            }
+        }
+
+        Ok(ProblemMatcher {
+            regex,
+            config: config.clone(),
+        })
+    }
+}
+
+impl Parser for ProblemMatcher {
+    fn parse(&self, success: bool, output: &str, _stdout: &str) -> Result<CompileResult, String> {
+        let mut result = CompileResult {
+            success,
+            ..Default::default()
+        };
+        for raw in output.lines() {
+            let line = ansi::strip(raw);
+            let caps = match self.regex.captures(&line) {
+                Some(caps) => caps,
+                None => continue,
+            };
+            let group = |index: Option<usize>| {
+                index
+                    .and_then(|i| caps.get(i))
+                    .map(|m| m.as_str().trim())
+                    .filter(|s| !s.is_empty())
+            };
+
+            let type_ = match group(self.config.severity) {
+                Some(severity) if severity.to_lowercase().starts_with("warn") => Type::Warning,
+                _ => Type::Error,
+            };
+            result.push(RustDiagnostic::new(
+                type_,
+                group(self.config.code),
+                group(Some(self.config.message)).unwrap_or_default(),
+                group(Some(self.config.file)),
+                group(self.config.line).and_then(|l| l.parse().ok()),
+                group(self.config.column).and_then(|c| c.parse().ok()),
+                Some(&line),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    fn watches(&self, path: &Path) -> bool {
+        self.config.extensions.is_empty()
+            || path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| self.config.extensions.iter().any(|x| x == e))
+                .unwrap_or(false)
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| extensions.contains(&e))
+        .unwrap_or(false)
+}
+
+fn finish(result: &mut CompileResult, diag: Option<RustDiagnostic>) {
+    if let Some(diag) = diag {
+        result.push(diag);
+    }
+}
+
+fn push_detail(diag: &mut RustDiagnostic, line: &str) {
+    let details = diag.details.get_or_insert_with(String::new);
+    details.push('\n');
+    details.push_str(line);
+}
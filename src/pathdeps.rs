@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::process::ProcessRunner;
+
+// The directories of the local packages a project builds that are outside
+// of it, e.g. from `shared = { path = "../shared-lib" }`, whose changes
+// should start builds as much as the project's own. Asks `cargo metadata`,
+// with `env` set for it like for the build.
+pub fn load<R>(
+    runner: &R,
+    project_root: &Path,
+    env: &[(String, String)],
+) -> Result<Vec<PathBuf>, String>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let output = runner.run(
+        "cargo",
+        &["metadata", "--format-version", "1"],
+        env,
+        project_root,
+    )?;
+    if !output.success {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    outside_roots(&String::from_utf8_lossy(&output.stdout), project_root)
+}
+
+// The packages without a `source`, i.e. neither from a registry nor git, in
+// `cargo metadata`'s output, by the directory of their manifest. Ones in
+// `project_root`, or in another's directory, are left out.
+pub fn outside_roots(metadata: &str, project_root: &Path) -> Result<Vec<PathBuf>, String> {
+    let metadata = serde_json::from_str::<Value>(metadata).map_err(|e| format!("{:?}", e))?;
+    // cargo's paths are canonical
+    let project_root = project_root
+        .canonicalize()
+        .unwrap_or_else(|_| project_root.to_path_buf());
+
+    let mut dirs = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|package| package["source"].is_null())
+        .filter_map(|package| package["manifest_path"].as_str())
+        .filter_map(|manifest| Path::new(manifest).parent().map(Path::to_path_buf))
+        .filter(|dir| !dir.starts_with(&project_root))
+        .collect::<Vec<_>>();
+    // parents sort before what's in them
+    dirs.sort();
+    let mut roots: Vec<PathBuf> = vec![];
+    for dir in dirs {
+        if !roots.iter().any(|root| dir.starts_with(root)) {
+            roots.push(dir);
+        }
+    }
+    Ok(roots)
+}
@@ -0,0 +1,300 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cargo::FinishedUnit;
+use crate::process::ProcessRunner;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnitState {
+    // waiting on crates it depends on
+    Pending,
+    // everything it depends on is done, so cargo is compiling it or about to
+    Active,
+    Done,
+}
+
+// A crate in the build, i.e. a package of the resolved dependency graph.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Unit {
+    pub name: String,
+    pub version: String,
+    // the package ids of what it's built on, dev-dependencies left out
+    pub deps: Vec<String>,
+    // since the build started, once it's done
+    pub finished_at: Option<Duration>,
+    pub fresh: bool,
+    // how long compiling it took going by cargo's timings, if it sent them
+    pub timing: Option<Duration>,
+}
+
+// The crates a build compiles and how far it has got with each, for
+// watching which ones hold it up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Pipeline {
+    // by package id
+    pub units: BTreeMap<String, Unit>,
+}
+
+impl Pipeline {
+    // Asks cargo for the project's dependency graph, with `env` set for it
+    // like for the build.
+    pub fn load<R>(
+        runner: &R,
+        project_root: &Path,
+        env: &[(String, String)],
+    ) -> Result<Self, String>
+    where
+        R: ProcessRunner + ?Sized,
+    {
+        let output = runner.run(
+            "cargo",
+            &["metadata", "--format-version", "1"],
+            env,
+            project_root,
+        )?;
+        if !output.success {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Pipeline::from_metadata(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    // The graph in the `resolve` part of `cargo metadata`'s output.
+    pub fn from_metadata(metadata: &str) -> Result<Self, String> {
+        let metadata = serde_json::from_str::<Value>(metadata).map_err(|e| format!("{:?}", e))?;
+        let packages = metadata["packages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|package| {
+                let id = package["id"].as_str()?;
+                let name = package["name"].as_str()?;
+                let version = package["version"].as_str().unwrap_or_default();
+                Some((id, (name, version)))
+            })
+            .collect::<HashMap<_, _>>();
+        let nodes = metadata["resolve"]["nodes"]
+            .as_array()
+            .ok_or_else(|| "cargo metadata has no dependency graph.".to_string())?;
+
+        let mut units = BTreeMap::new();
+        for node in nodes {
+            let id = match node["id"].as_str() {
+                Some(id) => id,
+                None => continue,
+            };
+            let (name, version) = packages.get(id).copied().unwrap_or((id, ""));
+            let deps = node["deps"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|dep| !is_dev_only(dep))
+                .filter_map(|dep| dep["pkg"].as_str().map(ToString::to_string))
+                .collect();
+            units.insert(
+                id.to_string(),
+                Unit {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    deps,
+                    finished_at: None,
+                    fresh: false,
+                    timing: None,
+                },
+            );
+        }
+        Ok(Pipeline { units })
+    }
+
+    // Marks a crate done, `at` since the build started. A timing that follows
+    // the crate's artifact only adds how long it took.
+    pub fn finish(&mut self, finished: &FinishedUnit, at: Duration) {
+        if let Some(unit) = self.units.get_mut(&finished.package_id) {
+            if unit.finished_at.is_none() {
+                unit.finished_at = Some(at);
+            }
+            unit.fresh |= finished.fresh;
+            if finished.duration.is_some() {
+                unit.timing = finished.duration;
+            }
+        }
+    }
+
+    // Forgets what the last build did, for the next one.
+    pub fn reset(&mut self) {
+        for unit in self.units.values_mut() {
+            unit.finished_at = None;
+            unit.fresh = false;
+            unit.timing = None;
+        }
+    }
+
+    pub fn state(&self, id: &str) -> UnitState {
+        let unit = match self.units.get(id) {
+            Some(unit) => unit,
+            None => return UnitState::Pending,
+        };
+        if unit.finished_at.is_some() {
+            UnitState::Done
+        } else if unit.deps.iter().all(|dep| self.is_done(dep)) {
+            UnitState::Active
+        } else {
+            UnitState::Pending
+        }
+    }
+
+    fn is_done(&self, id: &str) -> bool {
+        // crates outside the graph aren't waited on
+        match self.units.get(id) {
+            Some(unit) => unit.finished_at.is_some(),
+            None => true,
+        }
+    }
+
+    pub fn count(&self, state: UnitState) -> usize {
+        self.units
+            .keys()
+            .filter(|id| self.state(id) == state)
+            .count()
+    }
+
+    // How long a crate took: cargo's timing when there is one, or else from
+    // when the last thing it waited on was done to when it was. Fresh
+    // crates and ones not done yet took nothing.
+    pub fn duration(&self, id: &str) -> Duration {
+        let unit = match self.units.get(id) {
+            Some(unit) => unit,
+            None => return Duration::default(),
+        };
+        if unit.fresh {
+            return Duration::default();
+        }
+        if let Some(timing) = unit.timing {
+            return timing;
+        }
+        let finished_at = match unit.finished_at {
+            Some(at) => at,
+            None => return Duration::default(),
+        };
+        let ready_at = unit
+            .deps
+            .iter()
+            .filter_map(|dep| self.units.get(dep)?.finished_at)
+            .max()
+            .unwrap_or_default();
+        finished_at.saturating_sub(ready_at)
+    }
+
+    // The chain of crates, each waiting on the one before, that took the
+    // longest to compile in all. Making any other crate faster doesn't make
+    // the build any faster.
+    pub fn critical_path(&self) -> Vec<String> {
+        let mut longest = HashMap::new();
+        let end = self
+            .units
+            .keys()
+            .map(|id| (self.longest_to(id, &mut longest, &mut vec![]), id))
+            .max_by_key(|(duration, id)| (*duration, Reverse(*id)))
+            .filter(|(duration, _)| *duration > Duration::default());
+        let mut path: Vec<String> = vec![];
+        let mut next = end.map(|(_, id)| id.clone());
+        while let Some(id) = next.filter(|id| !path.contains(id)) {
+            next = self.units[&id]
+                .deps
+                .iter()
+                .filter(|dep| self.units.contains_key(*dep))
+                .max_by_key(|dep| {
+                    (
+                        longest.get(*dep).copied().unwrap_or_default(),
+                        Reverse(*dep),
+                    )
+                })
+                .cloned();
+            path.push(id);
+        }
+        path.reverse();
+        path
+    }
+
+    // The longest a chain of crates ending with `id` took, memoized in
+    // `longest`. `visiting` guards against cycles, which cargo wouldn't
+    // build anyway.
+    fn longest_to(
+        &self,
+        id: &str,
+        longest: &mut HashMap<String, Duration>,
+        visiting: &mut Vec<String>,
+    ) -> Duration {
+        if let Some(duration) = longest.get(id) {
+            return *duration;
+        }
+        if visiting.iter().any(|v| v == id) {
+            return Duration::default();
+        }
+        visiting.push(id.to_string());
+        let before = self.units[id]
+            .deps
+            .iter()
+            .filter(|dep| self.units.contains_key(*dep))
+            .map(|dep| self.longest_to(dep, longest, visiting))
+            .max()
+            .unwrap_or_default();
+        visiting.pop();
+        let duration = before + self.duration(id);
+        longest.insert(id.to_string(), duration);
+        duration
+    }
+
+    // The crates in layers, those without dependencies first and each after
+    // the layer of the deepest crate it depends on.
+    pub fn layers(&self) -> Vec<Vec<String>> {
+        let mut depths = HashMap::new();
+        let mut layers: Vec<Vec<String>> = vec![];
+        for id in self.units.keys() {
+            let depth = self.depth(id, &mut depths, &mut vec![]);
+            if layers.len() <= depth {
+                layers.resize(depth + 1, vec![]);
+            }
+            layers[depth].push(id.clone());
+        }
+        layers
+    }
+
+    fn depth(
+        &self,
+        id: &str,
+        depths: &mut HashMap<String, usize>,
+        visiting: &mut Vec<String>,
+    ) -> usize {
+        if let Some(depth) = depths.get(id) {
+            return *depth;
+        }
+        if visiting.iter().any(|v| v == id) {
+            return 0;
+        }
+        visiting.push(id.to_string());
+        let depth = self.units[id]
+            .deps
+            .iter()
+            .filter(|dep| self.units.contains_key(*dep))
+            .map(|dep| self.depth(dep, depths, visiting) + 1)
+            .max()
+            .unwrap_or_default();
+        visiting.pop();
+        depths.insert(id.to_string(), depth);
+        depth
+    }
+}
+
+// A dependency only pulled in for tests, examples and benchmarks.
+fn is_dev_only(dep: &Value) -> bool {
+    let kinds = match dep["dep_kinds"].as_array() {
+        Some(kinds) if !kinds.is_empty() => kinds,
+        _ => return false,
+    };
+    kinds.iter().all(|kind| kind["kind"] == "dev")
+}
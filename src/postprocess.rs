@@ -0,0 +1,175 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::cargo::CompileResult;
+use crate::process;
+use crate::rust::RustDiagnostic;
+
+// how long a script gets unless the config says otherwise
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+// how often a running script is checked on
+const POLL: Duration = Duration::from_millis(20);
+
+// Inside a Flatpak the script is on the host, which only this reaches.
+const FLATPAK_INFO: &str = "/.flatpak-info";
+const FLATPAK_SPAWN: &[&str] = &["flatpak-spawn", "--host"];
+
+// A script each build's diagnostics go through before they're listed, to
+// drop, tag or reword them, e.g. by a team's own policies. It reads
+//
+//   {"project_root": "...", "success": false, "errors": [...], "warnings": [...]}
+//
+// on stdin, with the diagnostics as `/results` serves them, and prints the
+// same with its changes. Lists it leaves out, or no output at all, are kept
+// as they were.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PostProcessor {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    errors: Option<Vec<RustDiagnostic>>,
+    warnings: Option<Vec<RustDiagnostic>>,
+}
+
+impl PostProcessor {
+    pub fn new(command: &str) -> Self {
+        PostProcessor {
+            command: command.to_string(),
+            timeout: TIMEOUT,
+        }
+    }
+
+    // Runs the script on `result`'s diagnostics, leaving them as they were if
+    // it fails, takes too long or prints something else.
+    pub fn apply(
+        &self,
+        project_root: &Path,
+        env: &[(String, String)],
+        result: &mut CompileResult,
+    ) -> Result<(), String> {
+        let input = json!({
+            "project_root": project_root,
+            "success": result.success,
+            "errors": result.errors,
+            "warnings": result.warnings,
+        });
+        let stdout = run(
+            &self.command,
+            self.timeout,
+            project_root,
+            env,
+            input.to_string().into_bytes(),
+        )?;
+        if stdout.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+
+        let output = serde_json::from_slice::<Output>(&stdout)
+            .map_err(|e| format!("`{}` didn't print diagnostics: {}", self.command, e))?;
+        if let Some(errors) = output.errors {
+            result.errors = errors;
+        }
+        if let Some(warnings) = output.warnings {
+            result.warnings = warnings;
+        }
+        Ok(())
+    }
+}
+
+// Runs a script of the user's in `project_root` with `input` on stdin,
+// stopping it after `timeout`, and returns what it printed. Failing is an
+// error that says why, with what it printed to stderr.
+pub fn run(
+    command: &str,
+    timeout: Duration,
+    project_root: &Path,
+    env: &[(String, String)],
+    input: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    let mut words = process::split_command(command)?;
+    if words.is_empty() {
+        return Err("There's no command to run.".to_string());
+    }
+    // the host doesn't get the sandbox's variables unless they're passed on
+    if Path::new(FLATPAK_INFO).exists() {
+        let spawn = FLATPAK_SPAWN
+            .iter()
+            .map(ToString::to_string)
+            .chain(env.iter().map(|(k, v)| format!("--env={}={}", k, v)));
+        words.splice(0..0, spawn.collect::<Vec<_>>());
+    }
+
+    let mut child = Command::new(&words[0])
+        .args(&words[1..])
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .current_dir(project_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run `{}`: {:?}", command, e))?;
+
+    // written and read on the side, so that a script which prints before
+    // it's done reading can't block on a full pipe
+    let mut stdin = child.stdin.take();
+    thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("{:?}", e))? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "`{}` took longer than {} seconds and was stopped.",
+                command,
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(POLL);
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    if !status.success() {
+        let stderr = stderr.join().unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(format!(
+            "`{}` failed ({}): {}",
+            command,
+            status,
+            stderr.trim()
+        ));
+    }
+    Ok(stdout)
+}
+
+fn read_all<S>(stream: Option<S>) -> thread::JoinHandle<Vec<u8>>
+where
+    S: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        if let Some(mut stream) = stream {
+            let _ = stream.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::Path;
+
+// where Linux lists the machine's power supplies
+const POWER_SUPPLIES: &str = "/sys/class/power_supply";
+
+// Whether the machine runs on battery: it has a mains supply, e.g. a
+// laptop's AC adapter, and none of them is plugged in. Machines that don't
+// say, like desktops and systems other than Linux, are taken to be plugged
+// in.
+pub fn on_battery() -> bool {
+    on_battery_in(Path::new(POWER_SUPPLIES))
+}
+
+// Like `on_battery`, for the supplies listed in `dir`.
+pub fn on_battery_in(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    let mut mains = false;
+    for entry in entries.filter_map(Result::ok) {
+        let read = |name: &str| {
+            fs::read_to_string(entry.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        if read("type") != "Mains" {
+            continue;
+        }
+        if read("online") == "1" {
+            return false;
+        }
+        mains = true;
+    }
+    mains
+}
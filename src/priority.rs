@@ -0,0 +1,167 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::process::{ProcessOutput, ProcessRunner, Stop};
+
+// How builds run next to everything else on the machine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Priority {
+    #[default]
+    Normal,
+    // behind whatever's in the foreground
+    Low,
+    // only with what the machine has to spare
+    Idle,
+}
+
+impl Priority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+            Priority::Idle => "idle",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+        match inp {
+            "normal" => Ok(Priority::Normal),
+            "low" => Ok(Priority::Low),
+            "idle" => Ok(Priority::Idle),
+            _ => Err(format!("Invalid priority {}", inp)),
+        }
+    }
+}
+
+// Runs commands at a lower priority than the desktop's, so that a build on
+// every change doesn't make the machine sluggish: through `nice`, and
+// `ionice` for the disk on Linux, or in a lower priority class on Windows.
+// What they start inherits it.
+//
+//   nice -n 19 ionice -c 3 cargo check
+#[derive(Clone)]
+pub struct PriorityRunner {
+    pub priority: Priority,
+    host: Arc<dyn ProcessRunner>,
+}
+
+impl PriorityRunner {
+    // `host` runs the command it's wrapped in.
+    pub fn new(priority: Priority, host: Arc<dyn ProcessRunner>) -> Self {
+        PriorityRunner { priority, host }
+    }
+
+    // What runs `program` at the priority, and with what.
+    pub fn command(&self, program: &str, args: &[&str]) -> (String, Vec<String>) {
+        let mut words = prefix(self.priority);
+        words.push(program.to_string());
+        words.extend(args.iter().map(ToString::to_string));
+        let program = words.remove(0);
+        (program, words)
+    }
+}
+
+#[cfg(unix)]
+fn prefix(priority: Priority) -> Vec<String> {
+    let words: &[&str] = match priority {
+        Priority::Normal => &[],
+        #[cfg(target_os = "linux")]
+        Priority::Low => &["nice", "-n", "10", "ionice", "-c", "2", "-n", "7"],
+        #[cfg(target_os = "linux")]
+        Priority::Idle => &["nice", "-n", "19", "ionice", "-c", "3"],
+        #[cfg(not(target_os = "linux"))]
+        Priority::Low => &["nice", "-n", "10"],
+        #[cfg(not(target_os = "linux"))]
+        Priority::Idle => &["nice", "-n", "19"],
+    };
+    words.iter().map(ToString::to_string).collect()
+}
+
+// `start` waits for the program and exits with its status
+#[cfg(windows)]
+fn prefix(priority: Priority) -> Vec<String> {
+    let class = match priority {
+        Priority::Normal => return vec![],
+        Priority::Low => "/belownormal",
+        Priority::Idle => "/low",
+    };
+    ["cmd", "/c", "start", "", "/b", "/wait", class]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+// What builds and jobs run with at `priority`: `host` as it is at normal
+// priority.
+pub fn runner(priority: Priority, host: Arc<dyn ProcessRunner>) -> Arc<dyn ProcessRunner> {
+    match priority {
+        Priority::Normal => host,
+        _ => Arc::new(PriorityRunner::new(priority, host)),
+    }
+}
+
+impl ProcessRunner for PriorityRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        let (program, words) = self.command(program, args);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host.run(&program, &words, env, cwd)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let (program, words) = self.command(program, args);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host.run_streaming(&program, &words, env, cwd, on_line)
+    }
+
+    fn run_streaming_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let (program, words) = self.command(program, args);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host
+            .run_streaming_with_timeout(&program, &words, env, cwd, timeout, on_line)
+    }
+
+    fn run_streaming_until(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        stop: &Stop,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let (program, words) = self.command(program, args);
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        self.host
+            .run_streaming_until(&program, &words, env, cwd, stop, on_line)
+    }
+}
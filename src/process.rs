@@ -0,0 +1,488 @@
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// what a run that was stopped for taking too long fails with, followed by
+// how long it got
+const TIMED_OUT: &str = "timed out after ";
+
+// what a run that was stopped on request fails with
+const CANCELLED: &str = "cancelled";
+
+// how often a process whose output has ended is checked on
+const POLL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Debug, Default)]
+pub struct ProcessOutput {
+    pub success: bool,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+// `env` holds variables to set on top of the ones this process inherited.
+pub trait ProcessRunner: Send + Sync {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String>;
+
+    // Like `run`, with `input` written to the process's stdin, e.g. for
+    // what shouldn't show up in its arguments, where any local user can
+    // read them. Runners that can't write to a process fail.
+    fn run_with_input(
+        &self,
+        program: &str,
+        _args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+        _input: &[u8],
+    ) -> Result<ProcessOutput, String> {
+        Err(format!("Could not pass input to `{}`.", program))
+    }
+
+    // Like `run`, but hands every line the process prints to `on_line` as it
+    // is printed. Runners that can't stream pass the lines on once the
+    // process has exited.
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let output = self.run(program, args, env, cwd)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stderr.lines().chain(stdout.lines()) {
+            on_line(line);
+        }
+        Ok(output)
+    }
+
+    // Like `run_streaming`, but stops the process, and whatever it started,
+    // once it has run for `timeout`, failing with what `timed_out` says.
+    // Runners that can't stop processes let them run.
+    fn run_streaming_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        _timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        self.run_streaming(program, args, env, cwd, on_line)
+    }
+
+    // Like `run_streaming_with_timeout`, with the timeout optional, and
+    // stopping the process too once `stop.cancel` is set, failing with
+    // what `cancelled` says. Runners that can't stop processes let them run.
+    fn run_streaming_until(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        stop: &Stop,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        match stop.timeout {
+            Some(timeout) => {
+                self.run_streaming_with_timeout(program, args, env, cwd, timeout, on_line)
+            }
+            None => self.run_streaming(program, args, env, cwd, on_line),
+        }
+    }
+}
+
+// When to stop a process that hasn't exited by itself.
+#[derive(Clone, Debug, Default)]
+pub struct Stop {
+    pub timeout: Option<Duration>,
+    // set from another thread to stop it there and then
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+// Runs everything `inner` streams with a timeout, e.g. for builds that may
+// hang on a stuck build script.
+pub struct Timeout<'a> {
+    pub inner: &'a dyn ProcessRunner,
+    pub timeout: Duration,
+}
+
+impl ProcessRunner for Timeout<'_> {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        self.inner.run(program, args, env, cwd)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        self.inner
+            .run_streaming_with_timeout(program, args, env, cwd, self.timeout, on_line)
+    }
+}
+
+// Runs everything `inner` streams until `stop` says to stop, e.g. for builds
+// that can be cancelled from the build queue.
+pub struct Stoppable<'a> {
+    pub inner: &'a dyn ProcessRunner,
+    pub stop: Stop,
+}
+
+impl ProcessRunner for Stoppable<'_> {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        self.inner.run(program, args, env, cwd)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        self.inner
+            .run_streaming_until(program, args, env, cwd, &self.stop, on_line)
+    }
+}
+
+// The error a run stopped after `timeout` fails with, e.g. "timed out after
+// 30 s".
+pub fn timed_out(timeout: Duration) -> String {
+    format!("{}{} s", TIMED_OUT, timeout.as_secs())
+}
+
+pub fn is_timed_out(error: &str) -> bool {
+    error.starts_with(TIMED_OUT)
+}
+
+pub fn is_cancelled(error: &str) -> bool {
+    error == CANCELLED
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRunner;
+
+impl ProcessRunner for SystemRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        let output = Command::new(program)
+            .args(args)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .current_dir(cwd)
+            .output()
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(ProcessOutput {
+            success: output.status.success(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        input: &[u8],
+    ) -> Result<ProcessOutput, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .current_dir(cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{:?}", e))?;
+
+        // written on the side, so that a process which prints before it's
+        // done reading can't block on a full pipe
+        let mut stdin = child.stdin.take();
+        let input = input.to_vec();
+        thread::spawn(move || {
+            if let Some(stdin) = stdin.as_mut() {
+                let _ = stdin.write_all(&input);
+            }
+        });
+        let output = child.wait_with_output().map_err(|e| format!("{:?}", e))?;
+
+        Ok(ProcessOutput {
+            success: output.status.success(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        stream(program, args, env, cwd, &Stop::default(), on_line)
+    }
+
+    fn run_streaming_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        let stop = Stop {
+            timeout: Some(timeout),
+            ..Stop::default()
+        };
+        stream(program, args, env, cwd, &stop, on_line)
+    }
+
+    fn run_streaming_until(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        stop: &Stop,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        stream(program, args, env, cwd, stop, on_line)
+    }
+}
+
+fn stream(
+    program: &str,
+    args: &[&str],
+    env: &[(String, String)],
+    cwd: &Path,
+    stop: &Stop,
+    on_line: &mut dyn FnMut(&str),
+) -> Result<ProcessOutput, String> {
+    let timeout = stop.timeout;
+    let cancelled =
+        || matches!(stop.cancel.as_ref(), Some(cancel) if cancel.load(Ordering::SeqCst));
+    let stoppable = timeout.is_some() || stop.cancel.is_some();
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .current_dir(cwd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // a group of its own, so that what it starts can be stopped with it
+    #[cfg(unix)]
+    if stoppable {
+        command.process_group(0);
+    }
+    let mut child = command.spawn().map_err(|e| format!("{:?}", e))?;
+
+    // read both streams at once so that neither pipe fills up and blocks
+    // the process
+    let (tx, rx) = mpsc::channel();
+    forward_lines(child.stdout.take(), false, tx.clone());
+    forward_lines(child.stderr.take(), true, tx);
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut output = ProcessOutput::default();
+    let mut take = |(is_stderr, line): (bool, Vec<u8>)| {
+        on_line(String::from_utf8_lossy(&line).trim_end());
+        if is_stderr {
+            output.stderr.extend(line);
+        } else {
+            output.stdout.extend(line);
+        }
+    };
+    loop {
+        // looked for between lines, and every so often when there are none
+        if cancelled() {
+            kill_group(&mut child);
+            return Err(CANCELLED.to_string());
+        }
+        let wait = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()).min(POLL),
+            None => POLL,
+        };
+        match rx.recv_timeout(wait) {
+            Ok(received) => take(received),
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                // whatever still holds the pipes open is left to the
+                // threads reading them
+                if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+                    kill_group(&mut child);
+                    return Err(timed_out(timeout.unwrap_or_default()));
+                }
+                // as it is once the process has exited, e.g. a daemon it
+                // started, after what's been read so far is taken in
+                if child.try_wait().map_err(|e| format!("{:?}", e))?.is_some() {
+                    while let Ok(received) = rx.try_recv() {
+                        take(received);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    // the streams can close before the process exits
+    while stoppable && child.try_wait().map_err(|e| format!("{:?}", e))?.is_none() {
+        if cancelled() {
+            kill_group(&mut child);
+            return Err(CANCELLED.to_string());
+        }
+        if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+            kill_group(&mut child);
+            return Err(timed_out(timeout.unwrap_or_default()));
+        }
+        thread::sleep(POLL);
+    }
+    output.success = child.wait().map_err(|e| format!("{:?}", e))?.success();
+
+    Ok(output)
+}
+
+// Stops `child` and the rest of the process group it leads.
+fn kill_group(child: &mut Child) {
+    // a negative ID stands for the group the process leads; `kill` takes
+    // nothing it could read or write through
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Sends each line read from `stream`, newline included, until it closes.
+fn forward_lines<S>(stream: Option<S>, is_stderr: bool, tx: mpsc::Sender<(bool, Vec<u8>)>)
+where
+    S: Read + Send + 'static,
+{
+    if let Some(stream) = stream {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            loop {
+                let mut line = vec![];
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if tx.send((is_stderr, line)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+// Splits `command` into a program and its arguments the way a POSIX shell
+// would, minus everything but quoting: words are separated by whitespace,
+// single quotes keep what's in them as is, double quotes let a backslash
+// escape `"`, `\`, `$` and `` ` `` and a backslash outside quotes escapes
+// whatever follows it.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    // whether there's a word going, which an empty pair of quotes starts
+    let mut in_word = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unterminated quote in `{}`", command)),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') | Some(c @ '`') => {
+                                word.push(c)
+                            }
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("Unterminated quote in `{}`", command)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unterminated quote in `{}`", command)),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => return Err(format!("Trailing backslash in `{}`", command)),
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
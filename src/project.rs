@@ -0,0 +1,207 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use crate::toolchain;
+
+const PACKAGE_COMMAND: &str = "cargo check";
+const WORKSPACE_COMMAND: &str = "cargo check --workspace";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectKind {
+    // a single crate
+    Package,
+    // a manifest with workspace members, or one without a package of its own
+    Workspace,
+    // no Cargo.toml at all
+    Other,
+}
+
+// What a folder picked as the project root holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectInfo {
+    pub kind: ProjectKind,
+    // the channel a rust-toolchain(.toml) file pins for it
+    pub toolchain: Option<String>,
+    // the features the package at the root declares, `default` left out
+    pub features: Vec<String>,
+}
+
+impl ProjectInfo {
+    // The command that checks everything in the project, if it's built with
+    // cargo.
+    pub fn default_command(&self) -> Option<&'static str> {
+        match self.kind {
+            ProjectKind::Package => Some(PACKAGE_COMMAND),
+            ProjectKind::Workspace => Some(WORKSPACE_COMMAND),
+            ProjectKind::Other => None,
+        }
+    }
+}
+
+pub fn detect<P: AsRef<Path>>(project_root: P) -> ProjectInfo {
+    let project_root = project_root.as_ref();
+    let manifest = fs::read_to_string(project_root.join("Cargo.toml")).ok();
+    let kind = match manifest.as_deref() {
+        Some(manifest) => manifest_kind(manifest),
+        None => ProjectKind::Other,
+    };
+
+    ProjectInfo {
+        kind,
+        toolchain: toolchain::pinned_channel(project_root),
+        features: manifest.as_deref().map(features).unwrap_or_default(),
+    }
+}
+
+// The features `--features` and `--no-default-features` pick in a cargo
+// command, e.g. "cargo check --no-default-features --features json,tls".
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureSelection {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+}
+
+impl FeatureSelection {
+    // What `command` asks for. Only what comes before a `--` is cargo's.
+    pub fn of(command: &str) -> Self {
+        let mut selection = FeatureSelection::default();
+        let words = cargo_words(command);
+        let mut words = words.iter().copied();
+        while let Some(word) = words.next() {
+            let value = match word {
+                "--no-default-features" => {
+                    selection.no_default_features = true;
+                    continue;
+                }
+                "--features" | "-F" => words.next(),
+                _ => word.strip_prefix("--features="),
+            };
+            let features = value
+                .unwrap_or_default()
+                .trim_matches(&['"', '\''][..])
+                .split(&[',', ' '][..])
+                .filter(|feature| !feature.is_empty());
+            for feature in features {
+                if !selection.features.iter().any(|f| f == feature) {
+                    selection.features.push(feature.to_string());
+                }
+            }
+        }
+        selection
+    }
+
+    // Turns `feature` on, or off if it was on.
+    pub fn toggle(&mut self, feature: &str) {
+        match self.features.iter().position(|f| f == feature) {
+            Some(index) => {
+                self.features.remove(index);
+            }
+            None => self.features.push(feature.to_string()),
+        }
+    }
+
+    // `command` with its feature flags replaced by these, ahead of any
+    // arguments after a `--`.
+    pub fn apply(&self, command: &str) -> String {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        let cargo = cargo_words(command).len();
+        let mut kept = vec![];
+        let mut skip_value = false;
+        for word in &words[..cargo] {
+            if skip_value {
+                skip_value = false;
+            } else if *word == "--features" || *word == "-F" {
+                skip_value = true;
+            } else if *word != "--no-default-features" && !word.starts_with("--features=") {
+                kept.push(word.to_string());
+            }
+        }
+        if self.no_default_features {
+            kept.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            kept.push("--features".to_string());
+            kept.push(self.features.join(","));
+        }
+        kept.extend(words[cargo..].iter().map(ToString::to_string));
+        kept.join(" ")
+    }
+}
+
+// The words of `command` up to a `--`, past which they're for whatever cargo
+// runs.
+fn cargo_words(command: &str) -> Vec<&str> {
+    command
+        .split_whitespace()
+        .take_while(|word| *word != "--")
+        .collect()
+}
+
+// The names in a manifest's `[features]`, `default` left out.
+fn features(manifest: &str) -> Vec<String> {
+    let manifest = match manifest.parse::<toml::Value>() {
+        Ok(manifest) => manifest,
+        Err(_) => return vec![],
+    };
+    manifest
+        .get("features")
+        .and_then(|features| features.as_table())
+        .map(|features| {
+            features
+                .keys()
+                .filter(|name| *name != "default")
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Whether `command` is one we would have suggested ourselves, and so can be
+// replaced when another project is picked without losing anything the user
+// typed.
+pub fn is_default_command(command: &str) -> bool {
+    let command = command.trim();
+    command.is_empty() || command == PACKAGE_COMMAND || command == WORKSPACE_COMMAND
+}
+
+// The project root to pick for what was dropped onto the window, as a file
+// manager's `text/uri-list`: the first folder, or the folder a Cargo.toml is
+// in. Anything else, like a source file or a link on the web, isn't one.
+pub fn dropped_root<S: AsRef<str>>(uris: &[S]) -> Option<String> {
+    uris.iter().find_map(|uri| {
+        let (path, _) = glib::filename_from_uri(uri.as_ref()).ok()?;
+        let root = if path.is_dir() {
+            path
+        } else if path.file_name() == Some(OsStr::new("Cargo.toml")) {
+            path.parent()?.to_path_buf()
+        } else {
+            return None;
+        };
+        root.into_os_string().into_string().ok()
+    })
+}
+
+// A manifest that can't be read is still a cargo project, and cargo will
+// complain about it on the first build.
+fn manifest_kind(manifest: &str) -> ProjectKind {
+    let manifest = match manifest.parse::<toml::Value>() {
+        Ok(manifest) => manifest,
+        Err(_) => return ProjectKind::Package,
+    };
+
+    let workspace = match manifest.get("workspace") {
+        Some(workspace) => workspace,
+        None => return ProjectKind::Package,
+    };
+    let has_members = workspace
+        .get("members")
+        .and_then(|m| m.as_array())
+        .map(|m| !m.is_empty())
+        .unwrap_or(false);
+    if has_members || manifest.get("package").is_none() {
+        ProjectKind::Workspace
+    } else {
+        ProjectKind::Package
+    }
+}
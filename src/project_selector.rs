@@ -0,0 +1,82 @@
+use gettextrs::gettext;
+use vgtk::lib::gtk::{prelude::*, Box, Button, Entry, EntryExt, Label};
+use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
+
+use crate::accessible::AccessibleNameExt;
+use crate::style::InvalidExt;
+use crate::LABEL_CHARS;
+
+// The project to watch, typed in or picked with a folder chooser.
+#[derive(Default)]
+pub struct ProjectSelector {
+    props: SelectorProps,
+}
+
+#[derive(Clone, Default)]
+pub struct SelectorProps {
+    pub project_root: String,
+    // not while following someone else's watch
+    pub editable: bool,
+    // why watching couldn't start with what's entered
+    pub error: Option<String>,
+    pub on_changed: Callback<String>,
+    pub on_entered: Callback<()>,
+    pub on_browse: Callback<()>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SelectorMessage {
+    NoOp,
+    Changed(String),
+    Entered,
+    Browse,
+}
+
+impl Component for ProjectSelector {
+    type Message = SelectorMessage;
+    type Properties = SelectorProps;
+
+    fn create(props: Self::Properties) -> Self {
+        ProjectSelector { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        self.props = props;
+        UpdateAction::Render
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            SelectorMessage::NoOp => {}
+            SelectorMessage::Changed(path) => self.props.on_changed.send(path),
+            SelectorMessage::Entered => self.props.on_entered.send(()),
+            SelectorMessage::Browse => self.props.on_browse.send(()),
+        }
+        UpdateAction::None
+    }
+
+    fn view(&self) -> VNode<ProjectSelector> {
+        let entry_name = gettext("Project root");
+        let browse_name = gettext("Choose the project folder");
+        gtk! {
+            <Box spacing=10>
+                <Label label=gettext("Project Root:") width_chars=LABEL_CHARS xalign=1.0 />
+                <Entry hexpand=true accessible_name=entry_name.as_str()
+                       editable=self.props.editable
+                       invalid=self.props.error.is_some()
+                       tooltip_text=self.props.error.as_deref().unwrap_or("")
+                       text=self.props.project_root.clone()
+                       on activate=|_| SelectorMessage::Entered
+                       on property_text_notify=|inp| {
+                           match inp.get_text().map(|s| s.as_str().to_owned()) {
+                               Some(path) => SelectorMessage::Changed(path),
+                               None => SelectorMessage::NoOp,
+                           }
+                       } />
+                <Button label="..." accessible_name=browse_name.as_str()
+                        sensitive=self.props.editable
+                        on clicked=|_| SelectorMessage::Browse />
+            </Box>
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use gettextrs::gettext;
+use vgtk::lib::gtk::{
+    prelude::*, Align, Box, Button, Image, Label, ListBox, ListBoxRow, Orientation, SelectionMode,
+};
+use vgtk::{ext::*, gtk, Component, UpdateAction, VNode};
+
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::watcher::{BuildQueue, Job, JobState};
+
+use crate::accessible::AccessibleNameExt;
+use crate::style::{Severity, SeverityExt, StyleClassExt};
+use crate::{format_duration, project_name};
+
+// The builds of every window, waiting, running and done, the latest first,
+// with the ones not done yet to be cancelled.
+#[derive(Default)]
+pub struct QueueList {
+    props: QueueProps,
+}
+
+#[derive(Clone, Default)]
+pub struct QueueProps {
+    pub jobs: Vec<Job>,
+    // the window's own project, for its builds to say so
+    pub project_root: String,
+    pub build_queue: BuildQueue,
+}
+
+#[derive(Clone, Debug)]
+pub enum QueueMessage {
+    // the queue says when it's done
+    Cancel(u64),
+}
+
+impl Component for QueueList {
+    type Message = QueueMessage;
+    type Properties = QueueProps;
+
+    fn create(props: Self::Properties) -> Self {
+        QueueList { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        self.props = props;
+        UpdateAction::Render
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            QueueMessage::Cancel(id) => self.props.build_queue.cancel(id),
+        }
+        UpdateAction::None
+    }
+
+    fn view(&self) -> VNode<QueueList> {
+        let project_root = Path::new(&self.props.project_root);
+        let rows = self
+            .props
+            .jobs
+            .iter()
+            .rev()
+            .map(|job| render_job(job, project_root))
+            .collect::<Vec<_>>();
+
+        gtk! {
+            <ListBox selection_mode=SelectionMode::None>
+                { rows.into_iter() }
+            </ListBox>
+        }
+    }
+}
+
+fn render_job(job: &Job, project_root: &Path) -> VNode<QueueList> {
+    let id = job.id;
+    let tooltip = job.project_root.display().to_string();
+    let name = project_name(&tooltip);
+    let severity = match job.state {
+        JobState::Failed => Severity::Error,
+        JobState::Cancelled => Severity::Warning,
+        _ => Severity::Info,
+    };
+    let badge = match job.state {
+        JobState::Pending => gettext("WAITING"),
+        JobState::Running => gettext("BUILDING"),
+        JobState::Passed => gettext("OK"),
+        JobState::Failed => gettext("FAILED"),
+        JobState::Cancelled => gettext("CANCELLED"),
+    };
+    let ago = |at: SystemTime| at.elapsed().map(format_duration).unwrap_or_default();
+    let when = match (job.started_at, job.finished_at) {
+        (Some(started), Some(finished)) => fill(
+            gettext("Took {0}, finished {1} ago"),
+            &[
+                &format_duration(finished.duration_since(started).unwrap_or_default()),
+                &ago(finished),
+            ],
+        ),
+        (None, Some(finished)) => fill(
+            gettext("Cancelled {} ago, before it started"),
+            &[&ago(finished)],
+        ),
+        (Some(started), None) => fill(gettext("Building for {}"), &[&ago(started)]),
+        (None, None) => fill(gettext("Waiting for {}"), &[&ago(job.queued_at)]),
+    };
+    let title = if job.project_root == project_root {
+        fill(gettext("{} (this window)"), &[&name])
+    } else {
+        name
+    };
+    let accessible_name = [
+        title.as_str(),
+        badge.as_str(),
+        job.command.as_str(),
+        when.as_str(),
+    ]
+    .join(", ");
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity accessible_name=accessible_name.as_str()>
+            <Box spacing=10>
+                <Image property_icon_name=Some(severity.icon_name()) valign=Align::Start />
+                <Box orientation=Orientation::Vertical spacing=4 hexpand=true>
+                    <Box spacing=6>
+                        <Label label=title style_class="title"
+                               halign=Align::Start tooltip_text=tooltip.as_str() />
+                        <Label label=badge style_class="badge" valign=Align::Start />
+                    </Box>
+                    <Label label=job.command.clone() halign=Align::Start />
+                    <Label label=when style_class="location" halign=Align::Start />
+                </Box>
+                <Button label=gettext("Cancel") valign=Align::Start
+                        no_show_all=true visible=!job.state.is_done()
+                        on clicked=|_| QueueMessage::Cancel(id) />
+            </Box>
+        </ListBoxRow>
+    }
+}
@@ -0,0 +1,392 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cargo::CompileResult;
+use crate::process::ProcessRunner;
+use crate::rust::RustDiagnostic;
+
+// Used when the config doesn't name an editor. `{}` is replaced with the path
+// of the quickfix file.
+pub const DEFAULT_COMMAND: &str = "gvim -q {}";
+
+// Used when the config doesn't name a terminal. It starts in the directory
+// it's opened at; `{dir}` stands in for it too, for terminals that want it
+// spelled out.
+pub const DEFAULT_TERMINAL: &str = if cfg!(target_os = "windows") {
+    "start cmd"
+} else if cfg!(target_os = "macos") {
+    "open -a Terminal {dir}"
+} else {
+    "${TERMINAL:-x-terminal-emulator}"
+};
+
+// Formats diagnostics as "file:line:col: error: message [E0308]" lines, which
+// both vim's default 'errorformat' and VS Code's `$gcc` problem matcher read.
+// Files are made absolute so the list works whatever the editor's cwd is.
+pub fn to_quickfix<'a, P, I>(project_root: P, diags: I) -> String
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = &'a RustDiagnostic>,
+{
+    let mut out = String::new();
+    for diag in diags {
+        let file = match diag.file.as_ref() {
+            Some(file) => project_root.as_ref().join(file),
+            None => continue,
+        };
+        let _ = write!(
+            out,
+            "{}:{}:{}: {}: {}",
+            file.display(),
+            diag.line.unwrap_or(1),
+            diag.column.unwrap_or(1),
+            diag.type_,
+            diag.message
+        );
+        if let Some(code) = diag.num.as_ref().or(diag.lint.as_ref()) {
+            let _ = write!(out, " [{}]", code);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Writes the diagnostics of `result` that are in `file`, or all of them, to a
+// quickfix file for the project and returns its path.
+pub fn write<P: AsRef<Path>>(
+    project_root: P,
+    result: &CompileResult,
+    file: Option<&str>,
+) -> Result<PathBuf, String> {
+    let project_root = project_root.as_ref();
+    let diags = result
+        .diagnostics()
+        .filter(|diag| file.is_none() || diag.file.as_deref() == file);
+    let contents = to_quickfix(project_root, diags);
+    if contents.is_empty() {
+        return Err("There are no diagnostics with a location to open.".to_string());
+    }
+
+    let path = std::env::temp_dir().join(format!(
+        "watch-rust-errors-{}.qf",
+        project_root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    ));
+    fs::write(&path, contents).map_err(|e| format!("{:?}", e))?;
+
+    Ok(path)
+}
+
+// Fills `quickfix` into `command` and starts it from the project root without
+// waiting for the editor to exit.
+pub fn open<P, Q>(command: &str, quickfix: Q, project_root: P) -> Result<(), String>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let command = command.replace("{}", &quote(&quickfix.as_ref().to_string_lossy()));
+    spawn(&command, project_root)
+}
+
+// Fills where `diag` is into an editor command, e.g.
+// "code --goto {file}:{line}:{column}", for `{file}`, `{line}`, `{column}`,
+// `{end_line}` and `{end_column}`. The end of the span is where it starts
+// when the compiler didn't say, and positions are 1 when unknown. `None` for
+// diagnostics that aren't in any file.
+pub fn fill<P: AsRef<Path>>(
+    command: &str,
+    project_root: P,
+    diag: &RustDiagnostic,
+) -> Option<String> {
+    let file = project_root.as_ref().join(diag.file.as_ref()?);
+    let line = diag.line.unwrap_or(1);
+    let column = diag.column.unwrap_or(1);
+    Some(
+        command
+            .replace("{file}", &quote(&file.to_string_lossy()))
+            .replace("{line}", &line.to_string())
+            .replace("{column}", &column.to_string())
+            .replace("{end_line}", &diag.end_line.unwrap_or(line).to_string())
+            .replace(
+                "{end_column}",
+                &diag.end_column.unwrap_or(column).to_string(),
+            ),
+    )
+}
+
+// Editors that want more than a command line with the file and line in it,
+// e.g.
+//
+//   [editor]
+//   preset = "neovim"
+//   multiplexer = "tmux"
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "preset", rename_all = "kebab-case")]
+pub enum EditorPreset {
+    // through Zed's CLI, which hands the file to the running Zed
+    Zed,
+    // these get the file typed into the terminal they're already running in
+    Helix(TerminalSession),
+    Neovim(TerminalSession),
+}
+
+// Where to find a terminal editor that's already running.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalSession {
+    pub multiplexer: Multiplexer,
+    // the tmux target, WezTerm pane ID or kitty window ID to type into;
+    // without one, the first pane `matching` finds
+    #[serde(default)]
+    pub pane: Option<String>,
+    // a regex for what runs in the pane, e.g. "^nvim$"; the editor's own
+    // program when left out
+    #[serde(default)]
+    pub matching: Option<String>,
+    // the tmux server's socket or kitty's `--listen-on` address, for
+    // multiplexers this isn't running under
+    #[serde(default)]
+    pub socket: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Multiplexer {
+    Tmux,
+    Wezterm,
+    Kitty,
+}
+
+impl EditorPreset {
+    // The preset as a command for `fill`, after finding the pane to type
+    // into if the config doesn't name one.
+    pub fn open_command(&self, runner: &dyn ProcessRunner) -> Result<String, String> {
+        let (session, keys) = match self {
+            EditorPreset::Zed => return Ok("zed {file}:{line}:{column}".to_string()),
+            EditorPreset::Helix(session) => (session, r"\033:open %s:{line}:{column}\r"),
+            EditorPreset::Neovim(session) => {
+                (session, r"\033:edit %s\r:call cursor({line}, {column})\r")
+            }
+        };
+        let pane = match session.pane.as_ref() {
+            Some(pane) => pane.clone(),
+            None => self.find_pane(runner)?.0,
+        };
+        let typed = format!("\"$(printf '{}' {{file}})\"", keys);
+        let socket = |flag: &str| {
+            session
+                .socket
+                .as_ref()
+                .map(|socket| format!(" {} {}", flag, quote(socket)))
+                .unwrap_or_default()
+        };
+        Ok(match session.multiplexer {
+            Multiplexer::Tmux => format!(
+                "tmux{} send-keys -t {} -l {}",
+                socket("-S"),
+                quote(&pane),
+                typed
+            ),
+            Multiplexer::Wezterm => format!(
+                "wezterm cli send-text --no-paste --pane-id {} {}",
+                quote(&pane),
+                typed
+            ),
+            Multiplexer::Kitty => format!(
+                "kitty @{} send-text --match id:{} {}",
+                socket("--to"),
+                quote(&pane),
+                typed
+            ),
+        })
+    }
+
+    // Where diagnostics would go, in words, for trying out the setup.
+    pub fn check(&self, runner: &dyn ProcessRunner) -> Result<String, String> {
+        let session = match self {
+            EditorPreset::Zed => return Ok("Diagnostics open through Zed's CLI.".to_string()),
+            EditorPreset::Helix(session) | EditorPreset::Neovim(session) => session,
+        };
+        match session.pane.as_ref() {
+            Some(pane) => Ok(format!("Diagnostics are typed into pane {}.", pane)),
+            None => {
+                let (pane, running) = self.find_pane(runner)?;
+                Ok(format!(
+                    "Diagnostics are typed into pane {}, which is running {}.",
+                    pane, running
+                ))
+            }
+        }
+    }
+
+    // The first pane running the editor, and what runs in it.
+    pub fn find_pane(&self, runner: &dyn ProcessRunner) -> Result<(String, String), String> {
+        let (session, program) = match self {
+            EditorPreset::Zed => return Err("Zed doesn't run in a terminal.".to_string()),
+            EditorPreset::Helix(session) => (session, r"\b(hx|helix)\b"),
+            EditorPreset::Neovim(session) => (session, r"\bn?vim\b"),
+        };
+        let matching = session.matching.as_deref().unwrap_or(program);
+        let matching = Regex::new(matching).map_err(|e| format!("{:?}", e))?;
+        let panes = match session.multiplexer {
+            Multiplexer::Tmux => tmux_panes(runner, session.socket.as_deref())?,
+            Multiplexer::Wezterm => wezterm_panes(runner)?,
+            Multiplexer::Kitty => kitty_panes(runner, session.socket.as_deref())?,
+        };
+        panes
+            .into_iter()
+            .find(|(_, running)| matching.is_match(running))
+            .ok_or_else(|| format!("No pane is running anything matching `{}`.", matching))
+    }
+}
+
+// Every pane as its ID and the program in the foreground.
+fn tmux_panes(
+    runner: &dyn ProcessRunner,
+    socket: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut args = vec![];
+    if let Some(socket) = socket {
+        args.extend(&["-S", socket]);
+    }
+    args.extend(&[
+        "list-panes",
+        "-a",
+        "-F",
+        "#{pane_id}\t#{pane_current_command}",
+    ]);
+    let output = list(runner, "tmux", &args)?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let (id, running) = line.split_once('\t')?;
+            Some((id.to_string(), running.to_string()))
+        })
+        .collect())
+}
+
+// WezTerm only says what's running through the pane's title.
+fn wezterm_panes(runner: &dyn ProcessRunner) -> Result<Vec<(String, String)>, String> {
+    let output = list(runner, "wezterm", &["cli", "list", "--format", "json"])?;
+    let panes: Value = serde_json::from_str(&output).map_err(|e| format!("{:?}", e))?;
+    Ok(panes
+        .as_array()
+        .map(|panes| {
+            panes
+                .iter()
+                .filter_map(|pane| {
+                    Some((
+                        pane["pane_id"].as_u64()?.to_string(),
+                        pane["title"].as_str()?.to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+// Every window of every tab, with the command lines in its foreground.
+fn kitty_panes(
+    runner: &dyn ProcessRunner,
+    socket: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut args = vec!["@"];
+    if let Some(socket) = socket {
+        args.extend(&["--to", socket]);
+    }
+    args.push("ls");
+    let output = list(runner, "kitty", &args)?;
+    let os_windows: Value = serde_json::from_str(&output).map_err(|e| format!("{:?}", e))?;
+    let windows = os_windows
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|os_window| os_window["tabs"].as_array())
+        .flatten()
+        .filter_map(|tab| tab["windows"].as_array())
+        .flatten();
+    Ok(windows
+        .filter_map(|window| {
+            let running = window["foreground_processes"]
+                .as_array()?
+                .iter()
+                .filter_map(|process| process["cmdline"].as_array())
+                .map(|cmdline| {
+                    cmdline
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some((window["id"].as_u64()?.to_string(), running))
+        })
+        .collect())
+}
+
+fn list(runner: &dyn ProcessRunner, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = runner.run(program, args, &[], Path::new("."))?;
+    if output.success {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!(
+            "Could not list {}'s panes: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// Opens the editor `command` names right where `diag` is.
+pub fn open_at<P: AsRef<Path>>(
+    command: &str,
+    project_root: P,
+    diag: &RustDiagnostic,
+) -> Result<(), String> {
+    let command = fill(command, &project_root, diag)
+        .ok_or_else(|| "The diagnostic isn't in any file.".to_string())?;
+    spawn(&command, project_root)
+}
+
+// Opens a terminal at `dir`.
+pub fn open_terminal<P: AsRef<Path>>(command: &str, dir: P) -> Result<(), String> {
+    let command = command.replace("{dir}", &quote(&dir.as_ref().to_string_lossy()));
+    spawn(&command, dir)
+}
+
+fn spawn<P: AsRef<Path>>(command: &str, project_root: P) -> Result<(), String> {
+    let mut child = if cfg!(target_os = "windows") {
+        Command::new("cmd")
+            .args(["/C", command])
+            .current_dir(project_root)
+            .spawn()
+    } else {
+        Command::new("sh")
+            .args(["-c", command])
+            .current_dir(project_root)
+            .spawn()
+    }
+    .map_err(|e| format!("{:?}", e))?;
+
+    // reap the editor once it exits
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+fn quote(path: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
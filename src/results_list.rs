@@ -0,0 +1,837 @@
+use gettextrs::{gettext, ngettext};
+use vgtk::lib::gio::Menu;
+use vgtk::lib::gtk::{
+    prelude::*, Align, Box, Button, Image, Label, ListBox, ListBoxRow, MenuButton, Orientation,
+    ReliefStyle, SelectionMode,
+};
+use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
+
+use watch_rust_errors::ansi;
+use watch_rust_errors::cargo::{CodeGroup, CrateGroup, DiagnosticId, FileGroup};
+use watch_rust_errors::explain;
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::libtest::TestFailure;
+use watch_rust_errors::listing;
+use watch_rust_errors::rust::{RustDiagnostic, Type};
+
+use crate::accessible::{AccessibleDescriptionExt, AccessibleNameExt};
+use crate::style::{Change, ChangeExt, CurrentExt, Severity, SeverityExt, StyleClassExt};
+
+// A list of diagnostics and whatever else goes with them, e.g. the latest
+// results or a comparison's. Which rows to show is up to whoever shows the
+// list; what's clicked in it is handed back to them.
+#[derive(Default)]
+pub struct ResultsList {
+    props: ResultsProps,
+}
+
+#[derive(Clone, Default)]
+pub struct ResultsProps {
+    pub rows: Vec<ListRow>,
+    pub on_toggle_cascade: Callback<DiagnosticId>,
+    pub on_toggle_macro_trace: Callback<DiagnosticId>,
+    pub on_apply_fix: Callback<DiagnosticId>,
+    pub on_explain: Callback<String>,
+    pub on_toggle_file: Callback<Option<String>>,
+    pub on_toggle_code: Callback<Option<String>>,
+    pub on_fetch: Callback<()>,
+    pub on_show_all_modules: Callback<()>,
+    pub on_show_all_codes: Callback<()>,
+    pub on_show_all_changes: Callback<()>,
+    pub on_show_baseline: Callback<()>,
+    pub on_show_unfocused: Callback<()>,
+    pub on_toggle_elsewhere: Callback<()>,
+    pub on_show_more: Callback<()>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ResultsMessage {
+    NoOp,
+    ToggleCascade(DiagnosticId),
+    ToggleMacroTrace(DiagnosticId),
+    ApplyFix(DiagnosticId),
+    Explain(String),
+    // folds a file's diagnostics away when grouping by file, or back out
+    ToggleFile(Option<String>),
+    // lists every location of a code when grouping by code, or stops
+    ToggleCode(Option<String>),
+    Fetch,
+    ShowAllModules,
+    ShowAllCodes,
+    ShowAllChanges,
+    ShowBaseline,
+    ShowUnfocused,
+    ToggleElsewhere,
+    ShowMore,
+}
+
+impl Component for ResultsList {
+    type Message = ResultsMessage;
+    type Properties = ResultsProps;
+
+    fn create(props: Self::Properties) -> Self {
+        ResultsList { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        self.props = props;
+        UpdateAction::Render
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        let props = &self.props;
+        match msg {
+            ResultsMessage::NoOp => {}
+            ResultsMessage::ToggleCascade(id) => props.on_toggle_cascade.send(id),
+            ResultsMessage::ToggleMacroTrace(id) => props.on_toggle_macro_trace.send(id),
+            ResultsMessage::ApplyFix(id) => props.on_apply_fix.send(id),
+            ResultsMessage::Explain(code) => props.on_explain.send(code),
+            ResultsMessage::ToggleFile(file) => props.on_toggle_file.send(file),
+            ResultsMessage::ToggleCode(code) => props.on_toggle_code.send(code),
+            ResultsMessage::Fetch => props.on_fetch.send(()),
+            ResultsMessage::ShowAllModules => props.on_show_all_modules.send(()),
+            ResultsMessage::ShowAllCodes => props.on_show_all_codes.send(()),
+            ResultsMessage::ShowAllChanges => props.on_show_all_changes.send(()),
+            ResultsMessage::ShowBaseline => props.on_show_baseline.send(()),
+            ResultsMessage::ShowUnfocused => props.on_show_unfocused.send(()),
+            ResultsMessage::ToggleElsewhere => props.on_toggle_elsewhere.send(()),
+            ResultsMessage::ShowMore => props.on_show_more.send(()),
+        }
+        UpdateAction::None
+    }
+
+    fn view(&self) -> VNode<ResultsList> {
+        gtk! {
+            <ListBox selection_mode=SelectionMode::None>
+                { self.props.rows.iter().map(ListRow::render) }
+            </ListBox>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ListRow {
+    Result(ResultRow),
+    // a file's header when grouping by file, which folds its diagnostics
+    // away when clicked
+    File {
+        file: Option<String>,
+        errors: usize,
+        warnings: usize,
+        // how many of the file's lines its diagnostics cover
+        lines: usize,
+        collapsed: bool,
+    },
+    // a code's header when grouping by code, with the message of the
+    // diagnostic standing in for the others, which lists where they all are
+    // when clicked
+    Code {
+        code: Option<String>,
+        message: String,
+        errors: usize,
+        warnings: usize,
+        expanded: bool,
+    },
+    // where each of a code's diagnostics is
+    Locations(Vec<String>),
+    // the build needs dependencies that working offline kept it from getting
+    Fetch,
+    ModuleFilter(String),
+    // the code picked in the codes tab
+    CodeFilter(String),
+    // the ref the changed files are listed since
+    ChangesFilter(String),
+    // how many diagnostics the hidden baseline has
+    BaselineFilter(usize),
+    // the file the list focuses on
+    FocusFilter(String),
+    // the header of what's not in the focused file, which opens it up when
+    // clicked
+    Elsewhere {
+        errors: usize,
+        warnings: usize,
+        expanded: bool,
+    },
+    // how many rows were left out to keep the list quick to build
+    ShowMore(usize),
+}
+
+impl ListRow {
+    pub fn file(group: &FileGroup, collapsed: bool) -> Self {
+        ListRow::File {
+            file: group.file.map(ToString::to_string),
+            errors: group.errors,
+            warnings: group.warnings,
+            lines: group.lines(),
+            collapsed,
+        }
+    }
+
+    pub fn code(group: &CodeGroup, expanded: bool) -> Self {
+        ListRow::Code {
+            code: group.code.map(ToString::to_string),
+            message: group
+                .diagnostics
+                .first()
+                .map(|(_, diag)| diag.message.clone())
+                .unwrap_or_default(),
+            errors: group.errors,
+            warnings: group.warnings,
+            expanded,
+        }
+    }
+
+    // The locations of a code's diagnostics, or "unknown" for those without.
+    pub fn locations(group: &CodeGroup) -> Self {
+        ListRow::Locations(
+            group
+                .diagnostics
+                .iter()
+                .map(|(_, diag)| diag.location().unwrap_or_else(|| gettext("unknown")))
+                .collect(),
+        )
+    }
+
+    fn render(&self) -> VNode<ResultsList> {
+        match self {
+            ListRow::Result(row) => row.clone().render(),
+            ListRow::File {
+                file,
+                errors,
+                warnings,
+                lines,
+                collapsed,
+            } => render_file_header(file.clone(), *errors, *warnings, *lines, *collapsed),
+            ListRow::Code {
+                code,
+                message,
+                errors,
+                warnings,
+                expanded,
+            } => render_code_header(code.clone(), message, *errors, *warnings, *expanded),
+            ListRow::Locations(locations) => render_locations(locations),
+            ListRow::Fetch => render_notice(
+                gettext("Dependencies need fetching before building offline."),
+                gettext("Fetch Dependencies"),
+                ResultsMessage::Fetch,
+            ),
+            ListRow::ModuleFilter(module) => render_notice(
+                fill(gettext("Only showing diagnostics in {}."), &[module]),
+                gettext("Show All"),
+                ResultsMessage::ShowAllModules,
+            ),
+            ListRow::CodeFilter(code) => render_notice(
+                fill(gettext("Only showing {} diagnostics."), &[code]),
+                gettext("Show All"),
+                ResultsMessage::ShowAllCodes,
+            ),
+            ListRow::ChangesFilter(base) => render_notice(
+                fill(
+                    gettext("Only showing diagnostics in files changed since {}."),
+                    &[base],
+                ),
+                gettext("Show All"),
+                ResultsMessage::ShowAllChanges,
+            ),
+            ListRow::BaselineFilter(hidden) => render_notice(
+                fill(
+                    ngettext(
+                        "Hiding {} diagnostic that was already in the baseline.",
+                        "Hiding {} diagnostics that were already in the baseline.",
+                        *hidden as u32,
+                    ),
+                    &[hidden],
+                ),
+                gettext("Show All"),
+                ResultsMessage::ShowBaseline,
+            ),
+            ListRow::FocusFilter(file) => render_notice(
+                fill(gettext("Focusing on {}, the file edited last."), &[file]),
+                gettext("Show All"),
+                ResultsMessage::ShowUnfocused,
+            ),
+            ListRow::Elsewhere {
+                errors,
+                warnings,
+                expanded,
+            } => render_elsewhere(*errors, *warnings, *expanded),
+            ListRow::ShowMore(hidden) => render_show_more(*hidden),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ResultRow {
+    pub severity: Severity,
+    pub title: String,
+    pub badge: Option<String>,
+    pub location: Option<String>,
+    // pango markup, so that colored compiler output keeps its colors
+    pub details: Option<String>,
+    // highlighted source around the location, also markup
+    pub snippet: Option<String>,
+    // what applying the fix would change, also markup
+    pub fix_preview: Option<String>,
+    // the compiler's notes and helps, and where else it points, as markup
+    pub children: Option<String>,
+    pub fix: Option<DiagnosticId>,
+    // the error code, when rustc can explain it
+    pub explain: Option<String>,
+    pub copy: Option<DiagnosticId>,
+    // the row keyboard navigation is on
+    pub current: bool,
+    // for a likely cause of other errors, its id, how many there are and
+    // whether they're listed
+    pub cascade: Option<(DiagnosticId, usize, bool)>,
+    // for an error from inside macros, its id, the macros it went through
+    // and whether where they were invoked is listed
+    pub macro_trace: Option<(DiagnosticId, Vec<String>, bool)>,
+    // what a post-processor tagged the diagnostic with
+    pub tags: Option<String>,
+    // indented under the error it likely follows from
+    pub consequence: bool,
+    pub change: Change,
+    // what a screen reader announces for the row, when it's more than what
+    // it shows, and what it reads after that
+    pub accessible_name: Option<String>,
+    pub accessible_description: Option<String>,
+}
+
+impl ResultRow {
+    pub fn diagnostic(
+        id: DiagnosticId,
+        diag: &RustDiagnostic,
+        snippet: Option<String>,
+        current: bool,
+    ) -> Self {
+        ResultRow {
+            severity: Severity::from(&diag.type_),
+            title: diag.title(),
+            badge: if diag.repeats > 0 {
+                Some(format!("×{}", diag.repeats + 1))
+            } else {
+                None
+            },
+            location: diag.location(),
+            details: diag
+                .styled_details
+                .as_ref()
+                .or(diag.details.as_ref())
+                .map(|d| ansi::to_pango(d.trim_end())),
+            snippet,
+            fix_preview: None,
+            children: children_markup(diag),
+            fix: if diag.is_fixable() { Some(id) } else { None },
+            explain: diag
+                .num
+                .clone()
+                .filter(|code| explain::is_explainable(code)),
+            copy: Some(id),
+            current,
+            cascade: None,
+            macro_trace: if diag.macro_backtrace.is_empty() {
+                None
+            } else {
+                let frames = diag.macro_backtrace.iter().map(ToString::to_string);
+                Some((id, frames.collect(), false))
+            },
+            tags: tags(diag),
+            consequence: false,
+            change: Change::Unchanged,
+            accessible_name: Some(accessible_name(diag)),
+            accessible_description: diag.details.as_ref().map(|d| d.trim_end().to_string()),
+        }
+    }
+
+    pub fn test_failure(failure: &TestFailure) -> Self {
+        ResultRow {
+            severity: Severity::Error,
+            title: fill(gettext("test failed: {}"), &[&failure.name]),
+            badge: None,
+            location: failure.file.as_ref().map(|file| {
+                format!(
+                    "{}:{}:{}",
+                    file,
+                    failure.line.unwrap_or_default(),
+                    failure.column.unwrap_or_default()
+                )
+            }),
+            details: failure.message.as_deref().map(ansi::to_pango),
+            snippet: None,
+            fix_preview: None,
+            children: None,
+            fix: None,
+            explain: None,
+            copy: None,
+            current: false,
+            cascade: None,
+            macro_trace: None,
+            tags: None,
+            consequence: false,
+            change: Change::Unchanged,
+            accessible_name: None,
+            accessible_description: None,
+        }
+    }
+
+    pub fn crate_header(group: &CrateGroup) -> Self {
+        let errors = group
+            .diagnostics
+            .iter()
+            .filter(|(_, d)| d.type_ == Type::Error)
+            .count();
+        let warnings = group.diagnostics.len() - errors;
+        let name = group
+            .name
+            .map(String::from)
+            .unwrap_or_else(|| gettext("other"));
+
+        ResultRow {
+            severity: if group.failed {
+                Severity::Error
+            } else {
+                Severity::Info
+            },
+            title: fill(
+                gettext("{0} ({1} errors, {2} warnings)"),
+                &[&name, &errors, &warnings],
+            ),
+            badge: Some(if group.failed {
+                gettext("FAILED")
+            } else {
+                gettext("OK")
+            }),
+            location: None,
+            details: None,
+            snippet: None,
+            fix_preview: None,
+            children: None,
+            fix: None,
+            explain: None,
+            copy: None,
+            current: false,
+            cascade: None,
+            macro_trace: None,
+            tags: None,
+            consequence: false,
+            change: Change::Unchanged,
+            accessible_name: None,
+            accessible_description: None,
+        }
+    }
+
+    // A diagnostic from somewhere other than the latest results, e.g. a
+    // comparison or a scheduled job.
+    pub fn listed(diag: &RustDiagnostic, badge: Option<String>) -> Self {
+        ResultRow {
+            severity: Severity::from(&diag.type_),
+            title: diag.title(),
+            badge,
+            location: diag.location(),
+            details: None,
+            snippet: None,
+            fix_preview: None,
+            children: None,
+            fix: None,
+            explain: None,
+            copy: None,
+            current: false,
+            cascade: None,
+            macro_trace: None,
+            tags: tags(diag),
+            consequence: false,
+            change: Change::Unchanged,
+            accessible_name: Some(accessible_name(diag)),
+            accessible_description: None,
+        }
+    }
+
+    pub fn text(severity: Severity, text: &str) -> Self {
+        ResultRow {
+            severity,
+            title: text.to_string(),
+            badge: None,
+            location: None,
+            details: None,
+            snippet: None,
+            fix_preview: None,
+            children: None,
+            fix: None,
+            explain: None,
+            copy: None,
+            current: false,
+            cascade: None,
+            macro_trace: None,
+            tags: None,
+            consequence: false,
+            change: Change::Unchanged,
+            accessible_name: None,
+            accessible_description: None,
+        }
+    }
+
+    // Every row has the same widgets, those it doesn't need hidden, so that
+    // a re-render patches the rows already in the list rather than building
+    // new ones whenever a row's parts differ from the row before at its spot.
+    pub fn render(self) -> VNode<ResultsList> {
+        let cascade = self.cascade;
+        let cascade_label = cascade
+            .map(|(_, count, expanded)| {
+                let label = if expanded {
+                    ngettext(
+                        "Hide {} error possibly caused by this",
+                        "Hide {} errors possibly caused by this",
+                        count as u32,
+                    )
+                } else {
+                    ngettext(
+                        "Show {} error possibly caused by this",
+                        "Show {} errors possibly caused by this",
+                        count as u32,
+                    )
+                };
+                fill(label, &[&count])
+            })
+            .unwrap_or_default();
+        let macro_trace = self.macro_trace.as_ref().map(|(id, _, _)| *id);
+        let (macro_trace_label, macro_trace_lines) = match self.macro_trace.as_ref() {
+            Some((_, frames, true)) => (gettext("Fold macro trace"), Some(frames.join("\n"))),
+            Some((_, frames, false)) => (
+                fill(
+                    ngettext(
+                        "Expand macro trace ({} macro)",
+                        "Expand macro trace ({} macros)",
+                        frames.len() as u32,
+                    ),
+                    &[&frames.len()],
+                ),
+                None,
+            ),
+            None => (String::new(), None),
+        };
+        let fix = self.fix;
+        let explain = self.explain.clone();
+        let more = gettext("More");
+        let name = self.accessible_name.clone().unwrap_or_else(|| {
+            let said = fill(gettext("{0}: {1}"), &[&self.severity.name(), &self.title]);
+            located(said, self.location.as_deref())
+        });
+        let name = match self.badge.as_ref() {
+            Some(badge) => fill(gettext("{0}, {1}"), &[&name, badge]),
+            None => name,
+        };
+        let description = self.accessible_description.clone().unwrap_or_default();
+
+        gtk! {
+            <ListBoxRow severity=self.severity change=self.change current=self.current
+                    is_focus=self.current accessible_name=name.as_str()
+                    accessible_description=description.as_str()>
+                <Box spacing=10 margin_start={ if self.consequence { 24 } else { 0 } }>
+                    <Image property_icon_name=Some(self.severity.icon_name()) valign=Align::Start />
+                    <Box orientation=Orientation::Vertical spacing=4 hexpand=true>
+                        <Box spacing=6>
+                            <Label label=self.title style_class="title" halign=Align::Start />
+                            <Label label=self.badge.clone().unwrap_or_default()
+                                   style_class="badge" valign=Align::Start
+                                   no_show_all=true visible=self.badge.is_some() />
+                            <Label label=self.tags.clone().unwrap_or_default()
+                                   style_class="tags" valign=Align::Start
+                                   no_show_all=true visible=self.tags.is_some() />
+                        </Box>
+                        <Label label=self.location.clone().unwrap_or_default()
+                               style_class="location" selectable=true can_focus=false
+                               halign=Align::Start
+                               no_show_all=true visible=self.location.is_some() />
+                        <Label label=self.details.clone().unwrap_or_default() use_markup=true
+                               style_class="details" selectable=true can_focus=false
+                               halign=Align::Start
+                               no_show_all=true visible=self.details.is_some() />
+                        <Label label=self.snippet.clone().unwrap_or_default() use_markup=true
+                               style_class="snippet" selectable=true can_focus=false
+                               halign=Align::Start
+                               no_show_all=true visible=self.snippet.is_some() />
+                        <Label label=self.fix_preview.clone().unwrap_or_default() use_markup=true
+                               style_class="fix-preview" selectable=true can_focus=false
+                               halign=Align::Start
+                               no_show_all=true visible=self.fix_preview.is_some() />
+                        <Label label=self.children.clone().unwrap_or_default() use_markup=true
+                               style_class="children" selectable=true can_focus=false
+                               halign=Align::Start
+                               margin_start=24 no_show_all=true visible=self.children.is_some() />
+                        <Button label=cascade_label relief=ReliefStyle::None halign=Align::Start
+                                no_show_all=true visible=cascade.is_some()
+                                on clicked=|_| match cascade {
+                                    Some((id, _, _)) => ResultsMessage::ToggleCascade(id),
+                                    None => ResultsMessage::NoOp,
+                                } />
+                        <Button label=macro_trace_label relief=ReliefStyle::None halign=Align::Start
+                                no_show_all=true visible=macro_trace.is_some()
+                                on clicked=|_| macro_trace.map(ResultsMessage::ToggleMacroTrace)
+                                    .unwrap_or(ResultsMessage::NoOp) />
+                        <Label label=macro_trace_lines.clone().unwrap_or_default()
+                               style_class="children" selectable=true can_focus=false
+                               halign=Align::Start
+                               margin_start=24 no_show_all=true visible=macro_trace_lines.is_some() />
+                    </Box>
+                    <Button label=gettext("Apply fix") valign=Align::Start
+                            no_show_all=true visible=fix.is_some()
+                            on clicked=|_| fix.map(ResultsMessage::ApplyFix).unwrap_or(ResultsMessage::NoOp) />
+                    <Button label=gettext("Explain") valign=Align::Start
+                            no_show_all=true visible=self.explain.is_some()
+                            on clicked=|_| match explain.clone() {
+                                Some(code) => ResultsMessage::Explain(code),
+                                None => ResultsMessage::NoOp,
+                            } />
+                    <MenuButton valign=Align::Start relief=ReliefStyle::None
+                            tooltip_text=more.as_str() accessible_name=more.as_str()
+                            menu_model=self.copy.map(row_menu).as_ref()
+                            no_show_all=true visible=self.copy.is_some()>
+                        // show_all doesn't reach past the button
+                        <Image property_icon_name=Some("view-more-symbolic") visible=true />
+                    </MenuButton>
+                </Box>
+            </ListBoxRow>
+        }
+    }
+}
+
+// A line for every note and help, followed by the other places the
+// diagnostic points at, if there's any of them.
+// What a screen reader announces for a diagnostic's row, e.g. "Error E0308:
+// mismatched types, at src/main.rs:12:5", rather than the title's
+// "error[E0308]".
+fn accessible_name(diag: &RustDiagnostic) -> String {
+    let severity = Severity::from(&diag.type_).name();
+    let said = match diag.num.as_ref() {
+        Some(code) => fill(gettext("{0} {1}: {2}"), &[&severity, code, &diag.message]),
+        None => fill(gettext("{0}: {1}"), &[&severity, &diag.message]),
+    };
+    located(said, diag.location().as_deref())
+}
+
+// e.g. "Warning: unused variable, at src/lib.rs:3:9"
+fn located(said: String, location: Option<&str>) -> String {
+    match location {
+        Some(location) => fill(gettext("{0}, at {1}"), &[&said, &location]),
+        None => said,
+    }
+}
+
+fn tags(diag: &RustDiagnostic) -> Option<String> {
+    if diag.tags.is_empty() {
+        None
+    } else {
+        Some(diag.tags.join(" · "))
+    }
+}
+
+fn children_markup(diag: &RustDiagnostic) -> Option<String> {
+    let mut lines = diag
+        .children
+        .iter()
+        .map(|child| {
+            let mut line = format!("<b>{}</b>: {}", child.level, ansi::escape(&child.message));
+            if let Some(span) = child.span.as_ref() {
+                line.push_str(&format!(" <i>{}</i>", ansi::escape(&span.to_string())));
+            }
+            line
+        })
+        .collect::<Vec<_>>();
+    if !diag.secondary_spans.is_empty() {
+        let spans = diag
+            .secondary_spans
+            .iter()
+            .map(|span| ansi::escape(&span.to_string()))
+            .collect::<Vec<_>>();
+        lines.push(format!("<b>see also</b>: <i>{}</i>", spans.join(", ")));
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+fn render_file_header(
+    file: Option<String>,
+    errors: usize,
+    warnings: usize,
+    lines: usize,
+    collapsed: bool,
+) -> VNode<ResultsList> {
+    let counts = fill(gettext("{0} errors, {1} warnings"), &[&errors, &warnings]);
+    let title = match file.as_deref() {
+        Some(file) if lines > 0 => fill(
+            gettext("{0} ({1} diagnostics across {2} lines: {3})"),
+            &[&file, &(errors + warnings), &lines, &counts],
+        ),
+        Some(file) => format!("{} ({})", file, counts),
+        None => format!("{} ({})", gettext("other"), counts),
+    };
+    let severity = if errors > 0 {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let icon = if collapsed {
+        "pan-end-symbolic"
+    } else {
+        "pan-down-symbolic"
+    };
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity>
+            <Button relief=ReliefStyle::None hexpand=true on clicked=|_| ResultsMessage::ToggleFile(file.clone())>
+                <Box spacing=10>
+                    <Image property_icon_name=Some(icon) />
+                    <Label label=title style_class="title" halign=Align::Start />
+                </Box>
+            </Button>
+        </ListBoxRow>
+    }
+}
+
+fn render_code_header(
+    code: Option<String>,
+    message: &str,
+    errors: usize,
+    warnings: usize,
+    expanded: bool,
+) -> VNode<ResultsList> {
+    let name = code.clone().unwrap_or_else(|| gettext("no code"));
+    let title = fill(
+        gettext("{0}: {1} ({2} errors, {3} warnings)"),
+        &[&name, &message, &errors, &warnings],
+    );
+    let severity = if errors > 0 {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let icon = if expanded {
+        "pan-down-symbolic"
+    } else {
+        "pan-end-symbolic"
+    };
+    // one diagnostic has no others to list
+    let expandable = errors + warnings > 1;
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity>
+            <Button relief=ReliefStyle::None hexpand=true sensitive=expandable
+                    on clicked=|_| ResultsMessage::ToggleCode(code.clone())>
+                <Box spacing=10>
+                    <Image property_icon_name=Some(icon) no_show_all=true visible=expandable />
+                    <Label label=title style_class="title" halign=Align::Start />
+                </Box>
+            </Button>
+        </ListBoxRow>
+    }
+}
+
+fn render_locations(locations: &[String]) -> VNode<ResultsList> {
+    gtk! {
+        <ListBoxRow activatable=false>
+            <Label label=locations.join("\n") style_class="location" selectable=true
+                   can_focus=false halign=Align::Start margin_start=48 />
+        </ListBoxRow>
+    }
+}
+
+fn render_elsewhere(errors: usize, warnings: usize, expanded: bool) -> VNode<ResultsList> {
+    let title = fill(
+        gettext("Everything else ({0} errors, {1} warnings)"),
+        &[&errors, &warnings],
+    );
+    let severity = if errors > 0 {
+        Severity::Error
+    } else if warnings > 0 {
+        Severity::Warning
+    } else {
+        Severity::Info
+    };
+    let icon = if expanded {
+        "pan-down-symbolic"
+    } else {
+        "pan-end-symbolic"
+    };
+
+    gtk! {
+        <ListBoxRow activatable=false severity=severity>
+            <Button relief=ReliefStyle::None hexpand=true on clicked=|_| ResultsMessage::ToggleElsewhere>
+                <Box spacing=10>
+                    <Image property_icon_name=Some(icon) />
+                    <Label label=title style_class="title" halign=Align::Start />
+                </Box>
+            </Button>
+        </ListBoxRow>
+    }
+}
+
+// A note on what the list is showing, with a button to do something about it.
+fn render_notice(text: String, button: String, msg: ResultsMessage) -> VNode<ResultsList> {
+    gtk! {
+        <ListBoxRow activatable=false severity=Severity::Info>
+            <Box spacing=10>
+                <Label label=text hexpand=true halign=Align::Start />
+                <Button label=button on clicked=|_| msg.clone() />
+            </Box>
+        </ListBoxRow>
+    }
+}
+
+fn render_show_more(hidden: usize) -> VNode<ResultsList> {
+    gtk! {
+        <ListBoxRow activatable=false>
+            <Box spacing=10>
+                <Label label=fill(gettext("…and {} more"), &[&listing::thousands(hidden)]) />
+                <Button label=gettext("Show more") relief=ReliefStyle::None
+                        on clicked=|_| ResultsMessage::ShowMore />
+            </Box>
+        </ListBoxRow>
+    }
+}
+
+// Menu shown next to each diagnostic. The row's id travels as the action's
+// string parameter since gio menus can only target actions by name.
+fn row_menu(id: DiagnosticId) -> Menu {
+    let menu = Menu::new();
+    menu.append(
+        Some(&gettext("Copy message")),
+        Some(&format!("win.copy-message::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("Copy file:line")),
+        Some(&format!("win.copy-location::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("Copy link to code host")),
+        Some(&format!("win.copy-link::{}", id.0)),
+    );
+    menu.append(Some(&gettext("Copy all output")), Some("win.copy-all"));
+    menu.append(
+        Some(&gettext("Open in editor")),
+        Some(&format!("win.open-diagnostic::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("Open file's problems in editor")),
+        Some(&format!("win.quickfix-file::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("Show in file manager")),
+        Some(&format!("win.reveal-diagnostic::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("File an issue…")),
+        Some(&format!("win.file-issue::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("When did this appear?…")),
+        Some(&format!("win.bisect::{}", id.0)),
+    );
+    menu.append(
+        Some(&gettext("Open terminal here")),
+        Some("win.terminal-here"),
+    );
+    menu
+}
@@ -0,0 +1,111 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lock;
+
+const APP_DIR: &str = "watch-rust-errors";
+const SESSIONS_DIR: &str = "sessions";
+
+// What a window that's watching is doing, written down as it changes so that
+// after a crash, or being killed for an update, the app can offer to go on
+// where it was. Stopping the watch or closing the window takes it away again,
+// so only sessions that were cut short are left. Kept per project root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub project_root: String,
+    pub command: String,
+    // what the results list was narrowed down to
+    #[serde(default)]
+    pub module_filter: Option<String>,
+    #[serde(default)]
+    pub code_filter: Option<String>,
+    // the instance that was watching
+    pub pid: u32,
+}
+
+impl SavedSession {
+    pub fn new(project_root: &str, command: &str) -> Self {
+        SavedSession {
+            project_root: project_root.to_string(),
+            command: command.to_string(),
+            pid: process::id(),
+            ..SavedSession::default()
+        }
+    }
+
+    // e.g. ~/.local/share/watch-rust-errors/sessions
+    pub fn dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join(APP_DIR).join(SESSIONS_DIR))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(sessions_dir()?)
+    }
+
+    pub fn save_to<D: AsRef<Path>>(&self, dir: D) -> Result<(), String> {
+        fs::create_dir_all(dir.as_ref()).map_err(|e| format!("{:?}", e))?;
+        let contents = serde_json::to_string(self).map_err(|e| format!("{:?}", e))?;
+
+        // write then rename so a crash while saving leaves the one before
+        let path = path(dir.as_ref(), &self.project_root);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, contents).map_err(|e| format!("{:?}", e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| format!("{:?}", e))
+    }
+
+    // Forgets the session on the project root, once it's over.
+    pub fn remove(project_root: &str) -> Result<(), String> {
+        Self::remove_from(sessions_dir()?, project_root)
+    }
+
+    pub fn remove_from<D: AsRef<Path>>(dir: D, project_root: &str) -> Result<(), String> {
+        match fs::remove_file(path(dir.as_ref(), project_root)) {
+            Err(ref e) if e.kind() != ErrorKind::NotFound => Err(format!("{:?}", e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The sessions of instances that ended without stopping their watches, by
+// project root. Those of instances still running are theirs to keep.
+pub fn interrupted() -> Vec<SavedSession> {
+    SavedSession::dir().map(interrupted_in).unwrap_or_default()
+}
+
+pub fn interrupted_in<D: AsRef<Path>>(dir: D) -> Vec<SavedSession> {
+    let entries = match fs::read_dir(dir.as_ref()) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut sessions = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("json")))
+        .filter_map(|path| {
+            let contents = fs::read_to_string(&path).ok()?;
+            match serde_json::from_str::<SavedSession>(&contents) {
+                Ok(session) => Some(session),
+                Err(err) => {
+                    eprintln!("Ignoring invalid session {}: {}", path.display(), err);
+                    None
+                }
+            }
+        })
+        .filter(|session| session.pid != process::id() && !lock::is_running(session.pid))
+        .collect::<Vec<_>>();
+    sessions.sort_by(|a, b| a.project_root.cmp(&b.project_root));
+    sessions
+}
+
+fn sessions_dir() -> Result<PathBuf, String> {
+    SavedSession::dir().ok_or_else(|| "No data directory available.".to_string())
+}
+
+fn path(dir: &Path, project_root: &str) -> PathBuf {
+    dir.join(format!("{}.json", lock::key(Path::new(project_root))))
+}
@@ -1,15 +1,56 @@
 use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::ansi;
 
 lazy_static! {
     static ref REGEX_ERR: Regex = Regex::new(r"(error|warning)(\[(E[0-9]+)\])?: (.*)").unwrap();
     static ref REGEX_CONTEXT: Regex = Regex::new(r" +--> ([^:]+):([0-9]+):([0-9]+)").unwrap();
+    static ref REGEX_ERR_NUM: Regex = Regex::new(r"^E[0-9]+$").unwrap();
+    // a note or help, either on its own with a location of its own after it,
+    // or as "= note: ..." at the end of the diagnostic
+    static ref REGEX_CHILD: Regex = Regex::new(r"^( *= )?(note|help): (.*)$").unwrap();
+    // another file the diagnostic points at, e.g. "  ::: src/lib.rs:3:8"
+    static ref REGEX_SECONDARY: Regex =
+        Regex::new(r"^ +::: ([^:]+):([0-9]+):([0-9]+)").unwrap();
+    // the note naming the lint behind a warning, e.g. "`#[warn(dead_code)]` on
+    // by default" or "`-D unused-variables` implied by `-D warnings`"
+    static ref REGEX_LINT: Regex = Regex::new(
+        r"`#\[(?:warn|deny|forbid)\(([A-Za-z0-9_:]+)\)\]` on by default|`-[WDF] ([A-Za-z0-9_:-]+)` implied by"
+    )
+    .unwrap();
+    // errors from the shell running the command rather than from the command,
+    // e.g. "sh: 1: eval: carg: not found" (dash) or "sh: line 2: carg:
+    // command not found" (bash), or the same from us when we start it
+    static ref REGEX_SHELL_ERR: Regex = Regex::new(
+        r"^(?:sh|watch-rust-errors): (?:line )?(?:[0-9]+: )?(?:eval: )?(.+?): (command not found|not found|Permission denied|No such file or directory)$"
+    )
+    .unwrap();
+    // the line number in the gutter of a line of source
+    static ref REGEX_GUTTER: Regex = Regex::new(r"^ *([0-9]+) \|").unwrap();
+    // the labels of a macro backtrace, numbered from the outermost macro when
+    // it's printed in full with `-Z macro-backtrace`
+    static ref REGEX_INVOCATION: Regex =
+        Regex::new(r"[-^]+ in this macro invocation(?: \(#([0-9]+)\))?").unwrap();
+    static ref REGEX_EXPANSION: Regex =
+        Regex::new(r"in this expansion of `([^`]+)`(?: \(#([0-9]+)\))?").unwrap();
+    // otherwise, a note names the macros from the innermost out, e.g. "this
+    // error originates in the macro `inner` which comes from the expansion of
+    // the macro `outer`"
+    static ref REGEX_ORIGINATES: Regex =
+        Regex::new(r"^this (?:error|warning) originates in the (?:derive |attribute )?macro").unwrap();
+    static ref REGEX_MACRO_NAME: Regex =
+        Regex::new(r"(derive macro|attribute macro|macro) `([^`]+)`").unwrap();
+    static ref REGEX_CMD_ERR: Regex =
+        Regex::new(r"^'(.+)' is not recognized as an internal or external command").unwrap();
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Type {
     Error,
     Warning,
@@ -36,7 +77,93 @@ impl FromStr for Type {
     }
 }
 
-#[derive(Clone, Debug)]
+fn not_found_hint(program: &str) -> String {
+    format!(
+        "check that `{}` is installed and on the PATH watch-rust-errors was started with",
+        program
+    )
+}
+
+// A place in the source, e.g. one of the others a diagnostic points at
+// besides its own location.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Note,
+    Help,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Note => write!(f, "note"),
+            Level::Help => write!(f, "help"),
+        }
+    }
+}
+
+// A note or help the compiler adds to a diagnostic, which is often where the
+// fix is spelled out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubDiagnostic {
+    pub level: Level,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Display for SubDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.level, self.message)?;
+        if let Some(span) = self.span.as_ref() {
+            write!(f, " ({})", span)?;
+        }
+        Ok(())
+    }
+}
+
+// A macro the diagnostic's code came out of, and where it was used.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroFrame {
+    // as the compiler spells it, e.g. "vec!" or "#[derive(Debug)]"
+    pub name: Option<String>,
+    pub invocation: Option<Span>,
+}
+
+impl Display for MacroFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.as_ref() {
+            Some(name) => write!(f, "`{}`", name)?,
+            None => write!(f, "a macro")?,
+        }
+        if let Some(span) = self.invocation.as_ref() {
+            write!(f, " invoked at {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub file: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RustDiagnostic {
     pub type_: Type,
     pub num: Option<String>,
@@ -44,11 +171,41 @@ pub struct RustDiagnostic {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub column: Option<u32>,
+    // where the offending span ends, which only JSON output says
+    #[serde(default)]
+    pub end_line: Option<u32>,
+    #[serde(default)]
+    pub end_column: Option<u32>,
     pub details: Option<String>,
+    pub lint: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+    // the crate that was being compiled when the diagnostic was reported
+    pub krate: Option<String>,
+    // `details` with the compiler's colors, when the output had any
+    #[serde(default)]
+    pub styled_details: Option<String>,
+    // how many identical diagnostics were folded into this one
+    #[serde(default)]
+    pub repeats: u32,
+    // where among the build's diagnostics the compiler reported this one
+    #[serde(default)]
+    pub order: usize,
+    #[serde(default)]
+    pub children: Vec<SubDiagnostic>,
+    // the other places the diagnostic points at
+    #[serde(default)]
+    pub secondary_spans: Vec<Span>,
+    // the macros the diagnostic's code was expanded from, the outermost one,
+    // invoked in the code being built, first
+    #[serde(default)]
+    pub macro_backtrace: Vec<MacroFrame>,
+    // what a post-processor labelled the diagnostic with, e.g. "owner:net"
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl RustDiagnostic {
-    fn new(
+    pub fn new(
         type_: Type,
         num: Option<&str>,
         message: &str,
@@ -61,43 +218,362 @@ impl RustDiagnostic {
             type_,
             num: num.map(|s| s.to_owned()),
             message: message.to_owned(),
-            file: file.map(ToString::to_string),
+            file: file.map(tidy_path),
             line,
             column,
+            end_line: None,
+            end_column: None,
             details: details.map(ToString::to_string),
+            lint: None,
+            suggestions: vec![],
+            krate: None,
+            styled_details: None,
+            repeats: 0,
+            order: 0,
+            children: vec![],
+            secondary_spans: vec![],
+            macro_backtrace: vec![],
+            tags: vec![],
         }
     }
-}
 
-impl Display for RustDiagnostic {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}{}: {}\n",
+    // e.g. "error[E0308]: mismatched types"
+    pub fn title(&self) -> String {
+        format!(
+            "{}{}: {}",
             self.type_,
             self.num
                 .as_ref()
                 .map(|n| format!("[{}]", n))
-                .unwrap_or_else(|| "".to_string()),
+                .unwrap_or_default(),
             self.message
-        )?;
+        )
+    }
 
-        if self.file.is_some() {
-            write!(
-                f,
-                "  --> {}:{}:{}\n",
-                self.file.as_ref().unwrap(),
+    // The error code or, for lints, the lint's name, e.g. "E0308" or
+    // "dead_code".
+    pub fn code(&self) -> Option<&str> {
+        self.num.as_deref().or(self.lint.as_deref())
+    }
+
+    // e.g. "src/main.rs:3:18", with "-" standing in for unknown positions
+    pub fn location(&self) -> Option<String> {
+        self.file.as_ref().map(|file| {
+            format!(
+                "{}:{}:{}",
+                file,
                 self.line
                     .map(|l| l.to_string())
                     .unwrap_or_else(|| "-".to_string()),
                 self.column
                     .map(|l| l.to_string())
                     .unwrap_or_else(|| "-".to_string())
-            )?;
+            )
+        })
+    }
+
+    // Whether `other`, e.g. from another build, reports the same problem,
+    // wherever in the file it has moved to since.
+    pub fn is_same(&self, other: &RustDiagnostic) -> bool {
+        self.type_ == other.type_
+            && self.num == other.num
+            && self.lint == other.lint
+            && self.message == other.message
+            && self.file == other.file
+    }
+
+    // The lines the primary span covers, when it's known where it is.
+    pub fn lines(&self) -> Option<RangeInclusive<u32>> {
+        let start = self.line?;
+        Some(start..=self.end_line.unwrap_or(start).max(start))
+    }
+
+    pub fn is_fixable(&self) -> bool {
+        !self.suggestions.is_empty()
+    }
+
+    // Turns the shell's own complaint about the command line, e.g. a missing
+    // program, into an error with a hint on what to check.
+    pub fn from_shell_error(inp: &str) -> Option<Self> {
+        let (message, hint) = if let Some(caps) = REGEX_SHELL_ERR.captures(inp) {
+            let program = &caps[1];
+            match &caps[2] {
+                "Permission denied" => (
+                    format!("permission denied running `{}`", program),
+                    format!(
+                        "make sure `{}` is executable, e.g. with `chmod +x`",
+                        program
+                    ),
+                ),
+                "No such file or directory" => (
+                    format!("no such file or directory: `{}`", program),
+                    "relative paths in the command are resolved from the project root".to_string(),
+                ),
+                _ => (
+                    format!("command not found: `{}`", program),
+                    not_found_hint(program),
+                ),
+            }
+        } else if let Some(caps) = REGEX_CMD_ERR.captures(inp) {
+            (
+                format!("command not found: `{}`", &caps[1]),
+                not_found_hint(&caps[1]),
+            )
+        } else {
+            return None;
+        };
+
+        Some(RustDiagnostic::new(
+            Type::Error,
+            None,
+            &message,
+            None,
+            None,
+            None,
+            Some(&format!("{}\nhint: {}", inp, hint)),
+        ))
+    }
+
+    // Parses a single line of `--message-format=json` output. Lines that are
+    // not compiler diagnostics (artifacts, build script output, notes etc.)
+    // yield `None`.
+    pub fn from_json(inp: &str) -> Result<Option<Self>, String> {
+        let msg: JsonMessage = serde_json::from_str(inp).map_err(|e| format!("{:?}", e))?;
+        let diag = match msg.message.as_ref() {
+            Some(diag) if msg.reason == "compiler-message" => diag,
+            _ => return Ok(None),
+        };
+        if diag.level != "error" && diag.level != "warning" {
+            return Ok(None);
+        }
+        let rendered = match diag.rendered {
+            Some(ref rendered) => format!("{}\n", rendered.trim_end()),
+            None => return Ok(None),
+        };
+
+        // the rendered text says the most, and when it's in a shape that
+        // isn't known, the JSON says enough to list the diagnostic
+        let mut result: RustDiagnostic = match rendered.parse() {
+            Ok(result) => result,
+            Err(_) => {
+                let span = diag.spans.iter().find(|span| span.is_primary);
+                RustDiagnostic::new(
+                    diag.level.parse()?,
+                    None,
+                    &diag.message,
+                    span.map(|span| span.file_name.as_str()),
+                    span.map(|span| span.line_start),
+                    span.map(|span| span.column_start),
+                    Some(&rendered),
+                )
+            }
+        };
+        if let Some(code) = diag.code.as_ref().map(|c| c.code.clone()) {
+            if REGEX_ERR_NUM.is_match(&code) {
+                result.num = Some(code);
+            } else {
+                result.lint = Some(code);
+            }
+        }
+        diag.collect_suggestions(&mut result.suggestions);
+        if let Some(span) = diag.spans.iter().find(|span| span.is_primary) {
+            result.end_line = Some(span.line_end);
+            result.end_column = Some(span.column_end);
+            // which says where every macro was invoked, the rendered text
+            // only where the outermost was
+            let frames = span.macro_backtrace();
+            if !frames.is_empty() {
+                result.macro_backtrace = frames;
+            }
         }
+        // the rendered text leaves out where the other spans in the same file
+        // are, and what it says about the children is only the gist
+        result.secondary_spans = diag
+            .spans
+            .iter()
+            .filter(|span| !span.is_primary)
+            .map(JsonSpan::span)
+            .collect();
+        result.children = diag
+            .children
+            .iter()
+            .filter_map(JsonDiagnostic::sub_diagnostic)
+            .collect();
+        result.krate = msg.package_id.as_deref().map(package_name);
 
-        if self.details.is_some() {
-            write!(f, "{}\n", self.details.as_ref().unwrap())?;
+        Ok(Some(result))
+    }
+}
+
+// How many lines of a file `diags` point at between them, each line counted
+// once however many spans cover it.
+pub fn covered_lines<'a, I>(diags: I) -> usize
+where
+    I: IntoIterator<Item = &'a RustDiagnostic>,
+{
+    let mut spans: Vec<_> = diags
+        .into_iter()
+        .filter_map(RustDiagnostic::lines)
+        .collect();
+    spans.sort_by_key(|span| *span.start());
+
+    let mut covered = 0;
+    let mut next = 0;
+    for span in spans {
+        let start = (*span.start()).max(next);
+        if start <= *span.end() {
+            covered += (span.end() - start) as usize + 1;
+            next = span.end() + 1;
+        }
+    }
+    covered
+}
+
+// Extracts the package name from a cargo package id, which is either
+// "foo 0.1.0 (path+file:///src/foo)" or, in newer cargo versions,
+// "path+file:///src/foo#0.1.0" / "registry+https://...#foo@0.1.0".
+fn package_name(package_id: &str) -> String {
+    if !package_id.contains("://") || package_id.contains(' ') {
+        return package_id
+            .split(' ')
+            .next()
+            .unwrap_or(package_id)
+            .to_string();
+    }
+
+    let (source, fragment) = match package_id.rfind('#') {
+        Some(index) => (&package_id[..index], &package_id[index + 1..]),
+        None => (package_id, ""),
+    };
+    match fragment.find('@') {
+        Some(index) => fragment[..index].to_string(),
+        None => source
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or(source)
+            .to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonMessage {
+    reason: String,
+    package_id: Option<String>,
+    message: Option<JsonDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct JsonDiagnostic {
+    level: String,
+    message: String,
+    rendered: Option<String>,
+    code: Option<JsonCode>,
+    spans: Vec<JsonSpan>,
+    children: Vec<JsonDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct JsonCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct JsonSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: u32,
+    column_start: u32,
+    line_end: u32,
+    column_end: u32,
+    is_primary: bool,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+    expansion: Option<Box<JsonExpansion>>,
+}
+
+#[derive(Deserialize)]
+struct JsonExpansion {
+    span: JsonSpan,
+    macro_decl_name: String,
+}
+
+impl JsonSpan {
+    fn span(&self) -> Span {
+        Span {
+            file: self.file_name.clone(),
+            line: self.line_start,
+            column: self.column_start,
+        }
+    }
+
+    // the macros the span was expanded from, outermost first
+    fn macro_backtrace(&self) -> Vec<MacroFrame> {
+        let mut frames = vec![];
+        let mut expansion = self.expansion.as_ref();
+        while let Some(next) = expansion {
+            frames.push(MacroFrame {
+                name: Some(next.macro_decl_name.clone()),
+                invocation: Some(next.span.span()),
+            });
+            expansion = next.span.expansion.as_ref();
+        }
+        frames.reverse();
+        frames
+    }
+}
+
+impl JsonDiagnostic {
+    fn sub_diagnostic(&self) -> Option<SubDiagnostic> {
+        let level = match self.level.as_str() {
+            "note" => Level::Note,
+            "help" => Level::Help,
+            _ => return None,
+        };
+        let span = self
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .or_else(|| self.spans.first());
+        Some(SubDiagnostic {
+            level,
+            message: self.message.clone(),
+            span: span.map(JsonSpan::span),
+        })
+    }
+
+    fn collect_suggestions(&self, suggestions: &mut Vec<Suggestion>) {
+        for span in self.spans.iter() {
+            if span.suggestion_applicability.as_deref() != Some("MachineApplicable") {
+                continue;
+            }
+            if let Some(replacement) = span.suggested_replacement.as_ref() {
+                suggestions.push(Suggestion {
+                    file: span.file_name.clone(),
+                    byte_start: span.byte_start,
+                    byte_end: span.byte_end,
+                    replacement: replacement.clone(),
+                });
+            }
+        }
+
+        for child in self.children.iter() {
+            child.collect_suggestions(suggestions);
+        }
+    }
+}
+
+impl Display for RustDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title())?;
+
+        if let Some(location) = self.location() {
+            writeln!(f, "  --> {}", location)?;
+        }
+
+        if let Some(details) = self.details.as_ref() {
+            writeln!(f, "{}", details)?;
         }
 
         Ok(())
@@ -107,7 +583,8 @@ impl Display for RustDiagnostic {
 impl FromStr for RustDiagnostic {
     type Err = String;
 
-    fn from_str(inp: &str) -> Result<Self, Self::Err> {
+    fn from_str(styled: &str) -> Result<Self, Self::Err> {
+        let inp = &*ansi::strip(styled);
         let err_handler = || format!("Invalid input: {}", inp);
 
         // split input into 3 lines delimited by \n
@@ -120,32 +597,219 @@ impl FromStr for RustDiagnostic {
         let err_num = err.get(3);
         let msg = err.get(4).ok_or_else(err_handler)?;
 
-        // extract file, line and col
-        let (file, line, col) = if lines.len() > 1 && !lines[1].is_empty() {
-            let context = REGEX_CONTEXT.captures(lines[1]).ok_or_else(err_handler)?;
-            let file = context.get(1);
-            let line = context.get(2);
-            let col = context.get(3);
-
-            (file, line, col)
-        } else {
-            (None, None, None)
+        // extract file, line and col, when the second line says where; some
+        // blocks, e.g. a linker's error or cargo's warnings about the
+        // manifest, have none and go straight on to what they say
+        let context = lines.get(1).and_then(|line| REGEX_CONTEXT.captures(line));
+        let (file, line, col) = match context.as_ref() {
+            Some(context) => (context.get(1), context.get(2), context.get(3)),
+            None => (None, None, None),
         };
 
-        let details = if lines.len() > 2 && !lines[2].is_empty() {
-            Some(lines[2])
-        } else {
-            None
-        };
+        // everything after the location, or after the title without one
+        let skip = if context.is_some() { 2 } else { 1 };
+        let details = inp
+            .splitn(skip + 1, '\n')
+            .nth(skip)
+            .filter(|d| !d.is_empty());
 
-        Ok(RustDiagnostic::new(
+        let mut diag = RustDiagnostic::new(
             err_or_warn.as_str().parse()?,
             err_num.map(|e| e.as_str()),
             msg.as_str(),
             file.map(|m| m.as_str()),
-            line.map(|m| m.as_str().parse().expect("Line number was not a number!")),
-            col.map(|m| m.as_str().parse().expect("Column number was not a number!")),
+            line.and_then(|m| m.as_str().parse().ok()),
+            col.and_then(|m| m.as_str().parse().ok()),
             details,
-        ))
+        );
+        diag.lint = details.and_then(|details| {
+            let caps = REGEX_LINT.captures(details)?;
+            let lint = caps.get(1).or_else(|| caps.get(2))?;
+            Some(lint.as_str().replace('-', "_"))
+        });
+        if diag.lint.is_none() {
+            diag.lint = details.and_then(level_note_lint);
+        }
+        if let Some(details) = details {
+            let (mut children, secondary_spans) = parse_children(details);
+            diag.macro_backtrace = parse_macro_backtrace(diag.file.as_deref(), details, &children);
+            // the trace says as much as the note naming its macros
+            if !diag.macro_backtrace.is_empty() {
+                children.retain(|child| !REGEX_ORIGINATES.is_match(&child.message));
+            }
+            diag.children = children;
+            diag.secondary_spans = secondary_spans;
+        }
+        if details.is_some() && inp != styled {
+            diag.styled_details = styled
+                .splitn(skip + 1, '\n')
+                .nth(skip)
+                .map(ToString::to_string);
+        }
+
+        Ok(diag)
+    }
+}
+
+// The lint underlined in the attribute a "the lint level is defined here"
+// note shows, e.g. `missing_docs` in `#![warn(missing_docs)]`, which is all
+// that names lints that are off by default, like most of rustdoc's.
+fn level_note_lint(details: &str) -> Option<String> {
+    let (_, note) = details.split_once("the lint level is defined here")?;
+    let mut lines = note.lines().skip_while(|line| !REGEX_GUTTER.is_match(line));
+    // the gutters line up, so the carets are under the lint
+    let source = lines.next()?;
+    let carets = lines.next()?;
+    let start = carets.find('^')?;
+    let end = start + carets[start..].chars().take_while(|c| *c == '^').count();
+    let lint = source.get(start..end)?;
+    if lint
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    {
+        Some(lint.to_string())
+    } else {
+        None
+    }
+}
+
+// `file` without the detours rustdoc reports files it includes with, e.g.
+// "README.md" for "src/../README.md" from `#![doc = include_str!("../README.md")]`.
+fn tidy_path(file: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    for part in file.split('/') {
+        match parts.last() {
+            Some(last) if part == ".." && *last != ".." && !last.is_empty() => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// The notes and helps in a diagnostic's text, and the other places it points
+// at. A note or help on a line of its own is followed by where it's about,
+// while one after "=" may go on over the lines after it.
+fn parse_children(details: &str) -> (Vec<SubDiagnostic>, Vec<Span>) {
+    let mut children: Vec<SubDiagnostic> = vec![];
+    let mut spans = vec![];
+    let mut awaiting_span = false;
+    let mut continues = false;
+    for line in details.lines() {
+        if let Some(caps) = REGEX_CHILD.captures(line) {
+            children.push(SubDiagnostic {
+                level: if &caps[2] == "help" {
+                    Level::Help
+                } else {
+                    Level::Note
+                },
+                message: caps[3].trim().to_string(),
+                span: None,
+            });
+            continues = caps.get(1).is_some();
+            awaiting_span = !continues;
+            continue;
+        }
+
+        let span = REGEX_CONTEXT
+            .captures(line)
+            .or_else(|| REGEX_SECONDARY.captures(line))
+            .and_then(|caps| {
+                Some(Span {
+                    file: caps[1].to_string(),
+                    line: caps[2].parse().ok()?,
+                    column: caps[3].parse().ok()?,
+                })
+            });
+        match (span, children.last_mut()) {
+            (Some(span), Some(child)) if awaiting_span => child.span = Some(span),
+            (Some(span), _) => spans.push(span),
+            (None, Some(child)) if continues && !line.trim().is_empty() && !line.contains('|') => {
+                child.message.push('\n');
+                child.message.push_str(line.trim());
+                continue;
+            }
+            _ => {}
+        }
+        awaiting_span = false;
+        continues = false;
+    }
+    (children, spans)
+}
+
+// Reads the macro backtrace out of the labels the compiler puts on the
+// source it shows, and the note naming the macros. Without `-Z
+// macro-backtrace`, only the outermost macro's invocation is shown.
+fn parse_macro_backtrace(
+    file: Option<&str>,
+    details: &str,
+    children: &[SubDiagnostic],
+) -> Vec<MacroFrame> {
+    let mut frames = vec![];
+
+    // a multiline span's box shifts every line of source it's shown with
+    // over by two columns
+    let boxed = details
+        .lines()
+        .any(|line| matches!(line.find('|'), Some(bar) if line[bar + 1..].starts_with(" /")));
+    let mut file = file.map(ToString::to_string);
+    let mut line_number = None;
+    for line in details.lines() {
+        if let Some(caps) = REGEX_SECONDARY.captures(line) {
+            file = Some(caps[1].to_string());
+        } else if let Some(caps) = REGEX_GUTTER.captures(line) {
+            line_number = caps[1].parse::<u32>().ok();
+        }
+
+        if let Some(caps) = REGEX_INVOCATION.captures(line) {
+            let index = caps
+                .get(1)
+                .and_then(|m| m.as_str().parse().ok())
+                .unwrap_or(1);
+            let marker = caps.get(0).map_or(0, |m| m.start());
+            let source_start = line.find('|').map_or(0, |bar| bar + 2) + if boxed { 2 } else { 0 };
+            frame_at(&mut frames, index).invocation =
+                file.clone().zip(line_number).map(|(file, line)| Span {
+                    file,
+                    line,
+                    column: (marker.saturating_sub(source_start) + 1) as u32,
+                });
+        } else if let Some(caps) = REGEX_EXPANSION.captures(line) {
+            let index = caps
+                .get(2)
+                .and_then(|m| m.as_str().parse().ok())
+                .unwrap_or(1);
+            frame_at(&mut frames, index).name = Some(caps[1].to_string());
+        }
+    }
+
+    let note = children
+        .iter()
+        .find(|child| REGEX_ORIGINATES.is_match(&child.message));
+    if let Some(note) = note {
+        let names = REGEX_MACRO_NAME
+            .captures_iter(&note.message)
+            .map(|caps| match &caps[1] {
+                "derive macro" => format!("#[derive({})]", &caps[2]),
+                "attribute macro" => format!("#[{}]", &caps[2]),
+                _ => format!("{}!", &caps[2]),
+            })
+            .collect::<Vec<_>>();
+        for (index, name) in names.into_iter().rev().enumerate() {
+            let frame = frame_at(&mut frames, index + 1);
+            if frame.name.is_none() {
+                frame.name = Some(name);
+            }
+        }
+    }
+    frames
+}
+
+// the frame for the macro numbered `index`, from 1 for the outermost
+fn frame_at(frames: &mut Vec<MacroFrame>, index: usize) -> &mut MacroFrame {
+    if frames.len() < index {
+        frames.resize(index, MacroFrame::default());
     }
+    &mut frames[index - 1]
 }
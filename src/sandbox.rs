@@ -0,0 +1,152 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use crate::process::ProcessRunner;
+
+// where Flatpak describes the sandbox an app runs in
+pub const FLATPAK_INFO: &str = "/.flatpak-info";
+
+// the app's ID when it isn't given one by the sandbox
+const APP_ID: &str = "in.nerdworks.WatchRustErrors";
+
+// the document portal on the session bus, which shows a sandboxed app the
+// files and folders it was handed, e.g. at
+// /run/user/1000/doc/a1b2c3d4/watch-rust-errors
+const DOCUMENTS: &str = "org.freedesktop.portal.Documents";
+const DOCUMENTS_PATH: &str = "/org/freedesktop/portal/documents";
+
+// Whether the app runs in a Flatpak sandbox, where it sees the user's files
+// only as far as its permissions go, and folders picked in the file chooser
+// through the document portal.
+pub fn in_flatpak() -> bool {
+    Path::new(FLATPAK_INFO).is_file()
+}
+
+// What the sandbox knows the app as, for `flatpak override`.
+pub fn app_id() -> String {
+    env::var("FLATPAK_ID").unwrap_or_else(|_| APP_ID.to_string())
+}
+
+// The document's ID and the path inside it of a file or folder the document
+// portal shows the app, e.g. ("a1b2c3d4", "watch-rust-errors/src") for
+// /run/user/1000/doc/a1b2c3d4/watch-rust-errors/src. Other paths have none.
+pub fn document(path: &Path) -> Option<(String, PathBuf)> {
+    let parts = path
+        .components()
+        .filter_map(|part| match part {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let doc = match parts.as_slice() {
+        ["run", "user", uid, "doc", ..] if uid.chars().all(|c| c.is_ascii_digit()) => 4,
+        ["run", "flatpak", "doc", ..] => 3,
+        _ => return None,
+    };
+    let id = parts.get(doc)?;
+    Some((id.to_string(), parts[doc + 1..].iter().collect()))
+}
+
+pub fn is_document(path: &Path) -> bool {
+    document(path).is_some()
+}
+
+// Where a file or folder the document portal shows the app really is, asked
+// of the portal. Paths that aren't the portal's are where they are.
+pub fn host_path(runner: &dyn ProcessRunner, path: &Path) -> Result<PathBuf, String> {
+    let (id, inside) = match document(path) {
+        Some(document) => document,
+        None => return Ok(path.to_path_buf()),
+    };
+    let ids = format!("['{}']", id);
+    let args = [
+        "call",
+        "--session",
+        "--dest",
+        DOCUMENTS,
+        "--object-path",
+        DOCUMENTS_PATH,
+        "--method",
+        "org.freedesktop.portal.Documents.GetHostPaths",
+        &ids,
+    ];
+    let output = runner.run("gdbus", &args, &[], Path::new("."))?;
+    if !output.success {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let reply = String::from_utf8_lossy(&output.stdout);
+    match parse_host_paths(&reply, &id) {
+        // the document is the folder itself, or a file the path is under
+        Some(host) => Ok(host.join(inside.iter().skip(1).collect::<PathBuf>())),
+        None => Err(format!(
+            "The document portal doesn't know {}",
+            path.display()
+        )),
+    }
+}
+
+// The project root to watch for a folder the user picked: where it is on the
+// host, if the sandbox lets the app see it there, so that changes made by
+// other apps are noticed. Otherwise it's the portal's path.
+pub fn project_root(runner: &dyn ProcessRunner, path: &Path) -> PathBuf {
+    if !is_document(path) {
+        return path.to_path_buf();
+    }
+    match host_path(runner, path) {
+        Ok(host) if host.is_dir() => host,
+        Ok(_) => path.to_path_buf(),
+        Err(err) => {
+            eprintln!("Failed to find {} on the host: {}", path.display(), err);
+            path.to_path_buf()
+        }
+    }
+}
+
+// Reads GetHostPaths' reply for the document `id`, as gdbus prints it, e.g.
+// "({'a1b2c3d4': b'/home/me/watch-rust-errors'},)".
+pub fn parse_host_paths(reply: &str, id: &str) -> Option<PathBuf> {
+    let key = format!("'{}': b'", id);
+    let start = reply.find(&key)? + key.len();
+    let mut path = String::new();
+    let mut chars = reply[start..].chars();
+    loop {
+        match chars.next()? {
+            '\'' => break,
+            '\\' => path.push(chars.next()?),
+            c => path.push(c),
+        }
+    }
+    Some(PathBuf::from(path))
+}
+
+// The file systems the sandbox in `info` lets the app see, e.g. "home" or
+// "xdg-documents:ro", as Flatpak's `filesystems` permission lists them.
+pub fn filesystems(info: &str) -> Vec<String> {
+    let mut context = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            context = line == "[Context]";
+            continue;
+        }
+        match line.strip_prefix("filesystems=") {
+            Some(list) if context => {
+                return list
+                    .split(';')
+                    .filter(|fs| !fs.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    vec![]
+}
+
+// Whether the sandbox described in `info` sees the user's home folder, where
+// projects usually are, as it is.
+pub fn sees_home(info: &str) -> bool {
+    filesystems(info).iter().any(|fs| {
+        let name = fs.split(':').next().unwrap_or_default();
+        name == "home" || name == "host" || name == "~"
+    })
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cargo;
+use crate::config::ScheduledJob;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::watcher::{BuildEvent, EventSender};
+
+// how often the scheduler thread checks for due jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Job {
+    config: ScheduledJob,
+    next_run: Instant,
+}
+
+struct State {
+    project_root: PathBuf,
+    process: Arc<dyn ProcessRunner>,
+    env: Vec<(String, String)>,
+    jobs: Vec<Job>,
+    quit: bool,
+    tx: EventSender,
+}
+
+// Runs heavier jobs, e.g. a full clippy, on a timer instead of on file
+// changes. Each job first runs one interval after the scheduler is created.
+#[derive(Clone)]
+pub struct Scheduler {
+    state: Arc<RwLock<State>>,
+}
+
+impl Scheduler {
+    pub fn new<P: AsRef<Path>, S: Into<EventSender>>(
+        project_root: P,
+        jobs: &[ScheduledJob],
+        tx: S,
+    ) -> Self {
+        Self::with_runner(
+            project_root,
+            jobs,
+            Arc::new(SystemRunner),
+            tx,
+            Instant::now(),
+        )
+    }
+
+    pub fn with_runner<P: AsRef<Path>, S: Into<EventSender>>(
+        project_root: P,
+        jobs: &[ScheduledJob],
+        process: Arc<dyn ProcessRunner>,
+        tx: S,
+        now: Instant,
+    ) -> Self {
+        let jobs = jobs
+            .iter()
+            .map(|config| Job {
+                config: config.clone(),
+                next_run: now + config.interval(),
+            })
+            .collect();
+
+        Scheduler {
+            state: Arc::new(RwLock::new(State {
+                project_root: project_root.as_ref().to_path_buf(),
+                process,
+                env: vec![],
+                jobs,
+                quit: false,
+                tx: tx.into(),
+            })),
+        }
+    }
+
+    pub fn start(&self) -> Result<(), String> {
+        let this = self.clone();
+        thread::Builder::new()
+            .name("scheduler".to_string())
+            .spawn(move || {
+                while !this.state.read().unwrap().quit {
+                    if let Err(err) = this.run_due(Instant::now()) {
+                        let tx = this.state.read().unwrap().tx.clone();
+                        let _ = tx.send(BuildEvent::Warning(format!(
+                            "Scheduled job failed: {}",
+                            err
+                        )));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+
+    // Asks the scheduler thread to exit, at the latest once the job that is
+    // currently running finishes.
+    pub fn stop(&self) {
+        self.state.write().unwrap().quit = true;
+    }
+
+    pub fn set_env(&self, env: Vec<(String, String)>) {
+        self.state.write().unwrap().env = env;
+    }
+
+    // Runs every job that is due at `now` and returns how many ran.
+    pub fn run_due(&self, now: Instant) -> Result<usize, String> {
+        let due = {
+            let mut state = self.state.write().unwrap();
+            let mut due = vec![];
+            for job in state.jobs.iter_mut().filter(|job| job.next_run <= now) {
+                job.next_run = now + job.config.interval();
+                due.push(job.config.clone());
+            }
+            due
+        };
+
+        for job in due.iter() {
+            // don't hold the lock while compiling so that `stop` never blocks
+            let (process, project_root, env, tx) = {
+                let state = self.state.read().unwrap();
+                if state.quit {
+                    break;
+                }
+                (
+                    state.process.clone(),
+                    state.project_root.clone(),
+                    state.env.clone(),
+                    state.tx.clone(),
+                )
+            };
+
+            let result = cargo::run_with(process.as_ref(), &project_root, &job.command, &env)?;
+            tx.send(BuildEvent::Scheduled(job.name.clone(), result))?;
+        }
+
+        Ok(due.len())
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha1_smol::Sha1;
+
+use crate::cargo::CompileResult;
+
+// from RFC 6455, appended to a client's key to prove the handshake was read
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// how long a client gets to take in an update before it's dropped, so that a
+// stuck browser tab can't hold up the others
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// how long a request gets to arrive, so that a client that never finishes one
+// doesn't keep its thread around
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the most a `POST /focus` may carry: a path, so a few KiB is plenty
+const MAX_FOCUS_BODY: usize = 4096;
+
+// the longest request or header line that's read, which real clients stay
+// well under
+const MAX_LINE: usize = 8192;
+
+// how many requests are served at once; the rest wait for one of these
+const WORKERS: usize = 4;
+
+// how many WebSocket clients can be connected at once, each with a thread
+// reading what it sends
+const MAX_CLIENTS: usize = 32;
+
+// the WebSocket frames sent or answered, from RFC 6455
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+// Serves the latest results as JSON to `GET /results` (or `/`), and pushes
+// every new result as a text message to WebSocket clients connected to any
+// path. Editors can `POST /focus` the path of the file they're showing.
+// Requests from web pages are only served for the origins allowed, so that
+// any page open in a browser can't read the results, and only when addressed
+// to this machine, so that a page can't get around that by pointing its own
+// name at it. Clones share the same listener and clients.
+#[derive(Clone)]
+pub struct Server {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    // the latest results as JSON
+    latest: Mutex<Option<String>>,
+    // for the thread sending updates to WebSocket clients, in the order
+    // `latest` changes and clients join
+    updates: Mutex<Sender<Update>>,
+    // how many WebSocket clients are connected
+    clients: AtomicUsize,
+    // told about every file an editor says it's showing
+    focus_listeners: Mutex<Vec<FocusListener>>,
+    // the pages that may be served, e.g. "http://localhost:3000"
+    allowed_origins: Mutex<Vec<String>>,
+}
+
+type FocusListener = Box<dyn Fn(String) + Send>;
+
+// A WebSocket client, written to both by the thread sending updates and by
+// the one reading from it, for its replies.
+type Client = Arc<Mutex<TcpStream>>;
+
+enum Update {
+    // a client to send updates to from now on, after `latest` if there is
+    // one yet
+    Join {
+        client: Client,
+        latest: Option<Vec<u8>>,
+    },
+    Publish(Vec<u8>),
+}
+
+impl Server {
+    // Listens on `address`, e.g. "127.0.0.1:7878"; port 0 picks a free one.
+    pub fn start(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{:?}", e))?;
+        let address = listener.local_addr().map_err(|e| format!("{:?}", e))?;
+        let (updates, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("server-updates".to_string())
+            .spawn(move || send_updates(rx))
+            .map_err(|e| format!("{:?}", e))?;
+        let shared = Arc::new(Shared {
+            latest: Mutex::new(None),
+            updates: Mutex::new(updates),
+            clients: AtomicUsize::new(0),
+            focus_listeners: Mutex::new(vec![]),
+            allowed_origins: Mutex::new(vec![]),
+        });
+
+        let (tx, rx) = mpsc::channel::<TcpStream>();
+        let rx = Arc::new(Mutex::new(rx));
+        for index in 0..WORKERS {
+            let rx = rx.clone();
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("server-{}", index))
+                .spawn(move || work(&rx, &shared, address))
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        thread::Builder::new()
+            .name("server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if tx.send(stream).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(Server { address, shared })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Calls `listener` with every path posted to `/focus`, on the thread
+    // serving the request.
+    pub fn on_focus<F: Fn(String) + Send + 'static>(&self, listener: F) {
+        self.shared
+            .focus_listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(listener));
+    }
+
+    // Lets pages from `origins`, e.g. "http://localhost:3000", read the
+    // results and post to `/focus`. Requests without an `Origin`, from
+    // editors and other tools rather than browsers, are always served.
+    pub fn allow_origins(&self, origins: Vec<String>) {
+        *self.shared.allowed_origins.lock().unwrap() = origins;
+    }
+
+    // Keeps `result` for requests to come and has it sent to every connected
+    // WebSocket client, dropping those that have gone away or don't take it
+    // in time. The sending is left to a thread of its own, for the caller not
+    // to wait on a slow client.
+    pub fn publish(&self, result: &CompileResult) {
+        let json = match serde_json::to_string(result) {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("Could not serialize results: {:?}", err);
+                return;
+            }
+        };
+
+        // queued while holding `latest`, so that a client joining gets this
+        // result either when it joins or as an update, not both
+        let frame = frame(OPCODE_TEXT, json.as_bytes());
+        let mut latest = self.shared.latest.lock().unwrap();
+        let _ = self
+            .shared
+            .updates
+            .lock()
+            .unwrap()
+            .send(Update::Publish(frame));
+        *latest = Some(json);
+    }
+}
+
+// Sends updates to WebSocket clients as they come, until the server is gone.
+fn send_updates(updates: Receiver<Update>) {
+    let mut clients: Vec<Client> = vec![];
+    for update in updates {
+        match update {
+            Update::Join { client, latest } => {
+                let joined = match latest {
+                    Some(frame) => client.lock().unwrap().write_all(&frame).is_ok(),
+                    None => true,
+                };
+                if joined {
+                    clients.push(client);
+                }
+            }
+            Update::Publish(frame) => {
+                clients.retain(|client| client.lock().unwrap().write_all(&frame).is_ok())
+            }
+        }
+    }
+}
+
+// Serves the requests the listener hands over, one at a time. `address` is
+// the one listened on.
+fn work(requests: &Mutex<Receiver<TcpStream>>, shared: &Arc<Shared>, address: SocketAddr) {
+    loop {
+        let stream = match requests.lock().unwrap().recv() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        if let Err(err) = serve(stream, shared, address) {
+            eprintln!("Server request failed: {}", err);
+        }
+    }
+}
+
+fn serve(mut stream: TcpStream, shared: &Arc<Shared>, address: SocketAddr) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|e| format!("{:?}", e))?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| format!("{:?}", e))?);
+    let mut request_line = String::new();
+    if read_line(&mut reader, &mut request_line)?.is_none() {
+        return respond(
+            &mut stream,
+            "414 URI Too Long",
+            "text/plain",
+            "Too long",
+            None,
+        );
+    }
+
+    // header names are case insensitive
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        match read_line(&mut reader, &mut line)? {
+            Some(0) => break,
+            Some(_) => {}
+            None => {
+                return respond(
+                    &mut stream,
+                    "431 Request Header Fields Too Large",
+                    "text/plain",
+                    "Too long",
+                    None,
+                )
+            }
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    // a page whose name was made to resolve here after it loaded would still
+    // be sent with that name, and a page from this machine is let in below
+    let local = match headers.get("host") {
+        Some(host) => is_local_host(host, address),
+        None => false,
+    };
+    if !local {
+        return respond(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain",
+            "Host not allowed",
+            None,
+        );
+    }
+
+    // browsers say which page a request is from, upgrades included
+    let origin = headers.get("origin").map(String::as_str);
+    if let Some(origin) = origin {
+        if !shared
+            .allowed_origins
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|o| o == origin)
+        {
+            return respond(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain",
+                "Origin not allowed",
+                None,
+            );
+        }
+    }
+
+    let upgrade = headers
+        .get("upgrade")
+        .map(|u| u.eq_ignore_ascii_case("websocket"));
+    match (method, headers.get("sec-websocket-key")) {
+        ("GET", Some(key)) if upgrade == Some(true) => {
+            if shared.clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+                shared.clients.fetch_sub(1, Ordering::SeqCst);
+                return respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "text/plain",
+                    "Too many clients",
+                    origin,
+                );
+            }
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\r\n",
+                accept_key(key)
+            );
+            stream
+                .write_all(response.as_bytes())
+                .map_err(|e| format!("{:?}", e))?;
+            stream
+                .set_write_timeout(Some(WRITE_TIMEOUT))
+                .map_err(|e| format!("{:?}", e))?;
+            // clients only send anything to close or check the connection,
+            // which they may not do for a long time
+            stream
+                .set_read_timeout(None)
+                .map_err(|e| format!("{:?}", e))?;
+            let client = Arc::new(Mutex::new(stream));
+
+            // a new client starts out with what's there already
+            {
+                let latest = shared.latest.lock().unwrap();
+                let _ = shared.updates.lock().unwrap().send(Update::Join {
+                    client: client.clone(),
+                    latest: latest
+                        .as_ref()
+                        .map(|latest| frame(OPCODE_TEXT, latest.as_bytes())),
+                });
+            }
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("server-client".to_string())
+                .spawn(move || {
+                    if let Err(err) = listen(reader, &client) {
+                        eprintln!("WebSocket client failed: {}", err);
+                    }
+                    let _ = client.lock().unwrap().shutdown(Shutdown::Both);
+                    shared.clients.fetch_sub(1, Ordering::SeqCst);
+                })
+                .map_err(|e| format!("{:?}", e))?;
+            Ok(())
+        }
+        ("GET", _) if path == "/" || path == "/results" => {
+            let body = shared
+                .latest
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap_or_else(|| "null".to_string());
+            respond(&mut stream, "200 OK", "application/json", &body, origin)
+        }
+        ("POST", _) if path == "/focus" => {
+            let length = headers
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+            if length > MAX_FOCUS_BODY {
+                return respond(
+                    &mut stream,
+                    "413 Payload Too Large",
+                    "text/plain",
+                    "Too large",
+                    origin,
+                );
+            }
+            let mut body = vec![0; length];
+            reader
+                .read_exact(&mut body)
+                .map_err(|e| format!("{:?}", e))?;
+            let file = String::from_utf8_lossy(&body).trim().to_string();
+            if file.is_empty() {
+                return respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain",
+                    "No file",
+                    origin,
+                );
+            }
+            for listener in shared.focus_listeners.lock().unwrap().iter() {
+                listener(file.clone());
+            }
+            respond(&mut stream, "204 No Content", "text/plain", "", origin)
+        }
+        ("GET", _) => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            "Not found",
+            origin,
+        ),
+        _ => respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed",
+            origin,
+        ),
+    }
+}
+
+// `origin` is the allowed page the request is from, if any, which may read
+// the response.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+    origin: Option<&str>,
+) -> Result<(), String> {
+    // a dashboard is likely to be served from somewhere else
+    let cors = origin
+        .map(|origin| {
+            format!(
+                "Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n",
+                origin
+            )
+        })
+        .unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         {}\
+         Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        cors,
+        body
+    );
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|e| format!("{:?}", e))
+}
+
+// Reads a line into `line` as `read_line` does, or `None` for one longer
+// than `MAX_LINE`.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<Option<usize>, String> {
+    let read = reader
+        .by_ref()
+        .take(MAX_LINE as u64 + 1)
+        .read_line(line)
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(Some(read).filter(|&read| read <= MAX_LINE))
+}
+
+// Whether `host`, a request's `Host`, names the machine listening on
+// `address`: "localhost", a loopback address or the address itself, with the
+// port listened on if there's one.
+fn is_local_host(host: &str, address: SocketAddr) -> bool {
+    let (name, port) = match host.rsplit_once(':') {
+        // past the brackets around an IPv6 address
+        Some((name, port)) if !port.contains(']') => (name, Some(port)),
+        _ => (host, None),
+    };
+    if let Some(port) = port {
+        if port.parse::<u16>() != Ok(address.port()) {
+            return false;
+        }
+    }
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    match name.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback() || ip == address.ip(),
+        Err(_) => name.eq_ignore_ascii_case("localhost"),
+    }
+}
+
+// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let digest = Sha1::from(format!("{}{}", key, WEBSOCKET_GUID)).digest();
+    BASE64.encode(digest.bytes())
+}
+
+// Reads what a WebSocket client sends until it closes the connection,
+// answering its pings and its close.
+fn listen<R: Read>(mut reader: R, client: &Mutex<TcpStream>) -> Result<(), String> {
+    while let Some((opcode, payload)) = read_frame(&mut reader)? {
+        match opcode {
+            OPCODE_PING => client
+                .lock()
+                .unwrap()
+                .write_all(&frame(OPCODE_PONG, &payload))
+                .map_err(|e| format!("{:?}", e))?,
+            // with the status code the client gave, if any
+            OPCODE_CLOSE => {
+                let status = &payload[..payload.len().min(2)];
+                return client
+                    .lock()
+                    .unwrap()
+                    .write_all(&frame(OPCODE_CLOSE, status))
+                    .map_err(|e| format!("{:?}", e));
+            }
+            // there's nothing else clients are expected to send
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// The opcode and unmasked payload of the next frame a client sends, or
+// `None` once it's gone. Frames longer than `MAX_LINE` aren't read.
+fn read_frame<R: Read>(reader: &mut R) -> Result<Option<(u8, Vec<u8>)>, String> {
+    let mut header = [0; 2];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(format!("{:?}", err)),
+    }
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader
+                .read_exact(&mut len)
+                .map_err(|e| format!("{:?}", e))?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader
+                .read_exact(&mut len)
+                .map_err(|e| format!("{:?}", e))?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if len > MAX_LINE as u64 {
+        return Err(format!("WebSocket frame of {} bytes is too large.", len));
+    }
+
+    let mut mask = [0; 4];
+    if header[1] & 0x80 != 0 {
+        reader
+            .read_exact(&mut mask)
+            .map_err(|e| format!("{:?}", e))?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader
+        .read_exact(&mut payload)
+        .map_err(|e| format!("{:?}", e))?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok(Some((header[0] & 0x0f, payload)))
+}
+
+// A single, unmasked WebSocket frame, as servers send them.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len();
+    let mut frame = vec![0x80 | opcode];
+    if len < 126 {
+        frame.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend(&(len as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend(&(len as u64).to_be_bytes());
+    }
+    frame.extend(payload);
+    frame
+}
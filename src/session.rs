@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::Stream;
+use serde_json::{json, Value};
+
+use crate::cargo::{self, SuccessPolicy};
+use crate::config::ScheduledJob;
+use crate::eventlog::EventLog;
+use crate::hooks::Hook;
+use crate::lsp::LspServer;
+use crate::parser::{Parser, RustcParser};
+use crate::postprocess::PostProcessor;
+use crate::process::{ProcessRunner, SystemRunner};
+use crate::schedule::Scheduler;
+use crate::server::Server;
+use crate::watcher::{BuildEvent, BuildQueue, Watcher};
+
+// What a session watches, and how.
+#[derive(Clone)]
+pub struct SessionConfig {
+    pub project_root: PathBuf,
+    pub command: String,
+    pub parser: Arc<dyn Parser>,
+    // variables to set for the command and the scheduled jobs
+    pub env: Vec<(String, String)>,
+    pub jobs: Vec<ScheduledJob>,
+    pub server: Option<Server>,
+    pub lsp_server: Option<LspServer>,
+    // a file to append every event to as a line of JSON
+    pub event_log: Option<PathBuf>,
+    // see `Watcher::set_build_hooks`
+    pub build_hooks: bool,
+    // see `Watcher::set_post_processor`
+    pub post_processor: Option<PostProcessor>,
+    // see `Watcher::set_post_build`
+    pub post_build: Vec<Hook>,
+    // see `Watcher::set_max_diagnostics`
+    pub max_diagnostics: usize,
+    // see `Watcher::set_build_timeout`
+    pub build_timeout: Option<Duration>,
+    // see `Watcher::set_success_policy`
+    pub success_policy: Option<SuccessPolicy>,
+    // see `Watcher::set_respect_ignores`
+    pub respect_ignores: bool,
+    // see `Watcher::set_log_runs`
+    pub log_runs: bool,
+    // see `Watcher::set_build_queue`
+    pub build_queue: Option<BuildQueue>,
+    // see `Watcher::set_held` and `Watcher::set_ac_power_only`
+    pub held: bool,
+    pub ac_power_only: bool,
+    // what runs the command and the jobs
+    pub process: Arc<dyn ProcessRunner>,
+}
+
+impl SessionConfig {
+    pub fn new<P: AsRef<Path>>(project_root: P, command: &str) -> Self {
+        SessionConfig {
+            project_root: project_root.as_ref().to_path_buf(),
+            command: command.to_string(),
+            parser: Arc::new(RustcParser),
+            env: vec![],
+            jobs: vec![],
+            server: None,
+            lsp_server: None,
+            event_log: None,
+            build_hooks: true,
+            post_processor: None,
+            post_build: vec![],
+            max_diagnostics: cargo::MAX_DIAGNOSTICS,
+            build_timeout: None,
+            success_policy: None,
+            respect_ignores: true,
+            log_runs: false,
+            build_queue: None,
+            held: false,
+            ac_power_only: false,
+            process: Arc::new(SystemRunner),
+        }
+    }
+}
+
+// A running watch, for programs that want to embed one. Its events come
+// through the `Events` stream `start` hands out, which ends once the session
+// is stopped or dropped and the compile that was running, if any, is done.
+pub struct WatchSession {
+    watcher: Watcher,
+    scheduler: Option<Scheduler>,
+    process: Arc<dyn ProcessRunner>,
+    // for schedulers started later; gone once stopped
+    tx: Option<UnboundedSender<BuildEvent>>,
+}
+
+impl WatchSession {
+    // Starts watching, compiling once right away.
+    pub fn start(config: SessionConfig) -> Result<(Self, Events), String> {
+        let log = config.event_log.as_ref().map(EventLog::open).transpose()?;
+        let (tx, rx) = mpsc::unbounded();
+        let mut watcher = Watcher::with_runner(
+            &config.project_root,
+            &config.command,
+            config.process.clone(),
+            tx.clone(),
+        )?;
+        watcher.set_env(config.env.clone());
+        watcher.set_parser(config.parser);
+        watcher.set_server(config.server);
+        watcher.set_lsp_server(config.lsp_server);
+        watcher.set_build_hooks(config.build_hooks);
+        watcher.set_post_processor(config.post_processor);
+        watcher.set_post_build(config.post_build);
+        watcher.set_max_diagnostics(config.max_diagnostics);
+        watcher.set_build_timeout(config.build_timeout);
+        watcher.set_success_policy(config.success_policy);
+        watcher.set_respect_ignores(config.respect_ignores);
+        watcher.set_log_runs(config.log_runs);
+        watcher.set_build_queue(config.build_queue);
+        watcher.set_held(config.held);
+        watcher.set_ac_power_only(config.ac_power_only);
+        watcher.start()?;
+
+        let mut session = WatchSession {
+            watcher,
+            scheduler: None,
+            process: config.process,
+            tx: Some(tx),
+        };
+        session.schedule(&config.project_root, &config.jobs, config.env)?;
+
+        Ok((session, Events::new(rx, log)))
+    }
+
+    // Settings that can change while watching go through the watcher.
+    pub fn watcher(&self) -> &Watcher {
+        &self.watcher
+    }
+
+    pub fn scheduler(&self) -> Option<&Scheduler> {
+        self.scheduler.as_ref()
+    }
+
+    // Replaces the scheduled jobs, e.g. after moving to another project.
+    pub fn schedule<P: AsRef<Path>>(
+        &mut self,
+        project_root: P,
+        jobs: &[ScheduledJob],
+        env: Vec<(String, String)>,
+    ) -> Result<(), String> {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.stop();
+        }
+        let tx = match self.tx.as_ref() {
+            Some(tx) => tx.clone(),
+            None => return Err("The session has stopped.".to_string()),
+        };
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        let scheduler =
+            Scheduler::with_runner(project_root, jobs, self.process.clone(), tx, Instant::now());
+        scheduler.set_env(env);
+        scheduler.start()?;
+        self.scheduler = Some(scheduler);
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.watcher.stop();
+        self.stop_jobs();
+    }
+
+    // Like `stop`, but kills the build that is running rather than waiting
+    // for it.
+    pub fn abort(&mut self) {
+        self.watcher.abort();
+        self.stop_jobs();
+    }
+
+    fn stop_jobs(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.stop();
+        }
+        self.tx = None;
+    }
+}
+
+impl Drop for WatchSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// The events of a `WatchSession`. Every finished build's diagnostics come
+// one by one before its `Finished`, errors first. They're logged as they're
+// taken from the stream, when the session was asked to.
+pub struct Events {
+    rx: UnboundedReceiver<BuildEvent>,
+    pending: VecDeque<BuildEvent>,
+    log: Option<EventLog>,
+}
+
+impl Events {
+    fn new(rx: UnboundedReceiver<BuildEvent>, log: Option<EventLog>) -> Self {
+        Events {
+            rx,
+            pending: VecDeque::new(),
+            log,
+        }
+    }
+
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<BuildEvent>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+
+        match Pin::new(&mut self.rx).poll_next(cx) {
+            Poll::Ready(Some(BuildEvent::Finished(result))) => {
+                self.pending
+                    .extend(result.diagnostics().cloned().map(BuildEvent::Diagnostic));
+                self.pending.push_back(BuildEvent::Finished(result));
+                Poll::Ready(self.pending.pop_front())
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl Stream for Events {
+    type Item = BuildEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<BuildEvent>> {
+        let this = self.get_mut();
+        let poll = this.poll_event(cx);
+        if let (Poll::Ready(Some(event)), Some(log)) = (&poll, this.log.as_mut()) {
+            // the rest isn't logged either, for a log that can't be written
+            // not to turn every event into a warning
+            if let Err(err) = log.record(event) {
+                this.log = None;
+                this.pending.push_back(BuildEvent::Warning(format!(
+                    "Failed to log events: {}",
+                    err
+                )));
+            }
+        }
+        poll
+    }
+}
+
+// An event as embedders outside of Rust get it, e.g.
+// {"event": "diagnostic", "diagnostic": {...}}
+pub fn to_json(event: &BuildEvent) -> Value {
+    match event {
+        BuildEvent::Started => json!({ "event": "started" }),
+        BuildEvent::InstallingToolchain(channel) => {
+            json!({ "event": "installing-toolchain", "toolchain": channel })
+        }
+        BuildEvent::Fetching(progress) => {
+            json!({ "event": "fetching", "progress": progress.to_string(), "fetch": progress })
+        }
+        BuildEvent::Compiling(progress) => {
+            json!({ "event": "compiling", "progress": progress.to_string(), "build": progress })
+        }
+        BuildEvent::Pipeline(pipeline) => json!({ "event": "pipeline", "pipeline": pipeline }),
+        BuildEvent::ExtraRoots(roots) => json!({ "event": "extra-roots", "roots": roots }),
+        BuildEvent::Diagnostic(diag) => json!({ "event": "diagnostic", "diagnostic": diag }),
+        BuildEvent::Finished(result) => json!({ "event": "finished", "result": result }),
+        BuildEvent::Scheduled(job, result) => {
+            json!({ "event": "scheduled", "job": job, "result": result })
+        }
+        BuildEvent::HookFailed(reason) => json!({ "event": "hook-failed", "reason": reason }),
+        BuildEvent::Warning(warning) => json!({ "event": "warning", "warning": warning }),
+        BuildEvent::Stopped(reason) => json!({ "event": "stopped", "reason": reason }),
+    }
+}
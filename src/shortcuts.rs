@@ -0,0 +1,27 @@
+// Keyboard shortcuts for the actions every window has, as GTK accelerators.
+// They're the window's before a focused entry gets the key, so each has a
+// modifier, or is a key nothing types, for entries to keep theirs.
+pub const WINDOW_ACCELS: &[(&str, &[&str])] = &[
+    ("copy-all", &["<Ctrl><Shift>c"]),
+    ("next-diagnostic", &["<Ctrl>Down"]),
+    ("previous-diagnostic", &["<Ctrl>Up"]),
+    ("next-error", &["<Alt>n"]),
+    ("previous-error", &["<Alt>p"]),
+    ("open-current", &["<Ctrl>Return"]),
+    ("clear", &["<Ctrl>l"]),
+    ("export", &["<Ctrl>e"]),
+    ("radiator", &["F11"]),
+    ("command-palette", &["<Ctrl>p"]),
+];
+
+// Whether an entry still gets the key of `accel` while the accelerator is
+// enabled: it takes a modifier, or it's a function key.
+pub fn leaves_typing_alone(accel: &str) -> bool {
+    if accel.starts_with('<') {
+        return true;
+    }
+    match accel.strip_prefix('F') {
+        Some(number) => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
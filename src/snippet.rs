@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Style, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+use crate::ansi;
+use crate::fixer;
+use crate::rust::RustDiagnostic;
+
+// how many lines to show either side of a diagnostic's line
+const CONTEXT_LINES: usize = 2;
+
+const LIGHT_THEME: &str = "InspiredGitHub";
+const DARK_THEME: &str = "base16-ocean.dark";
+
+lazy_static! {
+    // both take a while to load, so only do it once and only when needed
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+}
+
+// The source lines around a diagnostic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    // 1-based number of the first line in `lines`
+    pub first_line: usize,
+    pub lines: Vec<String>,
+    // 1-based line and column the diagnostic points at
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Snippet {
+    pub fn new(contents: &str, line: usize, column: Option<usize>) -> Option<Self> {
+        let all = contents.lines().collect::<Vec<_>>();
+        if line == 0 || line > all.len() {
+            return None;
+        }
+
+        let first = line.saturating_sub(CONTEXT_LINES).max(1);
+        let last = (line + CONTEXT_LINES).min(all.len());
+        Some(Snippet {
+            first_line: first,
+            lines: all[first - 1..last]
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            line,
+            column,
+        })
+    }
+
+    // Pango markup for the lines, highlighted as rust with line numbers in
+    // front and the diagnostic's column, to the end of the word it starts,
+    // underlined.
+    pub fn to_pango(&self, dark: bool) -> String {
+        let theme = &THEMES.themes[if dark { DARK_THEME } else { LIGHT_THEME }];
+        let syntax = SYNTAXES
+            .find_syntax_by_extension("rs")
+            .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, theme);
+
+        let mut out = String::new();
+        for (index, text) in self.lines.iter().enumerate() {
+            let number = self.first_line + index;
+            out.push_str(&format!("{:>4} │ ", number));
+
+            let underline = match self.column {
+                Some(column) if number == self.line => word_at(text, column),
+                _ => None,
+            };
+            let line = format!("{}\n", text);
+            let regions = match highlighter.highlight_line(&line, &SYNTAXES) {
+                Ok(regions) => regions,
+                Err(_) => {
+                    out.push_str(&ansi::escape(text));
+                    out.push('\n');
+                    continue;
+                }
+            };
+
+            let mut offset = 0;
+            for (style, region) in regions {
+                let region = region.trim_end_matches('\n');
+                let end = offset + region.chars().count();
+                match underline {
+                    Some((start, stop)) if start < end && stop > offset => {
+                        // split the region where the underline begins and ends
+                        let before = start.saturating_sub(offset);
+                        let within = stop.min(end) - offset;
+                        push_span(&mut out, style, slice(region, 0, before), false);
+                        push_span(&mut out, style, slice(region, before, within), true);
+                        push_span(&mut out, style, slice(region, within, end - offset), false);
+                    }
+                    _ => push_span(&mut out, style, region, false),
+                }
+                offset = end;
+            }
+            out.push('\n');
+        }
+
+        out.trim_end().to_string()
+    }
+}
+
+// Reads source files on demand, each at most once. Meant to be replaced for
+// every compile run so that edits show up.
+#[derive(Debug, Default)]
+pub struct SnippetCache {
+    project_root: PathBuf,
+    // `None` for files that could not be read
+    files: HashMap<String, Option<String>>,
+    // markup by file, line, column and whether it is for a dark theme
+    markup: HashMap<(String, usize, Option<usize>, bool), Option<String>>,
+    // the same for previews of fixes
+    fixes: HashMap<(String, usize, Option<usize>, bool), Option<String>>,
+}
+
+impl SnippetCache {
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        SnippetCache {
+            project_root: project_root.as_ref().to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    pub fn snippet(&mut self, diag: &RustDiagnostic) -> Option<Snippet> {
+        let file = diag.file.as_ref()?;
+        let line = diag.line? as usize;
+        let project_root = &self.project_root;
+        let contents = self
+            .files
+            .entry(file.clone())
+            .or_insert_with(|| fs::read_to_string(project_root.join(file)).ok())
+            .as_ref()?;
+
+        Snippet::new(contents, line, diag.column.map(|c| c as usize))
+    }
+
+    // The diagnostic's snippet as Pango markup, highlighted once per theme.
+    pub fn markup(&mut self, diag: &RustDiagnostic, dark: bool) -> Option<String> {
+        let key = (
+            diag.file.clone()?,
+            diag.line? as usize,
+            diag.column.map(|c| c as usize),
+            dark,
+        );
+        if let Some(markup) = self.markup.get(&key) {
+            return markup.clone();
+        }
+
+        let markup = self.snippet(diag).map(|snippet| snippet.to_pango(dark));
+        self.markup.insert(key, markup.clone());
+        markup
+    }
+
+    // What the diagnostic's suggestions would change as Pango markup, for
+    // diagnostics that have any that still fit the files.
+    pub fn fix_markup(&mut self, diag: &RustDiagnostic, dark: bool) -> Option<String> {
+        if !diag.is_fixable() {
+            return None;
+        }
+        let key = (
+            diag.file.clone()?,
+            diag.line? as usize,
+            diag.column.map(|c| c as usize),
+            dark,
+        );
+        if let Some(markup) = self.fixes.get(&key) {
+            return markup.clone();
+        }
+
+        let markup = fixer::preview(&self.project_root, diag)
+            .ok()
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| fixer::diff_markup(&lines, dark));
+        self.fixes.insert(key, markup.clone());
+        markup
+    }
+}
+
+// The char range from the 1-based `column` to the end of the word there,
+// covering at least one char.
+fn word_at(text: &str, column: usize) -> Option<(usize, usize)> {
+    let start = column.checked_sub(1)?;
+    let chars = text.chars().skip(start).collect::<Vec<_>>();
+    if chars.is_empty() {
+        return None;
+    }
+    let length = chars
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count()
+        .max(1);
+    Some((start, start + length))
+}
+
+fn slice(text: &str, start: usize, end: usize) -> &str {
+    let byte = |chars: usize| {
+        text.char_indices()
+            .nth(chars)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| text.len())
+    };
+    &text[byte(start)..byte(end)]
+}
+
+fn push_span(out: &mut String, style: Style, text: &str, underline: bool) {
+    if text.is_empty() {
+        return;
+    }
+
+    let color = style.foreground;
+    out.push_str(&format!(
+        "<span foreground=\"#{:02x}{:02x}{:02x}\"",
+        color.r, color.g, color.b
+    ));
+    if style.font_style.contains(FontStyle::BOLD) {
+        out.push_str(" weight=\"bold\"");
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out.push_str(" style=\"italic\"");
+    }
+    if underline {
+        out.push_str(" underline=\"error\"");
+    }
+    out.push('>');
+    out.push_str(&ansi::escape(text));
+    out.push_str("</span>");
+}
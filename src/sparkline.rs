@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+
+use vgtk::lib::gtk::{prelude::*, DrawingArea, Inhibit, StateFlags};
+
+thread_local! {
+    // what the chart shows, and the area it's drawn on; there's only one
+    static POINTS: RefCell<Vec<usize>> = RefCell::new(vec![]);
+    static AREA: RefCell<Option<DrawingArea>> = RefCell::new(None);
+}
+
+// Draws `points` as a line scaled to fill the area, in the area's foreground
+// color, e.g. `<DrawingArea sparkline=counts />`.
+pub trait SparklineExt {
+    fn set_sparkline(&self, points: Vec<usize>);
+}
+
+impl SparklineExt for DrawingArea {
+    fn set_sparkline(&self, points: Vec<usize>) {
+        POINTS.with(|p| *p.borrow_mut() = points);
+        AREA.with(|area| {
+            let mut area = area.borrow_mut();
+            // the area is new whenever the tab it's on was built again
+            if area.as_ref() != Some(self) {
+                self.connect_draw(|widget, cr| {
+                    let width = widget.get_allocated_width() as f64;
+                    let height = widget.get_allocated_height() as f64;
+                    let color = widget.get_style_context().get_color(StateFlags::NORMAL);
+                    cr.set_source_rgba(color.red, color.green, color.blue, color.alpha);
+                    cr.set_line_width(2.0);
+
+                    POINTS.with(|points| {
+                        let points = points.borrow();
+                        let max = points.iter().copied().max().unwrap_or(0).max(1) as f64;
+                        let step = width / (points.len().max(2) - 1) as f64;
+                        for (index, point) in points.iter().enumerate() {
+                            // keeps the line's width inside the area
+                            let y = height - 1.0 - *point as f64 / max * (height - 2.0);
+                            if index == 0 {
+                                cr.move_to(0.0, y);
+                            } else {
+                                cr.line_to(index as f64 * step, y);
+                            }
+                            // a single build still gets a line across
+                            if points.len() == 1 {
+                                cr.line_to(width, y);
+                            }
+                        }
+                    });
+                    cr.stroke();
+
+                    Inhibit(false)
+                });
+                *area = Some(self.clone());
+            }
+        });
+        self.queue_draw();
+    }
+}
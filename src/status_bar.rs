@@ -0,0 +1,436 @@
+use std::iter;
+use std::time::Instant;
+
+use gettextrs::{gettext, ngettext};
+use vgtk::lib::gio::Menu;
+use vgtk::lib::gtk::{
+    prelude::*, Align, Box, Button, Expander, Image, Label, MenuButton, ProgressBar, ReliefStyle,
+    Spinner,
+};
+use vgtk::{ext::*, gtk, Callback, Component, UpdateAction, VNode};
+
+use watch_rust_errors::cargo::{BuildProgress, FetchProgress, ReportedCounts};
+use watch_rust_errors::history::Slowdown;
+use watch_rust_errors::i18n::fill;
+use watch_rust_errors::listing;
+
+use crate::accessible::AccessibleNameExt;
+use crate::format_duration;
+use crate::style::StyleClassExt;
+
+// What's going on besides the results: how the last run went, a build,
+// comparison or bisection
+// that's running and how far it got, cargo getting dependencies or rustup a
+// toolchain, what the latest build's summary lines said, whether its
+// post-processor failed, whether it took much longer than usual, how many of
+// its diagnostics were dropped, the directories watched besides the project
+// and the compiler it's from.
+#[derive(Default)]
+pub struct StatusBar {
+    props: StatusProps,
+}
+
+#[derive(Clone, Default)]
+pub struct StatusProps {
+    // when the build that is running started
+    pub build_started: Option<Instant>,
+    // the ref being built to compare against
+    pub comparing: Option<String>,
+    // what a bisection is up to
+    pub bisecting: Option<String>,
+    pub fetch_progress: Option<FetchProgress>,
+    pub build_progress: Option<BuildProgress>,
+    // the watch is restarting with an edited project root or command
+    pub restarting: bool,
+    // the toolchain rustup is downloading before the next compile can start
+    pub installing_toolchain: Option<String>,
+    // the channel the project root's toolchain file pins
+    pub pinned_toolchain: Option<String>,
+    // the latest results' compiler, and whether it changed since the build
+    // before them
+    pub rustc_version: Option<String>,
+    pub toolchain_changed: bool,
+    // the counts cargo and rustc closed the latest build with
+    pub reported: Option<ReportedCounts>,
+    // why the latest results are as the compiler had them rather than as
+    // the post-processor would
+    pub post_process_error: Option<String>,
+    // why one of the hooks run after the latest build failed
+    pub hook_error: Option<String>,
+    // the latest thing that went wrong that the watch goes on despite
+    pub warning: Option<String>,
+    // how much longer than usual the latest build took, if it stood out
+    pub slowdown: Option<Slowdown>,
+    // how many of the latest build's diagnostics were dropped to keep
+    // within the project's limit
+    pub omitted: usize,
+    // the path dependencies' directories outside the project that are
+    // watched too
+    pub extra_roots: Vec<String>,
+    // e.g. "Last run: 14:32:05 · 2.3 s · 0 errors / 4 warnings", with
+    // buttons to run again and copy it while watching
+    pub last_run: Option<String>,
+    // the changes that started the last run, to tell what keeps starting
+    // builds
+    pub trigger_menu: Option<Menu>,
+    pub watching: bool,
+    pub on_run_now: Callback<()>,
+    pub on_copy_summary: Callback<()>,
+}
+
+#[derive(Clone, Debug)]
+pub enum StatusMessage {
+    RunNow,
+    CopySummary,
+}
+
+impl Component for StatusBar {
+    type Message = StatusMessage;
+    type Properties = StatusProps;
+
+    fn create(props: Self::Properties) -> Self {
+        StatusBar { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        self.props = props;
+        UpdateAction::Render
+    }
+
+    fn update(&mut self, msg: Self::Message) -> UpdateAction<Self> {
+        match msg {
+            StatusMessage::RunNow => self.props.on_run_now.send(()),
+            StatusMessage::CopySummary => self.props.on_copy_summary.send(()),
+        }
+        UpdateAction::None
+    }
+
+    fn view(&self) -> VNode<StatusBar> {
+        gtk! {
+            <Box spacing=6>
+                { self.render_last_run() }
+                { self.render_restarting() }
+                { self.render_build_timer() }
+                { self.render_build_progress() }
+                { self.render_comparing() }
+                { self.render_bisecting() }
+                { self.render_fetch_progress() }
+                { self.render_reported() }
+                { self.render_post_process_error() }
+                { self.render_hook_error() }
+                { self.render_warning() }
+                { self.render_slowdown() }
+                { self.render_omitted() }
+                { self.render_extra_roots() }
+                { self.render_toolchain() }
+            </Box>
+        }
+    }
+}
+
+impl StatusBar {
+    // Left out while building, when the timer says more.
+    fn render_last_run(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        let summary = match self.props.last_run.as_ref() {
+            Some(summary) if self.props.build_started.is_none() => summary,
+            _ => return vec![].into_iter(),
+        };
+        let run_now_tooltip = gettext("Build again without waiting for a change");
+        let trigger_tooltip = gettext("What triggered this run?");
+        vec![
+            gtk! {
+                <Label label=summary.clone() style_class="toolchain" halign=Align::Start />
+            },
+            gtk! {
+                <Button label=gettext("Run now") relief=ReliefStyle::None
+                        tooltip_text=run_now_tooltip.as_str()
+                        sensitive=self.props.watching
+                        on clicked=|_| StatusMessage::RunNow />
+            },
+            gtk! {
+                <Button label=gettext("Copy summary") relief=ReliefStyle::None
+                        on clicked=|_| StatusMessage::CopySummary />
+            },
+        ]
+        .into_iter()
+        .chain(self.props.trigger_menu.as_ref().map(|menu| {
+            gtk! {
+                <MenuButton relief=ReliefStyle::None tooltip_text=trigger_tooltip.as_str()
+                        accessible_name=trigger_tooltip.as_str()
+                        menu_model=Some(menu)>
+                    // show_all doesn't reach past the button
+                    <Image property_icon_name=Some("dialog-question-symbolic") visible=true />
+                </MenuButton>
+            }
+        }))
+        .collect::<Vec<_>>()
+        .into_iter()
+    }
+
+    fn render_restarting(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        let restarting = if self.props.restarting {
+            vec![
+                gtk! { <Spinner property_active=true /> },
+                gtk! {
+                    <Label label=gettext("Restarting watcher…") style_class="toolchain" halign=Align::Start />
+                },
+            ]
+        } else {
+            vec![]
+        };
+        restarting.into_iter()
+    }
+
+    fn render_build_timer(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.build_started.iter().flat_map(|started| {
+            vec![
+                gtk! { <Spinner property_active=true /> },
+                gtk! {
+                    <Label label=fill(gettext("Building… {}"), &[&format_duration(started.elapsed())])
+                           style_class="toolchain" halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    // A bar when cargo says how many crates there are to compile, and which
+    // one it's on either way.
+    fn render_build_progress(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.build_progress.iter().flat_map(|progress| {
+            let bar = progress.fraction().map(|fraction| {
+                gtk! {
+                    <ProgressBar fraction=fraction valign=Align::Center />
+                }
+            });
+            let current = gtk! {
+                <Label label=progress.to_string() style_class="toolchain" halign=Align::Start />
+            };
+            bar.into_iter().chain(iter::once(current))
+        })
+    }
+
+    fn render_comparing(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.comparing.iter().flat_map(|git_ref| {
+            vec![
+                gtk! { <Spinner property_active=true /> },
+                gtk! {
+                    <Label label=fill(gettext("Building {} to compare…"), &[git_ref])
+                           style_class="toolchain" halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    fn render_bisecting(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.bisecting.iter().flat_map(|status| {
+            vec![
+                gtk! { <Spinner property_active=true /> },
+                gtk! {
+                    <Label label=status.clone() style_class="toolchain" halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    // Which crates cargo has downloaded so far, listed in an expander so that
+    // a long first build doesn't look stuck.
+    fn render_fetch_progress(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.fetch_progress.iter().flat_map(|progress| {
+            let spinner = if progress.is_finished() {
+                None
+            } else {
+                Some(gtk! { <Spinner property_active=true /> })
+            };
+            let summary = progress.to_string();
+            let crates = progress.downloaded.join("\n");
+
+            spinner.into_iter().chain(Some(gtk! {
+                <Expander label=Some(summary.as_str()) style_class="toolchain">
+                    <Label label=crates halign=Align::Start selectable=true />
+                </Expander>
+            }))
+        })
+    }
+
+    // What the compiler said it found, called out when that's more than the
+    // list has, since then some of it is only in the raw output.
+    fn render_reported(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.reported.iter().flat_map(|reported| {
+            let icon = if reported.unread {
+                let tooltip =
+                    gettext("Not all of these could be listed; the raw output has the rest");
+                Some(gtk! {
+                    <Image property_icon_name=Some("dialog-warning-symbolic")
+                           tooltip_text=tooltip.as_str() />
+                })
+            } else {
+                None
+            };
+            icon.into_iter().chain(iter::once(gtk! {
+                <Label label=fill(gettext("Reported: {}"), &[reported]) style_class="toolchain"
+                       halign=Align::Start />
+            }))
+        })
+    }
+
+    fn render_post_process_error(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.post_process_error.iter().flat_map(|err| {
+            vec![
+                gtk! {
+                    <Image property_icon_name=Some("dialog-warning-symbolic") />
+                },
+                gtk! {
+                    <Label label=gettext("Post-processor failed; unprocessed results")
+                           tooltip_text=err.as_str() style_class="toolchain-changed"
+                           halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    fn render_hook_error(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.hook_error.iter().flat_map(|err| {
+            vec![
+                gtk! {
+                    <Image property_icon_name=Some("dialog-warning-symbolic") />
+                },
+                gtk! {
+                    <Label label=gettext("Post-build hook failed")
+                           tooltip_text=err.as_str() style_class="toolchain-changed"
+                           halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    fn render_warning(&self) -> impl Iterator<Item = VNode<StatusBar>> + '_ {
+        self.props.warning.iter().flat_map(|warning| {
+            vec![
+                gtk! {
+                    <Image property_icon_name=Some("dialog-warning-symbolic") />
+                },
+                gtk! {
+                    <Label label=gettext("Something went wrong")
+                           tooltip_text=warning.as_str() style_class="toolchain-changed"
+                           halign=Align::Start />
+                },
+            ]
+        })
+    }
+
+    // A dependency or build script added by accident shows in how long
+    // builds take well before anyone wonders why.
+    fn render_slowdown(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        self.props
+            .slowdown
+            .map(|slowdown| {
+                let label = fill(
+                    gettext("Build took {}% longer than usual"),
+                    &[&slowdown.percent_longer()],
+                );
+                let tooltip = fill(
+                    gettext("{0} against a median of {1} for the builds before it"),
+                    &[
+                        &format_duration(slowdown.duration),
+                        &format_duration(slowdown.median),
+                    ],
+                );
+                gtk! {
+                    <Label label=label tooltip_text=tooltip.as_str()
+                           style_class="toolchain-changed" halign=Align::Start />
+                }
+            })
+            .into_iter()
+    }
+
+    fn render_omitted(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        let omitted = self.props.omitted;
+        if omitted == 0 {
+            return None.into_iter();
+        }
+        let label = ngettext(
+            "{} more diagnostic not kept",
+            "{} more diagnostics not kept",
+            omitted as u32,
+        );
+        let tooltip = gettext("Raise max_diagnostics in the project's settings to keep more.");
+        Some(gtk! {
+            <Label label=fill(label, &[&listing::thousands(omitted)])
+                   tooltip_text=tooltip.as_str()
+                   style_class="toolchain-changed" halign=Align::Start />
+        })
+        .into_iter()
+    }
+
+    // The compiler behind the latest results. A changed toolchain is called out
+    // since it often explains a sudden wave of new warnings.
+    // Listed in an expander, like the crates being fetched.
+    fn render_extra_roots(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        let roots = &self.props.extra_roots;
+        if roots.is_empty() {
+            return None.into_iter();
+        }
+        let summary = fill(
+            ngettext(
+                "Also watching {} directory",
+                "Also watching {} directories",
+                roots.len() as u32,
+            ),
+            &[&roots.len()],
+        );
+        let tooltip = gettext("Path dependencies outside the project");
+        Some(gtk! {
+            <Expander label=Some(summary.as_str()) style_class="toolchain"
+                      tooltip_text=tooltip.as_str()>
+                <Label label=roots.join("\n") halign=Align::Start selectable=true />
+            </Expander>
+        })
+        .into_iter()
+    }
+
+    fn render_toolchain(&self) -> impl Iterator<Item = VNode<StatusBar>> {
+        if let Some(channel) = self.props.installing_toolchain.as_ref() {
+            return vec![
+                gtk! { <Spinner property_active=true /> },
+                gtk! {
+                    <Label label=fill(gettext("Installing toolchain {}…"), &[channel])
+                           style_class="toolchain" halign=Align::Start />
+                },
+            ]
+            .into_iter();
+        }
+
+        let version = self.props.rustc_version.clone().unwrap_or_default();
+        let changed = self.props.toolchain_changed;
+
+        let icon = if changed {
+            let tooltip = gettext("The toolchain changed since the previous build");
+            Some(gtk! {
+                <Image property_icon_name=Some("dialog-warning-symbolic")
+                       tooltip_text=tooltip.as_str() />
+            })
+        } else {
+            None
+        };
+        let label = gtk! {
+            <Label label=version
+                   style_class={ if changed { "toolchain-changed" } else { "toolchain" } }
+                   halign=Align::Start />
+        };
+        let pinned = self.props.pinned_toolchain.as_ref().map(|channel| {
+            let tooltip = gettext("Pinned by the project's rust-toolchain file");
+            gtk! {
+                <Label label=fill(gettext("Toolchain: {}"), &[channel])
+                       tooltip_text=tooltip.as_str()
+                       style_class="toolchain" halign=Align::Start />
+            }
+        });
+
+        pinned
+            .into_iter()
+            .chain(icon)
+            .chain(iter::once(label))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
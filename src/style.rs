@@ -0,0 +1,402 @@
+use std::cell::Cell;
+
+use gettextrs::gettext;
+use vgtk::lib::gdk::Screen;
+use vgtk::lib::gtk::{
+    prelude::*, CssProvider, Settings, StyleContext, STYLE_PROVIDER_PRIORITY_APPLICATION,
+};
+
+use watch_rust_errors::config::Theme;
+use watch_rust_errors::pipeline::UnitState;
+use watch_rust_errors::rust::Type;
+
+// the hottest `heat-N` class the style sheet has rules for
+const HOTTEST: usize = 4;
+
+thread_local! {
+    static PROVIDER: CssProvider = CssProvider::new();
+
+    // the desktop's own dark theme preference, captured before we override it
+    static SYSTEM_PREFERS_DARK: Cell<Option<bool>> = Cell::new(None);
+
+    // whether the theme that was applied last is a dark one
+    static DARK: Cell<bool> = Cell::new(false);
+}
+
+const LIGHT_PALETTE: &str = "
+@define-color wre_error #c01c28;
+@define-color wre_warning #9c6e03;
+@define-color wre_info #1a5fb4;
+@define-color wre_error_bg alpha(#c01c28, 0.08);
+@define-color wre_warning_bg alpha(#e5a50a, 0.10);
+@define-color wre_info_bg alpha(#1a5fb4, 0.06);
+@define-color wre_fixed #26a269;
+";
+
+const DARK_PALETTE: &str = "
+@define-color wre_error #ff7b63;
+@define-color wre_warning #f8e45c;
+@define-color wre_info #99c1f1;
+@define-color wre_error_bg alpha(#ff7b63, 0.10);
+@define-color wre_warning_bg alpha(#f8e45c, 0.08);
+@define-color wre_info_bg alpha(#99c1f1, 0.08);
+@define-color wre_fixed #8ff0a4;
+";
+
+const RULES: &str = "
+row.error { border-left: 4px solid @wre_error; background-color: @wre_error_bg; }
+row.warning { border-left: 4px solid @wre_warning; background-color: @wre_warning_bg; }
+row.info { border-left: 4px solid @wre_info; background-color: @wre_info_bg; }
+row.error image { color: @wre_error; }
+row.warning image { color: @wre_warning; }
+row.info image { color: @wre_info; }
+row.error .title { color: @wre_error; }
+row.warning .title { color: @wre_warning; }
+.title { font-weight: bold; }
+.location { font-family: monospace; opacity: 0.8; }
+.details { font-family: monospace; }
+.snippet { font-family: monospace; padding: 4px 0; }
+.fix-preview { font-family: monospace; padding: 4px 0; border-left: 2px solid alpha(currentColor, 0.3); padding-left: 6px; }
+.children { opacity: 0.9; }
+.banner { padding: 6px 12px; background-color: @wre_warning_bg; }
+.badge { border-radius: 8px; padding: 0 6px; font-size: smaller; font-weight: bold; }
+row.error .badge { background-color: @wre_error; color: white; }
+row.warning .badge { background-color: @wre_warning; color: black; }
+row.info .badge { background-color: @wre_info; color: white; }
+.tags { border: 1px solid alpha(currentColor, 0.4); border-radius: 8px; padding: 0 6px; font-size: smaller; }
+.toolchain { opacity: 0.8; }
+.sparkline { color: @wre_error; }
+.raw-output { padding: 6px; }
+.toolchain-changed { color: @wre_warning; font-weight: bold; }
+row.current { border-left-width: 8px; }
+row.new { background-color: alpha(@wre_info, 0.20); }
+row.fixed { border-left: 4px solid @wre_fixed; background-color: alpha(@wre_fixed, 0.10); }
+row.fixed image, row.fixed .title { color: @wre_fixed; }
+row.fixed .title, row.fixed .location { text-decoration-line: line-through; }
+button.heat-0, button.heat-1, button.heat-2, button.heat-3, button.heat-4 {
+    background-image: none; border-radius: 0; border: 1px solid @theme_bg_color;
+}
+button.heat-0 { background-color: @wre_info_bg; }
+button.heat-1 { background-color: alpha(@wre_warning, 0.20); }
+button.heat-2 { background-color: alpha(@wre_warning, 0.45); }
+button.heat-3 { background-color: alpha(@wre_error, 0.45); }
+button.heat-4 { background-color: alpha(@wre_error, 0.75); }
+button.current { border: 2px solid @theme_fg_color; }
+.unit { padding: 2px 6px; border-radius: 3px; border: 1px solid alpha(@theme_fg_color, 0.2); }
+.unit.pending { opacity: 0.5; }
+.unit.active { background-color: alpha(@wre_info, 0.35); }
+.unit.done { background-color: alpha(@wre_fixed, 0.25); }
+.unit.critical { border: 2px solid @wre_error; font-weight: bold; }
+entry.invalid { border-color: @wre_error; box-shadow: inset 0 0 0 1px @wre_error; }
+.radiator { padding: 40px; }
+.radiator.passing { background-color: @wre_fixed; }
+.radiator.failing { background-color: @wre_error; }
+.radiator.not-built { background-color: @wre_info; }
+.radiator label { color: white; }
+.onboarding { padding: 40px; }
+.onboarding-title { font-size: 24pt; font-weight: bold; }
+.onboarding button.suggested-action { font-size: 14pt; padding: 10px 30px; }
+.radiator-name { font-size: 48pt; font-weight: bold; }
+.radiator-status { font-size: 36pt; }
+.radiator-counts { font-size: 28pt; }
+.radiator-when { font-size: 18pt; opacity: 0.9; }
+@keyframes radiator-flash { 50% { opacity: 0.4; } }
+.radiator.flash { animation: radiator-flash 500ms ease-in-out infinite; }
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    const ALL: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Info];
+
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Severity::Error => "dialog-error-symbolic",
+            Severity::Warning => "dialog-warning-symbolic",
+            Severity::Info => "dialog-information-symbolic",
+        }
+    }
+
+    // what a screen reader says the severity icon is
+    pub fn name(self) -> String {
+        match self {
+            Severity::Error => gettext("Error"),
+            Severity::Warning => gettext("Warning"),
+            Severity::Info => gettext("Information"),
+        }
+    }
+}
+
+impl From<&Type> for Severity {
+    fn from(type_: &Type) -> Self {
+        match type_ {
+            Type::Error => Severity::Error,
+            Type::Warning => Severity::Warning,
+        }
+    }
+}
+
+// Lets `gtk!` set a severity CSS class through an attribute, e.g.
+// `<ListBoxRow severity=Severity::Error>`. Any previously applied severity
+// class is removed so rows can be reused for a different severity.
+pub trait SeverityExt {
+    fn set_severity(&self, severity: Severity);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> SeverityExt for W {
+    fn set_severity(&self, severity: Severity) {
+        let context = self.get_style_context();
+        for other in Severity::ALL.iter() {
+            context.remove_class(other.css_class());
+        }
+        context.add_class(severity.css_class());
+    }
+}
+
+// Marks the row keyboard navigation is on, e.g. `<ListBoxRow current=true>`.
+pub trait CurrentExt {
+    fn set_current(&self, current: bool);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> CurrentExt for W {
+    fn set_current(&self, current: bool) {
+        let context = self.get_style_context();
+        if current {
+            context.add_class("current");
+        } else {
+            context.remove_class("current");
+        }
+    }
+}
+
+// How a row's diagnostic compares with the previous build's, when showing
+// the changes between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Unchanged,
+    New,
+    Fixed,
+}
+
+// Highlights new diagnostics and strikes through fixed ones, e.g.
+// `<ListBoxRow change=Change::New>`, replacing whatever was set before.
+pub trait ChangeExt {
+    fn set_change(&self, change: Change);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> ChangeExt for W {
+    fn set_change(&self, change: Change) {
+        let context = self.get_style_context();
+        context.remove_class("new");
+        context.remove_class("fixed");
+        match change {
+            Change::Unchanged => {}
+            Change::New => context.add_class("new"),
+            Change::Fixed => context.add_class("fixed"),
+        }
+    }
+}
+
+// Colors a module in the overview by how many diagnostics it has for its
+// size, e.g. `<Button heat=2>`, replacing any level set before.
+pub trait HeatExt {
+    fn set_heat(&self, heat: usize);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> HeatExt for W {
+    fn set_heat(&self, heat: usize) {
+        let context = self.get_style_context();
+        for level in 0..=HOTTEST {
+            context.remove_class(&format!("heat-{}", level));
+        }
+        context.add_class(&format!("heat-{}", heat.min(HOTTEST)));
+    }
+}
+
+// Shows how far the build has got with a crate in the pipeline tab, e.g.
+// `<Label unit_state=UnitState::Active>`, replacing whatever was set before.
+pub trait UnitStateExt {
+    fn set_unit_state(&self, state: UnitState);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> UnitStateExt for W {
+    fn set_unit_state(&self, state: UnitState) {
+        let context = self.get_style_context();
+        context.remove_class("pending");
+        context.remove_class("active");
+        context.remove_class("done");
+        context.add_class(match state {
+            UnitState::Pending => "pending",
+            UnitState::Active => "active",
+            UnitState::Done => "done",
+        });
+    }
+}
+
+// Outlines a crate on the build's critical path, e.g. `<Label critical=true>`.
+pub trait CriticalExt {
+    fn set_critical(&self, critical: bool);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> CriticalExt for W {
+    fn set_critical(&self, critical: bool) {
+        let context = self.get_style_context();
+        if critical {
+            context.add_class("critical");
+        } else {
+            context.remove_class("critical");
+        }
+    }
+}
+
+// Marks an entry whose text keeps a watch from starting, e.g.
+// `<Entry invalid=true>`.
+pub trait InvalidExt {
+    fn set_invalid(&self, invalid: bool);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> InvalidExt for W {
+    fn set_invalid(&self, invalid: bool) {
+        let context = self.get_style_context();
+        if invalid {
+            context.add_class("invalid");
+        } else {
+            context.remove_class("invalid");
+        }
+    }
+}
+
+// Lets `gtk!` add a plain CSS class through an attribute.
+pub trait StyleClassExt {
+    fn set_style_class(&self, class: &str);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> StyleClassExt for W {
+    fn set_style_class(&self, class: &str) {
+        self.get_style_context().add_class(class);
+    }
+}
+
+// How the latest build went, as the radiator shows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radiator {
+    NotBuilt,
+    Passing,
+    Failing,
+}
+
+impl Radiator {
+    const ALL: [Radiator; 3] = [Radiator::NotBuilt, Radiator::Passing, Radiator::Failing];
+
+    fn css_class(self) -> &'static str {
+        match self {
+            Radiator::NotBuilt => "not-built",
+            Radiator::Passing => "passing",
+            Radiator::Failing => "failing",
+        }
+    }
+}
+
+// Colors the radiator's panel, e.g. `<Box radiator=Radiator::Failing>`,
+// replacing whatever was set before.
+pub trait RadiatorExt {
+    fn set_radiator(&self, radiator: Radiator);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> RadiatorExt for W {
+    fn set_radiator(&self, radiator: Radiator) {
+        let context = self.get_style_context();
+        for other in Radiator::ALL.iter() {
+            context.remove_class(other.css_class());
+        }
+        context.add_class(radiator.css_class());
+    }
+}
+
+// Makes the radiator flash while set, e.g. `<Box flash=true>`.
+pub trait FlashExt {
+    fn set_flash(&self, flash: bool);
+}
+
+impl<W: IsA<vgtk::lib::gtk::Widget>> FlashExt for W {
+    fn set_flash(&self, flash: bool) {
+        let context = self.get_style_context();
+        if flash {
+            context.add_class("flash");
+        } else {
+            context.remove_class("flash");
+        }
+    }
+}
+
+fn system_prefers_dark(settings: &Settings) -> bool {
+    SYSTEM_PREFERS_DARK.with(|cached| match cached.get() {
+        Some(dark) => dark,
+        None => {
+            let dark = settings.get_property_gtk_application_prefer_dark_theme()
+                || settings
+                    .get_property_gtk_theme_name()
+                    .map(|name| name.to_lowercase().contains("dark"))
+                    .unwrap_or(false);
+            cached.set(Some(dark));
+            dark
+        }
+    })
+}
+
+pub fn is_dark() -> bool {
+    DARK.with(Cell::get)
+}
+
+// Installs the application style sheet for the default screen.
+pub fn install(theme: Theme) {
+    if let Some(screen) = Screen::get_default() {
+        PROVIDER.with(|provider| {
+            StyleContext::add_provider_for_screen(
+                &screen,
+                provider,
+                STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        });
+    }
+
+    apply(theme);
+}
+
+// Switches GTK's dark theme variant and the results palette to match `theme`.
+pub fn apply(theme: Theme) {
+    let dark = match Settings::get_default() {
+        Some(settings) => {
+            let system = system_prefers_dark(&settings);
+            let dark = match theme {
+                Theme::System => system,
+                Theme::Light => false,
+                Theme::Dark => true,
+            };
+            settings.set_property_gtk_application_prefer_dark_theme(dark);
+            dark
+        }
+        None => false,
+    };
+
+    DARK.with(|cached| cached.set(dark));
+    let palette = if dark { DARK_PALETTE } else { LIGHT_PALETTE };
+    PROVIDER.with(|provider| {
+        if let Err(err) = provider.load_from_data(format!("{}{}", palette, RULES).as_bytes()) {
+            eprintln!("Failed to load style sheet: {}", err);
+        }
+    });
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::Path;
+use std::str;
+
+use serde::Deserialize;
+
+use crate::process::{ProcessRunner, SystemRunner};
+
+// files rustup reads to pick the toolchain for a directory
+const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain", "rust-toolchain.toml"];
+
+// what rustup picks the toolchain by ahead of toolchain files, and what
+// `cargo +nightly` sets
+pub const TOOLCHAIN_VAR: &str = "RUSTUP_TOOLCHAIN";
+
+// what cargo builds for when the command doesn't pass `--target`
+pub const TARGET_VAR: &str = "CARGO_BUILD_TARGET";
+
+// offered whether they're installed or not, since rustup can get them
+pub const CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+
+// the architectures that start the host part of a toolchain's name, e.g.
+// "nightly-2024-05-01-x86_64-unknown-linux-gnu"
+const ARCHES: &[&str] = &[
+    "x86_64",
+    "i686",
+    "aarch64",
+    "arm",
+    "armv7",
+    "loongarch64",
+    "powerpc64le",
+    "riscv64gc",
+    "s390x",
+];
+
+pub fn is_toolchain_file<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| TOOLCHAIN_FILES.contains(&name))
+        .unwrap_or(false)
+}
+
+#[derive(Deserialize)]
+struct ToolchainFile {
+    toolchain: ToolchainSection,
+}
+
+#[derive(Deserialize)]
+struct ToolchainSection {
+    channel: Option<String>,
+}
+
+// The channel pinned by the toolchain file rustup would pick for
+// `project_root`, which is the nearest one in it or its parents.
+pub fn pinned_channel<P: AsRef<Path>>(project_root: P) -> Option<String> {
+    project_root.as_ref().ancestors().find_map(|dir| {
+        TOOLCHAIN_FILES
+            .iter()
+            .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+            .map(|contents| parse_channel(&contents))
+    })?
+}
+
+// Toolchain files are either TOML with a `[toolchain]` table or, in the
+// legacy format, just the channel name.
+pub fn parse_channel(contents: &str) -> Option<String> {
+    match toml::from_str::<ToolchainFile>(contents) {
+        Ok(file) => file.toolchain.channel,
+        Err(_) => contents
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .filter(|l| !l.contains(&['=', '['][..]))
+            .map(ToString::to_string),
+    }
+}
+
+// Whether rustup already has `channel`. Without rustup there is nothing to
+// install, so that counts as installed.
+pub fn is_installed_with<R, P>(
+    runner: &R,
+    project_root: P,
+    channel: &str,
+    env: &[(String, String)],
+) -> bool
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let installed = match list(runner, project_root.as_ref(), env) {
+        Some(installed) => installed,
+        None => return true,
+    };
+
+    let prefix = format!("{}-", channel);
+    installed
+        .iter()
+        .any(|name| name == channel || name.starts_with(&prefix))
+}
+
+// The toolchains rustup has, by the names they're picked with, e.g.
+// "stable-x86_64-unknown-linux-gnu". Empty without rustup.
+pub fn installed_with<R, P>(runner: &R, project_root: P, env: &[(String, String)]) -> Vec<String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    list(runner, project_root.as_ref(), env).unwrap_or_default()
+}
+
+// A toolchain's name without the host it's for, e.g. "stable" for
+// "stable-x86_64-unknown-linux-gnu".
+pub fn short_name(toolchain: &str) -> &str {
+    ARCHES
+        .iter()
+        .filter_map(|arch| toolchain.find(&format!("-{}-", arch)))
+        .min()
+        .map_or(toolchain, |end| &toolchain[..end])
+}
+
+// The targets rustup has the standard library for with the toolchain `env`
+// picks, e.g. "wasm32-unknown-unknown", the host's among them. Empty without
+// rustup.
+pub fn installed_targets_with<R, P>(
+    runner: &R,
+    project_root: P,
+    env: &[(String, String)],
+) -> Vec<String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let args = ["target", "list", "--installed"];
+    match runner.run("rustup", &args, env, project_root.as_ref()) {
+        Ok(output) if output.success => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn list<R>(runner: &R, project_root: &Path, env: &[(String, String)]) -> Option<Vec<String>>
+where
+    R: ProcessRunner + ?Sized,
+{
+    let output = match runner.run("rustup", &["toolchain", "list"], env, project_root) {
+        Ok(output) if output.success => output,
+        _ => return None,
+    };
+
+    // e.g. "stable-x86_64-unknown-linux-gnu (default)", or "no installed
+    // toolchains"
+    let installed = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("no installed"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(ToString::to_string)
+        .collect();
+    Some(installed)
+}
+
+// The `rustc --version` rustup resolves for `project_root`, e.g.
+// "rustc 1.47.0 (18bf6b4f0 2020-10-07)".
+pub fn version<P: AsRef<Path>>(project_root: P) -> Result<String, String> {
+    version_with(&SystemRunner, project_root, &[])
+}
+
+// `env` matters since it may pick a toolchain through `RUSTUP_TOOLCHAIN`.
+pub fn version_with<R, P>(
+    runner: &R,
+    project_root: P,
+    env: &[(String, String)],
+) -> Result<String, String>
+where
+    R: ProcessRunner + ?Sized,
+    P: AsRef<Path>,
+{
+    let output = runner.run("rustc", &["--version"], env, project_root.as_ref())?;
+    if !output.success {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let version = str::from_utf8(&output.stdout)
+        .map_err(|e| format!("{:?}", e))?
+        .trim();
+    if version.is_empty() {
+        return Err("rustc did not report a version.".to_string());
+    }
+    Ok(version.to_string())
+}
@@ -0,0 +1,110 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::cargo;
+
+// What keeps a watch from starting, by the setting that needs fixing, so
+// each can be pointed out where it's entered.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Problems {
+    pub project_root: Option<String>,
+    pub command: Option<String>,
+}
+
+impl Problems {
+    pub fn is_empty(&self) -> bool {
+        self.project_root.is_none() && self.command.is_none()
+    }
+}
+
+// Checks that `project_root` is a directory and that the program `command`
+// starts can be found, on the PATH `env` sets if it sets one. Commands run
+// through the shell are left to it.
+pub fn check(project_root: &str, command: &str, env: &[(String, String)]) -> Problems {
+    let root = Path::new(project_root);
+    let project_root_problem = if project_root.trim().is_empty() {
+        Some("Choose the project to watch.".to_string())
+    } else if !root.exists() {
+        Some(format!("{} doesn't exist.", project_root))
+    } else if !root.is_dir() {
+        Some(format!("{} is not a directory.", project_root))
+    } else {
+        None
+    };
+
+    let command_problem = if command.trim().is_empty() {
+        Some("Enter the command to build with, e.g. \"cargo check\".".to_string())
+    } else {
+        match cargo::program(command) {
+            Err(err) => Some(err),
+            Ok(Some(program)) if !is_found(&program, root, env) => Some(if has_dir(&program) {
+                format!(
+                    "{} doesn't exist; relative paths are resolved from the project root.",
+                    program
+                )
+            } else {
+                format!(
+                    "{} isn't on the PATH; check the spelling or install it.",
+                    program
+                )
+            }),
+            Ok(_) => None,
+        }
+    };
+
+    Problems {
+        project_root: project_root_problem,
+        command: command_problem,
+    }
+}
+
+// Like `check`, for commands that run inside a container, where it's the
+// image's PATH that the program has to be on rather than the host's.
+pub fn check_in_container(project_root: &str, command: &str) -> Problems {
+    let mut problems = check(project_root, command, &[]);
+    if !command.trim().is_empty() {
+        problems.command = cargo::program(command).err();
+    }
+    problems
+}
+
+// What keeps the program a command other than the build's starts, e.g. an
+// editor, from being found, if anything. Relative paths are resolved from the
+// current directory, and commands run through the shell are left to it.
+pub fn program_problem(command: &str) -> Option<String> {
+    match cargo::program(command) {
+        Err(err) => Some(err),
+        Ok(Some(program)) if !is_found(&program, Path::new("."), &[]) => Some(format!(
+            "{} isn't on the PATH; check the spelling or install it.",
+            program
+        )),
+        Ok(_) => None,
+    }
+}
+
+fn has_dir(program: &str) -> bool {
+    program.contains('/') || (cfg!(target_os = "windows") && program.contains('\\'))
+}
+
+fn is_found(program: &str, project_root: &Path, env: &[(String, String)]) -> bool {
+    if has_dir(program) {
+        return is_program(&project_root.join(program));
+    }
+    let path = env
+        .iter()
+        .find(|(k, _)| k == "PATH")
+        .map(|(_, v)| OsString::from(v))
+        .or_else(|| env::var_os("PATH"))
+        .unwrap_or_default();
+    env::split_paths(&path).any(|dir| is_program(&dir.join(program)))
+}
+
+// Windows finds programs without their extension too.
+fn is_program(path: &Path) -> bool {
+    path.is_file()
+        || (cfg!(target_os = "windows")
+            && ["exe", "cmd", "bat"]
+                .iter()
+                .any(|ext| path.with_extension(ext).is_file()))
+}
@@ -1,97 +1,1547 @@
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
-use std::thread::{self, JoinHandle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+use futures::channel::mpsc::UnboundedSender;
 use glib::Sender;
-use watchexec::{
-    error::{Error as WatchError, Result as WatchResult},
-    pathop::PathOp,
-    Args, ArgsBuilder, Handler,
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, PollWatcher, RecursiveMode,
 };
 
-use crate::cargo::{self, CompileResult};
+use crate::cargo::{
+    self, BuildProgress, CompileResult, FetchProgress, Op, PathOp, Progress, SuccessPolicy,
+};
+use crate::git::{self, GitTrigger};
+use crate::hooks::{Hook, Report};
+use crate::ignores::IgnoreRules;
+use crate::logging::{self, RunLog};
+use crate::lsp::LspServer;
+use crate::parser::{Parser, RustcParser};
+use crate::pathdeps;
+use crate::pipeline::Pipeline;
+use crate::postprocess::PostProcessor;
+use crate::power;
+use crate::process::{ProcessRunner, Stop, Stoppable, SystemRunner};
+use crate::rust::RustDiagnostic;
+use crate::sandbox;
+use crate::server::Server;
+use crate::toolchain;
+use crate::wrapper;
 
-struct State {
-    project_root: PathBuf,
-    command: String,
-    quit: bool,
-    tx: Sender<CompileResult>,
-    runner: Option<JoinHandle<()>>,
+// how often a folder the file system can't tell about changes to is looked
+// at for them
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// how long the file system has to be quiet before a compile is started,
+// unless changed with `Watcher::set_debounce`
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+const IGNORED_DIRS: &[&str] = &["target", ".git", logging::LOG_DIR];
+
+// the swap, backup and lock files editors keep next to the file being edited,
+// e.g. vim's ".main.rs.swp" or emacs' ".#main.rs"
+const EDITOR_PREFIXES: &[&str] = &[".#", "#"];
+const EDITOR_SUFFIXES: &[&str] = &["~", ".swp", ".swx", ".tmp", ".bak"];
+// vim writes it to check whether it may create files in a directory
+const VIM_PROBE: &str = "4913";
+
+// how often a build waiting for its turn in the build queue checks on it
+const TURN_POLL: Duration = Duration::from_millis(100);
+
+// how often builds the build policy holds back check whether they may go
+const HOLD_POLL: Duration = Duration::from_millis(500);
+
+// how long `Watcher::abort` waits for the build it stops to be gone
+const ABORT_WAIT: Duration = Duration::from_secs(5);
+
+// how many finished builds the build queue keeps to show
+const KEPT_JOBS: usize = 20;
+
+// what inotify fails with once it's out of watches, as Linux numbers it
+const ENOSPC: i32 = 28;
+
+// the locks version control tools hold while they rewrite the working tree,
+// e.g. for every step of a checkout or rebase
+const VCS_LOCKS: &[&str] = &[".git/index.lock", ".hg/wlock"];
+
+// What the watcher thread reports back while it works.
+#[derive(Clone, Debug)]
+pub enum BuildEvent {
+    // a compile is about to start
+    Started,
+    // rustup is downloading the toolchain the project pins, which can take
+    // minutes before the compile even starts
+    InstallingToolchain(String),
+    // cargo is getting dependencies before it can compile
+    Fetching(FetchProgress),
+    // how far the compile has got
+    Compiling(BuildProgress),
+    // the crates the compile goes through, when it streams JSON messages
+    Pipeline(Pipeline),
+    // the directories outside the project that are watched along with it,
+    // for the path dependencies it builds; sent whenever they change
+    ExtraRoots(Vec<PathBuf>),
+    // one of the build's diagnostics; only a `WatchSession` reports these,
+    // each just before the `Finished` of its build
+    Diagnostic(RustDiagnostic),
+    Finished(CompileResult),
+    // a scheduled job, by name, finished
+    Scheduled(String, CompileResult),
+    // one of the hooks run after a build failed, and why
+    HookFailed(String),
+    // something went wrong that the watch goes on despite, e.g. a directory
+    // that couldn't be watched
+    Warning(String),
+    // the watcher thread gave up without being asked to, and why; nothing
+    // is built until the watch is started again
+    Stopped(String),
 }
 
+// Where the watcher and scheduler threads send their events: a glib channel,
+// for code running a main loop, or a futures channel, for a `WatchSession`.
+#[derive(Clone)]
+pub enum EventSender {
+    Glib(Sender<BuildEvent>),
+    Futures(UnboundedSender<BuildEvent>),
+}
+
+impl EventSender {
+    pub fn send(&self, event: BuildEvent) -> Result<(), String> {
+        match self {
+            EventSender::Glib(tx) => tx.send(event).map_err(|e| format!("{:?}", e)),
+            EventSender::Futures(tx) => tx.unbounded_send(event).map_err(|e| format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<Sender<BuildEvent>> for EventSender {
+    fn from(tx: Sender<BuildEvent>) -> Self {
+        EventSender::Glib(tx)
+    }
+}
+
+impl From<UnboundedSender<BuildEvent>> for EventSender {
+    fn from(tx: UnboundedSender<BuildEvent>) -> Self {
+        EventSender::Futures(tx)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Remove,
+    Rename,
+    Other,
+}
+
+impl From<&EventKind> for ChangeKind {
+    fn from(kind: &EventKind) -> Self {
+        match kind {
+            EventKind::Create(_) => ChangeKind::Create,
+            EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Rename,
+            EventKind::Modify(_) => ChangeKind::Modify,
+            EventKind::Remove(_) => ChangeKind::Remove,
+            _ => ChangeKind::Other,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    // for renames where both ends are known, the path the file was renamed to
+    pub renamed_to: Option<PathBuf>,
+}
+
+impl ChangeEvent {
+    pub fn new<P: AsRef<Path>>(path: P, kind: ChangeKind) -> Self {
+        ChangeEvent {
+            path: path.as_ref().to_path_buf(),
+            kind,
+            renamed_to: None,
+        }
+    }
+
+    fn from_event(event: &Event) -> Vec<Self> {
+        let kind = ChangeKind::from(&event.kind);
+        match (&event.kind, event.paths.as_slice()) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                vec![ChangeEvent {
+                    path: from.clone(),
+                    kind,
+                    renamed_to: Some(to.clone()),
+                }]
+            }
+            (_, paths) => paths.iter().map(|p| ChangeEvent::new(p, kind)).collect(),
+        }
+    }
+
+    // Whether a change to this path can affect the build: only the sources
+    // the parser's tools read and toolchain files count, and anything under
+    // the target directory or left behind by editors is skipped.
+    pub fn is_relevant(&self, project_root: &Path, parser: &dyn Parser) -> bool {
+        let relevant = |path: &Path| {
+            let relative = path.strip_prefix(project_root).unwrap_or(path);
+            let ignored = relative
+                .components()
+                .any(|c| IGNORED_DIRS.iter().any(|d| c.as_os_str() == *d));
+
+            !ignored
+                && !is_editor_file(path)
+                && (parser.watches(relative) || toolchain::is_toolchain_file(path))
+        };
+
+        relevant(&self.path) || self.renamed_to.as_deref().map(relevant).unwrap_or(false)
+    }
+
+    // Whether `ignores` leave out the changed file, and where it was renamed
+    // to if anywhere.
+    pub fn is_ignored_by(&self, ignores: &IgnoreRules) -> bool {
+        ignores.is_ignored(&self.path)
+            && self
+                .renamed_to
+                .as_deref()
+                .map(|to| ignores.is_ignored(to))
+                .unwrap_or(true)
+    }
+
+    fn touches_ignore_file(&self) -> bool {
+        IgnoreRules::is_ignore_file(&self.path)
+            || self
+                .renamed_to
+                .as_deref()
+                .map(IgnoreRules::is_ignore_file)
+                .unwrap_or(false)
+    }
+
+    fn is_vcs_lock(&self) -> bool {
+        VCS_LOCKS.iter().any(|lock| self.path.ends_with(lock))
+    }
+
+    fn touches_manifest(&self) -> bool {
+        let is_manifest = |path: &Path| {
+            matches!(
+                path.file_name().and_then(|name| name.to_str()),
+                Some("Cargo.toml") | Some("Cargo.lock")
+            )
+        };
+        is_manifest(&self.path) || self.renamed_to.as_deref().map(is_manifest).unwrap_or(false)
+    }
+
+    fn touches_toolchain(&self) -> bool {
+        toolchain::is_toolchain_file(&self.path)
+            || self
+                .renamed_to
+                .as_deref()
+                .map(toolchain::is_toolchain_file)
+                .unwrap_or(false)
+    }
+}
+
+// What the watcher thread is asked to do. It owns all of the watcher's state
+// and handles these one at a time, so nothing needs locking.
+enum Command {
+    // start watching the file system, handing changes back through the sender
+    Start(mpsc::Sender<Command>),
+    Stop,
+    // compile as if these events happened and reply with the outcome
+    Trigger(Vec<ChangeEvent>, mpsc::Sender<Result<(), String>>),
+    // something the file system watcher reported
+    Changed(notify::Result<Event>),
+    Update(Update),
+    LastEvents(mpsc::Sender<Vec<ChangeEvent>>),
+}
+
+// Settings that can change while watching. They take effect from the next
+// compile on.
+enum Update {
+    ProjectRoot(PathBuf),
+    Command(String),
+    Env(Vec<(String, String)>),
+    Parser(Arc<dyn Parser>),
+    Debounce(Duration),
+    BuildHooks(bool),
+    PostProcessor(Option<PostProcessor>),
+    PostBuild(Vec<Hook>),
+    MaxDiagnostics(usize),
+    BuildTimeout(Option<Duration>),
+    SuccessPolicy(Option<SuccessPolicy>),
+    RespectIgnores(bool),
+    LogRuns(bool),
+    Server(Option<Server>),
+    LspServer(Option<LspServer>),
+    BuildQueue(Option<BuildQueue>),
+    Held(bool),
+    AcPowerOnly(bool),
+}
+
+// A handle to the watcher thread. The thread runs until `stop` is called, or
+// until every handle is gone if it never started watching.
 #[derive(Clone)]
 pub struct Watcher {
-    state: Arc<RwLock<State>>,
+    commands: mpsc::Sender<Command>,
+    running: Arc<Mutex<Running>>,
+}
+
+// The build the watcher thread is running, for `Watcher::abort` to stop.
+#[derive(Default)]
+struct Running {
+    aborted: bool,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl Watcher {
-    pub fn new<P: AsRef<Path>>(
+    pub fn new<P: AsRef<Path>, S: Into<EventSender>>(
         project_root: P,
         command: &str,
-        tx: Sender<CompileResult>,
+        tx: S,
     ) -> Result<Self, String> {
-        Ok(Watcher {
-            state: Arc::new(RwLock::new(State {
-                project_root: project_root.as_ref().to_path_buf(),
+        Self::with_runner(project_root, command, Arc::new(SystemRunner), tx)
+    }
+
+    pub fn with_runner<P: AsRef<Path>, S: Into<EventSender>>(
+        project_root: P,
+        command: &str,
+        process: Arc<dyn ProcessRunner>,
+        tx: S,
+    ) -> Result<Self, String> {
+        let project_root = check_dir(project_root.as_ref())?;
+        let running = Arc::new(Mutex::new(Running::default()));
+        let core = Core {
+            project_root,
+            command: command.to_string(),
+            env: vec![],
+            parser: Arc::new(RustcParser),
+            debounce: DEBOUNCE,
+            build_hooks: true,
+            post_processor: None,
+            post_build: vec![],
+            max_diagnostics: cargo::MAX_DIAGNOSTICS,
+            build_timeout: None,
+            success_policy: None,
+            respect_ignores: true,
+            ignores: IgnoreRules::default(),
+            log_runs: false,
+            process,
+            tx: tx.into(),
+            last_events: vec![],
+            rustc_version: None,
+            toolchain_picked: false,
+            pipeline_sent: false,
+            extra_roots: vec![],
+            path_deps_read: false,
+            git_dir: None,
+            head: None,
+            fs_watcher: None,
+            server: None,
+            lsp_server: None,
+            build_queue: None,
+            cancel: None,
+            running: running.clone(),
+            held: false,
+            ac_power_only: false,
+        };
+
+        let (commands, rx) = mpsc::channel();
+        let tx = core.tx.clone();
+        thread::Builder::new()
+            .name("watcher".to_string())
+            .spawn(move || {
+                // a panic is reported like an error, rather than leaving
+                // the watch to look like it's still going
+                let err = match panic::catch_unwind(AssertUnwindSafe(|| core.run(rx))) {
+                    Ok(Ok(())) => return,
+                    Ok(Err(err)) => err,
+                    Err(panic) => panic_message(panic.as_ref()),
+                };
+                let _ = tx.send(BuildEvent::Stopped(err));
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(Watcher { commands, running })
+    }
+
+    // Starts watching the file system, compiling once right away.
+    pub fn start(&mut self) -> Result<(), String> {
+        self.send(Command::Start(self.commands.clone()))
+    }
+
+    // Asks the watcher thread to exit. It does so right away, or once the
+    // compile that is currently running finishes.
+    pub fn stop(&mut self) {
+        let _ = self.send(Command::Stop);
+    }
+
+    // Like `stop`, but kills the compile that is currently running rather
+    // than waiting for it, e.g. as the app quits. Nothing queued is built.
+    // Returns once the compile is gone, so that it doesn't outlive the app.
+    pub fn abort(&mut self) {
+        {
+            let mut running = self.running.lock().unwrap();
+            running.aborted = true;
+            if let Some(cancel) = running.cancel.as_ref() {
+                cancel.store(true, Ordering::SeqCst);
+            }
+        }
+        self.stop();
+        let deadline = Instant::now() + ABORT_WAIT;
+        while self.running.lock().unwrap().cancel.is_some() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // Runs a compile as if `events` had been reported by the file system
+    // watcher. An empty list stands for a manual trigger. Returns `false` once
+    // the watcher has been asked to stop.
+    pub fn inject(&self, events: &[ChangeEvent]) -> Result<bool, String> {
+        let (reply, outcome) = mpsc::channel();
+        if self.send(Command::Trigger(events.to_vec(), reply)).is_err() {
+            return Ok(false);
+        }
+
+        // the thread drops the reply channel unanswered when it has stopped
+        match outcome.recv() {
+            Ok(outcome) => outcome.map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    // Variables to set for the compile command.
+    pub fn set_env(&self, env: Vec<(String, String)>) {
+        let _ = self.send(Command::Update(Update::Env(env)));
+    }
+
+    pub fn set_command(&self, command: &str) {
+        let _ = self.send(Command::Update(Update::Command(command.to_string())));
+    }
+
+    // How the command's output is read, and which files are watched.
+    pub fn set_parser(&self, parser: Arc<dyn Parser>) {
+        let _ = self.send(Command::Update(Update::Parser(parser)));
+    }
+
+    // Moves the watch to another directory, without compiling until something
+    // changes there.
+    pub fn set_project_root<P: AsRef<Path>>(&self, project_root: P) -> Result<(), String> {
+        let project_root = check_dir(project_root.as_ref())?;
+        self.send(Command::Update(Update::ProjectRoot(project_root)))
+    }
+
+    pub fn set_debounce(&self, debounce: Duration) {
+        let _ = self.send(Command::Update(Update::Debounce(debounce)));
+    }
+
+    // Whether builds run the hooks for the compiler wrapper cargo goes
+    // through, e.g. to ask sccache for its statistics. They do unless told
+    // otherwise.
+    pub fn set_build_hooks(&self, enabled: bool) {
+        let _ = self.send(Command::Update(Update::BuildHooks(enabled)));
+    }
+
+    // The script the diagnostics go through before they're sent, if any.
+    pub fn set_post_processor(&self, post_processor: Option<PostProcessor>) {
+        let _ = self.send(Command::Update(Update::PostProcessor(post_processor)));
+    }
+
+    // The hooks run after each build, once its results are sent. Their
+    // failures are sent as `BuildEvent::HookFailed`.
+    pub fn set_post_build(&self, hooks: Vec<Hook>) {
+        let _ = self.send(Command::Update(Update::PostBuild(hooks)));
+    }
+
+    // How many of a build's diagnostics are sent, `cargo::MAX_DIAGNOSTICS`
+    // unless told otherwise; the rest are only counted.
+    pub fn set_max_diagnostics(&self, max: usize) {
+        let _ = self.send(Command::Update(Update::MaxDiagnostics(max)));
+    }
+
+    // How long the command may run before it's stopped, and the build fails
+    // with an error saying so, if there's a limit.
+    pub fn set_build_timeout(&self, timeout: Option<Duration>) {
+        let _ = self.send(Command::Update(Update::BuildTimeout(timeout)));
+    }
+
+    // What decides whether a build passed, if more than the command's exit
+    // status.
+    pub fn set_success_policy(&self, policy: Option<SuccessPolicy>) {
+        let _ = self.send(Command::Update(Update::SuccessPolicy(policy)));
+    }
+
+    // Whether changes to files the project's `.gitignore` and `.ignore`
+    // files leave out are skipped. They are unless told otherwise.
+    pub fn set_respect_ignores(&self, enabled: bool) {
+        let _ = self.send(Command::Update(Update::RespectIgnores(enabled)));
+    }
+
+    // Whether each build is appended to the project's `RunLog`. They aren't
+    // unless asked for.
+    pub fn set_log_runs(&self, enabled: bool) {
+        let _ = self.send(Command::Update(Update::LogRuns(enabled)));
+    }
+
+    // Where results are published as well as sent back, if anywhere.
+    pub fn set_server(&self, server: Option<Server>) {
+        let _ = self.send(Command::Update(Update::Server(server)));
+    }
+
+    // Where editors are sent the results' diagnostics, if anywhere.
+    pub fn set_lsp_server(&self, lsp_server: Option<LspServer>) {
+        let _ = self.send(Command::Update(Update::LspServer(lsp_server)));
+    }
+
+    // Builds wait for their turn in `build_queue`; `None` builds
+    // right away.
+    pub fn set_build_queue(&self, build_queue: Option<BuildQueue>) {
+        let _ = self.send(Command::Update(Update::BuildQueue(build_queue)));
+    }
+
+    // Builds wait while held, e.g. while the window is in the background,
+    // and go once it's let go. Triggers still build right away.
+    pub fn set_held(&self, held: bool) {
+        let _ = self.send(Command::Update(Update::Held(held)));
+    }
+
+    // Builds wait while the machine runs on battery, see `power::on_battery`.
+    pub fn set_ac_power_only(&self, enabled: bool) {
+        let _ = self.send(Command::Update(Update::AcPowerOnly(enabled)));
+    }
+
+    // The events that triggered the most recent compile.
+    pub fn last_events(&self) -> Vec<ChangeEvent> {
+        let (reply, events) = mpsc::channel();
+        match self.send(Command::LastEvents(reply)) {
+            Ok(()) => events.recv().unwrap_or_default(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn send(&self, command: Command) -> Result<(), String> {
+        self.commands
+            .send(command)
+            .map_err(|_| "The watcher has stopped.".to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Pending,
+    Running,
+    Passed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_done(self) -> bool {
+        matches!(
+            self,
+            JobState::Passed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+// A build in the build queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Job {
+    pub id: u64,
+    pub project_root: PathBuf,
+    pub command: String,
+    pub state: JobState,
+    pub queued_at: SystemTime,
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+}
+
+// Whether there's more to `jobs` than a window's own build to look at: the
+// builds of other projects, or builds waiting their turn.
+pub fn worth_listing(jobs: &[Job]) -> bool {
+    let projects = jobs
+        .iter()
+        .map(|job| &job.project_root)
+        .collect::<HashSet<_>>();
+    projects.len() > 1 || jobs.iter().any(|job| job.state == JobState::Pending)
+}
+
+// The build queue of every watcher sharing it, letting `parallelism` builds
+// run at a time while the rest wait their turn in the order they came.
+// Without a limit every build runs right away, and is only kept track of.
+// The latest finished builds are kept to look back on. Clones share the same
+// queue.
+#[derive(Clone, Default)]
+pub struct BuildQueue {
+    shared: Arc<QueueShared>,
+}
+
+type QueueListener = Box<dyn Fn() + Send>;
+
+#[derive(Default)]
+struct QueueShared {
+    jobs: Mutex<Jobs>,
+    // told whenever a job is added, starts, finishes or goes away
+    listeners: Mutex<Vec<QueueListener>>,
+}
+
+#[derive(Default)]
+struct Jobs {
+    parallelism: Option<usize>,
+    next_id: u64,
+    // in the order they were queued
+    jobs: Vec<Job>,
+    // what stops each running build
+    cancels: HashMap<u64, Arc<AtomicBool>>,
+}
+
+impl BuildQueue {
+    pub fn new(parallelism: Option<usize>) -> Self {
+        let queue = BuildQueue::default();
+        queue.set_parallelism(parallelism);
+        queue
+    }
+
+    // At least one build runs at a time.
+    pub fn set_parallelism(&self, parallelism: Option<usize>) {
+        self.update(|jobs| jobs.parallelism = parallelism.map(|n| n.max(1)));
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.shared.jobs.lock().unwrap().jobs.clone()
+    }
+
+    // Takes a pending build out of the queue, or stops a running one.
+    pub fn cancel(&self, id: u64) {
+        self.update(|jobs| {
+            if let Some(cancel) = jobs.cancels.get(&id) {
+                cancel.store(true, Ordering::SeqCst);
+            }
+            if let Some(job) = jobs.jobs.iter_mut().find(|job| job.id == id) {
+                if job.state == JobState::Pending {
+                    job.state = JobState::Cancelled;
+                    job.finished_at = Some(SystemTime::now());
+                }
+            }
+        });
+    }
+
+    // Calls `listener` whenever the queue changes, on the thread changing it.
+    pub fn on_change<F: Fn() + Send + 'static>(&self, listener: F) {
+        self.shared
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(listener));
+    }
+
+    fn enqueue(&self, project_root: &Path, command: &str) -> Turn {
+        let id = self.update(|jobs| {
+            let id = jobs.next_id;
+            jobs.next_id += 1;
+            jobs.jobs.push(Job {
+                id,
+                project_root: project_root.to_path_buf(),
                 command: command.to_string(),
-                quit: false,
-                tx,
-                runner: None,
-            })),
-        })
+                state: JobState::Pending,
+                queued_at: SystemTime::now(),
+                started_at: None,
+                finished_at: None,
+            });
+            id
+        });
+        Turn {
+            queue: self.clone(),
+            id,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Changes the queue, and then tells the listeners, outside of the lock
+    // so that they can look at it.
+    fn update<T, F: FnOnce(&mut Jobs) -> T>(&self, change: F) -> T {
+        let changed = {
+            let mut jobs = self.shared.jobs.lock().unwrap();
+            let changed = change(&mut jobs);
+            jobs.forget_finished();
+            changed
+        };
+        self.notify();
+        changed
+    }
+
+    fn notify(&self) {
+        for listener in self.shared.listeners.lock().unwrap().iter() {
+            listener();
+        }
+    }
+}
+
+impl Jobs {
+    fn forget_finished(&mut self) {
+        let finished = self.jobs.iter().filter(|job| job.state.is_done()).count();
+        let mut forget = finished.saturating_sub(KEPT_JOBS);
+        self.jobs.retain(|job| {
+            let kept = forget == 0 || !job.state.is_done();
+            if !kept {
+                forget -= 1;
+            }
+            kept
+        });
+    }
+
+    // Whether the pending job `index` is next in line and there's room for
+    // it.
+    fn may_start(&self, index: usize) -> bool {
+        let running = self
+            .jobs
+            .iter()
+            .filter(|job| job.state == JobState::Running)
+            .count();
+        let first = self.jobs[..index]
+            .iter()
+            .all(|job| job.state != JobState::Pending);
+        first
+            && match self.parallelism {
+                Some(limit) => running < limit,
+                None => true,
+            }
+    }
+}
+
+// Where a watcher's next build is at in the build queue.
+enum TurnState {
+    Ready,
+    Waiting,
+    Cancelled,
+}
+
+// A watcher's place in the build queue for its next build. Dropping it
+// before the build is done, e.g. as the watcher stops, cancels the job.
+struct Turn {
+    queue: BuildQueue,
+    id: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Turn {
+    // Starts the build if it's next in line and there's room for it.
+    fn take(&self) -> TurnState {
+        {
+            let mut jobs = self.queue.shared.jobs.lock().unwrap();
+            let index = match jobs.jobs.iter().position(|job| job.id == self.id) {
+                Some(index) => index,
+                None => return TurnState::Cancelled,
+            };
+            match jobs.jobs[index].state {
+                JobState::Running => return TurnState::Ready,
+                JobState::Pending if jobs.may_start(index) => {
+                    jobs.cancels.insert(self.id, self.cancel.clone());
+                    let job = &mut jobs.jobs[index];
+                    job.state = JobState::Running;
+                    job.started_at = Some(SystemTime::now());
+                }
+                JobState::Pending => return TurnState::Waiting,
+                _ => return TurnState::Cancelled,
+            }
+        }
+        self.queue.notify();
+        TurnState::Ready
     }
 
-    pub fn start(&mut self) {
-        let this = self.clone();
-        self.state.write().unwrap().runner = Some(thread::spawn(move || {
-            watchexec::watch(&this).unwrap();
-        }));
+    fn finish(&self, state: JobState) {
+        let state = if self.cancel.load(Ordering::SeqCst) {
+            JobState::Cancelled
+        } else {
+            state
+        };
+        self.queue.update(|jobs| {
+            jobs.cancels.remove(&self.id);
+            if let Some(job) = jobs.jobs.iter_mut().find(|job| job.id == self.id) {
+                if !job.state.is_done() {
+                    job.state = state;
+                    job.finished_at = Some(SystemTime::now());
+                }
+            }
+        });
     }
+}
 
-    pub fn try_stop(&mut self) {
-        self.state.write().unwrap().quit = true;
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.finish(JobState::Cancelled);
     }
+}
+
+// Whether a version control tool is in the middle of changing the tree
+// `project_root` is in, going by the lock it holds while it does.
+pub fn vcs_busy(project_root: &Path) -> bool {
+    project_root
+        .ancestors()
+        .any(|dir| VCS_LOCKS.iter().any(|lock| dir.join(lock).exists()))
+}
+
+fn is_editor_file(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    name == VIM_PROBE
+        || EDITOR_PREFIXES.iter().any(|p| name.starts_with(p))
+        || EDITOR_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+// The files behind `events`, relative to the project root when they're in it,
+// each listed once.
+fn changed_files(project_root: &Path, events: &[ChangeEvent]) -> Vec<String> {
+    let mut files: Vec<String> = vec![];
+    for event in events {
+        let path = event.renamed_to.as_ref().unwrap_or(&event.path);
+        let file = path
+            .strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+// Every change in `events` once, to tell what keeps starting builds when
+// something, like a build script writing into the project, does.
+fn path_ops(project_root: &Path, events: &[ChangeEvent]) -> Vec<PathOp> {
+    let relative = |path: &Path| {
+        path.strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let mut ops: Vec<PathOp> = vec![];
+    for event in events {
+        let op = PathOp {
+            path: relative(&event.path),
+            op: match event.kind {
+                ChangeKind::Create => Op::Created,
+                ChangeKind::Modify => Op::Modified,
+                ChangeKind::Remove => Op::Removed,
+                ChangeKind::Rename => Op::Renamed,
+                ChangeKind::Other => Op::Changed,
+            },
+            renamed_to: event.renamed_to.as_deref().map(relative),
+        };
+        if !ops.contains(&op) {
+            ops.push(op);
+        }
+    }
+    ops
+}
 
-    fn run(&self) -> Result<CompileResult, String> {
-        cargo::run(
-            &self.state.read().unwrap().project_root,
-            &self.state.read().unwrap().command,
+// The toolchain `env` has rustup build with, over what it would pick itself.
+fn picked_toolchain(env: &[(String, String)]) -> Option<&str> {
+    env.iter()
+        .rev()
+        .find(|(name, _)| name == toolchain::TOOLCHAIN_VAR)
+        .map(|(_, toolchain)| toolchain.as_str())
+}
+
+// What went wrong watching the file system, with what to do about running
+// out of inotify watches, which large trees on Linux do.
+pub fn watch_error(err: &notify::Error) -> String {
+    if is_out_of_watches(err) {
+        format!(
+            "The system's limit on inotify watches was reached ({}). Raise it with \
+             `sudo sysctl fs.inotify.max_user_watches=524288`, and in a file in \
+             /etc/sysctl.d to keep it after a reboot, or close other programs \
+             watching files.",
+            err
         )
+    } else {
+        format!("{:?}", err)
     }
 }
 
-impl Handler for Watcher {
-    fn on_manual(&self) -> WatchResult<bool> {
-        if self.state.read().unwrap().quit {
-            return Ok(false);
+// Whether the system ran out of inotify watches, after which changes may go
+// unnoticed; other errors are about a single change or path.
+pub fn is_out_of_watches(err: &notify::Error) -> bool {
+    match &err.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        notify::ErrorKind::Io(err) => err.raw_os_error() == Some(ENOSPC),
+        _ => false,
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message");
+    format!("The watcher crashed: {}", message)
+}
+
+fn check_dir(project_root: &Path) -> Result<PathBuf, String> {
+    if !project_root.is_dir() {
+        return Err(format!("{} is not a directory.", project_root.display()));
+    }
+    Ok(project_root.to_path_buf())
+}
+
+// Everything the watcher thread owns.
+struct Core {
+    project_root: PathBuf,
+    command: String,
+    env: Vec<(String, String)>,
+    parser: Arc<dyn Parser>,
+    debounce: Duration,
+    build_hooks: bool,
+    post_processor: Option<PostProcessor>,
+    post_build: Vec<Hook>,
+    max_diagnostics: usize,
+    build_timeout: Option<Duration>,
+    success_policy: Option<SuccessPolicy>,
+    respect_ignores: bool,
+    // read once watching starts, and again when an ignore file changes
+    ignores: IgnoreRules,
+    log_runs: bool,
+    process: Arc<dyn ProcessRunner>,
+    tx: EventSender,
+    last_events: Vec<ChangeEvent>,
+    rustc_version: Option<String>,
+    // another toolchain was picked through the environment since the last
+    // build
+    toolchain_picked: bool,
+    // whether the build's crate graph has been sent since the manifests or
+    // the command last changed
+    pipeline_sent: bool,
+    // the path dependencies' directories being watched, and whether they've
+    // been read since the manifests, the parser or the project root changed
+    extra_roots: Vec<PathBuf>,
+    path_deps_read: bool,
+    // the repository the project is in, if any, and what its HEAD was on as
+    // of the latest build
+    git_dir: Option<PathBuf>,
+    head: Option<String>,
+    // only there once watching has started
+    fs_watcher: Option<Box<dyn notify::Watcher + Send>>,
+    server: Option<Server>,
+    lsp_server: Option<LspServer>,
+    build_queue: Option<BuildQueue>,
+    // what the build queue or `Watcher::abort` stops the running build with
+    cancel: Option<Arc<AtomicBool>>,
+    running: Arc<Mutex<Running>>,
+    held: bool,
+    ac_power_only: bool,
+}
+
+// Changes waiting to be compiled. Whatever comes in while a compile runs
+// piles up here too, and gets a single compile once it's done rather than one
+// per change.
+#[derive(Default)]
+struct Queue {
+    events: Vec<ChangeEvent>,
+    // injected triggers, answered once the next compile is done
+    replies: Vec<mpsc::Sender<Result<(), String>>>,
+    // git checked out something else or moved the branch
+    head_moved: bool,
+    // when the file system will have been quiet for long enough
+    deadline: Option<Instant>,
+    // the place in the build queue, once it's due
+    turn: Option<Turn>,
+}
+
+impl Queue {
+    // triggers compile right away, file changes once they've settled
+    fn is_due(&self) -> bool {
+        !self.replies.is_empty()
+            || self
+                .deadline
+                .map(|deadline| deadline <= Instant::now())
+                .unwrap_or(false)
+    }
+
+    // Drops what was queued once its build is taken out of the build queue.
+    fn cancel(&mut self) {
+        self.turn = None;
+        self.events.clear();
+        self.head_moved = false;
+        self.deadline = None;
+        for reply in self.replies.drain(..) {
+            let _ = reply.send(Err("The build was cancelled.".to_string()));
         }
+    }
+}
 
-        self.run()
-            .and_then(|results| {
-                self.state
-                    .read()
-                    .unwrap()
-                    .tx
-                    .send(results)
-                    .map_err(|e| format!("{:?}", e))
-            })
-            .map(|_| true)
-            .map_err(|err| WatchError::Io(IoError::new(IoErrorKind::Other, format!("{:?}", err))))
+impl Core {
+    fn run(mut self, commands: mpsc::Receiver<Command>) -> Result<(), String> {
+        let mut queue = Queue::default();
+        loop {
+            if queue.is_due() {
+                // a checkout or rebase leaves the tree half done until it
+                // finishes, so changes wait for it to build once; only
+                // triggers go ahead
+                if queue.replies.is_empty() && vcs_busy(&self.project_root) {
+                    queue.deadline = Some(Instant::now() + self.debounce);
+                    continue;
+                }
+                // as do changes the build policy holds back, until it lets
+                // them go
+                if queue.replies.is_empty() && self.is_held() {
+                    queue.deadline = Some(Instant::now() + HOLD_POLL);
+                    continue;
+                }
+                match self.take_turn(&mut queue) {
+                    TurnState::Ready => {}
+                    TurnState::Waiting => {
+                        match commands.recv_timeout(TURN_POLL) {
+                            Ok(command) => {
+                                if !self.handle(command, &mut queue)? {
+                                    return Ok(());
+                                }
+                            }
+                            Err(RecvTimeoutError::Timeout) => {}
+                            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                        }
+                        continue;
+                    }
+                    TurnState::Cancelled => {
+                        queue.cancel();
+                        continue;
+                    }
+                }
+                self.build_queued(&mut queue)?;
+                // take in everything that came while compiling before
+                // deciding on the next compile
+                loop {
+                    match commands.try_recv() {
+                        Ok(command) => {
+                            if !self.handle(command, &mut queue)? {
+                                return Ok(());
+                            }
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return Ok(()),
+                    }
+                }
+                continue;
+            }
+
+            // wait for the next command, or until the file system has been
+            // quiet for long enough to compile what changed
+            let command = match queue.deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match commands.recv_timeout(timeout) {
+                        Ok(command) => command,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                    }
+                }
+                None => match commands.recv() {
+                    Ok(command) => command,
+                    Err(_) => return Ok(()),
+                },
+            };
+            if !self.handle(command, &mut queue)? {
+                return Ok(());
+            }
+        }
     }
 
-    fn on_update(&self, _ops: &[PathOp]) -> WatchResult<bool> {
-        self.on_manual()
+    // Returns `false` once the watcher should stop.
+    fn handle(&mut self, command: Command, queue: &mut Queue) -> Result<bool, String> {
+        match command {
+            Command::Start(sender) => {
+                self.watch(sender)?;
+                // run once before waiting for changes
+                queue.deadline = Some(Instant::now());
+            }
+            Command::Stop => return Ok(false),
+            Command::Trigger(events, reply) => {
+                queue.events.extend(events);
+                queue.replies.push(reply);
+            }
+            Command::Changed(Err(err)) if is_out_of_watches(&err) => {
+                return Err(watch_error(&err));
+            }
+            // e.g. a file that went away before it could be looked at
+            Command::Changed(Err(err)) => self.warn(watch_error(&err)),
+            Command::Changed(Ok(event)) => {
+                let events = ChangeEvent::from_event(&event);
+                // between the steps of a rebase there's a moment without a
+                // lock, which shouldn't be taken for the end of it
+                if events.iter().any(ChangeEvent::is_vcs_lock) && queue.deadline.is_some() {
+                    queue.deadline = Some(Instant::now() + self.debounce);
+                }
+                // a checkout or pull may not leave a change to any source
+                // that gets through, depending on how git writes them
+                if events.iter().any(|e| self.moves_head(e)) {
+                    queue.head_moved = true;
+                    queue.deadline = Some(Instant::now() + self.debounce);
+                }
+                if events.iter().any(|e| e.touches_ignore_file()) {
+                    self.load_ignores();
+                }
+                let events = events
+                    .into_iter()
+                    .filter(|e| e.is_relevant(&self.project_root, self.parser.as_ref()))
+                    .filter(|e| !e.is_ignored_by(&self.ignores))
+                    .collect::<Vec<_>>();
+                if !events.is_empty() {
+                    queue.events.extend(events);
+                    queue.deadline = Some(Instant::now() + self.debounce);
+                }
+            }
+            Command::Update(update) => self.update(update)?,
+            Command::LastEvents(reply) => {
+                let _ = reply.send(self.last_events.clone());
+            }
+        }
+
+        Ok(true)
     }
 
-    fn args(&self) -> Args {
-        ArgsBuilder::default()
-            .paths(vec![self.state.read().unwrap().project_root.clone()])
-            .cmd(vec![self.state.read().unwrap().command.clone()])
-            .filters(vec!["**/*.toml".to_owned(), "**/*.rs".to_owned()])
-            .debounce(500_u64)
-            .run_initially(true)
-            .build()
-            .unwrap()
+    // Compiles everything that's queued in one go. A build that fails is
+    // reported and the watch carries on; only the watcher failing stops it.
+    fn build_queued(&mut self, queue: &mut Queue) -> Result<(), String> {
+        queue.deadline = None;
+        let head_moved = mem::take(&mut queue.head_moved);
+        let turn = queue.turn.take();
+        let cancel = match turn.as_ref() {
+            Some(turn) => turn.cancel.clone(),
+            None => Arc::new(AtomicBool::new(false)),
+        };
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.aborted {
+                queue.cancel();
+                return Ok(());
+            }
+            running.cancel = Some(cancel.clone());
+        }
+        self.cancel = Some(cancel);
+        let outcome = self.build(mem::take(&mut queue.events), head_moved);
+        self.cancel = None;
+        self.running.lock().unwrap().cancel = None;
+        if let Some(turn) = turn {
+            turn.finish(match outcome {
+                Ok(Ok(true)) => JobState::Passed,
+                _ => JobState::Failed,
+            });
+        }
+        let outcome = outcome?.map(|_| ());
+        for reply in mem::take(&mut queue.replies) {
+            let _ = reply.send(outcome.clone());
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, update: Update) -> Result<(), String> {
+        match update {
+            Update::ProjectRoot(project_root) => {
+                let git_watches = self.git_watches();
+                if let Some(fs_watcher) = self.fs_watcher.as_mut() {
+                    fs_watcher
+                        .unwatch(&self.project_root)
+                        .map_err(|e| format!("{:?}", e))?;
+                    for (path, _) in git_watches {
+                        let _ = fs_watcher.unwatch(&path);
+                    }
+                    // the new project's are read with its first build
+                    for root in self.extra_roots.iter() {
+                        let _ = fs_watcher.unwatch(root);
+                    }
+                    fs_watcher
+                        .watch(&project_root, RecursiveMode::Recursive)
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+                if !mem::take(&mut self.extra_roots).is_empty() {
+                    let _ = self.send(BuildEvent::ExtraRoots(vec![]));
+                }
+                self.project_root = project_root;
+                self.watch_git();
+                self.load_ignores();
+                // another project may pin another toolchain
+                self.rustc_version = None;
+                self.pipeline_sent = false;
+                self.path_deps_read = false;
+            }
+            Update::Command(command) => {
+                self.command = command;
+                self.pipeline_sent = false;
+            }
+            Update::Env(env) => {
+                self.toolchain_picked |= picked_toolchain(&env) != picked_toolchain(&self.env);
+                self.env = env;
+                self.pipeline_sent = false;
+            }
+            Update::Parser(parser) => {
+                self.parser = parser;
+                self.path_deps_read = false;
+            }
+            Update::Debounce(debounce) => self.debounce = debounce,
+            Update::BuildHooks(enabled) => self.build_hooks = enabled,
+            Update::PostProcessor(post_processor) => self.post_processor = post_processor,
+            Update::PostBuild(hooks) => self.post_build = hooks,
+            Update::MaxDiagnostics(max) => self.max_diagnostics = max,
+            Update::BuildTimeout(timeout) => self.build_timeout = timeout,
+            Update::SuccessPolicy(policy) => self.success_policy = policy,
+            Update::RespectIgnores(enabled) => {
+                if enabled != self.respect_ignores {
+                    self.respect_ignores = enabled;
+                    self.load_ignores();
+                }
+            }
+            Update::LogRuns(enabled) => self.log_runs = enabled,
+            Update::Server(server) => self.server = server,
+            Update::LspServer(lsp_server) => self.lsp_server = lsp_server,
+            Update::BuildQueue(build_queue) => self.build_queue = build_queue,
+            Update::Held(held) => self.held = held,
+            Update::AcPowerOnly(enabled) => self.ac_power_only = enabled,
+        }
+
+        Ok(())
+    }
+
+    fn watch(&mut self, sender: mpsc::Sender<Command>) -> Result<(), String> {
+        let handler = move |event: notify::Result<Event>| {
+            let _ = sender.send(Command::Changed(event));
+        };
+        // the document portal only passes on changes made from inside the
+        // sandbox, so a folder it shows a Flatpak is looked at every so often
+        // instead
+        let mut fs_watcher: Box<dyn notify::Watcher + Send> =
+            if sandbox::is_document(&self.project_root) {
+                let config = notify::Config::default().with_poll_interval(POLL_INTERVAL);
+                Box::new(PollWatcher::new(handler, config).map_err(|e| watch_error(&e))?)
+            } else {
+                Box::new(notify::recommended_watcher(handler).map_err(|e| watch_error(&e))?)
+            };
+        fs_watcher
+            .watch(&self.project_root, RecursiveMode::Recursive)
+            .map_err(|e| watch_error(&e))?;
+        self.fs_watcher = Some(fs_watcher);
+        self.watch_git();
+        self.load_ignores();
+
+        Ok(())
+    }
+
+    // Reads the project's ignore rules, if they're respected and it's being
+    // watched; the walk through the tree isn't worth it otherwise.
+    fn load_ignores(&mut self) {
+        self.ignores = if self.respect_ignores && self.fs_watcher.is_some() {
+            let mut ignores = IgnoreRules::load(&self.project_root);
+            for root in self.extra_roots.iter() {
+                ignores.add(root);
+            }
+            ignores
+        } else {
+            IgnoreRules::default()
+        };
+        for error in self.ignores.errors.clone() {
+            self.warn(error);
+        }
+    }
+
+    // Finds the project's repository and, when it's outside of the project
+    // root, watches where git keeps HEAD and the branches too. It's only
+    // followed while watching, but found either way.
+    fn watch_git(&mut self) {
+        // compared with the paths the file system watcher reports, which
+        // are absolute
+        self.git_dir = git::git_dir(&self.project_root).and_then(|dir| dir.canonicalize().ok());
+        self.head = self.git_dir.as_deref().and_then(git::head);
+        let paths = self.git_watches();
+        let mut warnings = vec![];
+        if let Some(fs_watcher) = self.fs_watcher.as_mut() {
+            for (path, mode) in paths {
+                if let Err(err) = fs_watcher.watch(&path, mode) {
+                    warnings.push(format!(
+                        "Failed to watch {}: {}",
+                        path.display(),
+                        watch_error(&err)
+                    ));
+                }
+            }
+        }
+        for warning in warnings {
+            self.warn(warning);
+        }
+    }
+
+    // The repository and its branches, which git replaces HEAD and the refs
+    // in rather than writing to them, unless the project root's watch already
+    // has them.
+    fn git_watches(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let project_root = self
+            .project_root
+            .canonicalize()
+            .unwrap_or_else(|_| self.project_root.clone());
+        match self.git_dir.as_ref() {
+            Some(git_dir) if !git_dir.starts_with(&project_root) => vec![
+                (git_dir.clone(), RecursiveMode::NonRecursive),
+                (git_dir.join("refs").join("heads"), RecursiveMode::Recursive),
+            ],
+            _ => vec![],
+        }
+    }
+
+    // Whether `event` is git changing what HEAD is on, or moving the branch
+    // it's on.
+    fn moves_head(&self, event: &ChangeEvent) -> bool {
+        let git_dir = match self.git_dir.as_ref() {
+            Some(git_dir) => git_dir,
+            None => return false,
+        };
+        let branch = self
+            .head
+            .as_ref()
+            .map(|head| git_dir.join("refs").join("heads").join(head));
+        let moves = |path: &Path| path == git_dir.join("HEAD") || Some(path) == branch.as_deref();
+        moves(&event.path) || event.renamed_to.as_deref().map(moves).unwrap_or(false)
+    }
+
+    fn is_held(&self) -> bool {
+        self.held || (self.ac_power_only && power::on_battery())
+    }
+
+    // Whether the build could start, queueing it if it hasn't been yet.
+    fn take_turn(&self, queue: &mut Queue) -> TurnState {
+        let build_queue = match self.build_queue.as_ref() {
+            Some(build_queue) => build_queue,
+            None => return TurnState::Ready,
+        };
+        queue
+            .turn
+            .get_or_insert_with(|| build_queue.enqueue(&self.project_root, &self.command))
+            .take()
+    }
+
+    // Whether the build passed, or why it couldn't be run or read, e.g. for
+    // output that isn't UTF-8, which is reported as a build with that as its
+    // error. The outer error is the watcher's own, e.g. for a closed channel.
+    fn build(
+        &mut self,
+        events: Vec<ChangeEvent>,
+        head_moved: bool,
+    ) -> Result<Result<bool, String>, String> {
+        self.last_events = events;
+        self.send(BuildEvent::Started)?;
+        let mut results = match self.compile() {
+            Ok(results) => results,
+            Err(err) => {
+                self.send(BuildEvent::Finished(CompileResult::failed(&err)))?;
+                return Ok(Err(err));
+            }
+        };
+        if let Some(post_processor) = self.post_processor.as_ref() {
+            if let Err(err) = post_processor.apply(&self.project_root, &self.env, &mut results) {
+                results.post_process_error = Some(err);
+            }
+        }
+        // before trimming, so that every diagnostic counts
+        if let Some(policy) = self.success_policy.as_ref() {
+            policy.apply(&mut results);
+        }
+        // trimmed here, so that neither the channel nor anything the
+        // results are published to has to take them whole
+        results.truncate(self.max_diagnostics);
+        results.triggered_by = changed_files(&self.project_root, &self.last_events);
+        results.trigger_ops = path_ops(&self.project_root, &self.last_events);
+
+        let head = self.git_dir.as_deref().and_then(git::head);
+        if head_moved {
+            results.git_trigger = head.clone().map(|head| {
+                if Some(&head) == self.head.as_ref() {
+                    GitTrigger::Moved { branch: head }
+                } else {
+                    GitTrigger::Switched { to: head }
+                }
+            });
+        }
+        self.head = head;
+        if self.log_runs {
+            if let Err(err) = RunLog::new(&self.project_root).record(&self.command, &results) {
+                self.warn(format!("Failed to log the run: {}", err));
+            }
+        }
+        let success = results.success;
+        // the hooks run once the results are out, so as not to hold them up
+        let report =
+            (!self.post_build.is_empty()).then(|| Report::new(&self.project_root, &results));
+        self.send(BuildEvent::Finished(results))?;
+        if let Some(report) = report {
+            for hook in self.post_build.iter() {
+                if let Err(err) = hook.run(&self.project_root, &self.env, &report) {
+                    self.send(BuildEvent::HookFailed(err))?;
+                }
+            }
+        }
+        Ok(Ok(success))
+    }
+
+    fn compile(&mut self) -> Result<CompileResult, String> {
+        let toolchain_touched = mem::take(&mut self.toolchain_picked)
+            || self.last_events.iter().any(ChangeEvent::touches_toolchain);
+        let previous_version = self.rustc_version.clone();
+
+        // rustup only switches compilers when a toolchain file changes, and
+        // asking for the version is what makes it install a missing one;
+        // other tools don't use rust's toolchains at all
+        let rustc_version = if !self.parser.is_rust() {
+            None
+        } else if previous_version.is_none() || toolchain_touched {
+            let channel = picked_toolchain(&self.env)
+                .map(ToString::to_string)
+                .or_else(|| toolchain::pinned_channel(&self.project_root));
+            if let Some(channel) = channel {
+                if !toolchain::is_installed_with(
+                    self.process.as_ref(),
+                    &self.project_root,
+                    &channel,
+                    &self.env,
+                ) {
+                    self.send(BuildEvent::InstallingToolchain(channel))?;
+                }
+            }
+            toolchain::version_with(self.process.as_ref(), &self.project_root, &self.env).ok()
+        } else {
+            previous_version.clone()
+        };
+
+        // other tools don't go through cargo's compiler wrappers either
+        let hooks = if self.parser.is_rust() && self.build_hooks {
+            wrapper::hooks(&self.process, &self.project_root, &self.env)
+        } else {
+            vec![]
+        };
+        if self.parser.is_rust() && cargo::streams_json(&self.command) {
+            self.send_pipeline();
+        }
+        self.watch_path_deps();
+
+        let stoppable;
+        let runner: &dyn ProcessRunner = if self.build_timeout.is_some() || self.cancel.is_some() {
+            stoppable = Stoppable {
+                inner: self.process.as_ref(),
+                stop: Stop {
+                    timeout: self.build_timeout,
+                    cancel: self.cancel.clone(),
+                },
+            };
+            &stoppable
+        } else {
+            self.process.as_ref()
+        };
+        let tx = &self.tx;
+        let mut result = cargo::run_with_progress(
+            runner,
+            &self.project_root,
+            &self.command,
+            &self.env,
+            self.parser.as_ref(),
+            &hooks,
+            |progress| {
+                let _ = tx.send(match progress {
+                    Progress::Fetch(progress) => BuildEvent::Fetching(progress.clone()),
+                    Progress::Build(progress) => BuildEvent::Compiling(progress.clone()),
+                });
+            },
+        )?;
+        result.toolchain_changed = previous_version.is_some()
+            && rustc_version.is_some()
+            && rustc_version != previous_version;
+        result.rustc_version = rustc_version.clone();
+        self.rustc_version = rustc_version;
+
+        Ok(result)
+    }
+
+    // Reads the crate graph again when a manifest changed, so that what's
+    // compiled can be followed on it.
+    fn send_pipeline(&mut self) {
+        if self.pipeline_sent && !self.last_events.iter().any(ChangeEvent::touches_manifest) {
+            return;
+        }
+        match Pipeline::load(self.process.as_ref(), &self.project_root, &self.env) {
+            Ok(pipeline) => {
+                self.pipeline_sent = self.send(BuildEvent::Pipeline(pipeline)).is_ok();
+            }
+            Err(err) => self.warn(format!("Failed to read the crate graph: {}", err)),
+        }
+    }
+
+    // Watches the directories of the project's path dependencies outside of
+    // it as well, reading them again when a manifest changed. Nothing's read
+    // until the file system is watched, or for other tools than cargo.
+    fn watch_path_deps(&mut self) {
+        if self.fs_watcher.is_none()
+            || (self.path_deps_read && !self.last_events.iter().any(ChangeEvent::touches_manifest))
+        {
+            return;
+        }
+        self.path_deps_read = true;
+        let roots = if self.parser.is_rust() {
+            match pathdeps::load(self.process.as_ref(), &self.project_root, &self.env) {
+                Ok(roots) => roots,
+                Err(err) => {
+                    self.warn(format!("Failed to read the path dependencies: {}", err));
+                    return;
+                }
+            }
+        } else {
+            vec![]
+        };
+        if roots == self.extra_roots {
+            return;
+        }
+
+        let watched = mem::take(&mut self.extra_roots);
+        let mut warnings = vec![];
+        if let Some(fs_watcher) = self.fs_watcher.as_mut() {
+            for root in watched.iter().filter(|root| !roots.contains(root)) {
+                let _ = fs_watcher.unwatch(root);
+            }
+            for root in roots.iter().filter(|root| !watched.contains(root)) {
+                if let Err(err) = fs_watcher.watch(root, RecursiveMode::Recursive) {
+                    warnings.push(format!(
+                        "Failed to watch {}: {}",
+                        root.display(),
+                        watch_error(&err)
+                    ));
+                }
+            }
+        }
+        for warning in warnings {
+            self.warn(warning);
+        }
+        self.extra_roots = roots;
+        self.load_ignores();
+        let _ = self.send(BuildEvent::ExtraRoots(self.extra_roots.clone()));
+    }
+
+    fn send(&self, event: BuildEvent) -> Result<(), String> {
+        if let BuildEvent::Finished(result) = &event {
+            if let Some(server) = self.server.as_ref() {
+                server.publish(result);
+            }
+            if let Some(lsp_server) = self.lsp_server.as_ref() {
+                lsp_server.publish(&self.project_root, result);
+            }
+        }
+        self.tx.send(event)
+    }
+
+    fn warn(&self, warning: String) {
+        let _ = self.send(BuildEvent::Warning(warning));
     }
 }
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cargo::{BuildHook, CompileResult};
+use crate::process::ProcessRunner;
+
+// where cargo looks for its config in every directory up from the project
+const CARGO_CONFIGS: &[&str] = &[".cargo/config.toml", ".cargo/config"];
+
+lazy_static! {
+    // e.g. "Cache hits                           5", leaving out the per
+    // language lines like "Cache hits (Rust)"
+    static ref REGEX_STAT: Regex =
+        Regex::new(r"^(Compile requests|Cache hits|Cache misses)\s+([0-9]+)\s*$").unwrap();
+}
+
+// The wrapper cargo runs rustc through for `project_root`, if any, e.g.
+// "sccache". Set in the environment the way cargo reads it, with `env`
+// over ours, or else in cargo's config.
+pub fn detect<P: AsRef<Path>>(project_root: P, env: &[(String, String)]) -> Option<String> {
+    let var = |name: &str| {
+        env.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.clone())
+            .or_else(|| std::env::var(name).ok())
+    };
+    var("RUSTC_WRAPPER")
+        .or_else(|| var("CARGO_BUILD_RUSTC_WRAPPER"))
+        .or_else(|| configured(project_root.as_ref()))
+        .filter(|wrapper| !wrapper.is_empty())
+}
+
+// `build.rustc-wrapper` from the nearest cargo config that sets it
+fn configured(project_root: &Path) -> Option<String> {
+    project_root.ancestors().find_map(|dir| {
+        CARGO_CONFIGS.iter().find_map(|name| {
+            let contents = fs::read_to_string(dir.join(name)).ok()?;
+            let config = contents.parse::<toml::Value>().ok()?;
+            config
+                .get("build")?
+                .get("rustc-wrapper")?
+                .as_str()
+                .map(ToString::to_string)
+        })
+    })
+}
+
+// Whether `wrapper` is sccache, however it's spelled out.
+pub fn is_sccache(wrapper: &str) -> bool {
+    Path::new(wrapper)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem == "sccache")
+        .unwrap_or(false)
+}
+
+// What a build did with sccache's cache. sccache itself counts from when its
+// server started, so these are the difference its counts made over a build.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub requests: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    // Reads the counts out of `sccache --show-stats`.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut stats = CacheStats::default();
+        let mut found = false;
+        for line in output.lines() {
+            if let Some(caps) = REGEX_STAT.captures(line.trim()) {
+                let count = caps[2].parse().ok()?;
+                match &caps[1] {
+                    "Compile requests" => stats.requests = count,
+                    "Cache hits" => stats.hits = count,
+                    _ => stats.misses = count,
+                }
+                found = true;
+            }
+        }
+        Some(stats).filter(|_| found)
+    }
+
+    // What was counted since `earlier`. A server that restarted in between
+    // counts from zero again.
+    pub fn since(&self, earlier: &CacheStats) -> Self {
+        if self.requests < earlier.requests {
+            return *self;
+        }
+        CacheStats {
+            requests: self.requests - earlier.requests,
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+        }
+    }
+
+    // in percent, `None` when nothing was looked up
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            None
+        } else {
+            Some(self.hits as f64 * 100.0 / lookups as f64)
+        }
+    }
+}
+
+// Asks sccache for its counts around every build.
+pub struct SccacheStats {
+    program: String,
+    runner: Arc<dyn ProcessRunner>,
+    before: Mutex<Option<CacheStats>>,
+}
+
+impl SccacheStats {
+    pub fn new(program: &str, runner: Arc<dyn ProcessRunner>) -> Self {
+        SccacheStats {
+            program: program.to_string(),
+            runner,
+            before: Mutex::new(None),
+        }
+    }
+
+    fn show_stats(&self, project_root: &Path, env: &[(String, String)]) -> Option<CacheStats> {
+        let output = self
+            .runner
+            .run(&self.program, &["--show-stats"], env, project_root)
+            .ok()
+            .filter(|output| output.success)?;
+        CacheStats::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+impl BuildHook for SccacheStats {
+    fn before_build(&self, project_root: &Path, env: &[(String, String)]) {
+        *self.before.lock().unwrap() = self.show_stats(project_root, env);
+    }
+
+    fn after_build(
+        &self,
+        project_root: &Path,
+        env: &[(String, String)],
+        result: &mut CompileResult,
+    ) {
+        let before = self.before.lock().unwrap().take();
+        result.cache_stats = match (before, self.show_stats(project_root, env)) {
+            (Some(before), Some(after)) => Some(after.since(&before)),
+            _ => None,
+        };
+    }
+}
+
+// The hooks for what `project_root` builds with: recording the wrapper, and
+// for sccache, its statistics.
+pub fn hooks(
+    runner: &Arc<dyn ProcessRunner>,
+    project_root: &Path,
+    env: &[(String, String)],
+) -> Vec<Box<dyn BuildHook>> {
+    let wrapper = match detect(project_root, env) {
+        Some(wrapper) => wrapper,
+        None => return vec![],
+    };
+    let mut hooks: Vec<Box<dyn BuildHook>> = vec![Box::new(Wrapper(wrapper.clone()))];
+    if is_sccache(&wrapper) {
+        hooks.push(Box::new(SccacheStats::new(&wrapper, runner.clone())));
+    }
+    hooks
+}
+
+struct Wrapper(String);
+
+impl BuildHook for Wrapper {
+    fn after_build(
+        &self,
+        _project_root: &Path,
+        _env: &[(String, String)],
+        result: &mut CompileResult,
+    ) {
+        result.compiler_wrapper = Some(self.0.clone());
+    }
+}
@@ -0,0 +1,30 @@
+use watch_rust_errors::ansi;
+
+const COLORED: &str = "\u{1b}[0m\u{1b}[1m\u{1b}[38;5;9merror[E0308]\u{1b}[0m\u{1b}[0m\u{1b}[1m: mismatched types\u{1b}[0m\n";
+
+#[test]
+fn escapes_are_stripped() {
+    assert_eq!(ansi::strip(COLORED), "error[E0308]: mismatched types\n");
+    assert_eq!(ansi::strip("plain"), "plain");
+}
+
+#[test]
+fn colors_become_spans() {
+    assert_eq!(
+        ansi::to_pango("\u{1b}[31mred\u{1b}[0m & <plain>"),
+        "<span foreground=\"#cc0000\">red</span> &amp; &lt;plain&gt;"
+    );
+    assert_eq!(
+        ansi::to_pango(COLORED),
+        "<span foreground=\"#ef2929\" weight=\"bold\">error[E0308]</span>\
+         <span weight=\"bold\">: mismatched types</span>\n"
+    );
+}
+
+#[test]
+fn unterminated_styles_are_closed() {
+    assert_eq!(
+        ansi::to_pango("\u{1b}[1;94mbold blue"),
+        "<span foreground=\"#729fcf\" weight=\"bold\">bold blue</span>"
+    );
+}
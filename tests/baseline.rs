@@ -0,0 +1,49 @@
+use watch_rust_errors::baseline::{self, Baseline};
+use watch_rust_errors::cargo::{self, CompileResult, DiagnosticId};
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+const UNUSED_X: &str = "warning: unused variable: `x`\n  --> src/main.rs:2:9\n\n";
+
+fn result(output: &str) -> CompileResult {
+    cargo::parse(false, output, "").unwrap()
+}
+
+#[test]
+fn fingerprints_ignore_where_in_the_file() {
+    let moved = result(&MISMATCH.replace("4:5", "40:1"));
+    let dotted = result(&MISMATCH.replace("src/", "./src/"));
+    let other_file = result(&MISMATCH.replace("main.rs", "lib.rs"));
+    let fingerprint = baseline::fingerprint(&result(MISMATCH).errors[0]);
+
+    assert_eq!(baseline::fingerprint(&moved.errors[0]), fingerprint);
+    assert_eq!(baseline::fingerprint(&dotted.errors[0]), fingerprint);
+    assert_ne!(baseline::fingerprint(&other_file.errors[0]), fingerprint);
+}
+
+#[test]
+fn diagnostics_beyond_the_baseline_are_new() {
+    let baseline = Baseline::capture(&result(&format!("{}{}", MISMATCH, UNUSED_X)));
+    assert_eq!(baseline.len(), 2);
+
+    // errors come first, so the second warning is the last diagnostic
+    let later = result(&format!("{}{}{}", UNUSED_X, MISMATCH, UNUSED_X));
+    let new = baseline.new_diagnostics(&later);
+    assert_eq!(new.len(), 1);
+    assert!(new.contains(&DiagnosticId(2)));
+}
+
+#[test]
+fn baselines_are_kept_per_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let baseline = Baseline::capture(&result(MISMATCH));
+
+    baseline.save_to(dir.path(), "/src/foo").unwrap();
+    assert_eq!(
+        Baseline::load_from(dir.path(), "/src/foo").unwrap(),
+        Some(baseline)
+    );
+    assert_eq!(Baseline::load_from(dir.path(), "/src/bar").unwrap(), None);
+
+    Baseline::remove_from(dir.path(), "/src/foo").unwrap();
+    assert_eq!(Baseline::load_from(dir.path(), "/src/foo").unwrap(), None);
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+use watch_rust_errors::bisect::{self, BisectEvent};
+use watch_rust_errors::cargo;
+
+const ERROR: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+// stands in for a build, printing whatever the commit's errors.txt says
+const COMMAND: &str = "shell: cat errors.txt";
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn commit(dir: &Path, message: &str, errors: &str) {
+    fs::write(dir.join("errors.txt"), errors).unwrap();
+    fs::write(dir.join("log.txt"), message).unwrap();
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", message]);
+}
+
+#[test]
+fn the_first_commit_reporting_the_diagnostic_is_found() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    commit(dir.path(), "first", "");
+    git(dir.path(), &["tag", "good"]);
+    commit(dir.path(), "second", "");
+    commit(dir.path(), "break things", ERROR);
+    commit(dir.path(), "fourth", ERROR);
+    commit(dir.path(), "fifth", ERROR);
+
+    let diag = cargo::parse(false, ERROR, "").unwrap().errors.remove(0);
+    let mut events = vec![];
+    let bisection = bisect::bisect(dir.path(), "good", COMMAND, &[], &diag, |event| {
+        events.push(event)
+    })
+    .unwrap();
+
+    assert!(bisection.summary.ends_with(" break things"));
+    assert!(bisection.summary.starts_with(&bisection.commit[..7]));
+    assert_eq!(
+        events.last(),
+        Some(&BisectEvent::Done(Ok(bisection.clone())))
+    );
+    assert_eq!(events.len(), bisection.builds + 1);
+    // the working tree is left as it was
+    assert_eq!(
+        fs::read_to_string(dir.path().join("log.txt")).unwrap(),
+        "fifth"
+    );
+
+    // when the diagnostic isn't committed, there's nothing to look for
+    commit(dir.path(), "fix", "");
+    let err = bisect::bisect(dir.path(), "good", COMMAND, &[], &diag, |_| {}).unwrap_err();
+    assert!(err.contains("isn't there at HEAD"));
+}
+
+#[test]
+fn bisections_that_cant_start_still_end() {
+    // not a repository, so git has nothing to bisect
+    let dir = tempfile::tempdir().unwrap();
+    let diag = cargo::parse(false, ERROR, "").unwrap().errors.remove(0);
+    let mut events = vec![];
+    let err = bisect::bisect(dir.path(), "good", COMMAND, &[], &diag, |event| {
+        events.push(event)
+    })
+    .unwrap_err();
+    assert_eq!(events, vec![BisectEvent::Done(Err(err))]);
+}
+
+#[test]
+fn spawned_bisections_report_from_their_own_thread() {
+    let dir = tempfile::tempdir().unwrap();
+    let diag = cargo::parse(false, ERROR, "").unwrap().errors.remove(0);
+    let events =
+        block_on(bisect::spawn(dir.path(), "good", COMMAND, &[], &diag).collect::<Vec<_>>());
+    assert!(matches!(events.as_slice(), [BisectEvent::Done(Err(_))]));
+}
+
+#[test]
+fn git_bisect_output_is_read() {
+    let output = "Bisecting: 6 revisions left to test after this (roughly 3 steps)\n\
+                  [1a2b3c4d] Do something\n";
+    assert_eq!(bisect::steps_left(output), Some(3));
+    assert_eq!(bisect::first_bad_commit(output), None);
+
+    let output = "1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d is the first bad commit\n\
+                  commit 1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d\n";
+    assert_eq!(
+        bisect::first_bad_commit(output),
+        Some("1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d")
+    );
+}
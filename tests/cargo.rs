@@ -0,0 +1,291 @@
+mod common;
+
+use watch_rust_errors::cargo::{self, SuccessPolicy};
+use watch_rust_errors::parser::RustcParser;
+use watch_rust_errors::process::SystemRunner;
+use watch_rust_errors::rust::Type;
+
+use crate::common::Project;
+
+#[test]
+fn clean_project_succeeds() {
+    let project = Project::new("fn main() {}\n");
+
+    let result = cargo::run(project.root(), "cargo check").unwrap();
+
+    assert!(result.success);
+    assert!(result.errors.is_empty());
+    assert!(result.warnings.is_empty());
+}
+
+#[test]
+fn unused_variable_is_a_warning() {
+    let project = Project::new("fn main() {\n    let x = 5;\n}\n");
+
+    let result = cargo::run(project.root(), "cargo check").unwrap();
+
+    assert!(result.success);
+    assert!(result.errors.is_empty());
+    assert_eq!(result.warnings.len(), 1);
+
+    let warning = &result.warnings[0];
+    assert!(matches!(warning.type_, Type::Warning));
+    assert_eq!(warning.message, "unused variable: `x`");
+    assert_eq!(warning.file.as_deref(), Some("src/main.rs"));
+    assert_eq!(warning.line, Some(2));
+    assert_eq!(warning.column, Some(9));
+}
+
+#[test]
+fn type_mismatch_is_an_error() {
+    let project = Project::new("fn main() {\n    let _y: u32 = \"a\";\n}\n");
+
+    let result = cargo::run(project.root(), "cargo check").unwrap();
+
+    assert!(!result.success);
+    assert_eq!(result.errors.len(), 1);
+
+    let error = &result.errors[0];
+    assert!(matches!(error.type_, Type::Error));
+    assert_eq!(error.num.as_deref(), Some("E0308"));
+    assert_eq!(error.message, "mismatched types");
+    assert_eq!(error.file.as_deref(), Some("src/main.rs"));
+    assert_eq!(error.line, Some(2));
+}
+
+#[test]
+fn rerun_picks_up_source_changes() {
+    let project = Project::new("fn main() {\n    let _y: u32 = \"a\";\n}\n");
+
+    let result = cargo::run(project.root(), "cargo check").unwrap();
+    assert!(!result.success);
+
+    project.write("src/main.rs", "fn main() {\n    let _y: u32 = 1;\n}\n");
+
+    let result = cargo::run(project.root(), "cargo check").unwrap();
+    assert!(result.success);
+    assert!(result.errors.is_empty());
+}
+
+#[test]
+fn failing_command_is_reported() {
+    let project = Project::new("fn main() {}\n");
+
+    let result = cargo::run(project.root(), "shell: exit 3").unwrap();
+
+    assert!(!result.success);
+    assert!(result.errors.is_empty());
+    // timed all the same
+    assert!(result.started_at.unwrap() <= result.finished_at.unwrap());
+    assert!(result.duration.is_some());
+}
+
+#[test]
+fn failing_tests_are_reported() {
+    let project = Project::new("fn main() {}\n");
+    project.write(
+        "src/lib.rs",
+        "#[test]\nfn passes() {}\n\n#[test]\nfn fails() {\n    panic!(\"boom\");\n}\n",
+    );
+
+    let result = cargo::run(project.root(), "cargo test --lib").unwrap();
+
+    assert!(!result.success);
+    let tests = result.tests.unwrap();
+    assert_eq!(tests.passed, 1);
+    assert_eq!(tests.failures.len(), 1);
+    assert_eq!(tests.failures[0].name, "fails");
+    assert_eq!(tests.failures[0].message.as_deref(), Some("boom"));
+    assert_eq!(tests.failures[0].line, Some(6));
+}
+
+#[cfg(unix)]
+#[test]
+fn both_streams_are_kept_in_order() {
+    let project = Project::new("fn main() {}\n");
+
+    let result = cargo::run(project.root(), "shell: echo one; echo two >&2; echo three").unwrap();
+
+    assert!(result.success);
+    assert_eq!(result.output, "one\ntwo\nthree\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn separate_streams_are_kept_in_the_order_they_were_printed() {
+    let project = Project::new("fn main() {}\n");
+
+    let result = cargo::run(
+        project.root(),
+        "sh -c 'echo one; sleep 0.2; echo two >&2; sleep 0.2; echo three'",
+    )
+    .unwrap();
+
+    assert!(result.success);
+    assert_eq!(result.output, "one\ntwo\nthree\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn fetch_progress_is_reported_while_running() {
+    let project = Project::new("fn main() {}\n");
+    let mut reports = vec![];
+
+    let result = cargo::run_with_progress(
+        &SystemRunner,
+        project.root(),
+        "shell: echo '    Updating crates.io index' >&2; \
+         echo ' Downloading crates ...' >&2; \
+         echo '  Downloaded itoa v1.0.1' >&2; \
+         echo '  Downloaded serde v1.0.136' >&2; \
+         echo '  Downloaded 2 crates (90.1 KB) in 0.52s' >&2",
+        &[],
+        &RustcParser,
+        &[],
+        |progress| reports.push(progress.to_string()),
+    )
+    .unwrap();
+
+    assert!(result.success);
+    assert_eq!(
+        reports,
+        vec![
+            "Updating crates.io index…",
+            "Downloading crates…",
+            "Downloading crates… 1 done",
+            "Downloading crates… 2 done",
+            "Downloaded 2 crates (90.1 KB) in 0.52s",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn missing_command_is_reported() {
+    let project = Project::new("fn main() {}\n");
+
+    let result = cargo::run(project.root(), "no-such-command-wre check").unwrap();
+
+    assert!(!result.success);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(
+        result.errors[0].message,
+        "command not found: `no-such-command-wre`"
+    );
+}
+
+#[test]
+fn diagnostics_past_the_limit_are_only_counted() {
+    let mut output = String::new();
+    for line in 1..=3 {
+        output += &format!(
+            "error[E0308]: mismatched types\n --> src/main.rs:{}:5\n\n",
+            line
+        );
+    }
+    for line in 1..=4 {
+        output += &format!(
+            "warning: unused variable: `x`\n --> src/main.rs:{}:9\n\n",
+            line
+        );
+    }
+    let mut result = cargo::parse(false, &output, "").unwrap();
+
+    // errors are kept before warnings
+    result.truncate(5);
+    assert_eq!((result.errors.len(), result.warnings.len()), (3, 2));
+    assert_eq!(result.omitted, 2);
+    result.truncate(2);
+    assert_eq!((result.errors.len(), result.warnings.len()), (2, 0));
+    assert_eq!(result.omitted, 5);
+
+    result.output = "é".repeat(cargo::MAX_OUTPUT);
+    result.truncate(2);
+    assert!(result.output.len() < cargo::MAX_OUTPUT + 100);
+    assert!(result
+        .output
+        .ends_with(&format!("\n[{} more bytes left out]\n", cargo::MAX_OUTPUT)));
+}
+
+#[test]
+fn success_policies_decide_what_passes() {
+    let output = "error[E0601]: `main` function not found in crate `app`\n --> src/main.rs:1:1\n\n\
+                  warning: unused variable: `x`\n --> src/main.rs:2:9\n\n";
+    let mut result = cargo::parse(false, output, "").unwrap();
+    result.warnings[0].lint = Some("unused_variables".to_string());
+
+    let strict = SuccessPolicy {
+        warnings_fail: true,
+        tolerated: vec!["E0601".to_string()],
+    };
+    assert!(!strict.passes(&result));
+
+    // the only error and the only warning don't count
+    let lenient = SuccessPolicy {
+        warnings_fail: true,
+        tolerated: vec!["E0601".to_string(), "unused_variables".to_string()],
+    };
+    assert!(lenient.passes(&result));
+
+    // failing without a diagnostic to blame still fails
+    result.errors.clear();
+    assert!(!lenient.passes(&result));
+
+    result.success = true;
+    assert!(!strict.passes(&result));
+    strict.apply(&mut result);
+    assert!(!result.success);
+}
+
+#[test]
+fn rustdoc_warnings_are_read() {
+    let output = " Documenting app v0.1.0 (/src/app)
+warning: missing documentation for a function
+ --> src/lib.rs:7:1
+  |
+7 | pub fn g() {}
+  | ^^^^^^^^^^
+  |
+note: the lint level is defined here
+ --> src/lib.rs:1:9
+  |
+1 | #![warn(rustdoc::all, missing_docs)]
+  |                       ^^^^^^^^^^^^
+
+warning: unresolved link to `Foo`
+ --> src/../README.md:3:6
+  |
+3 | See [Foo] for more.
+  |      ^^^ no item named `Foo` in scope
+  |
+  = note: `#[warn(rustdoc::broken_intra_doc_links)]` on by default
+
+warning: `app` (lib doc) generated 2 warnings
+";
+    let result = cargo::parse(true, output, "").unwrap();
+
+    assert_eq!(result.warnings.len(), 2);
+    let missing = &result.warnings[0];
+    assert_eq!(missing.lint.as_deref(), Some("missing_docs"));
+    assert_eq!((missing.line, missing.column), (Some(7), Some(1)));
+
+    // docs included from another file point into it
+    let link = &result.warnings[1];
+    assert_eq!(
+        link.lint.as_deref(),
+        Some("rustdoc::broken_intra_doc_links")
+    );
+    assert_eq!(link.file.as_deref(), Some("README.md"));
+    assert_eq!((link.line, link.column), (Some(3), Some(6)));
+    assert_eq!(result.reported.unwrap().warnings, 2);
+}
+
+#[test]
+fn json_lines_cut_short_are_left_out() {
+    let whole = r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///src/foo)","message":{"level":"error","message":"mismatched types","rendered":"error[E0308]: mismatched types\n --> src/main.rs:4:5\n","code":{"code":"E0308"},"spans":[],"children":[]}}"#;
+    let stdout = format!("{}\n{}", whole, &whole[..60]);
+
+    let result = cargo::parse(false, "", &stdout).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+}
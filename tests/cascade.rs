@@ -0,0 +1,85 @@
+use watch_rust_errors::cargo::{self, CompileResult, DiagnosticId};
+use watch_rust_errors::cascade::Cascades;
+use watch_rust_errors::config::SortOrder;
+
+const TYPO: &str = "error[E0432]: unresolved import `std::collections::HashMapp`
+ --> src/main.rs:1:5
+
+error[E0412]: cannot find type `HashMapp` in this scope
+ --> src/main.rs:4:12
+
+error[E0433]: failed to resolve: use of undeclared type `HashMapp`
+ --> src/main.rs:8:13
+
+error[E0308]: mismatched types
+ --> src/main.rs:12:5
+
+error[E0599]: no method named `len` found for struct `HashMapp<u32, u32>`
+ --> src/main.rs:14:7
+
+";
+
+fn result(output: &str) -> CompileResult {
+    cargo::parse(false, output, "").unwrap()
+}
+
+#[test]
+fn errors_about_an_unresolved_name_follow_from_the_first() {
+    let cascades = Cascades::detect(&result(TYPO));
+
+    assert_eq!(
+        cascades.consequences_of(DiagnosticId(0)),
+        &[DiagnosticId(1), DiagnosticId(2), DiagnosticId(4)]
+    );
+    assert_eq!(cascades.cause_of(DiagnosticId(2)), Some(DiagnosticId(0)));
+    // nothing ties it to the typo
+    assert_eq!(cascades.cause_of(DiagnosticId(3)), None);
+    assert!(cascades.consequences_of(DiagnosticId(3)).is_empty());
+}
+
+#[test]
+fn other_crates_have_their_own_causes() {
+    let mut result = result(TYPO);
+    result.errors[1].krate = Some("other".to_string());
+
+    let cascades = Cascades::detect(&result);
+    assert_eq!(cascades.cause_of(DiagnosticId(1)), None);
+    assert_eq!(
+        cascades.consequences_of(DiagnosticId(0)),
+        &[DiagnosticId(2), DiagnosticId(4)]
+    );
+}
+
+#[test]
+fn unrelated_errors_are_not_folded() {
+    let cascades = Cascades::detect(&result(
+        "error[E0425]: cannot find value `x` in this scope\n --> src/main.rs:2:5\n\n\
+         error[E0425]: cannot find value `y` in this scope\n --> src/main.rs:3:5\n\n",
+    ));
+
+    assert!(cascades.is_empty());
+}
+
+#[test]
+fn root_causes_sort_first() {
+    let result = result(
+        "error[E0382]: borrow of moved value: `v`\n --> src/main.rs:1:5\n\n\
+         error[E0432]: unresolved import `std::collections::HashMapp`\n --> src/main.rs:2:5\n\n\
+         error[E0308]: mismatched types\n --> src/main.rs:3:5\n\n\
+         error[E0599]: no method named `len` found for struct `HashMapp<u32, u32>`\n \
+         --> src/main.rs:4:5\n\n\
+         error: expected `;`, found `let`\n --> src/main.rs:5:5\n\n",
+    );
+    let mut diags = result
+        .diagnostics()
+        .enumerate()
+        .map(|(i, d)| (DiagnosticId(i), d))
+        .collect::<Vec<_>>();
+
+    cargo::sort_diagnostics(&mut diags, SortOrder::RootCause);
+    let lines = diags
+        .iter()
+        .map(|(_, d)| d.line.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![5, 2, 3, 4, 1]);
+}
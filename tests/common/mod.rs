@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use glib::{source::Continue, MainContext, Receiver};
+use tempfile::TempDir;
+use watch_rust_errors::process::{ProcessOutput, ProcessRunner};
+use watch_rust_errors::watcher::BuildEvent;
+
+// A throwaway cargo project living in a temporary directory. The directory
+// is removed when the project is dropped.
+pub struct Project {
+    dir: TempDir,
+}
+
+impl Project {
+    pub fn new(main_rs: &str) -> Self {
+        let dir = tempfile::Builder::new()
+            .prefix("wre-test")
+            .tempdir()
+            .expect("Failed to create temp dir.");
+
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\n\
+             name = \"wre-test\"\n\
+             version = \"0.1.0\"\n\
+             edition = \"2018\"\n\
+             \n\
+             [workspace]\n",
+        )
+        .expect("Failed to write Cargo.toml.");
+
+        fs::create_dir(dir.path().join("src")).expect("Failed to create src dir.");
+        let project = Project { dir };
+        project.write("src/main.rs", main_rs);
+
+        project
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn write(&self, path: &str, contents: &str) {
+        fs::write(self.dir.path().join(path), contents).expect("Failed to write source file.");
+    }
+}
+
+// program, arguments and extra environment of a single run
+pub type Call = (String, Vec<String>, Vec<(String, String)>);
+
+// Returns canned output and records every invocation instead of spawning
+// anything.
+pub struct MockRunner {
+    output: ProcessOutput,
+    pub calls: Mutex<Vec<Call>>,
+    // what was written to the calls that were given input
+    pub inputs: Mutex<Vec<String>>,
+}
+
+impl MockRunner {
+    pub fn new(success: bool, stderr: &str) -> Self {
+        MockRunner {
+            output: ProcessOutput {
+                success,
+                stdout: vec![],
+                stderr: stderr.as_bytes().to_vec(),
+            },
+            calls: Mutex::new(vec![]),
+            inputs: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn with_stdout(success: bool, stdout: &str) -> Self {
+        MockRunner {
+            output: ProcessOutput {
+                success,
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: vec![],
+            },
+            calls: Mutex::new(vec![]),
+            inputs: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    // calls that ran the compile command, leaving out toolchain queries and
+    // cargo's metadata
+    pub fn compile_count(&self) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(program, ..)| program != "rustc" && program != "rustup")
+            .filter(|(_, args, _)| args.first().map(String::as_str) != Some("metadata"))
+            .count()
+    }
+}
+
+impl ProcessRunner for MockRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        self.calls.lock().unwrap().push((
+            program.to_string(),
+            args.iter().map(ToString::to_string).collect(),
+            env.to_vec(),
+        ));
+        Ok(self.output.clone())
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+        input: &[u8],
+    ) -> Result<ProcessOutput, String> {
+        self.inputs
+            .lock()
+            .unwrap()
+            .push(String::from_utf8_lossy(input).into_owned());
+        self.run(program, args, env, cwd)
+    }
+}
+
+// Pumps a private main context until every event sent so far is received.
+pub fn drain_events(rx: Receiver<BuildEvent>) -> Vec<BuildEvent> {
+    let context = MainContext::new();
+    let events = Rc::new(RefCell::new(vec![]));
+    let sink = events.clone();
+    rx.attach(Some(&context), move |event| {
+        sink.borrow_mut().push(event);
+        Continue(true)
+    });
+    while context.iteration(false) {}
+
+    let events = events.borrow().clone();
+    events
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use watch_rust_errors::cargo::{self, CompileResult, DiagnosticId};
+use watch_rust_errors::compare;
+
+const UNUSED_X: &str = "warning: unused variable: `x`\n  --> src/main.rs:2:9\n\n";
+const UNUSED_Y: &str = "warning: unused variable: `y`\n  --> src/main.rs:7:9\n\n";
+
+fn result(output: &str) -> CompileResult {
+    cargo::parse(true, output, "").unwrap()
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn moved_diagnostics_are_not_reported() {
+    let base = result(UNUSED_X);
+    let head = result(&UNUSED_X.replace("2:9", "12:9"));
+
+    let comparison = compare::diff("main", &base, &head);
+    assert!(comparison.introduced.is_empty());
+    assert!(comparison.fixed.is_empty());
+}
+
+#[test]
+fn new_and_fixed_diagnostics_are_reported() {
+    let base = result(UNUSED_X);
+    let head = result(&format!("{}{}", UNUSED_Y, UNUSED_Y));
+
+    let comparison = compare::diff("main", &base, &head);
+    assert_eq!(comparison.introduced.len(), 2);
+    assert_eq!(comparison.introduced[0].message, "unused variable: `y`");
+    assert_eq!(comparison.fixed.len(), 1);
+    assert_eq!(comparison.fixed[0].message, "unused variable: `x`");
+}
+
+#[test]
+fn other_refs_are_built_in_a_worktree() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    fs::create_dir(&project).unwrap();
+    git(dir.path(), &["init", "-q"]);
+    fs::write(project.join("out.txt"), UNUSED_X).unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "base"]);
+    fs::write(project.join("out.txt"), UNUSED_Y).unwrap();
+
+    let current = cargo::run(&project, "cat out.txt").unwrap();
+    let comparison = compare::compare(&project, "HEAD", "cat out.txt", &[], &current).unwrap();
+
+    assert_eq!(comparison.introduced.len(), 1);
+    assert_eq!(comparison.introduced[0].message, "unused variable: `y`");
+    assert_eq!(comparison.fixed.len(), 1);
+    // the worktree is cleaned up afterwards
+    let output = Command::new("git")
+        .args(["worktree", "list"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+}
+
+#[test]
+fn diagnostics_are_found_again_after_moving() {
+    let base = result(&format!("{}{}", UNUSED_X, UNUSED_Y));
+    let head = result(&format!(
+        "{}{}{}",
+        UNUSED_X.replace("2:9", "20:9"),
+        UNUSED_X.replace("2:9", "4:9"),
+        UNUSED_Y.replace("7:9", "9:9")
+    ));
+
+    let find = |index| {
+        let id = head.find_same(base.diagnostic(DiagnosticId(index)).unwrap())?;
+        head.diagnostic(id).and_then(|d| d.line)
+    };
+    assert_eq!(find(0), Some(4));
+    assert_eq!(find(1), Some(9));
+    assert_eq!(result("").find_same(&base.warnings[0]), None);
+}
+
+#[test]
+fn consecutive_builds_are_diffed_by_diagnostic() {
+    let previous = result(&format!("{}{}", UNUSED_X, UNUSED_Y));
+    let current = result(&format!(
+        "{}{}",
+        UNUSED_Y.replace("7:9", "8:9"),
+        UNUSED_X.replace("`x`", "`z`")
+    ));
+
+    let diff = compare::diff_runs(&previous, &current);
+    assert_eq!(diff.unchanged, 1);
+    assert_eq!(diff.new.len(), 1);
+    assert!(diff.new.contains(&DiagnosticId(1)));
+    assert_eq!(diff.fixed.len(), 1);
+    assert_eq!(diff.fixed[0].message, "unused variable: `x`");
+}
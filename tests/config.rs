@@ -0,0 +1,341 @@
+use watch_rust_errors::config::{
+    self, Alert, Config, LocalConfig, ParserKind, Profile, ProjectConfig, QuietHours,
+    ResultsLayout, ScheduledJob, ScrollTo, SortOrder, Theme, TimeOfDay, WindowState,
+};
+
+#[test]
+fn round_trips_through_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("config.toml");
+
+    let mut config = Config {
+        theme: Theme::Dark,
+        sort_order: SortOrder::File,
+        scroll_to: ScrollTo::FirstError,
+        results_layout: ResultsLayout::Split,
+        alert: Alert::SoundAndFlash,
+        window: Some(WindowState {
+            width: 1280,
+            height: 900,
+            maximized: true,
+            tab: Some("Raw output".to_string()),
+            split_position: Some(320),
+        }),
+        ..Default::default()
+    };
+    let mut project = ProjectConfig::default();
+    project
+        .env
+        .insert("RUSTFLAGS".to_string(), "-D warnings".to_string());
+    project.parser = ParserKind::Gcc;
+    project.jobs.push(ScheduledJob {
+        name: "clippy".to_string(),
+        command: "cargo clippy".to_string(),
+        every_minutes: 60,
+    });
+    project.profile = Some("Clippy".to_string());
+    project
+        .profiles
+        .push(Profile::new("Clippy", "cargo clippy --all-targets"));
+    config.set_project("/src/foo", project.clone());
+    config.save_to(&path).unwrap();
+
+    let loaded = Config::load_from(&path).unwrap();
+    assert_eq!(loaded.theme, Theme::Dark);
+    assert_eq!(loaded.sort_order, SortOrder::File);
+    assert_eq!(loaded.scroll_to, ScrollTo::FirstError);
+    assert_eq!("first-error".parse(), Ok(ScrollTo::FirstError));
+    assert_eq!(loaded.results_layout, ResultsLayout::Split);
+    assert_eq!("split".parse(), Ok(ResultsLayout::Split));
+    assert_eq!(loaded.alert, Alert::SoundAndFlash);
+    assert_eq!(loaded.window, config.window);
+    assert_eq!(loaded.project("/src/foo"), project);
+    assert_eq!(loaded.project("/src/bar"), ProjectConfig::default());
+}
+
+#[test]
+fn empty_projects_are_not_kept() {
+    let mut config = Config::default();
+    config.set_project("/src/foo", ProjectConfig::default());
+
+    assert!(config.projects.is_empty());
+}
+
+#[test]
+fn environment_lines_are_parsed() {
+    let env = config::parse_env(
+        "# comment\n\
+         RUSTFLAGS=-D warnings\n\
+         \n\
+         CARGO_TARGET_DIR = /tmp/target\n\
+         EMPTY=\n",
+    )
+    .unwrap();
+
+    assert_eq!(env.len(), 3);
+    assert_eq!(env["RUSTFLAGS"], "-D warnings");
+    assert_eq!(env["CARGO_TARGET_DIR"], "/tmp/target");
+    assert_eq!(env["EMPTY"], "");
+    assert_eq!(config::parse_env(&config::format_env(&env)).unwrap(), env);
+}
+
+#[test]
+fn malformed_environment_lines_are_an_error() {
+    assert!(config::parse_env("RUSTFLAGS").is_err());
+    assert!(config::parse_env("=value").is_err());
+    assert!(config::parse_env("MY VAR=1").is_err());
+}
+
+#[test]
+fn offline_projects_keep_cargo_off_the_network() {
+    let mut project = ProjectConfig::default();
+    project
+        .env
+        .insert("RUSTFLAGS".to_string(), "-D warnings".to_string());
+    project.offline = true;
+
+    assert_eq!(
+        project.env_vars(),
+        [
+            ("RUSTFLAGS".to_string(), "-D warnings".to_string()),
+            ("CARGO_NET_OFFLINE".to_string(), "true".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn a_picked_toolchain_is_set_for_rustup() {
+    let project = ProjectConfig {
+        toolchain: Some("nightly".to_string()),
+        ..ProjectConfig::default()
+    };
+
+    assert_eq!(
+        project.env_vars(),
+        [("RUSTUP_TOOLCHAIN".to_string(), "nightly".to_string())]
+    );
+}
+
+#[test]
+fn a_picked_target_is_what_cargo_builds_for() {
+    let project = ProjectConfig {
+        target: Some("wasm32-unknown-unknown".to_string()),
+        ..ProjectConfig::default()
+    };
+
+    assert_eq!(
+        project.env_vars(),
+        [(
+            "CARGO_BUILD_TARGET".to_string(),
+            "wasm32-unknown-unknown".to_string()
+        )]
+    );
+}
+
+#[test]
+fn missing_keys_use_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "").unwrap();
+
+    let loaded = Config::load_from(&path).unwrap();
+    assert_eq!(loaded.theme, Theme::System);
+}
+
+#[test]
+fn invalid_config_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "theme = \"purple\"\n").unwrap();
+
+    assert!(Config::load_from(&path).is_err());
+}
+
+#[test]
+fn broken_sections_can_be_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let broken = "theme = \"purple\"\ngroup_by_file = true\n";
+    std::fs::write(&path, broken).unwrap();
+
+    assert_eq!(
+        Config::sections(&path).unwrap(),
+        vec!["group_by_file", "theme"]
+    );
+    let backup = Config::reset_section(&path, "theme").unwrap();
+    assert!(Config::reset_section(&path, "theme").is_err());
+
+    assert_eq!(std::fs::read_to_string(backup).unwrap(), broken);
+    let loaded = Config::load_from(&path).unwrap();
+    assert_eq!(loaded.theme, Theme::System);
+    assert!(loaded.group_by_file);
+}
+
+#[test]
+fn ignored_codes_are_parsed() {
+    let ignored =
+        config::parse_ignored("# while scaffolding\nE0601, dead_code\n\nunused_imports E0601\n");
+
+    assert_eq!(ignored, vec!["E0601", "dead_code", "unused_imports"]);
+    assert_eq!(
+        config::parse_ignored(&config::format_ignored(&ignored)),
+        ignored
+    );
+}
+
+#[test]
+fn profiles_default_to_common_commands() {
+    let project = ProjectConfig::default();
+
+    let names: Vec<_> = project.profiles().into_iter().map(|p| p.name).collect();
+    assert_eq!(
+        names,
+        vec!["Check", "Clippy", "Test", "Release build", "Docs"]
+    );
+    assert_eq!(project.current_profile(), None);
+}
+
+#[test]
+fn switching_profiles_keeps_edited_commands() {
+    let mut project = ProjectConfig::default();
+
+    assert_eq!(
+        project.switch_profile("Clippy", "cargo check"),
+        Some("cargo clippy".to_string())
+    );
+    // nothing was changed so the defaults don't need saving
+    assert!(project.profiles.is_empty());
+
+    assert_eq!(
+        project.switch_profile("Test", "cargo clippy --all-targets"),
+        Some("cargo test".to_string())
+    );
+    assert_eq!(project.profile.as_deref(), Some("Test"));
+    assert_eq!(
+        project.switch_profile("Clippy", "cargo test"),
+        Some("cargo clippy --all-targets".to_string())
+    );
+
+    assert_eq!(project.switch_profile("Bench", "cargo bench"), None);
+    assert_eq!(project.profile.as_deref(), Some("Clippy"));
+}
+
+#[test]
+fn profiles_add_their_own_ignored_codes() {
+    let mut project = ProjectConfig {
+        ignored: vec!["E0601".to_string()],
+        ..Default::default()
+    };
+    let mut clippy = Profile::new("Clippy", "cargo clippy");
+    clippy.ignored = vec![
+        "E0601".to_string(),
+        "clippy::new_without_default".to_string(),
+    ];
+    project.profiles.push(clippy);
+    assert_eq!(project.ignored_codes(), vec!["E0601"]);
+
+    project.switch_profile("Clippy", "");
+    assert_eq!(
+        project.ignored_codes(),
+        vec!["E0601", "clippy::new_without_default"]
+    );
+}
+
+#[test]
+fn project_configs_only_run_commands_once_trusted() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(LocalConfig::load(dir.path()).unwrap(), None);
+
+    std::fs::write(
+        dir.path().join(config::LOCAL_CONFIG),
+        "command = \"cargo clippy\"\nignored = [\"dead_code\"]\n\n[env]\nRUSTC_WRAPPER = \"./evil\"\n",
+    )
+    .unwrap();
+    let local = LocalConfig::load(dir.path()).unwrap().unwrap();
+    assert!(local.runs_commands());
+
+    let mut project = ProjectConfig::default();
+    let merged = project.with_local(Some(&local));
+    assert_eq!(merged.ignored, vec!["dead_code"]);
+    assert!(merged.env.is_empty());
+    assert_eq!(local.command_for(&project), None);
+
+    project.trust_local_config = Some(true);
+    let merged = project.with_local(Some(&local));
+    assert_eq!(merged.env["RUSTC_WRAPPER"], "./evil");
+    assert_eq!(local.command_for(&project), Some("cargo clippy"));
+}
+
+#[test]
+fn quiet_hours_may_run_overnight() {
+    let config: Config =
+        toml::from_str("[quiet_hours]\nstart = \"22:30\"\nend = \"7:00\"\n").unwrap();
+    let quiet_hours = config.quiet_hours.unwrap();
+    assert_eq!(
+        quiet_hours,
+        QuietHours {
+            start: TimeOfDay::new(22, 30),
+            end: TimeOfDay::new(7, 0),
+        }
+    );
+    assert!(quiet_hours.contains(TimeOfDay::new(23, 0)));
+    assert!(quiet_hours.contains(TimeOfDay::new(3, 15)));
+    assert!(!quiet_hours.contains(TimeOfDay::new(7, 0)));
+    assert!(!quiet_hours.contains(TimeOfDay::new(12, 0)));
+
+    let lunch = QuietHours {
+        start: TimeOfDay::new(12, 0),
+        end: TimeOfDay::new(13, 0),
+    };
+    assert!(lunch.contains(TimeOfDay::new(12, 30)));
+    assert!(!lunch.contains(TimeOfDay::new(23, 0)));
+
+    assert_eq!(String::from(TimeOfDay::new(7, 0)), "07:00");
+    assert!("24:00".parse::<TimeOfDay>().is_err());
+}
+
+#[test]
+fn command_history_keeps_the_latest_first() {
+    let mut config = Config::default();
+    config.remember_command("cargo check");
+    config.remember_command("cargo test");
+    config.remember_command(" cargo check ");
+    config.remember_command("");
+    assert_eq!(config.command_history, vec!["cargo check", "cargo test"]);
+
+    for n in 0..config::MAX_HISTORY {
+        config.remember_command(&format!("cargo build -j{}", n));
+    }
+    assert_eq!(config.command_history.len(), config::MAX_HISTORY);
+    assert_eq!(config.command_history[1], "cargo build -j48");
+
+    let history = vec!["cargo check".to_string(), "cargo test".to_string()];
+    assert_eq!(
+        config::recall_command(&history, "cargo cl", true),
+        Some("cargo check")
+    );
+    assert_eq!(
+        config::recall_command(&history, "cargo check", true),
+        Some("cargo test")
+    );
+    assert_eq!(config::recall_command(&history, "cargo test", true), None);
+    assert_eq!(
+        config::recall_command(&history, "cargo test", false),
+        Some("cargo check")
+    );
+    assert_eq!(config::recall_command(&history, "cargo check", false), None);
+}
+
+#[test]
+fn success_policies_take_in_ignored_codes() {
+    let mut project = ProjectConfig {
+        ignored: vec!["dead_code".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(project.success_policy(), None);
+
+    project.warnings_fail = true;
+    let policy = project.success_policy().unwrap();
+    assert!(policy.warnings_fail);
+    assert_eq!(policy.tolerated, vec!["dead_code"]);
+}
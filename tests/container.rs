@@ -0,0 +1,141 @@
+mod common;
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::config::ProjectConfig;
+use watch_rust_errors::container::ContainerRunner;
+use watch_rust_errors::process::{self, ProcessOutput, ProcessRunner};
+
+use crate::common::MockRunner;
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+// An engine whose builds never finish, recording what it's run with.
+#[derive(Default)]
+struct HungEngine {
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+impl HungEngine {
+    fn record(&self, args: &[&str]) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(args.iter().map(ToString::to_string).collect());
+    }
+}
+
+impl ProcessRunner for HungEngine {
+    fn run(
+        &self,
+        _program: &str,
+        args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        self.record(args);
+        Ok(ProcessOutput::default())
+    }
+
+    fn run_streaming_with_timeout(
+        &self,
+        _program: &str,
+        args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+        timeout: Duration,
+        _on_line: &mut dyn FnMut(&str),
+    ) -> Result<ProcessOutput, String> {
+        self.record(args);
+        Err(process::timed_out(timeout))
+    }
+}
+
+// what the container was called
+fn name(args: &[String]) -> &str {
+    let name = args.iter().position(|arg| arg == "--name").unwrap();
+    &args[name + 1]
+}
+
+#[test]
+fn commands_run_in_the_project_mounted_in_a_container() {
+    let host = Arc::new(MockRunner::new(false, OUTPUT));
+    let container = ContainerRunner::with_host("podman", "rust:1.70", "/work/app", host.clone());
+
+    let result = cargo::run_with(
+        &container,
+        "/work/app",
+        "RUSTFLAGS=-Dwarnings cargo check",
+        &[],
+    )
+    .unwrap();
+    assert_eq!(result.errors.len(), 1);
+
+    let calls = host.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "podman");
+    assert_eq!(
+        calls[0].1,
+        [
+            "run",
+            "--rm",
+            "-v",
+            "/work/app:/src",
+            "-w",
+            "/src",
+            "-e",
+            "RUSTFLAGS",
+            "rust:1.70",
+            "cargo",
+            "check"
+        ]
+    );
+    // the values go through the engine's environment, off its command line
+    assert_eq!(
+        calls[0].2,
+        [("RUSTFLAGS".to_string(), "-Dwarnings".to_string())]
+    );
+
+    let args = container.args("cargo", &[], &[], Path::new("/work/app/crates/core"));
+    assert_eq!(args[5], "/src/crates/core");
+    let args = container.args("cargo", &[], &[], Path::new("/elsewhere"));
+    assert_eq!(args[5], "/src");
+}
+
+#[test]
+fn containers_that_time_out_are_killed() {
+    let host = Arc::new(HungEngine::default());
+    let container = ContainerRunner::with_host("podman", "rust:1.70", "/work/app", host.clone());
+
+    let err = container
+        .run_streaming_with_timeout(
+            "cargo",
+            &["check"],
+            &[],
+            Path::new("/work/app"),
+            Duration::from_secs(30),
+            &mut |_| {},
+        )
+        .unwrap_err();
+    assert!(process::is_timed_out(&err));
+
+    let calls = host.calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    let name = name(&calls[0]);
+    assert!(name.starts_with(&format!("wre-{}-", std::process::id())));
+    assert_eq!(calls[1], ["kill", name]);
+}
+
+#[test]
+fn projects_only_build_in_containers_with_an_image() {
+    let mut project = ProjectConfig::default();
+    assert!(project.container("/work/app").is_none());
+
+    project.container_image = Some("ghcr.io/org/ci:latest".to_string());
+    let container = project.container("/work/app").unwrap();
+    assert_eq!(container.engine, "docker");
+    assert_eq!(container.image, "ghcr.io/org/ci:latest");
+}
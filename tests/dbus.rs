@@ -0,0 +1,143 @@
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::dbus::{self, Call, DbusService, Message, MessageType, Value};
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+// Plays the bus daemon for one client: lets it in, says hello and hands it
+// the name it asks for.
+fn fake_bus(listener: UnixListener) -> thread::JoinHandle<UnixStream> {
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let mut auth = vec![];
+        let mut byte = [0; 1];
+        while !auth.ends_with(b"\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            auth.push(byte[0]);
+        }
+        assert!(auth.starts_with(b"\0AUTH EXTERNAL "));
+        stream.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+        let mut begin = [0; 7];
+        stream.read_exact(&mut begin).unwrap();
+        assert_eq!(&begin, b"BEGIN\r\n");
+
+        let hello = dbus::read_message(&mut stream).unwrap();
+        assert_eq!(hello.member.as_deref(), Some("Hello"));
+        reply(&mut stream, &hello, vec![Value::Str(":1.7".to_string())]);
+
+        let request = dbus::read_message(&mut stream).unwrap();
+        assert_eq!(request.member.as_deref(), Some("RequestName"));
+        assert_eq!(request.body[0], Value::Str(dbus::BUS_NAME.to_string()));
+        reply(&mut stream, &request, vec![Value::U32(1)]);
+        stream
+    })
+}
+
+fn reply(stream: &mut UnixStream, call: &Message, body: Vec<Value>) {
+    let mut reply = Message::reply_to(call, body);
+    reply.serial = 1;
+    stream.write_all(&reply.encode()).unwrap();
+}
+
+fn call(stream: &mut UnixStream, serial: u32, member: &str, body: Vec<Value>) -> Message {
+    let mut call = Message::call(
+        dbus::BUS_NAME,
+        dbus::OBJECT_PATH,
+        dbus::INTERFACE,
+        member,
+        body,
+    );
+    call.serial = serial;
+    call.sender = Some(":1.8".to_string());
+    stream.write_all(&call.encode()).unwrap();
+    let reply = dbus::read_message(stream).unwrap();
+    assert_eq!(reply.reply_serial, Some(serial));
+    assert_eq!(reply.destination.as_deref(), Some(":1.8"));
+    reply
+}
+
+#[test]
+fn messages_survive_the_wire() {
+    let mut message = Message::signal(
+        dbus::OBJECT_PATH,
+        dbus::INTERFACE,
+        "BuildFinished",
+        vec![
+            Value::Bool(true),
+            Value::U32(3),
+            Value::Str("é".to_string()),
+        ],
+    );
+    message.serial = 42;
+    assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+}
+
+#[test]
+fn scripts_drive_the_watch_and_hear_about_builds() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bus");
+    let bus = fake_bus(UnixListener::bind(&path).unwrap());
+    let service = DbusService::connect(&format!("unix:path={}", path.display())).unwrap();
+    let mut bus = bus.join().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    service.on_call(move |call| tx.send(call).unwrap());
+
+    let started = call(
+        &mut bus,
+        10,
+        "StartWatching",
+        vec![
+            Value::Str("/tmp/demo".to_string()),
+            Value::Str("cargo check".to_string()),
+        ],
+    );
+    assert_eq!(started.type_, MessageType::MethodReturn);
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+        Call::StartWatching {
+            project_root: "/tmp/demo".to_string(),
+            command: "cargo check".to_string(),
+        }
+    );
+    call(&mut bus, 11, "TriggerRun", vec![]);
+    assert_eq!(rx.recv().unwrap(), Call::TriggerRun);
+
+    service.set_watching(Some("/tmp/demo"));
+    service.publish(&cargo::parse(false, MISMATCH, "").unwrap());
+    let signal = dbus::read_message(&mut bus).unwrap();
+    assert_eq!(signal.type_, MessageType::Signal);
+    assert_eq!(signal.member.as_deref(), Some("BuildFinished"));
+    assert_eq!(
+        signal.body,
+        vec![Value::Bool(false), Value::U32(1), Value::U32(0)]
+    );
+
+    let status = call(&mut bus, 12, "GetStatus", vec![]);
+    assert_eq!(
+        status.body,
+        vec![
+            Value::Bool(true),
+            Value::Str("/tmp/demo".to_string()),
+            Value::Bool(false),
+            Value::U32(1),
+            Value::U32(0),
+        ]
+    );
+
+    let unknown = call(&mut bus, 13, "Explode", vec![]);
+    assert_eq!(unknown.type_, MessageType::Error);
+    let wrong = call(&mut bus, 14, "StartWatching", vec![]);
+    assert_eq!(wrong.type_, MessageType::Error);
+}
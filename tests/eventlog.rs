@@ -0,0 +1,98 @@
+mod common;
+
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+use watch_rust_errors::cargo;
+use watch_rust_errors::eventlog::{self, EventLog, LoggedEvent};
+use watch_rust_errors::session::{SessionConfig, WatchSession};
+use watch_rust_errors::watcher::BuildEvent;
+
+use crate::common::MockRunner;
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+#[test]
+fn entries_say_when_they_came_in() {
+    let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    assert_eq!(
+        eventlog::entry(&BuildEvent::Started, at),
+        json!({ "event": "started", "at": 1_700_000_000_123u64 })
+    );
+}
+
+#[test]
+fn sessions_append_their_events_as_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs").join("events.ndjson");
+    fs::create_dir(dir.path().join("logs")).unwrap();
+    fs::write(&path, "{\"event\":\"earlier\"}\n").unwrap();
+
+    let mut config = SessionConfig::new(".", "cargo check");
+    config.process = Arc::new(MockRunner::new(false, MISMATCH));
+    config.event_log = Some(path.clone());
+    let (session, events) = WatchSession::start(config).unwrap();
+    block_on(events.take(3).collect::<Vec<_>>());
+    drop(session);
+
+    let lines = fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let kinds = lines
+        .iter()
+        .map(|line| line["event"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec!["earlier", "started", "diagnostic", "finished"]);
+    assert_eq!(lines[2]["diagnostic"]["num"], "E0308");
+    assert!(lines[3]["at"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn logs_are_read_back_into_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.ndjson");
+    let mut log = EventLog::open(&path).unwrap();
+    log.record(&BuildEvent::Started).unwrap();
+    log.record(&BuildEvent::Finished(
+        cargo::parse(false, MISMATCH, "").unwrap(),
+    ))
+    .unwrap();
+    // from a later version, say
+    fs::write(
+        &path,
+        fs::read_to_string(&path).unwrap() + "{\"event\":\"rebooted\",\"at\":0}\n",
+    )
+    .unwrap();
+
+    let events = eventlog::read(&path).unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0].event, BuildEvent::Started));
+    match &events[1].event {
+        BuildEvent::Finished(result) => assert_eq!(result.errors[0].message, "mismatched types"),
+        event => panic!("unexpected event {:?}", event),
+    }
+    assert!(events[1].at >= events[0].at);
+}
+
+#[test]
+fn replays_stop_when_nobody_is_listening() {
+    let events = (0..3)
+        .map(|at| LoggedEvent {
+            event: BuildEvent::Started,
+            at,
+        })
+        .collect();
+
+    let mut sent = 0;
+    eventlog::replay(events, true, |_| {
+        sent += 1;
+        false
+    });
+    assert_eq!(sent, 1);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use std::path::Path;
+
+use watch_rust_errors::explain;
+
+use crate::common::MockRunner;
+
+const E0308: &str = "Expected type did not match the received type.
+
+Erroneous code examples:
+
+```compile_fail,E0308
+# fn main() {
+fn plus_one(x: i32) -> i32 {
+    x + 1
+}
+
+plus_one(\"Not a number\");
+# }
+```
+
+#### Note: `x` & `y`
+
+This error occurs when an expression was used in a place where the compiler
+expected an expression of a different type.
+";
+
+#[test]
+fn only_error_codes_are_explained() {
+    assert!(explain::is_explainable("E0308"));
+    assert!(!explain::is_explainable("dead_code"));
+    assert!(!explain::is_explainable("E03"));
+    assert!(!explain::is_explainable("clippy::needless_return"));
+
+    let runner = MockRunner::with_stdout(true, E0308);
+    let err = explain::explain(&runner, Path::new("."), &[], "dead_code").unwrap_err();
+    assert!(err.contains("dead_code"));
+    assert!(runner.calls.lock().unwrap().is_empty());
+}
+
+#[test]
+fn explanations_come_from_the_projects_rustc() {
+    let runner = MockRunner::with_stdout(true, E0308);
+    let env = vec![("RUSTUP_TOOLCHAIN".to_string(), "nightly".to_string())];
+    let text = explain::explain(&runner, Path::new("."), &env, "E0308").unwrap();
+    assert!(text.starts_with("Expected type"));
+
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "rustc");
+    assert_eq!(calls[0].1, vec!["--explain", "E0308"]);
+    assert_eq!(calls[0].2, env);
+
+    let runner = MockRunner::new(false, "error: `E9999` is not a valid error code\n");
+    let err = explain::explain(&runner, Path::new("."), &[], "E9999").unwrap_err();
+    assert_eq!(err, "error: `E9999` is not a valid error code");
+}
+
+#[test]
+fn explanations_read_as_markup() {
+    let markup = explain::to_pango(E0308);
+    // code blocks are monospace, without the lines rustdoc hides
+    assert!(markup.contains(
+        "<tt>fn plus_one(x: i32) -&gt; i32 {\n    x + 1\n}\n\nplus_one(&quot;Not a number&quot;);</tt>\n"
+    ));
+    assert!(!markup.contains("fn main"));
+    assert!(!markup.contains("```"));
+    assert!(markup.contains("<b>Note: <tt>x</tt> &amp; <tt>y</tt></b>"));
+    assert!(markup.ends_with("of a different type."));
+
+    // a backtick on its own is left alone
+    assert_eq!(explain::to_pango("`a` or `b"), "<tt>a</tt> or `b");
+}
@@ -0,0 +1,68 @@
+use watch_rust_errors::cargo::CompileResult;
+use watch_rust_errors::export::{self, Format};
+
+fn result() -> CompileResult {
+    let mut result = CompileResult::default();
+    result.push(
+        "error[E0308]: mismatched types\n  --> src/main.rs:3:18\n  |\n3 |     let x: u32 = \"a\";\n"
+            .parse()
+            .unwrap(),
+    );
+    result.push(
+        "warning: unused variable: `x`\n  --> src/main.rs:2:9\n"
+            .parse()
+            .unwrap(),
+    );
+    result
+}
+
+#[test]
+fn format_follows_the_extension() {
+    assert_eq!(Format::from_path("out.json"), Some(Format::Json));
+    assert_eq!(Format::from_path("out.md"), Some(Format::Markdown));
+    assert_eq!(Format::from_path("out.sarif"), Some(Format::Sarif));
+    assert_eq!(Format::from_path("out.txt"), None);
+}
+
+#[test]
+fn json_round_trips() {
+    let json = export::export(&result(), Format::Json).unwrap();
+    let parsed: CompileResult = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.errors.len(), 1);
+    assert_eq!(parsed.errors[0].num.as_deref(), Some("E0308"));
+    assert_eq!(parsed.warnings.len(), 1);
+}
+
+#[test]
+fn markdown_lists_diagnostics() {
+    let markdown = export::export(&result(), Format::Markdown).unwrap();
+
+    assert!(markdown.starts_with("# Compile failed\n"));
+    assert!(markdown.contains("### error[E0308]: mismatched types"));
+    assert!(markdown.contains("`src/main.rs:3:18`"));
+    assert!(markdown.contains("## Warnings"));
+}
+
+#[test]
+fn sarif_has_a_result_per_diagnostic() {
+    let sarif = export::export(&result(), Format::Sarif).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&sarif).unwrap();
+
+    assert_eq!(value["version"], "2.1.0");
+    let results = value["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["ruleId"], "E0308");
+    assert_eq!(results[0]["level"], "error");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+    assert_eq!(location["region"]["startLine"], 3);
+    assert!(results[1].get("ruleId").is_none());
+}
+
+#[test]
+fn unknown_extensions_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(export::write_to(&result(), dir.path().join("out.txt")).is_err());
+    assert!(export::write_to(&result(), dir.path().join("out.md")).is_ok());
+}
@@ -0,0 +1,140 @@
+use std::fs;
+
+use watch_rust_errors::external::{self, ExternalSource};
+use watch_rust_errors::parser::RustcParser;
+use watch_rust_errors::rust::Type;
+use watch_rust_errors::watcher::BuildEvent;
+
+#[test]
+fn bacon_locations_are_parsed() {
+    let result = external::parse_bacon_locations(
+        "warning src/main.rs:2:9 unused variable: `x`\n\
+         error src/main.rs:3:18 mismatched types\n\
+         test src/lib.rs:10:5 tests::it_works\n",
+    );
+
+    assert!(!result.success);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].type_, Type::Error);
+    assert_eq!(result.errors[0].message, "mismatched types");
+    assert_eq!(result.errors[0].line, Some(3));
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(
+        result.warnings[0].location().as_deref(),
+        Some("src/main.rs:2:9")
+    );
+
+    let failures = &result.tests.unwrap().failures;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].name, "tests::it_works");
+}
+
+#[test]
+fn empty_bacon_export_is_a_success() {
+    assert!(external::parse_bacon_locations("").success);
+}
+
+#[test]
+fn only_the_latest_cargo_watch_run_is_used() {
+    let result = external::parse_cargo_watch_log(
+        "[Running 'cargo check']\n\
+         error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n\
+         [Finished running. Exit status: 101]\n\
+         [Running 'cargo check']\n\
+         warning: unused variable: `x`\n \
+         --> src/main.rs:2:9\n\
+         \n\
+         [Finished running. Exit status: 0]\n",
+    )
+    .unwrap();
+
+    assert!(result.success);
+    assert!(result.errors.is_empty());
+    assert_eq!(result.warnings.len(), 1);
+}
+
+#[test]
+fn bacon_export_is_followed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut source = ExternalSource::bacon(dir.path()).unwrap();
+    assert!(source.poll().unwrap().is_none());
+
+    fs::write(
+        dir.path().join(".bacon-locations"),
+        "error src/main.rs:3:18 mismatched types\n",
+    )
+    .unwrap();
+    let result = source.poll().unwrap().unwrap();
+    assert_eq!(result.errors.len(), 1);
+    assert!(source.poll().unwrap().is_none());
+}
+
+fn read_piped(input: &str) -> Vec<BuildEvent> {
+    let mut events = vec![];
+    external::read_piped(input.as_bytes(), &RustcParser, |event| {
+        events.push(event);
+        true
+    })
+    .unwrap();
+    events
+}
+
+#[test]
+fn piped_output_is_one_build() {
+    let events = read_piped(
+        "   Compiling demo v0.1.0 (/tmp/demo)\n\
+         error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n\
+         error: could not compile `demo`\n",
+    );
+
+    assert!(matches!(events[0], BuildEvent::Started));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, BuildEvent::Compiling(_))));
+    match events.last() {
+        Some(BuildEvent::Finished(result)) => {
+            assert!(!result.success);
+            assert_eq!(result.errors.len(), 1);
+            assert_eq!(result.errors[0].line, Some(3));
+        }
+        other => panic!("expected the build's results, got {:?}", other),
+    }
+}
+
+#[test]
+fn piped_cargo_watch_runs_are_builds_of_their_own() {
+    let events = read_piped(
+        "[Running 'cargo check']\n\
+         error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n\
+         [Finished running. Exit status: 101]\n\
+         [Running 'cargo check']\n\
+         warning: unused variable: `x`\n \
+         --> src/main.rs:2:9\n\
+         \n\
+         [Finished running. Exit status: 0]\n",
+    );
+
+    let started = events
+        .iter()
+        .filter(|event| matches!(event, BuildEvent::Started))
+        .count();
+    assert_eq!(started, 2);
+    let results = events
+        .iter()
+        .filter_map(|event| match event {
+            BuildEvent::Finished(result) => Some(result),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+    assert!(!results[0].success);
+    assert_eq!(results[0].errors.len(), 1);
+    assert!(results[1].success);
+    assert_eq!(results[1].warnings.len(), 1);
+}
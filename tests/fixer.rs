@@ -0,0 +1,86 @@
+mod common;
+
+use futures::executor::block_on;
+use watch_rust_errors::cargo;
+use watch_rust_errors::fixer::{self, DiffLine};
+
+use crate::common::Project;
+
+const COMMAND: &str = "cargo check --message-format=json";
+
+#[test]
+fn json_diagnostics_carry_suggestions() {
+    let project = Project::new("fn main() {\n    let mut x = 5;\n    println!(\"{}\", x);\n}\n");
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+
+    assert_eq!(result.warnings.len(), 1);
+    let warning = &result.warnings[0];
+    assert_eq!(warning.lint.as_deref(), Some("unused_mut"));
+    assert_eq!(warning.line, Some(2));
+    // the span of "mut x", up to the column after it
+    assert_eq!(warning.end_line, Some(2));
+    assert_eq!(warning.end_column, Some(14));
+    assert_eq!(warning.krate.as_deref(), Some("wre-test"));
+    assert!(warning.is_fixable());
+}
+
+#[test]
+fn apply_fixes_the_file_on_disk() {
+    let project = Project::new("fn main() {\n    let mut x = 5;\n    println!(\"{}\", x);\n}\n");
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+    fixer::apply(project.root(), &result.warnings[0]).unwrap();
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+    assert!(result.success);
+    assert!(result.warnings.is_empty());
+}
+
+#[test]
+fn fixes_can_be_applied_in_the_background() {
+    let project = Project::new("fn main() {\n    let mut x = 5;\n    println!(\"{}\", x);\n}\n");
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+    let fix = fixer::apply_in_background(project.root(), &result.warnings[0]);
+    block_on(fix).unwrap();
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+    assert!(result.warnings.is_empty());
+}
+
+#[test]
+fn errors_without_suggestions_are_not_fixable() {
+    let project = Project::new("fn main() {\n    let _y: u32 = \"a\";\n}\n");
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].num.as_deref(), Some("E0308"));
+    assert!(fixer::apply(project.root(), &result.errors[0]).is_err());
+}
+
+#[test]
+fn previews_show_the_fix_without_applying_it() {
+    let source = "fn main() {\n    let mut x = 5;\n    println!(\"{}\", x);\n}\n";
+    let project = Project::new(source);
+
+    let result = cargo::run(project.root(), COMMAND).unwrap();
+    let lines = fixer::preview(project.root(), &result.warnings[0]).unwrap();
+
+    assert_eq!(
+        lines,
+        vec![
+            DiffLine::Context("fn main() {".to_string()),
+            DiffLine::Removed("    let mut x = 5;".to_string()),
+            DiffLine::Added("    let x = 5;".to_string()),
+            DiffLine::Context("    println!(\"{}\", x);".to_string()),
+        ]
+    );
+    let markup = fixer::diff_markup(&lines, false);
+    assert!(markup.contains("<span foreground=\"#c01c28\">-     let mut x = 5;</span>"));
+    assert!(markup.contains("&quot;{}&quot;"));
+
+    let contents = std::fs::read_to_string(project.root().join("src/main.rs")).unwrap();
+    assert_eq!(contents, source);
+}
@@ -0,0 +1,83 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use watch_rust_errors::cargo::{self, CompileResult};
+
+// Output captured from rustc, cargo and clippy, each `<name>.stderr` next to
+// the diagnostics it parses into in `<name>.expected`, and if the command
+// printed JSON or test results, `<name>.stdout`. Run the tests with
+// WRE_BLESS=1 to write the expected files from what's parsed now.
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+// One line per diagnostic with its macro trace, notes and helps under it,
+// then what cargo said about each crate and what the summary lines add up to.
+fn summarize(result: &CompileResult) -> String {
+    let mut summary = String::new();
+    for diag in result.diagnostics() {
+        summary.push_str(&diag.title());
+        if let Some(location) = diag.location() {
+            summary.push_str(&format!(" --> {}", location));
+        }
+        summary.push('\n');
+        for frame in &diag.macro_backtrace {
+            summary.push_str(&format!("    in {}\n", frame));
+        }
+        for child in &diag.children {
+            let child = child.to_string().replace('\n', "\n        ");
+            summary.push_str(&format!("    {}\n", child));
+        }
+    }
+    for krate in &result.crates {
+        summary.push_str(&format!(
+            "crate {}: failed {}, errors {:?}, warnings {:?}\n",
+            krate.name, krate.failed, krate.errors, krate.warnings
+        ));
+    }
+    if let Some(reported) = result.reported.as_ref() {
+        summary.push_str(&format!(
+            "reported {}, unread {}\n",
+            reported, reported.unread
+        ));
+    }
+    summary
+}
+
+fn check(stderr: &Path) -> Result<(), String> {
+    let output = fs::read_to_string(stderr).map_err(|e| format!("{:?}", e))?;
+    let stdout = fs::read_to_string(stderr.with_extension("stdout")).unwrap_or_default();
+    let result = cargo::parse(false, &output, &stdout)?;
+    let actual = summarize(&result);
+
+    let expected_path = stderr.with_extension("expected");
+    if std::env::var_os("WRE_BLESS").is_some() {
+        return fs::write(&expected_path, actual).map_err(|e| format!("{:?}", e));
+    }
+    let expected = fs::read_to_string(&expected_path).map_err(|e| format!("{:?}", e))?;
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("expected:\n{}\nparsed:\n{}", expected, actual))
+    }
+}
+
+#[test]
+fn fixtures_parse_as_expected() {
+    let mut fixtures = fs::read_dir(FIXTURES)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("stderr")))
+        .collect::<Vec<_>>();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    let failures = fixtures
+        .iter()
+        .filter_map(|path| {
+            check(path)
+                .err()
+                .map(|err| format!("{}: {}", path.display(), err))
+        })
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
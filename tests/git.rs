@@ -0,0 +1,106 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::git::{self as repo, ChangedFiles};
+
+use crate::common::MockRunner;
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n\
+                      warning: unused variable: `x`\n --> src/lib.rs:2:9\n\n";
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn changed_files_match_however_diagnostics_name_them() {
+    let runner = MockRunner::with_stdout(true, "src/main.rs\n");
+    let changes = ChangedFiles::list_with(&runner, "/src/foo", "main").unwrap();
+
+    assert_eq!(changes.len(), 1);
+    assert!(changes.contains("src/main.rs"));
+    assert!(changes.contains("./src/main.rs"));
+    assert!(changes.contains("/src/foo/src/main.rs"));
+    assert!(!changes.contains("src/lib.rs"));
+    assert!(!changes.contains("/src/bar/src/main.rs"));
+}
+
+#[test]
+fn only_diagnostics_in_changed_files_are_kept() {
+    let runner = MockRunner::with_stdout(true, "src/main.rs\n");
+    let changes = ChangedFiles::list_with(&runner, ".", "HEAD").unwrap();
+    let mut result = cargo::parse(false, OUTPUT, "").unwrap();
+
+    changes.retain(&mut result);
+    assert_eq!(result.errors.len(), 1);
+    assert!(result.warnings.is_empty());
+}
+
+#[test]
+fn git_failing_is_an_error() {
+    let runner = MockRunner::new(false, "fatal: not a git repository\n");
+    assert_eq!(
+        ChangedFiles::list_with(&runner, ".", "HEAD"),
+        Err("fatal: not a git repository".to_string())
+    );
+}
+
+#[test]
+fn edited_and_new_files_are_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    fs::write(dir.path().join("edited.rs"), "").unwrap();
+    fs::write(dir.path().join("untouched.rs"), "").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "base"]);
+    fs::write(dir.path().join("edited.rs"), "fn main() {}").unwrap();
+    fs::write(dir.path().join("new.rs"), "").unwrap();
+
+    let changes = ChangedFiles::list(dir.path(), "HEAD").unwrap();
+    assert_eq!(changes.len(), 2);
+    assert!(changes.contains("edited.rs"));
+    assert!(changes.contains("new.rs"));
+    assert!(!changes.contains("untouched.rs"));
+}
+
+#[test]
+fn head_is_read_from_the_repository_up_the_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let project_root = dir.path().join("crates/app");
+    fs::create_dir_all(&project_root).unwrap();
+    assert_eq!(repo::git_dir(&project_root), None);
+
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    let git_dir = repo::git_dir(&project_root).unwrap();
+    assert_eq!(git_dir, dir.path().join(".git"));
+    assert_eq!(repo::head(&git_dir).as_deref(), Some("main"));
+
+    fs::write(
+        dir.path().join(".git/HEAD"),
+        "3f2a9c1d0b5e8a7f6c4d2e1b0a9f8e7d6c5b4a39\n",
+    )
+    .unwrap();
+    assert_eq!(repo::head(&git_dir).as_deref(), Some("3f2a9c1"));
+
+    // a worktree's ".git" says where its repository is
+    fs::write(
+        project_root.join(".git"),
+        "gitdir: ../../.git/worktrees/app\n",
+    )
+    .unwrap();
+    assert_eq!(
+        repo::git_dir(&project_root),
+        Some(project_root.join("../../.git/worktrees/app"))
+    );
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use watch_rust_errors::config::Config;
+use watch_rust_errors::health::{self, Check, Paths};
+use watch_rust_errors::process::{ProcessOutput, ProcessRunner};
+
+// answers with what each program prints, as if the others weren't installed
+struct Tools(HashMap<&'static str, &'static str>);
+
+impl ProcessRunner for Tools {
+    fn run(
+        &self,
+        program: &str,
+        _args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        let stdout = self.0.get(program).ok_or("No such file or directory")?;
+        Ok(ProcessOutput {
+            success: true,
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        })
+    }
+}
+
+fn find<'a>(checks: &'a [Check], name: &str) -> &'a Check {
+    checks.iter().find(|check| check.name == name).unwrap()
+}
+
+#[test]
+fn a_complete_setup_passes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("max_user_watches"), "524288\n").unwrap();
+    let paths = Paths {
+        max_watches: dir.path().join("max_user_watches"),
+        config_file: Some(dir.path().join("config/config.toml")),
+        flatpak_info: dir.path().join("missing"),
+    };
+    let tools = Tools(
+        vec![
+            ("cargo", "cargo 1.75.0 (1d8b05cdd 2023-11-20)\n"),
+            ("rustup", "rustup 1.26.0 (5af9b9484 2023-04-05)\n"),
+            ("gdbus", "(true,)\n"),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    let config = Config {
+        open_command: Some("sh -c 'true' {file}".to_string()),
+        ..Config::default()
+    };
+
+    let checks = health::check_with(&tools, &config, &paths);
+    assert!(checks.iter().all(|check| check.passed), "{:?}", checks);
+    assert_eq!(
+        checks.iter().map(|check| check.name).collect::<Vec<_>>(),
+        vec![
+            "cargo",
+            "rustup",
+            "inotify watches",
+            "notifications",
+            "editor",
+            "config directory"
+        ]
+    );
+    assert_eq!(
+        find(&checks, "cargo").detail,
+        "cargo 1.75.0 (1d8b05cdd 2023-11-20)"
+    );
+    // nothing's left behind by checking
+    assert_eq!(fs::read_dir(dir.path().join("config")).unwrap().count(), 0);
+}
+
+#[test]
+fn whats_missing_comes_with_a_fix() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("max_user_watches"), "8192\n").unwrap();
+    let paths = Paths {
+        max_watches: dir.path().join("max_user_watches"),
+        config_file: None,
+        flatpak_info: dir.path().join("missing"),
+    };
+    let tools = Tools(
+        vec![("cargo", "cargo 1.75.0\n"), ("gdbus", "(false,)\n")]
+            .into_iter()
+            .collect(),
+    );
+    let config = Config {
+        open_command: Some("no-such-editor-wre {file}".to_string()),
+        ..Config::default()
+    };
+
+    let checks = health::check_with(&tools, &config, &paths);
+    assert!(find(&checks, "cargo").passed);
+    let failed: Vec<_> = checks.iter().filter(|check| !check.passed).collect();
+    assert_eq!(
+        failed.iter().map(|check| check.name).collect::<Vec<_>>(),
+        vec![
+            "rustup",
+            "inotify watches",
+            "notifications",
+            "editor",
+            "config directory"
+        ]
+    );
+    assert!(failed.iter().all(|check| check.hint.is_some()));
+    assert!(find(&checks, "editor")
+        .detail
+        .contains("no-such-editor-wre"));
+    assert!(find(&checks, "inotify watches")
+        .hint
+        .as_ref()
+        .unwrap()
+        .contains("max_user_watches"));
+
+    // without desktop notifications, whether there's a daemon doesn't matter,
+    // and neither do inotify's limits where there aren't any
+    let paths = Paths {
+        max_watches: dir.path().join("missing"),
+        ..paths
+    };
+    let tools = Tools(HashMap::new());
+    let config = Config {
+        notifications: serde_json::from_str(r#"[{"kind": "webhook", "url": "http://localhost"}]"#)
+            .unwrap(),
+        ..config
+    };
+    let names: Vec<_> = health::check_with(&tools, &config, &paths)
+        .into_iter()
+        .map(|check| check.name)
+        .collect();
+    assert_eq!(names, vec!["cargo", "rustup", "editor", "config directory"]);
+}
+
+#[test]
+fn a_flatpak_sandbox_is_checked_for_what_it_sees() {
+    let dir = tempfile::tempdir().unwrap();
+    let info = dir.path().join("flatpak-info");
+    fs::write(
+        &info,
+        "[Application]\nname=in.nerdworks.WatchRustErrors\n\n[Context]\nshared=network;ipc;\n",
+    )
+    .unwrap();
+    let paths = Paths {
+        max_watches: dir.path().join("missing"),
+        config_file: Some(dir.path().join("config.toml")),
+        flatpak_info: info.clone(),
+    };
+    let tools = Tools(HashMap::new());
+    let config = Config::default();
+
+    let checks = health::check_with(&tools, &config, &paths);
+    let sandbox = find(&checks, "sandbox");
+    assert!(!sandbox.passed);
+    assert!(sandbox.hint.as_ref().unwrap().contains("--filesystem=home"));
+    // builds need the SDK's Rust rather than rustup's
+    assert!(find(&checks, "cargo")
+        .hint
+        .as_ref()
+        .unwrap()
+        .contains("org.freedesktop.Sdk.Extension.rust-stable"));
+
+    fs::write(&info, "[Context]\nfilesystems=home;xdg-run/gvfs;\n").unwrap();
+    let checks = health::check_with(&tools, &config, &paths);
+    assert!(find(&checks, "sandbox").passed);
+}
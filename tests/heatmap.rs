@@ -0,0 +1,87 @@
+mod common;
+
+use std::fs;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::heatmap::{self, ModuleStats};
+use watch_rust_errors::parser::RustcParser;
+
+use crate::common::Project;
+
+#[test]
+fn modules_are_directories_within_the_project() {
+    assert_eq!(
+        heatmap::module_of("/src/foo", "src/ui/list.rs").as_deref(),
+        Some("src/ui")
+    );
+    assert_eq!(
+        heatmap::module_of("/src/foo", "/src/foo/src/main.rs").as_deref(),
+        Some("src")
+    );
+    assert_eq!(
+        heatmap::module_of("/src/foo", "build.rs").as_deref(),
+        Some(".")
+    );
+    assert_eq!(heatmap::module_of("/src/foo", "/src/bar/src/lib.rs"), None);
+    assert_eq!(heatmap::module_of("/src/foo", "../bar/src/lib.rs"), None);
+}
+
+#[test]
+fn modules_count_lines_and_diagnostics() {
+    let project = Project::new("fn main() {}\n");
+    fs::create_dir_all(project.root().join("src/ui")).unwrap();
+    project.write("src/ui/list.rs", "fn a() {}\nfn b() {}\nfn c() {}\n");
+    fs::create_dir_all(project.root().join("target/debug")).unwrap();
+    project.write("target/debug/build.rs", "fn main() {}\n");
+    let result = cargo::parse(
+        false,
+        "error: expected item\n --> src/ui/list.rs:1:1\n\n\
+         warning: unused\n --> src/ui/list.rs:2:1\n\n",
+        "",
+    )
+    .unwrap();
+
+    let modules = heatmap::module_stats(project.root(), &result, &RustcParser);
+
+    assert_eq!(
+        modules,
+        vec![
+            ModuleStats {
+                path: ".".to_string(),
+                lines: 6,
+                errors: 0,
+                warnings: 0,
+            },
+            ModuleStats {
+                path: "src".to_string(),
+                lines: 1,
+                errors: 0,
+                warnings: 0,
+            },
+            ModuleStats {
+                path: "src/ui".to_string(),
+                lines: 3,
+                errors: 1,
+                warnings: 1,
+            },
+        ]
+    );
+    assert_eq!(modules[1].heat(), 0);
+    assert_eq!(modules[2].heat(), 4);
+}
+
+#[test]
+fn treemap_fills_the_area_in_proportion() {
+    let rows = heatmap::layout(&[6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0], 600.0, 400.0);
+    let rects = rows.iter().flatten().collect::<Vec<_>>();
+
+    assert_eq!(rects.len(), 7);
+    let area: f64 = rects.iter().map(|r| r.width * r.height).sum();
+    assert!((area - 240_000.0).abs() < 1e-6);
+    assert!((rects[0].width * rects[0].height - 60_000.0).abs() < 1e-6);
+    for row in rows.iter() {
+        let width: f64 = row.iter().map(|r| r.width).sum();
+        assert!((width - 600.0).abs() < 1e-6);
+    }
+    assert!(heatmap::layout(&[], 600.0, 400.0).is_empty());
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use watch_rust_errors::cargo::{self, CompileResult};
+use watch_rust_errors::history::{BuildRecord, History, SlowBuilds, Slowdown};
+
+const UNUSED_X: &str = "warning: unused variable: `x`\n  --> src/main.rs:2:9\n\n";
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+fn result(output: &str, secs: Option<u64>) -> CompileResult {
+    let mut result = cargo::parse(false, output, "").unwrap();
+    result.duration = secs.map(Duration::from_secs);
+    result
+}
+
+#[test]
+fn builds_are_counted() {
+    let record = BuildRecord::new(&result(&format!("{}{}", MISMATCH, UNUSED_X), Some(3)));
+
+    assert_eq!(record.errors, 1);
+    assert_eq!(record.warnings, 1);
+    assert_eq!(record.duration, Some(Duration::from_secs(3)));
+    assert!(!record.success);
+}
+
+#[test]
+fn only_the_latest_builds_are_kept() {
+    let mut history = History::new(2);
+    assert!(history.is_empty());
+    assert_eq!(history.average_duration(), None);
+
+    history.push(BuildRecord::new(&result(MISMATCH, Some(2))));
+    history.push(BuildRecord::new(&result(UNUSED_X, None)));
+    history.push(BuildRecord::new(&result(
+        &format!("{}{}", MISMATCH, MISMATCH),
+        Some(4),
+    )));
+
+    assert_eq!(history.error_counts(), vec![0, 2]);
+    assert_eq!(history.last().map(|r| r.errors), Some(2));
+    // builds that fell out of the chart still count towards the average,
+    // those without a duration don't
+    assert_eq!(history.timed_builds(), 2);
+    assert_eq!(history.average_duration(), Some(Duration::from_secs(3)));
+
+    history.clear();
+    assert!(history.is_empty());
+    assert_eq!(history.average_duration(), None);
+}
+
+#[test]
+fn builds_much_slower_than_the_median_stand_out() {
+    let threshold = SlowBuilds::default();
+    let mut history = History::default();
+    let build = |history: &mut History, secs| {
+        history.push(BuildRecord::new(&result("", Some(secs))));
+        history.slowdown(&threshold)
+    };
+
+    // not until there are enough builds to go by
+    for secs in &[10, 4, 6, 5] {
+        assert_eq!(build(&mut history, *secs), None);
+    }
+    assert_eq!(build(&mut history, 30), None);
+    // the median of 10, 4, 6, 5 and 30 is 6
+    assert_eq!(build(&mut history, 8), None);
+    let slowdown = build(&mut history, 11).unwrap();
+    assert_eq!(
+        slowdown,
+        Slowdown {
+            duration: Duration::from_secs(11),
+            median: Duration::from_secs(7),
+        }
+    );
+    assert_eq!(slowdown.percent_longer(), 57);
+
+    // quick builds that are slower by a second don't count
+    let mut quick = History::default();
+    for _ in 0..5 {
+        build(&mut quick, 1);
+    }
+    assert_eq!(build(&mut quick, 2), None);
+
+    let off = SlowBuilds {
+        enabled: false,
+        ..SlowBuilds::default()
+    };
+    quick.push(BuildRecord::new(&result("", Some(60))));
+    assert!(quick.slowdown(&threshold).is_some());
+    assert_eq!(quick.slowdown(&off), None);
+}
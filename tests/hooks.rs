@@ -0,0 +1,113 @@
+mod common;
+
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use glib::MainContext;
+use watch_rust_errors::cargo;
+use watch_rust_errors::config::{LocalConfig, ProjectConfig};
+use watch_rust_errors::hooks::{Hook, Report};
+use watch_rust_errors::watcher::{BuildEvent, Watcher};
+
+use crate::common::{drain_events, MockRunner};
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n\
+                      warning: unused variable: `x`\n --> src/main.rs:2:9\n\n\
+                      warning: unused variable: `y`\n --> src/main.rs:3:9\n\n";
+
+#[test]
+fn hooks_are_told_how_the_build_went() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = cargo::parse(false, OUTPUT, "").unwrap();
+    let report = Report::new(dir.path(), &result);
+
+    let hook = Hook::new(
+        "sh -c 'echo $WRE_SUCCESS $WRE_ERRORS $WRE_WARNINGS $FOO > vars; cat > input.json'",
+    );
+    let env = [("FOO".to_string(), "bar".to_string())];
+    hook.run(dir.path(), &env, &report).unwrap();
+
+    let vars = fs::read_to_string(dir.path().join("vars")).unwrap();
+    assert_eq!(vars.trim(), "false 1 2 bar");
+    let input: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("input.json")).unwrap()).unwrap();
+    assert_eq!(input["success"], false);
+    assert_eq!(input["errors"][0]["num"], "E0308");
+    assert_eq!(input["warnings"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn failing_and_slow_hooks_say_why() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = cargo::parse(true, "", "").unwrap();
+    let report = Report::new(dir.path(), &result);
+
+    let err = Hook::new("sh -c 'echo no light here >&2; exit 2'")
+        .run(dir.path(), &[], &report)
+        .unwrap_err();
+    assert!(err.contains("no light here"));
+
+    let slow = Hook {
+        timeout: Duration::from_millis(200),
+        ..Hook::new("sleep 5")
+    };
+    let err = slow.run(dir.path(), &[], &report).unwrap_err();
+    assert!(err.contains("took longer"));
+}
+
+#[test]
+fn hook_failures_come_after_the_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(dir.path(), "cargo check", runner, tx).unwrap();
+    watcher.set_post_build(vec![Hook::new("touch ran"), Hook::new("false")]);
+
+    assert!(watcher.inject(&[]).unwrap());
+    drop(watcher);
+
+    let events = drain_events(rx)
+        .into_iter()
+        .filter_map(|event| match event {
+            BuildEvent::Finished(_) => Some("finished".to_string()),
+            BuildEvent::HookFailed(err) => Some(err),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0], "finished");
+    assert!(events[1].contains("`false` failed"));
+    assert!(dir.path().join("ran").exists());
+}
+
+#[test]
+fn project_hooks_only_run_once_trusted() {
+    let local: LocalConfig = toml::from_str("post_build = [\"./led\"]\n").unwrap();
+    assert!(local.runs_commands());
+
+    let mut project = ProjectConfig::default();
+    assert!(project
+        .with_local(Some(&local))
+        .post_build_hooks()
+        .is_empty());
+
+    // after the user's own
+    project.trust_local_config = Some(true);
+    project.post_build = vec!["mine".to_string(), " ".to_string()];
+    project.post_build_timeout = Some(3);
+    let hooks = project.with_local(Some(&local)).post_build_hooks();
+    assert_eq!(
+        hooks,
+        vec![
+            Hook {
+                command: "mine".to_string(),
+                timeout: Duration::from_secs(3),
+            },
+            Hook {
+                command: "./led".to_string(),
+                timeout: Duration::from_secs(3),
+            },
+        ]
+    );
+}
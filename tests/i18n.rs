@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use watch_rust_errors::i18n;
+
+#[test]
+fn fills_placeholders_in_order() {
+    let filled = i18n::fill("{} of {} checks failed.".to_string(), &[&2, &"7"]);
+    assert_eq!(filled, "2 of 7 checks failed.");
+
+    let filled = i18n::fill("Vor {} gebaut".to_string(), &[&"3 s"]);
+    assert_eq!(filled, "Vor 3 s gebaut");
+}
+
+#[test]
+fn numbered_placeholders_can_be_reordered() {
+    let filled = i18n::fill("{0} of {1} checks failed.".to_string(), &[&2, &7]);
+    assert_eq!(filled, "2 of 7 checks failed.");
+
+    let filled = i18n::fill(
+        "Von {1} Prüfungen sind {0} fehlgeschlagen.".to_string(),
+        &[&2, &7],
+    );
+    assert_eq!(filled, "Von 7 Prüfungen sind 2 fehlgeschlagen.");
+
+    assert_eq!(i18n::fill("{1} {1}".to_string(), &[&"a", &"b"]), "b b");
+    assert_eq!(i18n::fill("{x} {".to_string(), &[&1]), "{x} {");
+}
+
+#[test]
+fn leaves_placeholders_without_arguments() {
+    assert_eq!(i18n::fill("{} and {}".to_string(), &[&1]), "1 and {}");
+    assert_eq!(i18n::fill("{0} and {1}".to_string(), &[&1]), "1 and {1}");
+    assert_eq!(i18n::fill("Quit".to_string(), &[&1]), "Quit");
+}
+
+#[test]
+fn builds_the_catalogs() {
+    let catalog = concat!(
+        env!("OUT_DIR"),
+        "/locale/de/LC_MESSAGES/watch-rust-errors.mo"
+    );
+    // without msgfmt the build goes on without translations
+    if which("msgfmt") {
+        assert!(Path::new(catalog).is_file());
+    }
+}
+
+fn which(program: &str) -> bool {
+    std::process::Command::new(program)
+        .arg("--version")
+        .output()
+        .is_ok()
+}
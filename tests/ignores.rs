@@ -0,0 +1,48 @@
+use std::fs;
+
+use watch_rust_errors::ignores::IgnoreRules;
+
+#[test]
+fn nested_ignore_files_override_their_parents() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join(".gitignore"), "node_modules/\n*.generated.rs\n").unwrap();
+    fs::create_dir_all(root.join("web/node_modules/left-pad")).unwrap();
+    fs::create_dir_all(root.join("src/gen")).unwrap();
+    fs::write(root.join("src/gen/.ignore"), "!keep.generated.rs\n").unwrap();
+
+    let rules = IgnoreRules::load(root);
+    assert!(!rules.is_empty());
+    assert!(rules.is_ignored(&root.join("web/node_modules/left-pad/index.js")));
+    assert!(rules.is_ignored(&root.join("src/schema.generated.rs")));
+    assert!(rules.is_ignored(&root.join("src/gen/other.generated.rs")));
+    assert!(!rules.is_ignored(&root.join("src/gen/keep.generated.rs")));
+    assert!(!rules.is_ignored(&root.join("src/main.rs")));
+    // somewhere else entirely
+    let elsewhere = root.parent().unwrap().join("elsewhere.generated.rs");
+    assert!(!rules.is_ignored(&elsewhere));
+
+    assert!(IgnoreRules::is_ignore_file(&root.join("src/gen/.ignore")));
+    assert!(!IgnoreRules::is_ignore_file(&root.join("src/main.rs")));
+}
+
+#[test]
+fn projects_without_ignore_files_leave_nothing_out() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+
+    let rules = IgnoreRules::load(dir.path());
+    assert!(rules.is_empty());
+    assert!(!rules.is_ignored(&dir.path().join("src/lib.rs")));
+}
+
+#[test]
+fn rules_that_dont_parse_are_reported_and_the_rest_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(".gitignore"), "target/\n{a,b\n").unwrap();
+
+    let rules = IgnoreRules::load(dir.path());
+    assert_eq!(rules.errors.len(), 1);
+    assert!(rules.errors[0].contains("line 2"));
+    assert!(rules.is_ignored(&dir.path().join("target/debug/foo")));
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{MockRunner, Project};
+use watch_rust_errors::issue::{self, IssueDraft};
+use watch_rust_errors::process::SystemRunner;
+use watch_rust_errors::rust::RustDiagnostic;
+
+const MANIFEST: &str = "[package]\n\
+                        name = \"wre-test\"\n\
+                        version = \"0.1.0\"\n\
+                        repository = \"https://github.com/someone/wre-test.git\"\n";
+
+#[test]
+fn issues_go_to_the_crate_the_diagnostic_is_in() {
+    let project = Project::new("fn main() {}\n");
+    project.write("Cargo.toml", MANIFEST);
+    // not in a repository, so there's no origin to link to
+    let runner = MockRunner::new(false, "fatal: not a git repository");
+    let diag: RustDiagnostic = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n"
+        .parse()
+        .unwrap();
+
+    let draft = IssueDraft::new(
+        &runner,
+        project.root(),
+        &diag,
+        Some("rustc 1.60.0"),
+        "cargo check",
+    )
+    .unwrap();
+    assert_eq!(draft.repository, "https://github.com/someone/wre-test");
+    assert_eq!(draft.title, "error[E0308]: mismatched types");
+    assert!(draft.body.starts_with("At src/main.rs:4:5\n"));
+    assert!(draft
+        .body
+        .contains("- Toolchain: rustc 1.60.0\n- Command: `cargo check`\n"));
+    assert!(draft.url(None).starts_with(
+        "https://github.com/someone/wre-test/issues/new?title=error%5BE0308%5D%3A%20mismatched%20types&body=At%20src"
+    ));
+
+    // a dependency's diagnostics are its own repository's business
+    let dependency = tempfile::tempdir().unwrap();
+    fs::create_dir(dependency.path().join("src")).unwrap();
+    fs::write(
+        dependency.path().join("Cargo.toml"),
+        MANIFEST.replace("someone/wre-test", "else/dep"),
+    )
+    .unwrap();
+    let file = dependency.path().join("src").join("lib.rs");
+    let diag: RustDiagnostic = format!("warning: unused import\n --> {}:1:5\n", file.display())
+        .parse()
+        .unwrap();
+    let draft = IssueDraft::new(&runner, project.root(), &diag, None, "cargo check").unwrap();
+    assert_eq!(draft.repository, "https://github.com/else/dep");
+    assert_eq!(
+        draft.url(Some("https://tracker.example/new?summary={title}")),
+        "https://tracker.example/new?summary=warning%3A%20unused%20import"
+    );
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn permalinks_point_at_the_checked_out_commit() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q"]);
+    fs::create_dir_all(root.join("app").join("src")).unwrap();
+    fs::write(
+        root.join("app").join("src").join("main.rs"),
+        "fn main() {}\n",
+    )
+    .unwrap();
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "first"]);
+    let commit = git(root, &["rev-parse", "HEAD"]);
+    let diag: RustDiagnostic = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n"
+        .parse()
+        .unwrap();
+
+    // a project in a subdirectory is linked to there
+    let project_root = root.join("app");
+    let err = issue::permalink(&SystemRunner, &project_root, &diag, None).unwrap_err();
+    assert!(err.contains("no origin"));
+
+    git(
+        root,
+        &["remote", "add", "origin", "git@gitlab.com:org/app.git"],
+    );
+    assert_eq!(
+        issue::permalink(&SystemRunner, &project_root, &diag, None).unwrap(),
+        format!(
+            "https://gitlab.com/org/app/-/blob/{}/app/src/main.rs#L4",
+            commit
+        )
+    );
+
+    git(
+        root,
+        &[
+            "remote",
+            "set-url",
+            "origin",
+            "https://git.example/org/app.git",
+        ],
+    );
+    assert_eq!(
+        issue::permalink(
+            &SystemRunner,
+            &project_root,
+            &diag,
+            Some("{repository}/src/commit/{commit}/{path}#L{line}")
+        )
+        .unwrap(),
+        format!(
+            "https://git.example/org/app/src/commit/{}/app/src/main.rs#L4",
+            commit
+        )
+    );
+}
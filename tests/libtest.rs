@@ -0,0 +1,78 @@
+use watch_rust_errors::libtest;
+
+const HUMAN: &str = "
+running 4 tests
+test tests::bad ... FAILED
+test tests::boom ... FAILED
+test tests::ok ... ok
+test tests::skip ... ignored
+
+failures:
+
+---- tests::bad stdout ----
+
+thread 'tests::bad' (7476) panicked at src/lib.rs:7:16:
+assertion `left == right` failed: f is wrong
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+---- tests::boom stdout ----
+thread 'tests::boom' panicked at 'kaboom', src/lib.rs:9:17
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::bad
+    tests::boom
+
+test result: FAILED. 1 passed; 2 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+const JSON: &str = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "tests::ok" }
+{ "type": "test", "event": "started", "name": "tests::bad" }
+{ "type": "test", "name": "tests::ok", "event": "ok" }
+{ "type": "test", "name": "tests::bad", "event": "failed", "stdout": "thread 'tests::bad' panicked at src/lib.rs:7:16:\nnope\nnote: run with `RUST_BACKTRACE=1`\n" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 0 }
+"#;
+
+#[test]
+fn parse_human_output() {
+    let result = libtest::parse(HUMAN).unwrap();
+
+    assert_eq!(result.passed, 1);
+    assert_eq!(result.ignored, 1);
+    assert_eq!(result.failures.len(), 2);
+
+    let bad = &result.failures[0];
+    assert_eq!(bad.name, "tests::bad");
+    assert_eq!(bad.file.as_deref(), Some("src/lib.rs"));
+    assert_eq!(bad.line, Some(7));
+    assert_eq!(bad.column, Some(16));
+    assert_eq!(
+        bad.message.as_deref(),
+        Some("assertion `left == right` failed: f is wrong\n  left: 1\n right: 2")
+    );
+
+    let boom = &result.failures[1];
+    assert_eq!(boom.name, "tests::boom");
+    assert_eq!(boom.message.as_deref(), Some("kaboom"));
+    assert_eq!(boom.line, Some(9));
+}
+
+#[test]
+fn parse_json_output() {
+    let result = libtest::parse(JSON).unwrap();
+
+    assert_eq!(result.passed, 1);
+    assert_eq!(result.failures.len(), 1);
+    assert_eq!(result.failures[0].name, "tests::bad");
+    assert_eq!(result.failures[0].message.as_deref(), Some("nope"));
+    assert_eq!(result.failures[0].file.as_deref(), Some("src/lib.rs"));
+}
+
+#[test]
+fn no_tests_means_no_result() {
+    assert!(libtest::parse("    Finished dev [unoptimized] target(s)\n").is_none());
+}
@@ -0,0 +1,86 @@
+use watch_rust_errors::cargo::DiagnosticId;
+use watch_rust_errors::config::Config;
+use watch_rust_errors::list_state::{ListMessage, ListOption, ListState};
+
+#[test]
+fn filters_relist_the_results() {
+    let mut list = ListState::default();
+
+    assert!(list.update(ListMessage::FilterModule(Some("src/net".to_string()))));
+    assert!(list.update(ListMessage::FilterCode(Some("E0308".to_string()))));
+    assert_eq!(list.module_filter.as_deref(), Some("src/net"));
+    assert_eq!(list.code_filter.as_deref(), Some("E0308"));
+
+    assert!(list.update(ListMessage::FilterModule(None)));
+    assert_eq!(list.module_filter, None);
+    assert_eq!(list.code_filter.as_deref(), Some("E0308"));
+}
+
+#[test]
+fn opening_and_closing_leaves_the_results_listed() {
+    let mut list = ListState::default();
+    let id = DiagnosticId(2);
+
+    assert!(!list.update(ListMessage::ToggleCascade(id)));
+    assert!(!list.update(ListMessage::ToggleMacroTrace(id)));
+    assert!(!list.update(ListMessage::ToggleFile(Some("src/main.rs".to_string()))));
+    assert!(!list.update(ListMessage::ToggleCode(None)));
+    assert!(!list.update(ListMessage::ToggleElsewhere));
+    assert!(!list.update(ListMessage::SortCodes(true)));
+    assert!(list.expanded_causes.contains(&id));
+    assert!(list.expanded_macro_traces.contains(&id));
+    assert!(list
+        .collapsed_files
+        .contains(&Some("src/main.rs".to_string())));
+    assert!(list.expanded_codes.contains(&None));
+    assert!(list.show_elsewhere);
+    assert!(list.codes_by_name);
+
+    list.update(ListMessage::ToggleCascade(id));
+    list.update(ListMessage::ToggleElsewhere);
+    assert!(list.expanded_causes.is_empty());
+    assert!(!list.show_elsewhere);
+}
+
+#[test]
+fn new_results_forget_what_was_opened_in_the_last() {
+    let mut list = ListState::default();
+    list.update(ListMessage::ToggleCascade(DiagnosticId(0)));
+    list.update(ListMessage::ToggleMacroTrace(DiagnosticId(1)));
+    list.update(ListMessage::ToggleFile(None));
+    list.update(ListMessage::FilterModule(Some("src/net".to_string())));
+
+    list.forget_expanded();
+    assert!(list.expanded_causes.is_empty());
+    assert!(list.expanded_macro_traces.is_empty());
+    assert!(list.collapsed_files.contains(&None));
+    assert_eq!(list.module_filter.as_deref(), Some("src/net"));
+
+    list.clear();
+    assert_eq!(list.module_filter, None);
+    assert!(list.collapsed_files.contains(&None));
+}
+
+#[test]
+fn grouping_goes_by_file_or_by_code() {
+    let mut config = Config::default();
+
+    assert!(!ListOption::GroupByFile.toggle(&mut config));
+    assert!(config.group_by_file && !config.group_by_code);
+    assert!(!ListOption::GroupByCode.toggle(&mut config));
+    assert!(!config.group_by_file && config.group_by_code);
+    ListOption::GroupByCode.toggle(&mut config);
+    assert!(!config.group_by_file && !config.group_by_code);
+}
+
+#[test]
+fn options_that_narrow_the_list_relist_it() {
+    let mut config = Config::default();
+
+    assert!(ListOption::FocusFile.toggle(&mut config));
+    assert!(ListOption::HideBaseline.toggle(&mut config));
+    assert!(!ListOption::ShowDuplicates.toggle(&mut config));
+    assert!(!ListOption::ShowChanges.toggle(&mut config));
+    assert!(config.focus_file && config.hide_baseline);
+    assert!(config.show_duplicates && config.show_changes);
+}
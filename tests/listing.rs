@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+use watch_rust_errors::cargo::{self, CompileResult, DiagnosticId};
+use watch_rust_errors::cascade::Cascades;
+use watch_rust_errors::config::SortOrder;
+use watch_rust_errors::listing::{self, Listing, Pane};
+
+const OUTPUT: &str = "error[E0308]: mismatched types
+ --> src/main.rs:4:5
+
+warning: unused variable: `x`
+ --> src/net/client.rs:2:9
+
+error[E0599]: no method named `len` found for struct `Foo`
+ --> src/net/client.rs:8:7
+
+";
+
+struct State {
+    cascades: Cascades,
+    expanded_causes: HashSet<DiagnosticId>,
+    collapsed_files: HashSet<Option<String>>,
+    expanded_codes: HashSet<Option<String>>,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            cascades: Cascades::default(),
+            expanded_causes: HashSet::new(),
+            collapsed_files: HashSet::new(),
+            expanded_codes: HashSet::new(),
+        }
+    }
+
+    fn listing(&self) -> Listing<'_> {
+        Listing {
+            project_root: "/project",
+            group_by_file: false,
+            group_by_code: false,
+            sort_order: SortOrder::Compiler,
+            module_filter: None,
+            code_filter: None,
+            changed_files: None,
+            new_diagnostics: None,
+            focused_file: None,
+            show_elsewhere: false,
+            cascades: &self.cascades,
+            expanded_causes: &self.expanded_causes,
+            collapsed_files: &self.collapsed_files,
+            expanded_codes: &self.expanded_codes,
+            pane: None,
+        }
+    }
+}
+
+fn result() -> CompileResult {
+    cargo::parse(false, OUTPUT, "").unwrap()
+}
+
+fn ids(order: Vec<Vec<DiagnosticId>>) -> Vec<Vec<usize>> {
+    order
+        .into_iter()
+        .map(|group| group.into_iter().map(|id| id.0).collect())
+        .collect()
+}
+
+#[test]
+fn collapsed_files_keep_only_their_header() {
+    let result = result();
+    let mut state = State::new();
+    state
+        .collapsed_files
+        .insert(Some("src/net/client.rs".to_string()));
+    let listing = Listing {
+        group_by_file: true,
+        ..state.listing()
+    };
+
+    assert_eq!(ids(listing.display_order(&result)), vec![vec![0]]);
+    // both headers and the one diagnostic left
+    assert_eq!(listing.total_rows(&result), 3);
+}
+
+#[test]
+fn lists_narrow_down_to_a_module() {
+    let result = result();
+    let state = State::new();
+    let listing = Listing {
+        module_filter: Some("src/net"),
+        ..state.listing()
+    };
+
+    let groups = listing.file_groups(&result);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].file, Some("src/net/client.rs"));
+    assert_eq!((groups[0].errors, groups[0].warnings), (1, 1));
+}
+
+#[test]
+fn lists_narrow_down_to_a_code() {
+    let result = result();
+    let state = State::new();
+    let listing = Listing {
+        code_filter: Some("E0599"),
+        ..state.listing()
+    };
+
+    assert_eq!(ids(listing.display_order(&result)), vec![vec![1]]);
+    assert_eq!(listing.total_rows(&result), 1);
+}
+
+#[test]
+fn codes_are_counted_the_most_first_or_by_code() {
+    let output = format!(
+        "{}error[E0599]: no method named `push` found for struct `Bar`\n --> src/lib.rs:3:7\n\n",
+        OUTPUT
+    );
+    let result = cargo::parse(false, &output, "").unwrap();
+
+    let counts = listing::code_counts(&result, false)
+        .into_iter()
+        .map(|g| (g.code, g.errors, g.warnings))
+        .collect::<Vec<_>>();
+    // the warning has no code to go by
+    assert_eq!(counts, vec![(Some("E0599"), 2, 0), (Some("E0308"), 1, 0)]);
+
+    let codes = listing::code_counts(&result, true)
+        .into_iter()
+        .map(|g| g.code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, vec![Some("E0308"), Some("E0599")]);
+}
+
+#[test]
+fn a_hidden_baseline_leaves_only_whats_new() {
+    let result = result();
+    let state = State::new();
+    let new_diagnostics = vec![DiagnosticId(1)].into_iter().collect();
+    let listing = Listing {
+        new_diagnostics: Some(&new_diagnostics),
+        ..state.listing()
+    };
+
+    assert_eq!(ids(listing.display_order(&result)), vec![vec![1]]);
+    assert_eq!(listing.total_rows(&result), 1);
+}
+
+#[test]
+fn other_files_go_after_the_focused_one() {
+    let result = result();
+    let state = State::new();
+    let listing = Listing {
+        focused_file: Some("src/net/client.rs"),
+        ..state.listing()
+    };
+
+    assert_eq!(ids(listing.display_order(&result)), vec![vec![2, 1]]);
+    assert_eq!(listing.elsewhere(&result).len(), 1);
+    // the section's header stands in for what's in it
+    assert_eq!(listing.total_rows(&result), 3);
+
+    let listing = Listing {
+        show_elsewhere: true,
+        ..listing
+    };
+    assert_eq!(
+        ids(listing.display_order(&result)),
+        vec![vec![2, 1], vec![0]]
+    );
+    assert_eq!(listing.total_rows(&result), 4);
+}
+
+#[test]
+fn one_diagnostic_stands_in_for_each_code() {
+    let output = format!(
+        "{}error[E0308]: mismatched types\n --> src/lib.rs:9:1\n\n",
+        OUTPUT
+    );
+    let result = cargo::parse(false, &output, "").unwrap();
+    let mut state = State::new();
+    let listing = Listing {
+        group_by_code: true,
+        group_by_file: true,
+        ..state.listing()
+    };
+
+    let groups = listing.code_groups(&result);
+    let codes = groups.iter().map(|g| g.code).collect::<Vec<_>>();
+    // the most common code first, and no code last
+    assert_eq!(codes, vec![Some("E0308"), Some("E0599"), None]);
+    assert_eq!((groups[0].errors, groups[0].warnings), (2, 0));
+    assert_eq!(
+        ids(listing.display_order(&result)),
+        vec![vec![0], vec![1], vec![3]]
+    );
+    // a header and a diagnostic per code
+    assert_eq!(listing.total_rows(&result), 6);
+
+    state.expanded_codes.insert(Some("E0308".to_string()));
+    let listing = Listing {
+        group_by_code: true,
+        ..state.listing()
+    };
+    assert_eq!(listing.total_rows(&result), 7);
+}
+
+#[test]
+fn counts_are_shown_with_separators() {
+    assert_eq!(listing::thousands(0), "0");
+    assert_eq!(listing::thousands(999), "999");
+    assert_eq!(listing::thousands(4_213), "4,213");
+    assert_eq!(listing::thousands(1_234_567), "1,234,567");
+}
+
+#[test]
+fn files_with_the_most_errors_come_first() {
+    let mut result = result();
+    // a second error in main.rs, spanning lines 3 to 5 around the first
+    let mut error = result.errors[0].clone();
+    error.line = Some(3);
+    error.end_line = Some(5);
+    result.errors.push(error);
+    let state = State::new();
+    let listing = state.listing();
+
+    let groups = listing.file_groups(&result);
+    assert_eq!(groups[0].file, Some("src/main.rs"));
+    assert_eq!(groups[0].lines(), 3);
+    assert_eq!(groups[1].file, Some("src/net/client.rs"));
+    assert_eq!(groups[1].lines(), 2);
+
+    // with as many errors each, warnings decide, unless going by path
+    result.errors.pop();
+    assert_eq!(
+        listing.file_groups(&result)[0].file,
+        Some("src/net/client.rs")
+    );
+    let listing = Listing {
+        sort_order: SortOrder::File,
+        ..listing
+    };
+    assert_eq!(listing.file_groups(&result)[0].file, Some("src/main.rs"));
+}
+
+#[test]
+fn panes_split_errors_from_warnings() {
+    let result = result();
+    let state = State::new();
+    let errors = Listing {
+        group_by_file: true,
+        pane: Some(Pane::Errors),
+        ..state.listing()
+    };
+    let warnings = Listing {
+        pane: Some(Pane::Warnings),
+        ..errors
+    };
+
+    assert_eq!(errors.count_listed(&result), 2);
+    assert_eq!(ids(errors.display_order(&result)), vec![vec![0], vec![1]]);
+    let groups = errors.file_groups(&result);
+    assert_eq!((groups[1].errors, groups[1].warnings), (1, 0));
+
+    assert_eq!(warnings.count_listed(&result), 1);
+    assert_eq!(ids(warnings.display_order(&result)), vec![vec![2]]);
+    assert_eq!(warnings.total_rows(&result), 2);
+    assert_eq!(state.listing().count_listed(&result), 3);
+}
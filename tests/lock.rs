@@ -0,0 +1,120 @@
+use std::process;
+
+use watch_rust_errors::cargo::CompileResult;
+use watch_rust_errors::lock::{Acquire, ProjectLock, ProjectStatus, StatusReader};
+
+fn acquire(state_dir: &tempfile::TempDir, root: &tempfile::TempDir) -> Acquire {
+    ProjectLock::acquire_in(state_dir.path(), root.path()).unwrap()
+}
+
+#[test]
+fn second_acquire_reports_the_owner() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    let _lock = match acquire(&state_dir, &root) {
+        Acquire::Owned(lock) => lock,
+        Acquire::HeldBy(pid) => panic!("root already held by {}", pid),
+    };
+    match acquire(&state_dir, &root) {
+        Acquire::HeldBy(pid) => assert_eq!(pid, process::id()),
+        Acquire::Owned(_) => panic!("root was locked twice"),
+    }
+}
+
+#[test]
+fn dropping_the_lock_releases_the_root() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    drop(acquire(&state_dir, &root));
+    assert!(matches!(acquire(&state_dir, &root), Acquire::Owned(_)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn stale_locks_are_replaced() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    let lock = match acquire(&state_dir, &root) {
+        Acquire::Owned(lock) => lock,
+        Acquire::HeldBy(pid) => panic!("root already held by {}", pid),
+    };
+    // pretend the owner crashed
+    for entry in std::fs::read_dir(state_dir.path()).unwrap() {
+        std::fs::write(entry.unwrap().path(), u32::MAX.to_string()).unwrap();
+    }
+    std::mem::forget(lock);
+
+    assert!(matches!(acquire(&state_dir, &root), Acquire::Owned(_)));
+}
+
+#[test]
+fn taking_over_revokes_the_previous_lock() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    let lock = match acquire(&state_dir, &root) {
+        Acquire::Owned(lock) => lock,
+        Acquire::HeldBy(pid) => panic!("root already held by {}", pid),
+    };
+    assert!(lock.is_held());
+
+    // another instance takes over
+    for entry in std::fs::read_dir(state_dir.path()).unwrap() {
+        std::fs::write(entry.unwrap().path(), "1").unwrap();
+    }
+    assert!(!lock.is_held());
+
+    let new_lock = ProjectLock::take_over_in(state_dir.path(), root.path()).unwrap();
+    assert!(new_lock.is_held());
+}
+
+#[test]
+fn followers_see_published_results() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    let lock = match acquire(&state_dir, &root) {
+        Acquire::Owned(lock) => lock,
+        Acquire::HeldBy(pid) => panic!("root already held by {}", pid),
+    };
+    let mut reader = StatusReader::new_in(state_dir.path(), root.path()).unwrap();
+    assert!(reader.poll().unwrap().is_none());
+
+    let result = CompileResult {
+        success: true,
+        ..Default::default()
+    };
+    lock.write_status(&result).unwrap();
+
+    let published = reader.poll().unwrap().unwrap();
+    assert!(published.success);
+    assert!(reader.poll().unwrap().is_none());
+}
+
+#[test]
+fn project_status_follows_the_watch() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+    let mut status =
+        ProjectStatus::new_in(state_dir.path(), root.path().to_str().unwrap()).unwrap();
+    assert!(!status.poll().unwrap());
+    assert_eq!(status.watched_by, None);
+
+    let lock = match acquire(&state_dir, &root) {
+        Acquire::Owned(lock) => lock,
+        Acquire::HeldBy(pid) => panic!("root already held by {}", pid),
+    };
+    lock.write_status(&CompileResult::default()).unwrap();
+    assert!(status.poll().unwrap());
+    assert_eq!(status.watched_by, Some(process::id()));
+    assert!(status.latest.is_some());
+
+    // the results outlive the watch
+    drop(lock);
+    assert!(status.poll().unwrap());
+    assert_eq!(status.watched_by, None);
+    assert!(status.latest.is_some());
+}
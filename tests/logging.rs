@@ -0,0 +1,61 @@
+use std::fs;
+use std::time::{Duration, UNIX_EPOCH};
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::logging::RunLog;
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+fn lines(log: &RunLog, n: usize) -> Vec<serde_json::Value> {
+    fs::read_to_string(log.rotated(n))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn every_run_is_a_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = RunLog::new(dir.path());
+    let mut result = cargo::parse(false, OUTPUT, "").unwrap();
+    result.duration = Some(Duration::from_millis(2310));
+    result.finished_at = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    log.record("cargo check", &result).unwrap();
+    log.record("cargo clippy", &cargo::parse(true, "", "").unwrap())
+        .unwrap();
+
+    assert!(log.path.starts_with(dir.path().join(".watch-rust-errors")));
+    let runs = lines(&log, 0);
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0]["at"], 1_700_000_000_000u64);
+    assert_eq!(runs[0]["duration_ms"], 2310);
+    assert_eq!(runs[0]["command"], "cargo check");
+    assert_eq!(runs[0]["success"], false);
+    assert_eq!(runs[0]["errors"][0]["num"], "E0308");
+    assert_eq!(runs[1]["command"], "cargo clippy");
+    assert_eq!(runs[1]["errors"], serde_json::json!([]));
+}
+
+#[test]
+fn full_logs_are_moved_aside() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = cargo::parse(false, OUTPUT, "").unwrap();
+    // room for one run per file
+    let log = RunLog {
+        max_size: 100,
+        ..RunLog::new(dir.path())
+    };
+
+    for command in &["first", "second", "third", "fourth", "fifth"] {
+        log.record(command, &result).unwrap();
+    }
+
+    let commands: Vec<_> = (0..4)
+        .map(|n| lines(&log, n)[0]["command"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(commands, vec!["fifth", "fourth", "third", "second"]);
+    // only so many are kept
+    assert!(!log.rotated(4).exists());
+}
@@ -0,0 +1,126 @@
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::path::Path;
+
+use serde_json::json;
+use watch_rust_errors::cargo::{self, CompileResult};
+use watch_rust_errors::lsp::{self, LspServer, Publisher};
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+const UNUSED_X: &str = "warning: unused variable: `x`\n  --> src/lib.rs:2:9\n\n";
+
+fn result(output: &str) -> CompileResult {
+    cargo::parse(false, output, "").unwrap()
+}
+
+#[test]
+fn diagnostics_are_zero_based() {
+    let result = result(MISMATCH);
+
+    assert_eq!(
+        lsp::diagnostic(&result.errors[0]),
+        json!({
+            "range": {
+                "start": { "line": 3, "character": 4 },
+                "end": { "line": 3, "character": 4 },
+            },
+            "severity": 1,
+            "source": "watch-rust-errors",
+            "message": "mismatched types",
+            "code": "E0308",
+        })
+    );
+}
+
+#[test]
+fn ranges_end_where_the_compiler_says() {
+    let mut diag = result(MISMATCH).errors.remove(0);
+    diag.end_line = Some(4);
+    diag.end_column = Some(10);
+
+    assert_eq!(
+        lsp::diagnostic(&diag)["range"],
+        json!({
+            "start": { "line": 3, "character": 4 },
+            "end": { "line": 3, "character": 9 },
+        })
+    );
+}
+
+#[test]
+fn files_that_are_fixed_are_cleared() {
+    let root = Path::new("/src/my project");
+    let mut publisher = Publisher::default();
+
+    let published = publisher.publish(root, &result(&format!("{}{}", MISMATCH, UNUSED_X)));
+    let uris = published
+        .iter()
+        .map(|n| n["params"]["uri"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uris,
+        vec![
+            "file:///src/my%20project/src/lib.rs",
+            "file:///src/my%20project/src/main.rs"
+        ]
+    );
+
+    let published = publisher.publish(root, &result(MISMATCH));
+    assert_eq!(published.len(), 2);
+    assert_eq!(
+        published[0]["params"]["uri"],
+        "file:///src/my%20project/src/lib.rs"
+    );
+    assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+    assert_eq!(publisher.current().len(), 1);
+}
+
+#[test]
+fn messages_survive_framing() {
+    let message = json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} });
+    let framed = lsp::encode(&message);
+    assert!(framed.starts_with(b"Content-Length: "));
+
+    let mut reader = &framed[..];
+    assert_eq!(lsp::decode(&mut reader).unwrap(), Some(message));
+    assert_eq!(lsp::decode(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn messages_too_large_are_refused() {
+    let mut reader = &b"Content-Length: 4000000000\r\n\r\n{}"[..];
+    assert!(lsp::decode(&mut reader).is_err());
+
+    let header = format!("X-Padding: {}\r\n\r\n", "a".repeat(10_000));
+    assert!(lsp::decode(&mut header.as_bytes()).is_err());
+}
+
+#[test]
+fn editors_are_sent_diagnostics_once_initialized() {
+    let server = LspServer::start("127.0.0.1:0").unwrap();
+    server.publish(Path::new("/src/foo"), &result(MISMATCH));
+
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    stream
+        .write_all(&lsp::encode(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {},
+        })))
+        .unwrap();
+
+    let response = lsp::decode(&mut reader).unwrap().unwrap();
+    assert_eq!(response["id"], 1);
+    assert!(response["result"]["capabilities"].is_object());
+
+    // what was there before connecting, then what comes after
+    let notification = lsp::decode(&mut reader).unwrap().unwrap();
+    assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+    assert_eq!(notification["params"]["uri"], "file:///src/foo/src/main.rs");
+
+    server.publish(Path::new("/src/foo"), &result(""));
+    let notification = lsp::decode(&mut reader).unwrap().unwrap();
+    assert_eq!(notification["params"]["diagnostics"], json!([]));
+}
@@ -0,0 +1,250 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use watch_rust_errors::cargo;
+use watch_rust_errors::config::Config;
+use watch_rust_errors::history::{SlowBuilds, Slowdown};
+use watch_rust_errors::notifications::{Notice, Notifier, NotifyEvent, SinkConfig, SinkKind};
+
+use crate::common::MockRunner;
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+fn notice() -> Notice {
+    Notice::for_build(
+        "/src/foo",
+        Some(true),
+        &cargo::parse(false, MISMATCH, "").unwrap(),
+    )
+    .unwrap()
+}
+
+// the JSON a run of curl sent
+fn payload(args: &[String]) -> Value {
+    let data = args.iter().position(|arg| arg == "--data").unwrap();
+    serde_json::from_str(&args[data + 1]).unwrap()
+}
+
+// the URL and headers a run of curl was given on its stdin
+fn curl_config(runner: &MockRunner, call: usize) -> String {
+    runner.inputs.lock().unwrap()[call].clone()
+}
+
+#[test]
+fn only_failures_and_fixes_are_news() {
+    let failed = cargo::parse(false, MISMATCH, "").unwrap();
+    let succeeded = cargo::parse(true, "", "").unwrap();
+
+    assert_eq!(notice().event, NotifyEvent::BuildFailed);
+    assert_eq!(notice().title, "Build failed with 1 error(s)");
+    assert_eq!(
+        Notice::for_build("/src/foo", Some(false), &succeeded).map(|notice| notice.event),
+        Some(NotifyEvent::BuildFixed)
+    );
+    assert_eq!(Notice::for_build("/src/foo", Some(true), &succeeded), None);
+    assert_eq!(Notice::for_build("/src/foo", None, &succeeded), None);
+    assert_eq!(
+        Notice::for_build("/src/foo", None, &failed).map(|notice| notice.event),
+        Some(NotifyEvent::BuildFailed)
+    );
+    // only the build that broke things is
+    assert_eq!(Notice::for_build("/src/foo", Some(false), &failed), None);
+}
+
+#[test]
+fn slow_builds_are_only_news_to_sinks_that_ask() {
+    let slowdown = Slowdown {
+        duration: Duration::from_secs(12),
+        median: Duration::from_secs(8),
+    };
+    let notice = Notice::for_slow_build("/src/foo", &slowdown);
+    assert_eq!(notice.event, NotifyEvent::SlowBuild);
+    assert_eq!(
+        notice.title,
+        "Build took 50% longer than usual (12.0 s, median 8.0 s)"
+    );
+
+    let config: Config = toml::from_str(
+        r#"
+[slow_builds]
+factor = 2.0
+
+[[notifications]]
+kind = "desktop"
+events = ["slow-build"]
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.slow_builds,
+        SlowBuilds {
+            factor: 2.0,
+            ..SlowBuilds::default()
+        }
+    );
+    assert_eq!(config.notifications[0].events, vec![NotifyEvent::SlowBuild]);
+    assert!(!Config::default().notification_sinks()[0]
+        .events
+        .contains(&NotifyEvent::SlowBuild));
+}
+
+#[test]
+fn sinks_are_configured_per_event() {
+    let config: Config = toml::from_str(
+        r#"
+[[notifications]]
+kind = "slack"
+url = "https://hooks.example.com/1"
+
+[[notifications]]
+kind = "desktop"
+events = ["scheduled-findings"]
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.notification_sinks(),
+        vec![
+            SinkConfig {
+                kind: SinkKind::Slack {
+                    url: "https://hooks.example.com/1".to_string()
+                },
+                events: vec![NotifyEvent::BuildFailed, NotifyEvent::BuildFixed],
+            },
+            SinkConfig {
+                kind: SinkKind::Desktop,
+                events: vec![NotifyEvent::ScheduledFindings],
+            },
+        ]
+    );
+
+    // saving keeps them as they were
+    let saved: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+    assert_eq!(saved.notifications, config.notifications);
+
+    // scheduled jobs kept notifying the desktop before sinks were configurable
+    assert_eq!(
+        Config::default().notification_sinks()[0].kind,
+        SinkKind::Desktop
+    );
+}
+
+#[test]
+fn webhooks_are_posted_only_the_events_they_want() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let notifier = Notifier::remote_with(
+        runner.clone(),
+        &[
+            SinkConfig {
+                kind: SinkKind::Webhook {
+                    url: "https://example.com/hook".to_string(),
+                },
+                events: vec![NotifyEvent::BuildFailed],
+            },
+            SinkConfig {
+                kind: SinkKind::Slack {
+                    url: "https://hooks.example.com/1".to_string(),
+                },
+                events: vec![NotifyEvent::BuildFixed],
+            },
+            // left to the app
+            SinkConfig {
+                kind: SinkKind::Desktop,
+                events: vec![NotifyEvent::BuildFailed],
+            },
+        ],
+    );
+
+    assert!(notifier.wants(NotifyEvent::BuildFailed));
+    assert!(!notifier.wants(NotifyEvent::ScheduledFindings));
+    notifier.send(&notice()).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    let (program, args, _) = &calls[0];
+    assert_eq!(program, "curl");
+    assert_eq!(
+        curl_config(&runner, 0),
+        "url = \"https://example.com/hook\"\n"
+    );
+    assert_eq!(payload(args)["event"], "build-failed");
+    assert_eq!(payload(args)["body"], "/src/foo");
+}
+
+#[test]
+fn matrix_messages_go_to_the_room() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let notifier = Notifier::remote_with(
+        runner.clone(),
+        &[SinkConfig {
+            kind: SinkKind::Matrix {
+                homeserver: "https://matrix.example.org/".to_string(),
+                room: "!abc:example.org".to_string(),
+                access_token: "secret".to_string(),
+            },
+            events: vec![NotifyEvent::BuildFailed],
+        }],
+    );
+    notifier.send(&notice()).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    let (_, args, _) = &calls[0];
+    let config = curl_config(&runner, 0);
+    assert!(config.starts_with(
+        "url = \"https://matrix.example.org/_matrix/client/v3/rooms/%21abc%3Aexample.org/send/m.room.message/"
+    ));
+    assert!(config.ends_with("header = \"Authorization: Bearer secret\"\n"));
+    assert!(args.contains(&"PUT".to_string()));
+    assert_eq!(payload(args)["msgtype"], "m.text");
+    // where `ps` would show it
+    assert!(!args.iter().any(|arg| arg.contains("secret")));
+}
+
+#[test]
+fn emails_go_through_sendmail() {
+    let config: Config = toml::from_str(
+        r#"
+[[notifications]]
+kind = "email"
+to = "me@example.org"
+"#,
+    )
+    .unwrap();
+    let runner = Arc::new(MockRunner::new(true, ""));
+    Notifier::remote_with(runner.clone(), &config.notifications)
+        .send(&notice())
+        .unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    let (program, args, _) = &calls[0];
+    assert_eq!(program, "sendmail");
+    assert_eq!(args, &["-t", "-i"]);
+    assert_eq!(
+        runner.inputs.lock().unwrap()[0],
+        "To: me@example.org\n\
+         Subject: Build failed with 1 error(s)\n\
+         Content-Type: text/plain; charset=utf-8\n\
+         \n\
+         /src/foo\n"
+    );
+}
+
+#[test]
+fn failed_deliveries_are_errors() {
+    let notifier = Notifier::remote_with(
+        Arc::new(MockRunner::new(false, "curl: (22) 404\n")),
+        &[SinkConfig {
+            kind: SinkKind::Webhook {
+                url: "https://example.com/hook".to_string(),
+            },
+            events: vec![NotifyEvent::BuildFailed],
+        }],
+    );
+    assert_eq!(
+        notifier.send(&notice()),
+        Err("Could not send a notification: curl: (22) 404".to_string())
+    );
+}
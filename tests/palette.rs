@@ -0,0 +1,51 @@
+use watch_rust_errors::palette::{self, Command};
+
+fn commands() -> Vec<Command> {
+    vec![
+        Command::new("Start watching", "win.toggle-watch"),
+        Command::new("Theme: Dark", "win.theme::dark"),
+        Command::new("Group by file", "win.group-by-file"),
+        Command::new("Focus on the file edited last", "win.focus-file"),
+        Command::new("Export…", "win.export"),
+    ]
+}
+
+#[test]
+fn commands_are_found_by_letters_in_order() {
+    let commands = commands();
+    let labels = |query| {
+        palette::filter(&commands, query)
+            .into_iter()
+            .map(|command| command.label.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(labels("").len(), commands.len());
+    assert_eq!(labels("dark"), vec!["Theme: Dark"]);
+    assert_eq!(labels("EXP"), vec!["Export…"]);
+    // the start of words counts for more than letters in between
+    assert_eq!(labels("gbf"), vec!["Group by file"]);
+    assert_eq!(
+        labels("fi"),
+        vec!["Group by file", "Focus on the file edited last"]
+    );
+    assert!(labels("zzz").is_empty());
+    assert!(labels("krad").is_empty());
+}
+
+#[test]
+fn detailed_actions_are_split() {
+    assert_eq!(
+        palette::parse_action("win.theme::dark"),
+        Some(("win", "theme", Some("dark")))
+    );
+    assert_eq!(
+        palette::parse_action("win.profile::ci::nightly"),
+        Some(("win", "profile", Some("ci::nightly")))
+    );
+    assert_eq!(
+        palette::parse_action("app.quit"),
+        Some(("app", "quit", None))
+    );
+    assert_eq!(palette::parse_action("quit"), None);
+}
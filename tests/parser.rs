@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use watch_rust_errors::config::{Config, ParserKind};
+use watch_rust_errors::parser::{GccParser, Parser, PassthroughParser, RustcParser};
+use watch_rust_errors::rust::Type;
+
+const GCC: &str = "make: Entering directory '/src/foo'
+gcc -Wall -c main.c
+main.c: In function 'main':
+main.c:4:9: warning: unused variable 'y' [-Wunused-variable]
+    4 |     int y;
+      |         ^
+main.c:5:5: error: 'x' undeclared (first use in this function)
+    5 |     x = 1;
+      |     ^
+main.c:5:5: note: each undeclared identifier is reported only once
+util.h:2: error: expected ';' before '}' token
+make: *** [Makefile:2: main.o] Error 1
+";
+
+#[test]
+fn gcc_diagnostics_are_parsed() {
+    let result = GccParser.parse(false, GCC, GCC).unwrap();
+
+    assert!(!result.success);
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.errors.len(), 2);
+
+    let warning = &result.warnings[0];
+    assert_eq!(warning.type_, Type::Warning);
+    assert_eq!(warning.message, "unused variable 'y'");
+    assert_eq!(warning.location().as_deref(), Some("main.c:4:9"));
+    assert_eq!(warning.lint.as_deref(), Some("-Wunused-variable"));
+
+    let error = &result.errors[0];
+    assert_eq!(error.message, "'x' undeclared (first use in this function)");
+    assert_eq!(
+        error.details.as_deref(),
+        Some(
+            "main.c:5:5: error: 'x' undeclared (first use in this function)\n    \
+             5 |     x = 1;\n      \
+             |     ^\n\
+             main.c:5:5: note: each undeclared identifier is reported only once"
+        )
+    );
+
+    let error = &result.errors[1];
+    assert_eq!(error.location().as_deref(), Some("util.h:2:-"));
+}
+
+#[test]
+fn passthrough_only_reports_success() {
+    let result = PassthroughParser.parse(false, GCC, GCC).unwrap();
+
+    assert!(!result.success);
+    assert_eq!(result.diagnostics().count(), 0);
+}
+
+#[test]
+fn parsers_watch_their_own_sources() {
+    assert!(RustcParser.watches(Path::new("src/main.rs")));
+    assert!(!RustcParser.watches(Path::new("main.c")));
+
+    assert!(GccParser.watches(Path::new("src/main.c")));
+    assert!(GccParser.watches(Path::new("Makefile")));
+    assert!(!GccParser.watches(Path::new("src/main.rs")));
+    assert!(!GccParser.watches(Path::new("build/CMakeFiles/main.c")));
+
+    assert!(PassthroughParser.watches(Path::new("notes.txt")));
+}
+
+const ESLINT: &str = "
+[matchers.eslint]
+regexp = '^(.+):(\\d+):(\\d+): (error|warning) (.+?)(?: \\[(.+)\\])?$'
+file = 1
+line = 2
+column = 3
+severity = 4
+message = 5
+code = 6
+extensions = [\"js\"]
+";
+
+#[test]
+fn problem_matchers_come_from_the_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, ESLINT).unwrap();
+    let config = Config::load_from(&path).unwrap();
+
+    let matcher = config
+        .parser(&ParserKind::Matcher("eslint".to_string()))
+        .unwrap();
+    let result = matcher
+        .parse(
+            false,
+            "src/app.js:3:7: warning 'x' is unused [no-unused-vars]\n\
+             src/app.js:9:1: error Parsing error\n\
+             2 problems\n",
+            "",
+        )
+        .unwrap();
+
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.errors.len(), 1);
+    let warning = &result.warnings[0];
+    assert_eq!(warning.location().as_deref(), Some("src/app.js:3:7"));
+    assert_eq!(warning.message, "'x' is unused");
+    assert_eq!(warning.num.as_deref(), Some("no-unused-vars"));
+    assert!(matcher.watches(Path::new("src/app.js")));
+    assert!(!matcher.watches(Path::new("src/app.rs")));
+}
+
+#[test]
+fn invalid_problem_matchers_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+
+    std::fs::write(&path, ESLINT.replace("code = 6", "code = 7")).unwrap();
+    assert!(Config::load_from(&path).is_err());
+
+    std::fs::write(&path, ESLINT.replace("(error|warning)", "(error|warning")).unwrap();
+    assert!(Config::load_from(&path).is_err());
+
+    std::fs::write(&path, ESLINT.replace("eslint", "gcc")).unwrap();
+    assert!(Config::load_from(&path).is_err());
+
+    std::fs::write(&path, "[projects.\"/src/foo\"]\nparser = \"eslint\"\n").unwrap();
+    assert!(Config::load_from(&path).is_err());
+}
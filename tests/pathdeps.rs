@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+
+use watch_rust_errors::pathdeps;
+use watch_rust_errors::process::SystemRunner;
+
+fn write_package(dir: &Path, name: &str, dependencies: &str) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+             [dependencies]\n{}",
+            name, dependencies
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("src/lib.rs"), "").unwrap();
+}
+
+#[test]
+fn path_dependencies_outside_the_project_are_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let app = root.join("app");
+    write_package(
+        &app,
+        "app",
+        "shared = { path = \"../shared-lib\" }\ninner = { path = \"vendor/inner\" }\n\n[workspace]\n",
+    );
+    write_package(&app.join("vendor/inner"), "inner", "");
+    write_package(&root.join("shared-lib"), "shared", "");
+
+    let roots = pathdeps::load(&SystemRunner, &app, &[]).unwrap();
+    assert_eq!(roots, vec![root.join("shared-lib")]);
+}
+
+#[test]
+fn nested_and_registry_packages_are_left_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let manifest = |path: &str| root.join(path).join("Cargo.toml");
+    let metadata = serde_json::json!({
+        "packages": [
+            { "name": "app", "source": null, "manifest_path": manifest("app") },
+            { "name": "libs", "source": null, "manifest_path": manifest("libs") },
+            { "name": "util", "source": null, "manifest_path": manifest("libs/util") },
+            {
+                "name": "serde",
+                "source": "registry+https://github.com/rust-lang/crates.io-index",
+                "manifest_path": manifest("registry/serde"),
+            },
+        ]
+    });
+
+    let roots = pathdeps::outside_roots(&metadata.to_string(), &root.join("app")).unwrap();
+    assert_eq!(roots, vec![root.join("libs")]);
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use watch_rust_errors::cargo::{self, FinishedUnit};
+use watch_rust_errors::pipeline::{Pipeline, UnitState};
+
+// app -> (serde -> serde_derive, log), and a dev-dependency on tempfile
+const METADATA: &str = r#"{
+    "packages": [
+        {"id": "app 0.1.0 (path+file:///app)", "name": "app", "version": "0.1.0"},
+        {"id": "serde 1.0.0", "name": "serde", "version": "1.0.0"},
+        {"id": "serde_derive 1.0.0", "name": "serde_derive", "version": "1.0.0"},
+        {"id": "log 0.4.0", "name": "log", "version": "0.4.0"},
+        {"id": "tempfile 3.0.0", "name": "tempfile", "version": "3.0.0"}
+    ],
+    "resolve": {
+        "nodes": [
+            {"id": "app 0.1.0 (path+file:///app)", "deps": [
+                {"pkg": "serde 1.0.0", "dep_kinds": [{"kind": null}]},
+                {"pkg": "log 0.4.0", "dep_kinds": [{"kind": null}]},
+                {"pkg": "tempfile 3.0.0", "dep_kinds": [{"kind": "dev"}]}
+            ]},
+            {"id": "serde 1.0.0", "deps": [
+                {"pkg": "serde_derive 1.0.0", "dep_kinds": [{"kind": null}]}
+            ]},
+            {"id": "serde_derive 1.0.0", "deps": []},
+            {"id": "log 0.4.0", "deps": []},
+            {"id": "tempfile 3.0.0", "deps": []}
+        ]
+    }
+}"#;
+
+const APP: &str = "app 0.1.0 (path+file:///app)";
+
+fn finished(package_id: &str) -> FinishedUnit {
+    FinishedUnit {
+        package_id: package_id.to_string(),
+        fresh: false,
+        duration: None,
+    }
+}
+
+#[test]
+fn crates_wait_on_what_they_depend_on() {
+    let mut pipeline = Pipeline::from_metadata(METADATA).unwrap();
+
+    assert_eq!(pipeline.units[APP].name, "app");
+    assert_eq!(pipeline.units[APP].deps, vec!["serde 1.0.0", "log 0.4.0"]);
+    assert_eq!(pipeline.state("serde_derive 1.0.0"), UnitState::Active);
+    assert_eq!(pipeline.state("serde 1.0.0"), UnitState::Pending);
+    assert_eq!(pipeline.critical_path(), Vec::<String>::new());
+
+    pipeline.finish(&finished("serde_derive 1.0.0"), Duration::from_secs(4));
+    pipeline.finish(&finished("log 0.4.0"), Duration::from_secs(1));
+    assert_eq!(pipeline.state("serde 1.0.0"), UnitState::Active);
+    assert_eq!(pipeline.state(APP), UnitState::Pending);
+
+    pipeline.finish(&finished("serde 1.0.0"), Duration::from_secs(6));
+    pipeline.finish(&finished(APP), Duration::from_secs(7));
+    assert_eq!(pipeline.count(UnitState::Done), 4);
+    assert_eq!(pipeline.duration("serde 1.0.0"), Duration::from_secs(2));
+    assert_eq!(
+        pipeline.critical_path(),
+        vec!["serde_derive 1.0.0", "serde 1.0.0", APP]
+    );
+    assert_eq!(
+        pipeline.layers(),
+        vec![
+            vec!["log 0.4.0", "serde_derive 1.0.0", "tempfile 3.0.0"],
+            vec!["serde 1.0.0"],
+            vec![APP],
+        ]
+    );
+
+    // cargo's own timings win over the guess
+    pipeline.finish(
+        &FinishedUnit {
+            duration: Some(Duration::from_secs(9)),
+            ..finished("log 0.4.0")
+        },
+        Duration::from_secs(8),
+    );
+    assert_eq!(pipeline.duration("log 0.4.0"), Duration::from_secs(9));
+    assert_eq!(pipeline.critical_path(), vec!["log 0.4.0", APP]);
+
+    pipeline.reset();
+    assert_eq!(pipeline.count(UnitState::Done), 0);
+}
+
+#[test]
+fn json_messages_say_which_crate_is_done() {
+    assert!(cargo::streams_json("cargo check --message-format=json"));
+    assert!(cargo::streams_json(
+        "cargo clippy --message-format json-diagnostic-rendered-ansi"
+    ));
+    assert!(!cargo::streams_json("cargo check"));
+
+    let mut progress = cargo::BuildProgress::default();
+    assert!(progress.update(
+        r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0","target":{"name":"build-script-build","kind":["custom-build"]},"fresh":false}"#
+    ));
+    assert_eq!(progress.finished, None);
+    assert!(progress.update(
+        r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0","target":{"name":"serde","kind":["lib"]},"fresh":true}"#
+    ));
+    assert_eq!(
+        progress.finished,
+        Some(FinishedUnit {
+            fresh: true,
+            ..finished("serde 1.0.0")
+        })
+    );
+    assert!(progress.update(
+        r#"{"reason":"timing-info","package_id":"log 0.4.0","target":{"name":"log"},"duration":1.5}"#
+    ));
+    assert_eq!(
+        progress.finished,
+        Some(FinishedUnit {
+            duration: Some(Duration::from_millis(1500)),
+            ..finished("log 0.4.0")
+        })
+    );
+}
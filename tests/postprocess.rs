@@ -0,0 +1,88 @@
+use std::fs;
+use std::time::Duration;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::config::{LocalConfig, ProjectConfig};
+use watch_rust_errors::postprocess::PostProcessor;
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n\
+                      warning: unused variable: `x`\n --> src/main.rs:2:9\n\n";
+
+#[test]
+fn scripts_tag_and_drop_diagnostics() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut result = cargo::parse(false, OUTPUT, "").unwrap();
+
+    // what the script prints back: the error tagged, and no warnings
+    let mut error = result.errors[0].clone();
+    error.tags = vec!["owner:core".to_string()];
+    let output = serde_json::json!({ "errors": [error], "warnings": [] });
+    fs::write(dir.path().join("output.json"), output.to_string()).unwrap();
+
+    let post_processor = PostProcessor::new("sh -c 'cat > input.json; cat output.json'");
+    post_processor.apply(dir.path(), &[], &mut result).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].tags, vec!["owner:core"]);
+    assert!(result.warnings.is_empty());
+
+    let input: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("input.json")).unwrap()).unwrap();
+    assert_eq!(input["success"], false);
+    assert_eq!(input["errors"][0]["num"], "E0308");
+    assert_eq!(input["warnings"][0]["message"], "unused variable: `x`");
+}
+
+#[test]
+fn failing_scripts_leave_the_results_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut result = cargo::parse(false, OUTPUT, "").unwrap();
+
+    // printing nothing changes nothing
+    PostProcessor::new("true")
+        .apply(dir.path(), &[], &mut result)
+        .unwrap();
+    assert_eq!((result.errors.len(), result.warnings.len()), (1, 1));
+
+    let err = PostProcessor::new("sh -c 'echo no policy here >&2; exit 3'")
+        .apply(dir.path(), &[], &mut result)
+        .unwrap_err();
+    assert!(err.contains("no policy here"));
+
+    let err = PostProcessor::new("echo nonsense")
+        .apply(dir.path(), &[], &mut result)
+        .unwrap_err();
+    assert!(err.contains("didn't print diagnostics"));
+
+    let slow = PostProcessor {
+        timeout: Duration::from_millis(200),
+        ..PostProcessor::new("sleep 5")
+    };
+    let err = slow.apply(dir.path(), &[], &mut result).unwrap_err();
+    assert!(err.contains("took longer"));
+    assert_eq!((result.errors.len(), result.warnings.len()), (1, 1));
+}
+
+#[test]
+fn project_scripts_only_run_once_trusted() {
+    let local: LocalConfig = toml::from_str("post_process = \"./triage\"\n").unwrap();
+    assert!(local.runs_commands());
+
+    let mut project = ProjectConfig::default();
+    assert_eq!(project.with_local(Some(&local)).post_processor(), None);
+
+    project.trust_local_config = Some(true);
+    let post_processor = project.with_local(Some(&local)).post_processor().unwrap();
+    assert_eq!(post_processor.command, "./triage");
+
+    // the user's own script wins
+    project.post_process = Some("mine".to_string());
+    project.post_process_timeout = Some(30);
+    assert_eq!(
+        project.with_local(Some(&local)).post_processor(),
+        Some(PostProcessor {
+            command: "mine".to_string(),
+            timeout: Duration::from_secs(30),
+        })
+    );
+}
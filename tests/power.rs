@@ -0,0 +1,35 @@
+use std::fs;
+use std::path::Path;
+
+use watch_rust_errors::power;
+
+fn supply(dir: &Path, name: &str, kind: &str, online: Option<&str>) {
+    let supply = dir.join(name);
+    fs::create_dir(&supply).unwrap();
+    fs::write(supply.join("type"), format!("{}\n", kind)).unwrap();
+    if let Some(online) = online {
+        fs::write(supply.join("online"), format!("{}\n", online)).unwrap();
+    }
+}
+
+#[test]
+fn unplugged_laptops_are_on_battery() {
+    let dir = tempfile::tempdir().unwrap();
+    supply(dir.path(), "BAT0", "Battery", None);
+    supply(dir.path(), "AC", "Mains", Some("0"));
+    assert!(power::on_battery_in(dir.path()));
+
+    fs::write(dir.path().join("AC/online"), "1\n").unwrap();
+    assert!(!power::on_battery_in(dir.path()));
+}
+
+#[test]
+fn machines_without_a_mains_supply_are_plugged_in() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(!power::on_battery_in(dir.path()));
+    assert!(!power::on_battery_in(&dir.path().join("missing")));
+
+    // e.g. a wireless mouse's
+    supply(dir.path(), "hidpp_battery_0", "Battery", None);
+    assert!(!power::on_battery_in(dir.path()));
+}
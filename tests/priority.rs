@@ -0,0 +1,71 @@
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::priority::{self, Priority, PriorityRunner};
+
+use crate::common::MockRunner;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn builds_run_niced() {
+    let host = Arc::new(MockRunner::new(true, ""));
+    let runner = PriorityRunner::new(Priority::Idle, host.clone());
+
+    cargo::run_with(
+        &runner,
+        "/work/app",
+        "RUSTFLAGS=-Dwarnings cargo check",
+        &[],
+    )
+    .unwrap();
+
+    let calls = host.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "nice");
+    assert_eq!(
+        calls[0].1,
+        ["-n", "19", "ionice", "-c", "3", "cargo", "check"]
+    );
+    // the variables are still the command's
+    assert_eq!(
+        calls[0].2,
+        [("RUSTFLAGS".to_string(), "-Dwarnings".to_string())]
+    );
+
+    let low = PriorityRunner::new(Priority::Low, host.clone());
+    let (program, args) = low.command("rustc", &["--version"]);
+    assert_eq!(program, "nice");
+    assert_eq!(
+        args,
+        [
+            "-n",
+            "10",
+            "ionice",
+            "-c",
+            "2",
+            "-n",
+            "7",
+            "rustc",
+            "--version"
+        ]
+    );
+}
+
+#[test]
+fn normal_priority_runs_commands_as_they_are() {
+    let host = Arc::new(MockRunner::new(true, ""));
+    let runner = priority::runner(Priority::Normal, host.clone());
+
+    runner
+        .run("cargo", &["check"], &[], Path::new("/work/app"))
+        .unwrap();
+
+    let calls = host.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "cargo");
+    assert_eq!(calls[0].1, ["check"]);
+    assert_eq!("idle".parse::<Priority>(), Ok(Priority::Idle));
+    assert!("high".parse::<Priority>().is_err());
+}
@@ -0,0 +1,533 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use watch_rust_errors::cargo::{
+    self, BuildProgress, DiagnosticId, FetchProgress, ReportedCounts, SummaryLine,
+};
+use watch_rust_errors::config::SortOrder;
+use watch_rust_errors::process::{
+    self, ProcessOutput, ProcessRunner, Stop, Stoppable, SystemRunner, Timeout,
+};
+
+use crate::common::MockRunner;
+
+struct FailingRunner;
+
+impl ProcessRunner for FailingRunner {
+    fn run(
+        &self,
+        _program: &str,
+        _args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        Err("spawn failed".to_string())
+    }
+}
+
+#[test]
+fn commands_are_started_directly() {
+    let runner = MockRunner::new(true, "");
+
+    cargo::run_with(
+        &runner,
+        ".",
+        "RUSTFLAGS=-Dwarnings cargo clippy --manifest-path 'my crate/Cargo.toml'",
+        &[],
+    )
+    .unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "cargo");
+    assert_eq!(
+        calls[0].1,
+        ["clippy", "--manifest-path", "my crate/Cargo.toml"]
+    );
+    assert_eq!(
+        calls[0].2,
+        [("RUSTFLAGS".to_string(), "-Dwarnings".to_string())]
+    );
+}
+
+#[test]
+fn shell_commands_are_passed_to_the_shell() {
+    let runner = MockRunner::new(true, "");
+
+    cargo::run_with(&runner, ".", "shell: cargo clippy | tee out.txt", &[]).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    assert_ne!(calls[0].0, "cargo");
+    assert_eq!(
+        calls[0].1.last().map(String::as_str),
+        Some("cargo clippy | tee out.txt")
+    );
+}
+
+#[test]
+fn commands_are_split_like_the_shell_does() {
+    assert_eq!(
+        process::split_command(r#"a "b c" 'd "e"' f\ g "h\"i" '' "#).unwrap(),
+        ["a", "b c", "d \"e\"", "f g", "h\"i", ""]
+    );
+    assert!(process::split_command("cargo 'check").is_err());
+}
+
+#[test]
+fn environment_is_passed_to_the_runner() {
+    let runner = MockRunner::new(true, "");
+    let env = vec![("RUSTFLAGS".to_string(), "-D warnings".to_string())];
+
+    cargo::run_with(&runner, ".", "cargo check", &env).unwrap();
+
+    assert_eq!(runner.calls.lock().unwrap()[0].2, env);
+}
+
+#[test]
+fn canned_output_is_parsed() {
+    let runner = MockRunner::new(
+        false,
+        "    Checking foo v0.1.0\n\
+         warning: unused variable: `x`\n \
+         --> src/main.rs:2:9\n\
+         \n\
+         error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n",
+    );
+
+    let result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+
+    assert!(!result.success);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].num.as_deref(), Some("E0308"));
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.warnings[0].line, Some(2));
+}
+
+#[test]
+fn diagnostics_on_stdout_are_parsed() {
+    let stdout = "error[E0308]: mismatched types\n \
+                  --> src/main.rs:3:18\n\
+                  \n";
+    let runner = MockRunner::with_stdout(false, stdout);
+
+    let result = cargo::run_with(&runner, ".", "just check", &[]).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].file.as_deref(), Some("src/main.rs"));
+    assert_eq!(result.output, stdout);
+}
+
+// Prints a byte that isn't UTF-8 in the middle of a diagnostic.
+struct GarbledRunner;
+
+impl ProcessRunner for GarbledRunner {
+    fn run(
+        &self,
+        _program: &str,
+        _args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        Ok(ProcessOutput {
+            success: false,
+            stdout: vec![],
+            stderr: b"error: couldn't read `src/\xffmain.rs`\n\n".to_vec(),
+        })
+    }
+}
+
+#[test]
+fn output_that_is_not_utf8_is_still_read() {
+    let result = cargo::run_with(&GarbledRunner, ".", "cargo check", &[]).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(
+        result.errors[0].message,
+        "couldn't read `src/\u{fffd}main.rs`"
+    );
+}
+
+#[test]
+fn spawn_errors_are_propagated() {
+    assert_eq!(
+        cargo::run_with(&FailingRunner, ".", "cargo check", &[]).unwrap_err(),
+        "spawn failed"
+    );
+}
+
+#[test]
+fn per_crate_summaries_are_parsed() {
+    let runner = MockRunner::new(
+        false,
+        "    Checking good v0.1.0 (/ws/good)\n\
+         warning: unused variable: `x`\n \
+         --> good/src/lib.rs:2:9\n\
+         \n\
+         warning: `good` (lib) generated 1 warning\n    \
+         Checking bad v0.1.0 (/ws/bad)\n\
+         error[E0308]: mismatched types\n \
+         --> bad/src/lib.rs:3:18\n\
+         \n\
+         error: could not compile `bad` (lib) due to 1 previous error\n",
+    );
+
+    let result = cargo::run_with(&runner, ".", "cargo check --workspace", &[]).unwrap();
+
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.errors[0].krate.as_deref(), Some("bad"));
+    assert_eq!(result.warnings[0].krate.as_deref(), Some("good"));
+
+    let bad = result.crate_summary("bad").unwrap();
+    assert!(bad.failed);
+    assert_eq!(bad.errors, Some(1));
+    let good = result.crate_summary("good").unwrap();
+    assert!(!good.failed);
+    assert_eq!(good.warnings, Some(1));
+
+    let groups = result.by_crate();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].name, Some("bad"));
+    assert!(groups[0].failed);
+    assert_eq!(groups[1].name, Some("good"));
+    assert!(!groups[1].failed);
+}
+
+#[test]
+fn repeated_diagnostics_are_folded() {
+    let warning = "warning: unused variable: `x`\n \
+                   --> shared/src/lib.rs:2:9\n\
+                   \n";
+    let other = "warning: unused variable: `x`\n \
+                 --> shared/src/lib.rs:8:9\n\
+                 \n";
+    let runner = MockRunner::new(
+        true,
+        &format!(
+            "    Checking a v0.1.0\n{0}    Checking b v0.1.0\n{0}{1}    Checking c v0.1.0\n{0}",
+            warning, other
+        ),
+    );
+    let mut result = cargo::run_with(&runner, ".", "cargo check --workspace", &[]).unwrap();
+
+    result.dedup();
+
+    assert_eq!(result.warnings.len(), 2);
+    assert_eq!(result.warnings[0].line, Some(2));
+    assert_eq!(result.warnings[0].repeats, 2);
+    assert_eq!(result.warnings[0].krate.as_deref(), Some("a"));
+    assert_eq!(result.warnings[1].repeats, 0);
+}
+
+#[test]
+fn offline_resolution_failures_ask_for_a_fetch() {
+    let runner = MockRunner::new(
+        false,
+        "error: no matching package named `serde` found\n\
+         location searched: registry `crates-io`\n\
+         required by package `foo v0.1.0 (/src/foo)`\n\
+         As a reminder, you're using offline mode (--offline) which can sometimes cause \
+         surprising resolution failures\n",
+    );
+
+    let result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+    assert!(result.needs_fetch);
+
+    let runner = MockRunner::new(true, "");
+    let env = vec![("CARGO_NET_OFFLINE".to_string(), "true".to_string())];
+    cargo::fetch_with(&runner, ".", &env).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "cargo");
+    assert_eq!(calls[0].1, ["fetch"]);
+    assert_eq!(
+        calls[0].2,
+        [("CARGO_NET_OFFLINE".to_string(), "false".to_string())]
+    );
+}
+
+#[test]
+fn fetch_progress_lists_downloaded_crates() {
+    let mut progress = FetchProgress::default();
+
+    assert!(!progress.update("   Compiling itoa v1.0.1"));
+    assert!(progress.update("\x1b[1m\x1b[32m  Downloaded\x1b[0m itoa v1.0.1"));
+    assert!(progress.update("  Downloaded serde v1.0.136 (registry `crates-io`)"));
+
+    assert_eq!(progress.downloaded, vec!["itoa v1.0.1", "serde v1.0.136"]);
+    assert!(!progress.is_finished());
+}
+
+#[test]
+fn build_progress_follows_the_crates_compiled() {
+    let mut progress = BuildProgress::default();
+
+    assert!(!progress.update("  Downloaded itoa v1.0.1"));
+    assert!(progress.update("\x1b[1m\x1b[32m   Compiling\x1b[0m itoa v1.0.1"));
+    assert!(progress.update("    Checking serde v1.0.136"));
+    assert_eq!(progress.to_string(), "Compiling serde (2 so far)");
+    assert_eq!(progress.fraction(), None);
+
+    // the progress bar, when cargo draws it
+    assert!(progress.update("\r    Building [=====>      ] 45/120: serde, tokio\r"));
+    assert_eq!(progress.to_string(), "Compiling serde, tokio (45/120)");
+    assert_eq!(progress.fraction(), Some(0.375));
+
+    // and --message-format=json
+    let mut progress = BuildProgress::default();
+    assert!(progress.update(
+        r#"{"reason":"compiler-artifact","package_id":"itoa 1.0.1","target":{"name":"itoa"}}"#
+    ));
+    assert!(!progress.update(r#"{"reason":"build-finished","success":true}"#));
+    assert_eq!(progress.done, 1);
+    assert_eq!(progress.current.as_deref(), Some("itoa"));
+}
+
+#[test]
+fn summary_lines_are_counted_rather_than_listed() {
+    assert_eq!(
+        SummaryLine::classify("error: aborting due to previous error; 2 warnings emitted"),
+        Some(SummaryLine::Aborting {
+            errors: 1,
+            warnings: 2
+        })
+    );
+    assert_eq!(
+        SummaryLine::classify("error: could not compile `foo` (bin \"foo\") due to 3 previous errors; 1 warning emitted"),
+        Some(SummaryLine::CouldNotCompile {
+            krate: "foo".to_string(),
+            errors: Some(3),
+            warnings: Some(1)
+        })
+    );
+    assert_eq!(
+        SummaryLine::classify("error: linking with `cc` failed"),
+        None
+    );
+
+    // one of the two errors is in a shape that isn't read
+    let runner = MockRunner::new(
+        false,
+        "error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n\
+         warning: 1 warning emitted\n\
+         \n\
+         error: aborting due to 2 previous errors\n",
+    );
+    let result = cargo::run_with(&runner, ".", "rustc src/main.rs", &[]).unwrap();
+    assert_eq!(result.errors.len(), 1);
+    assert!(result.warnings.is_empty());
+    assert_eq!(
+        result.reported,
+        Some(ReportedCounts {
+            errors: 2,
+            warnings: 1,
+            unread: true
+        })
+    );
+}
+
+#[test]
+fn diagnostics_are_grouped_by_file() {
+    let runner = MockRunner::new(
+        false,
+        "warning: unused variable: `x`\n \
+         --> src/lib.rs:2:9\n\
+         \n\
+         error[E0308]: mismatched types\n \
+         --> src/main.rs:3:18\n\
+         \n\
+         warning: unused import: `std::fs`\n \
+         --> src/main.rs:1:5\n\
+         \n\
+         error: linking with `cc` failed: exit status: 1\n\
+         \n",
+    );
+
+    let result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+    let groups = result.by_file();
+
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0].file, Some("src/lib.rs"));
+    assert_eq!((groups[0].errors, groups[0].warnings), (0, 1));
+    assert_eq!(groups[1].file, Some("src/main.rs"));
+    assert_eq!((groups[1].errors, groups[1].warnings), (1, 1));
+    assert_eq!(groups[2].file, None);
+    assert_eq!(groups[2].diagnostics.len(), 1);
+}
+
+#[test]
+fn diagnostics_can_be_sorted() {
+    let runner = MockRunner::new(
+        false,
+        "warning: unused variable: `x`\n \
+         --> src/main.rs:9:9\n\
+         \n\
+         error[E0425]: cannot find value `z` in this scope\n \
+         --> src/main.rs:4:5\n\
+         \n\
+         error[E0308]: mismatched types\n \
+         --> src/lib.rs:3:18\n\
+         \n",
+    );
+    let result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+    let sorted = |order| {
+        let mut diags = result
+            .diagnostics()
+            .enumerate()
+            .map(|(i, d)| (DiagnosticId(i), d))
+            .collect::<Vec<_>>();
+        cargo::sort_diagnostics(&mut diags, order);
+        diags
+            .iter()
+            .map(|(_, d)| d.line.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(sorted(SortOrder::Compiler), vec![9, 4, 3]);
+    assert_eq!(sorted(SortOrder::Severity), vec![4, 3, 9]);
+    assert_eq!(sorted(SortOrder::File), vec![3, 4, 9]);
+    assert_eq!(sorted(SortOrder::Code), vec![3, 4, 9]);
+    assert_eq!(sorted(SortOrder::RootCause), vec![4, 3, 9]);
+}
+
+#[test]
+fn builds_are_timed() {
+    let runner = MockRunner::new(true, "");
+
+    let result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+
+    assert!(result.duration.is_some());
+    assert!(cargo::parse(true, "", "").unwrap().duration.is_none());
+}
+
+#[test]
+fn ignored_codes_and_lints_are_removed() {
+    let runner = MockRunner::new(
+        false,
+        "error[E0601]: `main` function not found in crate `foo`\n\
+         \x20 --> src/main.rs:1:1\n\
+         \n\
+         warning: function `helper` is never used\n\
+         \x20 --> src/lib.rs:3:4\n\
+         \x20 |\n\
+         \x20 = note: `#[warn(dead_code)]` on by default\n\
+         \n\
+         warning: unused variable: `x`\n\
+         \x20 --> src/lib.rs:7:9\n\
+         \x20 |\n\
+         \x20 = note: `-D unused-variables` implied by `-D warnings`\n\
+         \n",
+    );
+    let mut result = cargo::run_with(&runner, ".", "cargo check", &[]).unwrap();
+
+    result.remove_ignored(&["E0601".to_string(), "dead_code".to_string()]);
+
+    assert!(result.errors.is_empty());
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.warnings[0].lint.as_deref(), Some("unused_variables"));
+}
+
+#[test]
+fn hung_builds_are_stopped_with_what_they_started() {
+    let dir = tempfile::tempdir().unwrap();
+    let runner = Timeout {
+        inner: &SystemRunner,
+        timeout: Duration::from_secs(1),
+    };
+
+    // a child that outlives the shell, holding the pipes open
+    let started = Instant::now();
+    let result = cargo::run_with(
+        &runner,
+        dir.path(),
+        "sh -c 'echo compiling; sleep 30 & echo $! > pid; wait'",
+        &[],
+    )
+    .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    assert!(!result.success);
+    assert_eq!(result.errors[0].message, "build timed out after 1 s");
+    assert!(result.output.contains("compiling"));
+
+    // gone, or dead and waiting for init to reap it, once the signal lands
+    let pid = fs::read_to_string(dir.path().join("pid")).unwrap();
+    let stopped = || {
+        let status = fs::read_to_string(format!("/proc/{}/status", pid.trim())).unwrap_or_default();
+        status.is_empty() || status.contains("State:\tZ")
+    };
+    for _ in 0..100 {
+        if stopped() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(stopped());
+
+    // and ones that finish in time are left alone
+    let result = cargo::run_with(&runner, dir.path(), "true", &[]).unwrap();
+    assert!(result.success);
+    assert!(process::is_timed_out(&process::timed_out(
+        Duration::from_secs(1)
+    )));
+}
+
+#[test]
+fn builds_that_leave_something_running_still_finish() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // a daemon left holding the pipes open after the build exits
+    let started = Instant::now();
+    let result = cargo::run_with(
+        &SystemRunner,
+        dir.path(),
+        "sh -c 'echo compiling; sleep 30 & echo done >&2'",
+        &[],
+    )
+    .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    assert!(result.success);
+    assert!(result.output.contains("compiling"));
+    assert!(result.output.contains("done"));
+}
+
+#[test]
+fn cancelled_builds_are_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let runner = Stoppable {
+        inner: &SystemRunner,
+        stop: Stop {
+            timeout: None,
+            cancel: Some(cancel.clone()),
+        },
+    };
+
+    let cancelling = {
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            cancel.store(true, Ordering::SeqCst);
+        })
+    };
+    let started = Instant::now();
+    let result =
+        cargo::run_with(&runner, dir.path(), "sh -c 'echo compiling; sleep 30'", &[]).unwrap();
+    cancelling.join().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    assert!(!result.success);
+    assert_eq!(result.errors[0].message, "build cancelled");
+    assert!(result.output.contains("compiling"));
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use watch_rust_errors::project::{self, FeatureSelection, ProjectKind};
+
+use crate::common::Project;
+
+#[test]
+fn packages_are_checked_on_their_own() {
+    let project = Project::new("fn main() {}\n");
+
+    let info = project::detect(project.root());
+
+    assert_eq!(info.kind, ProjectKind::Package);
+    assert_eq!(info.default_command(), Some("cargo check"));
+    assert_eq!(info.toolchain, None);
+}
+
+#[test]
+fn workspaces_are_checked_as_a_whole() {
+    let project = Project::new("fn main() {}\n");
+    project.write("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n");
+    project.write("rust-toolchain.toml", "[toolchain]\nchannel = \"1.70.0\"\n");
+
+    let info = project::detect(project.root());
+
+    assert_eq!(info.kind, ProjectKind::Workspace);
+    assert_eq!(info.default_command(), Some("cargo check --workspace"));
+    assert_eq!(info.toolchain.as_deref(), Some("1.70.0"));
+}
+
+#[test]
+fn folders_without_a_manifest_get_no_command() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let info = project::detect(dir.path());
+
+    assert_eq!(info.kind, ProjectKind::Other);
+    assert_eq!(info.default_command(), None);
+}
+
+#[test]
+fn only_suggested_commands_are_replaced() {
+    assert!(project::is_default_command(""));
+    assert!(project::is_default_command("cargo check"));
+    assert!(project::is_default_command("cargo check --workspace"));
+    assert!(!project::is_default_command("cargo clippy"));
+}
+
+#[test]
+fn features_are_picked_in_the_command() {
+    let project = Project::new("fn main() {}\n");
+    project.write(
+        "Cargo.toml",
+        "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n\
+         [features]\ndefault = [\"json\"]\njson = []\ntls = []\n",
+    );
+    assert_eq!(
+        project::detect(project.root()).features,
+        vec!["json", "tls"]
+    );
+
+    let command = "cargo test --features=json -F tls --lib -- --nocapture";
+    let mut selection = FeatureSelection::of(command);
+    assert_eq!(selection.features, vec!["json", "tls"]);
+    assert!(!selection.no_default_features);
+
+    selection.toggle("json");
+    selection.no_default_features = true;
+    assert_eq!(
+        selection.apply(command),
+        "cargo test --lib --no-default-features --features tls -- --nocapture"
+    );
+
+    selection.toggle("tls");
+    selection.no_default_features = false;
+    assert_eq!(selection.apply("cargo check --features tls"), "cargo check");
+}
+
+#[test]
+fn dropped_folders_and_manifests_pick_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("my project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("Cargo.toml"), "").unwrap();
+    fs::write(root.join("src/main.rs"), "").unwrap();
+    let uri = |path: &Path| glib::filename_to_uri(path, None).unwrap().to_string();
+    let expected = Some(root.to_string_lossy().into_owned());
+
+    assert_eq!(project::dropped_root(&[uri(&root)]), expected);
+    assert_eq!(
+        project::dropped_root(&[uri(&root.join("Cargo.toml"))]),
+        expected
+    );
+    // the first that's a project wins
+    assert_eq!(
+        project::dropped_root(&[
+            "https://example.com/".to_string(),
+            uri(&root.join("src/main.rs")),
+            uri(&root.join("Cargo.toml")),
+        ]),
+        expected
+    );
+    assert_eq!(
+        project::dropped_root(&[uri(&root.join("src/main.rs"))]),
+        None
+    );
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use watch_rust_errors::cargo::CompileResult;
+use watch_rust_errors::config::Config;
+use watch_rust_errors::quickfix::{self, EditorPreset, Multiplexer, TerminalSession};
+
+use crate::common::MockRunner;
+
+fn result() -> CompileResult {
+    let mut result = CompileResult::default();
+    result.push(
+        "error[E0308]: mismatched types\n  --> src/main.rs:3:18\n  |\n3 |     let x: u32 = \"a\";\n"
+            .parse()
+            .unwrap(),
+    );
+    result.push(
+        "warning: unused variable: `x`\n  --> src/lib.rs:2:9\n"
+            .parse()
+            .unwrap(),
+    );
+    result.push(
+        "warning: unused manifest key: package.foo\n"
+            .parse()
+            .unwrap(),
+    );
+    result
+}
+
+#[test]
+fn diagnostics_become_errorformat_lines() {
+    let result = result();
+    let list = quickfix::to_quickfix("/src/foo", result.diagnostics());
+
+    assert_eq!(
+        list,
+        "/src/foo/src/main.rs:3:18: error: mismatched types [E0308]\n\
+         /src/foo/src/lib.rs:2:9: warning: unused variable: `x`\n"
+    );
+}
+
+#[test]
+fn lists_can_be_limited_to_one_file() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let path = quickfix::write(dir.path(), &result(), Some("src/lib.rs")).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+    assert!(contents.contains("unused variable"));
+
+    assert!(quickfix::write(dir.path(), &result(), Some("src/other.rs")).is_err());
+}
+
+#[test]
+fn commands_get_the_whole_span() {
+    let result = result();
+    let mut diag = result.errors[0].clone();
+    let command = "zed {file}:{line}:{column}-{end_line}:{end_column}";
+
+    assert_eq!(
+        quickfix::fill(command, "/src/foo", &diag).unwrap(),
+        "zed '/src/foo/src/main.rs':3:18-3:18"
+    );
+    diag.end_line = Some(4);
+    diag.end_column = Some(2);
+    assert_eq!(
+        quickfix::fill(command, "/src/foo", &diag).unwrap(),
+        "zed '/src/foo/src/main.rs':3:18-4:2"
+    );
+    assert!(quickfix::fill(command, "/src/foo", &result.warnings[1]).is_none());
+}
+
+#[test]
+fn editor_presets_type_into_a_multiplexer() {
+    let result = result();
+    let diag = &result.errors[0];
+    let config: Config = toml::from_str(
+        "[editor]\npreset = \"helix\"\nmultiplexer = \"tmux\"\npane = \"dev:1.0\"\n",
+    )
+    .unwrap();
+    let runner = MockRunner::new(true, "");
+
+    let command = config.open_command(&runner).unwrap().unwrap();
+    assert_eq!(
+        quickfix::fill(&command, "/src/foo", diag).unwrap(),
+        "tmux send-keys -t 'dev:1.0' -l \"$(printf '\\033:open %s:3:18\\r' '/src/foo/src/main.rs')\""
+    );
+    assert!(runner.calls.lock().unwrap().is_empty());
+
+    let config = Config {
+        open_command: Some("hx {file}:{line}".to_string()),
+        editor: Some(EditorPreset::Zed),
+        ..Default::default()
+    };
+    assert_eq!(
+        config.open_command(&runner).unwrap().as_deref(),
+        Ok("hx {file}:{line}")
+    );
+}
+
+#[test]
+fn terminal_editors_are_found_in_their_pane() {
+    let runner = MockRunner::with_stdout(true, "%0\tzsh\n%3\tnvim\n%4\thx\n");
+    let neovim = EditorPreset::Neovim(TerminalSession {
+        multiplexer: Multiplexer::Tmux,
+        pane: None,
+        matching: None,
+        socket: None,
+    });
+
+    assert_eq!(
+        neovim.find_pane(&runner).unwrap(),
+        ("%3".to_string(), "nvim".to_string())
+    );
+    assert!(neovim
+        .open_command(&runner)
+        .unwrap()
+        .starts_with("tmux send-keys -t '%3' -l"));
+
+    let zsh = EditorPreset::Helix(TerminalSession {
+        multiplexer: Multiplexer::Tmux,
+        pane: None,
+        matching: Some("^bash$".to_string()),
+        socket: None,
+    });
+    assert!(zsh.find_pane(&runner).is_err());
+}
+
+#[test]
+fn kitty_windows_are_matched_by_their_foreground_process() {
+    let runner = MockRunner::with_stdout(
+        true,
+        r#"[{"tabs": [{"windows": [
+            {"id": 1, "foreground_processes": [{"cmdline": ["/bin/zsh"]}]},
+            {"id": 2, "foreground_processes": [{"cmdline": ["hx", "src/main.rs"]}]}
+        ]}]}]"#,
+    );
+    let helix = EditorPreset::Helix(TerminalSession {
+        multiplexer: Multiplexer::Kitty,
+        pane: None,
+        matching: None,
+        socket: Some("unix:/tmp/kitty".to_string()),
+    });
+
+    assert_eq!(helix.find_pane(&runner).unwrap().0, "2");
+    assert_eq!(
+        runner.calls.lock().unwrap()[0].1,
+        ["@", "--to", "unix:/tmp/kitty", "ls"]
+    );
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::process::{self, Command};
+
+use watch_rust_errors::resume::{self, SavedSession};
+
+// a process that has come and gone, like an instance that crashed
+fn ended_pid() -> u32 {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    child.id()
+}
+
+#[test]
+fn sessions_cut_short_are_offered_again() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut crashed = SavedSession::new("/src/app", "cargo clippy");
+    crashed.code_filter = Some("E0308".to_string());
+    crashed.pid = ended_pid();
+    crashed.save_to(dir.path()).unwrap();
+    // still watching in this instance
+    SavedSession::new("/src/lib", "cargo check")
+        .save_to(dir.path())
+        .unwrap();
+    fs::write(dir.path().join("broken.json"), "{").unwrap();
+
+    assert_eq!(resume::interrupted_in(dir.path()), vec![crashed.clone()]);
+
+    // saved again as it changes, in the same place
+    crashed.module_filter = Some("parser".to_string());
+    crashed.save_to(dir.path()).unwrap();
+    assert_eq!(resume::interrupted_in(dir.path()), vec![crashed]);
+
+    SavedSession::remove_from(dir.path(), "/src/app").unwrap();
+    SavedSession::remove_from(dir.path(), "/src/nowhere").unwrap();
+    assert!(resume::interrupted_in(dir.path()).is_empty());
+}
+
+#[test]
+fn new_sessions_belong_to_this_instance() {
+    let session = SavedSession::new("/src/app", "cargo check");
+    assert_eq!(session.pid, process::id());
+    assert_eq!(session.module_filter, None);
+    assert!(resume::interrupted_in("/nonexistent/sessions").is_empty());
+}
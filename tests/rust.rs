@@ -0,0 +1,165 @@
+use watch_rust_errors::rust::{Level, RustDiagnostic, Span, Type};
+
+#[test]
+fn parse_error_with_code_and_location() {
+    let diag: RustDiagnostic = "error[E0425]: cannot find value `z` in this scope\n \
+                                --> src/lib.rs:10:5\n  \
+                                |\n"
+    .parse()
+    .unwrap();
+
+    assert!(matches!(diag.type_, Type::Error));
+    assert_eq!(diag.num.as_deref(), Some("E0425"));
+    assert_eq!(diag.message, "cannot find value `z` in this scope");
+    assert_eq!(diag.file.as_deref(), Some("src/lib.rs"));
+    assert_eq!(diag.line, Some(10));
+    assert_eq!(diag.column, Some(5));
+    assert_eq!(diag.details.as_deref(), Some("  |\n"));
+}
+
+#[test]
+fn parse_colored_error() {
+    let diag: RustDiagnostic = "\u{1b}[0m\u{1b}[1m\u{1b}[38;5;9merror[E0425]\u{1b}[0m\u{1b}[0m\u{1b}[1m: cannot find value `z` in this scope\u{1b}[0m\n\
+                                \u{1b}[0m  \u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[38;5;12m--> \u{1b}[0m\u{1b}[0msrc/lib.rs:10:5\u{1b}[0m\n\
+                                \u{1b}[0m   \u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[38;5;12m|\u{1b}[0m\n"
+        .parse()
+        .unwrap();
+
+    assert_eq!(diag.num.as_deref(), Some("E0425"));
+    assert_eq!(diag.message, "cannot find value `z` in this scope");
+    assert_eq!(diag.file.as_deref(), Some("src/lib.rs"));
+    assert_eq!(diag.line, Some(10));
+    assert_eq!(diag.details.as_deref(), Some("   |\n"));
+    assert!(diag.styled_details.unwrap().contains("\u{1b}[38;5;12m|"));
+}
+
+#[test]
+fn parse_warning_without_location() {
+    let diag: RustDiagnostic = "warning: unused manifest key: package.foo\n"
+        .parse()
+        .unwrap();
+
+    assert!(matches!(diag.type_, Type::Warning));
+    assert_eq!(diag.num, None);
+    assert_eq!(diag.message, "unused manifest key: package.foo");
+    assert_eq!(diag.file, None);
+    assert_eq!(diag.line, None);
+    assert_eq!(diag.details, None);
+}
+
+#[test]
+fn parse_error_without_location_keeps_what_follows() {
+    let diag: RustDiagnostic = "error: linking with `cc` failed: exit status: 1\n  \
+                                |\n  \
+                                = note: /usr/bin/ld: cannot find -lfoo\n"
+        .parse()
+        .unwrap();
+
+    assert_eq!(diag.message, "linking with `cc` failed: exit status: 1");
+    assert_eq!(diag.file, None);
+    assert_eq!(
+        diag.details.as_deref(),
+        Some("  |\n  = note: /usr/bin/ld: cannot find -lfoo\n")
+    );
+    assert_eq!(diag.children[0].message, "/usr/bin/ld: cannot find -lfoo");
+}
+
+#[test]
+fn parse_out_of_range_positions_as_unknown() {
+    let diag: RustDiagnostic = "error: oops\n --> src/lib.rs:99999999999:5\n"
+        .parse()
+        .unwrap();
+
+    assert_eq!(diag.file.as_deref(), Some("src/lib.rs"));
+    assert_eq!(diag.line, None);
+    assert_eq!(diag.column, Some(5));
+}
+
+#[test]
+fn parse_rejects_non_diagnostics() {
+    assert!("    Checking foo v0.1.0".parse::<RustDiagnostic>().is_err());
+}
+
+#[test]
+fn parse_shell_errors() {
+    for line in [
+        "sh: 1: carg: not found",
+        "sh: 1: eval: carg: not found",
+        "sh: carg: command not found",
+        "sh: line 1: carg: command not found",
+        "'carg' is not recognized as an internal or external command,",
+    ] {
+        let diag = RustDiagnostic::from_shell_error(line).unwrap();
+        assert_eq!(diag.type_, Type::Error);
+        assert_eq!(diag.message, "command not found: `carg`");
+        assert!(diag
+            .details
+            .unwrap()
+            .contains("hint: check that `carg` is installed"));
+    }
+
+    let diag = RustDiagnostic::from_shell_error("sh: 1: ./check.sh: Permission denied").unwrap();
+    assert_eq!(diag.message, "permission denied running `./check.sh`");
+
+    assert!(RustDiagnostic::from_shell_error("error: could not compile `foo`").is_none());
+}
+
+#[test]
+fn notes_helps_and_other_locations_are_kept() {
+    let diag: RustDiagnostic = "error[E0277]: the trait bound `Foo: Clone` is not satisfied\n  \
+                                --> src/main.rs:8:11\n   \
+                                |\n\
+                                8  |     takes(Foo);\n   \
+                                |           ^^^ the trait `Clone` is not implemented for `Foo`\n   \
+                                |\n  \
+                                ::: src/lib.rs:3:13\n   \
+                                |\n\
+                                note: required by a bound in `takes`\n  \
+                                --> src/lib.rs:3:13\n\
+                                help: consider annotating `Foo` with `#[derive(Clone)]`\n   \
+                                |\n   \
+                                = note: expected struct `Foo`\n              \
+                                found struct `Bar`\n"
+        .parse()
+        .unwrap();
+
+    let lib = Span {
+        file: "src/lib.rs".to_string(),
+        line: 3,
+        column: 13,
+    };
+    assert_eq!(diag.secondary_spans, vec![lib.clone()]);
+    assert_eq!(diag.children.len(), 3);
+    assert_eq!(diag.children[0].level, Level::Note);
+    assert_eq!(diag.children[0].span, Some(lib));
+    assert_eq!(diag.children[1].level, Level::Help);
+    assert_eq!(diag.children[1].span, None);
+    assert_eq!(
+        diag.children[2].message,
+        "expected struct `Foo`\nfound struct `Bar`"
+    );
+}
+
+#[test]
+fn json_children_come_with_their_spans() {
+    let line = r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///src/foo)","message":{"level":"error","message":"mismatched types","rendered":"error[E0308]: mismatched types\n --> src/main.rs:4:5\n","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","byte_start":40,"byte_end":45,"line_start":4,"column_start":5,"line_end":4,"column_end":10,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null},{"file_name":"src/main.rs","byte_start":10,"byte_end":13,"line_start":2,"column_start":8,"line_end":2,"column_end":11,"is_primary":false,"suggested_replacement":null,"suggestion_applicability":null}],"children":[{"level":"help","message":"try using a conversion method","rendered":null,"code":null,"spans":[{"file_name":"src/main.rs","byte_start":40,"byte_end":45,"line_start":4,"column_start":5,"line_end":4,"column_end":10,"is_primary":true,"suggested_replacement":"x.into()","suggestion_applicability":"MaybeIncorrect"}],"children":[]}]}}"#;
+    let diag = RustDiagnostic::from_json(line).unwrap().unwrap();
+
+    assert_eq!(diag.secondary_spans.len(), 1);
+    assert_eq!(diag.secondary_spans[0].to_string(), "src/main.rs:2:8");
+    assert_eq!(diag.children.len(), 1);
+    assert_eq!(
+        diag.children[0].to_string(),
+        "help: try using a conversion method (src/main.rs:4:5)"
+    );
+}
+
+#[test]
+fn json_diagnostics_rendered_in_an_unknown_shape_are_kept() {
+    let line = r#"{"reason":"compiler-message","package_id":null,"message":{"level":"error","message":"linking failed","rendered":"= linking failed\n","code":null,"spans":[{"file_name":"src/main.rs","byte_start":0,"byte_end":2,"line_start":1,"column_start":1,"line_end":1,"column_end":3,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null}],"children":[]}}"#;
+    let diag = RustDiagnostic::from_json(line).unwrap().unwrap();
+
+    assert!(matches!(diag.type_, Type::Error));
+    assert_eq!(diag.message, "linking failed");
+    assert_eq!(diag.location().as_deref(), Some("src/main.rs:1:1"));
+}
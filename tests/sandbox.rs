@@ -0,0 +1,91 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use watch_rust_errors::sandbox;
+
+use crate::common::MockRunner;
+
+#[test]
+fn finds_documents_the_portal_shows() {
+    assert_eq!(
+        sandbox::document(Path::new(
+            "/run/user/1000/doc/a1b2c3d4/watch-rust-errors/src"
+        )),
+        Some((
+            "a1b2c3d4".to_string(),
+            PathBuf::from("watch-rust-errors/src")
+        ))
+    );
+    assert_eq!(
+        sandbox::document(Path::new("/run/flatpak/doc/a1b2c3d4/watch-rust-errors")),
+        Some(("a1b2c3d4".to_string(), PathBuf::from("watch-rust-errors")))
+    );
+    assert_eq!(
+        sandbox::document(Path::new("/home/me/watch-rust-errors")),
+        None
+    );
+    assert_eq!(
+        sandbox::document(Path::new("/run/user/me/doc/a1b2c3d4")),
+        None
+    );
+    assert!(!sandbox::is_document(Path::new("/run/user/1000/doc")));
+}
+
+#[test]
+fn reads_host_paths_as_gdbus_prints_them() {
+    let reply = "({'a1b2c3d4': b'/home/me/watch-rust-errors', 'e5f6': b'/tmp/x'},)\n";
+    assert_eq!(
+        sandbox::parse_host_paths(reply, "a1b2c3d4"),
+        Some(PathBuf::from("/home/me/watch-rust-errors"))
+    );
+    assert_eq!(
+        sandbox::parse_host_paths("({'a1': b'/home/me/it\\'s'},)", "a1"),
+        Some(PathBuf::from("/home/me/it's"))
+    );
+    assert_eq!(sandbox::parse_host_paths("({},)", "a1b2c3d4"), None);
+}
+
+#[test]
+fn asks_the_portal_where_documents_are() {
+    let runner = MockRunner::with_stdout(true, "({'a1b2c3d4': b'/home/me/watch-rust-errors'},)\n");
+    let host = sandbox::host_path(
+        &runner,
+        Path::new("/run/user/1000/doc/a1b2c3d4/watch-rust-errors/src"),
+    );
+    assert_eq!(host, Ok(PathBuf::from("/home/me/watch-rust-errors/src")));
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "gdbus");
+    assert!(calls[0].1.contains(&"['a1b2c3d4']".to_string()));
+    drop(calls);
+
+    // other paths don't need asking about
+    let host = sandbox::host_path(&runner, Path::new("/home/me/watch-rust-errors"));
+    assert_eq!(host, Ok(PathBuf::from("/home/me/watch-rust-errors")));
+    assert_eq!(runner.call_count(), 1);
+}
+
+#[test]
+fn keeps_the_portals_path_where_the_host_one_cant_be_seen() {
+    let runner = MockRunner::with_stdout(true, "({'a1b2c3d4': b'/no/such/dir/wre'},)\n");
+    let path = Path::new("/run/user/1000/doc/a1b2c3d4/wre");
+    assert_eq!(sandbox::project_root(&runner, path), path);
+
+    let dir = tempfile::tempdir().unwrap();
+    let reply = format!("({{'a1b2c3d4': b'{}'}},)\n", dir.path().display());
+    let runner = MockRunner::with_stdout(true, &reply);
+    assert_eq!(sandbox::project_root(&runner, path), dir.path());
+}
+
+#[test]
+fn reads_what_the_sandbox_lets_the_app_see() {
+    let info = "[Application]\nname=in.nerdworks.WatchRustErrors\n\n\
+                [Context]\nshared=network;ipc;\nfilesystems=xdg-run/gvfs;home:ro;\n";
+    assert_eq!(sandbox::filesystems(info), vec!["xdg-run/gvfs", "home:ro"]);
+    assert!(sandbox::sees_home(info));
+    assert!(!sandbox::sees_home(
+        "[Context]\nfilesystems=xdg-documents;\n"
+    ));
+    // only the sandbox's own permissions count
+    assert!(!sandbox::sees_home("[Instance]\nfilesystems=home;\n"));
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use glib::MainContext;
+use watch_rust_errors::config::ScheduledJob;
+use watch_rust_errors::schedule::Scheduler;
+use watch_rust_errors::watcher::BuildEvent;
+
+use crate::common::{drain_events, MockRunner};
+
+fn clippy() -> ScheduledJob {
+    ScheduledJob {
+        name: "clippy".to_string(),
+        command: "cargo clippy --all-targets".to_string(),
+        every_minutes: 60,
+    }
+}
+
+#[test]
+fn jobs_run_once_per_interval() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (tx, rx) = MainContext::channel(Default::default());
+    let start = Instant::now();
+    let scheduler = Scheduler::with_runner(".", &[clippy()], runner.clone(), tx, start);
+
+    assert_eq!(scheduler.run_due(start).unwrap(), 0);
+    let later = start + Duration::from_secs(60 * 60);
+    assert_eq!(scheduler.run_due(later).unwrap(), 1);
+    assert_eq!(scheduler.run_due(later).unwrap(), 0);
+    assert_eq!(runner.compile_count(), 1);
+
+    let events = drain_events(rx);
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        BuildEvent::Scheduled(name, result) => {
+            assert_eq!(name, "clippy");
+            assert!(result.success);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[test]
+fn stopped_scheduler_runs_nothing() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (tx, _rx) = MainContext::channel(Default::default());
+    let start = Instant::now();
+    let scheduler = Scheduler::with_runner(".", &[clippy()], runner.clone(), tx, start);
+
+    scheduler.stop();
+
+    scheduler
+        .run_due(start + Duration::from_secs(2 * 60 * 60))
+        .unwrap();
+    assert_eq!(runner.compile_count(), 0);
+}
@@ -0,0 +1,247 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use watch_rust_errors::cargo::{self, CompileResult};
+use watch_rust_errors::server::{self, Server};
+
+const MISMATCH: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n";
+
+fn get(server: &Server, path: &str) -> String {
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap()
+}
+
+// Connects as a WebSocket client, returning the stream to write to, one to
+// read from past the response's headers, and those headers.
+fn upgrade(server: &Server) -> (TcpStream, BufReader<TcpStream>, Vec<String>) {
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    write!(
+        stream,
+        "GET /updates HTTP/1.1\r\n\
+         Host: localhost\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        headers.push(line);
+    }
+    (stream, reader, headers)
+}
+
+#[test]
+fn handshakes_are_answered_as_the_rfc_says() {
+    assert_eq!(
+        server::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+}
+
+#[test]
+fn the_latest_results_are_served_as_json() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    assert_eq!(body(&get(&server, "/results")), "null");
+
+    server.publish(&cargo::parse(false, MISMATCH, "").unwrap());
+    let response = get(&server, "/results");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    let result: CompileResult = serde_json::from_str(body(&response)).unwrap();
+    assert_eq!(result.errors.len(), 1);
+
+    assert!(get(&server, "/nothing").starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn websocket_clients_are_sent_every_result() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (_stream, mut reader, headers) = upgrade(&server);
+    assert_eq!(headers[0], "HTTP/1.1 101 Switching Protocols\r\n");
+    assert!(headers.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n".to_string()));
+
+    // whether the client is listed yet or not, it gets the result once
+    server.publish(&cargo::parse(false, MISMATCH, "").unwrap());
+
+    let mut header = [0; 2];
+    reader.read_exact(&mut header).unwrap();
+    assert_eq!(header[0], 0x81);
+    let len = match header[1] {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len).unwrap();
+            u16::from_be_bytes(len) as usize
+        }
+        len => len as usize,
+    };
+    let mut text = vec![0; len];
+    reader.read_exact(&mut text).unwrap();
+    let result: CompileResult = serde_json::from_slice(&text).unwrap();
+    assert_eq!(result.errors.len(), 1);
+}
+
+#[test]
+fn websocket_clients_are_answered_and_let_go() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (mut stream, mut reader, _) = upgrade(&server);
+
+    // masked, as clients send them: a ping saying "hi", then a close with
+    // status 1000
+    let mask = [1, 2, 3, 4];
+    let masked = |payload: &[u8]| {
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect::<Vec<u8>>()
+    };
+    stream.write_all(&[0x89, 0x82]).unwrap();
+    stream.write_all(&mask).unwrap();
+    stream.write_all(&masked(b"hi")).unwrap();
+    stream.write_all(&[0x88, 0x82]).unwrap();
+    stream.write_all(&mask).unwrap();
+    stream.write_all(&masked(&1000u16.to_be_bytes())).unwrap();
+
+    let mut replies = vec![];
+    reader.read_to_end(&mut replies).unwrap();
+    assert_eq!(replies, [0x8a, 2, b'h', b'i', 0x88, 2, 0x03, 0xe8]);
+}
+
+#[test]
+fn editors_can_say_which_file_they_show() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    server.on_focus(move |file| tx.send(file).unwrap());
+
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    let file = "/src/foo/src/lib.rs";
+    write!(
+        stream,
+        "POST /focus HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        file.len(),
+        file
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 204"));
+    assert_eq!(rx.recv().unwrap(), file);
+}
+
+#[test]
+fn oversized_focus_requests_are_turned_away() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    server.on_focus(|_| panic!("Told about an oversized request."));
+
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    write!(
+        stream,
+        "POST /focus HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4000000000\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 413"));
+}
+
+#[test]
+fn only_allowed_pages_are_served() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    server.allow_origins(vec!["http://localhost:3000".to_string()]);
+    let request = |origin: &str, upgrade: &str| {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        write!(
+            stream,
+            "GET /results HTTP/1.1\r\nHost: localhost\r\nOrigin: {}\r\n{}\r\n",
+            origin, upgrade
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let response = request("http://localhost:3000", "");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Access-Control-Allow-Origin: http://localhost:3000\r\n"));
+    assert!(request("https://example.com", "").starts_with("HTTP/1.1 403"));
+    assert!(request(
+        "https://example.com",
+        "Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n"
+    )
+    .starts_with("HTTP/1.1 403"));
+
+    // tools don't say where they're from
+    let response = get(&server, "/results");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!response.contains("Access-Control-Allow-Origin"));
+}
+
+#[test]
+fn only_requests_for_this_machine_are_served() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let port = server.address().port();
+    let request = |host: &str| {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        write!(stream, "GET /results HTTP/1.1\r\n{}\r\n", host).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    for host in &[
+        "localhost".to_string(),
+        format!("localhost:{}", port),
+        format!("127.0.0.1:{}", port),
+        format!("[::1]:{}", port),
+    ] {
+        let response = request(&format!("Host: {}\r\n", host));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", host);
+    }
+
+    // a page on another site whose name now points here
+    for host in &[
+        format!("Host: example.com:{}\r\n", port),
+        "Host: localhost:1\r\n".to_string(),
+        "".to_string(),
+    ] {
+        assert!(request(host).starts_with("HTTP/1.1 403"), "{}", host);
+    }
+}
+
+#[test]
+fn overlong_header_lines_are_turned_away() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+
+    // a line that goes one byte past the limit, all of which is read
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    write!(
+        stream,
+        "GET /results HTTP/1.1\r\nX-Padding: {}",
+        "a".repeat(8192 - "X-Padding: ".len() + 1)
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 431"));
+}
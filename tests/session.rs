@@ -0,0 +1,51 @@
+mod common;
+
+use std::sync::Arc;
+
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+use watch_rust_errors::session::{SessionConfig, WatchSession};
+use watch_rust_errors::watcher::BuildEvent;
+
+use crate::common::MockRunner;
+
+const OUTPUT: &str = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\n\
+                      warning: unused variable: `x`\n --> src/main.rs:2:9\n\n";
+
+fn config(runner: Arc<MockRunner>) -> SessionConfig {
+    let mut config = SessionConfig::new(".", "cargo check");
+    config.process = runner;
+    config
+}
+
+#[test]
+fn diagnostics_come_before_their_build_finishes() {
+    let runner = Arc::new(MockRunner::new(false, OUTPUT));
+    let (_session, events) = WatchSession::start(config(runner)).unwrap();
+
+    let events = block_on(events.take(4).collect::<Vec<_>>());
+    assert!(matches!(events[0], BuildEvent::Started));
+    match (&events[1], &events[2], &events[3]) {
+        (
+            BuildEvent::Diagnostic(error),
+            BuildEvent::Diagnostic(warning),
+            BuildEvent::Finished(result),
+        ) => {
+            assert_eq!(error.message, "mismatched types");
+            assert_eq!(warning.message, "unused variable: `x`");
+            assert_eq!(result.errors.len(), 1);
+        }
+        _ => panic!("unexpected events {:?}", events),
+    }
+}
+
+#[test]
+fn events_end_with_the_session() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (session, events) = WatchSession::start(config(runner)).unwrap();
+    drop(session);
+
+    // whatever the first compile got to before the watcher stopped
+    let events = block_on(events.collect::<Vec<_>>());
+    assert!(events.len() <= 2);
+}
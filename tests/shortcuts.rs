@@ -0,0 +1,24 @@
+use watch_rust_errors::shortcuts::{self, WINDOW_ACCELS};
+
+// While results are listed, the navigation actions are enabled, and an
+// accelerator without a modifier would take its key from the command entry:
+// "n" wouldn't type, and Up and Down wouldn't recall the command history.
+#[test]
+fn entries_keep_their_keys_while_results_are_listed() {
+    for (action, accels) in WINDOW_ACCELS {
+        for accel in accels.iter() {
+            assert!(
+                shortcuts::leaves_typing_alone(accel),
+                "win.{} takes {} from entries",
+                action,
+                accel
+            );
+        }
+    }
+
+    for key in &["n", "p", "Up", "Down", "Return", "F"] {
+        assert!(!shortcuts::leaves_typing_alone(key));
+    }
+    assert!(shortcuts::leaves_typing_alone("<Alt>n"));
+    assert!(shortcuts::leaves_typing_alone("F11"));
+}
@@ -0,0 +1,55 @@
+use watch_rust_errors::snippet::{Snippet, SnippetCache};
+
+const SOURCE: &str =
+    "fn main() {\n    let a = 1;\n    let b: u32 = \"a\";\n    let c = a + b;\n}\n";
+
+#[test]
+fn context_lines_are_clamped_to_the_file() {
+    let snippet = Snippet::new(SOURCE, 1, Some(4)).unwrap();
+    assert_eq!(snippet.first_line, 1);
+    assert_eq!(snippet.lines.len(), 3);
+
+    let snippet = Snippet::new(SOURCE, 5, None).unwrap();
+    assert_eq!(snippet.first_line, 3);
+    assert_eq!(
+        snippet.lines,
+        vec!["    let b: u32 = \"a\";", "    let c = a + b;", "}"]
+    );
+
+    assert!(Snippet::new(SOURCE, 0, None).is_none());
+    assert!(Snippet::new(SOURCE, 6, None).is_none());
+}
+
+#[test]
+fn error_column_is_underlined() {
+    let snippet = Snippet::new(SOURCE, 4, Some(13)).unwrap();
+
+    let markup = snippet.to_pango(false);
+
+    assert!(markup.starts_with("   2 │ "));
+    assert!(markup.contains("underline=\"error\">a</span>"));
+    assert_eq!(markup.matches("underline").count(), 1);
+    // quotes in the source are escaped
+    assert!(markup.contains("&quot;"));
+}
+
+#[test]
+fn files_are_read_from_the_project_root() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), SOURCE).unwrap();
+    let mut cache = SnippetCache::new(dir.path());
+
+    let diag = "error[E0308]: mismatched types\n --> src/main.rs:3:18\n"
+        .parse()
+        .unwrap();
+    let snippet = cache.snippet(&diag).unwrap();
+    assert_eq!(snippet.line, 3);
+    assert_eq!(snippet.column, Some(18));
+
+    let missing = "error[E0308]: mismatched types\n --> src/lib.rs:3:18\n"
+        .parse()
+        .unwrap();
+    assert!(cache.snippet(&missing).is_none());
+    assert!(cache.markup(&missing, true).is_none());
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use watch_rust_errors::process::{ProcessOutput, ProcessRunner};
+use watch_rust_errors::toolchain;
+
+mod common;
+use common::MockRunner;
+
+struct RustcRunner {
+    success: bool,
+    stdout: &'static str,
+}
+
+impl ProcessRunner for RustcRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        match program {
+            "rustc" => assert_eq!(args, ["--version"]),
+            "rustup" => assert_eq!(args, ["toolchain", "list"]),
+            _ => panic!("unexpected {}", program),
+        }
+        Ok(ProcessOutput {
+            success: self.success,
+            stdout: self.stdout.as_bytes().to_vec(),
+            stderr: b"error: toolchain 'nightly' is not installed\n".to_vec(),
+        })
+    }
+}
+
+#[test]
+fn toolchain_files_are_recognized() {
+    assert!(toolchain::is_toolchain_file("rust-toolchain"));
+    assert!(toolchain::is_toolchain_file("/src/foo/rust-toolchain.toml"));
+    assert!(!toolchain::is_toolchain_file("Cargo.toml"));
+}
+
+#[test]
+fn channels_are_read_from_both_file_formats() {
+    assert_eq!(
+        toolchain::parse_channel("[toolchain]\nchannel = \"1.47.0\"\n").as_deref(),
+        Some("1.47.0")
+    );
+    assert_eq!(
+        toolchain::parse_channel("nightly-2020-10-01\n").as_deref(),
+        Some("nightly-2020-10-01")
+    );
+    assert_eq!(toolchain::parse_channel("[toolchain]\n"), None);
+}
+
+#[test]
+fn installed_toolchains_are_matched_by_channel() {
+    let runner = RustcRunner {
+        success: true,
+        stdout: "stable-x86_64-unknown-linux-gnu (default)\n\
+                 1.47.0-x86_64-unknown-linux-gnu\n",
+    };
+
+    assert!(toolchain::is_installed_with(&runner, ".", "stable", &[]));
+    assert!(toolchain::is_installed_with(&runner, ".", "1.47.0", &[]));
+    assert!(!toolchain::is_installed_with(&runner, ".", "1.47", &[]));
+    assert!(!toolchain::is_installed_with(&runner, ".", "nightly", &[]));
+}
+
+#[test]
+fn installed_toolchains_are_listed_without_their_host() {
+    let runner = RustcRunner {
+        success: true,
+        stdout: "stable-x86_64-unknown-linux-gnu (default)\n\
+                 nightly-2024-05-01-aarch64-apple-darwin\n",
+    };
+
+    let installed = toolchain::installed_with(&runner, ".", &[]);
+    assert_eq!(
+        installed,
+        [
+            "stable-x86_64-unknown-linux-gnu",
+            "nightly-2024-05-01-aarch64-apple-darwin"
+        ]
+    );
+    assert_eq!(toolchain::short_name(&installed[0]), "stable");
+    assert_eq!(toolchain::short_name(&installed[1]), "nightly-2024-05-01");
+    assert_eq!(toolchain::short_name("my-toolchain"), "my-toolchain");
+}
+
+#[test]
+fn installed_targets_are_listed_for_the_picked_toolchain() {
+    let runner = MockRunner::with_stdout(
+        true,
+        "thumbv7em-none-eabihf\nwasm32-unknown-unknown\nx86_64-unknown-linux-gnu\n",
+    );
+    let env = [("RUSTUP_TOOLCHAIN".to_string(), "nightly".to_string())];
+
+    assert_eq!(
+        toolchain::installed_targets_with(&runner, ".", &env),
+        [
+            "thumbv7em-none-eabihf",
+            "wasm32-unknown-unknown",
+            "x86_64-unknown-linux-gnu"
+        ]
+    );
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].1, ["target", "list", "--installed"]);
+    assert_eq!(calls[0].2, env);
+
+    let runner = MockRunner::new(false, "rustup: command not found");
+    assert!(toolchain::installed_targets_with(&runner, ".", &[]).is_empty());
+}
+
+#[test]
+fn version_is_trimmed() {
+    let runner = RustcRunner {
+        success: true,
+        stdout: "rustc 1.47.0 (18bf6b4f0 2020-10-07)\n",
+    };
+
+    assert_eq!(
+        toolchain::version_with(&runner, ".", &[]).unwrap(),
+        "rustc 1.47.0 (18bf6b4f0 2020-10-07)"
+    );
+}
+
+#[test]
+fn failures_report_rustup_errors() {
+    let runner = RustcRunner {
+        success: false,
+        stdout: "",
+    };
+
+    assert_eq!(
+        toolchain::version_with(&runner, ".", &[]).unwrap_err(),
+        "error: toolchain 'nightly' is not installed"
+    );
+}
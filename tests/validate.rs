@@ -0,0 +1,35 @@
+use watch_rust_errors::validate::{self, Problems};
+
+#[test]
+fn missing_directories_and_programs_are_pointed_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    let missing = dir.path().join("missing");
+
+    let problems = validate::check(missing.to_str().unwrap(), "", &[]);
+    assert!(problems.project_root.unwrap().contains("doesn't exist"));
+    assert!(problems.command.is_some());
+
+    let problems = validate::check(root, "no-such-command-wre check", &[]);
+    assert_eq!(problems.project_root, None);
+    assert!(problems.command.unwrap().contains("isn't on the PATH"));
+
+    let problems = validate::check(root, "./build.sh", &[]);
+    assert!(problems.command.unwrap().contains("doesn't exist"));
+}
+
+#[cfg(unix)]
+#[test]
+fn programs_are_looked_up_on_the_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    std::fs::write(dir.path().join("mybuild"), "").unwrap();
+    let env = vec![("PATH".to_string(), root.to_string())];
+
+    assert!(validate::check(root, "mybuild --all", &env).is_empty());
+    assert!(!validate::check(root, "mybuild --all", &[]).is_empty());
+    assert_eq!(
+        validate::check(root, "shell: no-such-command-wre | tee log", &[]),
+        Problems::default()
+    );
+}
@@ -0,0 +1,739 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::stream::StreamExt;
+use glib::{MainContext, Receiver};
+use watch_rust_errors::cargo::{CompileResult, Op};
+use watch_rust_errors::git::GitTrigger;
+use watch_rust_errors::parser::{GccParser, PassthroughParser, RustcParser};
+use watch_rust_errors::process::{ProcessOutput, ProcessRunner, SystemRunner};
+use watch_rust_errors::watcher::{
+    self, BuildEvent, BuildQueue, ChangeEvent, ChangeKind, Job, JobState, Watcher,
+};
+
+use crate::common::{drain_events, MockRunner};
+
+const WARNING: &str = "warning: unused variable: `x`\n \
+                       --> src/main.rs:2:9\n\
+                       \n";
+
+fn watcher(runner: Arc<MockRunner>) -> (Watcher, Receiver<BuildEvent>) {
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(".", "cargo check", runner, tx).unwrap();
+    (watcher, rx)
+}
+
+// for a second at most, for the file system watcher to come through
+fn wait_for_compiles(runner: &MockRunner, count: usize) {
+    for _ in 0..50 {
+        if runner.compile_count() >= count {
+            return;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn drain(rx: Receiver<BuildEvent>) -> Vec<CompileResult> {
+    drain_events(rx)
+        .into_iter()
+        .filter_map(|event| match event {
+            BuildEvent::Finished(result) => Some(result),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn injected_events_trigger_a_compile() {
+    let runner = Arc::new(MockRunner::new(true, WARNING));
+    let (watcher, rx) = watcher(runner.clone());
+
+    let events = vec![ChangeEvent::new("src/main.rs", ChangeKind::Modify)];
+    assert!(watcher.inject(&events).unwrap());
+
+    assert_eq!(runner.compile_count(), 1);
+    assert_eq!(watcher.last_events(), events);
+
+    let results = drain(rx);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success);
+    assert_eq!(results[0].warnings.len(), 1);
+}
+
+#[test]
+fn every_injection_produces_a_result() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (watcher, rx) = watcher(runner.clone());
+
+    watcher.inject(&[]).unwrap();
+    watcher
+        .inject(&[ChangeEvent::new("Cargo.toml", ChangeKind::Create)])
+        .unwrap();
+
+    assert_eq!(runner.compile_count(), 2);
+    assert_eq!(drain(rx).len(), 2);
+}
+
+#[test]
+fn stopped_watcher_ignores_events() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (mut watcher, rx) = watcher(runner.clone());
+
+    watcher.stop();
+
+    assert!(!watcher
+        .inject(&[ChangeEvent::new("src/lib.rs", ChangeKind::Remove)])
+        .unwrap());
+    assert_eq!(runner.compile_count(), 0);
+    assert!(drain(rx).is_empty());
+}
+
+#[test]
+fn missing_project_root_is_an_error() {
+    let (tx, _rx) = MainContext::channel::<BuildEvent>(Default::default());
+
+    assert!(Watcher::new("/does/not/exist", "cargo check", tx).is_err());
+}
+
+#[test]
+fn toolchain_changes_are_flagged() {
+    let runner = Arc::new(ToolchainRunner(Mutex::new("rustc 1.46.0")));
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(".", "cargo check", runner.clone(), tx).unwrap();
+
+    watcher.inject(&[]).unwrap();
+    *runner.0.lock().unwrap() = "rustc 1.47.0";
+    // the version is only looked up again when a toolchain file changes
+    watcher
+        .inject(&[ChangeEvent::new("src/main.rs", ChangeKind::Modify)])
+        .unwrap();
+    watcher
+        .inject(&[ChangeEvent::new("rust-toolchain.toml", ChangeKind::Modify)])
+        .unwrap();
+
+    let results = drain(rx);
+    let versions: Vec<_> = results
+        .iter()
+        .map(|r| (r.rustc_version.as_deref(), r.toolchain_changed))
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            (Some("rustc 1.46.0"), false),
+            (Some("rustc 1.46.0"), false),
+            (Some("rustc 1.47.0"), true),
+        ]
+    );
+}
+
+#[test]
+fn missing_toolchains_are_reported_before_installing() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("rust-toolchain"), "nightly-2020-10-01\n").unwrap();
+    let runner = Arc::new(ToolchainRunner(Mutex::new("rustc 1.49.0-nightly")));
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(dir.path(), "cargo check", runner, tx).unwrap();
+
+    watcher.inject(&[]).unwrap();
+
+    let events = drain_events(rx);
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[0], BuildEvent::Started));
+    match &events[1] {
+        BuildEvent::InstallingToolchain(channel) => assert_eq!(channel, "nightly-2020-10-01"),
+        event => panic!("unexpected event {:?}", event),
+    }
+    assert!(matches!(events[2], BuildEvent::Finished(_)));
+}
+
+// Reports the given version for `rustc` and no installed toolchains.
+struct ToolchainRunner(Mutex<&'static str>);
+
+impl ProcessRunner for ToolchainRunner {
+    fn run(
+        &self,
+        program: &str,
+        _args: &[&str],
+        _env: &[(String, String)],
+        _cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        let stdout = if program == "rustc" {
+            self.0.lock().unwrap().as_bytes().to_vec()
+        } else {
+            vec![]
+        };
+        Ok(ProcessOutput {
+            success: true,
+            stdout,
+            stderr: vec![],
+        })
+    }
+}
+
+#[test]
+fn config_changes_apply_to_the_next_compile() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (watcher, rx) = watcher(runner.clone());
+    let dir = tempfile::tempdir().unwrap();
+
+    watcher.set_command("cargo clippy");
+    watcher.set_env(vec![("RUSTFLAGS".to_string(), "-D warnings".to_string())]);
+    watcher.set_project_root(dir.path()).unwrap();
+    assert!(watcher.set_project_root("/does/not/exist").is_err());
+    watcher.inject(&[]).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    let (program, args, env) = calls.last().unwrap();
+    assert_eq!(program, "cargo");
+    assert_eq!(args, &["clippy"]);
+    assert_eq!(
+        env,
+        &vec![("RUSTFLAGS".to_string(), "-D warnings".to_string())]
+    );
+    assert_eq!(drain(rx).len(), 1);
+}
+
+#[test]
+fn other_tools_are_read_with_their_parser() {
+    let runner = Arc::new(MockRunner::new(
+        false,
+        "main.c:5:5: error: 'x' undeclared (first use in this function)\n",
+    ));
+    let (watcher, rx) = watcher(runner.clone());
+
+    watcher.set_parser(Arc::new(GccParser));
+    watcher.inject(&[]).unwrap();
+
+    let results = drain(rx);
+    assert_eq!(results[0].errors.len(), 1);
+    assert_eq!(results[0].errors[0].file.as_deref(), Some("main.c"));
+    // C projects have no rust toolchain to ask about
+    assert_eq!(runner.call_count(), runner.compile_count());
+    assert!(results[0].rustc_version.is_none());
+}
+
+#[test]
+fn builds_know_what_triggered_them() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(dir.path(), "cargo check", runner, tx).unwrap();
+
+    watcher.inject(&[]).unwrap();
+    watcher
+        .inject(&[
+            ChangeEvent::new(dir.path().join("src/main.rs"), ChangeKind::Modify),
+            ChangeEvent::new(dir.path().join("src/main.rs"), ChangeKind::Modify),
+            ChangeEvent::new("/elsewhere/lib.rs", ChangeKind::Create),
+        ])
+        .unwrap();
+
+    let results = drain(rx);
+    assert!(results[0].triggered_by.is_empty());
+    assert_eq!(
+        results[1].triggered_by,
+        vec!["src/main.rs", "/elsewhere/lib.rs"]
+    );
+}
+
+#[test]
+fn builds_know_what_happened_to_the_files_that_triggered_them() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(dir.path(), "cargo check", runner, tx).unwrap();
+
+    watcher
+        .inject(&[
+            ChangeEvent::new(dir.path().join("src/gen.rs"), ChangeKind::Create),
+            ChangeEvent::new(dir.path().join("src/gen.rs"), ChangeKind::Modify),
+            ChangeEvent::new(dir.path().join("src/gen.rs"), ChangeKind::Modify),
+            ChangeEvent {
+                path: dir.path().join("src/old.rs"),
+                kind: ChangeKind::Rename,
+                renamed_to: Some(dir.path().join("src/new.rs")),
+            },
+        ])
+        .unwrap();
+
+    let results = drain(rx);
+    let ops = results[0]
+        .trigger_ops
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ops,
+        vec![
+            "created src/gen.rs",
+            "modified src/gen.rs",
+            "renamed src/old.rs → src/new.rs",
+        ]
+    );
+    assert_eq!(results[0].trigger_ops[0].op, Op::Created);
+}
+
+#[test]
+fn editor_files_are_not_relevant() {
+    let root = Path::new("/src/foo");
+    let relevant = |path: &str| {
+        ChangeEvent::new(root.join(path), ChangeKind::Modify).is_relevant(root, &RustcParser)
+    };
+
+    assert!(relevant("src/main.rs"));
+    assert!(relevant("Cargo.toml"));
+    assert!(!relevant("README.md"));
+    assert!(!relevant("target/debug/build/out.rs"));
+    assert!(!relevant("src/.#main.rs"));
+    assert!(!relevant("src/#main.rs#"));
+    assert!(!relevant("src/main.rs~"));
+    assert!(!relevant("src/.main.rs.swp"));
+    assert!(!relevant("src/4913"));
+
+    // nor is the run log, even for tools that watch everything
+    let log = ChangeEvent::new(
+        root.join(".watch-rust-errors/runs.jsonl"),
+        ChangeKind::Modify,
+    );
+    assert!(!log.is_relevant(root, &PassthroughParser));
+}
+
+#[test]
+fn builds_wait_for_git_to_finish() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+    let project_root = dir.path().join("crates/app");
+    fs::create_dir_all(&project_root).unwrap();
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    let lock = dir.path().join(".git/index.lock");
+    fs::write(&lock, "").unwrap();
+    assert!(watcher::vcs_busy(&project_root));
+
+    let (tx, _rx) = MainContext::channel(Default::default());
+    let mut watcher =
+        Watcher::with_runner(&project_root, "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.start().unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(runner.compile_count(), 0);
+
+    fs::remove_file(&lock).unwrap();
+    assert!(!watcher::vcs_busy(&project_root));
+    wait_for_compiles(&runner, 1);
+    assert_eq!(runner.compile_count(), 1);
+    watcher.stop();
+}
+
+#[test]
+fn held_builds_wait_to_be_let_go() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+
+    let (tx, _rx) = MainContext::channel(Default::default());
+    let mut watcher = Watcher::with_runner(dir.path(), "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.set_held(true);
+    watcher.start().unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(runner.compile_count(), 0);
+
+    watcher.set_held(false);
+    wait_for_compiles(&runner, 1);
+    assert_eq!(runner.compile_count(), 1);
+
+    // triggers go ahead anyway
+    watcher.set_held(true);
+    assert!(watcher.inject(&[]).unwrap());
+    assert_eq!(runner.compile_count(), 2);
+    watcher.stop();
+}
+
+#[test]
+fn checkouts_trigger_a_build() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = dir.path().join(".git");
+    fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+    fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+
+    let (tx, rx) = MainContext::channel(Default::default());
+    let mut watcher = Watcher::with_runner(dir.path(), "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.start().unwrap();
+    wait_for_compiles(&runner, 1);
+
+    // the way git does it, through a lock file
+    fs::write(git_dir.join("HEAD.lock"), "ref: refs/heads/feature\n").unwrap();
+    fs::rename(git_dir.join("HEAD.lock"), git_dir.join("HEAD")).unwrap();
+    wait_for_compiles(&runner, 2);
+    watcher.stop();
+
+    let results = drain(rx);
+    assert_eq!(results[0].git_trigger, None);
+    assert_eq!(
+        results[1].git_trigger,
+        Some(GitTrigger::Switched {
+            to: "feature".to_string()
+        })
+    );
+}
+
+#[test]
+fn ignored_files_do_not_trigger_builds() {
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(".gitignore"), "/generated/\n").unwrap();
+    fs::create_dir_all(dir.path().join("generated")).unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+
+    let (tx, _rx) = MainContext::channel(Default::default());
+    let mut watcher = Watcher::with_runner(dir.path(), "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.start().unwrap();
+    wait_for_compiles(&runner, 1);
+
+    fs::write(dir.path().join("generated/schema.rs"), "").unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(runner.compile_count(), 1);
+
+    // unless they're asked for
+    watcher.set_respect_ignores(false);
+    fs::write(dir.path().join("generated/schema.rs"), "fn f() {}").unwrap();
+    wait_for_compiles(&runner, 2);
+    assert_eq!(runner.compile_count(), 2);
+
+    watcher.set_respect_ignores(true);
+    fs::write(dir.path().join("src/main.rs"), "").unwrap();
+    wait_for_compiles(&runner, 3);
+    assert_eq!(runner.compile_count(), 3);
+    watcher.stop();
+}
+
+// Records every run and fails it, as if nothing could be started.
+struct BrokenRunner(MockRunner);
+
+impl ProcessRunner for BrokenRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        self.0.run(program, args, env, cwd)?;
+        Err("spawn failed".to_string())
+    }
+}
+
+#[test]
+fn builds_that_fail_to_run_are_reported_and_the_watch_goes_on() {
+    let runner = Arc::new(BrokenRunner(MockRunner::new(true, "")));
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+
+    let (tx, rx) = MainContext::channel(Default::default());
+    let mut watcher = Watcher::with_runner(dir.path(), "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.start().unwrap();
+    wait_for_compiles(&runner.0, 1);
+
+    fs::write(dir.path().join("src/main.rs"), "").unwrap();
+    wait_for_compiles(&runner.0, 2);
+    assert!(runner.0.compile_count() >= 2);
+    assert!(watcher.inject(&[]).is_err());
+    watcher.stop();
+
+    let results = drain(rx);
+    assert_eq!(results.len(), runner.0.compile_count());
+    for result in results {
+        assert!(!result.success);
+        assert!(result.errors[0].message.starts_with("build failed: "));
+    }
+}
+
+#[test]
+fn path_dependencies_outside_the_project_trigger_builds() {
+    let runner = Arc::new(MetadataRunner(MockRunner::new(true, "")));
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let app = root.join("app");
+    let shared = root.join("shared-lib");
+    for (dir, manifest) in [
+        (
+            &app,
+            "name = \"app\"\n[dependencies]\nshared = { path = \"../shared-lib\" }\n",
+        ),
+        (&shared, "name = \"shared\"\n"),
+    ] {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!("[package]\nversion = \"0.1.0\"\n{}", manifest),
+        )
+        .unwrap();
+    }
+
+    let (tx, rx) = MainContext::channel(Default::default());
+    let mut watcher = Watcher::with_runner(&app, "cargo check", runner.clone(), tx).unwrap();
+    watcher.set_debounce(Duration::from_millis(20));
+    watcher.start().unwrap();
+    wait_for_compiles(&runner.0, 1);
+
+    fs::write(shared.join("src/lib.rs"), "pub fn f() {}").unwrap();
+    wait_for_compiles(&runner.0, 2);
+    assert_eq!(runner.0.compile_count(), 2);
+    watcher.stop();
+
+    let extra_roots = drain_events(rx)
+        .into_iter()
+        .filter_map(|event| match event {
+            BuildEvent::ExtraRoots(roots) => Some(roots),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(extra_roots, vec![vec![shared]]);
+}
+
+// Asks cargo for the metadata and fakes everything else.
+struct MetadataRunner(MockRunner);
+
+impl ProcessRunner for MetadataRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        if args.first() == Some(&"metadata") {
+            SystemRunner.run(program, args, env, cwd)
+        } else {
+            self.0.run(program, args, env, cwd)
+        }
+    }
+}
+
+#[test]
+fn triggers_during_a_compile_share_one_follow_up() {
+    let (runner, started, release) = gated_runner();
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(".", "cargo check", runner.clone(), tx).unwrap();
+
+    let first = {
+        let watcher = watcher.clone();
+        thread::spawn(move || watcher.inject(&[]))
+    };
+    started.recv().unwrap();
+    let waiting = ["src/a.rs", "src/b.rs", "src/c.rs"]
+        .iter()
+        .map(|path| {
+            let watcher = watcher.clone();
+            let event = ChangeEvent::new(path, ChangeKind::Modify);
+            thread::spawn(move || watcher.inject(&[event]))
+        })
+        .collect::<Vec<_>>();
+    // gives the triggers time to queue up behind the running compile
+    thread::sleep(Duration::from_millis(100));
+    release.send(()).unwrap();
+    release.send(()).unwrap();
+
+    assert!(first.join().unwrap().unwrap());
+    for waiting in waiting {
+        assert!(waiting.join().unwrap().unwrap());
+    }
+    assert_eq!(runner.inner.compile_count(), 2);
+    let results = drain(rx);
+    assert_eq!(results.len(), 2);
+    // in whatever order the threads got there
+    let mut triggered_by = results[1].triggered_by.clone();
+    triggered_by.sort();
+    assert_eq!(triggered_by, vec!["src/a.rs", "src/b.rs", "src/c.rs"]);
+}
+
+fn gated_runner() -> (Arc<GatedRunner>, mpsc::Receiver<()>, mpsc::Sender<()>) {
+    let (started_tx, started) = mpsc::channel();
+    let (release, gate) = mpsc::channel();
+    let runner = Arc::new(GatedRunner {
+        inner: MockRunner::new(true, ""),
+        started: Mutex::new(started_tx),
+        gate: Mutex::new(gate),
+    });
+    (runner, started, release)
+}
+
+fn queued_watcher(runner: Arc<GatedRunner>, queue: &BuildQueue) -> (Watcher, Receiver<BuildEvent>) {
+    let (tx, rx) = MainContext::channel(Default::default());
+    let watcher = Watcher::with_runner(".", "cargo check", runner, tx).unwrap();
+    watcher.set_build_queue(Some(queue.clone()));
+    (watcher, rx)
+}
+
+fn states(queue: &BuildQueue) -> Vec<JobState> {
+    queue.jobs().iter().map(|job| job.state).collect()
+}
+
+#[test]
+fn the_build_queue_runs_builds_one_at_a_time() {
+    let (runner, started, release) = gated_runner();
+    let queue = BuildQueue::new(Some(1));
+    let (first, _first_events) = queued_watcher(runner.clone(), &queue);
+    let (second, _second_events) = queued_watcher(runner.clone(), &queue);
+
+    let first = thread::spawn(move || first.inject(&[]));
+    started.recv().unwrap();
+    let second = thread::spawn(move || second.inject(&[]));
+    // gives the second build time to queue up behind the first
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(states(&queue), vec![JobState::Running, JobState::Pending]);
+    assert!(started.try_recv().is_err());
+
+    release.send(()).unwrap();
+    started.recv().unwrap();
+    release.send(()).unwrap();
+    assert!(first.join().unwrap().unwrap());
+    assert!(second.join().unwrap().unwrap());
+    assert_eq!(states(&queue), vec![JobState::Passed, JobState::Passed]);
+    assert_eq!(runner.inner.compile_count(), 2);
+}
+
+#[test]
+fn cancelled_builds_leave_the_queue() {
+    let (runner, started, release) = gated_runner();
+    let queue = BuildQueue::new(Some(1));
+    let (first, _first_events) = queued_watcher(runner.clone(), &queue);
+    let (second, _second_events) = queued_watcher(runner.clone(), &queue);
+
+    let first = thread::spawn(move || first.inject(&[]));
+    started.recv().unwrap();
+    let second = thread::spawn(move || second.inject(&[]));
+    thread::sleep(Duration::from_millis(300));
+    queue.cancel(queue.jobs()[1].id);
+
+    assert_eq!(
+        second.join().unwrap(),
+        Err("The build was cancelled.".to_string())
+    );
+    release.send(()).unwrap();
+    assert!(first.join().unwrap().unwrap());
+    assert_eq!(states(&queue), vec![JobState::Passed, JobState::Cancelled]);
+    assert_eq!(runner.inner.compile_count(), 1);
+}
+
+fn job(project_root: &str, state: JobState) -> Job {
+    Job {
+        id: 0,
+        project_root: PathBuf::from(project_root),
+        command: "cargo check".to_string(),
+        state,
+        queued_at: SystemTime::now(),
+        started_at: None,
+        finished_at: None,
+    }
+}
+
+#[test]
+fn the_queue_is_worth_listing_once_there_are_other_builds() {
+    assert!(!watcher::worth_listing(&[]));
+    assert!(!watcher::worth_listing(&[
+        job("/a", JobState::Passed),
+        job("/a", JobState::Running),
+    ]));
+    assert!(watcher::worth_listing(&[
+        job("/a", JobState::Running),
+        job("/a", JobState::Pending),
+    ]));
+    assert!(watcher::worth_listing(&[
+        job("/a", JobState::Passed),
+        job("/b", JobState::Failed),
+    ]));
+}
+
+// Holds every compile until it's released, after saying it has started.
+struct GatedRunner {
+    inner: MockRunner,
+    started: Mutex<mpsc::Sender<()>>,
+    gate: Mutex<mpsc::Receiver<()>>,
+}
+
+impl ProcessRunner for GatedRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        cwd: &Path,
+    ) -> Result<ProcessOutput, String> {
+        if program != "rustc" && program != "rustup" {
+            let _ = self.started.lock().unwrap().send(());
+            let _ = self.gate.lock().unwrap().recv();
+        }
+        self.inner.run(program, args, env, cwd)
+    }
+}
+
+#[test]
+fn a_watcher_that_gives_up_says_why() {
+    let dir = tempfile::tempdir().unwrap();
+    let project_root = dir.path().join("project");
+    fs::create_dir(&project_root).unwrap();
+    let runner = Arc::new(MockRunner::new(true, ""));
+    let (tx, mut rx) = futures::channel::mpsc::unbounded();
+    let mut watcher = Watcher::with_runner(&project_root, "cargo check", runner, tx).unwrap();
+
+    // there's nothing left to watch by the time it starts
+    fs::remove_dir(&project_root).unwrap();
+    watcher.start().unwrap();
+
+    match futures::executor::block_on(rx.next()) {
+        Some(BuildEvent::Stopped(reason)) => assert!(!reason.is_empty()),
+        event => panic!("expected the watcher to stop, got {:?}", event),
+    }
+}
+
+#[test]
+fn running_out_of_inotify_watches_says_what_to_do() {
+    let err = notify::Error::new(notify::ErrorKind::MaxFilesWatch);
+    assert!(watcher::watch_error(&err).contains("fs.inotify.max_user_watches"));
+    assert!(watcher::is_out_of_watches(&err));
+
+    let err = notify::Error::io(std::io::Error::from_raw_os_error(28));
+    assert!(watcher::watch_error(&err).contains("fs.inotify.max_user_watches"));
+
+    let err = notify::Error::path_not_found();
+    assert!(!watcher::watch_error(&err).contains("inotify"));
+    // which the watch goes on after
+    assert!(!watcher::is_out_of_watches(&err));
+}
+
+#[test]
+fn aborting_kills_the_running_build() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = MainContext::channel(Default::default());
+    let command = "sh -c 'echo compiling; sleep 30'";
+    let mut watcher =
+        Watcher::with_runner(dir.path(), command, Arc::new(SystemRunner), tx).unwrap();
+    watcher.set_parser(Arc::new(PassthroughParser));
+
+    let started = Instant::now();
+    let building = {
+        let watcher = watcher.clone();
+        thread::spawn(move || watcher.inject(&[]))
+    };
+    thread::sleep(Duration::from_millis(500));
+    watcher.abort();
+    let _ = building.join().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let results = drain(rx);
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert_eq!(results[0].errors[0].message, "build cancelled");
+    // and the watcher is gone
+    assert!(!watcher.inject(&[]).unwrap());
+}
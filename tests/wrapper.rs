@@ -0,0 +1,106 @@
+mod common;
+
+use std::fs;
+use std::sync::Arc;
+
+use watch_rust_errors::cargo;
+use watch_rust_errors::parser::RustcParser;
+use watch_rust_errors::process::ProcessRunner;
+use watch_rust_errors::wrapper::{self, CacheStats};
+
+use crate::common::MockRunner;
+
+const SHOW_STATS: &str = "Compile requests                     12\n\
+                          Compile requests executed             9\n\
+                          Cache hits                            6\n\
+                          Cache hits (Rust)                     6\n\
+                          Cache misses                          3\n\
+                          Cache misses (Rust)                   3\n\
+                          Cache hits rate                   66.67 %\n";
+
+#[test]
+fn stats_are_read_from_sccache() {
+    let stats = CacheStats::parse(SHOW_STATS).unwrap();
+    assert_eq!(
+        stats,
+        CacheStats {
+            requests: 12,
+            hits: 6,
+            misses: 3
+        }
+    );
+
+    let earlier = CacheStats {
+        requests: 10,
+        hits: 5,
+        misses: 1,
+    };
+    let build = stats.since(&earlier);
+    assert_eq!((build.requests, build.hits, build.misses), (2, 1, 2));
+    assert_eq!(build.hit_rate().map(|rate| rate.round()), Some(33.0));
+    // the server restarted in between
+    assert_eq!(earlier.since(&stats), earlier);
+
+    assert_eq!(CacheStats::parse("sccache: error: couldn't connect"), None);
+}
+
+#[test]
+fn wrappers_are_found_in_cargo_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let project_root = dir.path().join("crates").join("foo");
+    fs::create_dir_all(&project_root).unwrap();
+    fs::create_dir(dir.path().join(".cargo")).unwrap();
+    fs::write(
+        dir.path().join(".cargo").join("config.toml"),
+        "[build]\nrustc-wrapper = \"/usr/local/bin/sccache\"\n",
+    )
+    .unwrap();
+
+    let env = vec![("RUSTC_WRAPPER".to_string(), "cachepot".to_string())];
+    assert_eq!(
+        wrapper::detect(&project_root, &env),
+        Some("cachepot".to_string())
+    );
+    if std::env::var_os("RUSTC_WRAPPER").is_none()
+        && std::env::var_os("CARGO_BUILD_RUSTC_WRAPPER").is_none()
+    {
+        let wrapper = wrapper::detect(&project_root, &[]).unwrap();
+        assert_eq!(wrapper, "/usr/local/bin/sccache");
+        assert!(wrapper::is_sccache(&wrapper));
+    }
+    assert!(!wrapper::is_sccache("cachepot"));
+}
+
+#[test]
+fn sccache_is_asked_for_its_stats_around_builds() {
+    let dir = tempfile::tempdir().unwrap();
+    let mock = Arc::new(MockRunner::with_stdout(true, SHOW_STATS));
+    let runner: Arc<dyn ProcessRunner> = mock.clone();
+    let env = vec![("RUSTC_WRAPPER".to_string(), "sccache".to_string())];
+    let hooks = wrapper::hooks(&runner, dir.path(), &env);
+
+    let result = cargo::run_with_progress(
+        runner.as_ref(),
+        dir.path(),
+        "cargo check",
+        &env,
+        &RustcParser,
+        &hooks,
+        |_| {},
+    )
+    .unwrap();
+
+    assert_eq!(result.compiler_wrapper.as_deref(), Some("sccache"));
+    // the mock's counts never move
+    assert_eq!(result.cache_stats, Some(CacheStats::default()));
+    let programs = mock
+        .calls
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(program, ..)| program.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(programs.first().map(String::as_str), Some("sccache"));
+    assert_eq!(programs.last().map(String::as_str), Some("sccache"));
+    assert_eq!(programs.len(), 3);
+}